@@ -0,0 +1,364 @@
+//! Sharing connections to the server between clients. GRPC multiplexes calls over a single HTTP/2
+//! connection, so a process running many workers against the same server rarely needs more than
+//! one. Clients made with [ClientOptions::connect_shared] get their channel from a
+//! [ConnectionManager], which builds at most a configurable number of channels per endpoint and
+//! set of connection settings, and hands cheap clones of them to every client that asks.
+//!
+//! Channels are connected lazily: the underlying connection is only established when the first
+//! call is made over it, and is re-established the same way if it is lost.
+
+use crate::{failover::FailoverSvc, ClientInitError, ClientOptions};
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Formatter},
+    sync::Arc,
+    time::Duration,
+};
+use temporal_sdk_core_api::telemetry::metrics::TemporalMeter;
+
+/// Hands out channels shared by every client connecting to the same endpoint with the same
+/// connection settings. Is cheap to clone, and clones share channels. See the [module docs](self).
+#[derive(Clone)]
+pub struct ConnectionManager {
+    inner: Arc<ManagerInner>,
+}
+
+struct ManagerInner {
+    max_connections_per_endpoint: usize,
+    factory: Box<dyn ChannelFactory>,
+    pools: Mutex<HashMap<ConnectionKey, Pool>>,
+}
+
+#[derive(Default)]
+struct Pool {
+    channels: Vec<FailoverSvc>,
+    /// Index of the channel to hand out next, once the pool is full
+    next: usize,
+}
+
+/// What decides whether two clients can share a channel: every option the channel is built from.
+/// The others (ex: headers, retries) are applied per client, on top of the shared channel.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(crate) struct ConnectionKey {
+    /// The target url, followed by any failover urls
+    urls: Vec<String>,
+    tls: Option<TlsKey>,
+    override_origin: Option<String>,
+    /// Keep alive interval and timeout
+    keep_alive: Option<(Duration, Duration)>,
+    connect_timeout: Option<Duration>,
+    /// Proxy address and basic auth
+    http_connect_proxy: Option<(String, Option<(String, String)>)>,
+    /// Failures before failover, and failback probe interval
+    failover: (usize, Duration),
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct TlsKey {
+    server_root_ca_cert: Option<Vec<u8>>,
+    domain: Option<String>,
+    /// The mTLS client certificate, if there is one. Its private key must match it, so needn't be
+    /// compared.
+    client_cert: Option<Vec<u8>>,
+}
+
+impl ConnectionKey {
+    fn new(options: &ClientOptions) -> Self {
+        Self {
+            urls: options.all_target_urls().map(ToString::to_string).collect(),
+            tls: options.tls_cfg.as_ref().map(|tls| TlsKey {
+                server_root_ca_cert: tls.server_root_ca_cert.clone(),
+                domain: tls.domain.clone(),
+                client_cert: tls
+                    .client_tls_config
+                    .as_ref()
+                    .map(|identity| identity.client_cert.clone()),
+            }),
+            override_origin: options.override_origin.as_ref().map(ToString::to_string),
+            keep_alive: options
+                .keep_alive
+                .as_ref()
+                .map(|ka| (ka.interval, ka.timeout)),
+            connect_timeout: options.connect_timeout,
+            http_connect_proxy: options
+                .http_connect_proxy
+                .as_ref()
+                .map(|p| (p.target_addr.clone(), p.basic_auth.clone())),
+            failover: (
+                options.failover.failures_before_failover,
+                options.failover.failback_probe_interval,
+            ),
+        }
+    }
+}
+
+impl Debug for ConnectionKey {
+    // Only the urls, since the rest may hold credentials
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConnectionKey")
+            .field("urls", &self.urls)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The channel a client made with [ClientOptions::connect_shared] got from its manager
+#[derive(Clone, Debug)]
+pub(crate) struct SharedConnection {
+    manager: ConnectionManager,
+    key: ConnectionKey,
+}
+
+impl SharedConnection {
+    /// Number of channels the manager has built for this client's endpoint and settings
+    pub(crate) fn connection_count(&self) -> usize {
+        self.manager
+            .inner
+            .pools
+            .lock()
+            .get(&self.key)
+            .map_or(0, |p| p.channels.len())
+    }
+}
+
+/// Builds the channels a [ConnectionManager] hands out
+pub(crate) trait ChannelFactory: Send + Sync {
+    fn lazy_channel(
+        &self,
+        options: &ClientOptions,
+        metrics_meter: Option<TemporalMeter>,
+    ) -> Result<FailoverSvc, ClientInitError>;
+}
+
+struct LazyChannels;
+
+impl ChannelFactory for LazyChannels {
+    fn lazy_channel(
+        &self,
+        options: &ClientOptions,
+        metrics_meter: Option<TemporalMeter>,
+    ) -> Result<FailoverSvc, ClientInitError> {
+        options.lazy_channels(metrics_meter)
+    }
+}
+
+impl ConnectionManager {
+    /// Create a manager which builds up to `max_connections_per_endpoint` channels (at least one)
+    /// for each endpoint and set of connection settings. Once that many exist, clients are handed
+    /// them in turn.
+    /// Hosts making very many calls may want more than one.
+    pub fn new(max_connections_per_endpoint: usize) -> Self {
+        Self::with_factory(max_connections_per_endpoint, Box::new(LazyChannels))
+    }
+
+    pub(crate) fn with_factory(
+        max_connections_per_endpoint: usize,
+        factory: Box<dyn ChannelFactory>,
+    ) -> Self {
+        Self {
+            inner: Arc::new(ManagerInner {
+                max_connections_per_endpoint: max_connections_per_endpoint.max(1),
+                factory,
+                pools: Default::default(),
+            }),
+        }
+    }
+
+    /// Returns the number of channels this manager has built, across all endpoints
+    pub fn connection_count(&self) -> usize {
+        self.inner
+            .pools
+            .lock()
+            .values()
+            .map(|p| p.channels.len())
+            .sum()
+    }
+
+    /// Get a channel for a client with the provided options, building one if the pool for its
+    /// endpoint and settings isn't yet full
+    pub(crate) fn channel_for(
+        &self,
+        options: &ClientOptions,
+        metrics_meter: Option<TemporalMeter>,
+    ) -> Result<(FailoverSvc, SharedConnection), ClientInitError> {
+        let key = ConnectionKey::new(options);
+        let mut pools = self.inner.pools.lock();
+        let pool = pools.entry(key.clone()).or_default();
+        let channel = if pool.channels.len() < self.inner.max_connections_per_endpoint {
+            let channel = self.inner.factory.lazy_channel(options, metrics_meter)?;
+            pool.channels.push(channel.clone());
+            channel
+        } else {
+            let channel = pool.channels[pool.next % pool.channels.len()].clone();
+            pool.next = pool.next.wrapping_add(1);
+            channel
+        };
+        drop(pools);
+        Ok((
+            channel,
+            SharedConnection {
+                manager: self.clone(),
+                key,
+            },
+        ))
+    }
+}
+
+impl Default for ConnectionManager {
+    /// A manager which builds a single channel per endpoint and set of connection settings
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+impl Debug for ConnectionManager {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConnectionManager")
+            .field(
+                "max_connections_per_endpoint",
+                &self.inner.max_connections_per_endpoint,
+            )
+            .field("connection_count", &self.connection_count())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ClientOptionsBuilder, ClientTlsConfig, HttpConnectProxyOptions, TlsConfig};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use url::Url;
+
+    #[derive(Clone, Default)]
+    struct CountingFactory {
+        built: Arc<AtomicUsize>,
+    }
+
+    impl ChannelFactory for CountingFactory {
+        fn lazy_channel(
+            &self,
+            options: &ClientOptions,
+            metrics_meter: Option<TemporalMeter>,
+        ) -> Result<FailoverSvc, ClientInitError> {
+            self.built.fetch_add(1, Ordering::SeqCst);
+            LazyChannels.lazy_channel(options, metrics_meter)
+        }
+    }
+
+    fn options_for(url: &str) -> ClientOptions {
+        ClientOptionsBuilder::default()
+            .identity("enchicat".to_string())
+            .target_url(Url::parse(url).unwrap())
+            .client_name("cute-kitty".to_string())
+            .client_version("0.1.0".to_string())
+            .skip_get_system_info(true)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn workers_share_a_single_channel() {
+        let factory = CountingFactory::default();
+        let manager = ConnectionManager::with_factory(1, Box::new(factory.clone()));
+        let opts = options_for("http://localhost:7233");
+        let mut clients = vec![];
+        for namespace in ["ns-1", "ns-2", "ns-3"] {
+            clients.push(
+                opts.connect_shared(&manager, namespace, None)
+                    .await
+                    .unwrap(),
+            );
+        }
+        assert_eq!(factory.built.load(Ordering::SeqCst), 1);
+        assert_eq!(manager.connection_count(), 1);
+        for client in &clients {
+            assert_eq!(client.get_client().inner().connection_count(), 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn channels_are_capped_per_endpoint_and_identity() {
+        let factory = CountingFactory::default();
+        let manager = ConnectionManager::with_factory(2, Box::new(factory.clone()));
+        let opts = options_for("http://localhost:7233");
+        for _ in 0..5 {
+            opts.connect_shared(&manager, "ns", None).await.unwrap();
+        }
+        assert_eq!(factory.built.load(Ordering::SeqCst), 2);
+
+        // A different endpoint, or the same endpoint with another identity, gets its own channel
+        options_for("http://otherhost:7233")
+            .connect_shared(&manager, "ns", None)
+            .await
+            .unwrap();
+        let mut with_identity = opts.clone();
+        with_identity.tls_cfg = Some(TlsConfig {
+            client_tls_config: Some(ClientTlsConfig {
+                client_cert: b"cert".to_vec(),
+                client_private_key: b"key".to_vec(),
+            }),
+            ..Default::default()
+        });
+        assert_ne!(
+            ConnectionKey::new(&opts),
+            ConnectionKey::new(&with_identity)
+        );
+        assert_eq!(manager.connection_count(), 3);
+    }
+
+    #[tokio::test]
+    async fn only_clients_with_the_same_channel_settings_share() {
+        let factory = CountingFactory::default();
+        let manager = ConnectionManager::with_factory(1, Box::new(factory.clone()));
+        let opts = options_for("http://localhost:7233");
+        let mut variants: Vec<ClientOptions> = vec![];
+        let mut variant = |change: &dyn Fn(&mut ClientOptions)| {
+            let mut o = opts.clone();
+            change(&mut o);
+            variants.push(o);
+        };
+        variant(&|o| {
+            o.tls_cfg = Some(TlsConfig {
+                server_root_ca_cert: Some(b"ca".to_vec()),
+                ..Default::default()
+            })
+        });
+        variant(&|o| {
+            o.tls_cfg = Some(TlsConfig {
+                domain: Some("other.domain".to_string()),
+                ..Default::default()
+            })
+        });
+        variant(&|o| o.override_origin = Some("https://origin".parse().unwrap()));
+        variant(&|o| o.keep_alive = None);
+        variant(&|o| o.connect_timeout = Some(Duration::from_secs(1)));
+        variant(&|o| {
+            o.http_connect_proxy = Some(HttpConnectProxyOptions {
+                target_addr: "proxy:8080".to_string(),
+                basic_auth: None,
+            })
+        });
+        variant(&|o| o.failover.failures_before_failover = 1);
+        for (i, v) in variants.iter().enumerate() {
+            assert_ne!(
+                ConnectionKey::new(&opts),
+                ConnectionKey::new(v),
+                "variant {i}"
+            );
+        }
+
+        // Options applied per client don't keep them from sharing
+        let mut per_client = opts.clone();
+        per_client.identity = "someone-else".to_string();
+        per_client.api_key = Some("key".to_string());
+        per_client.retry_config.max_retries = 1;
+        assert_eq!(ConnectionKey::new(&opts), ConnectionKey::new(&per_client));
+        opts.connect_shared(&manager, "ns", None).await.unwrap();
+        per_client
+            .connect_shared(&manager, "ns", None)
+            .await
+            .unwrap();
+        assert_eq!(factory.built.load(Ordering::SeqCst), 1);
+    }
+}
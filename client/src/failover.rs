@@ -0,0 +1,461 @@
+//! Client-side failover between several frontend endpoints (ex: a primary and a DR cluster).
+//!
+//! Calls are routed to a single active endpoint. After enough consecutive calls against it fail in
+//! a way that indicates the endpoint itself is unreachable, we move on to the next one. While not
+//! on the preferred (first) endpoint, one call per probe interval is sent to the preferred endpoint
+//! instead, and if it succeeds we fail back to it.
+//!
+//! Nothing is retried here. A failed call is surfaced as usual and the [crate::RetryClient] layer
+//! re-issues it, at which point it is routed to whichever endpoint is then active. That is also how
+//! long polls move over to the new endpoint after a failover.
+//!
+//! Whether or not there are multiple endpoints, this is also where we notice connections being lost
+//! and re-established. Tonic reconnects on its own, so without logging it here those would only
+//! be visible as a burst of failed calls.
+
+use crate::metrics::MetricsContext;
+use futures_util::{future::BoxFuture, FutureExt};
+use parking_lot::Mutex;
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+use tonic::{body::BoxBody, transport::Channel, Code};
+use tower::{Service, ServiceExt};
+
+/// Controls when a client configured with multiple endpoints fails over between them. See
+/// [crate::ClientOptions::failover_target_urls].
+#[derive(Clone, Debug)]
+pub struct FailoverConfig {
+    /// How many consecutive calls against the active endpoint must fail with a connection error
+    /// or `UNAVAILABLE` before switching to the next endpoint.
+    pub failures_before_failover: usize,
+    /// While not using the preferred endpoint, how often a call is sent to it to check whether it
+    /// has recovered.
+    pub failback_probe_interval: Duration,
+}
+
+impl Default for FailoverConfig {
+    fn default() -> Self {
+        Self {
+            failures_before_failover: 5,
+            failback_probe_interval: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Routes calls to one of several endpoints, failing over between them, and tracks whether each is
+/// reachable. See the module docs.
+#[derive(Clone, Debug)]
+pub(crate) struct FailoverSvc<S = Channel> {
+    /// Endpoints in order of preference
+    endpoints: Vec<S>,
+    shared: Arc<FailoverShared>,
+}
+
+#[derive(Debug)]
+struct FailoverShared {
+    /// The urls the endpoints were created from, used for logging and metrics
+    urls: Vec<String>,
+    state: Mutex<FailoverState>,
+    config: FailoverConfig,
+    metrics: Option<MetricsContext>,
+}
+
+#[derive(Debug)]
+struct FailoverState {
+    active: usize,
+    consecutive_failures: usize,
+    last_probe: Instant,
+    /// Per endpoint, whether the last call made to it failed to reach it at all
+    disconnected: Vec<bool>,
+}
+
+impl<S> FailoverSvc<S> {
+    /// Create a new failover service from (url, endpoint) pairs. `active` is the index of the
+    /// endpoint to start routing to, which may not be the preferred one if it could not be reached
+    /// at connection time.
+    pub(crate) fn new(
+        endpoints: Vec<(String, S)>,
+        active: usize,
+        config: FailoverConfig,
+        metrics: Option<MetricsContext>,
+    ) -> Self {
+        assert!(
+            active < endpoints.len(),
+            "Active endpoint must be one of the endpoints"
+        );
+        let (urls, endpoints): (Vec<_>, _) = endpoints.into_iter().unzip();
+        let disconnected = vec![false; urls.len()];
+        Self {
+            endpoints,
+            shared: Arc::new(FailoverShared {
+                urls,
+                state: Mutex::new(FailoverState {
+                    active,
+                    consecutive_failures: 0,
+                    last_probe: Instant::now(),
+                    disconnected,
+                }),
+                config,
+                metrics,
+            }),
+        }
+    }
+}
+
+impl FailoverShared {
+    /// Returns the index of the endpoint the next call should go to, and whether that call is a
+    /// probe of the preferred endpoint.
+    fn choose_endpoint(&self) -> (usize, bool) {
+        let mut state = self.state.lock();
+        if state.active != 0 && state.last_probe.elapsed() >= self.config.failback_probe_interval {
+            state.last_probe = Instant::now();
+            return (0, true);
+        }
+        (state.active, false)
+    }
+
+    fn record_outcome(&self, endpoint: usize, was_probe: bool, failed: bool) {
+        if self.urls.len() == 1 {
+            return;
+        }
+        let mut state = self.state.lock();
+        if was_probe {
+            if !failed && state.active != 0 {
+                info!(
+                    endpoint = %self.urls[0],
+                    "Preferred endpoint has recovered, failing back to it"
+                );
+                state.active = 0;
+                state.consecutive_failures = 0;
+                self.record_failover(0);
+            }
+            return;
+        }
+        // Calls which were in flight during a failover may complete against the endpoint we
+        // already moved away from. Those say nothing about the currently active one.
+        if endpoint != state.active {
+            return;
+        }
+        if !failed {
+            state.consecutive_failures = 0;
+            return;
+        }
+        state.consecutive_failures += 1;
+        if state.consecutive_failures >= self.config.failures_before_failover {
+            let next = (state.active + 1) % self.urls.len();
+            warn!(
+                from = %self.urls[state.active],
+                to = %self.urls[next],
+                failures = state.consecutive_failures,
+                "Endpoint appears to be unreachable, failing over"
+            );
+            state.active = next;
+            state.consecutive_failures = 0;
+            state.last_probe = Instant::now();
+            self.record_failover(next);
+        }
+    }
+
+    /// Log and count changes in whether calls can reach an endpoint at all. Unlike failover, only
+    /// connection errors count here, since an `UNAVAILABLE` answer means there is a connection.
+    fn record_connection_state(&self, endpoint: usize, connection_failed: bool) {
+        let mut state = self.state.lock();
+        let disconnected = &mut state.disconnected[endpoint];
+        if *disconnected == connection_failed {
+            return;
+        }
+        *disconnected = connection_failed;
+        let url = &self.urls[endpoint];
+        if connection_failed {
+            warn!(endpoint = %url, "Lost connection to endpoint, will reconnect on next call");
+        } else {
+            info!(endpoint = %url, "Connection to endpoint re-established");
+        }
+        if let Some(m) = self.metrics.as_ref() {
+            m.endpoint_connection_state(url.clone(), connection_failed);
+        }
+    }
+
+    fn record_failover(&self, to: usize) {
+        if let Some(m) = self.metrics.as_ref() {
+            m.endpoint_failover(self.urls[to].clone());
+        }
+    }
+}
+
+impl<S> Service<http::Request<BoxBody>> for FailoverSvc<S>
+where
+    S: Service<http::Request<BoxBody>, Response = http::Response<BoxBody>> + Clone + 'static,
+    S::Future: Send,
+    S::Error: Send,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // Which endpoint a call goes to isn't known until it's made, so readiness is waited on
+        // per call against the chosen endpoint.
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<BoxBody>) -> Self::Future {
+        let (endpoint, is_probe) = self.shared.choose_endpoint();
+        let callfut = self.endpoints[endpoint].clone().oneshot(req);
+        let shared = self.shared.clone();
+        async move {
+            let res = callfut.await;
+            shared.record_connection_state(endpoint, res.is_err());
+            shared.record_outcome(endpoint, is_probe, indicates_unreachable(&res));
+            res
+        }
+        .boxed()
+    }
+}
+
+/// True if the result of a call means the endpoint it went to couldn't be reached. Only
+/// trailers-only responses carry their status in the headers, which is how proxies and load
+/// balancers in front of a dead frontend answer with `UNAVAILABLE`.
+fn indicates_unreachable<E>(res: &Result<http::Response<BoxBody>, E>) -> bool {
+    match res {
+        Err(_) => true,
+        Ok(resp) => resp
+            .headers()
+            .get("grpc-status")
+            .and_then(|s| s.to_str().ok())
+            .and_then(|s| s.parse::<i32>().ok())
+            .is_some_and(|c| Code::from(c) == Code::Unavailable),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use tower::service_fn;
+
+    /// A scripted gateway which either answers successfully or is unreachable. When unreachable
+    /// it either fails to connect, or if `fronted` is set, has a proxy answering `UNAVAILABLE`.
+    struct MockGateway {
+        alive: AtomicBool,
+        fronted: bool,
+        calls: AtomicUsize,
+    }
+
+    impl MockGateway {
+        fn new(alive: bool) -> Arc<Self> {
+            Arc::new(Self {
+                alive: AtomicBool::new(alive),
+                fronted: false,
+                calls: AtomicUsize::new(0),
+            })
+        }
+
+        fn fronted_dead() -> Arc<Self> {
+            Arc::new(Self {
+                alive: AtomicBool::new(false),
+                fronted: true,
+                calls: AtomicUsize::new(0),
+            })
+        }
+
+        fn calls(&self) -> usize {
+            self.calls.load(Ordering::SeqCst)
+        }
+
+        fn svc(
+            self: &Arc<Self>,
+        ) -> impl Service<
+            http::Request<BoxBody>,
+            Response = http::Response<BoxBody>,
+            Error = &'static str,
+            Future = impl Send,
+        > + Clone
+               + 'static {
+            let gw = self.clone();
+            service_fn(move |_req: http::Request<BoxBody>| {
+                gw.calls.fetch_add(1, Ordering::SeqCst);
+                let res = if gw.alive.load(Ordering::SeqCst) {
+                    Ok(grpc_response(Code::Ok))
+                } else if gw.fronted {
+                    Ok(grpc_response(Code::Unavailable))
+                } else {
+                    Err("connection refused")
+                };
+                async move { res }
+            })
+        }
+    }
+
+    fn grpc_response(code: Code) -> http::Response<BoxBody> {
+        http::Response::builder()
+            .header("grpc-status", (code as i32).to_string())
+            .body(tonic::body::empty_body())
+            .unwrap()
+    }
+
+    fn req() -> http::Request<BoxBody> {
+        http::Request::new(tonic::body::empty_body())
+    }
+
+    fn cfg(probe_interval: Duration) -> FailoverConfig {
+        FailoverConfig {
+            failures_before_failover: 3,
+            failback_probe_interval: probe_interval,
+        }
+    }
+
+    #[tokio::test]
+    async fn fails_over_after_sustained_failures() {
+        let primary = MockGateway::new(false);
+        let secondary = MockGateway::new(true);
+        let mut svc = FailoverSvc::new(
+            vec![
+                ("primary".to_string(), primary.svc()),
+                ("secondary".to_string(), secondary.svc()),
+            ],
+            0,
+            cfg(Duration::from_secs(60)),
+            None,
+        );
+
+        for _ in 0..3 {
+            svc.call(req()).await.unwrap_err();
+        }
+        // Calls re-issued after the failure threshold (like a retried long poll) hit the
+        // secondary
+        for _ in 0..5 {
+            svc.call(req()).await.unwrap();
+        }
+        assert_eq!(primary.calls(), 3);
+        assert_eq!(secondary.calls(), 5);
+    }
+
+    #[tokio::test]
+    async fn success_resets_failure_count() {
+        let primary = MockGateway::new(false);
+        let secondary = MockGateway::new(true);
+        let mut svc = FailoverSvc::new(
+            vec![
+                ("primary".to_string(), primary.svc()),
+                ("secondary".to_string(), secondary.svc()),
+            ],
+            0,
+            cfg(Duration::from_secs(60)),
+            None,
+        );
+
+        for _ in 0..2 {
+            svc.call(req()).await.unwrap_err();
+        }
+        primary.alive.store(true, Ordering::SeqCst);
+        svc.call(req()).await.unwrap();
+        primary.alive.store(false, Ordering::SeqCst);
+        for _ in 0..2 {
+            svc.call(req()).await.unwrap_err();
+        }
+        assert_eq!(primary.calls(), 5);
+        assert_eq!(secondary.calls(), 0);
+    }
+
+    #[tokio::test]
+    async fn unavailable_status_counts_as_failure() {
+        let primary = MockGateway::fronted_dead();
+        let secondary = MockGateway::new(true);
+        let mut svc = FailoverSvc::new(
+            vec![
+                ("primary".to_string(), primary.svc()),
+                ("secondary".to_string(), secondary.svc()),
+            ],
+            0,
+            cfg(Duration::from_secs(60)),
+            None,
+        );
+
+        for _ in 0..4 {
+            svc.call(req()).await.unwrap();
+        }
+        assert_eq!(primary.calls(), 3);
+        assert_eq!(secondary.calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn probes_preferred_endpoint_and_fails_back() {
+        let primary = MockGateway::new(false);
+        let secondary = MockGateway::new(true);
+        let mut svc = FailoverSvc::new(
+            vec![
+                ("primary".to_string(), primary.svc()),
+                ("secondary".to_string(), secondary.svc()),
+            ],
+            // Primary was unreachable at connection time
+            1,
+            cfg(Duration::from_millis(50)),
+            None,
+        );
+
+        svc.call(req()).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        // Probe against the still-dead primary fails, and we stay on the secondary
+        svc.call(req()).await.unwrap_err();
+        svc.call(req()).await.unwrap();
+        assert_eq!(primary.calls(), 1);
+        assert_eq!(secondary.calls(), 2);
+
+        primary.alive.store(true, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        svc.call(req()).await.unwrap();
+        svc.call(req()).await.unwrap();
+        assert_eq!(primary.calls(), 3);
+        assert_eq!(secondary.calls(), 2);
+    }
+
+    #[tokio::test]
+    async fn tracks_connection_loss_and_recovery() {
+        let gateway = MockGateway::new(true);
+        let mut svc = FailoverSvc::new(
+            vec![("only".to_string(), gateway.svc())],
+            0,
+            cfg(Duration::from_secs(60)),
+            None,
+        );
+        let disconnected = |svc: &FailoverSvc<_>| svc.shared.state.lock().disconnected[0];
+
+        svc.call(req()).await.unwrap();
+        assert!(!disconnected(&svc));
+        gateway.alive.store(false, Ordering::SeqCst);
+        for _ in 0..5 {
+            svc.call(req()).await.unwrap_err();
+            assert!(disconnected(&svc));
+        }
+        gateway.alive.store(true, Ordering::SeqCst);
+        svc.call(req()).await.unwrap();
+        assert!(!disconnected(&svc));
+        // With nowhere else to go, failures never cause a failover
+        assert_eq!(svc.shared.state.lock().active, 0);
+        assert_eq!(gateway.calls(), 7);
+    }
+
+    #[tokio::test]
+    async fn stale_results_do_not_affect_new_endpoint() {
+        let primary = MockGateway::new(false);
+        let secondary = MockGateway::new(true);
+        let svc = FailoverSvc::new(
+            vec![
+                ("primary".to_string(), primary.svc()),
+                ("secondary".to_string(), secondary.svc()),
+            ],
+            1,
+            cfg(Duration::from_secs(60)),
+            None,
+        );
+        // Failures from calls that were routed to the primary before failing over are ignored
+        for _ in 0..5 {
+            svc.shared.record_outcome(0, false, true);
+        }
+        assert_eq!(svc.shared.choose_endpoint(), (1, false));
+    }
+}
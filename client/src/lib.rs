@@ -7,21 +7,33 @@
 #[macro_use]
 extern crate tracing;
 
+mod connections;
+mod failover;
 mod metrics;
 mod proxy;
 mod raw;
 mod retry;
+mod schedules;
+mod task_queues;
+mod tls;
 mod worker_registry;
 mod workflow_handle;
 
 pub use crate::{
+    connections::ConnectionManager,
+    failover::FailoverConfig,
     proxy::HttpConnectProxyOptions,
     retry::{CallType, RetryClient, RETRYABLE_ERROR_CODES},
+    schedules::{
+        CreateScheduleOptions, ScheduleAction, ScheduleCalendarSpec, ScheduleDefinition,
+        ScheduleIntervalSpec, SchedulePolicies, ScheduleRange, ScheduleSpec,
+    },
+    task_queues::{TaskQueueDescription, TaskQueuePoller, TaskQueueStats},
 };
 pub use metrics::{LONG_REQUEST_LATENCY_HISTOGRAM_NAME, REQUEST_LATENCY_HISTOGRAM_NAME};
 pub use raw::{CloudService, HealthService, OperatorService, TestService, WorkflowService};
 pub use temporal_sdk_core_protos::temporal::api::{
-    enums::v1::ArchivalState,
+    enums::v1::{ArchivalState, ScheduleOverlapPolicy, TaskQueueType},
     filter::v1::{StartTimeFilter, StatusFilter, WorkflowExecutionFilter, WorkflowTypeFilter},
     workflowservice::v1::{
         list_closed_workflow_executions_request::Filters as ListClosedFilters,
@@ -35,14 +47,17 @@ pub use workflow_handle::{
 };
 
 use crate::{
+    connections::SharedConnection,
+    failover::FailoverSvc,
     metrics::{GrpcMetricSvc, MetricsContext},
-    raw::{sealed::RawClientLike, AttachMetricLabels},
+    raw::{sealed::RawClientLike, AttachMetricLabels, UsesLongPollTimeout},
     sealed::WfHandleClient,
     workflow_handle::UntypedWorkflowHandle,
 };
 use backoff::{exponential, ExponentialBackoff, SystemClock};
 use http::{uri::InvalidUri, Uri};
 use parking_lot::RwLock;
+use prost::Message;
 use std::{
     collections::HashMap,
     fmt::{Debug, Formatter},
@@ -54,15 +69,20 @@ use std::{
 use temporal_sdk_core_api::telemetry::metrics::TemporalMeter;
 use temporal_sdk_core_protos::{
     coresdk::{workflow_commands::QueryResult, IntoPayloadsExt},
+    google::rpc::Status as RpcStatus,
     grpc::health::v1::health_client::HealthClient,
     temporal::api::{
         cloud::cloudservice::v1::cloud_service_client::CloudServiceClient,
         common::v1::{Header, Payload, Payloads, RetryPolicy, WorkflowExecution, WorkflowType},
-        enums::v1::{TaskQueueKind, WorkflowIdConflictPolicy, WorkflowIdReusePolicy},
+        enums::v1::{
+            DescribeTaskQueueMode, TaskQueueKind, WorkflowIdConflictPolicy, WorkflowIdReusePolicy,
+        },
+        errordetails::v1::WorkflowExecutionAlreadyStartedFailure,
         failure::v1::Failure,
         operatorservice::v1::operator_service_client::OperatorServiceClient,
         query::v1::WorkflowQuery,
         replication::v1::ClusterReplicationConfig,
+        schedule::v1::SchedulePatch,
         taskqueue::v1::TaskQueue,
         testservice::v1::test_service_client::TestServiceClient,
         update,
@@ -106,6 +126,17 @@ pub struct ClientOptions {
     #[builder(setter(into))]
     pub target_url: Url,
 
+    /// Additional frontend endpoints of the same Temporal service (ex: a DR cluster), in order of
+    /// preference after [ClientOptions::target_url]. If set, calls fail over to the next endpoint
+    /// when the active one becomes unreachable, and fail back once the preferred one recovers. All
+    /// endpoints share the rest of these options. See [FailoverConfig].
+    #[builder(default)]
+    pub failover_target_urls: Vec<Url>,
+
+    /// Controls failover between endpoints when [ClientOptions::failover_target_urls] is set
+    #[builder(default)]
+    pub failover: FailoverConfig,
+
     /// The name of the SDK being implemented on top of core. Is set as `client-name` header in
     /// all RPC calls
     #[builder(setter(into))]
@@ -116,7 +147,8 @@ pub struct ClientOptions {
     #[builder(setter(into))]
     pub client_version: String,
 
-    /// A human-readable string that can identify this process. Defaults to empty string.
+    /// A human-readable string that can identify this process. Defaults to empty string, in which
+    /// case core workers using this client identify themselves as `<pid>@<hostname>`.
     #[builder(default)]
     pub identity: String,
 
@@ -130,6 +162,14 @@ pub struct ClientOptions {
     #[builder(default)]
     pub retry_config: RetryConfig,
 
+    /// Retry configuration for workflow and activity task long polls, used instead of
+    /// [ClientOptions::retry_config] for them. Polls time out and are cancelled routinely, so
+    /// those errors are retried even though other calls don't retry them, but they still count
+    /// towards [RetryConfig::max_retries]. By default polls are retried indefinitely, backing off
+    /// to at most 10 seconds between attempts, so they are only returned if a finite limit is set.
+    #[builder(default = "RetryConfig::poll_retry_policy()")]
+    pub long_poll_retry_config: RetryConfig,
+
     /// If set, override the origin used when connecting. May be useful in rare situations where tls
     /// verification needs to use a different name from what should be set as the `:authority`
     /// header. If [TlsConfig::domain] is set, and this is not, this will be set to
@@ -142,6 +182,16 @@ pub struct ClientOptions {
     #[builder(default = "Some(ClientKeepAliveConfig::default())")]
     pub keep_alive: Option<ClientKeepAliveConfig>,
 
+    /// How long to wait for a connection to the server to be established before giving up on
+    /// that attempt. Also applies when a lost connection is re-established. If unset, only the
+    /// operating system's own timeout applies, which may be minutes.
+    #[builder(default = "Some(Duration::from_secs(10))")]
+    pub connect_timeout: Option<Duration>,
+
+    /// Deadlines for calls which don't set their own. See [RpcTimeouts].
+    #[builder(default)]
+    pub rpc_timeouts: RpcTimeouts,
+
     /// HTTP headers to include on every RPC call.
     #[builder(default)]
     pub headers: Option<HashMap<String, String>>,
@@ -205,6 +255,27 @@ impl Default for ClientKeepAliveConfig {
     }
 }
 
+/// Deadlines applied to calls which don't carry a `grpc-timeout` of their own. A call which hasn't
+/// completed by its deadline fails, so that a call on a connection which silently stopped
+/// responding is retried rather than waited on forever.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RpcTimeouts {
+    /// Deadline for long polls (ex: for workflow tasks, or waiting on an update). Server answers
+    /// those within a minute even when it has nothing to return, so this should be a bit longer.
+    pub long_poll: Duration,
+    /// Deadline for every other call
+    pub other: Duration,
+}
+
+impl Default for RpcTimeouts {
+    fn default() -> Self {
+        Self {
+            long_poll: LONG_POLL_TIMEOUT,
+            other: OTHER_CALL_TIMEOUT,
+        }
+    }
+}
+
 /// Configuration for retrying requests to the server
 #[derive(Clone, Debug, PartialEq)]
 pub struct RetryConfig {
@@ -300,6 +371,116 @@ pub enum ClientInitError {
     /// server capabilities / verify server is responding.
     #[error("`get_system_info` call error after connection: {0:?}")]
     SystemInfoCallError(tonic::Status),
+    /// The TLS options are incomplete or contain malformed certificates or keys. Configuration
+    /// error, fatal.
+    #[error("Invalid TLS configuration: {0}")]
+    InvalidTlsConfig(String),
+}
+
+/// Errors returned by workflow and schedule client calls where the server's answer is definitive,
+/// so retrying the call (which the [RetryClient] will not do) cannot change the outcome.
+#[derive(thiserror::Error, Debug)]
+pub enum WorkflowCallError {
+    /// The targeted workflow does not exist, or has already completed. Returned by
+    /// [WorkflowClientTrait::signal_workflow_execution] and
+    /// [WorkflowClientTrait::cancel_workflow_execution].
+    #[error("Workflow {workflow_id} (run {run_id:?}) not found or already completed")]
+    WorkflowNotFound {
+        /// Id of the workflow the call targeted
+        workflow_id: String,
+        /// Run id the call targeted, if one was specified
+        run_id: Option<String>,
+    },
+    /// A workflow with the same id is already running. Returned by
+    /// [WorkflowClientTrait::start_workflow].
+    #[error("Workflow already started with run id {run_id:?}")]
+    WorkflowAlreadyStarted {
+        /// Run id of the existing workflow, if the server included it in the error details
+        run_id: Option<String>,
+    },
+    /// A schedule with the same id already exists. Returned by
+    /// [WorkflowClientTrait::create_schedule].
+    #[error("Schedule {schedule_id} already exists")]
+    ScheduleAlreadyExists {
+        /// Id of the schedule the call tried to create
+        schedule_id: String,
+    },
+    /// The targeted schedule does not exist. Returned by the schedule calls other than
+    /// [WorkflowClientTrait::create_schedule].
+    #[error("Schedule {schedule_id} not found")]
+    ScheduleNotFound {
+        /// Id of the schedule the call targeted
+        schedule_id: String,
+    },
+    /// The call was aborted by the [RetryClient]'s cancellation token before it completed. See
+    /// [RetryClient::with_cancellation].
+    #[error("Call was cancelled before it completed")]
+    Cancelled,
+    /// Any other error returned by the call
+    #[error("{0}")]
+    Status(#[from] tonic::Status),
+}
+
+impl WorkflowCallError {
+    /// Interpret an error returned by a call which targets an existing workflow
+    fn from_targeting_call(status: Status, workflow_id: String, run_id: Option<String>) -> Self {
+        if status.code() == Code::NotFound {
+            return Self::WorkflowNotFound {
+                workflow_id,
+                run_id: run_id.filter(|r| !r.is_empty()),
+            };
+        }
+        status.into()
+    }
+
+    /// Interpret an error returned by a call which starts a workflow
+    fn from_start_call(status: Status) -> Self {
+        if status.code() == Code::AlreadyExists {
+            return Self::WorkflowAlreadyStarted {
+                run_id: already_started_run_id(&status),
+            };
+        }
+        status.into()
+    }
+
+    /// Interpret an error returned by a call which creates or targets a schedule
+    fn from_schedule_call(status: Status, schedule_id: String) -> Self {
+        match status.code() {
+            Code::AlreadyExists => Self::ScheduleAlreadyExists { schedule_id },
+            Code::NotFound => Self::ScheduleNotFound { schedule_id },
+            _ => status.into(),
+        }
+    }
+}
+
+impl From<WorkflowCallError> for Status {
+    fn from(e: WorkflowCallError) -> Self {
+        match e {
+            WorkflowCallError::Status(s) => s,
+            e @ WorkflowCallError::WorkflowNotFound { .. } => Status::not_found(e.to_string()),
+            e @ (WorkflowCallError::WorkflowAlreadyStarted { .. }
+            | WorkflowCallError::ScheduleAlreadyExists { .. }) => {
+                Status::already_exists(e.to_string())
+            }
+            e @ WorkflowCallError::ScheduleNotFound { .. } => Status::not_found(e.to_string()),
+            e @ WorkflowCallError::Cancelled => Status::cancelled(e.to_string()),
+        }
+    }
+}
+
+/// Server attaches a [WorkflowExecutionAlreadyStartedFailure] to the `google.rpc.Status` details
+/// of the error when a start is rejected because the workflow is already running.
+fn already_started_run_id(status: &Status) -> Option<String> {
+    let details = RpcStatus::decode(status.details()).ok()?;
+    details
+        .details
+        .iter()
+        .filter(|any| {
+            any.type_url
+                .ends_with("temporal.api.errordetails.v1.WorkflowExecutionAlreadyStartedFailure")
+        })
+        .find_map(|any| WorkflowExecutionAlreadyStartedFailure::decode(any.value.as_slice()).ok())
+        .map(|f| f.run_id)
 }
 
 /// A client with [ClientOptions] attached, which can be passed to initialize workers,
@@ -312,6 +493,10 @@ pub struct ConfiguredClient<C> {
     /// Capabilities as read from the `get_system_info` RPC call made on client connection
     capabilities: Option<get_system_info_response::Capabilities>,
     workers: Arc<SlotManager>,
+    /// Kept so that reconnecting produces a client which reports the same metrics
+    metrics_meter: Option<TemporalMeter>,
+    /// Set if the client's channel came from a [ConnectionManager]
+    shared_connection: Option<SharedConnection>,
 }
 
 impl<C> ConfiguredClient<C> {
@@ -340,6 +525,36 @@ impl<C> ConfiguredClient<C> {
     pub fn workers(&self) -> Arc<SlotManager> {
         self.workers.clone()
     }
+
+    /// Returns the number of connections to the server this client's calls are spread over. For a
+    /// client made with [ClientOptions::connect_shared], these are shared with every other client
+    /// using the same [ConnectionManager], endpoint, and connection settings.
+    pub fn connection_count(&self) -> usize {
+        self.shared_connection
+            .as_ref()
+            .map_or(1, SharedConnection::connection_count)
+    }
+}
+
+impl ConfiguredClient<TemporalServiceClientWithMetrics> {
+    /// Establish a brand new channel to the server with the same options as this client. The
+    /// returned client keeps this client's current headers, metrics, and worker registry. The new
+    /// channel is never shared, even if this client's was.
+    pub async fn reconnect(&self) -> Result<Self, ClientInitError> {
+        let mut fresh = self
+            .options
+            .connect_no_namespace(self.metrics_meter.clone())
+            .await?
+            .into_inner();
+        {
+            let current = self.headers.read();
+            let mut fresh_headers = fresh.headers.write();
+            fresh_headers.user_headers = current.user_headers.clone();
+            fresh_headers.api_key = current.api_key.clone();
+        }
+        fresh.workers = self.workers.clone();
+        Ok(fresh)
+    }
 }
 
 #[derive(Debug)]
@@ -395,10 +610,32 @@ impl ClientOptions {
     ) -> Result<RetryClient<Client>, ClientInitError> {
         let client = self.connect_no_namespace(metrics_meter).await?.into_inner();
         let client = Client::new(client, namespace.into());
-        let retry_client = RetryClient::new(client, self.retry_config.clone());
+        let retry_client = RetryClient::new(client, self.retry_config.clone())
+            .with_long_poll_retry_config(self.long_poll_retry_config.clone());
         Ok(retry_client)
     }
 
+    /// Like [Self::connect], but rather than connecting a channel of its own, the client uses one
+    /// from `connections`, shared with other clients for the same endpoint and connection
+    /// settings (TLS, keep alive, proxy, etc). The channel connects when the first call is made
+    /// over it, which unless [ClientOptions::skip_get_system_info] is set is the
+    /// `get_system_info` call made here.
+    pub async fn connect_shared(
+        &self,
+        connections: &ConnectionManager,
+        namespace: impl Into<String>,
+        metrics_meter: Option<TemporalMeter>,
+    ) -> Result<RetryClient<Client>, ClientInitError> {
+        let (channel, shared) = connections.channel_for(self, metrics_meter.clone())?;
+        let client = self
+            .configure_client(channel, Some(shared), metrics_meter)
+            .await?
+            .into_inner();
+        let client = Client::new(client, namespace.into());
+        Ok(RetryClient::new(client, self.retry_config.clone())
+            .with_long_poll_retry_config(self.long_poll_retry_config.clone()))
+    }
+
     /// Attempt to establish a connection to the Temporal server and return a gRPC client which is
     /// intercepted with retry, default headers functionality, and metrics if provided.
     ///
@@ -408,29 +645,21 @@ impl ClientOptions {
         metrics_meter: Option<TemporalMeter>,
     ) -> Result<RetryClient<ConfiguredClient<TemporalServiceClientWithMetrics>>, ClientInitError>
     {
-        let channel = Channel::from_shared(self.target_url.to_string())?;
-        let channel = self.add_tls_to_channel(channel).await?;
-        let channel = if let Some(keep_alive) = self.keep_alive.as_ref() {
-            channel
-                .keep_alive_while_idle(true)
-                .http2_keep_alive_interval(keep_alive.interval)
-                .keep_alive_timeout(keep_alive.timeout)
-        } else {
-            channel
-        };
-        let channel = if let Some(origin) = self.override_origin.clone() {
-            channel.origin(origin)
-        } else {
-            channel
-        };
-        // If there is a proxy, we have to connect that way
-        let channel = if let Some(proxy) = self.http_connect_proxy.as_ref() {
-            proxy.connect_endpoint(&channel).await?
-        } else {
-            channel.connect().await?
-        };
+        let channel = self.connect_channels(metrics_meter.clone()).await?;
+        self.configure_client(channel, None, metrics_meter).await
+    }
+
+    /// Wrap a channel with metrics, headers, and retries, then learn the server's capabilities
+    /// over it unless told not to
+    async fn configure_client(
+        &self,
+        channel: FailoverSvc,
+        shared_connection: Option<SharedConnection>,
+        metrics_meter: Option<TemporalMeter>,
+    ) -> Result<RetryClient<ConfiguredClient<TemporalServiceClientWithMetrics>>, ClientInitError>
+    {
         let service = ServiceBuilder::new()
-            .layer_fn(move |channel| GrpcMetricSvc {
+            .layer_fn(|channel| GrpcMetricSvc {
                 inner: channel,
                 metrics: metrics_meter.clone().map(MetricsContext::new),
                 disable_errcode_label: self.disable_error_code_metric_tags,
@@ -452,6 +681,8 @@ impl ClientOptions {
             options: Arc::new(self.clone()),
             capabilities: None,
             workers: Arc::new(SlotManager::new()),
+            metrics_meter,
+            shared_connection,
         };
         if !self.skip_get_system_info {
             match client
@@ -467,13 +698,138 @@ impl ClientOptions {
                 },
             };
         }
-        Ok(RetryClient::new(client, self.retry_config.clone()))
+        Ok(RetryClient::new(client, self.retry_config.clone())
+            .with_long_poll_retry_config(self.long_poll_retry_config.clone()))
+    }
+
+    /// Connect to the target url, or if failover urls are configured, to the first endpoint which
+    /// is reachable. The other endpoints are connected lazily, so that we can fail over (or back)
+    /// to them later.
+    async fn connect_channels(
+        &self,
+        metrics_meter: Option<TemporalMeter>,
+    ) -> Result<FailoverSvc, ClientInitError> {
+        let urls: Vec<_> = self.all_target_urls().collect();
+        let mut endpoints = Vec::with_capacity(urls.len());
+        for url in urls.iter() {
+            endpoints.push(self.configure_endpoint(url)?);
+        }
+        if endpoints.len() == 1 {
+            let channel = self.connect_endpoint(&endpoints[0]).await?;
+            return Ok(FailoverSvc::new(
+                vec![(self.target_url.to_string(), channel)],
+                0,
+                self.failover.clone(),
+                metrics_meter.map(MetricsContext::new),
+            ));
+        }
+
+        let mut channels = Vec::with_capacity(endpoints.len());
+        let mut active = None;
+        let mut first_err = None;
+        for (i, (url, endpoint)) in urls.iter().zip(endpoints.iter()).enumerate() {
+            if active.is_none() {
+                match self.connect_endpoint(endpoint).await {
+                    Ok(channel) => {
+                        active = Some(i);
+                        channels.push((url.to_string(), channel));
+                        continue;
+                    }
+                    Err(e) => {
+                        warn!(endpoint = %url, error = ?e, "Could not connect to endpoint");
+                        first_err.get_or_insert(e);
+                    }
+                }
+            }
+            channels.push((url.to_string(), self.connect_endpoint_lazy(endpoint)));
+        }
+        let Some(active) = active else {
+            return Err(first_err
+                .expect("There is an error for every endpoint if none connected")
+                .into());
+        };
+        Ok(FailoverSvc::new(
+            channels,
+            active,
+            self.failover.clone(),
+            metrics_meter.map(MetricsContext::new),
+        ))
+    }
+
+    /// Build channels to the target url and any failover urls without connecting any of them.
+    /// Each connects when the first call is made over it.
+    pub(crate) fn lazy_channels(
+        &self,
+        metrics_meter: Option<TemporalMeter>,
+    ) -> Result<FailoverSvc, ClientInitError> {
+        let mut channels = vec![];
+        for url in self.all_target_urls() {
+            let endpoint = self.configure_endpoint(url)?;
+            channels.push((url.to_string(), self.connect_endpoint_lazy(&endpoint)));
+        }
+        Ok(FailoverSvc::new(
+            channels,
+            0,
+            self.failover.clone(),
+            metrics_meter.map(MetricsContext::new),
+        ))
+    }
+
+    /// The target url, followed by any failover urls in order of preference
+    pub(crate) fn all_target_urls(&self) -> impl Iterator<Item = &Url> {
+        std::iter::once(&self.target_url).chain(self.failover_target_urls.iter())
+    }
+
+    /// Build an endpoint for the provided url with all the connection options applied
+    fn configure_endpoint(&self, url: &Url) -> Result<Endpoint, ClientInitError> {
+        let channel = Channel::from_shared(url.to_string())?;
+        let channel = self.add_tls_to_channel(channel)?;
+        let channel = if let Some(keep_alive) = self.keep_alive.as_ref() {
+            channel
+                .keep_alive_while_idle(true)
+                .http2_keep_alive_interval(keep_alive.interval)
+                .keep_alive_timeout(keep_alive.timeout)
+        } else {
+            channel
+        };
+        let channel = if let Some(connect_timeout) = self.connect_timeout {
+            channel.connect_timeout(connect_timeout)
+        } else {
+            channel
+        };
+        let channel = if let Some(origin) = self.override_origin.clone() {
+            channel.origin(origin)
+        } else {
+            channel
+        };
+        Ok(channel)
+    }
+
+    async fn connect_endpoint(
+        &self,
+        endpoint: &Endpoint,
+    ) -> Result<Channel, tonic::transport::Error> {
+        // If there is a proxy, we have to connect that way
+        if let Some(proxy) = self.http_connect_proxy.as_ref() {
+            proxy.connect_endpoint(endpoint).await
+        } else {
+            endpoint.connect().await
+        }
+    }
+
+    fn connect_endpoint_lazy(&self, endpoint: &Endpoint) -> Channel {
+        if let Some(proxy) = self.http_connect_proxy.as_ref() {
+            proxy.connect_endpoint_lazy(endpoint)
+        } else {
+            endpoint.connect_lazy()
+        }
     }
 
     /// If TLS is configured, set the appropriate options on the provided channel and return it.
     /// Passes it through if TLS options not set.
-    async fn add_tls_to_channel(&self, mut channel: Endpoint) -> Result<Endpoint, ClientInitError> {
+    fn add_tls_to_channel(&self, mut channel: Endpoint) -> Result<Endpoint, ClientInitError> {
         if let Some(tls_cfg) = &self.tls_cfg {
+            tls_cfg.validate()?;
             let mut tls = tonic::transport::ClientTlsConfig::new().with_native_roots();
 
             if let Some(root_cert) = &tls_cfg.server_root_ca_cert {
@@ -536,7 +892,12 @@ impl Interceptor for ServiceCallInterceptor {
             );
         }
         self.headers.read().apply_to_metadata(metadata);
-        request.set_default_timeout(OTHER_CALL_TIMEOUT);
+        let timeout = if request.extensions().get::<UsesLongPollTimeout>().is_some() {
+            self.opts.rpc_timeouts.long_poll
+        } else {
+            self.opts.rpc_timeouts.other
+        };
+        request.set_default_timeout(timeout);
 
         Ok(request)
     }
@@ -675,6 +1036,15 @@ impl Client {
         }
     }
 
+    /// Establish a brand new channel to the server, bound to the same namespace as this client.
+    /// See [ConfiguredClient::reconnect].
+    pub async fn reconnect(&self) -> Result<Self, ClientInitError> {
+        Ok(Client::new(
+            self.inner.reconnect().await?,
+            self.namespace.clone(),
+        ))
+    }
+
     /// Return an auto-retrying version of the underling grpc client (instrumented with metrics
     /// collection, if enabled).
     ///
@@ -685,6 +1055,7 @@ impl Client {
             self.raw_client().clone(),
             self.inner.options.retry_config.clone(),
         )
+        .with_long_poll_retry_config(self.inner.options.long_poll_retry_config.clone())
     }
 
     /// Access the underling grpc client. This raw client is not bound to a specific namespace.
@@ -695,6 +1066,26 @@ impl Client {
         self.inner.workflow_svc()
     }
 
+    async fn send_schedule_patch(
+        &self,
+        schedule_id: String,
+        patch: SchedulePatch,
+    ) -> Result<PatchScheduleResponse, WorkflowCallError> {
+        Ok(WorkflowService::patch_schedule(
+            &mut self.inner.client.clone(),
+            PatchScheduleRequest {
+                namespace: self.namespace.clone(),
+                schedule_id: schedule_id.clone(),
+                patch: Some(patch),
+                identity: self.inner.options.identity.clone(),
+                request_id: Uuid::new_v4().to_string(),
+            },
+        )
+        .await
+        .map_err(|e| WorkflowCallError::from_schedule_call(e, schedule_id))?
+        .into_inner())
+    }
+
     /// Return the options this client was initialized with
     pub fn options(&self) -> &ClientOptions {
         &self.inner.options
@@ -867,7 +1258,8 @@ impl SignalWithStartOptions {
 #[cfg_attr(test, mockall::automock)]
 #[async_trait::async_trait]
 pub trait WorkflowClientTrait {
-    /// Starts workflow execution.
+    /// Starts workflow execution. Fails with [WorkflowCallError::WorkflowAlreadyStarted] if the
+    /// workflow id is already in use and the id policies don't permit starting another.
     async fn start_workflow(
         &self,
         input: Vec<Payload>,
@@ -876,7 +1268,7 @@ pub trait WorkflowClientTrait {
         workflow_type: String,
         request_id: Option<String>,
         options: WorkflowOptions,
-    ) -> Result<StartWorkflowExecutionResponse>;
+    ) -> Result<StartWorkflowExecutionResponse, WorkflowCallError>;
 
     /// Notifies the server that workflow tasks for a given workflow should be sent to the normal
     /// non-sticky task queue. This normally happens when workflow has been evicted from the cache.
@@ -923,7 +1315,8 @@ pub trait WorkflowClientTrait {
         failure: Option<Failure>,
     ) -> Result<RespondActivityTaskFailedResponse>;
 
-    /// Send a signal to a certain workflow instance
+    /// Send a signal to a certain workflow instance. Fails with
+    /// [WorkflowCallError::WorkflowNotFound] if it doesn't exist or has already completed.
     async fn signal_workflow_execution(
         &self,
         workflow_id: String,
@@ -931,7 +1324,7 @@ pub trait WorkflowClientTrait {
         signal_name: String,
         payloads: Option<Payloads>,
         request_id: Option<String>,
-    ) -> Result<SignalWorkflowExecutionResponse>;
+    ) -> Result<SignalWorkflowExecutionResponse, WorkflowCallError>;
 
     /// Send signal and start workflow transcationally
     //#TODO maybe lift the Signal type from sdk::workflow_context::options
@@ -972,14 +1365,15 @@ pub trait WorkflowClientTrait {
         query_result: QueryResult,
     ) -> Result<RespondQueryTaskCompletedResponse>;
 
-    /// Cancel a currently executing workflow
+    /// Cancel a currently executing workflow. Fails with [WorkflowCallError::WorkflowNotFound] if
+    /// it doesn't exist or has already completed.
     async fn cancel_workflow_execution(
         &self,
         workflow_id: String,
         run_id: Option<String>,
         reason: String,
         request_id: Option<String>,
-    ) -> Result<RequestCancelWorkflowExecutionResponse>;
+    ) -> Result<RequestCancelWorkflowExecutionResponse, WorkflowCallError>;
 
     /// Terminate a currently executing workflow
     async fn terminate_workflow_execution(
@@ -1047,6 +1441,67 @@ pub trait WorkflowClientTrait {
         args: Option<Payloads>,
     ) -> Result<UpdateWorkflowExecutionResponse>;
 
+    /// Create a schedule. Fails with [WorkflowCallError::ScheduleAlreadyExists] if a schedule
+    /// with the same id exists.
+    async fn create_schedule(
+        &self,
+        schedule_id: String,
+        schedule: ScheduleDefinition,
+        options: CreateScheduleOptions,
+    ) -> Result<CreateScheduleResponse, WorkflowCallError>;
+
+    /// Get a schedule's definition and recent and upcoming actions
+    async fn describe_schedule(
+        &self,
+        schedule_id: String,
+    ) -> Result<DescribeScheduleResponse, WorkflowCallError>;
+
+    /// Replace a schedule's definition. If `conflict_token` (from
+    /// [WorkflowClientTrait::describe_schedule]) is provided, the update only succeeds if the
+    /// schedule has not changed since.
+    async fn update_schedule(
+        &self,
+        schedule_id: String,
+        schedule: ScheduleDefinition,
+        conflict_token: Option<Vec<u8>>,
+    ) -> Result<UpdateScheduleResponse, WorkflowCallError>;
+
+    /// Pause a schedule, optionally recording why
+    async fn pause_schedule(
+        &self,
+        schedule_id: String,
+        note: Option<String>,
+    ) -> Result<PatchScheduleResponse, WorkflowCallError>;
+
+    /// Unpause a schedule, optionally recording why
+    async fn unpause_schedule(
+        &self,
+        schedule_id: String,
+        note: Option<String>,
+    ) -> Result<PatchScheduleResponse, WorkflowCallError>;
+
+    /// Take a schedule's action now, handling any overlap with running workflows per
+    /// `overlap_policy` rather than the schedule's own policy
+    async fn trigger_schedule(
+        &self,
+        schedule_id: String,
+        overlap_policy: ScheduleOverlapPolicy,
+    ) -> Result<PatchScheduleResponse, WorkflowCallError>;
+
+    /// Delete a schedule. Workflows it already started are unaffected.
+    async fn delete_schedule(
+        &self,
+        schedule_id: String,
+    ) -> Result<DeleteScheduleResponse, WorkflowCallError>;
+
+    /// Describe the pollers server currently sees for one type of a (non-sticky) task queue, and
+    /// its backlog if server supports reporting it
+    async fn describe_task_queue(
+        &self,
+        task_queue: String,
+        task_queue_type: TaskQueueType,
+    ) -> Result<TaskQueueDescription>;
+
     /// Returns options that were used to initialize the client
     fn get_options(&self) -> &ClientOptions;
 
@@ -1098,7 +1553,7 @@ impl WorkflowClientTrait for Client {
         workflow_type: String,
         request_id: Option<String>,
         options: WorkflowOptions,
-    ) -> Result<StartWorkflowExecutionResponse> {
+    ) -> Result<StartWorkflowExecutionResponse, WorkflowCallError> {
         Ok(WorkflowService::start_workflow_execution(
             &mut self.inner.clone(),
             StartWorkflowExecutionRequest {
@@ -1128,7 +1583,8 @@ impl WorkflowClientTrait for Client {
                 ..Default::default()
             },
         )
-        .await?
+        .await
+        .map_err(WorkflowCallError::from_start_call)?
         .into_inner())
     }
 
@@ -1235,14 +1691,14 @@ impl WorkflowClientTrait for Client {
         signal_name: String,
         payloads: Option<Payloads>,
         request_id: Option<String>,
-    ) -> Result<SignalWorkflowExecutionResponse> {
+    ) -> Result<SignalWorkflowExecutionResponse, WorkflowCallError> {
         Ok(WorkflowService::signal_workflow_execution(
             &mut self.inner.client.clone(),
             SignalWorkflowExecutionRequest {
                 namespace: self.namespace.clone(),
                 workflow_execution: Some(WorkflowExecution {
-                    workflow_id,
-                    run_id,
+                    workflow_id: workflow_id.clone(),
+                    run_id: run_id.clone(),
                 }),
                 signal_name,
                 input: payloads,
@@ -1251,7 +1707,8 @@ impl WorkflowClientTrait for Client {
                 ..Default::default()
             },
         )
-        .await?
+        .await
+        .map_err(|e| WorkflowCallError::from_targeting_call(e, workflow_id, Some(run_id)))?
         .into_inner())
     }
 
@@ -1388,14 +1845,14 @@ impl WorkflowClientTrait for Client {
         run_id: Option<String>,
         reason: String,
         request_id: Option<String>,
-    ) -> Result<RequestCancelWorkflowExecutionResponse> {
+    ) -> Result<RequestCancelWorkflowExecutionResponse, WorkflowCallError> {
         Ok(WorkflowService::request_cancel_workflow_execution(
             &mut self.inner.client.clone(),
             RequestCancelWorkflowExecutionRequest {
                 namespace: self.namespace.clone(),
                 workflow_execution: Some(WorkflowExecution {
-                    workflow_id,
-                    run_id: run_id.unwrap_or_default(),
+                    workflow_id: workflow_id.clone(),
+                    run_id: run_id.clone().unwrap_or_default(),
                 }),
                 identity: self.inner.options.identity.clone(),
                 request_id: request_id.unwrap_or_else(|| Uuid::new_v4().to_string()),
@@ -1404,7 +1861,8 @@ impl WorkflowClientTrait for Client {
                 links: vec![],
             },
         )
-        .await?
+        .await
+        .map_err(|e| WorkflowCallError::from_targeting_call(e, workflow_id, run_id))?
         .into_inner())
     }
 
@@ -1586,6 +2044,145 @@ impl WorkflowClientTrait for Client {
         .into_inner())
     }
 
+    async fn create_schedule(
+        &self,
+        schedule_id: String,
+        schedule: ScheduleDefinition,
+        options: CreateScheduleOptions,
+    ) -> Result<CreateScheduleResponse, WorkflowCallError> {
+        Ok(WorkflowService::create_schedule(
+            &mut self.inner.client.clone(),
+            CreateScheduleRequest {
+                namespace: self.namespace.clone(),
+                schedule_id: schedule_id.clone(),
+                schedule: Some(schedule.into()),
+                initial_patch: options
+                    .trigger_immediately
+                    .then(|| schedules::trigger_patch(ScheduleOverlapPolicy::Unspecified)),
+                identity: self.inner.options.identity.clone(),
+                request_id: options
+                    .request_id
+                    .unwrap_or_else(|| Uuid::new_v4().to_string()),
+                memo: None,
+                search_attributes: options.search_attributes.map(|d| d.into()),
+            },
+        )
+        .await
+        .map_err(|e| WorkflowCallError::from_schedule_call(e, schedule_id))?
+        .into_inner())
+    }
+
+    async fn describe_schedule(
+        &self,
+        schedule_id: String,
+    ) -> Result<DescribeScheduleResponse, WorkflowCallError> {
+        Ok(WorkflowService::describe_schedule(
+            &mut self.inner.client.clone(),
+            DescribeScheduleRequest {
+                namespace: self.namespace.clone(),
+                schedule_id: schedule_id.clone(),
+            },
+        )
+        .await
+        .map_err(|e| WorkflowCallError::from_schedule_call(e, schedule_id))?
+        .into_inner())
+    }
+
+    async fn update_schedule(
+        &self,
+        schedule_id: String,
+        schedule: ScheduleDefinition,
+        conflict_token: Option<Vec<u8>>,
+    ) -> Result<UpdateScheduleResponse, WorkflowCallError> {
+        Ok(WorkflowService::update_schedule(
+            &mut self.inner.client.clone(),
+            UpdateScheduleRequest {
+                namespace: self.namespace.clone(),
+                schedule_id: schedule_id.clone(),
+                schedule: Some(schedule.into()),
+                conflict_token: conflict_token.unwrap_or_default(),
+                identity: self.inner.options.identity.clone(),
+                request_id: Uuid::new_v4().to_string(),
+                search_attributes: None,
+            },
+        )
+        .await
+        .map_err(|e| WorkflowCallError::from_schedule_call(e, schedule_id))?
+        .into_inner())
+    }
+
+    async fn pause_schedule(
+        &self,
+        schedule_id: String,
+        note: Option<String>,
+    ) -> Result<PatchScheduleResponse, WorkflowCallError> {
+        self.send_schedule_patch(schedule_id, schedules::pause_patch(note))
+            .await
+    }
+
+    async fn unpause_schedule(
+        &self,
+        schedule_id: String,
+        note: Option<String>,
+    ) -> Result<PatchScheduleResponse, WorkflowCallError> {
+        self.send_schedule_patch(schedule_id, schedules::unpause_patch(note))
+            .await
+    }
+
+    async fn trigger_schedule(
+        &self,
+        schedule_id: String,
+        overlap_policy: ScheduleOverlapPolicy,
+    ) -> Result<PatchScheduleResponse, WorkflowCallError> {
+        self.send_schedule_patch(schedule_id, schedules::trigger_patch(overlap_policy))
+            .await
+    }
+
+    async fn delete_schedule(
+        &self,
+        schedule_id: String,
+    ) -> Result<DeleteScheduleResponse, WorkflowCallError> {
+        Ok(WorkflowService::delete_schedule(
+            &mut self.inner.client.clone(),
+            DeleteScheduleRequest {
+                namespace: self.namespace.clone(),
+                schedule_id: schedule_id.clone(),
+                identity: self.inner.options.identity.clone(),
+            },
+        )
+        .await
+        .map_err(|e| WorkflowCallError::from_schedule_call(e, schedule_id))?
+        .into_inner())
+    }
+
+    async fn describe_task_queue(
+        &self,
+        task_queue: String,
+        task_queue_type: TaskQueueType,
+    ) -> Result<TaskQueueDescription> {
+        // Servers which don't know the enhanced mode ignore it, and answer with pollers only
+        let resp = WorkflowService::describe_task_queue(
+            &mut self.inner.client.clone(),
+            DescribeTaskQueueRequest {
+                namespace: self.namespace.clone(),
+                task_queue: Some(TaskQueue {
+                    name: task_queue,
+                    kind: TaskQueueKind::Normal as i32,
+                    normal_name: "".to_string(),
+                }),
+                task_queue_type: task_queue_type as i32,
+                api_mode: DescribeTaskQueueMode::Enhanced as i32,
+                task_queue_types: vec![task_queue_type as i32],
+                report_stats: true,
+                report_pollers: true,
+                ..Default::default()
+            },
+        )
+        .await?
+        .into_inner();
+        Ok(TaskQueueDescription::from_response(resp, task_queue_type))
+    }
+
     fn get_options(&self) -> &ClientOptions {
         &self.inner.options
     }
@@ -1647,6 +2244,10 @@ impl<T> RequestExt for tonic::Request<T> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use assert_matches::assert_matches;
+    use temporal_sdk_core_protos::{
+        temporal::api::errordetails::v1::NotFoundFailure, utilities::pack_any,
+    };
     use tonic::metadata::Ascii;
 
     #[test]
@@ -1742,4 +2343,173 @@ mod tests {
         let opts = builder.keep_alive(None).build().unwrap();
         assert!(opts.keep_alive.is_none());
     }
+
+    #[test]
+    fn long_polls_get_their_own_default_deadline() {
+        let rpc_timeouts = RpcTimeouts {
+            long_poll: Duration::from_secs(90),
+            other: Duration::from_secs(5),
+        };
+        let mut interceptor = ServiceCallInterceptor {
+            opts: ClientOptionsBuilder::default()
+                .target_url(Url::parse("https://smolkitty").unwrap())
+                .client_name("cute-kitty".to_string())
+                .client_version("0.1.0".to_string())
+                .rpc_timeouts(rpc_timeouts.clone())
+                .build()
+                .unwrap(),
+            headers: Arc::new(RwLock::new(ClientHeaders {
+                user_headers: HashMap::new(),
+                api_key: None,
+            })),
+        };
+        let deadline = |d: Duration| {
+            let mut req = tonic::Request::new(());
+            req.set_timeout(d);
+            req.metadata().get("grpc-timeout").unwrap().clone()
+        };
+
+        let req = interceptor.call(tonic::Request::new(())).unwrap();
+        assert_eq!(
+            req.metadata().get("grpc-timeout").unwrap(),
+            deadline(rpc_timeouts.other)
+        );
+        let mut req = tonic::Request::new(());
+        req.extensions_mut().insert(UsesLongPollTimeout);
+        let req = interceptor.call(req).unwrap();
+        assert_eq!(
+            req.metadata().get("grpc-timeout").unwrap(),
+            deadline(rpc_timeouts.long_poll)
+        );
+    }
+
+    #[tokio::test]
+    async fn calls_on_unresponsive_connection_fail_after_keep_alive_timeout() {
+        // Accepts connections, then never reads from or writes to them, like a load balancer
+        // which has silently dropped its backend
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut held = vec![];
+            while let Ok((sock, _)) = listener.accept().await {
+                held.push(sock);
+            }
+        });
+        let opts = ClientOptionsBuilder::default()
+            .target_url(Url::parse(&format!("http://{addr}")).unwrap())
+            .client_name("cute-kitty".to_string())
+            .client_version("0.1.0".to_string())
+            .keep_alive(Some(ClientKeepAliveConfig {
+                interval: Duration::from_millis(200),
+                timeout: Duration::from_millis(200),
+            }))
+            .build()
+            .unwrap();
+        // No interceptor, so the call carries no deadline and only keep alive can end it
+        let channel = opts
+            .configure_endpoint(&opts.target_url)
+            .unwrap()
+            .connect_lazy();
+        let mut client = WorkflowServiceClient::new(channel);
+        let poll = client.poll_workflow_task_queue(PollWorkflowTaskQueueRequest::default());
+        let res = tokio::time::timeout(Duration::from_secs(5), poll)
+            .await
+            .expect("Poll must fail rather than hang on a dead connection");
+        res.unwrap_err();
+    }
+
+    fn already_exists_status(failure: Option<WorkflowExecutionAlreadyStartedFailure>) -> Status {
+        let mut details = vec![pack_any(
+            "type.googleapis.com/temporal.api.errordetails.v1.NotFoundFailure".to_string(),
+            &NotFoundFailure::default(),
+        )
+        .unwrap()];
+        if let Some(f) = failure {
+            details.push(
+                pack_any(
+                    "type.googleapis.com/temporal.api.errordetails.v1.\
+                     WorkflowExecutionAlreadyStartedFailure"
+                        .to_string(),
+                    &f,
+                )
+                .unwrap(),
+            );
+        }
+        let details = RpcStatus {
+            code: Code::AlreadyExists as i32,
+            message: "already started".to_string(),
+            details,
+        };
+        Status::with_details(
+            Code::AlreadyExists,
+            "already started",
+            details.encode_to_vec().into(),
+        )
+    }
+
+    #[test]
+    fn already_started_error_carries_existing_run_id() {
+        let status = already_exists_status(Some(WorkflowExecutionAlreadyStartedFailure {
+            start_request_id: "req".to_string(),
+            run_id: "existing-run".to_string(),
+        }));
+        assert_matches!(
+            WorkflowCallError::from_start_call(status),
+            WorkflowCallError::WorkflowAlreadyStarted { run_id: Some(r) } if r == "existing-run"
+        );
+    }
+
+    #[test]
+    fn already_started_error_without_details() {
+        assert_matches!(
+            WorkflowCallError::from_start_call(already_exists_status(None)),
+            WorkflowCallError::WorkflowAlreadyStarted { run_id: None }
+        );
+        assert_matches!(
+            WorkflowCallError::from_start_call(Status::already_exists("no details at all")),
+            WorkflowCallError::WorkflowAlreadyStarted { run_id: None }
+        );
+    }
+
+    #[test]
+    fn not_found_on_targeting_calls() {
+        assert_matches!(
+            WorkflowCallError::from_targeting_call(
+                Status::not_found("workflow execution already completed"),
+                "wid".to_string(),
+                Some("".to_string())
+            ),
+            WorkflowCallError::WorkflowNotFound { workflow_id, run_id: None } if workflow_id == "wid"
+        );
+        assert_matches!(
+            WorkflowCallError::from_targeting_call(
+                Status::unavailable("try again"),
+                "wid".to_string(),
+                None
+            ),
+            WorkflowCallError::Status(s) if s.code() == Code::Unavailable
+        );
+    }
+
+    #[test]
+    fn schedule_call_errors() {
+        assert_matches!(
+            WorkflowCallError::from_schedule_call(
+                Status::already_exists("schedule is already registered"),
+                "sid".to_string()
+            ),
+            WorkflowCallError::ScheduleAlreadyExists { schedule_id } if schedule_id == "sid"
+        );
+        assert_matches!(
+            WorkflowCallError::from_schedule_call(
+                Status::not_found("schedule not found"),
+                "sid".to_string()
+            ),
+            WorkflowCallError::ScheduleNotFound { schedule_id } if schedule_id == "sid"
+        );
+        assert_matches!(
+            WorkflowCallError::from_schedule_call(Status::unavailable("try again"), "sid".into()),
+            WorkflowCallError::Status(s) if s.code() == Code::Unavailable
+        );
+    }
 }
@@ -1,4 +1,6 @@
-use crate::{AttachMetricLabels, LONG_POLL_METHOD_NAMES};
+use crate::{
+    failover::FailoverSvc, retry::is_retry_attempt, AttachMetricLabels, LONG_POLL_METHOD_NAMES,
+};
 use futures_util::{future::BoxFuture, FutureExt};
 use std::{
     sync::Arc,
@@ -9,7 +11,7 @@ use temporal_sdk_core_api::telemetry::metrics::{
     CoreMeter, Counter, HistogramDuration, MetricAttributes, MetricKeyValue, MetricParameters,
     TemporalMeter,
 };
-use tonic::{body::BoxBody, transport::Channel, Code};
+use tonic::{body::BoxBody, Code};
 use tower::Service;
 
 /// The string name (which may be prefixed) for this metric
@@ -34,6 +36,10 @@ pub(crate) struct MetricsContext {
 
     svc_request_latency: Arc<dyn HistogramDuration>,
     long_svc_request_latency: Arc<dyn HistogramDuration>,
+
+    endpoint_failover: Arc<dyn Counter>,
+    endpoint_connection_lost: Arc<dyn Counter>,
+    endpoint_reconnected: Arc<dyn Counter>,
 }
 
 impl MetricsContext {
@@ -72,6 +78,23 @@ impl MetricsContext {
                 unit: "duration".into(),
                 description: "Histogram of client long-poll request latencies".into(),
             }),
+            endpoint_failover: meter.counter(MetricParameters {
+                name: "endpoint_failover".into(),
+                description: "Count of client switches to a different endpoint".into(),
+                unit: "".into(),
+            }),
+            endpoint_connection_lost: meter.counter(MetricParameters {
+                name: "endpoint_connection_lost".into(),
+                description: "Count of times calls to an endpoint started failing to connect"
+                    .into(),
+                unit: "".into(),
+            }),
+            endpoint_reconnected: meter.counter(MetricParameters {
+                name: "endpoint_reconnected".into(),
+                description: "Count of times calls to an endpoint connected again after failing to"
+                    .into(),
+                unit: "".into(),
+            }),
             meter,
         }
     }
@@ -114,6 +137,26 @@ impl MetricsContext {
         }
     }
 
+    /// The client switched to a different endpoint, either failing over or failing back
+    pub(crate) fn endpoint_failover(&self, to_endpoint: String) {
+        let kvs = self
+            .meter
+            .extend_attributes(self.kvs.clone(), [endpoint_kv(to_endpoint)].into());
+        self.endpoint_failover.add(1, &kvs);
+    }
+
+    /// Calls to an endpoint started failing to connect, or connected again after having failed to
+    pub(crate) fn endpoint_connection_state(&self, endpoint: String, lost: bool) {
+        let kvs = self
+            .meter
+            .extend_attributes(self.kvs.clone(), [endpoint_kv(endpoint)].into());
+        if lost {
+            self.endpoint_connection_lost.add(1, &kvs);
+        } else {
+            self.endpoint_reconnected.add(1, &kvs);
+        }
+    }
+
     /// Record service request latency
     pub(crate) fn record_svc_req_latency(&self, dur: Duration) {
         if self.poll_is_long {
@@ -128,6 +171,8 @@ const KEY_NAMESPACE: &str = "namespace";
 const KEY_SVC_METHOD: &str = "operation";
 const KEY_TASK_QUEUE: &str = "task_queue";
 const KEY_STATUS_CODE: &str = "status_code";
+const KEY_ENDPOINT: &str = "endpoint";
+const KEY_IS_RETRY: &str = "is_retry";
 
 pub(crate) fn namespace_kv(ns: String) -> MetricKeyValue {
     MetricKeyValue::new(KEY_NAMESPACE, ns)
@@ -141,6 +186,15 @@ pub(crate) fn svc_operation(op: String) -> MetricKeyValue {
     MetricKeyValue::new(KEY_SVC_METHOD, op)
 }
 
+pub(crate) fn endpoint_kv(endpoint: String) -> MetricKeyValue {
+    MetricKeyValue::new(KEY_ENDPOINT, endpoint)
+}
+
+/// Whether the call is a [crate::RetryClient] retrying an earlier, failed attempt
+pub(crate) fn is_retry_kv(is_retry: bool) -> MetricKeyValue {
+    MetricKeyValue::new(KEY_IS_RETRY, is_retry)
+}
+
 pub(crate) fn status_code_kv(code: Code) -> MetricKeyValue {
     MetricKeyValue::new(KEY_STATUS_CODE, code_as_screaming_snake(&code))
 }
@@ -171,7 +225,7 @@ fn code_as_screaming_snake(code: &Code) -> &'static str {
 /// Implements metrics functionality for gRPC (really, any http) calls
 #[derive(Debug, Clone)]
 pub struct GrpcMetricSvc {
-    pub(crate) inner: Channel,
+    pub(crate) inner: FailoverSvc,
     // If set to none, metrics are a no-op
     pub(crate) metrics: Option<MetricsContext>,
     pub(crate) disable_errcode_label: bool,
@@ -201,7 +255,10 @@ impl Service<http::Request<BoxBody>> for GrpcMetricSvc {
                 // Attach method name label if possible
                 req.uri().to_string().rsplit_once('/').map(|split_tup| {
                     let method_name = split_tup.1;
-                    metrics.with_new_attrs([svc_operation(method_name.to_string())]);
+                    metrics.with_new_attrs([
+                        svc_operation(method_name.to_string()),
+                        is_retry_kv(is_retry_attempt()),
+                    ]);
                     if LONG_POLL_METHOD_NAMES.contains(&method_name) {
                         metrics.set_is_long_poll();
                     }
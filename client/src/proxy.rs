@@ -37,6 +37,17 @@ impl HttpConnectProxyOptions {
         endpoint.connect_with_connector(svc_fn).await
     }
 
+    /// Create a channel from the given endpoint that uses the HTTP CONNECT proxy, connecting to
+    /// it only once the channel is first used.
+    pub fn connect_endpoint_lazy(&self, endpoint: &Endpoint) -> Channel {
+        let proxy_options = self.clone();
+        let svc_fn = service_fn(move |uri: tonic::transport::Uri| {
+            let proxy_options = proxy_options.clone();
+            async move { proxy_options.connect(uri).await }
+        });
+        endpoint.connect_with_connector_lazy(svc_fn)
+    }
+
     async fn connect(
         &self,
         uri: tonic::transport::Uri,
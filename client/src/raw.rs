@@ -5,9 +5,10 @@
 use crate::{
     metrics::{namespace_kv, task_queue_kv},
     raw::sealed::RawClientLike,
+    retry::abort_if_cancelled,
     worker_registry::{Slot, SlotManager},
-    Client, ConfiguredClient, InterceptedMetricsSvc, RequestExt, RetryClient,
-    TemporalServiceClient, LONG_POLL_TIMEOUT, TEMPORAL_NAMESPACE_HEADER_KEY,
+    Client, ConfiguredClient, InterceptedMetricsSvc, RetryClient, TemporalServiceClient,
+    TEMPORAL_NAMESPACE_HEADER_KEY,
 };
 use futures_util::{future::BoxFuture, FutureExt, TryFutureExt};
 use std::sync::Arc;
@@ -150,12 +151,13 @@ where
         F: Send + Sync + Unpin + 'static,
     {
         let info = self.get_call_info(call_name, Some(&req));
+        let cancel = self.cancel_token_for(&info);
         let fact = || {
             let req_clone = req_cloner(&req);
             callfn(self, req_clone)
         };
         let res = Self::make_future_retry(info, fact);
-        res.map_err(|(e, _attempt)| e).map_ok(|x| x.0).await
+        abort_if_cancelled(cancel, res.map_err(|(e, _attempt)| e).map_ok(|x| x.0)).await
     }
 }
 
@@ -364,6 +366,11 @@ impl AttachMetricLabels {
 #[derive(Copy, Clone, Debug)]
 pub(super) struct IsUserLongPoll;
 
+/// A request extension that, when set, makes the call default to the long poll deadline from
+/// [crate::RpcTimeouts] rather than the one for other calls
+#[derive(Copy, Clone, Debug)]
+pub(super) struct UsesLongPollTimeout;
+
 // Blanket impl the trait for all raw-client-like things. Since the trait default-implements
 // everything, there's nothing to actually implement.
 impl<RC, T> WorkflowService for RC
@@ -613,7 +620,7 @@ proxier! {
                 r.extensions_mut().insert(IsUserLongPoll);
             }
             if r.get_ref().wait_new_event {
-                r.set_default_timeout(LONG_POLL_TIMEOUT);
+                r.extensions_mut().insert(UsesLongPollTimeout);
             }
         }
     );
@@ -634,7 +641,7 @@ proxier! {
             let mut labels = namespaced_request!(r);
             labels.task_q(r.get_ref().task_queue.clone());
             r.extensions_mut().insert(labels);
-            r.set_default_timeout(LONG_POLL_TIMEOUT);
+            r.extensions_mut().insert(UsesLongPollTimeout);
         }
     );
     (
@@ -663,7 +670,7 @@ proxier! {
             let mut labels = namespaced_request!(r);
             labels.task_q(r.get_ref().task_queue.clone());
             r.extensions_mut().insert(labels);
-            r.set_default_timeout(LONG_POLL_TIMEOUT);
+            r.extensions_mut().insert(UsesLongPollTimeout);
         }
     );
     (
@@ -1018,7 +1025,7 @@ proxier! {
         |r| {
             let labels = namespaced_request!(r);
             r.extensions_mut().insert(labels);
-            r.set_default_timeout(LONG_POLL_TIMEOUT);
+            r.extensions_mut().insert(UsesLongPollTimeout);
         }
     );
     (
@@ -1028,7 +1035,7 @@ proxier! {
         |r| {
             let labels = namespaced_request!(r);
             r.extensions_mut().insert(labels);
-            r.set_default_timeout(LONG_POLL_TIMEOUT);
+            r.extensions_mut().insert(UsesLongPollTimeout);
         }
     );
     (
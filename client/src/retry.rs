@@ -1,15 +1,18 @@
 use crate::{
-    raw::IsUserLongPoll, ClientOptions, ListClosedFilters, ListOpenFilters, Namespace,
-    RegisterNamespaceOptions, Result, RetryConfig, SignalWithStartOptions, StartTimeFilter,
-    WorkflowClientTrait, WorkflowOptions,
+    raw::IsUserLongPoll, ClientOptions, CreateScheduleOptions, ListClosedFilters, ListOpenFilters,
+    Namespace, RegisterNamespaceOptions, Result, RetryConfig, ScheduleDefinition,
+    ScheduleOverlapPolicy, SignalWithStartOptions, StartTimeFilter, TaskQueueDescription,
+    WorkflowCallError, WorkflowClientTrait, WorkflowOptions,
 };
 use backoff::{backoff::Backoff, exponential::ExponentialBackoff, Clock, SystemClock};
 use futures_retry::{ErrorHandler, FutureRetry, RetryPolicy};
-use std::{error::Error, fmt::Debug, future::Future, sync::Arc, time::Duration};
+use futures_util::future::{self, Either};
+use std::{error::Error, fmt::Debug, future::Future, pin::pin, sync::Arc, time::Duration};
 use temporal_sdk_core_protos::{
     coresdk::workflow_commands::QueryResult,
     temporal::api::{
         common::v1::{Payload, Payloads},
+        enums::v1::TaskQueueType,
         failure::v1::Failure,
         query::v1::WorkflowQuery,
         update,
@@ -17,7 +20,20 @@ use temporal_sdk_core_protos::{
     },
     TaskToken,
 };
-use tonic::{Code, Request};
+use tokio::task::futures::TaskLocalFuture;
+use tokio_util::sync::CancellationToken;
+use tonic::{Code, Request, Status};
+
+tokio::task_local! {
+    /// Set while a [RetryClient] is making an attempt at a call: true for every attempt after the
+    /// first
+    static IS_RETRY_ATTEMPT: bool;
+}
+
+/// True if the call being made is a [RetryClient] retrying an earlier attempt that failed
+pub(crate) fn is_retry_attempt() -> bool {
+    IS_RETRY_ATTEMPT.try_with(|r| *r).unwrap_or(false)
+}
 
 /// List of gRPC error codes that client will retry.
 pub const RETRYABLE_ERROR_CODES: [Code; 7] = [
@@ -41,6 +57,8 @@ const POLL_ACTIVITY_METH_NAME: &str = "poll_activity_task_queue";
 pub struct RetryClient<SG> {
     client: SG,
     retry_config: Arc<RetryConfig>,
+    long_poll_retry_config: Arc<RetryConfig>,
+    cancel: Option<CancellationToken>,
 }
 
 impl<SG> RetryClient<SG> {
@@ -49,6 +67,34 @@ impl<SG> RetryClient<SG> {
         Self {
             client,
             retry_config: Arc::new(retry_config),
+            long_poll_retry_config: Arc::new(RetryConfig::poll_retry_policy()),
+            cancel: None,
+        }
+    }
+
+    /// Use the provided retry config for workflow and activity task long polls, rather than the
+    /// default of retrying them indefinitely. See [ClientOptions::long_poll_retry_config].
+    pub fn with_long_poll_retry_config(mut self, retry_config: RetryConfig) -> Self {
+        self.long_poll_retry_config = Arc::new(retry_config);
+        self
+    }
+
+    /// Abort calls made through this client, including any retries they are waiting on, once
+    /// `token` is cancelled. Aborted calls fail with a [Code::Cancelled] status, or
+    /// [WorkflowCallError::Cancelled]. Long polls are never aborted this way.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Wrap `client` the same way this client wraps its own, keeping the retry configs and
+    /// cancellation token this client was set up with, ex: to replace a client after reconnecting
+    pub fn wrapping<T>(&self, client: T) -> RetryClient<T> {
+        RetryClient {
+            client,
+            retry_config: self.retry_config.clone(),
+            long_poll_retry_config: self.long_poll_retry_config.clone(),
+            cancel: self.cancel.clone(),
         }
     }
 }
@@ -73,20 +119,31 @@ impl<SG> RetryClient<SG> {
     ///
     /// This is the "old" path used by higher-level [WorkflowClientTrait] implementors
     // TODO: Get rid of this
-    pub(crate) async fn call_with_retry<R, F, Fut>(
+    pub(crate) async fn call_with_retry<R, E, F, Fut>(
         &self,
         factory: F,
         call_name: &'static str,
-    ) -> Result<R>
+    ) -> Result<R, E>
     where
         F: Fn() -> Fut + Unpin,
-        Fut: Future<Output = Result<R>>,
+        Fut: Future<Output = Result<R, E>>,
+        E: FromCallCancelled,
+        TonicErrorHandler<SystemClock>: ErrorHandler<E, OutError = E>,
     {
         let info = self.get_call_info::<()>(call_name, None);
-        let res = Self::make_future_retry(info, factory).await;
+        let cancel = self.cancel_token_for(&info);
+        let res = abort_if_cancelled(cancel, Self::make_future_retry(info, factory)).await;
         Ok(res.map_err(|(e, _attempt)| e)?.0)
     }
 
+    /// The token which should abort the described call, if any. Long polls are excluded since
+    /// worker shutdown already interrupts them at the appropriate point.
+    pub(crate) fn cancel_token_for(&self, info: &CallInfo) -> Option<CancellationToken> {
+        self.cancel
+            .clone()
+            .filter(|_| info.call_type == CallType::Normal)
+    }
+
     pub(crate) fn get_call_info<R>(
         &self,
         call_name: &'static str,
@@ -97,7 +154,7 @@ impl<SG> RetryClient<SG> {
             match call_name {
                 POLL_WORKFLOW_METH_NAME | POLL_ACTIVITY_METH_NAME => {
                     call_type = CallType::LongPoll;
-                    RetryConfig::poll_retry_policy()
+                    (*self.long_poll_retry_config).clone()
                 }
                 _ => (*self.retry_config).clone(),
             }
@@ -114,21 +171,65 @@ impl<SG> RetryClient<SG> {
         }
     }
 
-    pub(crate) fn make_future_retry<R, F, Fut>(
+    /// Each attempt made by the returned future knows whether it is a retry, see
+    /// [is_retry_attempt]
+    pub(crate) fn make_future_retry<R, E, F, Fut>(
         info: CallInfo,
-        factory: F,
-    ) -> FutureRetry<F, TonicErrorHandler<SystemClock>>
+        mut factory: F,
+    ) -> FutureRetry<
+        impl FnMut() -> TaskLocalFuture<bool, Fut> + Unpin,
+        TonicErrorHandler<SystemClock>,
+    >
     where
         F: FnMut() -> Fut + Unpin,
-        Fut: Future<Output = Result<R>>,
+        Fut: Future<Output = Result<R, E>>,
+        TonicErrorHandler<SystemClock>: ErrorHandler<E, OutError = E>,
     {
+        let mut attempts = 0;
         FutureRetry::new(
-            factory,
+            move || {
+                attempts += 1;
+                IS_RETRY_ATTEMPT.scope(attempts > 1, factory())
+            },
             TonicErrorHandler::new(info, RetryConfig::throttle_retry_policy()),
         )
     }
 }
 
+/// Errors which calls can fail with when they are aborted by a [RetryClient]'s cancellation token
+pub(crate) trait FromCallCancelled {
+    fn call_cancelled() -> Self;
+}
+impl FromCallCancelled for Status {
+    fn call_cancelled() -> Self {
+        Status::cancelled("Call aborted by client cancellation")
+    }
+}
+impl FromCallCancelled for WorkflowCallError {
+    fn call_cancelled() -> Self {
+        WorkflowCallError::Cancelled
+    }
+}
+impl<E: FromCallCancelled> FromCallCancelled for (E, usize) {
+    fn call_cancelled() -> Self {
+        (E::call_cancelled(), 0)
+    }
+}
+
+/// Resolve to the output of `call`, unless `cancel` is cancelled first
+pub(crate) async fn abort_if_cancelled<R, E: FromCallCancelled>(
+    cancel: Option<CancellationToken>,
+    call: impl Future<Output = Result<R, E>>,
+) -> Result<R, E> {
+    let Some(cancel) = cancel else {
+        return call.await;
+    };
+    match future::select(pin!(call), pin!(cancel.cancelled())).await {
+        Either::Left((res, _)) => res,
+        Either::Right(_) => Err(E::call_cancelled()),
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct TonicErrorHandler<C: Clock> {
     backoff: ExponentialBackoff<C>,
@@ -274,6 +375,31 @@ where
     }
 }
 
+impl<C> ErrorHandler<WorkflowCallError> for TonicErrorHandler<C>
+where
+    C: Clock,
+{
+    type OutError = WorkflowCallError;
+
+    fn handle(
+        &mut self,
+        current_attempt: usize,
+        e: WorkflowCallError,
+    ) -> RetryPolicy<WorkflowCallError> {
+        match e {
+            WorkflowCallError::Status(s) => {
+                match ErrorHandler::<tonic::Status>::handle(self, current_attempt, s) {
+                    RetryPolicy::ForwardError(s) => RetryPolicy::ForwardError(s.into()),
+                    RetryPolicy::WaitRetry(d) => RetryPolicy::WaitRetry(d),
+                    RetryPolicy::Repeat => RetryPolicy::Repeat,
+                }
+            }
+            // The server has told us definitively what state the workflow is in
+            e => RetryPolicy::ForwardError(e),
+        }
+    }
+}
+
 macro_rules! retry_call {
     ($myself:ident, $call_name:ident) => { retry_call!($myself, $call_name,) };
     ($myself:ident, $call_name:ident, $($args:expr),*) => {{
@@ -300,7 +426,7 @@ where
         workflow_type: String,
         request_id: Option<String>,
         options: WorkflowOptions,
-    ) -> Result<StartWorkflowExecutionResponse> {
+    ) -> Result<StartWorkflowExecutionResponse, WorkflowCallError> {
         retry_call!(
             self,
             start_workflow,
@@ -385,7 +511,7 @@ where
         signal_name: String,
         payloads: Option<Payloads>,
         request_id: Option<String>,
-    ) -> Result<SignalWorkflowExecutionResponse> {
+    ) -> Result<SignalWorkflowExecutionResponse, WorkflowCallError> {
         retry_call!(
             self,
             signal_workflow_execution,
@@ -472,7 +598,7 @@ where
         run_id: Option<String>,
         reason: String,
         request_id: Option<String>,
-    ) -> Result<RequestCancelWorkflowExecutionResponse> {
+    ) -> Result<RequestCancelWorkflowExecutionResponse, WorkflowCallError> {
         retry_call!(
             self,
             cancel_workflow_execution,
@@ -598,6 +724,87 @@ where
         )
     }
 
+    async fn create_schedule(
+        &self,
+        schedule_id: String,
+        schedule: ScheduleDefinition,
+        options: CreateScheduleOptions,
+    ) -> Result<CreateScheduleResponse, WorkflowCallError> {
+        retry_call!(
+            self,
+            create_schedule,
+            schedule_id.clone(),
+            schedule.clone(),
+            options.clone()
+        )
+    }
+
+    async fn describe_schedule(
+        &self,
+        schedule_id: String,
+    ) -> Result<DescribeScheduleResponse, WorkflowCallError> {
+        retry_call!(self, describe_schedule, schedule_id.clone())
+    }
+
+    async fn update_schedule(
+        &self,
+        schedule_id: String,
+        schedule: ScheduleDefinition,
+        conflict_token: Option<Vec<u8>>,
+    ) -> Result<UpdateScheduleResponse, WorkflowCallError> {
+        retry_call!(
+            self,
+            update_schedule,
+            schedule_id.clone(),
+            schedule.clone(),
+            conflict_token.clone()
+        )
+    }
+
+    async fn pause_schedule(
+        &self,
+        schedule_id: String,
+        note: Option<String>,
+    ) -> Result<PatchScheduleResponse, WorkflowCallError> {
+        retry_call!(self, pause_schedule, schedule_id.clone(), note.clone())
+    }
+
+    async fn unpause_schedule(
+        &self,
+        schedule_id: String,
+        note: Option<String>,
+    ) -> Result<PatchScheduleResponse, WorkflowCallError> {
+        retry_call!(self, unpause_schedule, schedule_id.clone(), note.clone())
+    }
+
+    async fn trigger_schedule(
+        &self,
+        schedule_id: String,
+        overlap_policy: ScheduleOverlapPolicy,
+    ) -> Result<PatchScheduleResponse, WorkflowCallError> {
+        retry_call!(self, trigger_schedule, schedule_id.clone(), overlap_policy)
+    }
+
+    async fn delete_schedule(
+        &self,
+        schedule_id: String,
+    ) -> Result<DeleteScheduleResponse, WorkflowCallError> {
+        retry_call!(self, delete_schedule, schedule_id.clone())
+    }
+
+    async fn describe_task_queue(
+        &self,
+        task_queue: String,
+        task_queue_type: TaskQueueType,
+    ) -> Result<TaskQueueDescription> {
+        retry_call!(
+            self,
+            describe_task_queue,
+            task_queue.clone(),
+            task_queue_type
+        )
+    }
+
     fn get_options(&self) -> &ClientOptions {
         self.client.get_options()
     }
@@ -626,6 +833,29 @@ mod tests {
         max_retries: 10,
     };
 
+    #[tokio::test]
+    async fn attempts_after_the_first_are_marked_as_retries() {
+        let seen = Arc::new(parking_lot::Mutex::new(vec![]));
+        let seen_c = seen.clone();
+        let info = RetryClient::new((), TEST_RETRY_CONFIG).get_call_info::<()>("whatever", None);
+        let res = RetryClient::<()>::make_future_retry(info, move || {
+            seen_c.lock().push(is_retry_attempt());
+            let attempt = seen_c.lock().len();
+            async move {
+                if attempt < 3 {
+                    Err(Status::unavailable("down"))
+                } else {
+                    Ok(())
+                }
+            }
+        })
+        .await;
+        assert!(res.is_ok());
+        assert_eq!(*seen.lock(), [false, true, true]);
+        // Outside of a retrying call, nothing is a retry
+        assert!(!is_retry_attempt());
+    }
+
     #[tokio::test]
     async fn non_retryable_errors() {
         for code in [
@@ -803,6 +1033,31 @@ mod tests {
         }
     }
 
+    #[test]
+    fn long_polls_use_their_own_retry_config() {
+        let poll_cfg = RetryConfig {
+            max_retries: 3,
+            ..RetryConfig::poll_retry_policy()
+        };
+        let fake_retry =
+            RetryClient::new((), TEST_RETRY_CONFIG).with_long_poll_retry_config(poll_cfg.clone());
+        for call in [POLL_WORKFLOW_METH_NAME, POLL_ACTIVITY_METH_NAME] {
+            let info = fake_retry.get_call_info::<()>(call, None);
+            assert_eq!(info.retry_cfg, poll_cfg);
+            // Timeouts are retried until the configured limit, after which they are returned
+            let mut err_handler =
+                TonicErrorHandler::new(info, RetryConfig::throttle_retry_policy());
+            for i in 1..3 {
+                let result = err_handler.handle(i, Status::new(Code::DeadlineExceeded, "timeout"));
+                assert_matches!(result, RetryPolicy::WaitRetry(_));
+            }
+            let result = err_handler.handle(3, Status::new(Code::DeadlineExceeded, "timeout"));
+            assert_matches!(result, RetryPolicy::ForwardError(_));
+        }
+        let info = fake_retry.get_call_info::<()>("get_workflow_execution_history", None);
+        assert_eq!(info.retry_cfg, TEST_RETRY_CONFIG);
+    }
+
     #[tokio::test]
     async fn long_poll_retries_deadline_exceeded() {
         let fake_retry = RetryClient::new((), TEST_RETRY_CONFIG);
@@ -820,4 +1075,275 @@ mod tests {
             }
         }
     }
+
+    #[tokio::test]
+    async fn typed_workflow_call_errors_are_not_retried() {
+        let mut mock_client = MockWorkflowClientTrait::new();
+        mock_client
+            .expect_signal_workflow_execution()
+            .returning(|wid, rid, _, _, _| {
+                Err(WorkflowCallError::WorkflowNotFound {
+                    workflow_id: wid,
+                    run_id: Some(rid),
+                })
+            })
+            .times(1);
+        mock_client
+            .expect_start_workflow()
+            .returning(|_, _, _, _, _, _| {
+                Err(WorkflowCallError::WorkflowAlreadyStarted {
+                    run_id: Some("existing".to_string()),
+                })
+            })
+            .times(1);
+        let retry_client = RetryClient::new(mock_client, TEST_RETRY_CONFIG);
+        let result = retry_client
+            .signal_workflow_execution(
+                "wid".to_string(),
+                "rid".to_string(),
+                "sig".to_string(),
+                None,
+                None,
+            )
+            .await;
+        assert_matches!(
+            result,
+            Err(WorkflowCallError::WorkflowNotFound { workflow_id, .. }) if workflow_id == "wid"
+        );
+        let result = retry_client
+            .start_workflow(
+                vec![],
+                "tq".to_string(),
+                "wid".to_string(),
+                "wtype".to_string(),
+                None,
+                Default::default(),
+            )
+            .await;
+        assert_matches!(
+            result,
+            Err(WorkflowCallError::WorkflowAlreadyStarted { .. })
+        );
+    }
+
+    #[tokio::test]
+    async fn schedule_calls_retry_until_typed_errors() {
+        let mut mock_client = MockWorkflowClientTrait::new();
+        mock_client
+            .expect_pause_schedule()
+            .withf(|sid, note| sid == "sid" && note.as_deref() == Some("maintenance"))
+            .returning(|_, _| Err(Status::new(Code::Unavailable, "retryable").into()))
+            .times(2);
+        mock_client
+            .expect_pause_schedule()
+            .returning(|_, _| Ok(Default::default()))
+            .times(1);
+        mock_client
+            .expect_trigger_schedule()
+            .withf(|sid, overlap| sid == "sid" && *overlap == ScheduleOverlapPolicy::AllowAll)
+            .returning(|sid, _| Err(WorkflowCallError::ScheduleNotFound { schedule_id: sid }))
+            .times(1);
+        let retry_client = RetryClient::new(mock_client, TEST_RETRY_CONFIG);
+        retry_client
+            .pause_schedule("sid".to_string(), Some("maintenance".to_string()))
+            .await
+            .unwrap();
+        let result = retry_client
+            .trigger_schedule("sid".to_string(), ScheduleOverlapPolicy::AllowAll)
+            .await;
+        assert_matches!(
+            result,
+            Err(WorkflowCallError::ScheduleNotFound { schedule_id }) if schedule_id == "sid"
+        );
+    }
+
+    #[tokio::test]
+    async fn cancellation_aborts_slow_calls() {
+        let mut mock_client = MockWorkflowClientTrait::new();
+        // The first attempt fails retryably, leaving the call to wait out a long backoff
+        mock_client
+            .expect_start_workflow()
+            .returning(|_, _, _, _, _, _| Err(Status::new(Code::Unavailable, "slow").into()))
+            .times(1);
+        let token = CancellationToken::new();
+        let retry_client = RetryClient::new(
+            mock_client,
+            RetryConfig {
+                initial_interval: Duration::from_secs(60),
+                max_interval: Duration::from_secs(60),
+                ..TEST_RETRY_CONFIG
+            },
+        )
+        .with_cancellation(token.clone());
+        let canceller = async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            token.cancel();
+        };
+        let start = Instant::now();
+        let (result, _) = tokio::join!(
+            retry_client.start_workflow(
+                vec![],
+                "tq".to_string(),
+                "wid".to_string(),
+                "wtype".to_string(),
+                None,
+                Default::default(),
+            ),
+            canceller
+        );
+        assert_matches!(result, Err(WorkflowCallError::Cancelled));
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn cancellation_does_not_apply_to_long_polls() {
+        let retry_client = RetryClient::new(MockWorkflowClientTrait::new(), TEST_RETRY_CONFIG)
+            .with_cancellation(CancellationToken::new());
+        for call in [POLL_WORKFLOW_METH_NAME, POLL_ACTIVITY_METH_NAME] {
+            let info = retry_client.get_call_info::<()>(call, None);
+            assert!(retry_client.cancel_token_for(&info).is_none());
+        }
+        let info = retry_client.get_call_info::<()>("start_workflow", None);
+        assert!(retry_client.cancel_token_for(&info).is_some());
+    }
+
+    #[test]
+    fn wrapping_keeps_retry_configs_and_cancellation() {
+        let long_poll_config = RetryConfig {
+            max_retries: 3,
+            ..TEST_RETRY_CONFIG
+        };
+        let token = CancellationToken::new();
+        let retry_client = RetryClient::new(MockWorkflowClientTrait::new(), TEST_RETRY_CONFIG)
+            .with_long_poll_retry_config(long_poll_config.clone())
+            .with_cancellation(token.clone());
+        let wrapped = retry_client.wrapping(MockWorkflowClientTrait::new());
+        assert_eq!(*wrapped.retry_config, TEST_RETRY_CONFIG);
+        assert_eq!(*wrapped.long_poll_retry_config, long_poll_config);
+        token.cancel();
+        assert!(wrapped.cancel.unwrap().is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn untyped_workflow_call_errors_are_retried() {
+        let mut mock_client = MockWorkflowClientTrait::new();
+        mock_client
+            .expect_cancel_workflow_execution()
+            .returning(|_, _, _, _| Err(Status::new(Code::Unavailable, "retryable").into()))
+            .times(3);
+        mock_client
+            .expect_cancel_workflow_execution()
+            .returning(|_, _, _, _| Ok(Default::default()))
+            .times(1);
+        let retry_client = RetryClient::new(mock_client, TEST_RETRY_CONFIG);
+        let result = retry_client
+            .cancel_workflow_execution("wid".to_string(), None, "reason".to_string(), None)
+            .await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn dropping_a_retrying_long_poll_drops_the_rpc_in_flight() {
+        use crate::{TemporalServiceClient, WorkflowService};
+        use std::{
+            convert::Infallible,
+            sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+        };
+        use tonic::body::BoxBody;
+
+        struct SetOnDrop(Arc<AtomicBool>);
+        impl Drop for SetOnDrop {
+            fn drop(&mut self) {
+                self.0.store(true, Ordering::SeqCst);
+            }
+        }
+
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let rpc_dropped = Arc::new(AtomicBool::new(false));
+        let retrying = Arc::new(tokio::sync::Notify::new());
+        // Stands in for the channel, beneath tonic's generated client
+        let svc = tower::service_fn({
+            let (attempts, rpc_dropped, retrying) =
+                (attempts.clone(), rpc_dropped.clone(), retrying.clone());
+            move |_: http::Request<BoxBody>| {
+                let attempt = attempts.fetch_add(1, Ordering::SeqCst) + 1;
+                let guard = (attempt > 1).then(|| SetOnDrop(rpc_dropped.clone()));
+                let retrying = retrying.clone();
+                async move {
+                    if attempt == 1 {
+                        return Ok::<_, Infallible>(Status::unavailable("down").into_http());
+                    }
+                    let _guard = guard;
+                    retrying.notify_one();
+                    // A long poll the server won't end for a long while
+                    future::pending().await
+                }
+            }
+        });
+        let mut client = RetryClient::new(TemporalServiceClient::new(svc), TEST_RETRY_CONFIG)
+            .with_long_poll_retry_config(TEST_RETRY_CONFIG);
+
+        tokio::select! {
+            _ = WorkflowService::poll_workflow_task_queue(
+                &mut client,
+                PollWorkflowTaskQueueRequest::default(),
+            ) => panic!("Poll should not resolve"),
+            _ = retrying.notified() => {}
+        }
+        // Dropping the call above must drop the retried attempt, rather than leave it running
+        assert!(rpc_dropped.load(Ordering::SeqCst));
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn long_poll_transient_errors_retried_until_a_task_arrives() {
+        use crate::{TemporalServiceClient, WorkflowService};
+        use prost::Message;
+        use std::{
+            convert::Infallible,
+            sync::atomic::{AtomicUsize, Ordering},
+        };
+        use tonic::body::BoxBody;
+
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let svc = tower::service_fn({
+            let attempts = attempts.clone();
+            move |_: http::Request<BoxBody>| {
+                let attempt = attempts.fetch_add(1, Ordering::SeqCst) + 1;
+                async move {
+                    if attempt <= 2 {
+                        return Ok::<_, Infallible>(Status::unavailable("down").into_http());
+                    }
+                    let task = PollWorkflowTaskQueueResponse {
+                        task_token: vec![1],
+                        ..Default::default()
+                    }
+                    .encode_to_vec();
+                    // An uncompressed gRPC message: flag byte, length, then the message itself
+                    let mut frame = vec![0];
+                    frame.extend((task.len() as u32).to_be_bytes());
+                    frame.extend(task);
+                    let body = http_body_util::Full::new(prost::bytes::Bytes::from(frame));
+                    Ok(http::Response::builder()
+                        .header("content-type", "application/grpc")
+                        .body(tonic::body::boxed(body))
+                        .unwrap())
+                }
+            }
+        });
+        let mut client = RetryClient::new(TemporalServiceClient::new(svc), TEST_RETRY_CONFIG)
+            .with_long_poll_retry_config(TEST_RETRY_CONFIG);
+
+        // Workers' poll buffers surface poll errors as they get them, so these must never reach
+        // them while the long poll retry config allows another attempt
+        let task = WorkflowService::poll_workflow_task_queue(
+            &mut client,
+            PollWorkflowTaskQueueRequest::default(),
+        )
+        .await
+        .unwrap()
+        .into_inner();
+        assert_eq!(task.task_token, vec![1]);
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
 }
@@ -0,0 +1,396 @@
+//! Options for schedules, which start workflows at times described by calendars or intervals and
+//! supersede cron strings (see [WorkflowOptions::cron_schedule]).
+
+use crate::WorkflowOptions;
+use std::{
+    collections::HashMap,
+    time::{Duration, SystemTime},
+};
+use temporal_sdk_core_protos::{
+    coresdk::IntoPayloadsExt,
+    temporal::api::{
+        common::v1::{Payload, WorkflowType},
+        enums::v1::{ScheduleOverlapPolicy, TaskQueueKind},
+        schedule::v1::{self as proto, schedule_action, SchedulePatch, TriggerImmediatelyRequest},
+        taskqueue::v1::TaskQueue,
+        workflow::v1::NewWorkflowExecutionInfo,
+    },
+};
+
+/// Everything that makes up a schedule: when it acts, what it does, and how it handles actions
+/// which overlap or were missed
+#[derive(Debug, Clone)]
+pub struct ScheduleDefinition {
+    /// When the schedule takes its action
+    pub spec: ScheduleSpec,
+    /// What the schedule does each time it acts
+    pub action: ScheduleAction,
+    /// How the schedule handles overlapping or missed actions
+    pub policies: SchedulePolicies,
+    /// Whether the schedule is paused
+    pub paused: bool,
+    /// A note describing the schedule's state, ex: why it was paused
+    pub note: String,
+}
+
+/// The times at which a schedule takes its action: every time matching any of the calendars or
+/// intervals, within the optional start and end times
+#[derive(Debug, Clone, Default)]
+pub struct ScheduleSpec {
+    /// Calendar-based times to act at
+    pub calendars: Vec<ScheduleCalendarSpec>,
+    /// Fixed intervals to act at
+    pub intervals: Vec<ScheduleIntervalSpec>,
+    /// Times matching any of these calendars are skipped, even if otherwise matched
+    pub skip: Vec<ScheduleCalendarSpec>,
+    /// No actions are taken before this time
+    pub start_time: Option<SystemTime>,
+    /// No actions are taken after this time
+    pub end_time: Option<SystemTime>,
+    /// Each action is delayed by a random amount up to this much
+    pub jitter: Option<Duration>,
+    /// IANA name of the time zone calendars are interpreted in. UTC if unset.
+    pub time_zone_name: Option<String>,
+}
+
+/// Matches every time whose fields fall in the given ranges. Empty fields take the server's
+/// defaults: zero for seconds, minutes and hours, and all values for the rest (ex: an empty
+/// calendar matches midnight every day).
+#[derive(Debug, Clone, Default)]
+pub struct ScheduleCalendarSpec {
+    /// Seconds, 0 to 59
+    pub second: Vec<ScheduleRange>,
+    /// Minutes, 0 to 59
+    pub minute: Vec<ScheduleRange>,
+    /// Hours, 0 to 23
+    pub hour: Vec<ScheduleRange>,
+    /// Days of the month, 1 to 31
+    pub day_of_month: Vec<ScheduleRange>,
+    /// Months, 1 to 12
+    pub month: Vec<ScheduleRange>,
+    /// Years
+    pub year: Vec<ScheduleRange>,
+    /// Days of the week, 0 (Sunday) to 6
+    pub day_of_week: Vec<ScheduleRange>,
+    /// Free-form description of the calendar
+    pub comment: String,
+}
+
+/// An inclusive range of values, `start`, `start + step`, ... up to `end`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScheduleRange {
+    /// First value in the range
+    pub start: i32,
+    /// Last value in the range
+    pub end: i32,
+    /// Distance between values in the range
+    pub step: i32,
+}
+
+impl ScheduleRange {
+    /// A range matching only `value`
+    pub fn single(value: i32) -> Self {
+        Self::new(value, value)
+    }
+
+    /// A range matching every value from `start` to `end`, inclusive
+    pub fn new(start: i32, end: i32) -> Self {
+        Self {
+            start,
+            end,
+            step: 1,
+        }
+    }
+}
+
+/// Matches every multiple of `every` since the epoch, shifted forward by `offset`
+#[derive(Debug, Clone, Copy)]
+pub struct ScheduleIntervalSpec {
+    /// Time between actions
+    pub every: Duration,
+    /// Shift applied to every matched time, which must be less than `every`
+    pub offset: Option<Duration>,
+}
+
+/// What a schedule does each time it acts
+#[derive(Debug, Clone)]
+pub enum ScheduleAction {
+    /// Start a workflow. The workflow id has the scheduled time appended to it, so that each
+    /// action starts a distinct workflow.
+    ///
+    /// Of the [WorkflowOptions], the id reuse policy, timeouts, search attributes and retry
+    /// policy apply. Everything else is ignored.
+    StartWorkflow {
+        /// Prefix of the id of every workflow the schedule starts
+        workflow_id: String,
+        /// Type of the workflow to start
+        workflow_type: String,
+        /// Task queue to start the workflow on
+        task_queue: String,
+        /// Input to the workflow. These are sent to server exactly as given, so, like the input to
+        /// [crate::WorkflowClientTrait::start_workflow], they must already have been encoded.
+        input: Vec<Payload>,
+        /// Options for the workflow
+        options: WorkflowOptions,
+    },
+}
+
+/// How a schedule handles actions which overlap or were missed
+#[derive(Debug, Clone, Default)]
+pub struct SchedulePolicies {
+    /// What to do when an action is due while the workflow started by a previous one is still
+    /// running. Server skips the action if left unspecified.
+    pub overlap: ScheduleOverlapPolicy,
+    /// How long after its scheduled time an action missed during an outage may still be taken.
+    /// Server uses one year if unset.
+    pub catchup_window: Option<Duration>,
+    /// Pause the schedule if a workflow it started fails or times out
+    pub pause_on_failure: bool,
+}
+
+/// Options for [crate::WorkflowClientTrait::create_schedule]
+#[derive(Debug, Clone, Default)]
+pub struct CreateScheduleOptions {
+    /// Take the schedule's action as soon as it is created, as well as at its scheduled times
+    pub trigger_immediately: bool,
+    /// Search attributes of the schedule itself (not of the workflows it starts)
+    pub search_attributes: Option<HashMap<String, Payload>>,
+    /// Request id for idempotency/deduplication
+    pub request_id: Option<String>,
+}
+
+impl From<ScheduleDefinition> for proto::Schedule {
+    fn from(val: ScheduleDefinition) -> Self {
+        proto::Schedule {
+            spec: Some(val.spec.into()),
+            action: Some(val.action.into()),
+            policies: Some(proto::SchedulePolicies {
+                overlap_policy: val.policies.overlap as i32,
+                catchup_window: val.policies.catchup_window.and_then(|d| d.try_into().ok()),
+                pause_on_failure: val.policies.pause_on_failure,
+                keep_original_workflow_id: false,
+            }),
+            state: Some(proto::ScheduleState {
+                notes: val.note,
+                paused: val.paused,
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+impl From<ScheduleSpec> for proto::ScheduleSpec {
+    fn from(val: ScheduleSpec) -> Self {
+        proto::ScheduleSpec {
+            structured_calendar: val.calendars.into_iter().map(Into::into).collect(),
+            interval: val
+                .intervals
+                .into_iter()
+                .map(|i| proto::IntervalSpec {
+                    interval: i.every.try_into().ok(),
+                    phase: i.offset.and_then(|d| d.try_into().ok()),
+                })
+                .collect(),
+            exclude_structured_calendar: val.skip.into_iter().map(Into::into).collect(),
+            start_time: val.start_time.map(Into::into),
+            end_time: val.end_time.map(Into::into),
+            jitter: val.jitter.and_then(|d| d.try_into().ok()),
+            timezone_name: val.time_zone_name.unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<ScheduleCalendarSpec> for proto::StructuredCalendarSpec {
+    fn from(val: ScheduleCalendarSpec) -> Self {
+        let ranges = |rs: Vec<ScheduleRange>| {
+            rs.into_iter()
+                .map(|r| proto::Range {
+                    start: r.start,
+                    end: r.end,
+                    step: r.step,
+                })
+                .collect()
+        };
+        proto::StructuredCalendarSpec {
+            second: ranges(val.second),
+            minute: ranges(val.minute),
+            hour: ranges(val.hour),
+            day_of_month: ranges(val.day_of_month),
+            month: ranges(val.month),
+            year: ranges(val.year),
+            day_of_week: ranges(val.day_of_week),
+            comment: val.comment,
+        }
+    }
+}
+
+impl From<ScheduleAction> for proto::ScheduleAction {
+    fn from(val: ScheduleAction) -> Self {
+        let action = match val {
+            ScheduleAction::StartWorkflow {
+                workflow_id,
+                workflow_type,
+                task_queue,
+                input,
+                options,
+            } => schedule_action::Action::StartWorkflow(NewWorkflowExecutionInfo {
+                workflow_id,
+                workflow_type: Some(WorkflowType {
+                    name: workflow_type,
+                }),
+                task_queue: Some(TaskQueue {
+                    name: task_queue,
+                    kind: TaskQueueKind::Normal as i32,
+                    normal_name: "".to_string(),
+                }),
+                input: input.into_payloads(),
+                workflow_execution_timeout: options
+                    .execution_timeout
+                    .and_then(|d| d.try_into().ok()),
+                workflow_run_timeout: options.run_timeout.and_then(|d| d.try_into().ok()),
+                workflow_task_timeout: options.task_timeout.and_then(|d| d.try_into().ok()),
+                workflow_id_reuse_policy: options.id_reuse_policy as i32,
+                retry_policy: options.retry_policy,
+                search_attributes: options.search_attributes.map(|d| d.into()),
+                ..Default::default()
+            }),
+        };
+        proto::ScheduleAction {
+            action: Some(action),
+        }
+    }
+}
+
+pub(crate) fn pause_patch(note: Option<String>) -> SchedulePatch {
+    SchedulePatch {
+        pause: note.unwrap_or_else(|| "Paused via client".to_string()),
+        ..Default::default()
+    }
+}
+
+pub(crate) fn unpause_patch(note: Option<String>) -> SchedulePatch {
+    SchedulePatch {
+        unpause: note.unwrap_or_else(|| "Unpaused via client".to_string()),
+        ..Default::default()
+    }
+}
+
+pub(crate) fn trigger_patch(overlap: ScheduleOverlapPolicy) -> SchedulePatch {
+    SchedulePatch {
+        trigger_immediately: Some(TriggerImmediatelyRequest {
+            overlap_policy: overlap as i32,
+        }),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use temporal_sdk_core_protos::coresdk::AsJsonPayloadExt;
+
+    #[test]
+    fn calendar_schedule_with_overlap_policy() {
+        let schedule: proto::Schedule = ScheduleDefinition {
+            spec: ScheduleSpec {
+                calendars: vec![ScheduleCalendarSpec {
+                    hour: vec![ScheduleRange::single(9)],
+                    day_of_week: vec![ScheduleRange::new(1, 5)],
+                    comment: "weekday mornings".to_string(),
+                    ..Default::default()
+                }],
+                time_zone_name: Some("Europe/Paris".to_string()),
+                ..Default::default()
+            },
+            action: ScheduleAction::StartWorkflow {
+                workflow_id: "report".to_string(),
+                workflow_type: "daily_report".to_string(),
+                task_queue: "reports".to_string(),
+                input: vec!["in".as_json_payload().unwrap()],
+                options: WorkflowOptions {
+                    run_timeout: Some(Duration::from_secs(60)),
+                    ..Default::default()
+                },
+            },
+            policies: SchedulePolicies {
+                overlap: ScheduleOverlapPolicy::BufferOne,
+                catchup_window: Some(Duration::from_secs(600)),
+                pause_on_failure: true,
+            },
+            paused: false,
+            note: "".to_string(),
+        }
+        .into();
+
+        let spec = schedule.spec.unwrap();
+        assert_eq!(
+            spec.structured_calendar,
+            vec![proto::StructuredCalendarSpec {
+                hour: vec![proto::Range {
+                    start: 9,
+                    end: 9,
+                    step: 1
+                }],
+                day_of_week: vec![proto::Range {
+                    start: 1,
+                    end: 5,
+                    step: 1
+                }],
+                comment: "weekday mornings".to_string(),
+                ..Default::default()
+            }]
+        );
+        assert!(spec.cron_string.is_empty());
+        assert_eq!(spec.timezone_name, "Europe/Paris");
+        assert_eq!(
+            schedule.policies,
+            Some(proto::SchedulePolicies {
+                overlap_policy: ScheduleOverlapPolicy::BufferOne as i32,
+                catchup_window: Some(Duration::from_secs(600).try_into().unwrap()),
+                pause_on_failure: true,
+                keep_original_workflow_id: false,
+            })
+        );
+        let Some(schedule_action::Action::StartWorkflow(start)) = schedule.action.unwrap().action
+        else {
+            panic!("expected a start workflow action");
+        };
+        assert_eq!(start.workflow_id, "report");
+        assert_eq!(start.workflow_type.unwrap().name, "daily_report");
+        assert_eq!(start.task_queue.unwrap().name, "reports");
+        assert_eq!(
+            start.input.unwrap().payloads,
+            vec!["in".as_json_payload().unwrap()]
+        );
+        assert_eq!(
+            start.workflow_run_timeout,
+            Some(Duration::from_secs(60).try_into().unwrap())
+        );
+        assert!(!schedule.state.unwrap().paused);
+    }
+
+    #[test]
+    fn pause_and_trigger_patches() {
+        assert_eq!(
+            pause_patch(Some("maintenance".to_string())),
+            SchedulePatch {
+                pause: "maintenance".to_string(),
+                ..Default::default()
+            }
+        );
+        // Server ignores empty notes, so there must always be one
+        assert!(!pause_patch(None).pause.is_empty());
+        assert!(!unpause_patch(None).unpause.is_empty());
+        assert!(unpause_patch(None).pause.is_empty());
+        assert_eq!(
+            trigger_patch(ScheduleOverlapPolicy::AllowAll),
+            SchedulePatch {
+                trigger_immediately: Some(TriggerImmediatelyRequest {
+                    overlap_policy: ScheduleOverlapPolicy::AllowAll as i32,
+                }),
+                ..Default::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,193 @@
+//! What server knows about a task queue: who is polling it and, on servers supporting the enhanced
+//! describe mode, how backlogged it is. See [crate::WorkflowClientTrait::describe_task_queue].
+
+use std::time::{Duration, SystemTime};
+use temporal_sdk_core_protos::{
+    temporal::api::{
+        enums::v1::TaskQueueType,
+        taskqueue::v1::{PollerInfo, TaskQueueStats as ProtoStats},
+        workflowservice::v1::DescribeTaskQueueResponse,
+    },
+    utilities::TryIntoOrNone,
+};
+
+/// The pollers of one type of a task queue as seen by server, and its backlog if server reports it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TaskQueueDescription {
+    /// Workers which recently polled the queue
+    pub pollers: Vec<TaskQueuePoller>,
+    /// Backlog statistics. Only reported by servers supporting the enhanced describe mode.
+    pub stats: Option<TaskQueueStats>,
+}
+
+/// A worker which recently polled a task queue
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TaskQueuePoller {
+    /// The identity the worker polls with
+    pub identity: String,
+    /// When the worker last polled
+    pub last_access_time: Option<SystemTime>,
+    /// The rate limit the worker asked server to apply to the whole queue, if it set one
+    pub rate_per_second: Option<f64>,
+}
+
+/// How backlogged a task queue is. Counts and rates are approximate, and exclude sticky queues.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TaskQueueStats {
+    /// Number of tasks waiting to be dispatched
+    pub approximate_backlog_count: i64,
+    /// Age of the oldest task waiting to be dispatched
+    pub approximate_backlog_age: Option<Duration>,
+    /// Tasks added per second, over the last 30 seconds
+    pub tasks_add_rate: f32,
+    /// Tasks dispatched per second, over the last 30 seconds
+    pub tasks_dispatch_rate: f32,
+}
+
+impl TaskQueueDescription {
+    /// Interpret a response to a describe call made in enhanced mode. Servers which don't support
+    /// that mode answer as they would a legacy call, with pollers but no stats.
+    pub(crate) fn from_response(
+        resp: DescribeTaskQueueResponse,
+        task_queue_type: TaskQueueType,
+    ) -> Self {
+        // Results are for the default build id, so there is at most one entry
+        let Some(version) = resp.versions_info.into_values().next() else {
+            return Self {
+                pollers: resp.pollers.into_iter().map(Into::into).collect(),
+                stats: None,
+            };
+        };
+        let type_info = version
+            .types_info
+            .into_iter()
+            .find_map(|(t, info)| (t == task_queue_type as i32).then_some(info))
+            .unwrap_or_default();
+        Self {
+            pollers: type_info.pollers.into_iter().map(Into::into).collect(),
+            stats: Some(type_info.stats.map(Into::into).unwrap_or_default()),
+        }
+    }
+}
+
+impl From<PollerInfo> for TaskQueuePoller {
+    fn from(p: PollerInfo) -> Self {
+        Self {
+            identity: p.identity,
+            last_access_time: p.last_access_time.try_into_or_none(),
+            rate_per_second: (p.rate_per_second > 0.0).then_some(p.rate_per_second),
+        }
+    }
+}
+
+impl From<ProtoStats> for TaskQueueStats {
+    fn from(s: ProtoStats) -> Self {
+        Self {
+            approximate_backlog_count: s.approximate_backlog_count,
+            approximate_backlog_age: s.approximate_backlog_age.try_into_or_none(),
+            tasks_add_rate: s.tasks_add_rate,
+            tasks_dispatch_rate: s.tasks_dispatch_rate,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use temporal_sdk_core_protos::temporal::api::taskqueue::v1::{
+        TaskQueueTypeInfo, TaskQueueVersionInfo,
+    };
+
+    fn poller(identity: &str, rate_per_second: f64) -> PollerInfo {
+        PollerInfo {
+            identity: identity.to_string(),
+            last_access_time: Some(SystemTime::UNIX_EPOCH.into()),
+            rate_per_second,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn legacy_response_has_pollers_but_no_stats() {
+        let desc = TaskQueueDescription::from_response(
+            DescribeTaskQueueResponse {
+                pollers: vec![poller("worker-1", 0.0), poller("worker-2", 10.0)],
+                ..Default::default()
+            },
+            TaskQueueType::Activity,
+        );
+        assert_eq!(
+            desc,
+            TaskQueueDescription {
+                pollers: vec![
+                    TaskQueuePoller {
+                        identity: "worker-1".to_string(),
+                        last_access_time: Some(SystemTime::UNIX_EPOCH),
+                        rate_per_second: None,
+                    },
+                    TaskQueuePoller {
+                        identity: "worker-2".to_string(),
+                        last_access_time: Some(SystemTime::UNIX_EPOCH),
+                        rate_per_second: Some(10.0),
+                    },
+                ],
+                stats: None,
+            }
+        );
+    }
+
+    #[test]
+    fn enhanced_response_is_read_for_the_requested_type() {
+        let type_info = |identity: &str, backlog: i64| TaskQueueTypeInfo {
+            pollers: vec![poller(identity, 0.0)],
+            stats: Some(ProtoStats {
+                approximate_backlog_count: backlog,
+                approximate_backlog_age: Some(Duration::from_secs(30).try_into().unwrap()),
+                tasks_add_rate: 2.0,
+                tasks_dispatch_rate: 1.5,
+            }),
+        };
+        let resp = DescribeTaskQueueResponse {
+            versions_info: HashMap::from([(
+                "".to_string(),
+                TaskQueueVersionInfo {
+                    types_info: HashMap::from([
+                        (TaskQueueType::Workflow as i32, type_info("wf-worker", 1)),
+                        (TaskQueueType::Activity as i32, type_info("act-worker", 7)),
+                    ]),
+                    ..Default::default()
+                },
+            )]),
+            ..Default::default()
+        };
+        let desc = TaskQueueDescription::from_response(resp, TaskQueueType::Activity);
+        assert_eq!(desc.pollers.len(), 1);
+        assert_eq!(desc.pollers[0].identity, "act-worker");
+        assert_eq!(
+            desc.stats,
+            Some(TaskQueueStats {
+                approximate_backlog_count: 7,
+                approximate_backlog_age: Some(Duration::from_secs(30)),
+                tasks_add_rate: 2.0,
+                tasks_dispatch_rate: 1.5,
+            })
+        );
+
+        // A type nobody has used yet has no pollers and an empty backlog
+        let desc = TaskQueueDescription::from_response(
+            DescribeTaskQueueResponse {
+                versions_info: HashMap::from([("".to_string(), Default::default())]),
+                ..Default::default()
+            },
+            TaskQueueType::Nexus,
+        );
+        assert_eq!(
+            desc,
+            TaskQueueDescription {
+                pollers: vec![],
+                stats: Some(Default::default()),
+            }
+        );
+    }
+}
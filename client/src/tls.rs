@@ -0,0 +1,161 @@
+//! Checking TLS options before any channel is built from them. Tonic only parses certificates and
+//! keys once it starts connecting, so without this a typo in a path handed to lang, or a key pasted
+//! where a certificate belongs, would only show up as an obscure handshake failure.
+
+use crate::{ClientInitError, TlsConfig};
+use base64::prelude::*;
+
+impl TlsConfig {
+    /// Check that every certificate and key set is well formed PEM of the right kind, and that the
+    /// domain override, if set, isn't empty. Called whenever a client is built with these options.
+    pub fn validate(&self) -> Result<(), ClientInitError> {
+        let invalid = ClientInitError::InvalidTlsConfig;
+        if let Some(root) = &self.server_root_ca_cert {
+            expect_certificates(root).map_err(|e| invalid(format!("server root CA cert: {e}")))?;
+        }
+        if self.domain.as_ref().is_some_and(|d| d.trim().is_empty()) {
+            return Err(invalid("domain override is empty".to_string()));
+        }
+        if let Some(client) = &self.client_tls_config {
+            expect_certificates(&client.client_cert)
+                .map_err(|e| invalid(format!("client cert: {e}")))?;
+            let labels = pem_labels(&client.client_private_key)
+                .map_err(|e| invalid(format!("client private key: {e}")))?;
+            if labels.len() != 1 || !labels[0].ends_with("PRIVATE KEY") {
+                return Err(invalid(format!(
+                    "client private key: expected a single private key, found {labels:?}"
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn expect_certificates(pem: &[u8]) -> Result<(), String> {
+    let labels = pem_labels(pem)?;
+    match labels.iter().find(|l| *l != "CERTIFICATE") {
+        Some(other) => Err(format!("expected only certificates, found {other:?}")),
+        None => Ok(()),
+    }
+}
+
+/// Returns the label (ex: `CERTIFICATE`) of every block in some PEM data, failing if there are
+/// none, if any block is unterminated, or if any block's contents aren't base64
+fn pem_labels(pem: &[u8]) -> Result<Vec<String>, String> {
+    let text = std::str::from_utf8(pem).map_err(|_| "not valid UTF-8 PEM".to_string())?;
+    let mut labels = vec![];
+    let mut lines = text.lines().map(str::trim);
+    while let Some(line) = lines.next() {
+        let Some(label) = line
+            .strip_prefix("-----BEGIN ")
+            .and_then(|l| l.strip_suffix("-----"))
+        else {
+            continue;
+        };
+        let end = format!("-----END {label}-----");
+        let mut body = String::new();
+        loop {
+            match lines.next() {
+                Some(l) if l == end => break,
+                // Legacy encrypted keys carry headers (ex: `Proc-Type: 4,ENCRYPTED`) before their
+                // contents
+                Some(l) if l.contains(':') => {}
+                Some(l) => body.push_str(l),
+                None => return Err(format!("{label} block is missing its END line")),
+            }
+        }
+        if body.is_empty() || BASE64_STANDARD.decode(&body).is_err() {
+            return Err(format!("{label} block does not contain valid base64"));
+        }
+        labels.push(label.to_string());
+    }
+    if labels.is_empty() {
+        return Err("no PEM data found".to_string());
+    }
+    Ok(labels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ClientTlsConfig;
+    use assert_matches::assert_matches;
+
+    fn pem(label: &str) -> Vec<u8> {
+        format!("-----BEGIN {label}-----\nAAECAwQF\nBgc=\n-----END {label}-----\n").into_bytes()
+    }
+
+    fn mtls(cert: Vec<u8>, key: Vec<u8>) -> TlsConfig {
+        TlsConfig {
+            client_tls_config: Some(ClientTlsConfig {
+                client_cert: cert,
+                client_private_key: key,
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn well_formed_configs_are_accepted() {
+        // Server-auth-only TLS needs nothing at all
+        TlsConfig::default().validate().unwrap();
+        let mut chain = pem("CERTIFICATE");
+        chain.extend(pem("CERTIFICATE"));
+        TlsConfig {
+            server_root_ca_cert: Some(chain.clone()),
+            domain: Some("my-ns.tmprl.cloud".to_string()),
+            client_tls_config: None,
+        }
+        .validate()
+        .unwrap();
+        mtls(chain, pem("PRIVATE KEY")).validate().unwrap();
+        mtls(pem("CERTIFICATE"), pem("EC PRIVATE KEY"))
+            .validate()
+            .unwrap();
+    }
+
+    #[test]
+    fn malformed_configs_are_rejected() {
+        let bad_base64 = b"-----BEGIN CERTIFICATE-----\n!!!\n-----END CERTIFICATE-----";
+        for (cfg, expected) in [
+            (
+                mtls(
+                    b"-----BEGIN CERTIFICATE-----\nAAEC\n".to_vec(),
+                    pem("PRIVATE KEY"),
+                ),
+                "client cert: CERTIFICATE block is missing its END line",
+            ),
+            (
+                mtls(b"/path/to/cert.pem".to_vec(), pem("PRIVATE KEY")),
+                "client cert: no PEM data found",
+            ),
+            (
+                mtls(pem("PRIVATE KEY"), pem("PRIVATE KEY")),
+                "client cert: expected only certificates, found \"PRIVATE KEY\"",
+            ),
+            (
+                mtls(pem("CERTIFICATE"), pem("CERTIFICATE")),
+                "client private key: expected a single private key, found [\"CERTIFICATE\"]",
+            ),
+            (
+                TlsConfig {
+                    server_root_ca_cert: Some(bad_base64.to_vec()),
+                    ..Default::default()
+                },
+                "server root CA cert: CERTIFICATE block does not contain valid base64",
+            ),
+            (
+                TlsConfig {
+                    domain: Some("".to_string()),
+                    ..Default::default()
+                },
+                "domain override is empty",
+            ),
+        ] {
+            assert_matches!(
+                cfg.validate(),
+                Err(ClientInitError::InvalidTlsConfig(msg)) if msg == expected
+            );
+        }
+    }
+}
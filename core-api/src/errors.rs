@@ -1,6 +1,10 @@
 //! Error types exposed by public APIs
 
-use temporal_sdk_core_protos::coresdk::activity_result::ActivityExecutionResult;
+use std::{convert::Infallible, time::Duration};
+use temporal_sdk_core_protos::{
+    coresdk::activity_result::ActivityExecutionResult,
+    temporal::api::enums::v1::WorkflowTaskFailedCause, TaskToken,
+};
 
 /// Errors thrown by [crate::Worker::validate]
 #[derive(thiserror::Error, Debug)]
@@ -11,6 +15,14 @@ pub enum WorkerValidationError {
         source: tonic::Status,
         namespace: String,
     },
+    /// The namespace lists this worker's build id (its binary checksum) as a bad binary, so server
+    /// would reset any workflow progress the worker made.
+    #[error("Build id {build_id} is marked as a bad binary in namespace {namespace}: {reason}")]
+    BadBinary {
+        build_id: String,
+        namespace: String,
+        reason: String,
+    },
 }
 
 /// Errors thrown by [crate::Worker::poll_workflow_activation]
@@ -21,10 +33,51 @@ pub enum PollWfError {
     /// may exit.
     #[error("Core is shut down and there are no more workflow replay tasks")]
     ShutDown,
-    /// Unhandled error when calling the temporal server. Core will attempt to retry any non-fatal
-    /// errors, so lang should consider this fatal.
+    /// The worker was configured not to process workflows, see
+    /// [crate::worker::WorkerConfig::activity_only]
+    #[error("Worker is configured to process only activities")]
+    WorkerConfiguredWithoutThisTaskType,
+    /// Every workflow task slot was in use when the poll had waited for
+    /// [crate::worker::WorkerConfig::poll_saturation_timeout]. Lang may poll again once it has
+    /// completed some of its outstanding work.
+    #[error("Worker is saturated: {outstanding} of {limit} workflow task slots are in use")]
+    WorkerSaturated {
+        /// Number of workflow tasks outstanding
+        outstanding: usize,
+        /// The number of workflow task slots
+        limit: usize,
+    },
+    /// Server returned a poll response which core couldn't interpret. The task it carried (if
+    /// any) is dropped and will time out on server. Lang may poll again.
+    #[error("Server returned a workflow poll response core couldn't interpret: {reason}")]
+    MalformedResponse {
+        /// What was wrong with the response
+        reason: String,
+    },
+    /// Polling failed with an error which is usually transient (ex: server is unavailable), and
+    /// core's own retries of it ran out. The worker is not shut down, so lang may poll again,
+    /// ideally after backing off.
+    #[error("Retryable grpc error when workflow polling: {0:?}")]
+    RetryableTonicError(tonic::Status),
+    /// Server kept rejecting the worker's credentials, so polling has stopped. Every poll returns
+    /// this until polling is resumed, which lang may do once it has refreshed the credentials. The
+    /// worker is not shut down. See [crate::worker::PollAuthFailureEvent].
+    #[error("Workflow polling stopped after server kept rejecting credentials: {0:?}")]
+    AuthFailure(tonic::Status),
+    /// Unhandled error when calling the temporal server, which retrying would not fix. The worker
+    /// begins shutting down when this is returned, so lang should consider it fatal.
     #[error("Unhandled grpc error when workflow polling: {0:?}")]
-    TonicError(#[from] tonic::Status),
+    TonicError(tonic::Status),
+}
+
+impl From<tonic::Status> for PollWfError {
+    fn from(status: tonic::Status) -> Self {
+        if is_retryable_poll_status(&status) {
+            Self::RetryableTonicError(status)
+        } else {
+            Self::TonicError(status)
+        }
+    }
 }
 
 /// Errors thrown by [crate::Worker::poll_activity_task]
@@ -34,10 +87,55 @@ pub enum PollActivityError {
     /// ensure it is finished with any workflow replay, see [PollWfError::ShutDown]
     #[error("Core is shut down")]
     ShutDown,
-    /// Unhandled error when calling the temporal server. Core will attempt to retry any non-fatal
-    /// errors, so lang should consider this fatal.
+    /// Every activity task slot was in use when the poll had waited for
+    /// [crate::worker::WorkerConfig::poll_saturation_timeout]. Lang may poll again once it has
+    /// completed some of its outstanding activities.
+    #[error("Worker is saturated: {outstanding} of {limit} activity task slots are in use")]
+    WorkerSaturated {
+        /// Number of activity tasks outstanding
+        outstanding: usize,
+        /// The number of activity task slots
+        limit: usize,
+    },
+    /// Server returned a poll response which core couldn't interpret. The task it carried (if
+    /// any) is dropped and will time out on server. Lang may poll again.
+    #[error("Server returned an activity poll response core couldn't interpret: {reason}")]
+    MalformedResponse {
+        /// What was wrong with the response
+        reason: String,
+    },
+    /// Polling failed with an error which is usually transient (ex: server is unavailable), and
+    /// core's own retries of it ran out. The worker is not shut down, so lang may poll again,
+    /// ideally after backing off.
+    #[error("Retryable grpc error when activity polling: {0:?}")]
+    RetryableTonicError(tonic::Status),
+    /// Server kept rejecting the worker's credentials, so polling has stopped. See
+    /// [PollWfError::AuthFailure].
+    #[error("Activity polling stopped after server kept rejecting credentials: {0:?}")]
+    AuthFailure(tonic::Status),
+    /// Unhandled error when calling the temporal server, which retrying would not fix. The worker
+    /// begins shutting down when this is returned, so lang should consider it fatal.
     #[error("Unhandled grpc error when activity polling: {0:?}")]
-    TonicError(#[from] tonic::Status),
+    TonicError(tonic::Status),
+}
+
+impl From<tonic::Status> for PollActivityError {
+    fn from(status: tonic::Status) -> Self {
+        if is_retryable_poll_status(&status) {
+            Self::RetryableTonicError(status)
+        } else {
+            Self::TonicError(status)
+        }
+    }
+}
+
+/// Whether a poll which failed with `status` is worth retrying, because server is (hopefully
+/// temporarily) unable to serve polls rather than something being wrong with the poll itself
+pub fn is_retryable_poll_status(status: &tonic::Status) -> bool {
+    matches!(
+        status.code(),
+        tonic::Code::Unavailable | tonic::Code::ResourceExhausted | tonic::Code::DeadlineExceeded
+    )
 }
 
 /// Errors thrown by [crate::Worker::complete_workflow_activation]
@@ -51,6 +149,71 @@ pub enum CompleteWfError {
         reason: String,
         /// The run associated with the completion
         run_id: String,
+        /// Correlation id of the activation being completed, if known
+        correlation_id: String,
+    },
+    /// Lang SDK sent a completion which doesn't complete any activation core is waiting on. EX:
+    /// it is for a run which isn't cached, echoes the correlation id of some other activation, or
+    /// differs from the completion already applied for the same activation.
+    #[error("Lang SDK sent a completion for run ({run_id}) which core can't accept: {reason}")]
+    InvalidCompletion {
+        /// Why the completion can't be accepted
+        reason: String,
+        /// The run associated with the completion
+        run_id: String,
+        /// Correlation id of the activation being completed, if known
+        correlation_id: String,
+    },
+    /// Lang SDK sent a command which is missing a field server requires. Only produced for
+    /// search attribute and memo upserts unless
+    /// [crate::worker::WorkerConfig::strict_command_validation] is enabled.
+    #[error("Lang SDK sent an invalid {command} command for run ({run_id}): `{field}` {reason}")]
+    InvalidCommand {
+        /// The kind of command which was invalid
+        command: &'static str,
+        /// The field of the command which was invalid
+        field: &'static str,
+        /// Why the field is invalid
+        reason: &'static str,
+        /// The run associated with the completion
+        run_id: String,
+        /// Correlation id of the activation being completed, if lang echoed it
+        correlation_id: String,
+    },
+    /// Lang SDK sent a payload larger than this worker allows, see
+    /// [crate::worker::WorkerConfig::outbound_payload_limit]
+    #[error(
+        "Lang SDK sent a {size} byte payload in a {command} command for run ({run_id}), which \
+         exceeds the worker's limit of {limit} bytes"
+    )]
+    PayloadTooLarge {
+        /// The kind of command which carried the payload
+        command: &'static str,
+        /// Size of the payload, in bytes
+        size: usize,
+        /// The worker's limit, in bytes
+        limit: usize,
+        /// The run associated with the completion
+        run_id: String,
+        /// Correlation id of the activation being completed, if lang echoed it
+        correlation_id: String,
+    },
+    /// Server rejected the completion because the attributes of one of its commands were invalid.
+    /// The workflow task has been failed with `cause`, and the run will be evicted.
+    #[error("Server rejected a command sent for run ({run_id}) with cause {cause:?}: {message}")]
+    CommandRejectedByServer {
+        /// The cause server gave, which names the kind of command rejected
+        cause: WorkflowTaskFailedCause,
+        /// Index of the rejected command among those sent to server in the workflow task
+        /// completion, if it could be determined. Server doesn't say which command it means, so
+        /// this is only known if the completion held just one command of that kind.
+        command_index: Option<usize>,
+        /// Server's description of what was wrong
+        message: String,
+        /// The run associated with the completion
+        run_id: String,
+        /// Correlation id of the activation being completed, if lang echoed it
+        correlation_id: String,
     },
 }
 
@@ -65,6 +228,34 @@ pub enum CompleteActivityError {
         /// The completion, which may not be included to avoid unnecessary copies.
         completion: Option<ActivityExecutionResult>,
     },
+    /// Lang SDK completed an activity task this worker isn't tracking. EX: one which was already
+    /// completed, or a task token which was never delivered by this worker.
+    #[error("Lang SDK completed activity task {task_token}, which core can't accept: {reason}")]
+    InvalidCompletion {
+        /// Why the completion can't be accepted
+        reason: String,
+        /// The task token the completion was for
+        task_token: TaskToken,
+    },
+}
+
+/// Errors thrown by the blocking (non-async) facade over a worker, wrapping those of the
+/// underlying call
+#[derive(thiserror::Error, Debug)]
+pub enum BlockingCallError<E = Infallible> {
+    /// The call did not finish within the timeout given for it
+    #[error("Call did not finish within {0:?}")]
+    Timeout(Duration),
+    /// The call was made from a thread running async code. Blocking there could stall or deadlock
+    /// the runtime, so the async API must be used instead.
+    #[error("Blocking calls cannot be made from within an async context")]
+    CalledFromAsyncContext,
+    /// The runtime was shut down before the call could finish
+    #[error("The runtime was shut down before the call finished")]
+    RuntimeShutDown,
+    /// The underlying call failed
+    #[error(transparent)]
+    Call(E),
 }
 
 /// Errors we can encounter during workflow processing which we may treat as either WFT failures
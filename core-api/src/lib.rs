@@ -9,6 +9,7 @@ use crate::{
     },
     worker::WorkerConfig,
 };
+use std::time::Duration;
 use temporal_sdk_core_protos::coresdk::{
     activity_task::ActivityTask, workflow_activation::WorkflowActivation,
     workflow_completion::WorkflowActivationCompletion, ActivityHeartbeat, ActivityTaskCompletion,
@@ -45,6 +46,22 @@ pub trait Worker: Send + Sync {
     /// Do not call poll concurrently. It handles polling the server concurrently internally.
     async fn poll_activity_task(&self) -> Result<ActivityTask, PollActivityError>;
 
+    /// Like [Worker::poll_activity_task], but returns up to `max_tasks` tasks at once, so that
+    /// lang bindings can pick up many tasks per crossing of their FFI boundary. Waits up to
+    /// `max_wait` for the first task, returning an empty batch if none arrived by then, and then
+    /// adds any others which are immediately available. Each task counts against slots and rate
+    /// limits exactly as if it had been polled alone. A `max_tasks` of zero is treated as one.
+    ///
+    /// If polling fails after some tasks were already gathered, those are returned and the error
+    /// is returned by the next call.
+    ///
+    /// Do not call poll concurrently, including with [Worker::poll_activity_task].
+    async fn poll_activity_tasks_batch(
+        &self,
+        max_tasks: usize,
+        max_wait: Duration,
+    ) -> Result<Vec<ActivityTask>, PollActivityError>;
+
     /// Tell the worker that a workflow activation has completed. May (and should) be freely called
     /// concurrently. The future may take some time to resolve, as fetching more events might be
     /// necessary for completion to... complete - thus SDK implementers should make sure they do
@@ -56,10 +73,21 @@ pub trait Worker: Send + Sync {
 
     /// Tell the worker that an activity has finished executing. May (and should) be freely called
     /// concurrently.
+    ///
+    /// Successfully completing does not necessarily mean server accepted the result, see
+    /// [ActivityCompletionOutcome].
     async fn complete_activity_task(
         &self,
         completion: ActivityTaskCompletion,
-    ) -> Result<(), CompleteActivityError>;
+    ) -> Result<ActivityCompletionOutcome, CompleteActivityError>;
+
+    /// Complete several activities at once, see [Worker::complete_activity_task]. Completions are
+    /// applied concurrently and independently of one another: the returned results are in the
+    /// same order as `completions`, and one failing has no effect on the rest.
+    async fn complete_activity_tasks_batch(
+        &self,
+        completions: Vec<ActivityTaskCompletion>,
+    ) -> Vec<Result<ActivityCompletionOutcome, CompleteActivityError>>;
 
     /// Notify the Temporal service that an activity is still alive. Long running activities that
     /// take longer than `activity_heartbeat_timeout` to finish must call this function in order to
@@ -117,3 +145,15 @@ pub trait Worker: Send + Sync {
     /// functions have returned `ShutDown` errors.
     async fn finalize_shutdown(self);
 }
+
+/// What became of a successful call to [Worker::complete_activity_task]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActivityCompletionOutcome {
+    /// The result was reported to server, or there was nothing which needed reporting (ex: the
+    /// activity will be completed asynchronously, or it was a local activity)
+    Accepted,
+    /// Server no longer knew about the activity, most likely because it had already been cancelled
+    /// or timed out, or its workflow has closed. The result was dropped. This is an expected race
+    /// and should not be treated as a failure or retried.
+    ActivityGone,
+}
@@ -45,6 +45,13 @@ pub struct TelemetryOptions {
     /// A prefix to be applied to all core-created metrics. Defaults to "temporal_".
     #[builder(default = "METRIC_PREFIX.to_string()")]
     pub metric_prefix: String,
+    /// If set, metrics which have been renamed, or had their attribute keys renamed, to follow the
+    /// naming shared across Temporal SDKs are also emitted under their old names and keys, so
+    /// dashboards and alerts built on those keep working while they're moved over. On by default
+    /// during the migration window, after which the old names will stop being emitted
+    /// altogether. Turn it off once nothing depends on them.
+    #[builder(default = "true")]
+    pub metric_prefix_compat: bool,
 }
 
 /// Options for exporting to an OpenTelemetry Collector
@@ -5,8 +5,10 @@ use std::{
     sync::Arc,
     time::Duration,
 };
-use temporal_sdk_core_protos::coresdk::{
-    ActivitySlotInfo, LocalActivitySlotInfo, WorkflowSlotInfo,
+use temporal_sdk_core_protos::{
+    compat::check_proto_version,
+    coresdk::{ActivitySlotInfo, LocalActivitySlotInfo, WorkflowSlotInfo},
+    temporal::api::common::v1::RetryPolicy,
 };
 
 const MAX_CONCURRENT_WFT_POLLS_DEFAULT: usize = 5;
@@ -26,7 +28,8 @@ pub struct WorkerConfig {
     pub worker_build_id: String,
     /// A human-readable string that can identify this worker. Using something like sdk version
     /// and host name is a good default. If set, overrides the identity set (if any) on the client
-    /// used by this worker.
+    /// used by this worker. If neither is set, `<pid>@<hostname>` is used. Every poll, completion
+    /// and heartbeat the worker sends carries this identity.
     #[builder(default)]
     pub client_identity_override: Option<String>,
     /// If set nonzero, workflows will be cached and sticky task queues will be used, meaning that
@@ -36,6 +39,21 @@ pub struct WorkerConfig {
     /// or failures.
     #[builder(default = "0")]
     pub max_cached_workflows: usize,
+    /// If set, also bounds the cache by the approximate number of bytes its runs retain (pending
+    /// history, buffered tasks, commands, and activation jobs). When exceeded, idle runs are
+    /// evicted least-recently-used first, as with [WorkerConfig::max_cached_workflows]. The most
+    /// recently used run is only evicted for this reason if another run needs its cache slot, so
+    /// a single run larger than the limit may still be cached.
+    #[builder(setter(into, strip_option), default)]
+    pub max_cache_bytes: Option<usize>,
+    /// If set, bounds how many runs which aren't cached may be having their history fetched and
+    /// replayed at once, independently of how many workflow tasks may be outstanding. A worker
+    /// restarting with a large backlog can otherwise start so many replays that they starve each
+    /// other of CPU and time out. Tasks needing a replay beyond this limit wait for one to finish,
+    /// and are dropped (to be retried by server) if they wait longer than their workflow task
+    /// timeout.
+    #[builder(setter(into, strip_option), default)]
+    pub max_concurrent_history_replays: Option<usize>,
     /// Set a [WorkerTuner] for this worker. Either this or at least one of the `max_outstanding_*`
     /// fields must be set.
     #[builder(setter(into = false, strip_option), default)]
@@ -57,9 +75,30 @@ pub struct WorkerConfig {
     #[builder(default = "5")]
     pub max_concurrent_at_polls: usize,
     /// If set to true this worker will only handle workflow tasks and local activities, it will not
-    /// poll for activity tasks.
+    /// poll for activity tasks. [crate::Worker::poll_activity_task] must still be polled, since it
+    /// delivers local activities.
     #[builder(default = "false")]
     pub no_remote_activities: bool,
+    /// If set, [crate::Worker::validate] of a worker which doesn't poll for activity tasks itself
+    /// (see [WorkerConfig::no_remote_activities]) asks server whether anything else polls its task
+    /// queue for them, and logs a warning if nothing does. Activities its workflows schedule on
+    /// their own task queue would otherwise sit there until they time out.
+    #[builder(default = "false")]
+    pub warn_if_no_activity_pollers: bool,
+    /// If set, task queues other than this worker's own which its workflows schedule activities on
+    /// are checked for pollers, and a warning is logged (and the
+    /// `activity_task_queue_without_pollers` metric incremented) for any which server knows of
+    /// nothing polling. This catches misspelled queue names, whose activities would otherwise sit
+    /// there until they time out. Each queue's result is cached for this long, so it is described
+    /// at most once per interval. Checks run in the background and never delay completions.
+    #[builder(default)]
+    pub activity_task_queue_check_ttl: Option<Duration>,
+    /// If set to true this worker will only handle activity tasks, it will not poll for workflow
+    /// tasks (or register for eager workflow start), and [crate::Worker::poll_workflow_activation]
+    /// fails immediately. Options which only affect workflows, like caching, are ignored. Cannot be
+    /// combined with [WorkerConfig::no_remote_activities].
+    #[builder(default = "false")]
+    pub activity_only: bool,
     /// How long a workflow task is allowed to sit on the sticky queue before it is timed out
     /// and moved to the non-sticky queue where it may be picked up by any worker.
     #[builder(default = "Duration::from_secs(10)")]
@@ -76,6 +115,15 @@ pub struct WorkerConfig {
     #[builder(default = "Duration::from_secs(30)")]
     pub default_heartbeat_throttle_interval: Duration,
 
+    /// After this many consecutive heartbeats of an activity fail to reach server, the worker
+    /// presumes it can no longer learn of the activity being cancelled, and delivers a
+    /// cancellation with reason `HeartbeatChannelLost` so the activity doesn't keep running
+    /// (and holding its slot) for nothing, and increments the `activity_heartbeat_channel_lost`
+    /// metric. The slot is still held until lang completes the activity. A heartbeat reaching
+    /// server resets the count. Zero disables this.
+    #[builder(default = "5")]
+    pub heartbeat_failures_before_cancel: usize,
+
     /// Sets the maximum number of activities per second the task queue will dispatch, controlled
     /// server-side. Note that this only takes effect upon an activity poll request. If multiple
     /// workers on the same queue have different values set, they will thrash with the last poller
@@ -85,6 +133,12 @@ pub struct WorkerConfig {
     #[builder(default)]
     pub max_task_queue_activities_per_second: Option<f64>,
 
+    /// The most activities scheduled by a single workflow task completion which will be requested
+    /// for eager execution on this worker. Each is only requested if an activity slot is free for
+    /// it. Setting this to zero disables eager activity execution.
+    #[builder(default = "3")]
+    pub max_eager_activities_per_workflow_task: usize,
+
     /// Limits the number of activities per second that this worker will process. The worker will
     /// not poll for new activities if by doing so it might receive and execute an activity which
     /// would cause it to exceed this limit. Negative, zero, or NaN values will cause building
@@ -110,12 +164,21 @@ pub struct WorkerConfig {
     pub ignore_evicts_on_shutdown: bool,
 
     /// Maximum number of next page (or initial) history event listing requests we'll make
-    /// concurrently. I don't this it's worth exposing this to users until we encounter a reason.
+    /// concurrently, including pages fetched ahead of runs needing them. I don't this it's worth
+    /// exposing this to users until we encounter a reason.
     #[builder(default = "5")]
     pub fetching_concurrency: usize,
 
+    /// If set, the most bytes of history fetched from server to rebuild one run. Once a rebuild
+    /// would fetch more, it is abandoned: the workflow task is failed saying the history is too
+    /// large for this worker, and the run is evicted. This keeps one huge history from exhausting
+    /// a small worker's memory, so such workflows can be routed to bigger workers instead.
+    #[builder(setter(into, strip_option), default)]
+    pub max_history_fetch_bytes: Option<usize>,
+
     /// If set, core will issue cancels for all outstanding activities after shutdown has been
-    /// initiated and this amount of time has elapsed.
+    /// initiated and this amount of time has elapsed. Any client calls other than task
+    /// completions (ex: history fetches) still in flight at that point are also aborted.
     #[builder(default)]
     pub graceful_shutdown_period: Option<Duration>,
 
@@ -125,6 +188,144 @@ pub struct WorkerConfig {
     #[builder(default = "Duration::from_secs(5)")]
     pub local_timeout_buffer_for_activities: Duration,
 
+    /// If set, a warning naming the workflow id, activity id, and elapsed time is logged for any
+    /// activity which runs longer than this. It's logged once while the activity is still running
+    /// and again when it completes.
+    #[builder(default)]
+    pub slow_activity_log_threshold: Option<Duration>,
+
+    /// The length of the rolling window over which poll outcome statistics (polls issued, polls
+    /// that returned a task, and polls that came back empty) are tracked for each polled queue.
+    /// See `Worker::poll_stats`. Resolution is one second.
+    #[builder(default = "Duration::from_secs(60)")]
+    pub poll_stats_window: Duration,
+
+    /// A query which arrives for a cached run while the run's last activation completion has not
+    /// yet been acknowledged by server (it is being reported, or is held while local activities
+    /// run) is deferred until that completion resolves, so that the answer never reflects commands
+    /// server might not record. If this is set, queries are not deferred. They are answered right
+    /// away, even if that exposes unacknowledged state, only waiting for lang to finish any
+    /// activation it is working on. A completion held for local activities is sent right away
+    /// (as a workflow task heartbeat) rather than making the query wait for them.
+    #[builder(default = "false")]
+    pub eager_query_answers: bool,
+
+    /// How long a query may stay deferred behind an unacknowledged completion (see
+    /// [WorkerConfig::eager_query_answers]) before it is failed. If unset (the default), this is
+    /// the run's workflow task timeout, since by then server has given up on the completion anyway.
+    #[builder(setter(into, strip_option), default)]
+    pub query_deferral_timeout: Option<Duration>,
+
+    /// How long lang may take to complete an activation before core decides it is stuck (ex: its
+    /// workflow code deadlocked or is busy looping) and gives up on it. The workflow task is failed
+    /// with a "potential deadlock detected" failure, rather than being left to time out on server,
+    /// and the run is evicted. A completion lang sends for the activation afterwards is rejected.
+    /// Disabled by default. See [ActivationDeadline].
+    #[builder(default)]
+    pub activation_deadline: ActivationDeadline,
+
+    /// If set, every command lang sends in an activation completion is checked for the fields
+    /// server requires before anything is sent to it. A command missing one fails the completion
+    /// with [crate::errors::CompleteWfError::InvalidCommand] naming the offending field, rather
+    /// than surfacing later as a less specific task failure from server. Enabled by default in
+    /// debug builds. Search attribute and memo upserts are checked even when this is unset.
+    #[builder(default = "cfg!(debug_assertions)")]
+    pub strict_command_validation: bool,
+
+    /// If set, replaying a history which contains a marker core can't interpret fails the workflow
+    /// task with a nondeterminism error. Otherwise such markers are skipped. Version and side
+    /// effect markers recorded by the Go and Java SDKs are always understood: version markers are
+    /// treated as patch markers, and side effect markers are skipped.
+    #[builder(default = "false")]
+    pub strict_marker_replay: bool,
+
+    /// While a run replays history, progress is reported every time this many events have been
+    /// applied: as a debug log, through the `workflow_replay_progress` gauge, and to
+    /// [WorkerConfig::lifecycle_observer] if set. Zero disables reporting.
+    #[builder(default = "10_000")]
+    pub replay_progress_interval: usize,
+
+    /// How many of its most recent state machine transitions (which machine, from and to which
+    /// state, and the event or command which caused it) each cached run remembers, to help debug
+    /// nondeterminism errors: the latest of them are appended to the error's message. Each entry
+    /// takes a few dozen bytes, and the log is dropped with the run. Zero disables it.
+    #[builder(default = "200")]
+    pub machine_transition_log_size: usize,
+
+    /// If set, each cached run keeps a copy of the history events it has processed, so that a
+    /// cache snapshot exported from this worker lets the worker which imports it pick the run up
+    /// without fetching its history from server. Roughly doubles the memory each cached workflow
+    /// uses. Without this, snapshots still carry the sticky queue and which runs were cached.
+    #[builder(default)]
+    pub retain_history_for_handover: bool,
+    /// If set, before any workflow task completion is sent, the run is replayed from the history
+    /// it has processed against a fresh set of workflow machines, answering each activation with
+    /// the commands lang answered it with. The completion is only sent if replay gives the same
+    /// activations and arrives at the same commands. Otherwise the task is failed as
+    /// nondeterministic, with the difference in the failure, so that a run which would not replay
+    /// cleanly is caught when its commands are written rather than at its next replay.
+    ///
+    /// Workflow code isn't run again, but each verification replays the run from its first event,
+    /// so the processing core does per completion grows with the length of the run's history. Runs
+    /// keep their history as with [WorkerConfig::retain_history_for_handover] while verified. Runs
+    /// longer than a few thousand events or activations stop being verified, and drop what was
+    /// kept for it. Time spent replaying is recorded in the
+    /// `workflow_completion_verification_latency` metric.
+    #[builder(default)]
+    pub verify_completions_by_replay: bool,
+
+    /// The order in which ready activations are delivered to lang, when more are ready than lang
+    /// is polling for. See [ActivationDeliveryOrder].
+    #[builder(default)]
+    pub activation_delivery_order: ActivationDeliveryOrder,
+
+    /// If set, no activation carries more than this many jobs. Any further jobs are delivered in
+    /// follow-up activations, each issued as soon as the one before it is completed, without
+    /// waiting for new work from server. Useful for lang runtimes which struggle with very large
+    /// activations (ex: embedded or wasm ones).
+    ///
+    /// Split activations all belong to the same workflow task, so the commands lang sends in
+    /// response to them are recorded together, exactly as if the jobs had arrived at once. Hence
+    /// this can be changed freely, and histories recorded with one limit replay under any other,
+    /// as long as workflow code doesn't depend on which jobs it is handed together (which lang
+    /// SDKs don't promise anyway).
+    #[builder(setter(into, strip_option), default)]
+    pub max_jobs_per_activation: Option<usize>,
+
+    /// If set, a run whose activations containing updates or cancellations are completed this many
+    /// times in a row without any commands is assumed to have lang dropping its jobs: an error
+    /// naming the job kinds is logged, and the `workflow_suspected_dropped_jobs` counter is
+    /// incremented. Activations which only resolve timers, activities and the like don't count,
+    /// and neither do signals, since handling one often only changes workflow state. A workflow
+    /// which legitimately ignores several cancellations in a row will trip this, so set it above
+    /// what workflows do normally.
+    #[builder(setter(into, strip_option), default)]
+    pub dropped_job_detection_threshold: Option<usize>,
+
+    /// If set, tripping [WorkerConfig::dropped_job_detection_threshold] also fails the workflow
+    /// task, evicting the run, so that the hang shows up in the workflow's history
+    #[builder(default)]
+    pub fail_wft_on_dropped_jobs: bool,
+
+    /// The version (`major.minor`) of the coresdk protos lang was built against. If set, building
+    /// the config fails unless core understands every message of that version (see
+    /// [temporal_sdk_core_protos::compat::check_proto_version]). Lang bridges should set it, so
+    /// that nothing lang sends is silently dropped.
+    #[builder(setter(into, strip_option), default)]
+    pub lang_proto_version: Option<String>,
+
+    /// Defaults applied to activities scheduled by workflows on this worker when lang leaves the
+    /// corresponding field unset. See [ActivityDefaults].
+    #[builder(default)]
+    pub activity_defaults: ActivityDefaults,
+
+    /// If set, activities scheduled by workflows with an execution timeout carry a header saying
+    /// how much of that timeout was left when they were scheduled, so activity implementations can
+    /// avoid retrying internally past the point their result could be used. See
+    /// [temporal_sdk_core_protos::constants::WORKFLOW_TIME_REMAINING_HEADER].
+    #[builder(default)]
+    pub attach_workflow_time_remaining: bool,
+
     /// Any error types listed here will cause any workflow being processed by this worker to fail,
     /// rather than simply failing the workflow task.
     #[builder(default)]
@@ -135,6 +336,56 @@ pub struct WorkerConfig {
     #[builder(default)]
     pub workflow_types_to_failure_errors: HashMap<String, HashSet<WorkflowErrorType>>,
 
+    /// If set, the largest size, in bytes, of any single payload lang sends. This should match
+    /// the limit of the worker's namespace. An oversized payload in a workflow completion fails it
+    /// with [crate::errors::CompleteWfError::PayloadTooLarge], and an oversized activity result is
+    /// replaced with a non-retryable application failure, unless
+    /// [WorkerConfig::large_payload_store] is set.
+    #[builder(setter(into, strip_option), default)]
+    pub outbound_payload_limit: Option<usize>,
+
+    /// If set, successful activity results (local or not) larger than
+    /// [WorkerConfig::outbound_payload_limit] are put in this store instead of failing the
+    /// activity, and replaced with a small reference payload. References in activity resolutions
+    /// are resolved back through the store before being delivered to workflows, so workflow code
+    /// only ever sees the original result. Every worker which may run the affected workflows must
+    /// be able to read what the others stored.
+    #[builder(setter(into = false, strip_option), default)]
+    pub large_payload_store: Option<Arc<dyn LargePayloadStore>>,
+
+    /// The encoded sizes of the activations and activity tasks delivered to lang, and of the
+    /// completions it sends back, are always recorded in the `message_size` histogram. If set, any
+    /// of those messages larger than this many bytes is also logged as a warning naming the
+    /// workflow or activity type, to help catch payload bloat before server starts rejecting it.
+    #[builder(setter(into, strip_option), default)]
+    pub message_size_warn_threshold: Option<usize>,
+
+    /// By default polls wait as long as it takes for a task to arrive, even while every slot for
+    /// their type of task is in use. If set, a poll which has waited this long and finds all those
+    /// slots in use returns [crate::errors::PollWfError::WorkerSaturated] (or
+    /// [crate::errors::PollActivityError::WorkerSaturated]) instead, so lang can back off or report
+    /// the saturation. A poll which finds a slot free keeps waiting. Only slot suppliers with a
+    /// fixed number of slots (ex: `max_outstanding_*`) can be saturated.
+    #[builder(default)]
+    pub poll_saturation_timeout: Option<Duration>,
+
+    /// Polled tasks wait in a buffer until lang polls for them, and a workflow task can carry
+    /// megabytes of history. If set, once the tasks waiting in any one buffer (workflow, sticky
+    /// workflow, or activity) take up more than this many bytes, encoded, that buffer starts no
+    /// more polls until lang claims some of them. Polls in flight still finish, so the buffer can
+    /// end up past the limit by as much as they return. Slots still limit how many tasks are
+    /// buffered, whatever their size. The size of each buffer is recorded in the
+    /// `poll_buffer_unclaimed_bytes` gauge either way.
+    #[builder(setter(into, strip_option), default)]
+    pub max_buffered_poll_bytes: Option<usize>,
+
+    /// If set, called with each [WorkerLifecycleEvent]: the stages of the namespace failing over,
+    /// polling stopping and resuming over rejected credentials, and replay progress reports. It is
+    /// called from whichever of the worker's tasks the event happened on, so it should return
+    /// quickly.
+    #[builder(setter(into = false, strip_option), default)]
+    pub lifecycle_observer: Option<WorkerLifecycleObserver>,
+
     /// The maximum allowed number of workflow tasks that will ever be given to this worker at one
     /// time. Note that one workflow task may require multiple activations - so the WFT counts as
     /// "outstanding" until all activations it requires have been completed.
@@ -229,6 +480,23 @@ impl WorkerConfigBuilder {
             }
         }
 
+        if self.activity_only.unwrap_or_default() && self.no_remote_activities.unwrap_or_default() {
+            return Err(
+                "`activity_only` and `no_remote_activities` leave the worker nothing to do"
+                    .to_owned(),
+            );
+        }
+
+        if matches!(self.max_cache_bytes, Some(Some(0))) {
+            return Err("`max_cache_bytes` must be positive if set".to_owned());
+        }
+        if matches!(self.max_concurrent_history_replays, Some(Some(0))) {
+            return Err("`max_concurrent_history_replays` must be positive if set".to_owned());
+        }
+        if matches!(self.max_jobs_per_activation, Some(Some(0))) {
+            return Err("`max_jobs_per_activation` must be positive if set".to_owned());
+        }
+
         if self.use_worker_versioning.unwrap_or_default()
             && self
                 .worker_build_id
@@ -241,10 +509,168 @@ impl WorkerConfigBuilder {
                     .to_owned(),
             );
         }
+        if let Some(Some(ref v)) = self.lang_proto_version {
+            check_proto_version(v).map_err(|e| e.to_string())?;
+        }
+        if let Some(ref defaults) = self.activity_defaults {
+            for (name, timeout) in [
+                (
+                    "schedule_to_close_timeout",
+                    defaults.schedule_to_close_timeout,
+                ),
+                ("heartbeat_timeout", defaults.heartbeat_timeout),
+            ] {
+                // Server takes timeouts as protobuf durations, whose seconds are an i64
+                if timeout.is_some_and(|d| prost_types::Duration::try_from(d).is_err()) {
+                    return Err(format!("`activity_defaults.{name}` is too long"));
+                }
+            }
+        }
         Ok(())
     }
 }
 
+/// The order in which ready activations are delivered when several are waiting for lang to poll
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ActivationDeliveryOrder {
+    /// In the order they became ready
+    #[default]
+    Fifo,
+    /// Those whose workflow task will time out soonest go first, to minimize tasks timing out on
+    /// server while waiting behind others. Activations with equal deadlines, or which have no
+    /// outstanding workflow task, go in the order they became ready, after any with a deadline.
+    DeadlineFirst,
+}
+
+/// See [WorkerConfig::activation_deadline]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ActivationDeadline {
+    /// Lang may take as long as it likes
+    #[default]
+    Disabled,
+    /// The run's workflow task timeout less a second (but at least half of it), so the failure
+    /// reaches server before the task would time out anyway. Runs whose tasks have no timeout
+    /// have no deadline.
+    FromWorkflowTaskTimeout,
+    /// This long, whatever the workflow task timeout
+    Fixed(Duration),
+}
+
+/// Observes [WorkerLifecycleEvent]s. See [WorkerConfig::lifecycle_observer].
+pub type WorkerLifecycleObserver = Arc<dyn Fn(&WorkerLifecycleEvent) + Send + Sync>;
+
+/// Something which happened to a worker that lang or tooling may want to report or react to
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum WorkerLifecycleEvent {
+    /// A stage of the worker riding out its namespace failing over to another cluster
+    NamespaceFailover(NamespaceFailoverEvent),
+    /// Polling stopped because server keeps rejecting the worker's credentials, or resumed
+    PollAuthFailure(PollAuthFailureEvent),
+    /// A run got another [WorkerConfig::replay_progress_interval] events further in replaying its
+    /// history, ex: for tooling to render progress bars
+    ReplayProgress(ReplayProgress),
+}
+
+/// Stages of a worker riding out a failover of its (global) namespace to another cluster.
+///
+/// When server reports that the namespace isn't active on the cluster the worker is connected to,
+/// the worker stops issuing calls, reconnects so that the endpoint is resolved again, and describes
+/// the namespace to learn its active cluster. Calls then resume, and any which failed because the
+/// namespace wasn't active, including completions of tasks polled before the failover, are issued
+/// again. If server still reports the namespace inactive, the cycle repeats.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NamespaceFailoverEvent {
+    /// Server reported the namespace isn't active on the cluster the worker is connected to.
+    /// Calls are paused.
+    Detected {
+        /// The cluster the worker is connected to, as server reported it
+        current_cluster: String,
+        /// The cluster the namespace is active on, as server reported it
+        active_cluster: String,
+    },
+    /// The worker reconnected, and calls resume
+    Resumed {
+        /// The cluster the namespace is active on, as described after reconnecting. Empty if the
+        /// namespace couldn't be described.
+        active_cluster: String,
+    },
+}
+
+/// Stages of a worker handling server rejecting its credentials.
+///
+/// Polls which server rejects as unauthenticated or permission denied are retried a few times. If
+/// they keep being rejected, every poller of the worker stops, and polls return
+/// [crate::errors::PollWfError::AuthFailure] (or [crate::errors::PollActivityError::AuthFailure])
+/// until lang, presumably having refreshed the credentials, resumes polling.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PollAuthFailureEvent {
+    /// Polling stopped
+    Stopped {
+        /// The queue whose poll was rejected last
+        task_queue: String,
+        /// Server's explanation of the rejection
+        message: String,
+    },
+    /// Lang resumed polling
+    Resumed,
+}
+
+/// How far a run has gotten in replaying its history
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReplayProgress {
+    /// The run's workflow id
+    pub workflow_id: String,
+    /// The run's id
+    pub run_id: String,
+    /// The id of the last event applied, and hence the number of events applied
+    pub events_applied: u64,
+    /// The id of the last event known to exist, from the workflow task and any history pages
+    /// fetched so far. May grow as more pages are fetched.
+    pub total_known_events: u64,
+    /// Number of history pages fetched for this run's current workflow task
+    pub history_pages_fetched: u64,
+}
+
+/// Worker-wide defaults for scheduling activities. Each one is only used for activities whose
+/// schedule command from lang leaves the corresponding field unset - anything lang specifies always
+/// takes precedence. Fields left as `None` here apply no default at all.
+#[derive(Clone, Debug, Default, PartialEq, derive_builder::Builder)]
+#[builder(setter(into, strip_option), default)]
+#[non_exhaustive]
+pub struct ActivityDefaults {
+    /// Retry policy for activities which don't specify one
+    pub retry_policy: Option<RetryPolicy>,
+    /// Schedule-to-close timeout for activities which don't specify one
+    pub schedule_to_close_timeout: Option<Duration>,
+    /// Task queue for activities which don't specify one. If this is also unset, such activities
+    /// are scheduled on the workflow's own task queue.
+    pub task_queue: Option<String>,
+    /// Heartbeat timeout for activities which don't specify one
+    pub heartbeat_timeout: Option<Duration>,
+}
+
+/// Somewhere to keep activity results too large to pass through server, addressed by a digest of
+/// their contents. See [WorkerConfig::large_payload_store]. References to stored results are
+/// recorded in workflow histories, so entries must stay retrievable for as long as those histories
+/// may be replayed.
+#[async_trait::async_trait]
+pub trait LargePayloadStore: Send + Sync {
+    /// Store `data` under `digest`, the lowercase hex SHA-256 of `data`. Storing the same data
+    /// again must succeed.
+    async fn put(&self, digest: &str, data: Vec<u8>) -> Result<(), LargePayloadStoreError>;
+
+    /// Return the data stored under `digest`
+    async fn get(&self, digest: &str) -> Result<Vec<u8>, LargePayloadStoreError>;
+}
+
+/// The error a [LargePayloadStore] operation may fail with
+pub type LargePayloadStoreError = Box<dyn std::error::Error + Send + Sync>;
+
+/// The `encoding` metadata of payloads standing in for a result kept in a [LargePayloadStore].
+/// Their data is the digest the result is stored under.
+pub const LARGE_PAYLOAD_REFERENCE_ENCODING: &str = "binary/temporal-large-payload-ref";
+
 /// This trait allows users to customize the performance characteristics of workers dynamically.
 /// For more, see the docstrings of the traits in the return types of its functions.
 pub trait WorkerTuner {
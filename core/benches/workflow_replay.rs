@@ -3,7 +3,9 @@ use futures_util::StreamExt;
 use std::time::Duration;
 use temporal_sdk::{WfContext, WorkflowFunction};
 use temporal_sdk_core::replay::HistoryForReplay;
-use temporal_sdk_core_protos::DEFAULT_WORKFLOW_TYPE;
+use temporal_sdk_core_protos::{
+    temporal::api::enums::v1::EventType, TestHistoryBuilder, DEFAULT_WORKFLOW_TYPE,
+};
 use temporal_sdk_core_test_utils::{canned_histories, replay_sdk_worker};
 
 pub fn criterion_benchmark(c: &mut Criterion) {
@@ -48,6 +50,43 @@ pub fn criterion_benchmark(c: &mut Criterion) {
             })
         })
     });
+
+    let num_jobs = 100;
+    let t = many_jobs_history(num_jobs);
+    let hist = HistoryForReplay::new(
+        t.get_full_history_info().unwrap().into(),
+        "whatever".to_string(),
+    );
+
+    c.bench_function("Activation with many jobs replay", |b| {
+        b.iter(|| {
+            tokio_runtime.block_on(async {
+                let func = signals_wf(num_jobs);
+                let mut worker = replay_sdk_worker([hist.clone()]);
+                worker.register_wf(DEFAULT_WORKFLOW_TYPE, func);
+                worker.run().await.unwrap();
+            })
+        })
+    });
+
+    let mut t = TestHistoryBuilder::default();
+    t.add_by_type(EventType::WorkflowExecutionStarted);
+    t.add_workflow_task_scheduled_and_started();
+    let hist = HistoryForReplay::new(
+        t.get_full_history_info().unwrap().into(),
+        "whatever".to_string(),
+    );
+
+    c.bench_function("First workflow task", |b| {
+        b.iter(|| {
+            tokio_runtime.block_on(async {
+                let func = WorkflowFunction::new(|_: WfContext| async { Ok(().into()) });
+                let mut worker = replay_sdk_worker([hist.clone()]);
+                worker.register_wf(DEFAULT_WORKFLOW_TYPE, func);
+                worker.run().await.unwrap();
+            })
+        })
+    });
 }
 
 criterion_group!(benches, criterion_benchmark);
@@ -74,3 +113,27 @@ fn big_signals_wf(num_tasks: usize) -> WorkflowFunction {
         Ok(().into())
     })
 }
+
+/// All the signals arrive before the first workflow task, so they (plus the start job) are
+/// delivered to lang as one activation
+fn many_jobs_history(num_signals: usize) -> TestHistoryBuilder {
+    let mut t = TestHistoryBuilder::default();
+    t.add_by_type(EventType::WorkflowExecutionStarted);
+    for _ in 1..=num_signals {
+        t.add_we_signaled("sig", vec![]);
+    }
+    t.add_full_wf_task();
+    t.add_workflow_execution_completed();
+    t
+}
+
+fn signals_wf(num_signals: usize) -> WorkflowFunction {
+    WorkflowFunction::new(move |ctx: WfContext| async move {
+        let mut sigs = ctx.make_signal_channel("sig");
+        for _ in 1..=num_signals {
+            let _ = sigs.next().await.unwrap();
+        }
+
+        Ok(().into())
+    })
+}
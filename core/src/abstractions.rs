@@ -9,6 +9,7 @@ use std::{
         atomic::{AtomicBool, AtomicUsize, Ordering},
         Arc,
     },
+    time::Duration,
 };
 use temporal_sdk_core_api::worker::{
     SlotKind, SlotMarkUsedContext, SlotReleaseContext, SlotReservationContext, SlotSupplier,
@@ -35,6 +36,10 @@ pub(crate) struct MeteredPermitDealer<SK: SlotKind> {
     /// we add user-defined cache sizing, that logic will need to live with the supplier and
     /// there will need to be some associated refactoring.
     max_permits: Option<usize>,
+    /// A cap on extant permits which lang may adjust at runtime, shared by all clones of this
+    /// dealer. [NO_SLOT_TARGET] when unset. Lowering it never revokes extant permits, it only
+    /// withholds new ones until enough are released.
+    slot_target: Arc<AtomicUsize>,
     metrics_ctx: MetricsContext,
     /// Only applies to permit dealers for workflow tasks. True if this permit dealer is associated
     /// with a sticky queue poller.
@@ -42,6 +47,27 @@ pub(crate) struct MeteredPermitDealer<SK: SlotKind> {
     context_data: Arc<PermitDealerContextData>,
 }
 
+const NO_SLOT_TARGET: usize = usize::MAX;
+
+/// The current runtime slot target of a type of task slot, along with how many slots are in use
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SlotUsage {
+    /// The target set by lang, if any. See [crate::Worker::set_workflow_slot_target].
+    pub target: Option<usize>,
+    /// The number of slots currently handed out, which may exceed the target if it was lowered
+    /// while they were in use
+    pub issued: usize,
+}
+
+/// Slot targets and usage for each type of task a worker processes
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorkerSlotUsage {
+    /// Workflow task slots
+    pub workflow: SlotUsage,
+    /// Activity task slots, if the worker processes activities
+    pub activity: Option<SlotUsage>,
+}
+
 #[derive(Clone, Debug)]
 #[cfg_attr(test, derive(Default))]
 pub(crate) struct PermitDealerContextData {
@@ -64,6 +90,7 @@ where
             supplier,
             unused_claimants: Arc::new(AtomicUsize::new(0)),
             extant_permits: watch::channel(0),
+            slot_target: Arc::new(AtomicUsize::new(NO_SLOT_TARGET)),
             metrics_ctx,
             max_permits,
             is_sticky_poller: false,
@@ -82,23 +109,21 @@ where
     }
 
     pub(crate) async fn acquire_owned(&self) -> OwnedMeteredSemPermit<SK> {
-        if let Some(max) = self.max_permits {
-            self.extant_permits
-                .1
-                .clone()
-                .wait_for(|&ep| ep < max)
-                .await
-                .expect("Extant permit channel is never closed");
-        }
+        // The limit is re-read whenever the extant count changes, and changing the slot target
+        // also notifies the channel, so waiters see a raised target right away.
+        self.extant_permits
+            .1
+            .clone()
+            .wait_for(|&ep| !self.at_limit(ep))
+            .await
+            .expect("Extant permit channel is never closed");
         let res = self.supplier.reserve_slot(self).await;
         self.build_owned(res)
     }
 
     pub(crate) fn try_acquire_owned(&self) -> Result<OwnedMeteredSemPermit<SK>, ()> {
-        if let Some(max) = self.max_permits {
-            if *self.extant_permits.1.borrow() >= max {
-                return Err(());
-            }
+        if self.at_limit(*self.extant_permits.1.borrow()) {
+            return Err(());
         }
         if let Some(res) = self.supplier.try_reserve_slot(self) {
             Ok(self.build_owned(res))
@@ -111,6 +136,61 @@ where
         self.extant_permits.1.clone()
     }
 
+    /// Set (or clear, with `None`) the runtime cap on extant permits. Raising it takes effect
+    /// immediately for anyone waiting on a permit.
+    pub(crate) fn set_slot_target(&self, target: Option<usize>) {
+        self.slot_target
+            .store(target.unwrap_or(NO_SLOT_TARGET), Ordering::Release);
+        // Wake up any waiters so they re-evaluate against the new target
+        self.extant_permits.0.send_modify(|_| {});
+    }
+
+    pub(crate) fn slot_usage(&self) -> SlotUsage {
+        SlotUsage {
+            target: self.slot_target(),
+            issued: *self.extant_permits.1.borrow(),
+        }
+    }
+
+    fn slot_target(&self) -> Option<usize> {
+        match self.slot_target.load(Ordering::Acquire) {
+            NO_SLOT_TARGET => None,
+            t => Some(t),
+        }
+    }
+
+    /// If every slot is in use by a task, rather than held by a poller waiting for one, returns
+    /// how many are in use and the limit they are held to
+    pub(crate) fn saturation(&self) -> Option<(usize, usize)> {
+        let extant = *self.extant_permits.1.borrow();
+        let supplier_limit = self.supplier.available_slots().map(|avail| avail + extant);
+        let limit = supplier_limit.into_iter().chain(self.limit()).min()?;
+        let used = extant.saturating_sub(self.unused_claimants.load(Ordering::Acquire));
+        (used >= limit).then_some((used, limit))
+    }
+
+    /// Resolves with [Self::saturation] the first time it finds every slot in use, checking each
+    /// time `wait` elapses. Never resolves if the number of slots isn't limited.
+    pub(crate) async fn saturated_after(&self, wait: Duration) -> (usize, usize) {
+        loop {
+            tokio::time::sleep(wait).await;
+            if let Some(saturation) = self.saturation() {
+                return saturation;
+            }
+        }
+    }
+
+    fn limit(&self) -> Option<usize> {
+        match (self.max_permits, self.slot_target()) {
+            (Some(max), Some(target)) => Some(max.min(target)),
+            (max, target) => max.or(target),
+        }
+    }
+
+    fn at_limit(&self, extant: usize) -> bool {
+        self.limit().is_some_and(|l| extant >= l)
+    }
+
     fn build_owned(&self, res: SlotSupplierPermit) -> OwnedMeteredSemPermit<SK> {
         self.unused_claimants.fetch_add(1, Ordering::Release);
         self.extant_permits.0.send_modify(|ep| *ep += 1);
@@ -440,4 +520,40 @@ pub(crate) mod tests {
         // Now it'll proceed
         acquire_fut.await;
     }
+
+    #[tokio::test]
+    async fn slot_target_withholds_permits_without_revoking() {
+        let sem = fixed_size_permit_dealer::<WorkflowSlotKind>(5);
+        let perm1 = sem.try_acquire_owned().unwrap();
+        let perm2 = sem.try_acquire_owned().unwrap();
+        // Shrinking below current usage leaves the extant permits alone
+        sem.set_slot_target(Some(1));
+        assert_eq!(
+            sem.slot_usage(),
+            SlotUsage {
+                target: Some(1),
+                issued: 2
+            }
+        );
+        sem.try_acquire_owned().unwrap_err();
+        drop(perm1);
+        // Still at the target
+        sem.try_acquire_owned().unwrap_err();
+        drop(perm2);
+        let perm = sem.try_acquire_owned().unwrap();
+        // Growing the target wakes up waiters immediately
+        let acquire_fut = sem.acquire_owned();
+        advance_fut!(acquire_fut);
+        sem.set_slot_target(Some(2));
+        let _perm2 = acquire_fut.await;
+        sem.set_slot_target(None);
+        assert_eq!(
+            sem.slot_usage(),
+            SlotUsage {
+                target: None,
+                issued: 2
+            }
+        );
+        drop(perm);
+    }
 }
@@ -0,0 +1,204 @@
+//! A synchronous facade over a [Worker], for lang bindings which call into core from foreign
+//! threads that aren't part of any async runtime.
+//!
+//! Every call is spawned onto the runtime the worker was initialized with, and the calling thread
+//! only waits for its result. The caller never enters the runtime itself, so there is no way to
+//! trip over tokio's panics about starting a runtime from within another, or blocking on one.
+
+use crate::{CoreRuntime, Worker};
+use std::{
+    future::Future,
+    sync::{mpsc, Arc},
+    time::Duration,
+};
+use temporal_sdk_core_api::{
+    errors::{
+        BlockingCallError, CompleteActivityError, CompleteWfError, PollActivityError, PollWfError,
+    },
+    ActivityCompletionOutcome, Worker as WorkerTrait,
+};
+use temporal_sdk_core_protos::coresdk::{
+    activity_task::ActivityTask, workflow_activation::WorkflowActivation,
+    workflow_completion::WorkflowActivationCompletion, ActivityTaskCompletion,
+};
+
+/// Owns a [Worker] along with a handle to the runtime it runs on, and exposes blocking versions
+/// of its async API. May be shared between, and called concurrently from, any number of threads.
+///
+/// Every call accepts an optional timeout. Polls which time out are cancelled before they can
+/// hand out a task, so no task is lost. Completions which time out are *not* cancelled, since
+/// core may already have acted on part of them - they continue in the background, and must not
+/// be retried.
+pub struct CoreBlocking {
+    worker: Arc<Worker>,
+    runtime_handle: tokio::runtime::Handle,
+}
+
+impl CoreBlocking {
+    /// Wrap a worker which was initialized with `runtime`
+    pub fn new(runtime: &CoreRuntime, worker: Worker) -> Self {
+        Self {
+            worker: Arc::new(worker),
+            runtime_handle: runtime.tokio_handle(),
+        }
+    }
+
+    /// Returns the wrapped worker, ex: to call its non-async methods
+    pub fn worker(&self) -> &Worker {
+        &self.worker
+    }
+
+    /// Blocking version of [WorkerTrait::poll_workflow_activation]
+    pub fn poll_workflow_activation_blocking(
+        &self,
+        timeout: Option<Duration>,
+    ) -> Result<WorkflowActivation, BlockingCallError<PollWfError>> {
+        let worker = self.worker.clone();
+        self.call(timeout, CancelOnTimeout::Yes, async move {
+            worker.poll_workflow_activation().await
+        })
+    }
+
+    /// Blocking version of [WorkerTrait::poll_activity_task]
+    pub fn poll_activity_task_blocking(
+        &self,
+        timeout: Option<Duration>,
+    ) -> Result<ActivityTask, BlockingCallError<PollActivityError>> {
+        let worker = self.worker.clone();
+        self.call(timeout, CancelOnTimeout::Yes, async move {
+            worker.poll_activity_task().await
+        })
+    }
+
+    /// Blocking version of [WorkerTrait::poll_activity_tasks_batch]
+    pub fn poll_activity_tasks_batch_blocking(
+        &self,
+        max_tasks: usize,
+        max_wait: Duration,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<ActivityTask>, BlockingCallError<PollActivityError>> {
+        let worker = self.worker.clone();
+        self.call(timeout, CancelOnTimeout::Yes, async move {
+            worker.poll_activity_tasks_batch(max_tasks, max_wait).await
+        })
+    }
+
+    /// Blocking version of [WorkerTrait::complete_workflow_activation]
+    pub fn complete_workflow_activation_blocking(
+        &self,
+        completion: WorkflowActivationCompletion,
+        timeout: Option<Duration>,
+    ) -> Result<(), BlockingCallError<CompleteWfError>> {
+        let worker = self.worker.clone();
+        self.call(timeout, CancelOnTimeout::No, async move {
+            worker.complete_workflow_activation(completion).await
+        })
+    }
+
+    /// Blocking version of [WorkerTrait::complete_activity_task]
+    pub fn complete_activity_task_blocking(
+        &self,
+        completion: ActivityTaskCompletion,
+        timeout: Option<Duration>,
+    ) -> Result<ActivityCompletionOutcome, BlockingCallError<CompleteActivityError>> {
+        let worker = self.worker.clone();
+        self.call(timeout, CancelOnTimeout::No, async move {
+            worker.complete_activity_task(completion).await
+        })
+    }
+
+    /// Blocking version of [WorkerTrait::complete_activity_tasks_batch]. If `timeout` elapses, all
+    /// of the completions continue in the background and none of their results are returned.
+    pub fn complete_activity_tasks_batch_blocking(
+        &self,
+        completions: Vec<ActivityTaskCompletion>,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<Result<ActivityCompletionOutcome, CompleteActivityError>>, BlockingCallError>
+    {
+        let worker = self.worker.clone();
+        self.call(timeout, CancelOnTimeout::No, async move {
+            Ok(worker.complete_activity_tasks_batch(completions).await)
+        })
+    }
+
+    /// Blocking version of [WorkerTrait::shutdown], which also finalizes the worker once it has
+    /// shut down, if no completion which timed out is still being processed. If `timeout` elapses
+    /// first, shutdown continues in the background.
+    pub fn shutdown_blocking(self, timeout: Option<Duration>) -> Result<(), BlockingCallError> {
+        let worker = self.worker;
+        block_on_handle(
+            &self.runtime_handle,
+            timeout,
+            CancelOnTimeout::No,
+            async move {
+                worker.shutdown().await;
+                if let Ok(worker) = Arc::try_unwrap(worker) {
+                    worker.finalize_shutdown().await;
+                }
+                Ok(())
+            },
+        )
+    }
+
+    fn call<T, E>(
+        &self,
+        timeout: Option<Duration>,
+        cancel: CancelOnTimeout,
+        fut: impl Future<Output = Result<T, E>> + Send + 'static,
+    ) -> Result<T, BlockingCallError<E>>
+    where
+        T: Send + 'static,
+        E: Send + 'static,
+    {
+        block_on_handle(&self.runtime_handle, timeout, cancel, fut)
+    }
+}
+
+/// Whether a call which times out should be cancelled, or left to finish in the background
+#[derive(Clone, Copy)]
+enum CancelOnTimeout {
+    Yes,
+    No,
+}
+
+/// Runs `fut` as a task on the runtime behind `handle`, and blocks the current thread until it
+/// finishes or `timeout` elapses
+fn block_on_handle<T, E>(
+    handle: &tokio::runtime::Handle,
+    timeout: Option<Duration>,
+    cancel: CancelOnTimeout,
+    fut: impl Future<Output = Result<T, E>> + Send + 'static,
+) -> Result<T, BlockingCallError<E>>
+where
+    T: Send + 'static,
+    E: Send + 'static,
+{
+    // Blocking a runtime thread could starve (or, on a current thread runtime, deadlock) the very
+    // task we'd be waiting on.
+    if tokio::runtime::Handle::try_current().is_ok() {
+        return Err(BlockingCallError::CalledFromAsyncContext);
+    }
+    let (tx, rx) = mpsc::sync_channel::<Result<Result<T, E>, BlockingCallError<E>>>(1);
+    let cancel_after = timeout.filter(|_| matches!(cancel, CancelOnTimeout::Yes));
+    handle.spawn(async move {
+        let res = match cancel_after {
+            // Dropping the future inside the task means it either finishes or is cancelled, never
+            // both, so a result can't go missing.
+            Some(t) => tokio::time::timeout(t, fut)
+                .await
+                .map_err(|_| BlockingCallError::Timeout(t)),
+            None => Ok(fut.await),
+        };
+        let _ = tx.send(res);
+    });
+    let res = match timeout {
+        Some(t) if cancel_after.is_none() => rx.recv_timeout(t).map_err(|e| match e {
+            mpsc::RecvTimeoutError::Timeout => BlockingCallError::Timeout(t),
+            mpsc::RecvTimeoutError::Disconnected => BlockingCallError::RuntimeShutDown,
+        }),
+        // If the task is dropped without running, ex: because the runtime is shutting down, the
+        // sender is dropped along with it.
+        _ => rx.recv().map_err(|_| BlockingCallError::RuntimeShutDown),
+    };
+    res.and_then(|r| r)?.map_err(BlockingCallError::Call)
+}
@@ -1,16 +1,21 @@
 use crate::{
-    advance_fut, job_assert, prost_dur,
+    advance_fut, job_assert,
+    pollers::{PollError, AUTH_FAILURES_BEFORE_STOPPING},
+    prost_dur,
+    telemetry::construct_filter_string,
     test_help::{
         build_fake_worker, build_mock_pollers, canned_histories, gen_assert_and_reply,
         mock_manual_poller, mock_poller, mock_poller_from_resps, mock_sdk_cfg, mock_worker,
-        poll_and_reply, single_hist_mock_sg, test_worker_cfg, MockPollCfg, MockWorkerInputs,
-        MocksHolder, QueueResponse, ResponseType, WorkerExt, WorkflowCachingPolicy, TEST_Q,
+        mock_worker_with_telemetry, poll_and_reply, single_hist_mock_sg, test_worker_cfg,
+        BufferedTelemetry, InMemoryStore, MockPollCfg, MockWorkerInputs, MocksHolder,
+        QueueResponse, ResponseType, WorkerExt, WorkflowCachingPolicy, TEST_Q,
     },
     worker::client::mocks::{mock_manual_workflow_client, mock_workflow_client},
-    ActivityHeartbeat, Worker,
+    ActivityHeartbeat, SlotUsage, TaskToken, Worker,
 };
-use futures_util::FutureExt;
+use futures_util::{stream::FuturesUnordered, FutureExt, StreamExt};
 use itertools::Itertools;
+use prost::Message;
 use std::{
     cell::RefCell,
     collections::{hash_map::Entry, HashMap, HashSet, VecDeque},
@@ -26,7 +31,12 @@ use temporal_client::WorkflowOptions;
 use temporal_sdk::{ActivityOptions, WfContext};
 use temporal_sdk_core_api::{
     errors::{CompleteActivityError, PollActivityError},
-    Worker as WorkerTrait,
+    telemetry::{metrics::MetricUpdateVal, CoreTelemetry, Logger},
+    worker::{
+        ActivityDefaultsBuilder, LargePayloadStore, PollAuthFailureEvent, WorkerLifecycleEvent,
+        LARGE_PAYLOAD_REFERENCE_ENCODING,
+    },
+    ActivityCompletionOutcome, Worker as WorkerTrait,
 };
 use temporal_sdk_core_protos::{
     coresdk::{
@@ -45,7 +55,9 @@ use temporal_sdk_core_protos::{
     },
     temporal::api::{
         command::v1::{command::Attributes, ScheduleActivityTaskCommandAttributes},
+        common::v1::{ActivityType, Payloads},
         enums::v1::{CommandType, EventType},
+        failure::v1::{failure::FailureInfo, ApplicationFailureInfo, Failure},
         history::v1::{
             history_event::Attributes as EventAttributes, ActivityTaskScheduledEventAttributes,
         },
@@ -61,6 +73,7 @@ use temporal_sdk_core_protos::{
 use temporal_sdk_core_test_utils::{fanout_tasks, start_timer_cmd, TestWorker};
 use tokio::{join, sync::Barrier, time::sleep};
 use tokio_util::sync::CancellationToken;
+use tracing::Level;
 
 fn three_tasks() -> VecDeque<PollActivityTaskQueueResponse> {
     VecDeque::from(vec![
@@ -120,20 +133,502 @@ async fn max_activities_respected() {
     poll_fut.await.unwrap();
 }
 
+#[tokio::test(start_paused = true)]
+async fn activity_tasks_polled_and_completed_in_batches() {
+    let mut tasks = three_tasks();
+    let mut mock_client = mock_workflow_client();
+    mock_client
+        .expect_poll_activity_task()
+        .times(3)
+        .returning(move |_, _| Ok(tasks.pop_front().unwrap()));
+    mock_client
+        .expect_complete_activity_task()
+        .times(2)
+        .returning(|_, _| Ok(RespondActivityTaskCompletedResponse::default()));
+    let worker = Worker::new_test(
+        test_worker_cfg()
+            .max_outstanding_activities(2_usize)
+            .build()
+            .unwrap(),
+        mock_client,
+    );
+    // Give the pollers a moment to buffer what they can. Time is paused, so this only elapses
+    // once they have.
+    sleep(Duration::from_millis(100)).await;
+
+    // Each task takes its own slot, so only two of the three come back
+    let batch = worker
+        .poll_activity_tasks_batch(10, Duration::from_secs(1))
+        .await
+        .unwrap();
+    assert_eq!(
+        batch.iter().map(|t| t.task_token.clone()).collect_vec(),
+        [vec![1], vec![2]]
+    );
+    assert!(worker
+        .poll_activity_tasks_batch(10, Duration::from_millis(50))
+        .await
+        .unwrap()
+        .is_empty());
+
+    // A bad completion doesn't affect the others in its batch
+    let mut completions = batch
+        .into_iter()
+        .map(|t| ActivityTaskCompletion {
+            task_token: t.task_token,
+            result: Some(ActivityExecutionResult::ok(vec![1].into())),
+        })
+        .collect_vec();
+    completions.insert(
+        1,
+        ActivityTaskCompletion {
+            task_token: vec![1],
+            result: None,
+        },
+    );
+    let results = worker.complete_activity_tasks_batch(completions).await;
+    assert_matches!(
+        results.as_slice(),
+        [
+            Ok(ActivityCompletionOutcome::Accepted),
+            Err(CompleteActivityError::MalformedActivityCompletion { .. }),
+            Ok(ActivityCompletionOutcome::Accepted),
+        ]
+    );
+
+    // The freed slots let the last task through, and a batch of one is just that task
+    let batch = worker
+        .poll_activity_tasks_batch(1, Duration::from_secs(1))
+        .await
+        .unwrap();
+    assert_eq!(batch.len(), 1);
+    assert_eq!(batch[0].task_token, vec![3]);
+}
+
 #[tokio::test]
-async fn activity_not_found_returns_ok() {
+async fn failed_activity_frees_slot_for_parked_poll() {
+    let mut tasks = three_tasks();
+    let mut mock_client = mock_workflow_client();
+    mock_client
+        .expect_poll_activity_task()
+        .times(2)
+        .returning(move |_, _| Ok(tasks.pop_front().unwrap()));
+    mock_client
+        .expect_fail_activity_task()
+        .times(1)
+        .returning(|_, _| Ok(RespondActivityTaskFailedResponse::default()));
+
+    let worker = Worker::new_test(
+        test_worker_cfg()
+            .max_outstanding_activities(1_usize)
+            .build()
+            .unwrap(),
+        mock_client,
+    );
+
+    let r1 = worker.poll_activity_task().await.unwrap();
+    let poll_fut = worker.poll_activity_task();
+    advance_fut!(poll_fut);
+    assert_eq!(worker.slot_usage().activity.unwrap().issued, 1);
+    worker
+        .complete_activity_task(ActivityTaskCompletion {
+            task_token: r1.task_token,
+            result: Some(ActivityExecutionResult::fail("Ahh".into())),
+        })
+        .await
+        .unwrap();
+    let r2 = poll_fut.await.unwrap();
+    assert_eq!(r2.task_token, vec![2]);
+}
+
+#[tokio::test]
+async fn activity_poll_reports_saturation_after_timeout() {
+    let mut tasks = three_tasks();
+    let mut mock_client = mock_workflow_client();
+    mock_client
+        .expect_poll_activity_task()
+        .times(2)
+        .returning(move |_, _| Ok(tasks.pop_front().unwrap()));
+    mock_client
+        .expect_complete_activity_task()
+        .returning(|_, _| Ok(RespondActivityTaskCompletedResponse::default()));
+
+    let worker = Worker::new_test(
+        test_worker_cfg()
+            .max_outstanding_activities(1_usize)
+            .poll_saturation_timeout(Some(Duration::from_millis(100)))
+            .build()
+            .unwrap(),
+        mock_client,
+    );
+
+    let r1 = worker.poll_activity_task().await.unwrap();
+    assert_matches!(
+        worker.poll_activity_task().await.unwrap_err(),
+        PollActivityError::WorkerSaturated {
+            outstanding: 1,
+            limit: 1
+        }
+    );
+    worker
+        .complete_activity_task(ActivityTaskCompletion {
+            task_token: r1.task_token,
+            result: Some(ActivityExecutionResult::ok(vec![1].into())),
+        })
+        .await
+        .unwrap();
+    let r2 = worker.poll_activity_task().await.unwrap();
+    assert_eq!(r2.task_token, vec![2]);
+}
+
+#[tokio::test]
+async fn activity_failure_next_retry_delay_is_sent_to_server() {
+    let mut mock_client = mock_workflow_client();
+    mock_client
+        .expect_fail_activity_task()
+        .times(1)
+        .withf(|_, failure| {
+            matches!(
+                failure.as_ref().and_then(|f| f.failure_info.as_ref()),
+                Some(FailureInfo::ApplicationFailureInfo(ai))
+                    if ai.next_retry_delay == Some(prost_dur!(from_secs(30)))
+            )
+        })
+        .returning(|_, _| Ok(RespondActivityTaskFailedResponse::default()));
+    let core = mock_worker(MocksHolder::from_client_with_activities(
+        mock_client,
+        three_tasks().into_iter().take(1).map(Into::into),
+    ));
+
+    let task = core.poll_activity_task().await.unwrap();
+    let mut failure: Failure = "Upstream asked us to wait".into();
+    if let Some(FailureInfo::ApplicationFailureInfo(ai)) = failure.failure_info.as_mut() {
+        ai.next_retry_delay = Some(prost_dur!(from_secs(30)));
+    }
+    core.complete_activity_task(ActivityTaskCompletion {
+        task_token: task.task_token,
+        result: Some(ActivityExecutionResult::fail(failure)),
+    })
+    .await
+    .unwrap();
+    core.drain_activity_poller_and_shutdown().await;
+}
+
+#[tokio::test]
+async fn activity_slot_target_withholds_tasks_until_usage_drops_below_it() {
+    let mut mock_client = mock_workflow_client();
+    mock_client
+        .expect_complete_activity_task()
+        .returning(|_, _| Ok(RespondActivityTaskCompletedResponse::default()));
+    let core = mock_worker(MocksHolder::from_client_with_activities(
+        mock_client,
+        three_tasks().into_iter().map(Into::into),
+    ));
+
+    let r1 = core.poll_activity_task().await.unwrap();
+    let r2 = core.poll_activity_task().await.unwrap();
+    // Shrink below current usage. Nothing outstanding is revoked.
+    core.set_activity_slot_target(Some(1));
+    assert_eq!(
+        core.slot_usage().activity,
+        Some(SlotUsage {
+            target: Some(1),
+            issued: 2
+        })
+    );
+    core.complete_activity_task(ActivityTaskCompletion {
+        task_token: r1.task_token,
+        result: Some(ActivityExecutionResult::ok(vec![1].into())),
+    })
+    .await
+    .unwrap();
+    // Usage is now at the target, so still nothing new is delivered
+    let poll_fut = core.poll_activity_task();
+    advance_fut!(poll_fut);
+    core.complete_activity_task(ActivityTaskCompletion {
+        task_token: r2.task_token,
+        result: Some(ActivityExecutionResult::ok(vec![1].into())),
+    })
+    .await
+    .unwrap();
+    let r3 = poll_fut.await.unwrap();
+    assert_eq!(r3.task_token, vec![3]);
+    core.complete_activity_task(ActivityTaskCompletion {
+        task_token: r3.task_token,
+        result: Some(ActivityExecutionResult::ok(vec![1].into())),
+    })
+    .await
+    .unwrap();
+    core.drain_activity_poller_and_shutdown().await;
+}
+
+#[tokio::test]
+async fn completing_untracked_activity_is_invalid() {
     let mut mock_client = mock_workflow_client();
     // Mock won't even be called, since we weren't tracking activity
     mock_client.expect_complete_activity_task().times(0);
 
     let core = mock_worker(MocksHolder::from_client_with_activities(mock_client, []));
 
-    core.complete_activity_task(ActivityTaskCompletion {
-        task_token: vec![1],
+    let err = core
+        .complete_activity_task(ActivityTaskCompletion {
+            task_token: vec![1],
+            result: Some(ActivityExecutionResult::ok(vec![1].into())),
+        })
+        .await
+        .unwrap_err();
+    assert_matches!(err, CompleteActivityError::InvalidCompletion { task_token, .. }
+                         if task_token.0 == vec![1]);
+    core.drain_activity_poller_and_shutdown().await;
+}
+
+#[tokio::test]
+async fn each_activity_result_kind_is_reported_to_server() {
+    let mut mock_client = mock_workflow_client();
+    mock_client
+        .expect_complete_activity_task()
+        .times(1)
+        .withf(|tt, result| {
+            tt.0 == vec![1] && matches!(result, Some(ps) if ps.payloads[0].data == vec![1])
+        })
+        .returning(|_, _| Ok(RespondActivityTaskCompletedResponse::default()));
+    mock_client
+        .expect_fail_activity_task()
+        .times(1)
+        .withf(|tt, failure| {
+            tt.0 == vec![2]
+                && matches!(failure, Some(Failure {
+                    message,
+                    failure_info: Some(FailureInfo::ApplicationFailureInfo(info)),
+                    ..
+                }) if message == "Ahh"
+                    && info.r#type == "MyError"
+                    && info.non_retryable
+                    && info.details.as_ref().is_some_and(|d| d.payloads[0].data == vec![2]))
+        })
+        .returning(|_, _| Ok(RespondActivityTaskFailedResponse::default()));
+    mock_client
+        .expect_cancel_activity_task()
+        .times(1)
+        .withf(|tt, details| {
+            tt.0 == vec![3] && matches!(details, Some(ps) if ps.payloads[0].data == vec![3])
+        })
+        .returning(|_, _| Ok(RespondActivityTaskCanceledResponse::default()));
+    let core = mock_worker(MocksHolder::from_client_with_activities(
+        mock_client,
+        three_tasks().into_iter().map(Into::into),
+    ));
+
+    let failure = Failure {
+        message: "Ahh".to_string(),
+        failure_info: Some(FailureInfo::ApplicationFailureInfo(
+            ApplicationFailureInfo {
+                r#type: "MyError".to_string(),
+                non_retryable: true,
+                details: Some(Payloads {
+                    payloads: vec![vec![2].into()],
+                }),
+                ..Default::default()
+            },
+        )),
+        ..Default::default()
+    };
+    let results = [
+        ActivityExecutionResult::ok(vec![1].into()),
+        ActivityExecutionResult::fail(failure),
+        ActivityExecutionResult::cancel_from_details(Some(vec![3].into())),
+    ];
+    for result in results {
+        let task = core.poll_activity_task().await.unwrap();
+        let outcome = core
+            .complete_activity_task(ActivityTaskCompletion {
+                task_token: task.task_token,
+                result: Some(result),
+            })
+            .await
+            .unwrap();
+        assert_eq!(outcome, ActivityCompletionOutcome::Accepted);
+    }
+    core.drain_activity_poller_and_shutdown().await;
+}
+
+#[tokio::test]
+async fn completing_activity_twice_is_invalid() {
+    let mut mock_client = mock_workflow_client();
+    // Only the first completion is reported
+    mock_client
+        .expect_complete_activity_task()
+        .times(1)
+        .returning(|_, _| Ok(RespondActivityTaskCompletedResponse::default()));
+    let core = mock_worker(MocksHolder::from_client_with_activities(
+        mock_client,
+        three_tasks().into_iter().take(1).map(Into::into),
+    ));
+
+    let task = core.poll_activity_task().await.unwrap();
+    let completion = || ActivityTaskCompletion {
+        task_token: task.task_token.clone(),
         result: Some(ActivityExecutionResult::ok(vec![1].into())),
+    };
+    core.complete_activity_task(completion()).await.unwrap();
+    assert_matches!(
+        core.complete_activity_task(completion()).await,
+        Err(CompleteActivityError::InvalidCompletion { .. })
+    );
+    core.drain_activity_poller_and_shutdown().await;
+}
+
+#[tokio::test]
+async fn late_completion_of_cancelled_activity_is_benign() {
+    let mut mock_client = mock_workflow_client();
+    mock_client
+        .expect_record_activity_heartbeat()
+        .times(1)
+        .returning(|_, _| {
+            Ok(RecordActivityTaskHeartbeatResponse {
+                cancel_requested: true,
+                activity_paused: false,
+            })
+        });
+    // Only the cancel is reported, the late result is dropped
+    mock_client
+        .expect_cancel_activity_task()
+        .times(1)
+        .returning(|_, _| Ok(Default::default()));
+    mock_client.expect_complete_activity_task().never();
+    let core = mock_worker(MocksHolder::from_client_with_activities(
+        mock_client,
+        [PollActivityTaskQueueResponse {
+            task_token: vec![1],
+            activity_id: "act1".to_string(),
+            heartbeat_timeout: Some(prost_dur!(from_millis(1))),
+            ..Default::default()
+        }
+        .into()],
+    ));
+
+    let act = core.poll_activity_task().await.unwrap();
+    core.record_activity_heartbeat(ActivityHeartbeat {
+        task_token: act.task_token.clone(),
+        details: vec![],
+    });
+    let cancel = core.poll_activity_task().await.unwrap();
+    assert_matches!(cancel.variant, Some(activity_task::Variant::Cancel(_)));
+    core.complete_activity_task(ActivityTaskCompletion {
+        task_token: act.task_token.clone(),
+        result: Some(ActivityExecutionResult::cancel_from_details(None)),
     })
     .await
     .unwrap();
+    // The activity function finished anyway after lang reported the cancel. That must not be an
+    // error, or the SDK would tear down its activity processing over it.
+    let res = core
+        .complete_activity_task(ActivityTaskCompletion {
+            task_token: act.task_token,
+            result: Some(ActivityExecutionResult::ok(vec![1].into())),
+        })
+        .await;
+    assert_matches!(res, Ok(ActivityCompletionOutcome::ActivityGone));
+    core.drain_activity_poller_and_shutdown().await;
+}
+
+#[tokio::test]
+async fn activity_gone_on_completion_after_retry_or_cancel_is_benign() {
+    let mut mock_client = mock_workflow_client();
+    mock_client
+        .expect_record_activity_heartbeat()
+        .times(1)
+        .returning(|_, _| {
+            Ok(RecordActivityTaskHeartbeatResponse {
+                cancel_requested: true,
+                activity_paused: false,
+            })
+        });
+    mock_client
+        .expect_complete_activity_task()
+        .times(1)
+        .returning(|_, _| Err(tonic::Status::not_found("Activity gone")));
+    mock_client
+        .expect_cancel_activity_task()
+        .times(1)
+        .returning(|_, _| Err(tonic::Status::not_found("Activity gone")));
+
+    let core = mock_worker(MocksHolder::from_client_with_activities(
+        mock_client,
+        [
+            PollActivityTaskQueueResponse {
+                task_token: vec![1],
+                activity_id: "act1".to_string(),
+                attempt: 2,
+                ..Default::default()
+            }
+            .into(),
+            PollActivityTaskQueueResponse {
+                task_token: vec![2],
+                activity_id: "act2".to_string(),
+                attempt: 1,
+                heartbeat_timeout: Some(prost_dur!(from_millis(1))),
+                ..Default::default()
+            }
+            .into(),
+        ],
+    ));
+
+    // A retry's previous attempt may have timed out, leaving server without this one
+    let act = core.poll_activity_task().await.unwrap();
+    let res = core
+        .complete_activity_task(ActivityTaskCompletion {
+            task_token: act.task_token,
+            result: Some(ActivityExecutionResult::ok(vec![1].into())),
+        })
+        .await;
+    assert_matches!(res, Ok(ActivityCompletionOutcome::ActivityGone));
+
+    // A first attempt which was cancelled is also allowed to have been forgotten
+    let act = core.poll_activity_task().await.unwrap();
+    core.record_activity_heartbeat(ActivityHeartbeat {
+        task_token: act.task_token.clone(),
+        details: vec![vec![1_u8, 2, 3].into()],
+    });
+    let cancel = core.poll_activity_task().await.unwrap();
+    assert_matches!(cancel.variant, Some(activity_task::Variant::Cancel(_)));
+    let res = core
+        .complete_activity_task(ActivityTaskCompletion {
+            task_token: act.task_token,
+            result: Some(ActivityExecutionResult::cancel_from_details(None)),
+        })
+        .await;
+    assert_matches!(res, Ok(ActivityCompletionOutcome::ActivityGone));
+    core.drain_activity_poller_and_shutdown().await;
+}
+
+#[tokio::test]
+async fn activity_not_found_on_first_attempt_completion_is_only_warned_about() {
+    let mut mock_client = mock_workflow_client();
+    mock_client
+        .expect_complete_activity_task()
+        .times(1)
+        .returning(|_, _| Err(tonic::Status::not_found("Never heard of it")));
+
+    let core = mock_worker(MocksHolder::from_client_with_activities(
+        mock_client,
+        [PollActivityTaskQueueResponse {
+            task_token: vec![1],
+            activity_id: "act1".to_string(),
+            attempt: 1,
+            ..Default::default()
+        }
+        .into()],
+    ));
+
+    let act = core.poll_activity_task().await.unwrap();
+    let res = core
+        .complete_activity_task(ActivityTaskCompletion {
+            task_token: act.task_token,
+            result: Some(ActivityExecutionResult::ok(vec![1].into())),
+        })
+        .await;
+    assert_matches!(res, Ok(ActivityCompletionOutcome::ActivityGone));
     core.drain_activity_poller_and_shutdown().await;
 }
 
@@ -234,6 +729,95 @@ async fn heartbeats_report_cancels_only_once() {
     core.drain_activity_poller_and_shutdown().await;
 }
 
+#[tokio::test]
+async fn cancelled_activity_is_not_also_timed_out() {
+    let mut mock_client = mock_workflow_client();
+    mock_client
+        .expect_record_activity_heartbeat()
+        .times(1)
+        .returning(|_, _| {
+            Ok(RecordActivityTaskHeartbeatResponse {
+                cancel_requested: true,
+                activity_paused: false,
+            })
+        });
+    mock_client
+        .expect_cancel_activity_task()
+        .times(1)
+        .returning(|_, _| Ok(RespondActivityTaskCanceledResponse::default()));
+    mock_client
+        .expect_complete_activity_task()
+        .times(1)
+        .returning(|_, _| Ok(RespondActivityTaskCompletedResponse::default()));
+
+    let mut mh = MocksHolder::from_client_with_activities(
+        mock_client,
+        [
+            PollActivityTaskQueueResponse {
+                task_token: vec![1],
+                activity_id: "act1".to_string(),
+                start_to_close_timeout: Some(prost_dur!(from_millis(50))),
+                ..Default::default()
+            }
+            .into(),
+            PollActivityTaskQueueResponse {
+                task_token: vec![2],
+                activity_id: "act2".to_string(),
+                ..Default::default()
+            }
+            .into(),
+        ],
+    );
+    mh.worker_cfg(|wc| wc.local_timeout_buffer_for_activities = Duration::from_millis(10));
+    let core = mock_worker(mh);
+
+    let act = core.poll_activity_task().await.unwrap();
+    core.record_activity_heartbeat(ActivityHeartbeat {
+        task_token: act.task_token.clone(),
+        details: vec![],
+    });
+    // The cancel comes from the heartbeat response. Once it's processed, it's delivered ahead of
+    // the next task from server.
+    sleep(Duration::from_millis(10)).await;
+    let act = core.poll_activity_task().await.unwrap();
+    assert_matches!(
+        &act,
+        ActivityTask {
+            task_token,
+            variant: Some(activity_task::Variant::Cancel(Cancel { reason, .. })),
+            ..
+        } => {
+            assert_eq!(task_token, &vec![1]);
+            assert_eq!(*reason, ActivityCancelReason::Cancelled as i32);
+        }
+    );
+    // Lang is slow to wrap up, and the start to close timeout elapses locally in the meantime
+    sleep(Duration::from_millis(100)).await;
+    core.complete_activity_task(ActivityTaskCompletion {
+        task_token: act.task_token,
+        result: Some(ActivityExecutionResult::cancel_from_details(None)),
+    })
+    .await
+    .unwrap();
+    // Cancels always come before new tasks, so this would be the timeout if it were issued
+    let act = core.poll_activity_task().await.unwrap();
+    assert_matches!(
+        &act,
+        ActivityTask {
+            task_token,
+            variant: Some(activity_task::Variant::Start(_)),
+            ..
+        } => { task_token == &[2] }
+    );
+    core.complete_activity_task(ActivityTaskCompletion {
+        task_token: act.task_token,
+        result: Some(ActivityExecutionResult::ok(vec![1].into())),
+    })
+    .await
+    .unwrap();
+    core.drain_activity_poller_and_shutdown().await;
+}
+
 #[tokio::test]
 async fn activity_cancel_interrupts_poll() {
     let mut mock_poller = mock_manual_poller();
@@ -241,21 +825,21 @@ async fn activity_cancel_interrupts_poll() {
     let shutdown_token_clone = shutdown_token.clone();
     let mut poll_resps = VecDeque::from(vec![
         async {
-            Some(Ok(PollActivityTaskQueueResponse {
+            Ok(PollActivityTaskQueueResponse {
                 task_token: vec![1],
                 heartbeat_timeout: Some(prost_dur!(from_secs(1))),
                 ..Default::default()
-            }))
+            })
         }
         .boxed(),
         async {
             tokio::time::sleep(Duration::from_millis(500)).await;
-            Some(Ok(Default::default()))
+            Ok(Default::default())
         }
         .boxed(),
         async move {
             shutdown_token.cancelled().await;
-            None
+            Err(PollError::ShutDown)
         }
         .boxed(),
     ]);
@@ -321,25 +905,29 @@ async fn activity_cancel_interrupts_poll() {
 
 #[tokio::test]
 async fn activity_poll_timeout_retries() {
-    let mock_client = mock_workflow_client();
+    let mut mock_client = mock_workflow_client();
     let mut calls = 0;
-    let mut mock_act_poller = mock_poller();
-    mock_act_poller.expect_poll().times(3).returning(move || {
-        calls += 1;
-        if calls <= 2 {
-            Some(Ok(PollActivityTaskQueueResponse::default()))
-        } else {
-            Some(Ok(PollActivityTaskQueueResponse {
-                task_token: b"hello!".to_vec(),
-                ..Default::default()
-            }))
-        }
-    });
-    let mw = MockWorkerInputs {
-        act_poller: Some(Box::from(mock_act_poller)),
-        ..Default::default()
-    };
-    let core = mock_worker(MocksHolder::from_mock_worker(mock_client, mw));
+    mock_client
+        .expect_poll_activity_task()
+        .times(3)
+        .returning(move |_, _| {
+            calls += 1;
+            if calls <= 2 {
+                Ok(PollActivityTaskQueueResponse::default())
+            } else {
+                Ok(PollActivityTaskQueueResponse {
+                    task_token: b"hello!".to_vec(),
+                    ..Default::default()
+                })
+            }
+        });
+    // With one slot, which the task ends up holding, no fourth poll is made
+    let cfg = test_worker_cfg()
+        .max_concurrent_at_polls(1_usize)
+        .max_outstanding_activities(1_usize)
+        .build()
+        .unwrap();
+    let core = Worker::new_test(cfg, mock_client);
     let r = core.poll_activity_task().await.unwrap();
     assert_matches!(r.task_token.as_slice(), b"hello!");
 }
@@ -509,50 +1097,219 @@ async fn activity_timeout_no_double_resolve() {
 }
 
 #[tokio::test]
-async fn can_heartbeat_acts_during_shutdown() {
-    let mut mock_client = mock_workflow_client();
-    mock_client
-        .expect_record_activity_heartbeat()
-        .times(1)
-        .returning(|_, _| {
-            Ok(RecordActivityTaskHeartbeatResponse {
-                cancel_requested: false,
-                activity_paused: false,
-            })
+async fn can_heartbeat_acts_during_shutdown() {
+    let mut mock_client = mock_workflow_client();
+    mock_client
+        .expect_record_activity_heartbeat()
+        .times(1)
+        .returning(|_, _| {
+            Ok(RecordActivityTaskHeartbeatResponse {
+                cancel_requested: false,
+                activity_paused: false,
+            })
+        });
+    mock_client
+        .expect_complete_activity_task()
+        .times(1)
+        .returning(|_, _| Ok(RespondActivityTaskCompletedResponse::default()));
+
+    let core = mock_worker(MocksHolder::from_client_with_activities(
+        mock_client,
+        [PollActivityTaskQueueResponse {
+            task_token: vec![1],
+            activity_id: "act1".to_string(),
+            heartbeat_timeout: Some(prost_dur!(from_millis(1))),
+            ..Default::default()
+        }
+        .into()],
+    ));
+
+    let act = core.poll_activity_task().await.unwrap();
+    // Make sure shutdown has progressed before trying to record heartbeat / complete
+    let shutdown_fut = core.shutdown();
+    advance_fut!(shutdown_fut);
+    core.record_activity_heartbeat(ActivityHeartbeat {
+        task_token: act.task_token.clone(),
+
+        details: vec![vec![1_u8, 2, 3].into()],
+    });
+    core.complete_activity_task(ActivityTaskCompletion {
+        task_token: act.task_token,
+
+        result: Some(ActivityExecutionResult::ok(vec![1].into())),
+    })
+    .await
+    .unwrap();
+    core.drain_activity_poller_and_shutdown().await;
+}
+
+#[rstest::rstest]
+#[case::over_limit(Some(64), true)]
+#[case::unlimited(None, false)]
+#[tokio::test]
+async fn oversized_activity_result_fails_activity(
+    #[case] limit: Option<usize>,
+    #[case] over_limit: bool,
+) {
+    let mut mock_client = mock_workflow_client();
+    if over_limit {
+        mock_client
+            .expect_fail_activity_task()
+            .times(1)
+            .withf(|_, failure| {
+                matches!(
+                    failure.as_ref().and_then(|f| f.failure_info.as_ref()),
+                    Some(FailureInfo::ApplicationFailureInfo(info))
+                        if info.non_retryable && info.r#type == "PayloadSizeLimitExceeded"
+                )
+            })
+            .returning(|_, _| Ok(RespondActivityTaskFailedResponse::default()));
+    } else {
+        mock_client
+            .expect_complete_activity_task()
+            .times(1)
+            .returning(|_, _| Ok(RespondActivityTaskCompletedResponse::default()));
+    }
+    let mut mock = MocksHolder::from_client_with_activities(
+        mock_client,
+        [PollActivityTaskQueueResponse {
+            task_token: vec![1],
+            activity_id: "act1".to_string(),
+            ..Default::default()
+        }
+        .into()],
+    );
+    mock.worker_cfg(|wc| {
+        wc.outbound_payload_limit = limit;
+    });
+    let core = mock_worker(mock);
+
+    let act = core.poll_activity_task().await.unwrap();
+    core.complete_activity_task(ActivityTaskCompletion {
+        task_token: act.task_token,
+        result: Some(ActivityExecutionResult::ok(vec![0; 100].into())),
+    })
+    .await
+    .unwrap();
+    core.drain_activity_poller_and_shutdown().await;
+}
+
+#[tokio::test]
+async fn oversized_activity_tasks_and_completions_are_warned_about() {
+    let telem = BufferedTelemetry::with_options(|o| {
+        o.logging(Logger::Forward {
+            filter: construct_filter_string(Level::WARN, Level::WARN),
         });
+    });
+    let _g = tracing::subscriber::set_default(telem.trace_subscriber().unwrap());
+
+    let mut mock_client = mock_workflow_client();
     mock_client
         .expect_complete_activity_task()
         .times(1)
         .returning(|_, _| Ok(RespondActivityTaskCompletedResponse::default()));
-
-    let core = mock_worker(MocksHolder::from_client_with_activities(
+    let mut mock = MocksHolder::from_client_with_activities(
         mock_client,
         [PollActivityTaskQueueResponse {
             task_token: vec![1],
             activity_id: "act1".to_string(),
-            heartbeat_timeout: Some(prost_dur!(from_millis(1))),
+            activity_type: Some(ActivityType {
+                name: "big_act".to_string(),
+            }),
+            input: Some(Payloads {
+                payloads: vec![vec![0; 1000].into()],
+            }),
             ..Default::default()
         }
         .into()],
-    ));
+    );
+    mock.worker_cfg(|wc| wc.message_size_warn_threshold = Some(500));
+    let core = mock_worker_with_telemetry(mock, &telem);
 
     let act = core.poll_activity_task().await.unwrap();
-    // Make sure shutdown has progressed before trying to record heartbeat / complete
-    let shutdown_fut = core.shutdown();
-    advance_fut!(shutdown_fut);
-    core.record_activity_heartbeat(ActivityHeartbeat {
-        task_token: act.task_token.clone(),
+    let task_size = act.encoded_len();
+    let completion = ActivityTaskCompletion {
+        task_token: act.task_token,
+        result: Some(ActivityExecutionResult::ok(vec![0; 1000].into())),
+    };
+    let completion_size = completion.encoded_len();
+    core.complete_activity_task(completion).await.unwrap();
+    core.drain_activity_poller_and_shutdown().await;
 
-        details: vec![vec![1_u8, 2, 3].into()],
+    let sizes: Vec<_> = telem
+        .updates()
+        .into_iter()
+        .filter(|(name, _, _)| name.ends_with("message_size"))
+        .map(|(_, attrs, update)| match update {
+            MetricUpdateVal::Value(v) => (attrs["message_type"].clone(), v as usize),
+            other => panic!("Unexpected histogram update {other:?}"),
+        })
+        .collect();
+    assert_eq!(
+        sizes,
+        [
+            ("activity_task".to_string(), task_size),
+            ("activity_task_completion".to_string(), completion_size),
+        ]
+    );
+    let warnings: Vec<_> = telem
+        .fetch_buffered_logs()
+        .into_iter()
+        .filter(|l| l.message.contains("message size warning threshold"))
+        .map(|l| (l.message, l.fields["activity_type"].clone()))
+        .collect();
+    assert_eq!(
+        warnings,
+        [
+            (
+                "Activity task exceeds the message size warning threshold".to_string(),
+                "big_act".into()
+            ),
+            (
+                "Activity completion exceeds the message size warning threshold".to_string(),
+                "big_act".into()
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn oversized_activity_result_is_offloaded_to_store() {
+    let store = Arc::new(InMemoryStore::default());
+    let mut mock_client = mock_workflow_client();
+    mock_client
+        .expect_complete_activity_task()
+        .times(1)
+        .withf(|_, result| {
+            let reference = &result.as_ref().unwrap().payloads[0];
+            reference.metadata["encoding"] == LARGE_PAYLOAD_REFERENCE_ENCODING.as_bytes()
+        })
+        .returning(|_, _| Ok(RespondActivityTaskCompletedResponse::default()));
+    let mut mock = MocksHolder::from_client_with_activities(
+        mock_client,
+        [PollActivityTaskQueueResponse {
+            task_token: vec![1],
+            activity_id: "act1".to_string(),
+            ..Default::default()
+        }
+        .into()],
+    );
+    let store_c = store.clone();
+    mock.worker_cfg(move |wc| {
+        wc.outbound_payload_limit = Some(64);
+        wc.large_payload_store = Some(store_c as Arc<dyn LargePayloadStore>);
     });
+    let core = mock_worker(mock);
+
+    let act = core.poll_activity_task().await.unwrap();
     core.complete_activity_task(ActivityTaskCompletion {
         task_token: act.task_token,
-
-        result: Some(ActivityExecutionResult::ok(vec![1].into())),
+        result: Some(ActivityExecutionResult::ok(vec![0; 100].into())),
     })
     .await
     .unwrap();
     core.drain_activity_poller_and_shutdown().await;
+    assert_eq!(store.entries.lock().len(), 1);
 }
 
 /// Verifies that if a user has tried to record a heartbeat and then immediately after failed the
@@ -611,6 +1368,99 @@ async fn complete_act_with_fail_flushes_heartbeat() {
     assert_eq!(last_seen_payload.data, &[last_hb]);
 }
 
+/// A retry delivered to the same worker right after a failure must see only the heartbeat details
+/// server gave it, and no heartbeat for the failed attempt may be sent after its failure.
+#[tokio::test]
+async fn retry_on_same_worker_sees_only_server_heartbeat_details() {
+    let mut mock_client = mock_workflow_client();
+    let failed = Arc::new(AtomicBool::new(false));
+    let failed_c = failed.clone();
+    let reported = Rc::new(RefCell::new(vec![]));
+    let reported_c = reported.clone();
+    mock_client
+        .expect_record_activity_heartbeat()
+        .returning_st(move |tt, payload| {
+            assert!(
+                !failed_c.load(Ordering::SeqCst),
+                "Heartbeat sent after the attempt's failure"
+            );
+            reported_c
+                .borrow_mut()
+                .push((tt, payload.unwrap().payloads[0].data[0]));
+            Ok(RecordActivityTaskHeartbeatResponse::default())
+        });
+    mock_client
+        .expect_fail_activity_task()
+        .times(1)
+        .returning(move |_, _| {
+            failed.store(true, Ordering::SeqCst);
+            Ok(RespondActivityTaskFailedResponse::default())
+        });
+    mock_client
+        .expect_complete_activity_task()
+        .times(1)
+        .returning(|_, _| Ok(RespondActivityTaskCompletedResponse::default()));
+
+    let core = mock_worker(MocksHolder::from_client_with_activities(
+        mock_client,
+        [
+            PollActivityTaskQueueResponse {
+                task_token: vec![1],
+                activity_id: "act1".to_string(),
+                attempt: 1,
+                heartbeat_timeout: Some(prost_dur!(from_secs(10))),
+                ..Default::default()
+            }
+            .into(),
+            PollActivityTaskQueueResponse {
+                task_token: vec![2],
+                activity_id: "act1".to_string(),
+                attempt: 2,
+                heartbeat_timeout: Some(prost_dur!(from_secs(10))),
+                heartbeat_details: Some(Payloads {
+                    payloads: vec![vec![100].into()],
+                }),
+                ..Default::default()
+            }
+            .into(),
+        ],
+    ));
+
+    let act = core.poll_activity_task().await.unwrap();
+    for i in 1..=3 {
+        core.record_activity_heartbeat(ActivityHeartbeat {
+            task_token: act.task_token.clone(),
+            details: vec![vec![i].into()],
+        });
+    }
+    core.complete_activity_task(ActivityTaskCompletion {
+        task_token: act.task_token,
+        result: Some(ActivityExecutionResult::fail("Ahh".into())),
+    })
+    .await
+    .unwrap();
+
+    let retry = core.poll_activity_task().await.unwrap();
+    assert_matches!(
+        retry.variant,
+        Some(activity_task::Variant::Start(s)) if s.heartbeat_details.len() == 1
+            && s.heartbeat_details[0].data == [100]
+    );
+    core.complete_activity_task(ActivityTaskCompletion {
+        task_token: retry.task_token,
+        result: Some(ActivityExecutionResult::ok(vec![1].into())),
+    })
+    .await
+    .unwrap();
+    core.drain_activity_poller_and_shutdown().await;
+
+    // The first heartbeat goes out immediately, and the last is flushed before the failure
+    assert_eq!(
+        reported.borrow().as_slice(),
+        &[(TaskToken(vec![1]), 1), (TaskToken(vec![1]), 3)]
+    );
+}
+
 #[tokio::test]
 async fn max_tq_acts_set_passed_to_poll_properly() {
     let rate = 9.28;
@@ -634,6 +1484,108 @@ async fn max_tq_acts_set_passed_to_poll_properly() {
     worker.poll_activity_task().await.unwrap();
 }
 
+#[tokio::test(start_paused = true)]
+async fn worker_activity_rate_limit_holds_back_polls_and_can_be_changed() {
+    let polls = Arc::new(AtomicUsize::new(0));
+    let polls_clone = polls.clone();
+    let mut mock_client = mock_workflow_client();
+    mock_client
+        .expect_poll_activity_task()
+        .returning(move |_, _| {
+            let n = polls_clone.fetch_add(1, Ordering::SeqCst) + 1;
+            Ok(PollActivityTaskQueueResponse {
+                task_token: n.to_be_bytes().to_vec(),
+                activity_id: n.to_string(),
+                ..Default::default()
+            })
+        });
+
+    let cfg = test_worker_cfg()
+        .max_concurrent_at_polls(5_usize)
+        .max_outstanding_activities(100_usize)
+        .max_worker_activities_per_second(5.0)
+        .build()
+        .unwrap();
+    let worker = Worker::new_test(cfg, mock_client);
+
+    // Far more tasks are wanted than the limit allows in a second. Time is paused, so the only
+    // uncertainty is whether the poll due right at the end of the second makes it in.
+    let waiting: FuturesUnordered<_> = (0..50).map(|_| worker.poll_activity_task()).collect();
+    let _ = tokio::time::timeout(Duration::from_secs(1), waiting.collect::<Vec<_>>()).await;
+    let first_second = polls.load(Ordering::SeqCst);
+    assert!(
+        (5..=6).contains(&first_second),
+        "{first_second} polls in the first second"
+    );
+
+    worker.set_max_worker_activities_per_second(Some(200.0));
+    let waiting: FuturesUnordered<_> = (0..40).map(|_| worker.poll_activity_task()).collect();
+    let tasks = tokio::time::timeout(Duration::from_secs(1), waiting.collect::<Vec<_>>())
+        .await
+        .expect("Raised limit lets the polls through");
+    assert!(tasks.iter().all(Result::is_ok));
+}
+
+#[tokio::test]
+async fn polling_stops_after_repeated_auth_failures_until_resumed() {
+    let polls = Arc::new(AtomicUsize::new(0));
+    let polls_clone = polls.clone();
+    let mut mock_client = mock_workflow_client();
+    mock_client
+        .expect_poll_activity_task()
+        .returning(move |_, _| {
+            if polls_clone.fetch_add(1, Ordering::SeqCst) < AUTH_FAILURES_BEFORE_STOPPING {
+                Err(tonic::Status::permission_denied("API key expired"))
+            } else {
+                Ok(PollActivityTaskQueueResponse {
+                    task_token: vec![1],
+                    activity_id: "act".to_string(),
+                    ..Default::default()
+                })
+            }
+        });
+    let events = Arc::new(parking_lot::Mutex::new(vec![]));
+    let mut cfg = test_worker_cfg()
+        .max_concurrent_at_polls(1_usize)
+        .build()
+        .unwrap();
+    cfg.lifecycle_observer = Some({
+        let events = events.clone();
+        Arc::new(move |e: &WorkerLifecycleEvent| events.lock().push(e.clone()))
+    });
+    let worker = Worker::new_test(cfg, mock_client);
+
+    assert_matches!(
+        worker.poll_activity_task().await,
+        Err(PollActivityError::AuthFailure(s)) if s.code() == tonic::Code::PermissionDenied
+    );
+    // Later polls fail right away, and server isn't polled again
+    assert_matches!(
+        worker.poll_activity_task().await,
+        Err(PollActivityError::AuthFailure(_))
+    );
+    assert_eq!(polls.load(Ordering::SeqCst), AUTH_FAILURES_BEFORE_STOPPING);
+    assert_eq!(
+        *events.lock(),
+        vec![WorkerLifecycleEvent::PollAuthFailure(
+            PollAuthFailureEvent::Stopped {
+                task_queue: TEST_Q.to_string(),
+                message: "API key expired".to_string(),
+            }
+        )]
+    );
+
+    worker.resume_polling();
+    let task = worker.poll_activity_task().await.unwrap();
+    assert_eq!(task.task_token, vec![1]);
+    assert_eq!(
+        events.lock().last(),
+        Some(&WorkerLifecycleEvent::PollAuthFailure(
+            PollAuthFailureEvent::Resumed
+        ))
+    );
+}
+
 #[rstest::rstest]
 #[tokio::test]
 async fn no_eager_activities_requested_when_worker_options_disable_it(
@@ -710,13 +1662,77 @@ async fn no_eager_activities_requested_when_worker_options_disable_it(
     assert_eq!(num_eager_requested.load(Ordering::Relaxed), 0);
 }
 
+#[rstest::rstest]
+#[tokio::test]
+async fn eager_activity_requests_are_capped_per_workflow_task(#[values(0, 1)] cap: usize) {
+    let wfid = "fake_wf_id";
+    let mut t = TestHistoryBuilder::default();
+    t.add_by_type(EventType::WorkflowExecutionStarted);
+    t.add_full_wf_task();
+    t.add_workflow_execution_completed();
+    let num_eager_requested = Arc::new(AtomicUsize::new(usize::MAX));
+    let num_eager_requested_clone = num_eager_requested.clone();
+
+    let mut mock = mock_workflow_client();
+    mock.expect_complete_workflow_task()
+        .times(1)
+        .returning(move |req| {
+            let count = req
+                .commands
+                .into_iter()
+                .filter(|c| {
+                    matches!(
+                        c.attributes,
+                        Some(Attributes::ScheduleActivityTaskCommandAttributes(
+                            ScheduleActivityTaskCommandAttributes {
+                                request_eager_execution: true,
+                                ..
+                            }
+                        ))
+                    )
+                })
+                .count();
+            num_eager_requested_clone.store(count, Ordering::Relaxed);
+            Ok(RespondWorkflowTaskCompletedResponse::default())
+        });
+    let mut mock = single_hist_mock_sg(wfid, t, [1], mock, true);
+    mock.worker_cfg(|wc| {
+        wc.max_cached_workflows = 2;
+        wc.max_eager_activities_per_workflow_task = cap;
+    });
+    let core = mock_worker(mock);
+
+    let wf_task = core.poll_workflow_activation().await.unwrap();
+    let cmds = (1..=2)
+        .map(|seq| {
+            ScheduleActivity {
+                seq,
+                activity_id: format!("act_id_{seq}"),
+                task_queue: TEST_Q.to_string(),
+                cancellation_type: ActivityCancellationType::TryCancel as i32,
+                ..Default::default()
+            }
+            .into()
+        })
+        .collect();
+    core.complete_workflow_activation(WorkflowActivationCompletion::from_cmds(
+        wf_task.run_id,
+        cmds,
+    ))
+    .await
+    .unwrap();
+    core.drain_pollers_and_shutdown().await;
+
+    assert_eq!(num_eager_requested.load(Ordering::Relaxed), cap);
+}
+
 /// This test verifies that activity tasks which come as replies to completing a WFT are properly
 /// delivered via polling.
 #[tokio::test]
 async fn activity_tasks_from_completion_are_delivered() {
     // Construct the history - one task with 5 activities, 4 on the same task queue, and 1 on a
-    // different queue, 3 activities will be executed eagerly as specified by the
-    // MAX_EAGER_ACTIVITY_RESERVATIONS_PER_WORKFLOW_TASK constant.
+    // different queue, 3 activities will be executed eagerly as specified by the default
+    // max_eager_activities_per_workflow_task.
     let wfid = "fake_wf_id";
     let mut t = TestHistoryBuilder::default();
     t.add_by_type(EventType::WorkflowExecutionStarted);
@@ -1060,11 +2076,11 @@ async fn graceful_shutdown(#[values(true, false)] at_max_outstanding: bool) {
     mock_act_poller
         .expect_poll()
         .times(3)
-        .returning(move || Some(Ok(tasks.pop_front().unwrap())));
+        .returning(move || Ok(tasks.pop_front().unwrap()));
     mock_act_poller
         .expect_poll()
         .times(1)
-        .returning(move || None);
+        .returning(move || Err(PollError::ShutDown));
     // They shall all be reported as failed
     let mut mock_client = mock_workflow_client();
     mock_client
@@ -1137,11 +2153,11 @@ async fn activities_must_be_flushed_to_server_on_shutdown(#[values(true, false)]
     mock_act_poller
         .expect_poll()
         .times(1)
-        .returning(move || Some(Ok(tasks.pop_front().unwrap())));
+        .returning(move || Ok(tasks.pop_front().unwrap()));
     mock_act_poller
         .expect_poll()
         .times(1)
-        .returning(move || None);
+        .returning(move || Err(PollError::ShutDown));
     let mut mock_client = mock_manual_workflow_client();
     mock_client
         .expect_complete_activity_task()
@@ -1237,3 +2253,82 @@ async fn pass_activity_summary_to_metadata() {
         .unwrap();
     worker.run_until_done().await.unwrap();
 }
+
+#[test]
+fn worker_activity_default_timeouts_must_fit_in_a_duration() {
+    for defaults in [
+        ActivityDefaultsBuilder::default()
+            .schedule_to_close_timeout(Duration::MAX)
+            .build(),
+        ActivityDefaultsBuilder::default()
+            .heartbeat_timeout(Duration::MAX)
+            .build(),
+    ] {
+        let err = test_worker_cfg()
+            .activity_defaults(defaults.unwrap())
+            .build()
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("is too long"), "{err}");
+    }
+}
+
+#[tokio::test]
+async fn worker_activity_defaults_fill_unset_schedule_fields() {
+    let t = canned_histories::single_activity("1");
+    let mut mock_cfg = MockPollCfg::from_hist_builder(t);
+    let wf_id = mock_cfg.hists[0].wf_id.clone();
+    let wf_type = DEFAULT_WORKFLOW_TYPE;
+    mock_cfg.completion_asserts_from_expectations(|mut asserts| {
+        asserts
+            .then(move |wft| {
+                assert_matches!(
+                    wft.commands[0].attributes.as_ref(),
+                    Some(Attributes::ScheduleActivityTaskCommandAttributes(attrs)) => {
+                        // Lang didn't set these, so the worker's defaults are used
+                        assert_eq!(attrs.task_queue.as_ref().unwrap().name, "default-tq");
+                        assert_eq!(attrs.heartbeat_timeout, Some(prost_dur!(from_secs(5))));
+                        // Lang did set this one, so the worker default is ignored
+                        assert_eq!(
+                            attrs.schedule_to_close_timeout,
+                            Some(prost_dur!(from_secs(10)))
+                        );
+                    }
+                );
+            })
+            .then(move |wft| {
+                assert_eq!(
+                    wft.commands[0].command_type(),
+                    CommandType::CompleteWorkflowExecution
+                );
+            });
+    });
+
+    let mut worker = mock_sdk_cfg(mock_cfg, |cfg| {
+        cfg.activity_defaults = ActivityDefaultsBuilder::default()
+            .task_queue("default-tq")
+            .heartbeat_timeout(Duration::from_secs(5))
+            .schedule_to_close_timeout(Duration::from_secs(60))
+            .build()
+            .unwrap();
+    });
+    worker.register_wf(wf_type, |ctx: WfContext| async move {
+        ctx.activity(ActivityOptions {
+            activity_type: DEFAULT_ACTIVITY_TYPE.to_string(),
+            schedule_to_close_timeout: Some(Duration::from_secs(10)),
+            ..Default::default()
+        })
+        .await;
+        Ok(().into())
+    });
+    worker
+        .submit_wf(
+            wf_id.to_owned(),
+            wf_type.to_owned(),
+            vec![],
+            WorkflowOptions::default(),
+        )
+        .await
+        .unwrap();
+    worker.run_until_done().await.unwrap();
+}
@@ -4,7 +4,7 @@ use crate::{
         build_fake_sdk, canned_histories, mock_sdk, mock_sdk_cfg, mock_worker, single_hist_mock_sg,
         MockPollCfg, ResponseType,
     },
-    worker::client::mocks::mock_workflow_client,
+    worker::client::{mocks::mock_workflow_client, WorkflowTaskCompletion},
 };
 use temporal_client::WorkflowOptions;
 use temporal_sdk::{ChildWorkflowOptions, Signal, WfContext, WorkflowResult};
@@ -110,6 +110,78 @@ async fn cancel_child_workflow() {
     worker.run().await.unwrap();
 }
 
+#[rstest::rstest]
+#[case::abandon(ChildWorkflowCancellationType::Abandon)]
+#[case::wait_cancel_completed(ChildWorkflowCancellationType::WaitCancellationCompleted)]
+#[tokio::test]
+async fn cancel_type_decides_whether_cancel_is_requested_and_awaited(
+    #[case] cancel_type: ChildWorkflowCancellationType,
+    #[values(false, true)] replaying: bool,
+) {
+    let abandons = cancel_type == ChildWorkflowCancellationType::Abandon;
+    let t = if abandons {
+        canned_histories::single_child_workflow_abandon_cancelled("child-id-1")
+    } else {
+        canned_histories::single_child_workflow_cancelled("child-id-1")
+    };
+    let mock_cfg = if replaying {
+        MockPollCfg::from_resps(t, [ResponseType::AllHistory])
+    } else {
+        let mut mock_cfg = MockPollCfg::from_hist_builder(t);
+        mock_cfg.completion_asserts_from_expectations(|mut asserts| {
+            let types = |wft: &WorkflowTaskCompletion| -> Vec<_> {
+                wft.commands.iter().map(|c| c.command_type()).collect()
+            };
+            asserts.then(move |wft| {
+                assert_eq!(types(wft), [CommandType::StartChildWorkflowExecution]);
+            });
+            if abandons {
+                // Abandoning asks nothing of server, and the child resolves immediately
+                asserts.then(move |wft| {
+                    assert_eq!(types(wft), [CommandType::CompleteWorkflowExecution]);
+                });
+            } else {
+                // The parent must wait for the child to actually be cancelled
+                asserts
+                    .then(move |wft| {
+                        assert_eq!(
+                            types(wft),
+                            [CommandType::RequestCancelExternalWorkflowExecution]
+                        );
+                    })
+                    .then(move |wft| {
+                        assert_eq!(types(wft), [CommandType::CompleteWorkflowExecution]);
+                    });
+            }
+        });
+        mock_cfg
+    };
+
+    let mut worker = build_fake_sdk(mock_cfg);
+    worker.register_wf(DEFAULT_WORKFLOW_TYPE, move |ctx: WfContext| async move {
+        let child = ctx.child_workflow(ChildWorkflowOptions {
+            workflow_id: "child-id-1".to_string(),
+            workflow_type: "child".to_string(),
+            cancel_type,
+            ..Default::default()
+        });
+        let started = child
+            .start(&ctx)
+            .await
+            .into_started()
+            .expect("Child should get started");
+        started.cancel(&ctx);
+        let stat = started
+            .result()
+            .await
+            .status
+            .expect("child wf has a result");
+        assert_matches!(stat, child_workflow_result::Status::Cancelled(_));
+        Ok(().into())
+    });
+    worker.run().await.unwrap();
+}
+
 #[rstest::rstest]
 #[case::abandon(ChildWorkflowCancellationType::Abandon)]
 #[case::try_cancel(ChildWorkflowCancellationType::TryCancel)]
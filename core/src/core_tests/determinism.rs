@@ -5,6 +5,7 @@ use crate::{
     worker::client::mocks::mock_workflow_client,
 };
 use std::{
+    future::Future,
     sync::atomic::{AtomicBool, AtomicUsize, Ordering},
     time::Duration,
 };
@@ -273,6 +274,110 @@ async fn child_wf_id_or_type_change_is_nondeterministic(
     worker.run_until_done().await.unwrap();
 }
 
+/// Replays `t` with workflow code which no longer matches it, expecting the task to be failed once
+/// for nondeterminism with a message containing each of `should_contain`
+async fn replay_expecting_nondeterminism<F, Fut>(
+    t: TestHistoryBuilder,
+    should_contain: &'static [&'static str],
+    wf: F,
+) where
+    F: Fn(WfContext) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = WorkflowResult<()>> + Send + 'static,
+{
+    let wf_id = "fakeid";
+    let wf_type = DEFAULT_WORKFLOW_TYPE;
+    let mut mh = MockPollCfg::from_resp_batches(
+        wf_id,
+        t,
+        // Two polls are needed, since the first will fail
+        [ResponseType::AllHistory, ResponseType::AllHistory],
+        mock_workflow_client(),
+    );
+    mh.num_expected_fails = 1;
+    mh.expect_fail_wft_matcher = Box::new(move |_, cause, f| {
+        matches!(cause, WorkflowTaskFailedCause::NonDeterministicError)
+            && matches!(f, Some(Failure {
+                message,
+                ..
+            }) if should_contain.iter().all(|s| message.contains(s)))
+    });
+    let mut worker = mock_sdk_cfg(mh, |cfg| cfg.max_cached_workflows = 2);
+    worker.register_wf(wf_type.to_owned(), wf);
+    worker
+        .submit_wf(
+            wf_id.to_owned(),
+            wf_type.to_owned(),
+            vec![],
+            WorkflowOptions::default(),
+        )
+        .await
+        .unwrap();
+    worker.run_until_done().await.unwrap();
+}
+
+#[tokio::test]
+async fn timer_in_place_of_activity_is_nondeterministic() {
+    replay_expecting_nondeterminism(
+        canned_histories::single_activity("1"),
+        &["History event 5", "ActivityTaskScheduled", "StartTimer"],
+        |ctx: WfContext| async move {
+            ctx.timer(Duration::from_secs(1)).await;
+            Ok(().into())
+        },
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn reordered_activities_are_nondeterministic() {
+    let mut t = TestHistoryBuilder::default();
+    t.add_by_type(EventType::WorkflowExecutionStarted);
+    t.add_full_wf_task();
+    t.set_flags_first_wft(&[CoreInternalFlags::IdAndTypeDeterminismChecks as u32], &[]);
+    let first = t.add_activity_task_scheduled("first");
+    let second = t.add_activity_task_scheduled("second");
+    for scheduled in [first, second] {
+        let started = t.add_activity_task_started(scheduled);
+        t.add_activity_task_completed(scheduled, started, Default::default());
+    }
+    t.add_workflow_task_scheduled_and_started();
+
+    replay_expecting_nondeterminism(
+        t,
+        &["does not match activity id", "first"],
+        |ctx: WfContext| async move {
+            let act = |id: &str| {
+                ctx.activity(ActivityOptions {
+                    activity_id: Some(id.to_string()),
+                    activity_type: DEFAULT_ACTIVITY_TYPE.to_string(),
+                    ..Default::default()
+                })
+            };
+            tokio::join!(act("second"), act("first"));
+            Ok(().into())
+        },
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn nondeterminism_errors_include_recent_transitions() {
+    replay_expecting_nondeterminism(
+        canned_histories::single_timer("badid"),
+        &[
+            "Timer fired event did not have expected timer id 1",
+            "Recent machine transitions:",
+            "TimerMachine",
+            "StartCommandCreated -> StartCommandRecorded on event 5 (TimerStarted)",
+        ],
+        |ctx: WfContext| async move {
+            ctx.timer(Duration::from_secs(1)).await;
+            Ok(().into())
+        },
+    )
+    .await;
+}
+
 /// Repros a situation where if, upon completing a task there is some internal error which causes
 /// us to want to auto-fail the workflow task while there is also an outstanding eviction, the wf
 /// would get evicted but then try to send some info down the completion channel afterward, causing
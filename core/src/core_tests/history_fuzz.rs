@@ -0,0 +1,436 @@
+//! Property tests which replay randomly generated histories. Each history is built from a random
+//! mix of timers, activities, signals, and cancellations interleaved the way server would record
+//! them. A reference responder, which knows what commands produced the history, drives the
+//! workflow through replay. Replay must never hit a nondeterminism error, and every activation
+//! must contain exactly the jobs the history implies, in the order core promises to deliver them.
+//!
+//! The seed is fixed, so every run checks the same cases and a failure is always reproducible. Set
+//! [SEED_ENV_VAR] to explore others.
+
+use crate::{
+    errors::PollWfError,
+    prost_dur,
+    replay::{HistoryForReplay, ReplayWorkerInput},
+    test_help::test_worker_cfg,
+    Worker,
+};
+use futures_util::stream;
+use proptest::{
+    prelude::*,
+    sample::Index,
+    test_runner::{Config, RngAlgorithm, TestCaseError, TestRng, TestRunner},
+};
+use std::{
+    collections::{BTreeSet, VecDeque},
+    env,
+    time::Duration,
+};
+use temporal_sdk_core_api::Worker as WorkerTrait;
+use temporal_sdk_core_protos::{
+    coresdk::{
+        activity_result::{activity_resolution, ActivityResolution},
+        workflow_activation::{
+            remove_from_cache::EvictionReason, workflow_activation_job, ResolveActivity,
+            WorkflowActivation, WorkflowActivationJob,
+        },
+        workflow_commands::{
+            workflow_command, ActivityCancellationType, CancelTimer, CompleteWorkflowExecution,
+            RequestCancelActivity, ScheduleActivity, StartTimer,
+        },
+        workflow_completion::WorkflowActivationCompletion,
+    },
+    temporal::api::{enums::v1::EventType, history::v1::TimerCanceledEventAttributes},
+    TestHistoryBuilder, DEFAULT_ACTIVITY_TYPE,
+};
+
+/// Set to run the property test with a seed other than [DEFAULT_SEED]
+const SEED_ENV_VAR: &str = "TEMPORAL_HISTORY_FUZZ_SEED";
+const DEFAULT_SEED: u64 = 0x7e3b_0c1d_5a2f_9e48;
+const NUM_CASES: u32 = 64;
+const MAX_TASKS: usize = 8;
+const MAX_CHOICES_PER_STEP: usize = 4;
+
+/// Something the workflow may do when completing a workflow task
+#[derive(Debug, Clone)]
+enum CommandChoice {
+    StartTimer,
+    ScheduleActivity,
+    /// Cancel one of the timers which are running as of this task
+    CancelTimer(Index),
+    /// Request cancellation of one of the activities which are outstanding as of this task
+    CancelActivity(Index),
+}
+
+/// Something which may happen server-side between two workflow tasks
+#[derive(Debug, Clone)]
+enum ServerChoice {
+    /// Fire one of the running timers
+    FireTimer(Index),
+    /// Start and complete one of the outstanding activities
+    CompleteActivity(Index),
+    Signal,
+    CancelWorkflow,
+}
+
+/// The raw, unvalidated material a history is built from. Choices which don't make sense at the
+/// point they occur (ex: firing a timer when none are running) are skipped while building, which
+/// keeps every generated history valid while still allowing proptest to shrink freely.
+#[derive(Debug, Clone)]
+struct TaskChoices {
+    commands: Vec<CommandChoice>,
+    then: Vec<ServerChoice>,
+}
+
+fn command_choice() -> impl Strategy<Value = CommandChoice> {
+    prop_oneof![
+        3 => Just(CommandChoice::StartTimer),
+        3 => Just(CommandChoice::ScheduleActivity),
+        1 => any::<Index>().prop_map(CommandChoice::CancelTimer),
+        1 => any::<Index>().prop_map(CommandChoice::CancelActivity),
+    ]
+}
+
+fn server_choice() -> impl Strategy<Value = ServerChoice> {
+    prop_oneof![
+        3 => any::<Index>().prop_map(ServerChoice::FireTimer),
+        3 => any::<Index>().prop_map(ServerChoice::CompleteActivity),
+        1 => Just(ServerChoice::Signal),
+        1 => Just(ServerChoice::CancelWorkflow),
+    ]
+}
+
+fn history_choices() -> impl Strategy<Value = Vec<TaskChoices>> {
+    let task = (
+        prop::collection::vec(command_choice(), 0..MAX_CHOICES_PER_STEP),
+        prop::collection::vec(server_choice(), 0..MAX_CHOICES_PER_STEP),
+    )
+        .prop_map(|(commands, then)| TaskChoices { commands, then });
+    prop::collection::vec(task, 0..MAX_TASKS)
+}
+
+/// A job lang should see, reduced to what identifies it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExpectedJob {
+    InitializeWorkflow,
+    FireTimer(u32),
+    ActivityCompleted(u32),
+    ActivityCancelled(u32),
+    Signal,
+    CancelWorkflow,
+}
+
+impl ExpectedJob {
+    fn from_job(job: &WorkflowActivationJob) -> Option<Self> {
+        Some(match job.variant.as_ref()? {
+            workflow_activation_job::Variant::InitializeWorkflow(_) => Self::InitializeWorkflow,
+            workflow_activation_job::Variant::FireTimer(ft) => Self::FireTimer(ft.seq),
+            workflow_activation_job::Variant::ResolveActivity(ResolveActivity {
+                seq,
+                result: Some(ActivityResolution { status: Some(s) }),
+                ..
+            }) => match s {
+                activity_resolution::Status::Completed(_) => Self::ActivityCompleted(*seq),
+                activity_resolution::Status::Cancelled(_) => Self::ActivityCancelled(*seq),
+                _ => return None,
+            },
+            workflow_activation_job::Variant::SignalWorkflow(_) => Self::Signal,
+            workflow_activation_job::Variant::CancelWorkflow(_) => Self::CancelWorkflow,
+            _ => return None,
+        })
+    }
+
+    /// Where core puts this job relative to jobs of other kinds. Jobs of the same kind go in the
+    /// order their events appear in history.
+    fn ordinal(&self) -> u8 {
+        match self {
+            Self::InitializeWorkflow => 0,
+            Self::Signal => 1,
+            Self::FireTimer(_)
+            | Self::ActivityCompleted(_)
+            | Self::ActivityCancelled(_)
+            | Self::CancelWorkflow => 2,
+        }
+    }
+}
+
+/// What the reference responder expects to see on, and reply to, one workflow task
+#[derive(Debug, Default)]
+struct ExpectedTask {
+    /// In the order they must appear in the activation
+    jobs: Vec<ExpectedJob>,
+    commands: Vec<workflow_command::Variant>,
+    /// Activities this task's commands request cancellation of. They are try-cancelled, so lang
+    /// learns they are cancelled right away.
+    cancelled_activities: Vec<u32>,
+}
+
+/// Builds a valid history out of the provided choices, along with what the workflow must be sent
+/// and must reply with on each of its tasks for that history to be produced
+fn build_history(choices: &[TaskChoices]) -> (TestHistoryBuilder, Vec<ExpectedTask>) {
+    let mut t = TestHistoryBuilder::default();
+    t.add_by_type(EventType::WorkflowExecutionStarted);
+    t.add_full_wf_task();
+
+    let mut next_seq = 1;
+    // (seq, started event id)
+    let mut running_timers: Vec<(u32, i64)> = vec![];
+    // (seq, scheduled event id)
+    let mut outstanding_acts: Vec<(u32, i64)> = vec![];
+    let mut cancel_requested = false;
+    let mut tasks = vec![];
+    let mut next_jobs = vec![ExpectedJob::InitializeWorkflow];
+
+    for choice in choices {
+        let mut task = ExpectedTask {
+            jobs: std::mem::take(&mut next_jobs),
+            ..Default::default()
+        };
+        // Things started by this task can't be cancelled by it, but may resolve before the next
+        let mut new_timers = vec![];
+        let mut new_acts = vec![];
+        for cmd in &choice.commands {
+            match cmd {
+                CommandChoice::StartTimer => {
+                    let seq = next_seq;
+                    next_seq += 1;
+                    task.commands.push(
+                        StartTimer {
+                            seq,
+                            start_to_fire_timeout: Some(prost_dur!(from_secs(1))),
+                            summary: None,
+                        }
+                        .into(),
+                    );
+                    t.add_timer_started(seq.to_string());
+                    new_timers.push((seq, t.current_event_id()));
+                }
+                CommandChoice::ScheduleActivity => {
+                    let seq = next_seq;
+                    next_seq += 1;
+                    task.commands.push(
+                        ScheduleActivity {
+                            seq,
+                            activity_id: seq.to_string(),
+                            activity_type: DEFAULT_ACTIVITY_TYPE.to_string(),
+                            start_to_close_timeout: Some(prost_dur!(from_secs(5))),
+                            cancellation_type: ActivityCancellationType::TryCancel as i32,
+                            ..Default::default()
+                        }
+                        .into(),
+                    );
+                    new_acts.push((seq, t.add_activity_task_scheduled(seq.to_string())));
+                }
+                CommandChoice::CancelTimer(ix) if !running_timers.is_empty() => {
+                    let (seq, started_event_id) =
+                        running_timers.remove(ix.index(running_timers.len()));
+                    task.commands.push(CancelTimer { seq }.into());
+                    t.add(TimerCanceledEventAttributes {
+                        timer_id: seq.to_string(),
+                        started_event_id,
+                        ..Default::default()
+                    });
+                }
+                CommandChoice::CancelActivity(ix) if !outstanding_acts.is_empty() => {
+                    let (seq, scheduled_event_id) =
+                        outstanding_acts.remove(ix.index(outstanding_acts.len()));
+                    task.commands.push(RequestCancelActivity { seq }.into());
+                    task.cancelled_activities.push(seq);
+                    t.add_activity_task_cancel_requested(scheduled_event_id);
+                }
+                CommandChoice::CancelTimer(_) | CommandChoice::CancelActivity(_) => {}
+            }
+        }
+        running_timers.extend(new_timers);
+        outstanding_acts.extend(new_acts);
+
+        for server in &choice.then {
+            match server {
+                ServerChoice::FireTimer(ix) if !running_timers.is_empty() => {
+                    let (seq, started_event_id) =
+                        running_timers.remove(ix.index(running_timers.len()));
+                    t.add_timer_fired(started_event_id, seq.to_string());
+                    next_jobs.push(ExpectedJob::FireTimer(seq));
+                }
+                ServerChoice::CompleteActivity(ix) if !outstanding_acts.is_empty() => {
+                    let (seq, scheduled_event_id) =
+                        outstanding_acts.remove(ix.index(outstanding_acts.len()));
+                    let started_event_id = t.add_activity_task_started(scheduled_event_id);
+                    t.add_activity_task_completed(
+                        scheduled_event_id,
+                        started_event_id,
+                        b"hi".into(),
+                    );
+                    next_jobs.push(ExpectedJob::ActivityCompleted(seq));
+                }
+                ServerChoice::CancelWorkflow if !cancel_requested => {
+                    cancel_requested = true;
+                    t.add_cancel_requested();
+                    next_jobs.push(ExpectedJob::CancelWorkflow);
+                }
+                ServerChoice::Signal => {
+                    t.add_we_signaled("sig", vec![]);
+                    next_jobs.push(ExpectedJob::Signal);
+                }
+                _ => {}
+            }
+        }
+        // Server only schedules a new task if something happened
+        if next_jobs.is_empty() {
+            t.add_we_signaled("sig", vec![]);
+            next_jobs.push(ExpectedJob::Signal);
+        }
+        t.add_full_wf_task();
+        task.jobs.sort_by_key(ExpectedJob::ordinal);
+        tasks.push(task);
+    }
+
+    next_jobs.sort_by_key(ExpectedJob::ordinal);
+    tasks.push(ExpectedTask {
+        jobs: next_jobs,
+        commands: vec![CompleteWorkflowExecution { result: None }.into()],
+        cancelled_activities: vec![],
+    });
+    t.add_workflow_execution_completed();
+    (t, tasks)
+}
+
+/// Plays the part of a workflow which issued exactly the commands recorded in a history
+struct ReferenceResponder {
+    tasks: VecDeque<ExpectedTask>,
+    total_tasks: usize,
+    /// Try-cancelled activities whose cancellation lang hasn't been told about yet
+    awaiting_cancel_resolution: BTreeSet<u32>,
+}
+
+impl ReferenceResponder {
+    fn new(tasks: Vec<ExpectedTask>) -> Self {
+        Self {
+            total_tasks: tasks.len(),
+            tasks: tasks.into(),
+            awaiting_cancel_resolution: Default::default(),
+        }
+    }
+
+    fn respond(
+        &mut self,
+        act: &WorkflowActivation,
+    ) -> Result<Vec<workflow_command::Variant>, String> {
+        let mut jobs = vec![];
+        for job in &act.jobs {
+            match ExpectedJob::from_job(job) {
+                Some(ExpectedJob::ActivityCancelled(seq))
+                    if self.awaiting_cancel_resolution.remove(&seq) => {}
+                Some(j) => jobs.push(j),
+                None => return Err(format!("Unexpected job in activation: {job:?}")),
+            }
+        }
+        // Only resolutions of our own cancels, which don't correspond to a new workflow task
+        if jobs.is_empty() {
+            return Ok(vec![]);
+        }
+        let task_num = self.total_tasks - self.tasks.len() + 1;
+        let task = self
+            .tasks
+            .pop_front()
+            .ok_or_else(|| format!("Got activation after the final task: {act}"))?;
+        if jobs != task.jobs {
+            return Err(format!(
+                "Activation for task {task_num} of {} had jobs {jobs:?}, expected {:?}",
+                self.total_tasks, task.jobs
+            ));
+        }
+        self.awaiting_cancel_resolution
+            .extend(task.cancelled_activities);
+        Ok(task.commands)
+    }
+}
+
+async fn replay_with_reference_responder(choices: &[TaskChoices]) -> Result<(), String> {
+    let (t, tasks) = build_history(choices);
+    let hist = HistoryForReplay::new(
+        t.get_full_history_info().unwrap().into(),
+        "fuzzed".to_string(),
+    );
+    let worker = ReplayWorkerInput::new(test_worker_cfg().build().unwrap(), stream::iter([hist]))
+        .into_core_worker()
+        .map_err(|e| e.to_string())?;
+    let res = drive(&worker, ReferenceResponder::new(tasks)).await;
+    worker.shutdown().await;
+    res
+}
+
+async fn drive(worker: &Worker, mut responder: ReferenceResponder) -> Result<(), String> {
+    loop {
+        let act = match worker.poll_workflow_activation().await {
+            Ok(act) => act,
+            Err(PollWfError::ShutDown) => break,
+            Err(e) => return Err(format!("Poll failed: {e:?}")),
+        };
+        if let Some(reason) = act.eviction_reason() {
+            worker
+                .complete_workflow_activation(WorkflowActivationCompletion::empty(act.run_id))
+                .await
+                .map_err(|e| format!("{e:?}"))?;
+            if reason == EvictionReason::WorkflowExecutionEnding {
+                continue;
+            }
+            return Err(format!(
+                "Workflow evicted during replay ({reason}): {act:?}"
+            ));
+        }
+        let cmds = responder.respond(&act)?;
+        worker
+            .complete_workflow_activation(WorkflowActivationCompletion::from_cmds(act.run_id, cmds))
+            .await
+            .map_err(|e| format!("Completion was rejected: {e:?}"))?;
+    }
+    if !responder.tasks.is_empty() {
+        return Err(format!(
+            "Replay finished with {} workflow tasks never activated",
+            responder.tasks.len()
+        ));
+    }
+    Ok(())
+}
+
+#[test]
+fn replaying_generated_histories_is_deterministic() {
+    let seed = env::var(SEED_ENV_VAR)
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_SEED);
+    let mut seed_bytes = [0; 32];
+    for chunk in seed_bytes.chunks_mut(8) {
+        chunk.copy_from_slice(&seed.to_le_bytes());
+    }
+    let mut runner = TestRunner::new_with_rng(
+        Config {
+            cases: NUM_CASES,
+            failure_persistence: None,
+            ..Config::default()
+        },
+        TestRng::from_seed(RngAlgorithm::ChaCha, &seed_bytes),
+    );
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    let res = runner.run(&history_choices(), |choices| {
+        rt.block_on(async {
+            tokio::time::timeout(
+                Duration::from_secs(10),
+                replay_with_reference_responder(&choices),
+            )
+            .await
+            .unwrap_or_else(|_| Err("Replay did not finish".to_string()))
+        })
+        .map_err(TestCaseError::fail)
+    });
+    if let Err(e) = res {
+        panic!(
+            "Replaying generated histories failed with seed {seed} (reproduce with \
+             {SEED_ENV_VAR}={seed}): {e}"
+        );
+    }
+}
@@ -370,6 +370,54 @@ async fn local_act_retry_long_backoff_uses_timer() {
     worker.run_until_done().await.unwrap();
 }
 
+/// A local activity which only succeeded after retrying is recorded by a single marker carrying
+/// the final attempt. Replaying it must resolve from that marker, without running the activity.
+#[tokio::test]
+async fn local_act_retried_until_success_replays_from_marker() {
+    let mut t = TestHistoryBuilder::default();
+    t.add_by_type(EventType::WorkflowExecutionStarted);
+    t.add_full_wf_task();
+    t.add_local_activity_marker(1, "1", Some(b"echo".into()), None, |d| d.attempt = 3);
+    t.add_workflow_execution_completed();
+
+    let wf_id = "fakeid";
+    let mock = mock_workflow_client();
+    let mh = MockPollCfg::from_resp_batches(wf_id, t, [ResponseType::AllHistory], mock);
+    let mut worker = mock_sdk(mh);
+
+    worker.register_wf(
+        DEFAULT_WORKFLOW_TYPE.to_owned(),
+        |ctx: WfContext| async move {
+            let la_res = ctx
+                .local_activity(LocalActivityOptions {
+                    activity_type: DEFAULT_ACTIVITY_TYPE.to_string(),
+                    input: "hi".as_json_payload().expect("serializes fine"),
+                    ..Default::default()
+                })
+                .await;
+            assert_eq!(la_res.unwrap_ok_payload().data, b"echo");
+            Ok(().into())
+        },
+    );
+    let runs = Arc::new(AtomicUsize::new(0));
+    let runs_c = runs.clone();
+    worker.register_activity(DEFAULT_ACTIVITY_TYPE, move |_ctx: ActContext, _: String| {
+        runs_c.fetch_add(1, Ordering::SeqCst);
+        async { Result::<(), _>::Err(anyhow!("Must not run on replay").into()) }
+    });
+    worker
+        .submit_wf(
+            wf_id.to_owned(),
+            DEFAULT_WORKFLOW_TYPE.to_owned(),
+            vec![],
+            WorkflowOptions::default(),
+        )
+        .await
+        .unwrap();
+    worker.run_until_done().await.unwrap();
+    assert_eq!(runs.load(Ordering::SeqCst), 0);
+}
+
 #[tokio::test]
 async fn local_act_null_result() {
     let mut t = TestHistoryBuilder::default();
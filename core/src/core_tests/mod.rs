@@ -1,9 +1,11 @@
 mod activity_tasks;
 mod child_workflows;
 mod determinism;
+mod history_fuzz;
 mod local_activities;
 mod queries;
 mod replay_flag;
+mod replay_worker;
 mod updates;
 mod workers;
 mod workflow_cancels;
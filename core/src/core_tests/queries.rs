@@ -4,8 +4,9 @@ use crate::{
         MockPollCfg, MocksHolder, ResponseType, WorkerExt,
     },
     worker::{client::mocks::mock_workflow_client, LEGACY_QUERY_ID},
+    Worker, WorkerConfig,
 };
-use futures_util::stream;
+use futures_util::{stream, StreamExt};
 use std::{
     collections::{HashMap, VecDeque},
     time::Duration,
@@ -13,6 +14,7 @@ use std::{
 use temporal_sdk_core_api::Worker as WorkerTrait;
 use temporal_sdk_core_protos::{
     coresdk::{
+        activity_result::ActivityExecutionResult,
         workflow_activation::{
             remove_from_cache::EvictionReason, workflow_activation_job, WorkflowActivationJob,
         },
@@ -21,10 +23,11 @@ use temporal_sdk_core_protos::{
             ContinueAsNewWorkflowExecution, QueryResult, RequestCancelActivity,
         },
         workflow_completion::WorkflowActivationCompletion,
+        ActivityTaskCompletion,
     },
     temporal::api::{
         common::v1::Payload,
-        enums::v1::{CommandType, EventType},
+        enums::v1::{CommandType, EventType, WorkflowTaskFailedCause},
         failure::v1::Failure,
         history::v1::{history_event, ActivityTaskCancelRequestedEventAttributes, History},
         query::v1::WorkflowQuery,
@@ -35,8 +38,10 @@ use temporal_sdk_core_protos::{
     TestHistoryBuilder,
 };
 use temporal_sdk_core_test_utils::{
-    query_ok, schedule_activity_cmd, start_timer_cmd, WorkerTestHelpers,
+    query_ok, schedule_activity_cmd, schedule_local_activity_cmd, start_timer_cmd,
+    WorkerTestHelpers,
 };
+use tokio::{join, sync::oneshot};
 
 #[rstest::rstest]
 #[case::with_history(true)]
@@ -272,6 +277,27 @@ async fn legacy_query_failure_on_wft_failure() {
     core.shutdown().await;
 }
 
+#[tokio::test]
+async fn legacy_query_answered_when_its_task_is_auto_failed() {
+    let wfid = "fake_wf_id";
+    let mut t = TestHistoryBuilder::default();
+    t.add_by_type(EventType::WorkflowExecutionStarted);
+    // Applying this nonsense event fails the task before lang ever sees it
+    t.add_external_signal_completed(100);
+    t.add_full_wf_task();
+    let mut pr = hist_to_poll_resp(&t, wfid.to_owned(), ResponseType::AllHistory);
+    pr.query = Some(WorkflowQuery {
+        query_type: "query-type".to_string(),
+        query_args: Some(b"hi".into()),
+        header: None,
+    });
+    let mut mock = MockPollCfg::from_resp_batches(wfid, t, [pr], mock_workflow_client());
+    mock.num_expected_legacy_query_resps = 1;
+    let core = mock_worker(build_mock_pollers(mock));
+
+    core.drain_pollers_and_shutdown().await;
+}
+
 #[rstest::rstest]
 #[tokio::test]
 async fn query_failure_because_nondeterminism(#[values(true, false)] legacy: bool) {
@@ -1022,3 +1048,434 @@ async fn queries_arent_lost_in_buffer_void(#[values(false, true)] buffered_becau
 
     core.shutdown().await;
 }
+
+#[derive(Clone, Copy, Debug)]
+enum QueryResultsProvided {
+    Matched,
+    Missing,
+    Extra,
+}
+
+#[rstest::rstest]
+#[tokio::test]
+async fn new_query_results_reconciled_with_dispatched_queries(
+    #[values(
+        QueryResultsProvided::Matched,
+        QueryResultsProvided::Missing,
+        QueryResultsProvided::Extra
+    )]
+    provided: QueryResultsProvided,
+) {
+    let wfid = "fake_wf_id";
+    let t = canned_histories::single_timer("1");
+    let tasks = VecDeque::from(vec![{
+        let mut pr = hist_to_poll_resp(&t, wfid.to_owned(), 1.into());
+        pr.queries = HashMap::new();
+        for qid in ["q1", "q2"] {
+            pr.queries.insert(
+                qid.to_string(),
+                WorkflowQuery {
+                    query_type: "query-type".to_string(),
+                    query_args: Some(b"hi".into()),
+                    header: Default::default(),
+                },
+            );
+        }
+        pr
+    }]);
+    let mut mock_client = mock_workflow_client();
+    mock_client
+        .expect_complete_workflow_task()
+        .times(1)
+        .returning(move |resp| {
+            assert_eq!(resp.commands.len(), 1);
+            let mut ids: Vec<_> = resp
+                .query_responses
+                .iter()
+                .map(|qr| qr.query_id.as_str())
+                .collect();
+            ids.sort_unstable();
+            // Every dispatched query gets exactly one result, and nothing else is sent
+            assert_eq!(ids, vec!["q1", "q2"]);
+            let q2 = resp
+                .query_responses
+                .iter()
+                .find(|qr| qr.query_id == "q2")
+                .unwrap();
+            if matches!(provided, QueryResultsProvided::Missing) {
+                assert_matches!(q2.variant, Some(query_result::Variant::Failed(_)));
+            } else {
+                assert_matches!(q2.variant, Some(query_result::Variant::Succeeded(_)));
+            }
+            Ok(RespondWorkflowTaskCompletedResponse::default())
+        });
+
+    let mut mock = single_hist_mock_sg(wfid, t, tasks, mock_client, true);
+    mock.worker_cfg(|wc| wc.max_cached_workflows = 10);
+    let core = mock_worker(mock);
+
+    let task = core.poll_workflow_activation().await.unwrap();
+    core.complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
+        task.run_id,
+        start_timer_cmd(1, Duration::from_secs(1)),
+    ))
+    .await
+    .unwrap();
+
+    let task = core.poll_workflow_activation().await.unwrap();
+    assert_eq!(task.jobs.len(), 2);
+    let mut commands = vec![query_ok("q1", "hi")];
+    match provided {
+        QueryResultsProvided::Matched => commands.push(query_ok("q2", "hi")),
+        QueryResultsProvided::Missing => {}
+        QueryResultsProvided::Extra => {
+            commands.push(query_ok("q2", "hi"));
+            commands.push(query_ok("q3", "never asked"));
+            commands.push(query_ok("q1", "duplicate"));
+        }
+    }
+    core.complete_workflow_activation(WorkflowActivationCompletion::from_cmds(
+        task.run_id,
+        commands,
+    ))
+    .await
+    .unwrap();
+}
+
+/// Builds a worker whose workflow schedules a local activity in its first task, and which only
+/// receives a legacy query once the activity is being executed (and thus the completion which
+/// scheduled it is being held)
+fn query_during_held_completion_worker(
+    respond_query_matcher: fn(&QueryResult) -> bool,
+    cfg: impl FnOnce(&mut WorkerConfig),
+) -> (Worker, oneshot::Sender<()>) {
+    let wfid = "fake_wf_id";
+    let mut t = TestHistoryBuilder::default();
+    t.add_by_type(EventType::WorkflowExecutionStarted);
+    t.add_full_wf_task();
+    t.add_local_activity_result_marker(1, "1", "done".into());
+    t.add_workflow_execution_completed();
+
+    let first_task = hist_to_poll_resp(&t, wfid.to_owned(), 1.into()).resp;
+    let query_task = {
+        let mut pr = hist_to_poll_resp(&t, wfid.to_owned(), 1.into());
+        pr.query = Some(WorkflowQuery {
+            query_type: "q".to_string(),
+            ..Default::default()
+        });
+        pr.started_event_id = 0;
+        pr.resp
+    };
+    let (send_query, query_rx) = oneshot::channel();
+    let tasks = stream::iter([first_task]).chain(stream::once(async move {
+        let _ = query_rx.await;
+        query_task
+    }));
+
+    let mut mock = mock_workflow_client();
+    mock.expect_complete_workflow_task()
+        .times(1)
+        .returning(|_| Ok(Default::default()));
+    mock.expect_respond_legacy_query()
+        .times(1)
+        .withf(move |_, res| respond_query_matcher(res))
+        .returning(|_, _| Ok(Default::default()));
+    let mut mock = MocksHolder::from_wft_stream(mock, tasks);
+    mock.worker_cfg(|wc| {
+        wc.max_cached_workflows = 1;
+        cfg(wc);
+    });
+    (mock_worker(mock), send_query)
+}
+
+async fn schedule_la_and_deliver_query(
+    core: &Worker,
+    send_query: oneshot::Sender<()>,
+    la_runs_for: Duration,
+) {
+    let wf_fut = async {
+        let task = core.poll_workflow_activation().await.unwrap();
+        // Completing this is held until the local activity resolves
+        core.complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
+            task.run_id,
+            schedule_local_activity_cmd(
+                1,
+                "1",
+                ActivityCancellationType::TryCancel,
+                Duration::from_secs(60),
+            ),
+        ))
+        .await
+        .unwrap();
+    };
+    let act_fut = async {
+        let act_task = core.poll_activity_task().await.unwrap();
+        send_query.send(()).unwrap();
+        tokio::time::sleep(la_runs_for).await;
+        core.complete_activity_task(ActivityTaskCompletion {
+            task_token: act_task.task_token,
+            result: Some(ActivityExecutionResult::ok(vec![1].into())),
+        })
+        .await
+        .unwrap();
+    };
+    join!(wf_fut, act_fut);
+}
+
+#[tokio::test]
+async fn legacy_query_deferred_until_held_completion_resolves() {
+    let (core, send_query) = query_during_held_completion_worker(
+        |res| matches!(res.variant, Some(query_result::Variant::Succeeded(_))),
+        |_| {},
+    );
+    schedule_la_and_deliver_query(&core, send_query, Duration::from_millis(100)).await;
+
+    let task = core.poll_workflow_activation().await.unwrap();
+    assert_matches!(
+        task.jobs.as_slice(),
+        [WorkflowActivationJob {
+            variant: Some(workflow_activation_job::Variant::ResolveActivity(_)),
+        }]
+    );
+    core.complete_execution(&task.run_id).await;
+
+    // The query is only answered once the completion has been acknowledged
+    let task = core.poll_workflow_activation().await.unwrap();
+    assert_matches!(
+        task.jobs.as_slice(),
+        [WorkflowActivationJob {
+            variant: Some(workflow_activation_job::Variant::QueryWorkflow(q)),
+        }] => assert_eq!(q.query_id, LEGACY_QUERY_ID)
+    );
+    core.complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
+        task.run_id,
+        query_ok(LEGACY_QUERY_ID, "hi"),
+    ))
+    .await
+    .unwrap();
+    core.shutdown().await;
+}
+
+#[tokio::test]
+async fn legacy_query_deferred_too_long_is_failed() {
+    let (core, send_query) = query_during_held_completion_worker(
+        |res| matches!(res.variant, Some(query_result::Variant::Failed(_))),
+        |wc| wc.query_deferral_timeout = Some(Duration::from_millis(100)),
+    );
+    schedule_la_and_deliver_query(&core, send_query, Duration::from_millis(500)).await;
+
+    // The query was failed without lang ever seeing it
+    let task = core.poll_workflow_activation().await.unwrap();
+    assert_matches!(
+        task.jobs.as_slice(),
+        [WorkflowActivationJob {
+            variant: Some(workflow_activation_job::Variant::ResolveActivity(_)),
+        }]
+    );
+    core.complete_execution(&task.run_id).await;
+    core.shutdown().await;
+}
+
+#[tokio::test]
+async fn eager_legacy_query_answered_while_completion_unacknowledged() {
+    let wfid = "fake_wf_id";
+    let wft_timeout = Duration::from_millis(500);
+    let mut t = TestHistoryBuilder::default();
+    t.add_wfe_started_with_wft_timeout(wft_timeout);
+    t.add_full_wf_task();
+    // Task created by the WFT heartbeat
+    t.add_full_wf_task();
+    t.add_local_activity_result_marker(1, "1", "done".into());
+    t.add_workflow_execution_completed();
+
+    let first_task = hist_to_poll_resp(&t, wfid.to_owned(), 1.into()).resp;
+    let heartbeat_task = hist_to_poll_resp(&t, wfid.to_owned(), 2.into()).resp;
+    let query_task = {
+        let mut pr = hist_to_poll_resp(&t, wfid.to_owned(), 2.into());
+        pr.query = Some(WorkflowQuery {
+            query_type: "q".to_string(),
+            ..Default::default()
+        });
+        pr.started_event_id = 0;
+        pr.resp
+    };
+    let (send_query, query_rx) = oneshot::channel();
+    let tasks = stream::iter([first_task]).chain(stream::once(async move {
+        let _ = query_rx.await;
+        query_task
+    }));
+
+    let mut mock = mock_workflow_client();
+    let heartbeat_resp = RespondWorkflowTaskCompletedResponse {
+        workflow_task: Some(heartbeat_task),
+        ..Default::default()
+    };
+    mock.expect_complete_workflow_task()
+        .times(1)
+        .returning(move |_| Ok(heartbeat_resp.clone()));
+    mock.expect_complete_workflow_task()
+        .times(1)
+        .returning(|_| Ok(Default::default()));
+    mock.expect_respond_legacy_query()
+        .times(1)
+        .withf(|_, res| matches!(res.variant, Some(query_result::Variant::Succeeded(_))))
+        .returning(|_, _| Ok(Default::default()));
+    let mut mock = MocksHolder::from_wft_stream(mock, tasks);
+    mock.worker_cfg(|wc| {
+        wc.max_cached_workflows = 1;
+        wc.eager_query_answers = true;
+    });
+    let core = mock_worker(mock);
+
+    let (query_answered, query_answered_rx) = oneshot::channel();
+    let wf_fut = async {
+        let task = core.poll_workflow_activation().await.unwrap();
+        // Held until the WFT heartbeat, which server answers with a new WFT. Nothing done since
+        // has been acknowledged, and won't be until the local activity resolves.
+        core.complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
+            task.run_id,
+            schedule_local_activity_cmd(
+                1,
+                "1",
+                ActivityCancellationType::TryCancel,
+                Duration::from_secs(60),
+            ),
+        ))
+        .await
+        .unwrap();
+        // Give the new WFT a moment to be applied before the query arrives
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        send_query.send(()).unwrap();
+
+        let task = core.poll_workflow_activation().await.unwrap();
+        assert_matches!(
+            task.jobs.as_slice(),
+            [WorkflowActivationJob {
+                variant: Some(workflow_activation_job::Variant::QueryWorkflow(q)),
+            }] => assert_eq!(q.query_id, LEGACY_QUERY_ID)
+        );
+        core.complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
+            task.run_id,
+            query_ok(LEGACY_QUERY_ID, "hi"),
+        ))
+        .await
+        .unwrap();
+        query_answered.send(()).unwrap();
+    };
+    let act_fut = async {
+        let act_task = core.poll_activity_task().await.unwrap();
+        // The local activity only resolves once the query is answered, so it can't have waited
+        // for that
+        query_answered_rx.await.unwrap();
+        core.complete_activity_task(ActivityTaskCompletion {
+            task_token: act_task.task_token,
+            result: Some(ActivityExecutionResult::ok(vec![1].into())),
+        })
+        .await
+        .unwrap();
+    };
+    join!(wf_fut, act_fut);
+
+    let task = core.poll_workflow_activation().await.unwrap();
+    assert_matches!(
+        task.jobs.as_slice(),
+        [WorkflowActivationJob {
+            variant: Some(workflow_activation_job::Variant::ResolveActivity(_)),
+        }]
+    );
+    core.complete_execution(&task.run_id).await;
+    core.shutdown().await;
+}
+
+fn single_timer_with_query_mocks() -> MockPollCfg {
+    let wfid = "fake_wf_id";
+    let t = canned_histories::single_timer("1");
+    let tasks = VecDeque::from(vec![hist_to_poll_resp(&t, wfid.to_owned(), 1.into()), {
+        let mut pr = hist_to_poll_resp(&t, wfid.to_owned(), ResponseType::OneTask(2));
+        pr.queries = HashMap::from([(
+            "q1".to_string(),
+            WorkflowQuery {
+                query_type: "query-type".to_string(),
+                query_args: Some(b"hi".into()),
+                header: None,
+            },
+        )]);
+        pr
+    }]);
+    MockPollCfg::from_resp_batches(wfid, t, tasks, mock_workflow_client())
+}
+
+#[tokio::test]
+async fn verified_completions_are_sent() {
+    let mut mh = single_timer_with_query_mocks();
+    mh.completion_mock_fn = Some(Box::new(|c| {
+        if c.commands[0].command_type() == CommandType::CompleteWorkflowExecution {
+            assert_eq!(c.query_responses.len(), 1);
+        }
+        Ok(Default::default())
+    }));
+    let mut mock = build_mock_pollers(mh);
+    mock.worker_cfg(|wc| {
+        wc.max_cached_workflows = 10;
+        wc.verify_completions_by_replay = true;
+    });
+    let core = mock_worker(mock);
+
+    let task = core.poll_workflow_activation().await.unwrap();
+    core.complete_timer(&task.run_id, 1, Duration::from_secs(1))
+        .await;
+    let task = core.poll_workflow_activation().await.unwrap();
+    core.complete_execution(&task.run_id).await;
+    let task = core.poll_workflow_activation().await.unwrap();
+    core.complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
+        task.run_id,
+        query_ok("q1", "response"),
+    ))
+    .await
+    .unwrap();
+    core.shutdown().await;
+}
+
+/// A query handler which issues a command only has it sent because the run was cached. Replay
+/// never delivers the query, so the command would be missing from then on.
+#[tokio::test]
+async fn commands_answering_queries_fail_verification() {
+    let mut mh = single_timer_with_query_mocks();
+    mh.num_expected_fails = 1;
+    mh.expect_fail_wft_matcher = Box::new(|_, cause, f| {
+        matches!(cause, WorkflowTaskFailedCause::NonDeterministicError)
+            && matches!(f, Some(Failure { message, .. }) if message.contains("only queries"))
+    });
+    let mut mock = build_mock_pollers(mh);
+    mock.worker_cfg(|wc| {
+        wc.max_cached_workflows = 10;
+        wc.verify_completions_by_replay = true;
+    });
+    let core = mock_worker(mock);
+
+    let task = core.poll_workflow_activation().await.unwrap();
+    core.complete_timer(&task.run_id, 1, Duration::from_secs(1))
+        .await;
+    let task = core.poll_workflow_activation().await.unwrap();
+    core.complete_workflow_activation(WorkflowActivationCompletion::empty(task.run_id))
+        .await
+        .unwrap();
+    let task = core.poll_workflow_activation().await.unwrap();
+    assert_matches!(
+        task.jobs.as_slice(),
+        [WorkflowActivationJob {
+            variant: Some(workflow_activation_job::Variant::QueryWorkflow(_)),
+        }]
+    );
+    core.complete_workflow_activation(WorkflowActivationCompletion::from_cmds(
+        task.run_id,
+        vec![
+            query_ok("q1", "response"),
+            start_timer_cmd(2, Duration::from_secs(1)),
+        ],
+    ))
+    .await
+    .unwrap();
+    core.handle_eviction().await;
+    core.shutdown().await;
+}
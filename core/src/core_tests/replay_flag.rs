@@ -5,10 +5,11 @@ use crate::{
     },
     worker::{client::mocks::mock_workflow_client, LEGACY_QUERY_ID},
 };
+use parking_lot::Mutex;
 use rstest::{fixture, rstest};
-use std::{collections::VecDeque, time::Duration};
+use std::{collections::VecDeque, sync::Arc, time::Duration};
 use temporal_sdk::{WfContext, Worker, WorkflowFunction};
-use temporal_sdk_core_api::Worker as CoreWorker;
+use temporal_sdk_core_api::{worker::WorkerLifecycleEvent, Worker as CoreWorker};
 use temporal_sdk_core_protos::{
     coresdk::{
         workflow_activation::{workflow_activation_job, WorkflowActivationJob},
@@ -58,6 +59,35 @@ async fn replay_flag_is_correct(#[case] mut worker: Worker, #[case] num_timers:
     worker.run().await.unwrap();
 }
 
+#[tokio::test]
+async fn replay_progress_is_reported() {
+    let t = canned_histories::long_sequential_timers(500);
+    let total_events = t.get_full_history_info().unwrap().events().len() as u64;
+    let reports = Arc::new(Mutex::new(vec![]));
+    let reports_c = reports.clone();
+    let mut mock = build_mock_pollers(MockPollCfg::from_resps(t, [ResponseType::AllHistory]));
+    mock.worker_cfg(move |c| {
+        c.max_cached_workflows = 1;
+        c.replay_progress_interval = 500;
+        c.lifecycle_observer = Some(Arc::new(move |e: &WorkerLifecycleEvent| {
+            if let WorkerLifecycleEvent::ReplayProgress(p) = e {
+                reports_c.lock().push(p.clone())
+            }
+        }));
+    });
+    let mut worker = Worker::new_from_core(Arc::new(mock_worker(mock)), "replay_q".to_string());
+    worker.register_wf(DEFAULT_WORKFLOW_TYPE, timers_wf(500));
+    worker.run().await.unwrap();
+
+    let reports = reports.lock();
+    let applied: Vec<_> = reports.iter().map(|p| p.events_applied).collect();
+    assert_eq!(&applied[..4], &[500, 1000, 1500, 2000]);
+    for p in reports.iter() {
+        assert_eq!(p.total_known_events, total_events);
+        assert_eq!(p.history_pages_fetched, 0);
+    }
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn replay_flag_is_correct_partial_history() {
     let func = timers_wf(1);
@@ -0,0 +1,385 @@
+use crate::{
+    errors::PollWfError,
+    replay::{HistoryForReplay, ReplayOutcome, ReplayWorkerInput},
+    test_help::{canned_histories, test_worker_cfg, TEST_Q},
+};
+use futures_util::stream;
+use parking_lot::Mutex;
+use prost::Message;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use temporal_sdk_core_api::{worker::WorkerLifecycleEvent, Worker as WorkerTrait};
+use temporal_sdk_core_protos::{
+    coresdk::{
+        workflow_activation::workflow_activation_job,
+        workflow_commands::{ActivityCancellationType, CompleteWorkflowExecution},
+        workflow_completion::WorkflowActivationCompletion,
+    },
+    temporal::api::{
+        enums::v1::{EventType, WorkflowTaskFailedCause},
+        history::v1::History,
+    },
+    TestHistoryBuilder,
+};
+use temporal_sdk_core_test_utils::{schedule_activity_cmd, start_timer_cmd};
+
+fn history_info(t: TestHistoryBuilder) -> (History, String) {
+    let info = t.get_full_history_info().unwrap();
+    let run_id = info.orig_run_id().to_string();
+    (info.into(), run_id)
+}
+
+#[tokio::test]
+async fn replay_reports_the_outcome_of_each_history() {
+    let (timer_hist, timer_run) = history_info(canned_histories::single_timer("1"));
+    let (act_hist, act_run) = history_info(canned_histories::single_activity("1"));
+    let (mismatched_hist, mismatched_run) = history_info(canned_histories::single_timer("1"));
+    let histories = [
+        HistoryForReplay::from_encoded(&timer_hist.encode_to_vec(), "timer-wf").unwrap(),
+        HistoryForReplay::new(act_hist, "activity-wf".to_string()),
+        HistoryForReplay::new(mismatched_hist, "mismatched-wf".to_string()),
+    ];
+    let outcomes = Arc::new(Mutex::new(vec![]));
+    let outcomes_c = outcomes.clone();
+    let worker =
+        ReplayWorkerInput::new(test_worker_cfg().build().unwrap(), stream::iter(histories))
+            .with_outcome_callback(move |o| outcomes_c.lock().push(o.clone()))
+            .into_core_worker()
+            .unwrap();
+
+    let schedule_activity = || {
+        schedule_activity_cmd(
+            1,
+            TEST_Q,
+            "1",
+            ActivityCancellationType::TryCancel,
+            Duration::from_secs(60),
+            Duration::from_secs(60),
+        )
+    };
+    let mut wf_ids = HashMap::new();
+    loop {
+        let act = match worker.poll_workflow_activation().await {
+            Ok(act) => act,
+            Err(PollWfError::ShutDown) => break,
+            Err(e) => panic!("Poll failed: {e:?}"),
+        };
+        let cmds = match act.jobs[0].variant.as_ref().unwrap() {
+            workflow_activation_job::Variant::InitializeWorkflow(init) => {
+                wf_ids.insert(act.run_id.clone(), init.workflow_id.clone());
+                match init.workflow_id.as_str() {
+                    "timer-wf" => vec![start_timer_cmd(1, Duration::from_secs(1))],
+                    // The history of this one started a timer instead
+                    _ => vec![schedule_activity()],
+                }
+            }
+            workflow_activation_job::Variant::FireTimer(_)
+            | workflow_activation_job::Variant::ResolveActivity(_) => {
+                vec![CompleteWorkflowExecution { result: None }.into()]
+            }
+            workflow_activation_job::Variant::RemoveFromCache(_) => vec![],
+            other => panic!("Unexpected job {other:?} for {:?}", wf_ids.get(&act.run_id)),
+        };
+        worker
+            .complete_workflow_activation(WorkflowActivationCompletion::from_cmds(act.run_id, cmds))
+            .await
+            .unwrap();
+    }
+    worker.shutdown().await;
+
+    let outcomes = outcomes.lock();
+    assert_eq!(
+        outcomes[..2],
+        [
+            ReplayOutcome::Succeeded {
+                workflow_id: "timer-wf".to_string(),
+                run_id: timer_run,
+            },
+            ReplayOutcome::Succeeded {
+                workflow_id: "activity-wf".to_string(),
+                run_id: act_run,
+            },
+        ]
+    );
+    assert_matches!(
+        &outcomes[2..],
+        [ReplayOutcome::Failed {
+            workflow_id,
+            run_id,
+            cause: WorkflowTaskFailedCause::NonDeterministicError,
+            message,
+        }] if workflow_id == "mismatched-wf"
+            && *run_id == mismatched_run
+            && message.contains("TimerStarted")
+    );
+}
+
+#[tokio::test]
+async fn replay_progress_reaches_the_replayer_callback() {
+    let (hist, run_id) = history_info(canned_histories::single_timer("1"));
+    let mut cfg = test_worker_cfg()
+        .replay_progress_interval(2_usize)
+        .build()
+        .unwrap();
+    let observed = Arc::new(Mutex::new(0));
+    let observed_c = observed.clone();
+    cfg.lifecycle_observer = Some(Arc::new(move |e: &WorkerLifecycleEvent| {
+        if matches!(e, WorkerLifecycleEvent::ReplayProgress(_)) {
+            *observed_c.lock() += 1;
+        }
+    }));
+    let reports = Arc::new(Mutex::new(vec![]));
+    let reports_c = reports.clone();
+    let worker = ReplayWorkerInput::new(
+        cfg,
+        stream::iter([HistoryForReplay::new(hist, "timer-wf".to_string())]),
+    )
+    .with_progress_callback(move |p| reports_c.lock().push(p.clone()))
+    .into_core_worker()
+    .unwrap();
+
+    loop {
+        let act = match worker.poll_workflow_activation().await {
+            Ok(act) => act,
+            Err(PollWfError::ShutDown) => break,
+            Err(e) => panic!("Poll failed: {e:?}"),
+        };
+        let cmds = match act.jobs[0].variant.as_ref().unwrap() {
+            workflow_activation_job::Variant::InitializeWorkflow(_) => {
+                vec![start_timer_cmd(1, Duration::from_secs(1))]
+            }
+            workflow_activation_job::Variant::FireTimer(_) => {
+                vec![CompleteWorkflowExecution { result: None }.into()]
+            }
+            _ => vec![],
+        };
+        worker
+            .complete_workflow_activation(WorkflowActivationCompletion::from_cmds(act.run_id, cmds))
+            .await
+            .unwrap();
+    }
+    worker.shutdown().await;
+
+    let reports = reports.lock();
+    assert!(!reports.is_empty());
+    assert!(reports
+        .iter()
+        .all(|p| p.workflow_id == "timer-wf" && p.run_id == run_id));
+    assert!(reports
+        .windows(2)
+        .all(|w| w[0].events_applied < w[1].events_applied));
+    // The observer the config already had is still told about them
+    assert_eq!(*observed.lock(), reports.len());
+}
+
+/// A replayed reset run must look the same to lang as it did when it ran live: it is the run
+/// named by the reset, and it knows it was reset and why.
+#[tokio::test]
+async fn replayed_reset_runs_are_reported_as_reset_like_live_ones() {
+    let t = canned_histories::workflow_fails_with_reset_after_timer("1", "reset-run");
+    let orig_run_id = t.get_orig_run_id().to_string();
+    let hist = t.get_full_history_info().unwrap().into();
+    let worker = ReplayWorkerInput::new(
+        test_worker_cfg().build().unwrap(),
+        stream::iter([HistoryForReplay::new(hist, "wf".to_string())]),
+    )
+    .into_core_worker()
+    .unwrap();
+
+    let mut init = None;
+    let mut reset_failure = None;
+    loop {
+        let act = match worker.poll_workflow_activation().await {
+            Ok(act) => act,
+            Err(PollWfError::ShutDown) => break,
+            Err(e) => panic!("Unexpected poll error {e:?}"),
+        };
+        let mut cmds = vec![];
+        for job in act.jobs {
+            match job.variant.unwrap() {
+                workflow_activation_job::Variant::InitializeWorkflow(i) => {
+                    init = Some((act.run_id.clone(), i));
+                    cmds.push(start_timer_cmd(1, Duration::from_secs(1)));
+                }
+                workflow_activation_job::Variant::UpdateRandomSeed(u) => {
+                    reset_failure = u.reset_failure;
+                }
+                workflow_activation_job::Variant::FireTimer(_) => {
+                    cmds.push(CompleteWorkflowExecution { result: None }.into());
+                }
+                workflow_activation_job::Variant::RemoveFromCache(_) => {}
+                other => panic!("Unexpected job {other:?}"),
+            }
+        }
+        worker
+            .complete_workflow_activation(WorkflowActivationCompletion::from_cmds(act.run_id, cmds))
+            .await
+            .unwrap();
+    }
+    worker.shutdown().await;
+
+    let (run_id, init) = init.unwrap();
+    assert_eq!(run_id, "reset-run");
+    assert!(init.is_reset);
+    assert_eq!(init.reset_from_execution_run_id, orig_run_id);
+    assert_eq!(reset_failure.unwrap().message, "operator asked nicely");
+}
+
+#[tokio::test]
+async fn histories_replay_under_any_max_jobs_per_activation() {
+    let mut t = TestHistoryBuilder::default();
+    t.add_by_type(EventType::WorkflowExecutionStarted);
+    for i in 0..5 {
+        t.add_we_signaled(&format!("sig{i}"), vec![]);
+    }
+    t.add_full_wf_task();
+    t.add_timer_started("1".to_string());
+    t.add_timer_fired(t.current_event_id(), "1".to_string());
+    t.add_full_wf_task();
+    t.add_workflow_execution_completed();
+    let (hist, run_id) = history_info(t);
+
+    for (max_jobs, expected_sizes) in [(None, vec![6, 1]), (Some(2), vec![2, 2, 2, 1])] {
+        let mut cfg = test_worker_cfg();
+        if let Some(max) = max_jobs {
+            cfg.max_jobs_per_activation(max);
+        }
+        let outcomes = Arc::new(Mutex::new(vec![]));
+        let outcomes_c = outcomes.clone();
+        let worker = ReplayWorkerInput::new(
+            cfg.build().unwrap(),
+            stream::iter([HistoryForReplay::new(hist.clone(), "wf".to_string())]),
+        )
+        .with_outcome_callback(move |o| outcomes_c.lock().push(o.clone()))
+        .into_core_worker()
+        .unwrap();
+
+        let mut sizes = vec![];
+        loop {
+            let act = match worker.poll_workflow_activation().await {
+                Ok(act) => act,
+                Err(PollWfError::ShutDown) => break,
+                Err(e) => panic!("Poll failed: {e:?}"),
+            };
+            let mut cmds = vec![];
+            for job in &act.jobs {
+                match job.variant.as_ref().unwrap() {
+                    workflow_activation_job::Variant::SignalWorkflow(s)
+                        if s.signal_name == "sig4" =>
+                    {
+                        cmds.push(start_timer_cmd(1, Duration::from_secs(1)));
+                    }
+                    workflow_activation_job::Variant::FireTimer(_) => {
+                        cmds.push(CompleteWorkflowExecution { result: None }.into());
+                    }
+                    _ => {}
+                }
+            }
+            if !act.is_only_eviction() {
+                sizes.push(act.jobs.len());
+            }
+            worker
+                .complete_workflow_activation(WorkflowActivationCompletion::from_cmds(
+                    act.run_id, cmds,
+                ))
+                .await
+                .unwrap();
+        }
+        worker.shutdown().await;
+
+        assert_eq!(
+            sizes, expected_sizes,
+            "with max_jobs_per_activation {max_jobs:?}"
+        );
+        assert_eq!(
+            outcomes.lock().as_slice(),
+            [ReplayOutcome::Succeeded {
+                workflow_id: "wf".to_string(),
+                run_id: run_id.clone(),
+            }]
+        );
+    }
+}
+
+#[test]
+fn undecodable_histories_are_rejected() {
+    HistoryForReplay::from_encoded(b"not a history", "wf").unwrap_err();
+}
+
+/// Workflow processing happens on its own thread, so even a run with a huge amount of history to
+/// apply at once must not hold up other tasks on lang's runtime (ex: heartbeats and polls)
+#[tokio::test]
+async fn applying_huge_histories_does_not_starve_the_callers_runtime() {
+    const SIGNALS: usize = 10_000;
+    let mut t = TestHistoryBuilder::default();
+    t.add_by_type(EventType::WorkflowExecutionStarted);
+    t.add_full_wf_task();
+    for _ in 0..SIGNALS {
+        t.add_we_signaled("sig", vec![]);
+    }
+    t.add_full_wf_task();
+    t.add_workflow_execution_completed();
+    let (hist, _) = history_info(t);
+    let worker = ReplayWorkerInput::new(
+        test_worker_cfg().build().unwrap(),
+        stream::iter([HistoryForReplay::new(hist, "huge-wf".to_string())]),
+    )
+    .into_core_worker()
+    .unwrap();
+
+    let done = Arc::new(AtomicBool::new(false));
+    let done_c = done.clone();
+    let probe = tokio::spawn(async move {
+        let tick = Duration::from_millis(10);
+        let mut worst_lag = Duration::ZERO;
+        while !done_c.load(Ordering::Acquire) {
+            let start = Instant::now();
+            tokio::time::sleep(tick).await;
+            worst_lag = worst_lag.max(start.elapsed().saturating_sub(tick));
+        }
+        worst_lag
+    });
+
+    let mut signals_seen = 0;
+    loop {
+        let act = match worker.poll_workflow_activation().await {
+            Ok(act) => act,
+            Err(PollWfError::ShutDown) => break,
+            Err(e) => panic!("Poll failed: {e:?}"),
+        };
+        signals_seen += act
+            .jobs
+            .iter()
+            .filter(|j| {
+                matches!(
+                    j.variant,
+                    Some(workflow_activation_job::Variant::SignalWorkflow(_))
+                )
+            })
+            .count();
+        let cmds = if signals_seen == SIGNALS {
+            vec![CompleteWorkflowExecution { result: None }.into()]
+        } else {
+            vec![]
+        };
+        worker
+            .complete_workflow_activation(WorkflowActivationCompletion::from_cmds(act.run_id, cmds))
+            .await
+            .unwrap();
+    }
+    worker.shutdown().await;
+    done.store(true, Ordering::Release);
+
+    assert_eq!(signals_seen, SIGNALS);
+    let worst_lag = probe.await.unwrap();
+    assert!(
+        worst_lag < Duration::from_millis(500),
+        "Probe was delayed by up to {worst_lag:?}"
+    );
+}
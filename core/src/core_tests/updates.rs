@@ -1,11 +1,16 @@
 use crate::{
+    errors::PollWfError,
     prost_dur,
+    replay::{HistoryForReplay, ReplayOutcome, ReplayWorkerInput},
     test_help::{
-        build_mock_pollers, hist_to_poll_resp, mock_worker, MockPollCfg, PollWFTRespExt,
-        ResponseType,
+        build_mock_pollers, hist_to_poll_resp, mock_worker, test_worker_cfg, MockPollCfg,
+        PollWFTRespExt, ResponseType,
     },
     worker::client::mocks::mock_workflow_client,
 };
+use futures_util::stream;
+use parking_lot::Mutex;
+use std::sync::Arc;
 use temporal_sdk_core_api::Worker;
 use temporal_sdk_core_protos::{
     coresdk::{
@@ -325,3 +330,73 @@ async fn replay_with_signal_and_update_same_task() {
     .await
     .unwrap();
 }
+
+#[tokio::test]
+async fn replaying_accepted_and_rejected_updates_produces_no_new_commands() {
+    let mut t = TestHistoryBuilder::default();
+    t.add_by_type(EventType::WorkflowExecutionStarted);
+    t.add_full_wf_task();
+    // An update which came with this signal was rejected, which leaves nothing in history
+    t.add_we_signaled("hi", vec![]);
+    t.add_full_wf_task();
+    t.add_full_wf_task();
+    let accept_id = t.add_update_accepted("upd-accepted", "update");
+    t.add_we_signaled("bye", vec![]);
+    t.add_full_wf_task();
+    t.add_update_completed(accept_id);
+    t.add_workflow_execution_completed();
+    let run_id = t.get_orig_run_id().to_string();
+
+    let outcomes = Arc::new(Mutex::new(vec![]));
+    let outcomes_c = outcomes.clone();
+    let hist = HistoryForReplay::new(t.get_full_history_info().unwrap().into(), "fakeid".into());
+    let core = ReplayWorkerInput::new(test_worker_cfg().build().unwrap(), stream::iter([hist]))
+        .with_outcome_callback(move |o| outcomes_c.lock().push(o.clone()))
+        .into_core_worker()
+        .unwrap();
+
+    let mut updates_seen = vec![];
+    loop {
+        let task = match core.poll_workflow_activation().await {
+            Ok(task) => task,
+            Err(PollWfError::ShutDown) => break,
+            Err(e) => panic!("Poll failed: {e:?}"),
+        };
+        let cmds = match task.jobs[0].variant.as_ref().unwrap() {
+            workflow_activation_job::Variant::DoUpdate(d) => {
+                updates_seen.push(d.protocol_instance_id.clone());
+                vec![UpdateResponse {
+                    protocol_instance_id: d.protocol_instance_id.clone(),
+                    response: Some(Response::Accepted(())),
+                }
+                .into()]
+            }
+            workflow_activation_job::Variant::SignalWorkflow(s) if s.signal_name == "bye" => vec![
+                UpdateResponse {
+                    protocol_instance_id: "upd-accepted".to_string(),
+                    response: Some(Response::Completed(Payload::default())),
+                }
+                .into(),
+                CompleteWorkflowExecution { result: None }.into(),
+            ],
+            _ => vec![],
+        };
+        core.complete_workflow_activation(WorkflowActivationCompletion::from_cmds(
+            task.run_id,
+            cmds,
+        ))
+        .await
+        .unwrap();
+    }
+    core.shutdown().await;
+
+    // Only the accepted update is delivered, and the commands answering it match history
+    assert_eq!(updates_seen, ["upd-accepted"]);
+    assert_eq!(
+        outcomes.lock().as_slice(),
+        [ReplayOutcome::Succeeded {
+            workflow_id: "fakeid".to_string(),
+            run_id,
+        }]
+    );
+}
@@ -2,32 +2,43 @@ use crate::{
     prost_dur,
     test_help::{
         build_fake_worker, build_mock_pollers, canned_histories, mock_worker, test_worker_cfg,
-        MockPollCfg, MockWorkerInputs, MocksHolder, ResponseType, WorkerExt,
+        MockPollCfg, MockWorkerInputs, MocksHolder, ResponseType, WorkerExt, TEST_Q,
     },
     worker::{
         self,
         client::{
-            mocks::{mock_workflow_client, DEFAULT_TEST_CAPABILITIES, DEFAULT_WORKERS_REGISTRY},
+            mocks::{
+                mock_workflow_client, MockManualWorkerClient, DEFAULT_TEST_CAPABILITIES,
+                DEFAULT_WORKERS_REGISTRY,
+            },
             MockWorkerClient,
         },
     },
-    PollActivityError, PollWfError,
+    CoreBlocking, CoreRuntime, PollActivityError, PollWfError,
+};
+use futures_util::{stream, stream::StreamExt, FutureExt};
+use std::{cell::RefCell, sync::Arc, time::Duration};
+use temporal_sdk_core_api::{
+    errors::BlockingCallError, telemetry::TelemetryOptionsBuilder, Worker,
 };
-use futures_util::{stream, stream::StreamExt};
-use std::{cell::RefCell, time::Duration};
-use temporal_sdk_core_api::Worker;
 use temporal_sdk_core_protos::{
     coresdk::{
+        activity_result::ActivityExecutionResult,
+        activity_task::{activity_task, ActivityTask},
         workflow_activation::workflow_activation_job,
         workflow_commands::{workflow_command, CompleteWorkflowExecution, StartTimer},
         workflow_completion::WorkflowActivationCompletion,
+        ActivityTaskCompletion,
     },
     temporal::api::workflowservice::v1::{
-        PollWorkflowTaskQueueResponse, RespondWorkflowTaskCompletedResponse, ShutdownWorkerResponse,
+        PollActivityTaskQueueResponse, PollWorkflowTaskQueueResponse,
+        RespondActivityTaskCompletedResponse, RespondWorkflowTaskCompletedResponse,
+        ShutdownWorkerResponse,
     },
 };
 use temporal_sdk_core_test_utils::{start_timer_cmd, WorkerTestHelpers};
 use tokio::sync::{watch, Barrier};
+use tokio_util::sync::CancellationToken;
 
 #[tokio::test]
 async fn after_shutdown_of_worker_get_shutdown_err() {
@@ -59,6 +70,120 @@ async fn after_shutdown_of_worker_get_shutdown_err() {
     });
 }
 
+#[test]
+fn worker_runs_on_embedder_provided_current_thread_runtime() {
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let core_rt = CoreRuntime::init_with_runtime(
+        rt.handle().clone(),
+        TelemetryOptionsBuilder::default().build().unwrap(),
+    )
+    .unwrap();
+    assert!(!core_rt.owns_tokio_runtime());
+    // Not inside the embedder's runtime here, so the worker's tasks can only be spawned through
+    // the handle core was given
+    let worker = {
+        let _rg = core_rt.tokio_handle().enter();
+        build_fake_worker("fake_wf_id", canned_histories::single_timer("1"), [1])
+    };
+
+    rt.block_on(async {
+        let res = worker.poll_workflow_activation().await.unwrap();
+        assert_eq!(res.jobs.len(), 1);
+        tokio::join!(worker.shutdown(), async {
+            worker
+                .complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
+                    res.run_id,
+                    workflow_command::Variant::StartTimer(StartTimer {
+                        seq: 1,
+                        start_to_fire_timeout: Some(prost_dur!(from_secs(1))),
+                        summary: None,
+                    }),
+                ))
+                .await
+                .unwrap();
+            assert_matches!(
+                worker.poll_workflow_activation().await.unwrap_err(),
+                PollWfError::ShutDown
+            );
+        });
+    });
+    drop(worker);
+    drop(core_rt);
+    // Dropping the core runtime must leave the embedder's runtime usable
+    assert_eq!(rt.block_on(async { 1 }), 1);
+}
+
+#[test]
+fn blocking_facade_callable_from_plain_threads() {
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let core_rt = CoreRuntime::init_with_runtime(
+        rt.handle().clone(),
+        TelemetryOptionsBuilder::default().build().unwrap(),
+    )
+    .unwrap();
+    let core = {
+        let _rg = core_rt.tokio_handle().enter();
+        let mut mh = build_mock_pollers(MockPollCfg::from_resp_batches(
+            "fake_wf_id",
+            canned_histories::single_timer("1"),
+            [1],
+            mock_workflow_client(),
+        ));
+        mh.make_wft_stream_interminable();
+        Arc::new(CoreBlocking::new(&core_rt, mock_worker(mh)))
+    };
+
+    // Blocking inside the runtime is refused, rather than risking a deadlock
+    rt.block_on(async {
+        assert_matches!(
+            core.poll_workflow_activation_blocking(None).unwrap_err(),
+            BlockingCallError::CalledFromAsyncContext
+        );
+    });
+
+    let res = core
+        .poll_workflow_activation_blocking(Some(Duration::from_secs(5)))
+        .unwrap();
+    assert_eq!(res.jobs.len(), 1);
+    // The next poll has nothing to return once the activation is completed, from another thread
+    let poller = {
+        let core = core.clone();
+        std::thread::spawn(move || {
+            core.poll_workflow_activation_blocking(Some(Duration::from_millis(200)))
+        })
+    };
+    let completer = {
+        let core = core.clone();
+        std::thread::spawn(move || {
+            core.complete_workflow_activation_blocking(
+                WorkflowActivationCompletion::from_cmd(
+                    res.run_id,
+                    start_timer_cmd(1, Duration::from_secs(1)),
+                ),
+                None,
+            )
+        })
+    };
+    completer.join().unwrap().unwrap();
+    assert_matches!(
+        poller.join().unwrap().unwrap_err(),
+        BlockingCallError::Timeout(_)
+    );
+
+    let core = Arc::into_inner(core).unwrap();
+    std::thread::spawn(move || core.shutdown_blocking(Some(Duration::from_secs(5))))
+        .join()
+        .unwrap()
+        .unwrap();
+    drop(core_rt);
+}
+
 #[tokio::test]
 async fn shutdown_worker_can_complete_pending_activation() {
     let t = canned_histories::single_timer("1");
@@ -125,6 +250,63 @@ async fn worker_shutdown_during_poll_doesnt_deadlock() {
     worker.finalize_shutdown().await;
 }
 
+#[tokio::test]
+async fn slow_client_calls_are_aborted_after_grace_period() {
+    let (tx, rx) = watch::channel(false);
+    let stream = stream::unfold(rx, |mut rx| async move {
+        rx.changed().await.unwrap();
+        Some((
+            Ok(PollWorkflowTaskQueueResponse::default().try_into().unwrap()),
+            rx,
+        ))
+    });
+    let mw = MockWorkerInputs {
+        wft_stream: stream.boxed(),
+        act_poller: None,
+        config: test_worker_cfg()
+            .max_cached_workflows(1_usize)
+            .graceful_shutdown_period(Duration::from_millis(100))
+            .build()
+            .unwrap(),
+    };
+    let calls_cancelled = CancellationToken::new();
+    let mut mock_client = MockManualWorkerClient::new();
+    mock_client
+        .expect_capabilities()
+        .returning(|| Some(*DEFAULT_TEST_CAPABILITIES));
+    mock_client
+        .expect_workers()
+        .returning(|| DEFAULT_WORKERS_REGISTRY.clone());
+    mock_client.expect_is_mock().returning(|| true);
+    let cc = calls_cancelled.clone();
+    mock_client.expect_shutdown_worker().returning(move |_| {
+        let cc = cc.clone();
+        // Stands in for a call which would otherwise only give up at its timeout
+        async move {
+            cc.cancelled().await;
+            Err(tonic::Status::cancelled("aborted"))
+        }
+        .boxed()
+    });
+    let cc = calls_cancelled.clone();
+    mock_client
+        .expect_cancel_outstanding_calls()
+        .returning(move || cc.cancel());
+    let worker = mock_worker(MocksHolder::from_mock_worker(mock_client, mw));
+
+    let pollfut = worker.poll_workflow_activation();
+    let shutdownfut = async {
+        tokio::time::timeout(Duration::from_secs(5), worker.shutdown())
+            .await
+            .expect("Shutdown must not wait out the slow call");
+        let _ = tx.send(true);
+    };
+    let (pollres, _) = tokio::join!(pollfut, shutdownfut);
+    assert_matches!(pollres.unwrap_err(), PollWfError::ShutDown);
+    assert!(calls_cancelled.is_cancelled());
+    worker.finalize_shutdown().await;
+}
+
 #[tokio::test]
 async fn can_shutdown_local_act_only_worker_when_act_polling() {
     let t = canned_histories::single_timer("1");
@@ -314,6 +496,7 @@ async fn worker_shutdown_api(#[case] use_cache: bool, #[case] api_success: bool)
     mock.expect_workers()
         .returning(|| DEFAULT_WORKERS_REGISTRY.clone());
     mock.expect_is_mock().returning(|| true);
+    mock.expect_cancel_outstanding_calls().returning(|| ());
     if use_cache {
         if api_success {
             mock.expect_shutdown_worker()
@@ -362,3 +545,158 @@ async fn worker_shutdown_api(#[case] use_cache: bool, #[case] api_success: bool)
         );
     });
 }
+
+#[tokio::test]
+async fn shutting_down_one_worker_leaves_others_running() {
+    let worker_a = build_fake_worker("wf_a", canned_histories::single_timer("1"), [1, 2]);
+    let worker_b = build_fake_worker("wf_b", canned_histories::single_timer("1"), [1]);
+
+    for w in [&worker_a, &worker_b] {
+        let res = w.poll_workflow_activation().await.unwrap();
+        w.complete_workflow_activation(WorkflowActivationCompletion::from_cmds(
+            res.run_id,
+            vec![start_timer_cmd(1, Duration::from_secs(1))],
+        ))
+        .await
+        .unwrap();
+    }
+
+    tokio::join!(worker_b.shutdown(), async {
+        assert_matches!(
+            worker_b.poll_workflow_activation().await.unwrap_err(),
+            PollWfError::ShutDown
+        );
+    });
+    // Shutting down again is harmless
+    worker_b.shutdown().await;
+
+    // The other worker carries on polling as normal
+    let res = worker_a.poll_workflow_activation().await.unwrap();
+    assert_matches!(
+        res.jobs[0].variant,
+        Some(workflow_activation_job::Variant::FireTimer(_))
+    );
+    worker_a
+        .complete_workflow_activation(WorkflowActivationCompletion::from_cmds(
+            res.run_id,
+            vec![CompleteWorkflowExecution::default().into()],
+        ))
+        .await
+        .unwrap();
+    tokio::join!(worker_a.shutdown(), async {
+        assert_matches!(
+            worker_a.poll_workflow_activation().await.unwrap_err(),
+            PollWfError::ShutDown
+        );
+    });
+}
+
+#[tokio::test]
+async fn workers_for_different_task_queues_run_side_by_side() {
+    let act_worker = |task_queue: &str, num_tasks: u8| {
+        let mut mock_client = mock_workflow_client();
+        let expected_queue = task_queue.to_string();
+        mock_client
+            .expect_complete_activity_task()
+            .times(num_tasks as usize)
+            .returning(|_, _| Ok(RespondActivityTaskCompletedResponse::default()));
+        // Both queues hand out the same task tokens, so completions must not cross over
+        let tasks = (1..=num_tasks).map(move |i| PollActivityTaskQueueResponse {
+            task_token: vec![i],
+            activity_id: format!("{expected_queue}-{i}"),
+            ..Default::default()
+        });
+        let mut mock = MocksHolder::from_client_with_activities(mock_client, tasks.map(Into::into));
+        mock.worker_cfg(|wc| wc.task_queue = task_queue.to_string());
+        mock_worker(mock)
+    };
+    let activity_id = |task: &ActivityTask| match &task.variant {
+        Some(activity_task::Variant::Start(s)) => s.activity_id.clone(),
+        other => panic!("Unexpected activity task {other:?}"),
+    };
+    let worker_a = act_worker("queue_a", 2);
+    let worker_b = act_worker("queue_b", 1);
+
+    let (task_a, task_b) =
+        tokio::join!(worker_a.poll_activity_task(), worker_b.poll_activity_task());
+    let (task_a, task_b) = (task_a.unwrap(), task_b.unwrap());
+    assert_eq!(activity_id(&task_a), "queue_a-1");
+    assert_eq!(activity_id(&task_b), "queue_b-1");
+    for (w, task) in [(&worker_a, task_a), (&worker_b, task_b)] {
+        w.complete_activity_task(ActivityTaskCompletion {
+            task_token: task.task_token,
+            result: Some(ActivityExecutionResult::ok(vec![1].into())),
+        })
+        .await
+        .unwrap();
+    }
+
+    worker_b.drain_activity_poller_and_shutdown().await;
+    assert_matches!(
+        worker_b.poll_activity_task().await.unwrap_err(),
+        PollActivityError::ShutDown
+    );
+
+    // The other queue's buffered task is still delivered
+    let task = worker_a.poll_activity_task().await.unwrap();
+    assert_eq!(activity_id(&task), "queue_a-2");
+    worker_a
+        .complete_activity_task(ActivityTaskCompletion {
+            task_token: task.task_token,
+            result: Some(ActivityExecutionResult::ok(vec![1].into())),
+        })
+        .await
+        .unwrap();
+    worker_a.drain_activity_poller_and_shutdown().await;
+}
+
+#[tokio::test]
+async fn activity_only_worker_never_polls_workflows() {
+    // There is no workflow poll expectation, so the mock panics if workflows are ever polled
+    let mut mock_client = mock_workflow_client();
+    let mut task = Some(PollActivityTaskQueueResponse {
+        task_token: vec![1],
+        activity_id: "act".to_string(),
+        ..Default::default()
+    });
+    mock_client
+        .expect_poll_activity_task()
+        .returning(move |_, _| Ok(task.take().unwrap_or_default()));
+    mock_client
+        .expect_complete_activity_task()
+        .times(1)
+        .returning(|_, _| Ok(RespondActivityTaskCompletedResponse::default()));
+    let cfg = test_worker_cfg()
+        .activity_only(true)
+        .max_cached_workflows(10_usize)
+        .build()
+        .unwrap();
+    let worker = worker::Worker::new_test(cfg, mock_client);
+
+    assert_matches!(
+        worker.poll_workflow_activation().await.unwrap_err(),
+        PollWfError::WorkerConfiguredWithoutThisTaskType
+    );
+    let stats = worker.poll_stats(TEST_Q).unwrap();
+    assert_eq!(stats.workflow, None);
+    assert!(stats.activity.is_some());
+    // Without workflows, there's no sticky queue either
+    assert_eq!(worker.poll_stats("some-other-queue"), None);
+
+    let task = worker.poll_activity_task().await.unwrap();
+    worker
+        .complete_activity_task(ActivityTaskCompletion {
+            task_token: task.task_token,
+            result: Some(ActivityExecutionResult::ok(vec![1].into())),
+        })
+        .await
+        .unwrap();
+    tokio::time::timeout(Duration::from_secs(5), worker.shutdown())
+        .await
+        .expect("Shutdown must not wait on workflows");
+    assert_matches!(
+        worker.poll_activity_task().await.unwrap_err(),
+        PollActivityError::ShutDown
+    );
+    worker.finalize_shutdown().await;
+}
@@ -122,3 +122,44 @@ async fn immediate_cancel() {
     )
     .await;
 }
+
+#[rstest]
+#[case::incremental(vec![1.into(), 2.into(), ResponseType::AllHistory])]
+#[case::replay(vec![ResponseType::AllHistory])]
+#[tokio::test]
+async fn signals_after_cancel_req_are_delivered_and_cancel_is_sent_last(
+    #[case] hist_batches: Vec<ResponseType>,
+) {
+    let wfid = "fake_wf_id";
+    let t = canned_histories::timer_wf_cancel_req_then_signals_then_cancelled("sig");
+    let core = build_fake_worker(wfid, t, hist_batches);
+
+    poll_and_reply(
+        &core,
+        NonSticky,
+        &[
+            gen_assert_and_reply(
+                &job_assert!(workflow_activation_job::Variant::InitializeWorkflow(_)),
+                vec![start_timer_cmd(1, Duration::from_secs(100))],
+            ),
+            // Lang hasn't finished reacting to the cancel yet when the next task completes
+            gen_assert_and_reply(
+                &job_assert!(
+                    workflow_activation_job::Variant::SignalWorkflow(_),
+                    workflow_activation_job::Variant::CancelWorkflow(_)
+                ),
+                vec![],
+            ),
+            // The signal handler starts a timer, and the cancel command is issued ahead of it.
+            // Core must send the terminal command last for this to match history.
+            gen_assert_and_reply(
+                &job_assert!(workflow_activation_job::Variant::SignalWorkflow(_)),
+                vec![
+                    CancelWorkflowExecution::default().into(),
+                    start_timer_cmd(2, Duration::from_secs(1)),
+                ],
+            ),
+        ],
+    )
+    .await;
+}
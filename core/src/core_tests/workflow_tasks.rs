@@ -1,40 +1,53 @@
 use crate::{
     advance_fut,
     internal_flags::CoreInternalFlags,
-    job_assert,
+    job_assert, prost_dur,
+    protosext::ValidPollWFTQResponse,
     replay::TestHistoryBuilder,
+    telemetry::{construct_filter_string, telemetry_init},
     test_help::{
         build_fake_worker, build_mock_pollers, build_multihist_mock_sg, canned_histories,
         gen_assert_and_fail, gen_assert_and_reply, hist_to_poll_resp, mock_sdk, mock_sdk_cfg,
-        mock_worker, poll_and_reply, poll_and_reply_clears_outstanding_evicts, single_hist_mock_sg,
-        test_worker_cfg, FakeWfResponses, MockPollCfg, MocksHolder, ResponseType, WorkerExt,
+        mock_worker, mock_worker_with_telemetry, poll_and_reply,
+        poll_and_reply_clears_outstanding_evicts, single_hist_mock_sg, test_worker_cfg,
+        BufferedTelemetry, FakeWfResponses, MockPollCfg, MockWorkerInputs, MocksHolder,
+        ResponseType, WorkerExt,
         WorkflowCachingPolicy::{self, AfterEveryReply, NonSticky},
+        TEST_Q,
     },
     worker::{
-        client::mocks::{mock_manual_workflow_client, mock_workflow_client},
-        TunerBuilder,
+        client::{
+            mocks::{
+                mock_manual_workflow_client, mock_workflow_client, DEFAULT_TEST_CAPABILITIES,
+                DEFAULT_WORKERS_REGISTRY,
+            },
+            MockWorkerClient,
+        },
+        CacheSnapshot, TunerBuilder,
     },
-    Worker,
+    RunProcessingStats, Worker,
 };
-use futures_util::{stream, FutureExt};
+use futures_util::{stream, FutureExt, Stream, StreamExt};
 use mockall::TimesRange;
+use prost::Message;
 use rstest::{fixture, rstest};
 use std::{
     collections::{HashMap, HashSet, VecDeque},
     sync::{
-        atomic::{AtomicU64, AtomicUsize, Ordering},
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
         mpsc::sync_channel,
         Arc,
     },
-    time::Duration,
+    time::{Duration, SystemTime},
 };
 use temporal_client::WorkflowOptions;
 use temporal_sdk::{ActivityOptions, CancellableFuture, TimerOptions, WfContext};
 use temporal_sdk_core_api::{
-    errors::PollWfError,
+    errors::{CompleteWfError, PollWfError, WorkflowErrorType},
+    telemetry::{metrics::MetricUpdateVal, CoreTelemetry, Logger, TelemetryOptionsBuilder},
     worker::{
-        SlotMarkUsedContext, SlotReleaseContext, SlotReservationContext, SlotSupplier,
-        SlotSupplierPermit, WorkflowSlotKind,
+        ActivationDeadline, SlotMarkUsedContext, SlotReleaseContext, SlotReservationContext,
+        SlotSupplier, SlotSupplierPermit, WorkflowSlotKind,
     },
     Worker as WorkerTrait,
 };
@@ -43,27 +56,30 @@ use temporal_sdk_core_protos::{
         activity_result::{self as ar, activity_resolution, ActivityResolution},
         common::VersioningIntent,
         workflow_activation::{
-            remove_from_cache::EvictionReason, workflow_activation_job, FireTimer,
-            InitializeWorkflow, ResolveActivity, UpdateRandomSeed, WorkflowActivationJob,
+            remove_from_cache::EvictionReason, workflow_activation_job, ActivationMetadata,
+            FireTimer, InitializeWorkflow, ResolveActivity, UpdateRandomSeed, WorkflowActivation,
+            WorkflowActivationJob,
         },
         workflow_commands::{
-            update_response::Response, workflow_command, ActivityCancellationType, CancelTimer,
-            CompleteWorkflowExecution, ContinueAsNewWorkflowExecution, FailWorkflowExecution,
-            RequestCancelActivity, ScheduleActivity, SetPatchMarker, StartChildWorkflowExecution,
-            UpdateResponse,
+            query_result, update_response::Response, workflow_command, ActivityCancellationType,
+            CancelTimer, CompleteWorkflowExecution, ContinueAsNewWorkflowExecution,
+            FailWorkflowExecution, RequestCancelActivity, ScheduleActivity, SetPatchMarker,
+            StartChildWorkflowExecution, StartTimer, UpdateResponse,
         },
-        workflow_completion::WorkflowActivationCompletion,
+        workflow_completion::{workflow_activation_completion, WorkflowActivationCompletion},
     },
     default_act_sched, default_wes_attribs,
+    rng::random_checksum,
     temporal::api::{
         command::v1::command::Attributes,
-        common::v1::{Payload, RetryPolicy, WorkerVersionStamp},
-        enums::v1::{CommandType, EventType, WorkflowTaskFailedCause},
+        common::v1::{Header, Payload, RetryPolicy, WorkerVersionStamp},
+        enums::v1::{CommandType, EventType, TaskQueueKind, WorkflowTaskFailedCause},
         failure::v1::Failure,
         history::v1::{
-            history_event, TimerFiredEventAttributes,
-            WorkflowPropertiesModifiedExternallyEventAttributes,
+            history_event, ActivityPropertiesModifiedExternallyEventAttributes, History,
+            TimerFiredEventAttributes, WorkflowPropertiesModifiedExternallyEventAttributes,
         },
+        query::v1::WorkflowQuery,
         sdk::v1::UserMetadata,
         workflowservice::v1::{
             GetWorkflowExecutionHistoryResponse, RespondWorkflowTaskCompletedResponse,
@@ -71,12 +87,13 @@ use temporal_sdk_core_protos::{
     },
     DEFAULT_ACTIVITY_TYPE, DEFAULT_WORKFLOW_TYPE,
 };
-use temporal_sdk_core_test_utils::{fanout_tasks, start_timer_cmd, WorkerTestHelpers};
+use temporal_sdk_core_test_utils::{fanout_tasks, start_timer_cmd, WorkerTestHelpers, NAMESPACE};
 use tokio::{
     join,
-    sync::{Barrier, Semaphore},
+    sync::{Barrier, Notify, Semaphore},
     time,
 };
+use tracing::Level;
 
 #[fixture(hist_batches = &[])]
 fn single_timer_setup(hist_batches: &'static [usize]) -> Worker {
@@ -207,6 +224,70 @@ async fn parallel_timer_test_across_wf_bridge(hist_batches: &'static [usize]) {
     .await;
 }
 
+#[rstest(hist_batches, case::incremental(&[1, 2]), case::replay(&[2]))]
+#[tokio::test]
+async fn same_task_resolutions_follow_history_order(hist_batches: &'static [usize]) {
+    let mut t = TestHistoryBuilder::default();
+    t.add_by_type(EventType::WorkflowExecutionStarted);
+    t.add_full_wf_task();
+    let timer_1_started = t.add_by_type(EventType::TimerStarted);
+    let timer_2_started = t.add_by_type(EventType::TimerStarted);
+    let act_scheduled = t.add_activity_task_scheduled("3");
+    let act_started = t.add_activity_task_started(act_scheduled);
+    // Resolved in neither command nor sequence number order
+    t.add_timer_fired(timer_2_started, "2".to_string());
+    t.add_activity_task_completed(act_scheduled, act_started, Default::default());
+    t.add_timer_fired(timer_1_started, "1".to_string());
+    t.add_workflow_task_scheduled_and_started();
+    let core = build_fake_worker("fake_wf_id", t, hist_batches);
+
+    poll_and_reply(
+        &core,
+        NonSticky,
+        &[
+            gen_assert_and_reply(
+                &job_assert!(workflow_activation_job::Variant::InitializeWorkflow(_)),
+                vec![
+                    start_timer_cmd(1, Duration::from_secs(1)),
+                    start_timer_cmd(2, Duration::from_secs(1)),
+                    ScheduleActivity {
+                        seq: 3,
+                        activity_id: "3".to_string(),
+                        ..default_act_sched()
+                    }
+                    .into(),
+                ],
+            ),
+            gen_assert_and_reply(
+                &|res| {
+                    assert_matches!(
+                        res.jobs.as_slice(),
+                        [
+                            WorkflowActivationJob {
+                                variant: Some(workflow_activation_job::Variant::FireTimer(
+                                    FireTimer { seq: 2 }
+                                )),
+                            },
+                            WorkflowActivationJob {
+                                variant: Some(workflow_activation_job::Variant::ResolveActivity(
+                                    ResolveActivity { seq: 3, .. }
+                                )),
+                            },
+                            WorkflowActivationJob {
+                                variant: Some(workflow_activation_job::Variant::FireTimer(
+                                    FireTimer { seq: 1 }
+                                )),
+                            }
+                        ]
+                    );
+                },
+                vec![CompleteWorkflowExecution { result: None }.into()],
+            ),
+        ],
+    )
+    .await;
+}
+
 #[rstest(hist_batches, case::incremental(&[1, 2]), case::replay(&[2]))]
 #[tokio::test]
 async fn timer_cancel(hist_batches: &'static [usize]) {
@@ -246,6 +327,171 @@ async fn timer_cancel(hist_batches: &'static [usize]) {
     .await;
 }
 
+/// Starts timers 1, 2, and 3 (in that order) in the first workflow task, and fires 2
+fn racing_timer_cancel_start() -> TestHistoryBuilder {
+    let mut t = TestHistoryBuilder::default();
+    t.add_by_type(EventType::WorkflowExecutionStarted);
+    t.add_full_wf_task();
+    for id in 1..=3 {
+        t.add_timer_started(id.to_string());
+    }
+    t.add_timer_fired(6, "2".to_string());
+    t
+}
+
+#[rstest(hist_batches, case::incremental(&[1, 2, 3]), case::replay(&[3]))]
+#[tokio::test]
+async fn timer_fired_after_cancel_sent_is_not_delivered(hist_batches: &'static [usize]) {
+    // The cancel for timer 1 is sent in the second task, but the timer fires before server
+    // processes it, so the cancel has no effect and history has the fire rather than a cancel.
+    //  9: EVENT_TYPE_WORKFLOW_TASK_SCHEDULED
+    // 10: EVENT_TYPE_WORKFLOW_TASK_STARTED
+    // 11: EVENT_TYPE_WORKFLOW_TASK_COMPLETED
+    // 12: EVENT_TYPE_TIMER_FIRED (1)
+    // 13: EVENT_TYPE_TIMER_FIRED (3)
+    let mut t = racing_timer_cancel_start();
+    t.add_full_wf_task();
+    t.add_timer_fired(5, "1".to_string());
+    t.add_timer_fired(7, "3".to_string());
+    t.add_full_wf_task();
+    t.add_workflow_execution_completed();
+    let core = build_fake_worker("fake_wf_id", t, hist_batches);
+
+    poll_and_reply(
+        &core,
+        NonSticky,
+        &[
+            gen_assert_and_reply(
+                &job_assert!(workflow_activation_job::Variant::InitializeWorkflow(_)),
+                vec![
+                    start_timer_cmd(1, Duration::from_secs(500)),
+                    start_timer_cmd(2, Duration::from_secs(1)),
+                    start_timer_cmd(3, Duration::from_secs(2)),
+                ],
+            ),
+            gen_assert_and_reply(
+                &job_assert!(workflow_activation_job::Variant::FireTimer(FireTimer {
+                    seq: 2
+                })),
+                vec![CancelTimer { seq: 1 }.into()],
+            ),
+            // Lang already resolved timer 1 as cancelled, so must only hear about timer 3
+            gen_assert_and_reply(
+                &job_assert!(workflow_activation_job::Variant::FireTimer(FireTimer {
+                    seq: 3
+                })),
+                vec![CompleteWorkflowExecution { result: None }.into()],
+            ),
+        ],
+    )
+    .await;
+}
+
+#[rstest(hist_batches, case::incremental(&[1, 2, 3]), case::replay(&[3]))]
+#[tokio::test]
+async fn timer_fired_before_cancelling_task_started_wins(hist_batches: &'static [usize]) {
+    // Timer 1 fires right after timer 2, before the task for timer 2 starts. Lang hears about both
+    // fires at once, and its cancel of timer 1 is ignored rather than sent.
+    //  9: EVENT_TYPE_TIMER_FIRED (1)
+    // 10: EVENT_TYPE_WORKFLOW_TASK_SCHEDULED
+    // 11: EVENT_TYPE_WORKFLOW_TASK_STARTED
+    // 12: EVENT_TYPE_WORKFLOW_TASK_COMPLETED
+    // 13: EVENT_TYPE_TIMER_FIRED (3)
+    let mut t = racing_timer_cancel_start();
+    t.add_timer_fired(5, "1".to_string());
+    t.add_full_wf_task();
+    t.add_timer_fired(7, "3".to_string());
+    t.add_full_wf_task();
+    t.add_workflow_execution_completed();
+    let core = build_fake_worker("fake_wf_id", t, hist_batches);
+
+    poll_and_reply(
+        &core,
+        NonSticky,
+        &[
+            gen_assert_and_reply(
+                &job_assert!(workflow_activation_job::Variant::InitializeWorkflow(_)),
+                vec![
+                    start_timer_cmd(1, Duration::from_secs(500)),
+                    start_timer_cmd(2, Duration::from_secs(1)),
+                    start_timer_cmd(3, Duration::from_secs(2)),
+                ],
+            ),
+            gen_assert_and_reply(
+                &job_assert!(
+                    workflow_activation_job::Variant::FireTimer(FireTimer { seq: 2 }),
+                    workflow_activation_job::Variant::FireTimer(FireTimer { seq: 1 })
+                ),
+                vec![CancelTimer { seq: 1 }.into()],
+            ),
+            gen_assert_and_reply(
+                &job_assert!(workflow_activation_job::Variant::FireTimer(FireTimer {
+                    seq: 3
+                })),
+                vec![CompleteWorkflowExecution { result: None }.into()],
+            ),
+        ],
+    )
+    .await;
+}
+
+#[rstest(hist_batches, case::incremental(&[1, 2, 3]), case::replay(&[3]))]
+#[tokio::test]
+async fn timer_fired_before_cancel_recorded_wins(hist_batches: &'static [usize]) {
+    // Timer 1 fires while the task which would cancel it is in flight, and server rejects that
+    // task's completion. Lang hears about the fire along with whatever prompted the cancel.
+    //  9: EVENT_TYPE_WORKFLOW_TASK_SCHEDULED
+    // 10: EVENT_TYPE_WORKFLOW_TASK_STARTED
+    // 11: EVENT_TYPE_WORKFLOW_TASK_FAILED
+    // 12: EVENT_TYPE_TIMER_FIRED (1)
+    // 13: EVENT_TYPE_WORKFLOW_TASK_SCHEDULED
+    // 14: EVENT_TYPE_WORKFLOW_TASK_STARTED
+    // 15: EVENT_TYPE_WORKFLOW_TASK_COMPLETED
+    // 16: EVENT_TYPE_TIMER_FIRED (3)
+    let mut t = racing_timer_cancel_start();
+    t.add_workflow_task_scheduled_and_started();
+    t.add_workflow_task_failed_with_failure(
+        WorkflowTaskFailedCause::UnhandledCommand,
+        Default::default(),
+    );
+    t.add_timer_fired(5, "1".to_string());
+    t.add_full_wf_task();
+    t.add_timer_fired(7, "3".to_string());
+    t.add_full_wf_task();
+    t.add_workflow_execution_completed();
+    let core = build_fake_worker("fake_wf_id", t, hist_batches);
+
+    poll_and_reply(
+        &core,
+        NonSticky,
+        &[
+            gen_assert_and_reply(
+                &job_assert!(workflow_activation_job::Variant::InitializeWorkflow(_)),
+                vec![
+                    start_timer_cmd(1, Duration::from_secs(500)),
+                    start_timer_cmd(2, Duration::from_secs(1)),
+                    start_timer_cmd(3, Duration::from_secs(2)),
+                ],
+            ),
+            // Cancelling the already fired timer is ignored, and no cancel command is sent
+            gen_assert_and_reply(
+                &job_assert!(
+                    workflow_activation_job::Variant::FireTimer(FireTimer { seq: 2 }),
+                    workflow_activation_job::Variant::FireTimer(FireTimer { seq: 1 })
+                ),
+                vec![CancelTimer { seq: 1 }.into()],
+            ),
+            gen_assert_and_reply(
+                &job_assert!(workflow_activation_job::Variant::FireTimer(FireTimer {
+                    seq: 3
+                })),
+                vec![CompleteWorkflowExecution { result: None }.into()],
+            ),
+        ],
+    )
+    .await;
+}
+
 #[rstest(hist_batches, case::incremental(&[1, 2]), case::replay(&[2]))]
 #[tokio::test]
 async fn scheduled_activity_cancellation_try_cancel(hist_batches: &'static [usize]) {
@@ -701,7 +947,7 @@ async fn workflow_update_random_seed_on_workflow_reset() {
                         res.jobs.as_slice(),
                         [WorkflowActivationJob {
                             variant: Some(workflow_activation_job::Variant::UpdateRandomSeed(
-                                UpdateRandomSeed{randomness_seed})),
+                                UpdateRandomSeed{randomness_seed, reset_failure})),
                         },
                             WorkflowActivationJob {
                             variant: Some(workflow_activation_job::Variant::FireTimer(_),),
@@ -709,6 +955,8 @@ async fn workflow_update_random_seed_on_workflow_reset() {
                         ] => {
                             assert_ne!(randomness_seed_from_start.load(Ordering::SeqCst),
                                       *randomness_seed);
+                            assert_eq!(reset_failure.as_ref().unwrap().message,
+                                       "operator asked nicely");
                         }
                     );
                 },
@@ -720,81 +968,558 @@ async fn workflow_update_random_seed_on_workflow_reset() {
 }
 
 #[tokio::test]
-async fn cancel_timer_before_sent_wf_bridge() {
+async fn random_checksums_must_match_latest_seed() {
     let wfid = "fake_wf_id";
-    let cancel_timer_id = 1;
+    let t = canned_histories::workflow_fails_with_reset_after_timer(
+        "1",
+        "86E39A5F-AE31-4626-BDFE-398EE072D156",
+    );
+    let mut mock = single_hist_mock_sg(wfid, t, [2], mock_workflow_client(), true);
+    mock.worker_cfg(|wc| wc.strict_command_validation = true);
+    let core = mock_worker(mock);
+    let with_checksum = |run_id: &str, cmd: workflow_command::Variant, checksum| {
+        let mut completion = WorkflowActivationCompletion::from_cmd(run_id.to_string(), cmd);
+        if let Some(workflow_activation_completion::Status::Successful(s)) = &mut completion.status
+        {
+            s.random_checksum = checksum;
+        }
+        completion
+    };
 
-    let mut t = TestHistoryBuilder::default();
-    t.add_by_type(EventType::WorkflowExecutionStarted);
-    t.add_full_wf_task();
-    t.add_workflow_execution_completed();
+    let activation = core.poll_workflow_activation().await.unwrap();
+    let first_seed = assert_matches!(
+        activation.jobs.as_slice(),
+        [WorkflowActivationJob {
+            variant: Some(workflow_activation_job::Variant::InitializeWorkflow(
+                InitializeWorkflow { randomness_seed, .. }
+            )),
+        }] => *randomness_seed
+    );
+    let timer = start_timer_cmd(1, Duration::from_secs(1));
+    let err = core
+        .complete_workflow_activation(with_checksum(
+            &activation.run_id,
+            timer.clone(),
+            random_checksum(first_seed.wrapping_add(1)),
+        ))
+        .await
+        .unwrap_err();
+    assert_matches!(err, CompleteWfError::MalformedWorkflowCompletion { reason, .. }
+                         if reason.contains("random checksum"));
+    core.complete_workflow_activation(with_checksum(
+        &activation.run_id,
+        timer,
+        random_checksum(first_seed),
+    ))
+    .await
+    .unwrap();
 
-    let core = build_fake_worker(wfid, t, [1]);
+    // After the reset, only the new seed's checksum is accepted
+    let activation = core.poll_workflow_activation().await.unwrap();
+    let new_seed = assert_matches!(
+        activation.jobs.as_slice(),
+        [WorkflowActivationJob {
+            variant: Some(workflow_activation_job::Variant::UpdateRandomSeed(
+                UpdateRandomSeed { randomness_seed, .. }
+            )),
+        }, ..] => *randomness_seed
+    );
+    let complete: workflow_command::Variant = CompleteWorkflowExecution { result: None }.into();
+    core.complete_workflow_activation(with_checksum(
+        &activation.run_id,
+        complete.clone(),
+        random_checksum(first_seed),
+    ))
+    .await
+    .unwrap_err();
+    core.complete_workflow_activation(with_checksum(
+        &activation.run_id,
+        complete,
+        random_checksum(new_seed),
+    ))
+    .await
+    .unwrap();
+    core.shutdown().await;
+}
 
-    poll_and_reply(
-        &core,
-        NonSticky,
-        &[gen_assert_and_reply(
-            &job_assert!(workflow_activation_job::Variant::InitializeWorkflow(_)),
-            vec![
-                start_timer_cmd(cancel_timer_id, Duration::from_secs(1)),
-                CancelTimer {
-                    seq: cancel_timer_id,
-                }
-                .into(),
-                CompleteWorkflowExecution { result: None }.into(),
-            ],
-        )],
-    )
-    .await;
+fn reactivation_request(run_id: &str) -> WorkflowActivationCompletion {
+    let mut completion = WorkflowActivationCompletion::empty(run_id);
+    if let Some(workflow_activation_completion::Status::Successful(s)) = &mut completion.status {
+        s.request_reactivation = true;
+    }
+    completion
 }
 
-#[rstest]
-#[case::no_evict_inc(&[1, 2, 2], NonSticky)]
-#[case::no_evict(&[2, 2], NonSticky)]
 #[tokio::test]
-async fn complete_activation_with_failure(
-    #[case] batches: &'static [usize],
-    #[case] evict: WorkflowCachingPolicy,
-) {
+async fn requested_reactivation_is_issued_without_completing_task() {
     let wfid = "fake_wf_id";
-    let timer_id = 1;
+    let t = canned_histories::single_timer("1");
+    let mut mh = MockPollCfg::from_resp_batches(wfid, t, [1, 2], mock_workflow_client());
+    mh.completion_asserts_from_expectations(|mut asserts| {
+        asserts
+            .then(|wft| {
+                assert_eq!(wft.commands.len(), 1);
+                assert_eq!(wft.commands[0].command_type(), CommandType::StartTimer);
+            })
+            .then(|wft| {
+                assert_eq!(
+                    wft.commands[0].command_type(),
+                    CommandType::CompleteWorkflowExecution
+                );
+            });
+    });
+    let core = mock_worker(build_mock_pollers(mh));
 
-    let hist =
-        canned_histories::workflow_fails_with_failure_after_timer(timer_id.to_string().as_str());
-    let mock_sg = build_multihist_mock_sg(
-        vec![FakeWfResponses {
-            wf_id: wfid.to_string(),
-            hist,
-            response_batches: batches.iter().map(Into::into).collect(),
-        }],
-        true,
-        1,
-    );
-    let core = mock_worker(mock_sg);
+    let activation = core.poll_workflow_activation().await.unwrap();
+    core.complete_workflow_activation(reactivation_request(&activation.run_id))
+        .await
+        .unwrap();
+    let reactivation = core.poll_workflow_activation().await.unwrap();
+    assert_eq!(reactivation.run_id, activation.run_id);
+    assert!(reactivation.jobs.is_empty());
+    assert!(!reactivation.is_replaying);
+    core.complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
+        activation.run_id,
+        start_timer_cmd(1, Duration::from_secs(1)),
+    ))
+    .await
+    .unwrap();
 
-    poll_and_reply(
-        &core,
-        evict,
-        &[
-            gen_assert_and_reply(
-                &|_| {},
-                vec![start_timer_cmd(timer_id, Duration::from_secs(1))],
-            ),
-            gen_assert_and_fail(&|_| {}),
-            gen_assert_and_reply(
-                &job_assert!(workflow_activation_job::Variant::FireTimer(_)),
-                vec![CompleteWorkflowExecution { result: None }.into()],
-            ),
-        ],
-    )
-    .await;
-    core.shutdown().await;
+    let activation = core.poll_workflow_activation().await.unwrap();
+    assert_matches!(
+        activation.jobs.as_slice(),
+        [WorkflowActivationJob {
+            variant: Some(workflow_activation_job::Variant::FireTimer(_)),
+        }]
+    );
+    core.complete_execution(&activation.run_id).await;
+    core.drain_pollers_and_shutdown().await;
 }
 
-#[rstest(hist_batches, case::incremental(&[1, 2]), case::replay(&[2]))]
 #[tokio::test]
-async fn simple_timer_fail_wf_execution(hist_batches: &'static [usize]) {
+async fn reactivation_requests_are_limited_per_task() {
+    let wfid = "fake_wf_id";
+    let t = canned_histories::single_timer("1");
+    let mut mh = MockPollCfg::from_resp_batches(wfid, t, [1], mock_workflow_client());
+    mh.completion_asserts_from_expectations(|mut asserts| {
+        asserts.then(|wft| assert!(wft.commands.is_empty()));
+    });
+    let core = mock_worker(build_mock_pollers(mh));
+
+    let activation = core.poll_workflow_activation().await.unwrap();
+    let run_id = activation.run_id;
+    // Ten reactivations are granted per task, after which the task is completed as normal
+    for _ in 0..10 {
+        core.complete_workflow_activation(reactivation_request(&run_id))
+            .await
+            .unwrap();
+        let reactivation = core.poll_workflow_activation().await.unwrap();
+        assert!(reactivation.jobs.is_empty());
+    }
+    core.complete_workflow_activation(reactivation_request(&run_id))
+        .await
+        .unwrap();
+    core.drain_pollers_and_shutdown().await;
+}
+
+#[tokio::test]
+async fn activations_carry_correlation_ids_into_completion_spans() {
+    let telem = telemetry_init(
+        TelemetryOptionsBuilder::default()
+            .logging(Logger::Forward {
+                filter: construct_filter_string(Level::DEBUG, Level::WARN),
+            })
+            .build()
+            .unwrap(),
+    )
+    .unwrap();
+    let _g = tracing::subscriber::set_default(telem.trace_subscriber().unwrap());
+
+    let t = canned_histories::single_timer("1");
+    let mh = MockPollCfg::from_resp_batches("fake_wf_id", t, [1, 2], mock_workflow_client());
+    let core = mock_worker(build_mock_pollers(mh));
+
+    let activation = core.poll_workflow_activation().await.unwrap();
+    let correlation_id = activation.correlation_id.clone();
+    assert_eq!(correlation_id.len(), 26);
+    // Echoing some other activation's id is rejected, and the error names the real one
+    let err = core
+        .complete_workflow_activation(
+            WorkflowActivationCompletion::from_cmd(
+                &activation.run_id,
+                start_timer_cmd(1, Duration::from_secs(1)),
+            )
+            .with_correlation_id("not-this-one"),
+        )
+        .await
+        .unwrap_err();
+    assert_matches!(
+        err,
+        CompleteWfError::InvalidCompletion { correlation_id: id, .. }
+            if id == correlation_id
+    );
+    core.complete_workflow_activation(
+        WorkflowActivationCompletion::from_cmd(
+            &activation.run_id,
+            start_timer_cmd(1, Duration::from_secs(1)),
+        )
+        .with_correlation_id(&correlation_id),
+    )
+    .await
+    .unwrap();
+
+    let activation = core.poll_workflow_activation().await.unwrap();
+    assert_ne!(activation.correlation_id, correlation_id);
+    core.complete_execution(&activation.run_id).await;
+    core.drain_pollers_and_shutdown().await;
+
+    let logs = telem.fetch_buffered_logs();
+    let with_message = |msg: &str| {
+        logs.iter()
+            .filter(|l| l.message.contains(msg))
+            .map(|l| l.fields.get("correlation_id").cloned())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        with_message("Sending activation to lang"),
+        [
+            Some(correlation_id.clone().into()),
+            Some(activation.correlation_id.clone().into())
+        ]
+    );
+    assert_eq!(
+        with_message("Sending responses to server")[0],
+        Some(correlation_id.into())
+    );
+}
+
+#[tokio::test]
+async fn retries_of_applied_completions_succeed_without_reapplying() {
+    let t = canned_histories::single_timer("1");
+    let mh = MockPollCfg::from_resp_batches("fake_wf_id", t, [1, 2], mock_workflow_client());
+    let core = mock_worker(build_mock_pollers(mh));
+
+    let activation = core.poll_workflow_activation().await.unwrap();
+    let completion = || {
+        WorkflowActivationCompletion::from_cmd(
+            &activation.run_id,
+            start_timer_cmd(1, Duration::from_secs(1)),
+        )
+        .with_correlation_id(&activation.correlation_id)
+    };
+    core.complete_workflow_activation(completion())
+        .await
+        .unwrap();
+    // Lang lost the result of the first attempt, and tries again
+    core.complete_workflow_activation(completion())
+        .await
+        .unwrap();
+
+    // The timer was only started once, so the run carries on as normal
+    let activation = core.poll_workflow_activation().await.unwrap();
+    assert_matches!(
+        activation.jobs.as_slice(),
+        [WorkflowActivationJob {
+            variant: Some(workflow_activation_job::Variant::FireTimer(_)),
+        }]
+    );
+    core.complete_execution(&activation.run_id).await;
+    core.drain_pollers_and_shutdown().await;
+}
+
+#[tokio::test]
+async fn differing_retries_of_applied_completions_are_rejected() {
+    let t = canned_histories::single_timer("1");
+    let mh = MockPollCfg::from_resp_batches("fake_wf_id", t, [1, 2], mock_workflow_client());
+    let core = mock_worker(build_mock_pollers(mh));
+
+    let activation = core.poll_workflow_activation().await.unwrap();
+    let completion = |seq| {
+        WorkflowActivationCompletion::from_cmd(
+            &activation.run_id,
+            start_timer_cmd(seq, Duration::from_secs(1)),
+        )
+    };
+    core.complete_workflow_activation(
+        completion(1).with_correlation_id(&activation.correlation_id),
+    )
+    .await
+    .unwrap();
+    let err = core
+        .complete_workflow_activation(completion(2).with_correlation_id(&activation.correlation_id))
+        .await
+        .unwrap_err();
+    assert_matches!(
+        err,
+        CompleteWfError::InvalidCompletion { reason, correlation_id, .. }
+            if reason.contains("different completion was already applied")
+                && correlation_id == activation.correlation_id
+    );
+    // Without the correlation id it can't be told apart from a completion of an activation which
+    // was never issued
+    let err = core
+        .complete_workflow_activation(completion(2))
+        .await
+        .unwrap_err();
+    assert_matches!(
+        err,
+        CompleteWfError::InvalidCompletion { reason, .. }
+            if reason.contains("no outstanding activation")
+    );
+
+    let activation = core.poll_workflow_activation().await.unwrap();
+    core.complete_execution(&activation.run_id).await;
+    core.drain_pollers_and_shutdown().await;
+}
+
+#[tokio::test]
+async fn activation_spans_carry_task_details() {
+    let telem = telemetry_init(
+        TelemetryOptionsBuilder::default()
+            .logging(Logger::Forward {
+                filter: construct_filter_string(Level::DEBUG, Level::WARN),
+            })
+            .build()
+            .unwrap(),
+    )
+    .unwrap();
+    let _g = tracing::subscriber::set_default(telem.trace_subscriber().unwrap());
+
+    let t = canned_histories::single_timer("1");
+    let mh = MockPollCfg::from_resp_batches("fake_wf_id", t, [1, 2], mock_workflow_client());
+    let core = mock_worker(build_mock_pollers(mh));
+
+    let activation = core.poll_workflow_activation().await.unwrap();
+    core.complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
+        &activation.run_id,
+        start_timer_cmd(1, Duration::from_secs(1)),
+    ))
+    .await
+    .unwrap();
+    core.complete_execution(&activation.run_id).await;
+    core.drain_pollers_and_shutdown().await;
+
+    let logs = telem.fetch_buffered_logs();
+    let first_with_message = |msg: &str| {
+        logs.iter()
+            .find(|l| l.message.contains(msg))
+            .unwrap_or_else(|| panic!("No log line containing {msg:?}"))
+    };
+    let delivered = first_with_message("Sending activation to lang");
+    let completed = first_with_message("Sending responses to server");
+    // Each is logged from within the one span covering that leg of the activation
+    assert_eq!(delivered.span_contexts, ["next_workflow_activation"]);
+    assert_eq!(completed.span_contexts, ["complete_workflow_activation"]);
+    for log in [delivered, completed] {
+        assert_eq!(
+            log.fields.get("run_id"),
+            Some(&activation.run_id.as_str().into())
+        );
+        assert_eq!(log.fields.get("workflow_id"), Some(&"fake_wf_id".into()));
+        assert_eq!(log.fields.get("task_queue"), Some(&TEST_Q.into()));
+        assert_eq!(log.fields.get("attempt"), Some(&1.into()));
+    }
+    let task_token = delivered.fields.get("task_token").unwrap();
+    assert_eq!(task_token.as_str().unwrap().len(), 16);
+    assert_eq!(completed.fields.get("task_token"), Some(task_token));
+}
+
+#[rstest]
+#[case::over_limit(Some(64), true)]
+#[case::unlimited(None, false)]
+#[tokio::test]
+async fn oversized_payload_rejected_only_when_limited(
+    #[case] limit: Option<usize>,
+    #[case] over_limit: bool,
+) {
+    let mut t = TestHistoryBuilder::default();
+    t.add_by_type(EventType::WorkflowExecutionStarted);
+    t.add_full_wf_task();
+    t.add_workflow_execution_completed();
+    let mut mock = single_hist_mock_sg("fake_wf_id", t, [1], mock_workflow_client(), true);
+    mock.worker_cfg(|wc| {
+        wc.outbound_payload_limit = limit;
+    });
+    let core = mock_worker(mock);
+    let complete_with = |run_id: &str, result: Vec<u8>| {
+        WorkflowActivationCompletion::from_cmd(
+            run_id.to_string(),
+            CompleteWorkflowExecution {
+                result: Some(result.into()),
+            }
+            .into(),
+        )
+    };
+
+    let activation = core.poll_workflow_activation().await.unwrap();
+    let res = core
+        .complete_workflow_activation(complete_with(&activation.run_id, vec![0; 100]))
+        .await;
+    if over_limit {
+        assert_matches!(
+            res,
+            Err(CompleteWfError::PayloadTooLarge {
+                command: "CompleteWorkflowExecution",
+                limit: 64,
+                ..
+            })
+        );
+        // Nothing was sent, so the run can still complete with a payload which fits
+        core.complete_workflow_activation(complete_with(&activation.run_id, vec![0; 2]))
+            .await
+            .unwrap();
+    } else {
+        res.unwrap();
+    }
+    core.drain_pollers_and_shutdown().await;
+}
+
+#[tokio::test]
+async fn oversized_activation_completions_are_warned_about() {
+    let telem = BufferedTelemetry::with_options(|o| {
+        o.logging(Logger::Forward {
+            filter: construct_filter_string(Level::WARN, Level::WARN),
+        });
+    });
+    let _g = tracing::subscriber::set_default(telem.trace_subscriber().unwrap());
+
+    let mut t = TestHistoryBuilder::default();
+    t.add_by_type(EventType::WorkflowExecutionStarted);
+    t.add_full_wf_task();
+    t.add_workflow_execution_completed();
+    let mut mock = single_hist_mock_sg("fake_wf_id", t, [1], mock_workflow_client(), true);
+    mock.worker_cfg(|wc| wc.message_size_warn_threshold = Some(500));
+    let core = mock_worker_with_telemetry(mock, &telem);
+
+    let activation = core.poll_workflow_activation().await.unwrap();
+    let activation_size = activation.encoded_len();
+    let completion = WorkflowActivationCompletion::from_cmd(
+        activation.run_id,
+        CompleteWorkflowExecution {
+            result: Some(vec![0; 1000].into()),
+        }
+        .into(),
+    );
+    let completion_size = completion.encoded_len();
+    core.complete_workflow_activation(completion).await.unwrap();
+    core.drain_pollers_and_shutdown().await;
+
+    let sizes: Vec<_> = telem
+        .updates()
+        .into_iter()
+        .filter(|(name, _, _)| name.ends_with("message_size"))
+        .map(|(_, attrs, update)| {
+            assert_eq!(attrs.get("task_queue").map(String::as_str), Some(TEST_Q));
+            match update {
+                MetricUpdateVal::Value(v) => (attrs["message_type"].clone(), v as usize),
+                other => panic!("Unexpected histogram update {other:?}"),
+            }
+        })
+        .collect();
+    assert_eq!(
+        sizes,
+        [
+            ("workflow_activation".to_string(), activation_size),
+            (
+                "workflow_activation_completion".to_string(),
+                completion_size
+            ),
+        ]
+    );
+    // Only the completion is over the threshold
+    let warnings: Vec<_> = telem
+        .fetch_buffered_logs()
+        .into_iter()
+        .filter(|l| l.message.contains("message size warning threshold"))
+        .collect();
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].message.starts_with("Activation completion"));
+    assert_eq!(
+        warnings[0].fields.get("workflow_type"),
+        Some(&DEFAULT_WORKFLOW_TYPE.into())
+    );
+    assert_eq!(
+        warnings[0].fields.get("size"),
+        Some(&(completion_size as u64).into())
+    );
+}
+
+#[tokio::test]
+async fn cancel_timer_before_sent_wf_bridge() {
+    let wfid = "fake_wf_id";
+    let cancel_timer_id = 1;
+
+    let mut t = TestHistoryBuilder::default();
+    t.add_by_type(EventType::WorkflowExecutionStarted);
+    t.add_full_wf_task();
+    t.add_workflow_execution_completed();
+
+    let core = build_fake_worker(wfid, t, [1]);
+
+    poll_and_reply(
+        &core,
+        NonSticky,
+        &[gen_assert_and_reply(
+            &job_assert!(workflow_activation_job::Variant::InitializeWorkflow(_)),
+            vec![
+                start_timer_cmd(cancel_timer_id, Duration::from_secs(1)),
+                CancelTimer {
+                    seq: cancel_timer_id,
+                }
+                .into(),
+                CompleteWorkflowExecution { result: None }.into(),
+            ],
+        )],
+    )
+    .await;
+}
+
+#[rstest]
+#[case::no_evict_inc(&[1, 2, 2], NonSticky)]
+#[case::no_evict(&[2, 2], NonSticky)]
+#[tokio::test]
+async fn complete_activation_with_failure(
+    #[case] batches: &'static [usize],
+    #[case] evict: WorkflowCachingPolicy,
+) {
+    let wfid = "fake_wf_id";
+    let timer_id = 1;
+
+    let hist =
+        canned_histories::workflow_fails_with_failure_after_timer(timer_id.to_string().as_str());
+    let mock_sg = build_multihist_mock_sg(
+        vec![FakeWfResponses {
+            wf_id: wfid.to_string(),
+            hist,
+            response_batches: batches.iter().map(Into::into).collect(),
+        }],
+        true,
+        1,
+    );
+    let core = mock_worker(mock_sg);
+
+    poll_and_reply(
+        &core,
+        evict,
+        &[
+            gen_assert_and_reply(
+                &|_| {},
+                vec![start_timer_cmd(timer_id, Duration::from_secs(1))],
+            ),
+            gen_assert_and_fail(&|_| {}),
+            gen_assert_and_reply(
+                &job_assert!(workflow_activation_job::Variant::FireTimer(_)),
+                vec![CompleteWorkflowExecution { result: None }.into()],
+            ),
+        ],
+    )
+    .await;
+    core.shutdown().await;
+}
+
+#[rstest(hist_batches, case::incremental(&[1, 2]), case::replay(&[2]))]
+#[tokio::test]
+async fn simple_timer_fail_wf_execution(hist_batches: &'static [usize]) {
     let wfid = "fake_wf_id";
     let timer_id = 1;
 
@@ -911,30 +1636,183 @@ async fn workflow_failures_only_reported_once() {
     .await;
 }
 
+/// The cause and failure lang provides when failing an activation are what server is told the
+/// workflow task failed with
 #[tokio::test]
-async fn max_wft_respected() {
-    let total_wfs = 100;
-    let wf_ids: Vec<_> = (0..total_wfs).map(|i| format!("fake-wf-{i}")).collect();
-    let hists = wf_ids.iter().map(|wf_id| {
-        let hist = canned_histories::single_timer("1");
-        FakeWfResponses {
-            wf_id: wf_id.to_string(),
-            hist,
-            response_batches: vec![1.into(), 2.into()],
-        }
-    });
-    let mh = MockPollCfg::new(hists.into_iter().collect(), true, 0);
-    let mut worker = mock_sdk_cfg(mh, |cfg| {
-        cfg.max_cached_workflows = total_wfs as usize;
-        cfg.max_outstanding_workflow_tasks = Some(1);
-    });
-    let active_count: &'static _ = Box::leak(Box::new(Semaphore::new(1)));
-    worker.register_wf(DEFAULT_WORKFLOW_TYPE, move |ctx: WfContext| async move {
-        drop(
-            active_count
-                .try_acquire()
-                .expect("No multiple concurrent workflow tasks!"),
-        );
+async fn lang_failure_reported_with_its_cause_and_stack_trace() {
+    let wfid = "fake_wf_id";
+    let t = canned_histories::single_timer("1");
+    let mut mock = mock_workflow_client();
+    mock.expect_fail_workflow_task()
+        .withf(|_, cause, failure| {
+            *cause == WorkflowTaskFailedCause::WorkflowWorkerUnhandledFailure
+                && failure.as_ref().is_some_and(|f| {
+                    f.message == "Workflow code panicked" && f.stack_trace == "at wf.rs:7"
+                })
+        })
+        .returning(|_, _, _| Ok(Default::default()))
+        .times(1);
+    let mut mock = single_hist_mock_sg(wfid, t, [1], mock, true);
+    mock.worker_cfg(|wc| wc.max_cached_workflows = 1);
+    let core = mock_worker(mock);
+
+    let act = core.poll_workflow_activation().await.unwrap();
+    core.complete_workflow_activation(WorkflowActivationCompletion::fail(
+        act.run_id,
+        Failure {
+            message: "Workflow code panicked".to_string(),
+            stack_trace: "at wf.rs:7".to_string(),
+            ..Default::default()
+        },
+        Some(WorkflowTaskFailedCause::WorkflowWorkerUnhandledFailure),
+    ))
+    .await
+    .unwrap();
+    let evict = core.poll_workflow_activation().await.unwrap();
+    assert_matches!(
+        evict.jobs.as_slice(),
+        [WorkflowActivationJob {
+            variant: Some(workflow_activation_job::Variant::RemoveFromCache(_)),
+        }]
+    );
+    core.complete_workflow_activation(WorkflowActivationCompletion::empty(evict.run_id))
+        .await
+        .unwrap();
+    core.shutdown().await;
+}
+
+#[tokio::test]
+async fn activations_lang_never_completes_fail_their_task() {
+    let wfid = "fake_wf_id";
+    let t = canned_histories::single_timer("1");
+    let mut mock = mock_workflow_client();
+    mock.expect_fail_workflow_task()
+        .withf(|_, cause, failure| {
+            *cause == WorkflowTaskFailedCause::WorkflowWorkerUnhandledFailure
+                && failure
+                    .as_ref()
+                    .is_some_and(|f| f.message.contains("Potential deadlock detected"))
+        })
+        .returning(|_, _, _| Ok(Default::default()))
+        .times(1);
+    let mut mock = single_hist_mock_sg(wfid, t, [1], mock, true);
+    mock.worker_cfg(|wc| {
+        wc.max_cached_workflows = 1;
+        wc.activation_deadline = ActivationDeadline::Fixed(Duration::from_millis(100));
+    });
+    let core = mock_worker(mock);
+
+    // Imagine the workflow code deadlocks, so lang never completes this
+    let stuck = core.poll_workflow_activation().await.unwrap();
+    let evict = core.poll_workflow_activation().await.unwrap();
+    assert_eq!(evict.run_id, stuck.run_id);
+    assert_matches!(
+        evict.jobs.as_slice(),
+        [WorkflowActivationJob {
+            variant: Some(workflow_activation_job::Variant::RemoveFromCache(_)),
+        }]
+    );
+    // Should lang come unstuck, its completion is too late
+    let late = core
+        .complete_workflow_activation(
+            WorkflowActivationCompletion::from_cmd(
+                stuck.run_id,
+                start_timer_cmd(1, Duration::from_secs(1)),
+            )
+            .with_correlation_id(stuck.correlation_id),
+        )
+        .await;
+    assert_matches!(late, Err(CompleteWfError::InvalidCompletion { .. }));
+    core.complete_workflow_activation(WorkflowActivationCompletion::empty(evict.run_id))
+        .await
+        .unwrap();
+    core.shutdown().await;
+}
+
+#[rstest]
+#[case::timer(WorkflowTaskFailedCause::BadStartTimerAttributes, Some(0))]
+#[case::activity(WorkflowTaskFailedCause::BadScheduleActivityAttributes, Some(1))]
+#[case::complete(
+    WorkflowTaskFailedCause::BadCompleteWorkflowExecutionAttributes,
+    Some(2)
+)]
+#[case::unattributable(WorkflowTaskFailedCause::BadCancelTimerAttributes, None)]
+#[tokio::test]
+async fn commands_server_rejects_fail_the_task_with_its_cause(
+    #[case] cause: WorkflowTaskFailedCause,
+    #[case] command_index: Option<usize>,
+) {
+    let t = canned_histories::single_timer("1");
+    let mut mh = MockPollCfg::from_resp_batches("fake_wf_id", t, [1], mock_workflow_client());
+    mh.num_expected_completions = Some(1.into());
+    mh.completion_mock_fn = Some(Box::new(move |_| {
+        Err(tonic::Status::invalid_argument(format!(
+            "{cause:?}: the command is invalid"
+        )))
+    }));
+    mh.num_expected_fails = 1;
+    mh.expect_fail_wft_matcher = Box::new(move |_, c, f| {
+        *c == cause
+            && f.as_ref()
+                .is_some_and(|f| f.message.ends_with("the command is invalid"))
+    });
+    let mut mock = build_mock_pollers(mh);
+    mock.worker_cfg(|wc| wc.max_cached_workflows = 1);
+    let core = mock_worker(mock);
+
+    let act = core.poll_workflow_activation().await.unwrap();
+    let res = core
+        .complete_workflow_activation(WorkflowActivationCompletion::from_cmds(
+            act.run_id,
+            vec![
+                start_timer_cmd(1, Duration::from_secs(1)),
+                ScheduleActivity {
+                    seq: 1,
+                    activity_id: "1".to_string(),
+                    ..default_act_sched()
+                }
+                .into(),
+                CompleteWorkflowExecution { result: None }.into(),
+            ],
+        ))
+        .await;
+    assert_matches!(
+        res,
+        Err(CompleteWfError::CommandRejectedByServer {
+            cause: c,
+            command_index: i,
+            message,
+            ..
+        }) if c == cause && i == command_index && message.ends_with("the command is invalid")
+    );
+    core.handle_eviction().await;
+    core.shutdown().await;
+}
+
+#[tokio::test]
+async fn max_wft_respected() {
+    let total_wfs = 100;
+    let wf_ids: Vec<_> = (0..total_wfs).map(|i| format!("fake-wf-{i}")).collect();
+    let hists = wf_ids.iter().map(|wf_id| {
+        let hist = canned_histories::single_timer("1");
+        FakeWfResponses {
+            wf_id: wf_id.to_string(),
+            hist,
+            response_batches: vec![1.into(), 2.into()],
+        }
+    });
+    let mh = MockPollCfg::new(hists.into_iter().collect(), true, 0);
+    let mut worker = mock_sdk_cfg(mh, |cfg| {
+        cfg.max_cached_workflows = total_wfs as usize;
+        cfg.max_outstanding_workflow_tasks = Some(1);
+    });
+    let active_count: &'static _ = Box::leak(Box::new(Semaphore::new(1)));
+    worker.register_wf(DEFAULT_WORKFLOW_TYPE, move |ctx: WfContext| async move {
+        drop(
+            active_count
+                .try_acquire()
+                .expect("No multiple concurrent workflow tasks!"),
+        );
         ctx.timer(Duration::from_secs(1)).await;
         Ok(().into())
     });
@@ -948,6 +1826,83 @@ async fn max_wft_respected() {
     worker.run_until_done().await.unwrap();
 }
 
+#[tokio::test]
+async fn wft_poll_parks_until_completion_frees_slot() {
+    let hists = ["wf-1", "wf-2"].map(|wf_id| FakeWfResponses {
+        wf_id: wf_id.to_string(),
+        hist: canned_histories::single_timer("1"),
+        response_batches: vec![1.into()],
+    });
+    let mut mock = build_mock_pollers(MockPollCfg::new(hists.into(), true, 0));
+    mock.worker_cfg(|cfg| {
+        cfg.max_cached_workflows = 2;
+        cfg.max_outstanding_workflow_tasks = Some(1);
+    });
+    let core = mock_worker(mock);
+
+    let first = core.poll_workflow_activation().await.unwrap();
+    // The other run's task can't be handed out while the only slot is taken
+    let poll_fut = core.poll_workflow_activation();
+    advance_fut!(poll_fut);
+    assert_eq!(core.slot_usage().workflow.issued, 1);
+    core.complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
+        first.run_id.clone(),
+        start_timer_cmd(1, Duration::from_secs(1)),
+    ))
+    .await
+    .unwrap();
+    let second = poll_fut.await.unwrap();
+    assert_ne!(second.run_id, first.run_id);
+    core.complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
+        second.run_id,
+        start_timer_cmd(1, Duration::from_secs(1)),
+    ))
+    .await
+    .unwrap();
+    core.shutdown().await;
+}
+
+#[tokio::test]
+async fn wft_poll_reports_saturation_after_timeout() {
+    let hists = ["wf-1", "wf-2"].map(|wf_id| FakeWfResponses {
+        wf_id: wf_id.to_string(),
+        hist: canned_histories::single_timer("1"),
+        response_batches: vec![1.into()],
+    });
+    let mut mock = build_mock_pollers(MockPollCfg::new(hists.into(), true, 0));
+    mock.worker_cfg(|cfg| {
+        cfg.max_cached_workflows = 2;
+        cfg.max_outstanding_workflow_tasks = Some(1);
+        cfg.poll_saturation_timeout = Some(Duration::from_millis(100));
+    });
+    let core = mock_worker(mock);
+
+    let first = core.poll_workflow_activation().await.unwrap();
+    assert_matches!(
+        core.poll_workflow_activation().await.unwrap_err(),
+        PollWfError::WorkerSaturated {
+            outstanding: 1,
+            limit: 1
+        }
+    );
+    core.complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
+        first.run_id.clone(),
+        start_timer_cmd(1, Duration::from_secs(1)),
+    ))
+    .await
+    .unwrap();
+    // Saturation doesn't shut the worker down, and the next poll gets the other run's task
+    let second = core.poll_workflow_activation().await.unwrap();
+    assert_ne!(second.run_id, first.run_id);
+    core.complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
+        second.run_id,
+        start_timer_cmd(1, Duration::from_secs(1)),
+    ))
+    .await
+    .unwrap();
+    core.shutdown().await;
+}
+
 #[rstest(hist_batches, case::incremental(&[1, 2]), case::replay(&[3]))]
 #[tokio::test]
 async fn activity_not_canceled_on_replay_repro(hist_batches: &'static [usize]) {
@@ -1095,6 +2050,163 @@ async fn lots_of_workflows() {
     worker.shutdown().await;
 }
 
+#[tokio::test]
+async fn history_replays_beyond_the_limit_wait_for_a_slot() {
+    let hists = ["wf-1", "wf-2"].map(|wf_id| FakeWfResponses {
+        wf_id: wf_id.to_string(),
+        // Getting the second task of a run which isn't cached means replaying the first
+        hist: canned_histories::single_timer("1"),
+        response_batches: vec![2.into()],
+    });
+    let mut mock = build_multihist_mock_sg(hists, false, 0);
+    mock.worker_cfg(|wc| {
+        wc.max_cached_workflows = 2;
+        wc.max_concurrent_history_replays = Some(1);
+    });
+    let core = mock_worker(mock);
+
+    let mut activated_runs = vec![];
+    while activated_runs.len() < 4 {
+        let act = core.poll_workflow_activation().await.unwrap();
+        let reply = match act.jobs[0].variant {
+            Some(workflow_activation_job::Variant::RemoveFromCache(_)) => {
+                core.complete_workflow_activation(WorkflowActivationCompletion::empty(act.run_id))
+                    .await
+                    .unwrap();
+                continue;
+            }
+            Some(workflow_activation_job::Variant::InitializeWorkflow(_)) => {
+                assert!(act.is_replaying);
+                start_timer_cmd(1, Duration::from_secs(1))
+            }
+            _ => CompleteWorkflowExecution { result: None }.into(),
+        };
+        activated_runs.push(act.run_id.clone());
+        core.complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
+            act.run_id, reply,
+        ))
+        .await
+        .unwrap();
+    }
+    // The second run only starts replaying once the first has caught up with its history
+    assert_eq!(activated_runs[0], activated_runs[1]);
+    assert_eq!(activated_runs[2], activated_runs[3]);
+    assert_ne!(activated_runs[0], activated_runs[2]);
+    core.drain_pollers_and_shutdown().await;
+}
+
+#[tokio::test]
+async fn queued_history_replays_expire_while_lang_waits() {
+    let telem = BufferedTelemetry::new();
+    // The workflow stream runs on its own thread and runtime, so the expiry has to be waited out
+    // for real. Keep the task timeout short to make that cheap.
+    let wft_timeout = Duration::from_millis(200);
+    let hists = ["wf-1", "wf-2"].map(|wf_id| {
+        let mut t = TestHistoryBuilder::default();
+        t.add_wfe_started_with_wft_timeout(wft_timeout);
+        t.add_full_wf_task();
+        let timer_started_event_id = t.add_by_type(EventType::TimerStarted);
+        t.add_timer_fired(timer_started_event_id, "1".to_string());
+        t.add_workflow_task_scheduled_and_started();
+        FakeWfResponses {
+            wf_id: wf_id.to_string(),
+            hist: t,
+            response_batches: vec![2.into()],
+        }
+    });
+    let mut mock = build_multihist_mock_sg(hists, false, 0);
+    mock.worker_cfg(|wc| {
+        wc.max_cached_workflows = 2;
+        wc.max_concurrent_history_replays = Some(1);
+    });
+    let core = mock_worker_with_telemetry(mock, &telem);
+    let expired = || {
+        telem
+            .updates()
+            .into_iter()
+            .filter(|(name, _, _)| name.ends_with("history_replay_queue_expired"))
+            .count()
+    };
+
+    // The first run keeps the only replay slot until lang replies to its replay activation
+    let first = core.poll_workflow_activation().await.unwrap();
+    let poll_fut = core.poll_workflow_activation();
+    let wait_then_complete = async {
+        // Well past the task timeout. Nothing else happens in the meantime, so the second run's
+        // queued task can only be dropped by its own expiry.
+        time::sleep(wft_timeout * 3).await;
+        assert_eq!(expired(), 1);
+        core.complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
+            first.run_id.clone(),
+            start_timer_cmd(1, Duration::from_secs(1)),
+        ))
+        .await
+        .unwrap();
+    };
+    let (next, _) = join!(poll_fut, wait_then_complete);
+    let next = next.unwrap();
+    assert_eq!(next.run_id, first.run_id);
+    core.complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
+        next.run_id,
+        CompleteWorkflowExecution { result: None }.into(),
+    ))
+    .await
+    .unwrap();
+    core.drain_pollers_and_shutdown().await;
+}
+
+#[tokio::test]
+async fn normal_queue_tasks_for_cached_runs_count_as_sticky_timeout_fallbacks() {
+    let telem = BufferedTelemetry::new();
+    let t = canned_histories::long_sequential_timers(2);
+    // Only the last task arrives on the normal queue while its run is cached
+    let tasks = [
+        (ResponseType::ToTaskNum(1), TaskQueueKind::Normal),
+        (ResponseType::OneTask(2), TaskQueueKind::Sticky),
+        (ResponseType::OneTask(3), TaskQueueKind::Normal),
+    ]
+    .map(|(resp_type, kind)| {
+        let mut wft: ValidPollWFTQResponse = hist_to_poll_resp(&t, "fake_wf_id", resp_type)
+            .resp
+            .try_into()
+            .unwrap();
+        wft.task_queue_kind = kind;
+        Ok(wft)
+    });
+    let mut client = mock_workflow_client();
+    client
+        .expect_complete_workflow_task()
+        .returning(|_| Ok(Default::default()));
+    let mut mock =
+        MocksHolder::from_mock_worker(client, MockWorkerInputs::new(stream::iter(tasks).boxed()));
+    mock.worker_cfg(|wc| wc.max_cached_workflows = 10);
+    let core = mock_worker_with_telemetry(mock, &telem);
+
+    for seq in 1..=3 {
+        let act = core.poll_workflow_activation().await.unwrap();
+        let cmd = if seq < 3 {
+            start_timer_cmd(seq, Duration::from_secs(1))
+        } else {
+            CompleteWorkflowExecution { result: None }.into()
+        };
+        core.complete_workflow_activation(WorkflowActivationCompletion::from_cmd(act.run_id, cmd))
+            .await
+            .unwrap();
+    }
+    core.drain_pollers_and_shutdown().await;
+
+    let fallbacks: u64 = telem
+        .updates()
+        .into_iter()
+        .filter(|(name, _, _)| name.ends_with("sticky_timeout_fallback"))
+        .map(|(_, _, update)| match update {
+            MetricUpdateVal::Delta(d) => d,
+            other => panic!("Unexpected counter update {other:?}"),
+        })
+        .sum();
+    assert_eq!(fallbacks, 1);
+}
+
 #[rstest(hist_batches, case::incremental(&[1, 2]), case::replay(&[2]))]
 #[tokio::test]
 async fn wft_timeout_repro(hist_batches: &'static [usize]) {
@@ -1171,6 +2283,60 @@ async fn complete_after_eviction() {
     core.shutdown().await;
 }
 
+#[tokio::test]
+async fn completions_inconsistent_with_run_are_rejected() {
+    let wfid = "fake_wf_id";
+    let t = canned_histories::single_timer("1");
+    let mock = single_hist_mock_sg(wfid, t, [1, 2], mock_workflow_client(), true);
+    let core = mock_worker(mock);
+
+    let activation = core.poll_workflow_activation().await.unwrap();
+    // Cancelling a timer this run never started, as if the commands were meant for another run
+    let err = core
+        .complete_workflow_activation(WorkflowActivationCompletion::from_cmds(
+            activation.run_id.clone(),
+            vec![
+                start_timer_cmd(1, Duration::from_secs(1)),
+                CancelTimer { seq: 2 }.into(),
+            ],
+        ))
+        .await
+        .unwrap_err();
+    assert_matches!(err, CompleteWfError::MalformedWorkflowCompletion { reason, .. }
+                         if reason.contains("Command 1"));
+    let err = core
+        .complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
+            "not-a-run".to_string(),
+            start_timer_cmd(1, Duration::from_secs(1)),
+        ))
+        .await
+        .unwrap_err();
+    assert_matches!(err, CompleteWfError::InvalidCompletion { run_id, .. }
+                         if run_id == "not-a-run");
+
+    // Neither rejection touched the run, so the activation can still be completed properly
+    core.complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
+        activation.run_id,
+        start_timer_cmd(1, Duration::from_secs(1)),
+    ))
+    .await
+    .unwrap();
+    let next_activation = core.poll_workflow_activation().await.unwrap();
+    assert_matches!(
+        next_activation.jobs.as_slice(),
+        [WorkflowActivationJob {
+            variant: Some(workflow_activation_job::Variant::FireTimer(_)),
+        },]
+    );
+    core.complete_workflow_activation(WorkflowActivationCompletion::from_cmds(
+        next_activation.run_id,
+        vec![CompleteWorkflowExecution { result: None }.into()],
+    ))
+    .await
+    .unwrap();
+    core.shutdown().await;
+}
+
 #[tokio::test]
 async fn sends_appropriate_sticky_task_queue_responses() {
     // This test verifies that when completions are sent with sticky queues enabled, that they
@@ -1179,12 +2345,20 @@ async fn sends_appropriate_sticky_task_queue_responses() {
     let t = canned_histories::single_timer("1");
     let mut mock = mock_workflow_client();
     mock.expect_complete_workflow_task()
-        .withf(|comp| comp.sticky_attributes.is_some())
+        .withf(|comp| {
+            // The configured sticky schedule-to-start timeout is what's sent
+            comp.sticky_attributes
+                .as_ref()
+                .is_some_and(|sa| sa.schedule_to_start_timeout == Some(prost_dur!(from_secs(3))))
+        })
         .times(1)
         .returning(|_| Ok(Default::default()));
     mock.expect_complete_workflow_task().times(0);
     let mut mock = single_hist_mock_sg(wfid, t, [1], mock, false);
-    mock.worker_cfg(|wc| wc.max_cached_workflows = 10);
+    mock.worker_cfg(|wc| {
+        wc.max_cached_workflows = 10;
+        wc.sticky_queue_schedule_to_start_timeout = Duration::from_secs(3);
+    });
     let core = mock_worker(mock);
 
     let activation = core.poll_workflow_activation().await.unwrap();
@@ -1298,6 +2472,332 @@ async fn buffered_work_drained_on_shutdown() {
     });
 }
 
+/// Passes through `tasks`, notifying `handed_out` once the `nth` (counting from zero) of them has
+/// been handed to the worker
+fn notify_when_handed_out<T>(
+    tasks: impl Stream<Item = T>,
+    nth: usize,
+    handed_out: Arc<Notify>,
+) -> impl Stream<Item = T> {
+    tasks.enumerate().map(move |(i, task)| {
+        if i == nth {
+            handed_out.notify_one();
+        }
+        task
+    })
+}
+
+/// An activity resolution arriving in a new WFT while lang is still working on the previous
+/// activation for the same run must be buffered, and not delivered until the outstanding
+/// completion has been fully applied and reported.
+#[tokio::test]
+async fn activity_resolution_buffered_while_prior_activation_outstanding() {
+    let wfid = "fake_wf_id";
+    let t = canned_histories::single_activity("fake_activity");
+    let tasks = VecDeque::from(vec![
+        hist_to_poll_resp(&t, wfid.to_owned(), 1.into()).resp,
+        hist_to_poll_resp(&t, wfid.to_owned(), 2.into()).resp,
+    ]);
+    let second_task_polled = Arc::new(Notify::new());
+    let first_wft_reported = Arc::new(AtomicUsize::new(0));
+    let first_wft_reported_clone = first_wft_reported.clone();
+    let mut mock = mock_workflow_client();
+    mock.expect_complete_workflow_task().returning(move |c| {
+        if c.commands[0].command_type() == CommandType::ScheduleActivityTask {
+            first_wft_reported_clone.fetch_add(1, Ordering::SeqCst);
+        }
+        Ok(RespondWorkflowTaskCompletedResponse::default())
+    });
+    let mut mock = MocksHolder::from_wft_stream(
+        mock,
+        notify_when_handed_out(stream::iter(tasks), 1, second_task_polled.clone()),
+    );
+    mock.worker_cfg(|wc| wc.max_cached_workflows = 10);
+    let core = &mock_worker(mock);
+
+    let act1 = core.poll_workflow_activation().await.unwrap();
+    assert_matches!(
+        act1.jobs.as_slice(),
+        [WorkflowActivationJob {
+            variant: Some(workflow_activation_job::Variant::InitializeWorkflow(_)),
+        }]
+    );
+    let poll_fut = async move {
+        let act2 = core.poll_workflow_activation().await.unwrap();
+        // The resolution may only be seen once the schedule command has gone to server
+        assert_eq!(first_wft_reported.load(Ordering::SeqCst), 1);
+        assert_matches!(
+            act2.jobs.as_slice(),
+            [WorkflowActivationJob {
+                variant: Some(workflow_activation_job::Variant::ResolveActivity(_)),
+            }]
+        );
+        core.complete_workflow_activation(WorkflowActivationCompletion::from_cmds(
+            act2.run_id,
+            vec![CompleteWorkflowExecution { result: None }.into()],
+        ))
+        .await
+        .unwrap();
+    };
+    let complete_first = async move {
+        // The second task shows up while the first activation is still outstanding
+        second_task_polled.notified().await;
+        core.complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
+            act1.run_id,
+            ScheduleActivity {
+                activity_id: "fake_activity".to_string(),
+                ..default_act_sched()
+            }
+            .into(),
+        ))
+        .await
+        .unwrap();
+    };
+    join!(poll_fut, complete_first);
+    core.shutdown().await;
+}
+
+/// A signal arriving in a task which gets buffered while the run is waiting to be evicted is never
+/// applied to the evicted instance of the run. It must still reach lang once the run is rebuilt
+/// from that task's history.
+#[tokio::test]
+async fn signal_buffered_during_eviction_delivered_after_rebuild() {
+    let wfid = "fake_wf_id";
+    let mut t = TestHistoryBuilder::default();
+    t.add_by_type(EventType::WorkflowExecutionStarted);
+    t.add_full_wf_task();
+    t.add_we_signaled("sig", vec![]);
+    t.add_full_wf_task();
+    t.add_workflow_execution_completed();
+    let tasks = VecDeque::from(vec![
+        hist_to_poll_resp(&t, wfid.to_owned(), 1.into()).resp,
+        hist_to_poll_resp(&t, wfid.to_owned(), 2.into()).resp,
+    ]);
+    let mut mock = mock_workflow_client();
+    mock.expect_complete_workflow_task()
+        .returning(|_| Ok(RespondWorkflowTaskCompletedResponse::default()));
+    let mut mock = MocksHolder::from_wft_stream(mock, stream::iter(tasks));
+    mock.worker_cfg(|wc| wc.max_cached_workflows = 10);
+    let core = &mock_worker(mock);
+
+    let act1 = core.poll_workflow_activation().await.unwrap();
+    core.request_workflow_eviction(&act1.run_id);
+    let poll_fut = async move {
+        let evict = core.poll_workflow_activation().await.unwrap();
+        assert_matches!(
+            evict.jobs.as_slice(),
+            [WorkflowActivationJob {
+                variant: Some(workflow_activation_job::Variant::RemoveFromCache(_)),
+            }]
+        );
+        core.complete_workflow_activation(WorkflowActivationCompletion::empty(evict.run_id))
+            .await
+            .unwrap();
+        let act = core.poll_workflow_activation().await.unwrap();
+        assert_matches!(
+            act.jobs.as_slice(),
+            [WorkflowActivationJob {
+                variant: Some(workflow_activation_job::Variant::InitializeWorkflow(_)),
+            }]
+        );
+        core.complete_workflow_activation(WorkflowActivationCompletion::empty(act.run_id))
+            .await
+            .unwrap();
+        let act = core.poll_workflow_activation().await.unwrap();
+        assert_matches!(
+            act.jobs.as_slice(),
+            [WorkflowActivationJob {
+                variant: Some(workflow_activation_job::Variant::SignalWorkflow(s)),
+            }] if s.signal_name == "sig"
+        );
+        core.complete_workflow_activation(WorkflowActivationCompletion::from_cmds(
+            act.run_id,
+            vec![CompleteWorkflowExecution { result: None }.into()],
+        ))
+        .await
+        .unwrap();
+    };
+    let complete_first = async move {
+        // Give the second task a chance to arrive and be buffered before completing
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        core.complete_workflow_activation(WorkflowActivationCompletion::empty(act1.run_id))
+            .await
+            .unwrap();
+    };
+    join!(poll_fut, complete_first);
+    core.shutdown().await;
+}
+
+/// A completion which fails the activation and forces eviction must fail the task, evict with
+/// the lang-requested reason, and fail the task which was buffered while the activation was
+/// outstanding rather than applying it. Only a task polled after the failure gets applied to the
+/// new instance of the run.
+#[tokio::test]
+async fn fail_and_evict_fails_buffered_task() {
+    let wfid = "fake_wf_id";
+    let t = canned_histories::single_timer("1");
+    let (task_tx, task_rx) = tokio::sync::mpsc::unbounded_channel();
+    task_tx
+        .send(hist_to_poll_resp(&t, wfid.to_owned(), 1.into()).resp)
+        .unwrap();
+    let second_task_polled = Arc::new(Notify::new());
+    let buffered_task_failed = Arc::new(Notify::new());
+    let fresh_task_sent = AtomicBool::new(false);
+    let mut mock = mock_workflow_client();
+    // Both the failed activation's task and the buffered one are failed
+    let fails = AtomicUsize::new(0);
+    let buffered_task_failed_clone = buffered_task_failed.clone();
+    mock.expect_fail_workflow_task()
+        .returning(move |_, _, _| {
+            if fails.fetch_add(1, Ordering::SeqCst) == 1 {
+                buffered_task_failed_clone.notify_one();
+            }
+            Ok(Default::default())
+        })
+        .times(2);
+    mock.expect_complete_workflow_task()
+        .returning(|_| Ok(RespondWorkflowTaskCompletedResponse::default()));
+    let mut mock = MocksHolder::from_wft_stream(
+        mock,
+        notify_when_handed_out(
+            tokio_stream::wrappers::UnboundedReceiverStream::new(task_rx),
+            1,
+            second_task_polled.clone(),
+        ),
+    );
+    mock.worker_cfg(|wc| wc.max_cached_workflows = 10);
+    let core = &mock_worker(mock);
+
+    let act1 = core.poll_workflow_activation().await.unwrap();
+    // Arrives while the first activation is outstanding, so it gets buffered
+    task_tx
+        .send(hist_to_poll_resp(&t, wfid.to_owned(), 2.into()).resp)
+        .unwrap();
+    let poll_fut = async {
+        let evict = core.poll_workflow_activation().await.unwrap();
+        assert_matches!(
+            evict.jobs.as_slice(),
+            [WorkflowActivationJob {
+                variant: Some(workflow_activation_job::Variant::RemoveFromCache(r)),
+            }] if r.reason() == EvictionReason::LangRequested
+        );
+        core.complete_workflow_activation(WorkflowActivationCompletion::empty(evict.run_id))
+            .await
+            .unwrap();
+        let send_fresh = async {
+            // Once it's been failed, the buffered task can't be delivered anymore
+            buffered_task_failed.notified().await;
+            fresh_task_sent.store(true, Ordering::SeqCst);
+            task_tx
+                .send(hist_to_poll_resp(&t, wfid.to_owned(), 2.into()).resp)
+                .unwrap();
+        };
+        let (act, _) = join!(core.poll_workflow_activation(), send_fresh);
+        let act = act.unwrap();
+        assert!(fresh_task_sent.load(Ordering::SeqCst));
+        assert_matches!(
+            act.jobs.as_slice(),
+            [WorkflowActivationJob {
+                variant: Some(workflow_activation_job::Variant::InitializeWorkflow(_)),
+            }]
+        );
+        core.complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
+            act.run_id,
+            start_timer_cmd(1, Duration::from_secs(1)),
+        ))
+        .await
+        .unwrap();
+        let act = core.poll_workflow_activation().await.unwrap();
+        assert_matches!(
+            act.jobs.as_slice(),
+            [WorkflowActivationJob {
+                variant: Some(workflow_activation_job::Variant::FireTimer(_)),
+            }]
+        );
+        core.complete_workflow_activation(WorkflowActivationCompletion::from_cmds(
+            act.run_id,
+            vec![CompleteWorkflowExecution { result: None }.into()],
+        ))
+        .await
+        .unwrap();
+    };
+    let fail_first = async {
+        // The second task shows up while the first activation is still outstanding
+        second_task_polled.notified().await;
+        core.complete_workflow_activation(WorkflowActivationCompletion::fail_and_evict(
+            act1.run_id,
+            "Coroutine state is unrecoverable".into(),
+        ))
+        .await
+        .unwrap();
+    };
+    join!(poll_fut, fail_first);
+    drop(task_tx);
+    core.shutdown().await;
+}
+
+/// A legacy query buffered behind an activation which lang fails and force-evicts will never be
+/// answered by lang, so the querier must be sent a failure instead
+#[tokio::test]
+async fn fail_and_evict_fails_buffered_legacy_query() {
+    let wfid = "fake_wf_id";
+    let t = canned_histories::single_timer("1");
+    let query_task = {
+        let mut pr = hist_to_poll_resp(&t, wfid.to_owned(), 1.into());
+        pr.query = Some(WorkflowQuery {
+            query_type: "q".to_string(),
+            ..Default::default()
+        });
+        pr.started_event_id = 0;
+        pr.resp
+    };
+    let tasks = [
+        hist_to_poll_resp(&t, wfid.to_owned(), 1.into()).resp,
+        query_task,
+    ];
+    let query_polled = Arc::new(Notify::new());
+    let mut mock = mock_workflow_client();
+    mock.expect_fail_workflow_task()
+        .returning(|_, _, _| Ok(Default::default()))
+        .times(1);
+    mock.expect_respond_legacy_query()
+        .withf(|_, res| matches!(res.variant, Some(query_result::Variant::Failed(_))))
+        .returning(|_, _| Ok(Default::default()))
+        .times(1);
+    let mut mock = MocksHolder::from_wft_stream(
+        mock,
+        notify_when_handed_out(stream::iter(tasks), 1, query_polled.clone()),
+    );
+    mock.worker_cfg(|wc| wc.max_cached_workflows = 10);
+    let core = mock_worker(mock);
+
+    let act = core.poll_workflow_activation().await.unwrap();
+    let poll_fut = async {
+        let evict = core.poll_workflow_activation().await.unwrap();
+        assert_matches!(
+            evict.jobs.as_slice(),
+            [WorkflowActivationJob {
+                variant: Some(workflow_activation_job::Variant::RemoveFromCache(_)),
+            }]
+        );
+        core.complete_workflow_activation(WorkflowActivationCompletion::empty(evict.run_id))
+            .await
+            .unwrap();
+    };
+    let fail_first = async {
+        // The query shows up while the activation is still outstanding, so it gets buffered
+        query_polled.notified().await;
+        core.complete_workflow_activation(WorkflowActivationCompletion::fail_and_evict(
+            act.run_id,
+            "Coroutine state is unrecoverable".into(),
+        ))
+        .await
+        .unwrap();
+    };
+    join!(poll_fut, fail_first);
+    core.drain_pollers_and_shutdown().await;
+}
+
 #[tokio::test]
 async fn fail_wft_then_recover() {
     let t = canned_histories::long_sequential_timers(1);
@@ -1356,6 +2856,66 @@ async fn fail_wft_then_recover() {
     core.shutdown().await;
 }
 
+/// Runs the same nondeterministic completion against both nondeterminism policies. By default the
+/// WFT is failed, but if configured the workflow itself is failed with the nondeterminism error.
+#[rstest::rstest]
+#[tokio::test]
+async fn nondeterminism_fails_task_or_workflow_per_config(
+    #[values(true, false)] fail_workflow: bool,
+) {
+    let t = canned_histories::long_sequential_timers(1);
+    let mut mh = MockPollCfg::from_resp_batches(
+        "fake_wf_id",
+        t,
+        [ResponseType::AllHistory],
+        mock_workflow_client(),
+    );
+    if fail_workflow {
+        mh.num_expected_completions = Some(1.into());
+        mh.completion_mock_fn = Some(Box::new(|c| {
+            assert_matches!(
+                c.commands.as_slice(),
+                [cmd] => {
+                    assert_eq!(cmd.command_type(), CommandType::FailWorkflowExecution);
+                    assert_matches!(
+                        cmd.attributes.as_ref(),
+                        Some(Attributes::FailWorkflowExecutionCommandAttributes(a))
+                            if a.failure.as_ref().unwrap().message.contains("Nondeterminism")
+                    );
+                }
+            );
+            Ok(Default::default())
+        }));
+    } else {
+        mh.num_expected_fails = 1;
+        mh.expect_fail_wft_matcher =
+            Box::new(|_, cause, _| matches!(cause, WorkflowTaskFailedCause::NonDeterministicError));
+    }
+    let mut mock = build_mock_pollers(mh);
+    mock.worker_cfg(|wc| {
+        wc.max_cached_workflows = 2;
+        if fail_workflow {
+            wc.workflow_failure_errors = HashSet::from([WorkflowErrorType::Nondeterminism]);
+        }
+    });
+    let core = mock_worker(mock);
+
+    let act = core.poll_workflow_activation().await.unwrap();
+    // Start an activity instead of a timer, triggering nondeterminism error
+    core.complete_workflow_activation(WorkflowActivationCompletion::from_cmds(
+        act.run_id.clone(),
+        vec![ScheduleActivity {
+            activity_id: "fake_activity".to_string(),
+            ..default_act_sched()
+        }
+        .into()],
+    ))
+    .await
+    .unwrap();
+    core.handle_eviction().await;
+    core.shutdown().await;
+}
+
 #[tokio::test]
 async fn poll_response_triggers_wf_error() {
     let mut t = TestHistoryBuilder::default();
@@ -1643,20 +3203,193 @@ async fn cache_miss_will_fetch_history() {
     worker.shutdown().await;
 }
 
+/// Replays a run which started five sequential timers, with its history either delivered whole
+/// in the poll response or split into three pages. Returns whether each activation lang saw was
+/// replaying, its history length, and its jobs.
+async fn sequential_timer_activations(
+    paginated: bool,
+) -> Vec<(bool, u32, Vec<WorkflowActivationJob>)> {
+    let wfid = "fake_wf_id";
+    let mut t = TestHistoryBuilder::default();
+    t.add_by_type(EventType::WorkflowExecutionStarted);
+    t.add_full_wf_task();
+    for i in 1..=5 {
+        let timer_started_event_id = t.add_by_type(EventType::TimerStarted);
+        t.add_timer_fired(timer_started_event_id, i.to_string());
+        if i < 5 {
+            t.add_full_wf_task();
+        } else {
+            t.add_workflow_task_scheduled_and_started();
+        }
+    }
+
+    let mut mock_client = mock_workflow_client();
+    let response = if paginated {
+        let mut resp = hist_to_poll_resp(&t, wfid, ResponseType::AllHistory).resp;
+        let mut events = resp.history.take().unwrap().events;
+        // Both page boundaries fall in the middle of a workflow task
+        let last_page = events.split_off(12);
+        let second_page = events.split_off(5);
+        resp.history = Some(History { events });
+        resp.next_page_token = vec![1];
+        for (token, page, next_token) in [(1, second_page, vec![2]), (2, last_page, vec![])] {
+            mock_client
+                .expect_get_workflow_execution_history()
+                .withf(move |_, _, npt| npt == &[token])
+                .times(1)
+                .returning(move |_, _, _| {
+                    Ok(GetWorkflowExecutionHistoryResponse {
+                        history: Some(History {
+                            events: page.clone(),
+                        }),
+                        next_page_token: next_token.clone(),
+                        ..Default::default()
+                    })
+                });
+        }
+        ResponseType::Raw(resp)
+    } else {
+        mock_client.expect_get_workflow_execution_history().times(0);
+        ResponseType::AllHistory
+    };
+    let mh = MockPollCfg::from_resp_batches(wfid, t, [response], mock_client);
+    let core = mock_worker(build_mock_pollers(mh));
+
+    let mut activations = vec![];
+    for seq in 1..=6 {
+        let activation = core.poll_workflow_activation().await.unwrap();
+        let cmd = if seq <= 5 {
+            start_timer_cmd(seq, Duration::from_secs(1))
+        } else {
+            CompleteWorkflowExecution { result: None }.into()
+        };
+        core.complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
+            activation.run_id.clone(),
+            cmd,
+        ))
+        .await
+        .unwrap();
+        activations.push((
+            activation.is_replaying,
+            activation.history_length,
+            activation.jobs,
+        ));
+    }
+    core.drain_pollers_and_shutdown().await;
+    activations
+}
+
 #[tokio::test]
-async fn history_byte_size_and_can_suggestion_in_activation() {
+async fn paginated_history_replays_like_single_page() {
+    let single_page = sequential_timer_activations(false).await;
+    assert_eq!(single_page.iter().filter(|a| a.0).count(), 5);
+    assert_matches!(
+        single_page.last().unwrap().2.as_slice(),
+        [WorkflowActivationJob {
+            variant: Some(workflow_activation_job::Variant::FireTimer(_)),
+        }]
+    );
+    assert_eq!(sequential_timer_activations(true).await, single_page);
+}
+
+#[rstest]
+#[case::with_history(true, 0)]
+#[case::metadata_only(false, 1)]
+#[tokio::test]
+async fn cache_snapshot_handover(#[case] retain_history: bool, #[case] expected_fetches: usize) {
     let mut t = TestHistoryBuilder::default();
     t.add_by_type(EventType::WorkflowExecutionStarted);
     t.add_full_wf_task();
     t.add_we_signaled("sig", vec![]);
     t.add_full_wf_task();
     t.add_workflow_execution_completed();
-    t.modify_event(7, |he| {
-        if let Some(history_event::Attributes::WorkflowTaskStartedEventAttributes(ref mut attrs)) =
-            he.attributes
-        {
-            attrs.suggest_continue_as_new = true;
-        }
+    let get_exec_resp: GetWorkflowExecutionHistoryResponse = t.get_history_info(2).unwrap().into();
+
+    // The old worker processes the first task, then hands over
+    let mh = MockPollCfg::from_resp_batches(
+        "fake_wf_id",
+        t.clone(),
+        [ResponseType::ToTaskNum(1)],
+        mock_workflow_client(),
+    );
+    let mut mock = build_mock_pollers(mh);
+    mock.worker_cfg(|cfg| {
+        cfg.max_cached_workflows = 1;
+        cfg.retain_history_for_handover = retain_history;
+    });
+    let old_worker = mock_worker(mock);
+    let activation = old_worker.poll_workflow_activation().await.unwrap();
+    old_worker
+        .complete_workflow_activation(WorkflowActivationCompletion::empty(activation.run_id))
+        .await
+        .unwrap();
+    let snapshot = old_worker.export_cache_snapshot().await;
+    old_worker.shutdown().await;
+
+    // The new worker only sees the partial history sent to the sticky queue
+    let mut mh = MockPollCfg::from_resp_batches(
+        "fake_wf_id",
+        t,
+        [ResponseType::OneTask(2)],
+        mock_workflow_client(),
+    );
+    mh.mock_client
+        .expect_get_workflow_execution_history()
+        .times(expected_fetches)
+        .returning(move |_, _, _| Ok(get_exec_resp.clone()));
+    let mut mock = build_mock_pollers(mh);
+    mock.worker_cfg(|cfg| {
+        cfg.max_cached_workflows = 1;
+    });
+    let worker = mock_worker(mock);
+    let snapshot = CacheSnapshot::decode_for(&snapshot, NAMESPACE, TEST_Q).unwrap();
+    assert_eq!(snapshot.runs.len(), 1);
+    worker.import_cache_snapshot(snapshot);
+
+    let activation = worker.poll_workflow_activation().await.unwrap();
+    assert_eq!(activation.history_length, 3);
+    assert_matches!(
+        activation.jobs.as_slice(),
+        [WorkflowActivationJob {
+            variant: Some(workflow_activation_job::Variant::InitializeWorkflow(_)),
+        }]
+    );
+    worker
+        .complete_workflow_activation(WorkflowActivationCompletion::empty(activation.run_id))
+        .await
+        .unwrap();
+    let activation = worker.poll_workflow_activation().await.unwrap();
+    assert_eq!(activation.history_length, 7);
+    assert_matches!(
+        activation.jobs.as_slice(),
+        [WorkflowActivationJob {
+            variant: Some(workflow_activation_job::Variant::SignalWorkflow(_)),
+        }]
+    );
+    worker
+        .complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
+            activation.run_id,
+            CompleteWorkflowExecution { result: None }.into(),
+        ))
+        .await
+        .unwrap();
+    worker.shutdown().await;
+}
+
+#[tokio::test]
+async fn history_byte_size_and_can_suggestion_in_activation() {
+    let mut t = TestHistoryBuilder::default();
+    t.add_by_type(EventType::WorkflowExecutionStarted);
+    t.add_full_wf_task();
+    t.add_we_signaled("sig", vec![]);
+    t.add_full_wf_task();
+    t.add_workflow_execution_completed();
+    t.modify_event(7, |he| {
+        if let Some(history_event::Attributes::WorkflowTaskStartedEventAttributes(ref mut attrs)) =
+            he.attributes
+        {
+            attrs.suggest_continue_as_new = true;
+        }
     });
 
     let mh = MockPollCfg::from_resp_batches(
@@ -1763,30 +3496,382 @@ async fn pagination_works_with_tasks_from_completion() {
     mock.worker_cfg(|wc| wc.max_cached_workflows = 2);
     let core = mock_worker(mock);
 
-    let wf_task = core.poll_workflow_activation().await.unwrap();
-    core.complete_workflow_activation(WorkflowActivationCompletion::empty(wf_task.run_id))
+    let wf_task = core.poll_workflow_activation().await.unwrap();
+    core.complete_workflow_activation(WorkflowActivationCompletion::empty(wf_task.run_id))
+        .await
+        .unwrap();
+    let wf_task = core.poll_workflow_activation().await.unwrap();
+    assert_matches!(
+        wf_task.jobs.as_slice(),
+        [WorkflowActivationJob {
+            variant: Some(workflow_activation_job::Variant::SignalWorkflow(_)),
+        },]
+    );
+    core.complete_workflow_activation(WorkflowActivationCompletion::from_cmds(
+        wf_task.run_id,
+        vec![CompleteWorkflowExecution { result: None }.into()],
+    ))
+    .await
+    .unwrap();
+    core.shutdown().await;
+}
+
+#[tokio::test]
+async fn poll_faster_than_complete_wont_overflow_cache() {
+    // Make workflow tasks for 5 different runs
+    let tasks: Vec<_> = (1..=5)
+        .map(|i| FakeWfResponses {
+            wf_id: format!("wf-{i}"),
+            hist: canned_histories::single_timer("1"),
+            response_batches: vec![ResponseType::ToTaskNum(1)],
+        })
+        .collect();
+    let mut mock_client = mock_workflow_client();
+    mock_client
+        .expect_complete_workflow_task()
+        .times(3)
+        .returning(|_| Ok(Default::default()));
+    let mut mock_cfg = MockPollCfg::new(tasks, true, 0);
+    mock_cfg.mock_client = mock_client;
+    let mut mock = build_mock_pollers(mock_cfg);
+    mock.worker_cfg(|wc| {
+        wc.max_cached_workflows = 3;
+        wc.max_outstanding_workflow_tasks = Some(3);
+    });
+    let core = mock_worker(mock);
+    // Poll 4 times, completing once, such that max tasks are never exceeded
+    let p1 = core.poll_workflow_activation().await.unwrap();
+    let p2 = core.poll_workflow_activation().await.unwrap();
+    let p3 = core.poll_workflow_activation().await.unwrap();
+    for (i, p_res) in [&p1, &p2, &p3].into_iter().enumerate() {
+        assert_matches!(
+            &p_res.jobs[0].variant,
+            Some(workflow_activation_job::Variant::InitializeWorkflow(sw))
+            if sw.workflow_id == format!("wf-{}", i + 1)
+        );
+    }
+    // Complete first task to free a wft slot. Cache size is at 3
+    core.complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
+        p1.run_id,
+        start_timer_cmd(1, Duration::from_secs(1)),
+    ))
+    .await
+    .unwrap();
+    // Now we're at cache limit. We will poll for a task, discover it is for a new run, issue
+    // an eviction, and buffer the new run task. However, the run we're trying to evict has pending
+    // activations! Thus, we must complete them first before this poll will unblock, and then it
+    // will unblock with the eviciton.
+    let p4 = core.poll_workflow_activation();
+    // Make sure the task gets buffered before we start the complete, so the LRU list is in the
+    // expected order and what we expect to evict will be evicted.
+    advance_fut!(p4);
+    let p4 = async {
+        let p4 = p4.await.unwrap();
+        assert_matches!(
+            &p4.jobs.as_slice(),
+            [WorkflowActivationJob {
+                variant: Some(workflow_activation_job::Variant::RemoveFromCache(_)),
+            }]
+        );
+        p4
+    };
+    let p2_pending_completer = async {
+        core.complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
+            p2.run_id,
+            start_timer_cmd(1, Duration::from_secs(1)),
+        ))
+        .await
+        .unwrap();
+    };
+    let (p4, _) = join!(p4, p2_pending_completer);
+    assert_eq!(core.cached_workflows().await, 3);
+
+    // This poll should also block until the eviction is actually completed
+    let blocking_poll = async {
+        let res = core.poll_workflow_activation().await.unwrap();
+        assert_matches!(
+            &res.jobs[0].variant,
+            Some(workflow_activation_job::Variant::InitializeWorkflow(sw))
+            if sw.workflow_id == format!("wf-{}", 4)
+        );
+        res
+    };
+    let complete_evict = async {
+        core.complete_workflow_activation(WorkflowActivationCompletion::empty(p4.run_id))
+            .await
+            .unwrap();
+    };
+
+    let (_p5, _) = join!(blocking_poll, complete_evict);
+    assert_eq!(core.cached_workflows().await, 3);
+    // The next poll will get an buffer a task for a new run, and generate an eviction for p3 but
+    // that eviction cannot be obtained until we complete the existing outstanding task.
+    let p6 = async {
+        let p6 = core.poll_workflow_activation().await.unwrap();
+        assert_matches!(
+            p6.jobs.as_slice(),
+            [WorkflowActivationJob {
+                variant: Some(workflow_activation_job::Variant::RemoveFromCache(_)),
+            }]
+        );
+        p6
+    };
+    let completer = async {
+        core.complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
+            p3.run_id,
+            start_timer_cmd(1, Duration::from_secs(1)),
+        ))
+        .await
+        .unwrap();
+    };
+    let (p6, _) = join!(p6, completer);
+    let complete_evict = async {
+        core.complete_workflow_activation(WorkflowActivationCompletion::empty(p6.run_id))
+            .await
+            .unwrap();
+    };
+    let blocking_poll = async {
+        // This poll will also block until the last eviction goes through, and when it does it'll
+        // produce the final start workflow task
+        let res = core.poll_workflow_activation().await.unwrap();
+        assert_matches!(
+            &res.jobs[0].variant,
+            Some(workflow_activation_job::Variant::InitializeWorkflow(sw))
+            if sw.workflow_id == "wf-5"
+        );
+    };
+
+    join!(blocking_poll, complete_evict);
+    // p5 outstanding and final poll outstanding -- hence one permit available
+    assert_eq!(core.available_wft_permits(), Some(1));
+    assert_eq!(core.cached_workflows().await, 3);
+}
+
+#[tokio::test]
+async fn full_cache_of_busy_runs_delays_new_runs_instead_of_evicting() {
+    let tasks: Vec<_> = (1..=3)
+        .map(|i| FakeWfResponses {
+            wf_id: format!("wf-{i}"),
+            hist: canned_histories::single_timer("1"),
+            response_batches: vec![ResponseType::ToTaskNum(1)],
+        })
+        .collect();
+    let mut mock_client = mock_workflow_client();
+    mock_client
+        .expect_complete_workflow_task()
+        .times(2)
+        .returning(|_| Ok(Default::default()));
+    let mut mock_cfg = MockPollCfg::new(tasks, true, 0);
+    mock_cfg.mock_client = mock_client;
+    let mut mock = build_mock_pollers(mock_cfg);
+    mock.worker_cfg(|wc| {
+        wc.max_cached_workflows = 2;
+        wc.max_outstanding_workflow_tasks = Some(3);
+    });
+    let core = mock_worker(mock);
+    let p1 = core.poll_workflow_activation().await.unwrap();
+    let p2 = core.poll_workflow_activation().await.unwrap();
+
+    // Both cached runs have activations outstanding, so the task for the third run must wait
+    // rather than cause either of them to be evicted.
+    let p3 = core.poll_workflow_activation();
+    advance_fut!(p3);
+    // The second run going idle makes it the one to evict, even though the first run is less
+    // recently used. Had the first run been chosen while busy, this poll would not resolve until
+    // it was completed.
+    core.complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
+        p2.run_id.clone(),
+        start_timer_cmd(1, Duration::from_secs(1)),
+    ))
+    .await
+    .unwrap();
+    let evict = p3.await.unwrap();
+    assert_eq!(evict.run_id, p2.run_id);
+    assert_matches!(
+        evict.jobs.as_slice(),
+        [WorkflowActivationJob {
+            variant: Some(workflow_activation_job::Variant::RemoveFromCache(_)),
+        }]
+    );
+    core.complete_workflow_activation(WorkflowActivationCompletion::empty(evict.run_id))
+        .await
+        .unwrap();
+
+    let p3 = core.poll_workflow_activation().await.unwrap();
+    assert_matches!(
+        &p3.jobs[0].variant,
+        Some(workflow_activation_job::Variant::InitializeWorkflow(sw)) if sw.workflow_id == "wf-3"
+    );
+    // The first run was never asked to leave the cache
+    core.complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
+        p1.run_id,
+        start_timer_cmd(1, Duration::from_secs(1)),
+    ))
+    .await
+    .unwrap();
+    assert_eq!(core.cached_workflows().await, 2);
+}
+
+#[tokio::test]
+async fn task_waiting_for_cache_slot_is_failed_at_shutdown() {
+    let tasks: Vec<_> = ["wf-1", "wf-2"]
+        .into_iter()
+        .map(|wf_id| FakeWfResponses {
+            wf_id: wf_id.to_string(),
+            hist: canned_histories::single_timer("1"),
+            response_batches: vec![ResponseType::ToTaskNum(1)],
+        })
+        .collect();
+    let mut mock_cfg = MockPollCfg::new(tasks, true, 1);
+    mock_cfg.expect_fail_wft_matcher = Box::new(|_, _, failure| {
+        failure
+            .as_ref()
+            .is_some_and(|f| f.message.contains("shut down before this task"))
+    });
+    let mut mock = build_mock_pollers(mock_cfg);
+    mock.worker_cfg(|wc| wc.max_cached_workflows = 1);
+    let core = mock_worker(mock);
+
+    let p1 = core.poll_workflow_activation().await.unwrap();
+    // The second run's task is buffered, since the only cache slot is taken
+    let p2 = core.poll_workflow_activation();
+    advance_fut!(p2);
+    core.initiate_shutdown();
+    core.complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
+        p1.run_id.clone(),
+        start_timer_cmd(1, Duration::from_secs(1)),
+    ))
+    .await
+    .unwrap();
+    // Evictions are ignored on shutdown, so the buffered task is never started, and is failed
+    let evict = p2.await.unwrap();
+    assert_eq!(evict.run_id, p1.run_id);
+    assert!(evict.is_only_eviction());
+    core.complete_workflow_activation(WorkflowActivationCompletion::empty(evict.run_id))
+        .await
+        .unwrap();
+    assert_matches!(
+        core.poll_workflow_activation().await.unwrap_err(),
+        PollWfError::ShutDown
+    );
+    core.shutdown().await;
+}
+
+#[tokio::test]
+async fn lru_run_is_evicted_for_new_run_and_replayed_when_it_returns() {
+    let release_wf_1 = Arc::new(Semaphore::new(0));
+    let release = release_wf_1.clone();
+    let tasks: Vec<_> = (1..=3)
+        .map(|i| FakeWfResponses {
+            wf_id: format!("wf-{i}"),
+            hist: canned_histories::single_timer("1"),
+            response_batches: if i == 1 {
+                // The first run's timer fires only after it has been evicted
+                let release = release.clone();
+                vec![
+                    ResponseType::ToTaskNum(1),
+                    ResponseType::UntilResolved(
+                        async move {
+                            release.acquire().await.unwrap().forget();
+                        }
+                        .boxed(),
+                        2,
+                    ),
+                ]
+            } else {
+                vec![ResponseType::ToTaskNum(1)]
+            },
+        })
+        .collect();
+    let mut mock_cfg = MockPollCfg::new(tasks, true, 0);
+    mock_cfg
+        .mock_client
+        .expect_complete_workflow_task()
+        .times(4)
+        .returning(|_| Ok(Default::default()));
+    let mut mock = build_mock_pollers(mock_cfg);
+    mock.worker_cfg(|wc| wc.max_cached_workflows = 2);
+    let core = mock_worker(mock);
+    let assert_evicts = |act: &WorkflowActivation, run_id: &str| {
+        assert_eq!(act.run_id, run_id);
+        assert_matches!(
+            act.jobs.as_slice(),
+            [WorkflowActivationJob {
+                variant: Some(workflow_activation_job::Variant::RemoveFromCache(_)),
+            }]
+        );
+    };
+
+    let p1 = core.poll_workflow_activation().await.unwrap();
+    let p2 = core.poll_workflow_activation().await.unwrap();
+    // The third run can't be cached until the first run, which is least recently used, no longer
+    // has an outstanding activation
+    let p3 = core.poll_workflow_activation();
+    advance_fut!(p3);
+    core.complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
+        p1.run_id.clone(),
+        start_timer_cmd(1, Duration::from_secs(1)),
+    ))
+    .await
+    .unwrap();
+    let evict = p3.await.unwrap();
+    assert_evicts(&evict, &p1.run_id);
+    core.complete_workflow_activation(WorkflowActivationCompletion::empty(evict.run_id))
+        .await
+        .unwrap();
+    let p3 = core.poll_workflow_activation().await.unwrap();
+    assert_matches!(
+        &p3.jobs[0].variant,
+        Some(workflow_activation_job::Variant::InitializeWorkflow(sw)) if sw.workflow_id == "wf-3"
+    );
+    assert_eq!(core.cached_workflows().await, 2);
+    for run_id in [&p2.run_id, &p3.run_id] {
+        core.complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
+            run_id.clone(),
+            start_timer_cmd(1, Duration::from_secs(1)),
+        ))
+        .await
+        .unwrap();
+    }
+
+    // The first run returning evicts the second, which is now least recently used, and is then
+    // rebuilt by replaying its full history
+    release_wf_1.add_permits(1);
+    let evict = core.poll_workflow_activation().await.unwrap();
+    assert_evicts(&evict, &p2.run_id);
+    core.complete_workflow_activation(WorkflowActivationCompletion::empty(evict.run_id))
         .await
         .unwrap();
-    let wf_task = core.poll_workflow_activation().await.unwrap();
+    let replay = core.poll_workflow_activation().await.unwrap();
+    assert_eq!(replay.run_id, p1.run_id);
+    assert!(replay.is_replaying);
     assert_matches!(
-        wf_task.jobs.as_slice(),
+        replay.jobs.as_slice(),
         [WorkflowActivationJob {
-            variant: Some(workflow_activation_job::Variant::SignalWorkflow(_)),
-        },]
+            variant: Some(workflow_activation_job::Variant::InitializeWorkflow(_)),
+        }]
     );
-    core.complete_workflow_activation(WorkflowActivationCompletion::from_cmds(
-        wf_task.run_id,
-        vec![CompleteWorkflowExecution { result: None }.into()],
+    core.complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
+        replay.run_id,
+        start_timer_cmd(1, Duration::from_secs(1)),
     ))
     .await
     .unwrap();
-    core.shutdown().await;
+    let fired = core.poll_workflow_activation().await.unwrap();
+    assert_matches!(
+        fired.jobs.as_slice(),
+        [WorkflowActivationJob {
+            variant: Some(workflow_activation_job::Variant::FireTimer(_)),
+        }]
+    );
+    core.complete_execution(&fired.run_id).await;
+    core.drain_pollers_and_shutdown().await;
 }
 
-#[tokio::test]
-async fn poll_faster_than_complete_wont_overflow_cache() {
-    // Make workflow tasks for 5 different runs
-    let tasks: Vec<_> = (1..=5)
+/// Builds a worker which will see one new run, which is "big" because lang starts many timers in
+/// it, and then `num_small` ordinary runs
+fn big_then_small_runs_worker(num_small: usize, max_cache_bytes: Option<usize>) -> Worker {
+    let tasks: Vec<_> = (0..=num_small)
         .map(|i| FakeWfResponses {
             wf_id: format!("wf-{i}"),
             hist: canned_histories::single_timer("1"),
@@ -1796,122 +3881,79 @@ async fn poll_faster_than_complete_wont_overflow_cache() {
     let mut mock_client = mock_workflow_client();
     mock_client
         .expect_complete_workflow_task()
-        .times(3)
+        .times(num_small + 1)
         .returning(|_| Ok(Default::default()));
     let mut mock_cfg = MockPollCfg::new(tasks, true, 0);
     mock_cfg.mock_client = mock_client;
     let mut mock = build_mock_pollers(mock_cfg);
     mock.worker_cfg(|wc| {
-        wc.max_cached_workflows = 3;
-        wc.max_outstanding_workflow_tasks = Some(3);
+        wc.max_cached_workflows = 10;
+        wc.max_cache_bytes = max_cache_bytes;
     });
-    let core = mock_worker(mock);
-    // Poll 4 times, completing once, such that max tasks are never exceeded
-    let p1 = core.poll_workflow_activation().await.unwrap();
-    let p2 = core.poll_workflow_activation().await.unwrap();
-    let p3 = core.poll_workflow_activation().await.unwrap();
-    for (i, p_res) in [&p1, &p2, &p3].into_iter().enumerate() {
-        assert_matches!(
-            &p_res.jobs[0].variant,
-            Some(workflow_activation_job::Variant::InitializeWorkflow(sw))
-            if sw.workflow_id == format!("wf-{}", i + 1)
-        );
-    }
-    // Complete first task to free a wft slot. Cache size is at 3
-    core.complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
-        p1.run_id,
-        start_timer_cmd(1, Duration::from_secs(1)),
+    mock_worker(mock)
+}
+
+async fn complete_big_run(core: &Worker) -> String {
+    let act = core.poll_workflow_activation().await.unwrap();
+    core.complete_workflow_activation(WorkflowActivationCompletion::from_cmds(
+        act.run_id.clone(),
+        (1..=1000)
+            .map(|seq| start_timer_cmd(seq, Duration::from_secs(1)))
+            .collect(),
     ))
     .await
     .unwrap();
-    // Now we're at cache limit. We will poll for a task, discover it is for a new run, issue
-    // an eviction, and buffer the new run task. However, the run we're trying to evict has pending
-    // activations! Thus, we must complete them first before this poll will unblock, and then it
-    // will unblock with the eviciton.
-    let p4 = core.poll_workflow_activation();
-    // Make sure the task gets buffered before we start the complete, so the LRU list is in the
-    // expected order and what we expect to evict will be evicted.
-    advance_fut!(p4);
-    let p4 = async {
-        let p4 = p4.await.unwrap();
-        assert_matches!(
-            &p4.jobs.as_slice(),
-            [WorkflowActivationJob {
-                variant: Some(workflow_activation_job::Variant::RemoveFromCache(_)),
-            }]
-        );
-        p4
-    };
-    let p2_pending_completer = async {
-        core.complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
-            p2.run_id,
-            start_timer_cmd(1, Duration::from_secs(1)),
-        ))
+    act.run_id
+}
+
+#[tokio::test]
+async fn cache_byte_limit_evicts_big_run_for_small_ones() {
+    // Find out how big the big run is, with no byte limit
+    let core = big_then_small_runs_worker(0, None);
+    complete_big_run(&core).await;
+    let big_run_bytes = core.cached_workflow_bytes().await;
+    core.shutdown().await;
+
+    let max_cache_bytes = big_run_bytes / 2;
+    let core = big_then_small_runs_worker(3, Some(max_cache_bytes));
+    let big_run_id = complete_big_run(&core).await;
+    // Being the only run, the big one stays cached even though it exceeds the limit
+    assert_eq!(core.cached_workflows().await, 1);
+
+    // The first small run needs room, which is made by evicting the big one, even though the run
+    // count limit is nowhere near reached
+    let evict = core.poll_workflow_activation().await.unwrap();
+    assert_eq!(evict.run_id, big_run_id);
+    assert_matches!(
+        evict.jobs.as_slice(),
+        [WorkflowActivationJob {
+            variant: Some(workflow_activation_job::Variant::RemoveFromCache(rc)),
+        }] if rc.reason() == EvictionReason::CacheFull
+    );
+    core.complete_workflow_activation(WorkflowActivationCompletion::empty(evict.run_id))
         .await
         .unwrap();
-    };
-    let (p4, _) = join!(p4, p2_pending_completer);
-    assert_eq!(core.cached_workflows().await, 3);
 
-    // This poll should also block until the eviction is actually completed
-    let blocking_poll = async {
-        let res = core.poll_workflow_activation().await.unwrap();
+    // All the small runs fit together, so none of them are evicted to make room for the others
+    for i in 1..=3 {
+        let act = core.poll_workflow_activation().await.unwrap();
         assert_matches!(
-            &res.jobs[0].variant,
+            &act.jobs[0].variant,
             Some(workflow_activation_job::Variant::InitializeWorkflow(sw))
-            if sw.workflow_id == format!("wf-{}", 4)
-        );
-        res
-    };
-    let complete_evict = async {
-        core.complete_workflow_activation(WorkflowActivationCompletion::empty(p4.run_id))
-            .await
-            .unwrap();
-    };
-
-    let (_p5, _) = join!(blocking_poll, complete_evict);
-    assert_eq!(core.cached_workflows().await, 3);
-    // The next poll will get an buffer a task for a new run, and generate an eviction for p3 but
-    // that eviction cannot be obtained until we complete the existing outstanding task.
-    let p6 = async {
-        let p6 = core.poll_workflow_activation().await.unwrap();
-        assert_matches!(
-            p6.jobs.as_slice(),
-            [WorkflowActivationJob {
-                variant: Some(workflow_activation_job::Variant::RemoveFromCache(_)),
-            }]
+            if sw.workflow_id == format!("wf-{i}")
         );
-        p6
-    };
-    let completer = async {
         core.complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
-            p3.run_id,
+            act.run_id,
             start_timer_cmd(1, Duration::from_secs(1)),
         ))
         .await
         .unwrap();
-    };
-    let (p6, _) = join!(p6, completer);
-    let complete_evict = async {
-        core.complete_workflow_activation(WorkflowActivationCompletion::empty(p6.run_id))
-            .await
-            .unwrap();
-    };
-    let blocking_poll = async {
-        // This poll will also block until the last eviction goes through, and when it does it'll
-        // produce the final start workflow task
-        let res = core.poll_workflow_activation().await.unwrap();
-        assert_matches!(
-            &res.jobs[0].variant,
-            Some(workflow_activation_job::Variant::InitializeWorkflow(sw))
-            if sw.workflow_id == "wf-5"
-        );
-    };
-
-    join!(blocking_poll, complete_evict);
-    // p5 outstanding and final poll outstanding -- hence one permit available
-    assert_eq!(core.available_wft_permits(), Some(1));
+    }
     assert_eq!(core.cached_workflows().await, 3);
+    let small_runs_bytes = core.cached_workflow_bytes().await;
+    assert!(small_runs_bytes > 0);
+    assert!(small_runs_bytes <= max_cache_bytes);
+    core.shutdown().await;
 }
 
 #[tokio::test]
@@ -2144,6 +4186,124 @@ async fn continue_as_new_preserves_some_values() {
     worker.shutdown().await;
 }
 
+/// Context like a W3C traceparent reaches lang through the start job's headers, and lang passing it
+/// on to the activities it schedules sends it to server in their commands
+#[tokio::test]
+async fn workflow_start_headers_can_be_propagated_to_activities() {
+    let wfid = "fake_wf_id";
+    let traceparent = HashMap::<String, Payload>::from([(
+        "traceparent".to_string(),
+        b"00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01".into(),
+    )]);
+    let mut wes_attrs = default_wes_attribs();
+    wes_attrs.header = Some(traceparent.clone().into());
+    let mut t = TestHistoryBuilder::default();
+    t.add(wes_attrs);
+    t.add_full_wf_task();
+    let mut mock_client = mock_workflow_client();
+    let expected_header = traceparent.clone();
+    mock_client
+        .expect_complete_workflow_task()
+        .times(1)
+        .returning(move |c| {
+            assert_matches!(
+                c.commands[0].attributes.as_ref(),
+                Some(Attributes::ScheduleActivityTaskCommandAttributes(a))
+                    if a.header == Some(Header::from(expected_header.clone()))
+            );
+            Ok(Default::default())
+        });
+    let mock = single_hist_mock_sg(wfid, t, vec![ResponseType::AllHistory], mock_client, true);
+    let worker = mock_worker(mock);
+
+    let act = worker.poll_workflow_activation().await.unwrap();
+    let headers = assert_matches!(
+        &act.jobs[0].variant,
+        Some(workflow_activation_job::Variant::InitializeWorkflow(init)) => init.headers.clone()
+    );
+    assert_eq!(headers, traceparent);
+    worker
+        .complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
+            act.run_id,
+            ScheduleActivity {
+                seq: 1,
+                activity_id: "act".to_string(),
+                activity_type: DEFAULT_ACTIVITY_TYPE.to_string(),
+                task_queue: TEST_Q.to_string(),
+                start_to_close_timeout: Some(prost_dur!(from_secs(10))),
+                headers,
+                ..Default::default()
+            }
+            .into(),
+        ))
+        .await
+        .unwrap();
+    worker.shutdown().await;
+}
+
+#[tokio::test]
+async fn continue_as_new_of_retrying_cron_workflow() {
+    let wfid = "fake_wf_id";
+    let retry_policy = RetryPolicy {
+        maximum_attempts: 5,
+        ..Default::default()
+    };
+    let mut wes_attrs = default_wes_attribs();
+    wes_attrs.retry_policy = Some(retry_policy.clone());
+    wes_attrs.attempt = 3;
+    wes_attrs.cron_schedule = "0 * * * *".to_string();
+    wes_attrs.workflow_execution_expiration_time =
+        Some((SystemTime::now() + Duration::from_secs(60 * 60)).into());
+    wes_attrs.first_workflow_task_backoff = Some(prost_dur!(from_secs(30)));
+    let mut mock_client = mock_workflow_client();
+    let t = {
+        let mut t = TestHistoryBuilder::default();
+        t.add(wes_attrs);
+        t.add_full_wf_task();
+        t
+    };
+    mock_client
+        .expect_complete_workflow_task()
+        .returning(move |mut c| {
+            let cmd = c.commands.pop().unwrap().attributes.unwrap();
+            if let Attributes::ContinueAsNewWorkflowExecutionCommandAttributes(a) = cmd {
+                assert_eq!(a.cron_schedule, "0 * * * *");
+                assert_eq!(a.retry_policy.as_ref(), Some(&retry_policy));
+                // The retry backoff of this run has nothing to do with when the next one starts
+                assert_eq!(a.backoff_start_interval, None);
+                // Capped to what's left before the execution as a whole expires
+                let run_timeout: Duration = a.workflow_run_timeout.unwrap().try_into().unwrap();
+                assert!(run_timeout <= Duration::from_secs(60 * 60));
+                assert!(run_timeout > Duration::from_secs(50 * 60));
+            } else {
+                panic!("Wrong attributes type");
+            }
+            Ok(Default::default())
+        });
+    let mock = single_hist_mock_sg(wfid, t, vec![ResponseType::AllHistory], mock_client, true);
+    let worker = mock_worker(mock);
+    let r = worker.poll_workflow_activation().await.unwrap();
+    assert_matches!(
+        r.jobs.as_slice(),
+        [WorkflowActivationJob {
+            variant: Some(workflow_activation_job::Variant::InitializeWorkflow(init)),
+        }] if init.attempt == 3 && init.cron_schedule == "0 * * * *"
+    );
+    worker
+        .complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
+            r.run_id,
+            ContinueAsNewWorkflowExecution {
+                workflow_type: "meow".to_string(),
+                workflow_run_timeout: Some(prost_dur!(from_secs(2 * 60 * 60))),
+                ..Default::default()
+            }
+            .into(),
+        ))
+        .await
+        .unwrap();
+    worker.shutdown().await;
+}
+
 #[rstest]
 #[tokio::test]
 async fn ignorable_events_are_ok(#[values(true, false)] attribs_unset: bool) {
@@ -2175,6 +4335,92 @@ async fn ignorable_events_are_ok(#[values(true, false)] attribs_unset: bool) {
     );
 }
 
+/// A WFT whose new events produce no jobs must be completed by core on lang's behalf rather than
+/// handing lang an empty activation.
+#[tokio::test]
+async fn wft_with_only_ignorable_events_autocompletes() {
+    let mut t = TestHistoryBuilder::default();
+    t.add_by_type(EventType::WorkflowExecutionStarted);
+    t.add_full_wf_task();
+    let id = t.add(WorkflowPropertiesModifiedExternallyEventAttributes::default());
+    t.modify_event(id, |e| {
+        e.worker_may_ignore = true;
+        e.attributes = None;
+    });
+    t.add_workflow_task_scheduled_and_started();
+
+    let mut mh = MockPollCfg::from_resp_batches("fake_wf_id", t, [1, 2], mock_workflow_client());
+    mh.num_expected_completions = Some(2.into());
+    mh.completion_mock_fn = Some(Box::new(|c| {
+        assert!(c.commands.is_empty());
+        Ok(Default::default())
+    }));
+    let mut mock = build_mock_pollers(mh);
+    mock.worker_cfg(|wc| wc.max_cached_workflows = 1);
+    let core = mock_worker(mock);
+
+    let act = core.poll_workflow_activation().await.unwrap();
+    assert_matches!(
+        act.jobs.as_slice(),
+        [WorkflowActivationJob {
+            variant: Some(workflow_activation_job::Variant::InitializeWorkflow(_)),
+        }]
+    );
+    core.complete_workflow_activation(WorkflowActivationCompletion::empty(act.run_id))
+        .await
+        .unwrap();
+    // The second task only contains the ignorable event, so lang never sees it and the next poll
+    // finds no more work.
+    assert_matches!(
+        core.poll_workflow_activation().await.unwrap_err(),
+        PollWfError::ShutDown
+    );
+    core.shutdown().await;
+}
+
+/// Events server records on its own about the execution's properties have no matching command, and
+/// must not disturb matching the commands around them.
+#[rstest(hist_batches, case::incremental(&[1, 2]), case::replay(&[2]))]
+#[tokio::test]
+async fn metadata_events_between_activity_schedule_and_completion(hist_batches: &'static [usize]) {
+    let mut t = TestHistoryBuilder::default();
+    t.add_by_type(EventType::WorkflowExecutionStarted);
+    t.add_full_wf_task();
+    let scheduled_event_id = t.add_activity_task_scheduled("act");
+    t.add(WorkflowPropertiesModifiedExternallyEventAttributes::default());
+    t.add(ActivityPropertiesModifiedExternallyEventAttributes {
+        scheduled_event_id,
+        new_retry_policy: Some(RetryPolicy {
+            maximum_attempts: 3,
+            ..Default::default()
+        }),
+    });
+    let started_event_id = t.add_activity_task_started(scheduled_event_id);
+    t.add_activity_task_completed(scheduled_event_id, started_event_id, Default::default());
+    t.add_workflow_task_scheduled_and_started();
+    let core = build_fake_worker("fake_wf_id", t, hist_batches);
+
+    poll_and_reply(
+        &core,
+        NonSticky,
+        &[
+            gen_assert_and_reply(
+                &job_assert!(workflow_activation_job::Variant::InitializeWorkflow(_)),
+                vec![ScheduleActivity {
+                    activity_id: "act".to_string(),
+                    ..default_act_sched()
+                }
+                .into()],
+            ),
+            gen_assert_and_reply(
+                &job_assert!(workflow_activation_job::Variant::ResolveActivity(_)),
+                vec![CompleteWorkflowExecution { result: None }.into()],
+            ),
+        ],
+    )
+    .await;
+}
+
 #[tokio::test]
 async fn fetching_to_continue_replay_works() {
     let mut mock_client = mock_workflow_client();
@@ -2284,6 +4530,42 @@ async fn fetching_error_evicts_wf() {
     );
 }
 
+#[tokio::test]
+async fn failing_to_report_workflow_completion_evicts_for_the_failure() {
+    let mut t = TestHistoryBuilder::default();
+    t.add_by_type(EventType::WorkflowExecutionStarted);
+    t.add_workflow_task_scheduled_and_started();
+    let mut mock_client = mock_workflow_client();
+    mock_client
+        .expect_complete_workflow_task()
+        .times(1)
+        .returning(|_| Err(tonic::Status::internal("Server fell over")));
+    let mut mock = single_hist_mock_sg("wfid", t, [1], mock_client, true);
+    mock.worker_cfg(|wc| wc.max_cached_workflows = 10);
+    let core = mock_worker(mock);
+
+    let act = core.poll_workflow_activation().await.unwrap();
+    core.complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
+        act.run_id,
+        CompleteWorkflowExecution { result: None }.into(),
+    ))
+    .await
+    .unwrap();
+    // Server never accepted the completion, so the run is evicted for the error rather than as a
+    // finished workflow
+    let evict_act = core.poll_workflow_activation().await.unwrap();
+    assert_matches!(
+        evict_act.jobs.as_slice(),
+        [WorkflowActivationJob {
+            variant: Some(workflow_activation_job::Variant::RemoveFromCache(r)),
+        }] => r.reason() == EvictionReason::Fatal
+    );
+    core.complete_workflow_activation(WorkflowActivationCompletion::empty(evict_act.run_id))
+        .await
+        .unwrap();
+    core.shutdown().await;
+}
+
 /// This test verifies that if we fail to fetch a page during a completion, that we don't get stuck
 /// in the complete waiting for the completion to finish.
 #[tokio::test]
@@ -2354,6 +4636,68 @@ async fn ensure_fetching_fail_during_complete_sends_task_failure() {
     core.shutdown().await;
 }
 
+#[tokio::test]
+async fn exceeding_the_history_fetch_limit_fails_the_task_and_is_counted() {
+    let wfid = "fake_wf_id";
+    let mut t = TestHistoryBuilder::default();
+    t.add_by_type(EventType::WorkflowExecutionStarted);
+    t.add_full_wf_task(); // started 3
+    t.add_we_signaled("sig1", vec![]);
+    t.add_full_wf_task(); // started 7
+    t.add_by_type(EventType::TimerStarted);
+    t.add_full_wf_task(); // started 11
+    t.add_workflow_execution_completed();
+
+    // Set up like the test above, so the page is fetched while the run is cached
+    let mut first_poll = hist_to_poll_resp(&t, wfid, ResponseType::OneTask(4)).resp;
+    first_poll.previous_started_event_id = 0;
+    first_poll.started_event_id = 11;
+    let mut next_page: GetWorkflowExecutionHistoryResponse =
+        t.get_full_history_info().unwrap().into();
+    next_page.history.as_mut().unwrap().events.truncate(9);
+    next_page.next_page_token = vec![2];
+
+    let mut mock = mock_workflow_client();
+    mock.expect_get_workflow_execution_history()
+        .returning(move |_, _, _| Ok(next_page.clone()))
+        .times(1);
+    mock.expect_fail_workflow_task()
+        .returning(|_, _, _| Ok(Default::default()))
+        .times(1);
+
+    let telem = BufferedTelemetry::new();
+    let mut mock = single_hist_mock_sg(wfid, t, [ResponseType::Raw(first_poll)], mock, true);
+    mock.make_wft_stream_interminable();
+    mock.worker_cfg(|wc| {
+        wc.max_cached_workflows = 2;
+        // Less than any page
+        wc.max_history_fetch_bytes = Some(10);
+    });
+    let core = mock_worker_with_telemetry(mock, &telem);
+
+    let wf_task = core.poll_workflow_activation().await.unwrap();
+    core.complete_workflow_activation(WorkflowActivationCompletion::empty(wf_task.run_id))
+        .await
+        .unwrap();
+
+    let wf_task = core.poll_workflow_activation().await.unwrap();
+    assert_matches!(
+        wf_task.jobs.as_slice(),
+        [WorkflowActivationJob {
+            variant: Some(workflow_activation_job::Variant::RemoveFromCache(c)),
+        }] if c.message.starts_with("History too large for this worker's configured limit")
+    );
+    core.shutdown().await;
+
+    let exceeded: Vec<_> = telem
+        .updates()
+        .into_iter()
+        .filter(|(name, _, _)| name.ends_with("workflow_history_fetch_limit_exceeded"))
+        .map(|(_, _, update)| update)
+        .collect();
+    assert_matches!(exceeded.as_slice(), [MetricUpdateVal::Delta(1)]);
+}
+
 #[tokio::test]
 async fn lang_internal_flags() {
     let mut t = TestHistoryBuilder::default();
@@ -3115,3 +5459,357 @@ async fn pass_timer_summary_to_metadata() {
         .unwrap();
     worker.run_until_done().await.unwrap();
 }
+
+#[tokio::test]
+async fn activation_metadata_matches_between_execution_and_replay() {
+    async fn metadata_of_each_task(worker: &Worker) -> Vec<ActivationMetadata> {
+        let act = worker.poll_workflow_activation().await.unwrap();
+        let first = act.metadata.clone().unwrap();
+        worker
+            .complete_timer(&act.run_id, 1, Duration::from_secs(1))
+            .await;
+        let act = worker.poll_workflow_activation().await.unwrap();
+        let second = act.metadata.clone().unwrap();
+        worker.complete_execution(&act.run_id).await;
+        vec![first, second]
+    }
+
+    let t = canned_histories::single_timer("1");
+    let live = metadata_of_each_task(&build_fake_worker("fake_wf_id", t.clone(), [1, 2])).await;
+    let replayed = metadata_of_each_task(&build_fake_worker(
+        "fake_wf_id",
+        t,
+        [ResponseType::AllHistory],
+    ))
+    .await;
+
+    assert_eq!(live, replayed);
+    for meta in &live {
+        assert_eq!(meta.attempt, 1);
+        assert!(meta.scheduled_time.is_some());
+        assert!(meta.started_time.is_some());
+    }
+    assert_ne!(live[0].started_time, live[1].started_time);
+}
+
+#[tokio::test]
+async fn invalid_wft_event_times_are_left_out_of_activation_metadata() {
+    let mut t = canned_histories::single_timer("1");
+    // The first task's scheduled event
+    t.modify_event(2, |e| {
+        e.event_time = Some(prost_types::Timestamp {
+            seconds: -5,
+            nanos: 0,
+        })
+    });
+    let worker = build_fake_worker("fake_wf_id", t, [1, 2]);
+
+    let act = worker.poll_workflow_activation().await.unwrap();
+    let meta = act.metadata.clone().unwrap();
+    assert_eq!(meta.scheduled_time, None);
+    assert!(meta.started_time.is_some());
+    worker
+        .complete_timer(&act.run_id, 1, Duration::from_secs(1))
+        .await;
+    let act = worker.poll_workflow_activation().await.unwrap();
+    assert!(act.metadata.unwrap().scheduled_time.is_some());
+    worker.complete_execution(&act.run_id).await;
+}
+
+#[rstest]
+#[case::missing_timer_duration(
+    StartTimer { seq: 1, start_to_fire_timeout: None, summary: None }.into(),
+    "start_to_fire_timeout"
+)]
+#[case::zero_timer_duration(
+    StartTimer {
+        seq: 1,
+        start_to_fire_timeout: Some(prost_types::Duration { seconds: 0, nanos: 0 }),
+        summary: None,
+    }
+    .into(),
+    "start_to_fire_timeout"
+)]
+#[case::negative_timer_duration(
+    StartTimer {
+        seq: 1,
+        start_to_fire_timeout: Some(prost_types::Duration { seconds: -1, nanos: 0 }),
+        summary: None,
+    }
+    .into(),
+    "start_to_fire_timeout"
+)]
+#[case::negative_activity_timeout(
+    ScheduleActivity {
+        heartbeat_timeout: Some(prost_types::Duration { seconds: -10, nanos: 0 }),
+        ..default_act_sched()
+    }
+    .into(),
+    "heartbeat_timeout"
+)]
+#[tokio::test]
+async fn invalid_command_durations_fail_wft_naming_field(
+    #[case] cmd: workflow_command::Variant,
+    #[case] field: &'static str,
+) {
+    let mut t = TestHistoryBuilder::default();
+    t.add_by_type(EventType::WorkflowExecutionStarted);
+    t.add_full_wf_task();
+
+    let mut mh = MockPollCfg::from_resp_batches(
+        "fake_wf_id",
+        t,
+        [ResponseType::AllHistory],
+        mock_workflow_client(),
+    );
+    mh.num_expected_fails = 1;
+    mh.expect_fail_wft_matcher = Box::new(move |_, _, f| {
+        f.as_ref()
+            .is_some_and(|f| f.message.contains(&format!("`{field}`")))
+    });
+    let core = mock_worker(build_mock_pollers(mh));
+
+    let act = core.poll_workflow_activation().await.unwrap();
+    core.complete_workflow_activation(WorkflowActivationCompletion::from_cmds(
+        act.run_id,
+        vec![cmd],
+    ))
+    .await
+    .unwrap();
+    core.handle_eviction().await;
+}
+
+#[tokio::test]
+async fn jobs_beyond_limit_are_delivered_in_follow_up_activations() {
+    let mut t = TestHistoryBuilder::default();
+    t.add_by_type(EventType::WorkflowExecutionStarted);
+    for i in 0..8 {
+        t.add_we_signaled(&format!("sig{i}"), vec![]);
+    }
+    t.add_workflow_task_scheduled_and_started();
+
+    let mut mh = MockPollCfg::from_resp_batches(
+        "fake_wf_id",
+        t,
+        [ResponseType::AllHistory],
+        mock_workflow_client(),
+    );
+    // Only once the last of the jobs has been delivered is the task completed
+    mh.num_expected_completions = Some(1.into());
+    mh.completion_mock_fn = Some(Box::new(|c| {
+        assert_matches!(
+            c.commands.as_slice(),
+            [cmd] if cmd.command_type() == CommandType::CompleteWorkflowExecution
+        );
+        Ok(Default::default())
+    }));
+    let mut mock = build_mock_pollers(mh);
+    mock.worker_cfg(|wc| wc.max_jobs_per_activation = Some(3));
+    let core = mock_worker(mock);
+
+    let job_names = |jobs: &[WorkflowActivationJob]| -> Vec<String> {
+        jobs.iter()
+            .map(|j| match j.variant.as_ref().unwrap() {
+                workflow_activation_job::Variant::InitializeWorkflow(_) => "init".to_string(),
+                workflow_activation_job::Variant::SignalWorkflow(s) => s.signal_name.clone(),
+                v => panic!("Unexpected job {v:?}"),
+            })
+            .collect()
+    };
+    for expected in [
+        vec!["init", "sig0", "sig1"],
+        vec!["sig2", "sig3", "sig4"],
+        vec!["sig5", "sig6", "sig7"],
+    ] {
+        let act = core.poll_workflow_activation().await.unwrap();
+        assert_eq!(job_names(&act.jobs), expected);
+        let completion = if expected.last() == Some(&"sig7") {
+            WorkflowActivationCompletion::from_cmd(
+                act.run_id,
+                CompleteWorkflowExecution { result: None }.into(),
+            )
+        } else {
+            WorkflowActivationCompletion::empty(act.run_id)
+        };
+        core.complete_workflow_activation(completion).await.unwrap();
+    }
+    core.drain_pollers_and_shutdown().await;
+}
+
+#[tokio::test]
+async fn hot_runs_ranks_cached_runs_by_processing() {
+    let hists = vec![
+        FakeWfResponses {
+            wf_id: "busy".to_string(),
+            hist: canned_histories::long_sequential_timers(5),
+            response_batches: vec![1.into(), 2.into(), 3.into()],
+        },
+        FakeWfResponses {
+            wf_id: "quiet".to_string(),
+            hist: canned_histories::single_timer("1"),
+            response_batches: vec![1.into()],
+        },
+    ];
+    let mut mock = build_mock_pollers(MockPollCfg::new(hists, true, 0));
+    mock.worker_cfg(|wc| wc.max_cached_workflows = 2);
+    // Both runs must stay cached once the mock runs out of tasks
+    mock.make_wft_stream_interminable();
+    let core = mock_worker(mock);
+
+    for _ in 0..4 {
+        let act = core.poll_workflow_activation().await.unwrap();
+        let next_timer = match &act.jobs[0].variant {
+            Some(workflow_activation_job::Variant::FireTimer(FireTimer { seq })) => seq + 1,
+            _ => 1,
+        };
+        core.complete_workflow_activation(WorkflowActivationCompletion::from_cmd(
+            act.run_id,
+            start_timer_cmd(next_timer, Duration::from_secs(1)),
+        ))
+        .await
+        .unwrap();
+    }
+
+    let wf_ids = |ranked: &[RunProcessingStats]| -> Vec<_> {
+        ranked.iter().map(|s| s.workflow_id.clone()).collect()
+    };
+    let hot = core.hot_runs(2);
+    assert_eq!(wf_ids(&hot.by_activations), ["busy", "quiet"]);
+    assert_eq!(hot.by_activations[0].activations, 3);
+    assert_eq!(hot.by_activations[1].activations, 1);
+    assert_eq!(wf_ids(&hot.by_jobs), ["busy", "quiet"]);
+    assert_eq!(hot.by_machine_time.len(), 2);
+    assert!(hot.by_buffered_jobs.is_empty());
+    assert_eq!(wf_ids(&core.hot_runs(1).by_activations), ["busy"]);
+
+    // Evicted runs no longer count
+    let busy_run = hot.by_activations[0].run_id.clone();
+    core.request_workflow_eviction(&busy_run);
+    let evict_act = core.poll_workflow_activation().await.unwrap();
+    assert_eq!(evict_act.run_id, busy_run);
+    core.complete_workflow_activation(WorkflowActivationCompletion::empty(evict_act.run_id))
+        .await
+        .unwrap();
+    assert_eq!(core.cached_workflows().await, 1);
+    assert_eq!(wf_ids(&core.hot_runs(2).by_activations), ["quiet"]);
+    core.drain_pollers_and_shutdown().await;
+}
+
+#[tokio::test]
+async fn runs_hitting_fatal_machine_errors_are_recovered() {
+    let telem = BufferedTelemetry::new();
+    let wfid = "fake_wf_id";
+    let mut t = TestHistoryBuilder::default();
+    t.add_by_type(EventType::WorkflowExecutionStarted);
+    t.add_by_type(EventType::WorkflowExecutionSignaled);
+    t.add_workflow_task_scheduled_and_started();
+    let run_id = t.get_orig_run_id().to_string();
+    let mut resp = hist_to_poll_resp(&t, wfid, ResponseType::AllHistory).resp;
+    // A signal without its attributes can't be applied to the run's machines
+    for e in resp.history.as_mut().unwrap().events.iter_mut() {
+        if e.event_type() == EventType::WorkflowExecutionSignaled {
+            e.attributes = None;
+        }
+    }
+
+    // The default mock client accepts any sticky queue reset
+    let mut mock_client = MockWorkerClient::new();
+    mock_client
+        .expect_capabilities()
+        .returning(|| Some(*DEFAULT_TEST_CAPABILITIES));
+    mock_client
+        .expect_workers()
+        .returning(|| DEFAULT_WORKERS_REGISTRY.clone());
+    mock_client.expect_is_mock().returning(|| true);
+    mock_client
+        .expect_shutdown_worker()
+        .returning(|_| Ok(Default::default()));
+    mock_client
+        .expect_cancel_outstanding_calls()
+        .returning(|| ());
+    let expected_run_id = run_id.clone();
+    mock_client
+        .expect_reset_sticky_task_queue()
+        .withf(move |wid, rid| *wid == wfid && *rid == expected_run_id.as_str())
+        .times(1)
+        .returning(|_, _| Ok(Default::default()));
+    let mut mh = MockPollCfg::from_resp_batches(wfid, t, [ResponseType::Raw(resp)], mock_client);
+    mh.num_expected_fails = 1;
+    mh.expect_fail_wft_matcher = Box::new(|_, cause, f| {
+        *cause == WorkflowTaskFailedCause::Unspecified
+            && f.as_ref()
+                .is_some_and(|f| f.message.contains("did not have appropriate attributes"))
+    });
+    let mut mock = build_mock_pollers(mh);
+    mock.worker_cfg(|wc| wc.max_cached_workflows = 10);
+    let core = mock_worker_with_telemetry(mock, &telem);
+
+    // Lang never sees the broken run, only its eviction
+    let act = core.poll_workflow_activation().await.unwrap();
+    assert_eq!(act.run_id, run_id);
+    assert!(act.eviction_reason().is_some());
+    core.complete_workflow_activation(WorkflowActivationCompletion::empty(act.run_id))
+        .await
+        .unwrap();
+    core.drain_pollers_and_shutdown().await;
+
+    let recoveries: Vec<_> = telem
+        .updates()
+        .into_iter()
+        .filter(|(name, _, _)| name.ends_with("workflow_run_recoveries"))
+        .map(|(_, attrs, update)| (attrs.get("cause").cloned(), update))
+        .collect();
+    assert_matches!(
+        recoveries.as_slice(),
+        [(Some(cause), MetricUpdateVal::Delta(1))] if cause == "fatal_machine_error"
+    );
+}
+
+#[tokio::test]
+async fn ignored_cancellation_fails_the_task_when_configured() {
+    let telem = BufferedTelemetry::new();
+    let mut t = TestHistoryBuilder::default();
+    t.add_by_type(EventType::WorkflowExecutionStarted);
+    t.add_full_wf_task();
+    t.add_cancel_requested();
+    t.add_workflow_task_scheduled_and_started();
+    let mut mh = MockPollCfg::from_resp_batches("fake_wf_id", t, [1, 2], mock_workflow_client());
+    mh.num_expected_fails = 1;
+    mh.expect_fail_wft_matcher = Box::new(|_, _, f| {
+        f.as_ref()
+            .is_some_and(|f| f.message.contains("Lang may be dropping them"))
+    });
+    let mut mock = build_mock_pollers(mh);
+    mock.worker_cfg(|wc| {
+        wc.max_cached_workflows = 10;
+        wc.dropped_job_detection_threshold = Some(1);
+        wc.fail_wft_on_dropped_jobs = true;
+    });
+    let core = mock_worker_with_telemetry(mock, &telem);
+
+    let act = core.poll_workflow_activation().await.unwrap();
+    core.complete_workflow_activation(WorkflowActivationCompletion::empty(act.run_id))
+        .await
+        .unwrap();
+    let act = core.poll_workflow_activation().await.unwrap();
+    assert_matches!(
+        act.jobs.as_slice(),
+        [WorkflowActivationJob {
+            variant: Some(workflow_activation_job::Variant::CancelWorkflow(_)),
+        }]
+    );
+    // Lang answers the cancellation with nothing at all
+    core.complete_workflow_activation(WorkflowActivationCompletion::empty(act.run_id))
+        .await
+        .unwrap();
+    core.handle_eviction().await;
+    core.drain_pollers_and_shutdown().await;
+
+    let dropped: Vec<_> = telem
+        .updates()
+        .into_iter()
+        .filter(|(name, _, _)| name.ends_with("workflow_suspected_dropped_jobs"))
+        .map(|(_, _, update)| update)
+        .collect();
+    assert_matches!(dropped.as_slice(), [MetricUpdateVal::Delta(1)]);
+}
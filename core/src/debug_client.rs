@@ -2,9 +2,11 @@
 //! machinery.
 
 use anyhow::Context;
-use hyper::http::HeaderValue;
 use prost::Message;
-use reqwest::{self, header::HeaderMap};
+use reqwest::{
+    self,
+    header::{HeaderMap, HeaderValue},
+};
 use std::time::Duration;
 use temporal_sdk_core_protos::temporal::api::history::v1::History;
 use url::Url;
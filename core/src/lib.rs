@@ -12,6 +12,7 @@ extern crate tracing;
 extern crate core;
 
 mod abstractions;
+mod blocking;
 #[cfg(feature = "debug-plugin")]
 pub mod debug_client;
 #[cfg(feature = "ephemeral-server")]
@@ -32,19 +33,21 @@ mod test_help;
 
 pub(crate) use temporal_sdk_core_api::errors;
 
+pub use abstractions::{SlotUsage, WorkerSlotUsage};
+pub use blocking::CoreBlocking;
 pub use pollers::{
-    Client, ClientOptions, ClientOptionsBuilder, ClientTlsConfig, RetryClient, RetryConfig,
-    TlsConfig, WorkflowClientTrait,
+    Client, ClientOptions, ClientOptionsBuilder, ClientTlsConfig, PollStats, RetryClient,
+    RetryConfig, TaskQueuePollStats, TlsConfig, WorkflowClientTrait,
 };
 pub use temporal_sdk_core_api as api;
 pub use temporal_sdk_core_protos as protos;
 pub use temporal_sdk_core_protos::TaskToken;
 pub use url::Url;
 pub use worker::{
-    FixedSizeSlotSupplier, RealSysInfo, ResourceBasedSlotsOptions,
-    ResourceBasedSlotsOptionsBuilder, ResourceBasedTuner, ResourceSlotOptions, SlotSupplierOptions,
-    TunerBuilder, TunerHolder, TunerHolderOptions, TunerHolderOptionsBuilder, Worker, WorkerConfig,
-    WorkerConfigBuilder,
+    ClockSkewEstimate, FixedSizeSlotSupplier, HotRuns, RealSysInfo, ResourceBasedSlotsOptions,
+    ResourceBasedSlotsOptionsBuilder, ResourceBasedTuner, ResourceSlotOptions, RunProcessingStats,
+    SlotSupplierOptions, TunerBuilder, TunerHolder, TunerHolderOptions, TunerHolderOptionsBuilder,
+    Worker, WorkerConfig, WorkerConfigBuilder,
 };
 
 use crate::{
@@ -53,7 +56,11 @@ use crate::{
         metrics::MetricsContext, remove_trace_subscriber_for_current_thread,
         set_trace_subscriber_for_current_thread, telemetry_init, TelemetryInstance,
     },
-    worker::client::WorkerClientBag,
+    worker::{
+        activity_poll_options,
+        client::{FailoverWorkerClient, WorkerClientBag, FAILOVER_RESOLVE_INTERVAL},
+        CacheSnapshot,
+    },
 };
 use anyhow::bail;
 use futures_util::Stream;
@@ -82,6 +89,50 @@ pub fn init_worker<CT>(
     worker_config: WorkerConfig,
     client: CT,
 ) -> Result<Worker, anyhow::Error>
+where
+    CT: Into<sealed::AnyClient>,
+{
+    init_worker_inner(runtime, worker_config, client, None)
+}
+
+/// Initialize a worker bound to a task queue, taking over the workflow cache of a worker which
+/// previously polled it, as exported by [Worker::export_cache_snapshot]. This lets a process
+/// replacing another (ex: during a rolling deploy) avoid replaying every cached workflow from the
+/// start.
+///
+/// If the snapshot can't be used, because it is malformed or damaged, from an incompatible version
+/// of core, or for a different task queue, a warning is logged and the worker starts with an empty
+/// cache, exactly as if [init_worker] had been called. A run's history from the snapshot is only
+/// used if it starts the same workflow type the run's next task is for.
+pub fn init_worker_with_cache_snapshot<CT>(
+    runtime: &CoreRuntime,
+    worker_config: WorkerConfig,
+    client: CT,
+    snapshot: &[u8],
+) -> Result<Worker, anyhow::Error>
+where
+    CT: Into<sealed::AnyClient>,
+{
+    let snapshot = match CacheSnapshot::decode_for(
+        snapshot,
+        &worker_config.namespace,
+        &worker_config.task_queue,
+    ) {
+        Ok(s) => Some(s),
+        Err(e) => {
+            warn!("Ignoring workflow cache snapshot: {e}");
+            None
+        }
+    };
+    init_worker_inner(runtime, worker_config, client, snapshot)
+}
+
+fn init_worker_inner<CT>(
+    runtime: &CoreRuntime,
+    worker_config: WorkerConfig,
+    client: CT,
+    snapshot: Option<CacheSnapshot>,
+) -> Result<Worker, anyhow::Error>
 where
     CT: Into<sealed::AnyClient>,
 {
@@ -92,8 +143,17 @@ where
     if client.namespace() == "" {
         bail!("Namespace cannot be empty");
     }
+    if !worker_config.no_remote_activities {
+        activity_poll_options(&worker_config)?;
+    }
     let client_ident = client.get_options().identity.clone();
-    let sticky_q = sticky_q_name_for_worker(&client_ident, &worker_config);
+    let mut sticky_q = sticky_q_name_for_worker(&client_ident, &worker_config);
+    if let Some(handed_over_q) = snapshot.as_ref().and_then(|s| s.sticky_queue.clone()) {
+        // Keep polling the queue server is already routing the cached runs' tasks to
+        if sticky_q.is_some() {
+            sticky_q = Some(handed_over_q);
+        }
+    }
     let client_bag = Arc::new(WorkerClientBag::new(
         client,
         worker_config.namespace.clone(),
@@ -101,13 +161,25 @@ where
         worker_config.worker_build_id.clone(),
         worker_config.use_worker_versioning,
     ));
+    let client_bag = Arc::new(FailoverWorkerClient::new(
+        client_bag,
+        worker_config.lifecycle_observer.clone(),
+        FAILOVER_RESOLVE_INTERVAL,
+    ));
 
-    Ok(Worker::new(
+    // The worker spawns its pollers & background tasks as it is constructed, and those must land
+    // on the runtime's executor even if the caller is not currently inside it.
+    let _rg = runtime.runtime_handle.enter();
+    let worker = Worker::new(
         worker_config,
         sticky_q,
         client_bag,
         Some(&runtime.telemetry),
-    ))
+    );
+    if let Some(snapshot) = snapshot {
+        worker.import_cache_snapshot(snapshot);
+    }
+    Ok(worker)
 }
 
 /// Create a worker for replaying one or more existing histories. It will auto-shutdown as soon as
@@ -132,10 +204,29 @@ pub(crate) fn init_worker_client(
     client: ConfiguredClient<TemporalServiceClientWithMetrics>,
 ) -> RetryClient<Client> {
     let mut client = Client::new(client, config.namespace.clone());
-    if let Some(ref id_override) = config.client_identity_override {
-        client.options_mut().identity.clone_from(id_override);
+    client.options_mut().identity = worker_identity(config, &client.options().identity);
+    let poll_retry_config = client.options().long_poll_retry_config.clone();
+    RetryClient::new(client, RetryConfig::default()).with_long_poll_retry_config(poll_retry_config)
+}
+
+/// The identity a worker sends to server: the one its config overrides with, else its client's,
+/// else [default_worker_identity]
+pub(crate) fn worker_identity(config: &WorkerConfig, client_identity: &str) -> String {
+    let identity = config
+        .client_identity_override
+        .as_deref()
+        .unwrap_or(client_identity);
+    if identity.is_empty() {
+        default_worker_identity()
+    } else {
+        identity.to_string()
     }
-    RetryClient::new(client, RetryConfig::default())
+}
+
+/// The identity workers use when neither their client nor their config specifies one
+fn default_worker_identity() -> String {
+    let host = sysinfo::System::host_name().unwrap_or_else(|| "unknown-host".to_string());
+    format!("{}@{host}", std::process::id())
 }
 
 /// Creates a unique sticky queue name for a worker, iff the config allows for 1 or more cached
@@ -194,8 +285,13 @@ mod sealed {
 /// Holds shared state/components needed to back instances of workers and clients. More than one
 /// may be instantiated, but typically only one is needed. More than one runtime instance may be
 /// useful if multiple different telemetry settings are required.
+///
+/// The tokio runtime backing it is either built and owned by core (see [CoreRuntime::new]) or
+/// provided by the embedder (see [CoreRuntime::init_with_runtime]). A runtime provided by the
+/// embedder is never shut down by core.
 pub struct CoreRuntime {
     telemetry: TelemetryInstance,
+    /// Only set when core built the runtime itself
     runtime: Option<tokio::runtime::Runtime>,
     runtime_handle: tokio::runtime::Handle,
 }
@@ -252,12 +348,27 @@ impl CoreRuntime {
                 }
             })
             .build()?;
-        let _rg = runtime.enter();
-        let mut me = Self::new_assume_tokio_initialized_telem(telemetry);
+        let mut me = Self::from_handle(telemetry, runtime.handle().clone());
         me.runtime = Some(runtime);
         Ok(me)
     }
 
+    /// Create a new core runtime which spawns all of its tasks (and those of workers initialized
+    /// with it) onto a tokio runtime owned by the caller, as identified by `handle`. Also
+    /// initialize telemetry for the thread this is being called on. The runtime must have its
+    /// time and IO drivers enabled, but may be either multi or current thread.
+    ///
+    /// Unlike [Self::new_assume_tokio], this does not need to be called from within the context of
+    /// the runtime. Core will never shut down the runtime, so it must outlive the returned
+    /// instance and any workers created with it.
+    pub fn init_with_runtime(
+        handle: tokio::runtime::Handle,
+        telemetry_options: TelemetryOptions,
+    ) -> Result<Self, anyhow::Error> {
+        let telemetry = telemetry_init(telemetry_options)?;
+        Ok(Self::from_handle(telemetry, handle))
+    }
+
     /// Initialize telemetry for the thread this is being called on, assuming a tokio runtime is
     /// already active and this call exists in its context. See [Self::new] for more.
     ///
@@ -274,7 +385,10 @@ impl CoreRuntime {
     /// # Panics
     /// If there is no currently active Tokio runtime
     pub fn new_assume_tokio_initialized_telem(telemetry: TelemetryInstance) -> Self {
-        let runtime_handle = tokio::runtime::Handle::current();
+        Self::from_handle(telemetry, tokio::runtime::Handle::current())
+    }
+
+    fn from_handle(telemetry: TelemetryInstance, runtime_handle: tokio::runtime::Handle) -> Self {
         if let Some(sub) = telemetry.trace_subscriber() {
             set_trace_subscriber_for_current_thread(sub);
         }
@@ -290,6 +404,12 @@ impl CoreRuntime {
         self.runtime_handle.clone()
     }
 
+    /// Returns true if core built the tokio runtime backing this instance, and hence will shut it
+    /// down when this instance is dropped.
+    pub fn owns_tokio_runtime(&self) -> bool {
+        self.runtime.is_some()
+    }
+
     /// Return a reference to the owned [TelemetryInstance]
     pub fn telemetry(&self) -> &TelemetryInstance {
         &self.telemetry
@@ -1,26 +1,73 @@
+mod poll_auth;
 mod poll_buffer;
+mod poll_stats;
+
+pub(crate) use poll_auth::PollAuthFailures;
 
 pub(crate) use poll_buffer::{
-    new_activity_task_buffer, new_workflow_task_buffer, WorkflowTaskPoller,
+    new_activity_task_buffer, new_workflow_task_buffer, ActivityRateLimits, InvalidPollOptions,
+    PollOptions, PollerScaler, WorkflowTaskPoller,
 };
+pub use poll_stats::{PollStats, TaskQueuePollStats};
+pub(crate) use poll_stats::{PollStatsTracker, WorkerPollStatsTrackers};
 pub use temporal_client::{
     Client, ClientOptions, ClientOptionsBuilder, ClientTlsConfig, RetryClient, RetryConfig,
     TlsConfig, WorkflowClientTrait,
 };
 
-use crate::abstractions::OwnedMeteredSemPermit;
-use temporal_sdk_core_protos::temporal::api::workflowservice::v1::{
-    PollActivityTaskQueueResponse, PollWorkflowTaskQueueResponse,
+use crate::{
+    abstractions::OwnedMeteredSemPermit,
+    errors::{PollActivityError, PollWfError},
+};
+use temporal_sdk_core_protos::temporal::api::{
+    enums::v1::TaskQueueKind,
+    workflowservice::v1::{PollActivityTaskQueueResponse, PollWorkflowTaskQueueResponse},
 };
 
 #[cfg(test)]
 use futures_util::Future;
 #[cfg(test)]
+pub(crate) use poll_auth::AUTH_FAILURES_BEFORE_STOPPING;
+#[cfg(test)]
 pub(crate) use poll_buffer::MockPermittedPollBuffer;
 use temporal_sdk_core_api::worker::{ActivitySlotKind, WorkflowSlotKind};
 
 pub(crate) type Result<T, E = tonic::Status> = std::result::Result<T, E>;
 
+/// Why a [Poller] did not produce a task
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum PollError {
+    /// The poller has been shut down, and will never produce anything again
+    #[error("Poller is shut down")]
+    ShutDown,
+    /// Polling server failed, after any retries the poller makes itself
+    #[error("Poll failed: {0:?}")]
+    TonicError(#[from] tonic::Status),
+    /// Server returned a response which couldn't be interpreted
+    #[error("Server returned a poll response which couldn't be interpreted: {reason}")]
+    MalformedResponse { reason: String },
+}
+
+impl From<PollError> for PollWfError {
+    fn from(e: PollError) -> Self {
+        match e {
+            PollError::ShutDown => Self::ShutDown,
+            PollError::TonicError(status) => status.into(),
+            PollError::MalformedResponse { reason } => Self::MalformedResponse { reason },
+        }
+    }
+}
+
+impl From<PollError> for PollActivityError {
+    fn from(e: PollError) -> Self {
+        match e {
+            PollError::ShutDown => Self::ShutDown,
+            PollError::TonicError(status) => status.into(),
+            PollError::MalformedResponse { reason } => Self::MalformedResponse { reason },
+        }
+    }
+}
+
 /// A trait for things that poll the server. Hides complexity of concurrent polling or polling
 /// on sticky/nonsticky queues simultaneously.
 #[cfg_attr(test, mockall::automock)]
@@ -30,17 +77,16 @@ pub(crate) trait Poller<PollResult>
 where
     PollResult: Send + Sync + 'static,
 {
-    async fn poll(&self) -> Option<Result<PollResult>>;
+    async fn poll(&self) -> Result<PollResult, PollError>;
     fn notify_shutdown(&self);
     async fn shutdown(self);
     /// Need a separate shutdown to be able to consume boxes :(
     async fn shutdown_box(self: Box<Self>);
 }
 pub(crate) type BoxedPoller<T> = Box<dyn Poller<T> + Send + Sync + 'static>;
-pub(crate) type BoxedWFPoller = BoxedPoller<(
-    PollWorkflowTaskQueueResponse,
-    OwnedMeteredSemPermit<WorkflowSlotKind>,
-)>;
+/// A polled workflow task, labelled with the kind of queue it came from
+pub(crate) type LabelledWFT = (PollWorkflowTaskQueueResponse, TaskQueueKind);
+pub(crate) type BoxedWFPoller = BoxedPoller<(LabelledWFT, OwnedMeteredSemPermit<WorkflowSlotKind>)>;
 pub(crate) type BoxedActPoller = BoxedPoller<(
     PollActivityTaskQueueResponse,
     OwnedMeteredSemPermit<ActivitySlotKind>,
@@ -51,7 +97,7 @@ impl<T> Poller<T> for Box<dyn Poller<T> + Send + Sync>
 where
     T: Send + Sync + 'static,
 {
-    async fn poll(&self) -> Option<Result<T>> {
+    async fn poll(&self) -> Result<T, PollError> {
         Poller::poll(self.as_ref()).await
     }
 
@@ -74,7 +120,7 @@ mockall::mock! {
     #[allow(unused)]
     impl<T: Send + Sync + 'static> Poller<T> for ManualPoller<T> {
         fn poll<'a, 'b>(&self)
-          -> impl Future<Output = Option<Result<T>>> + Send + 'b
+          -> impl Future<Output = Result<T, PollError>> + Send + 'b
             where 'a: 'b, Self: 'b;
         fn notify_shutdown(&self);
         fn shutdown<'a>(self)
@@ -0,0 +1,249 @@
+//! Stopping polling once server keeps rejecting the worker's credentials. See
+//! [temporal_sdk_core_api::worker::PollAuthFailureEvent].
+//!
+//! Expired or wrong credentials would otherwise have pollers retrying forever, with nothing but
+//! an absence of tasks to show for it. Polls rejected as `Unauthenticated` or `PermissionDenied`
+//! are retried in place a few times, since a rejection can be a blip (ex: while credentials are
+//! being rotated). If they keep being rejected, every poller of the worker stops, since they all
+//! poll with the same credentials, and polls lang makes fail with the rejection until
+//! [PollAuthFailures::resume] is called.
+
+use crate::pollers;
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use temporal_sdk_core_api::worker::{
+    PollAuthFailureEvent, WorkerLifecycleEvent, WorkerLifecycleObserver,
+};
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+use tonic::Code;
+
+/// Consecutive rejected polls after which polling is stopped
+pub(crate) const AUTH_FAILURES_BEFORE_STOPPING: usize = 3;
+const AUTH_FAILURE_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Shared by every poller of one worker
+pub(crate) struct PollAuthFailures {
+    namespace: String,
+    identity: String,
+    observer: Option<WorkerLifecycleObserver>,
+    consecutive_failures: AtomicUsize,
+    /// The rejection polling was stopped at, if it is stopped
+    stopped: watch::Sender<Option<tonic::Status>>,
+    /// Polls stopped for auth failures must not hold up shutdown
+    shutdown: CancellationToken,
+}
+
+impl PollAuthFailures {
+    pub(crate) fn new(
+        namespace: String,
+        identity: String,
+        observer: Option<WorkerLifecycleObserver>,
+        shutdown: CancellationToken,
+    ) -> Self {
+        Self {
+            namespace,
+            identity,
+            observer,
+            consecutive_failures: AtomicUsize::new(0),
+            stopped: watch::channel(None).0,
+            shutdown,
+        }
+    }
+
+    /// Run the poll of `task_queue` until it produces something other than an auth failure. While
+    /// polling is stopped, waits for it to be resumed before polling.
+    pub(crate) async fn poll<T, Fut>(
+        &self,
+        task_queue: &str,
+        poll: impl Fn() -> Fut,
+    ) -> pollers::Result<T>
+    where
+        Fut: Future<Output = pollers::Result<T>>,
+    {
+        let mut stopped = self.stopped.subscribe();
+        loop {
+            // Only errors if the sender is dropped, which can't happen while `self` is borrowed
+            let _ = stopped.wait_for(Option::is_none).await;
+            match poll().await {
+                Err(e) if is_auth_failure(&e) => {
+                    let failures = self.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+                    if failures < AUTH_FAILURES_BEFORE_STOPPING {
+                        warn!(task_queue, failures, error = ?e, "Poll was rejected, retrying");
+                        tokio::time::sleep(AUTH_FAILURE_RETRY_DELAY).await;
+                    } else {
+                        self.stop(task_queue, e);
+                    }
+                }
+                r => {
+                    self.consecutive_failures.store(0, Ordering::Relaxed);
+                    return r;
+                }
+            }
+        }
+    }
+
+    fn stop(&self, task_queue: &str, status: tonic::Status) {
+        let message = status.message().to_string();
+        // Other pollers' polls may be rejected too, but stopping is only reported once
+        let newly_stopped = self.stopped.send_if_modified(|stopped| {
+            if stopped.is_some() {
+                return false;
+            }
+            *stopped = Some(status);
+            true
+        });
+        if !newly_stopped {
+            return;
+        }
+        error!(
+            namespace = %self.namespace,
+            task_queue,
+            identity = %self.identity,
+            error = %message,
+            "Server keeps rejecting this worker's polls, so polling has stopped. Check the \
+             worker's credentials (API key or TLS certificate) are valid and allowed to access the \
+             namespace, then refresh them and resume polling."
+        );
+        self.emit(PollAuthFailureEvent::Stopped {
+            task_queue: task_queue.to_string(),
+            message,
+        });
+    }
+
+    /// The rejection polling was stopped at, unless it has been resumed since or the worker is
+    /// shutting down
+    pub(crate) fn stopped_at(&self) -> Option<tonic::Status> {
+        if self.shutdown.is_cancelled() {
+            return None;
+        }
+        self.stopped.borrow().clone()
+    }
+
+    /// Resolves with the rejection polling stops at, once it does. Never resolves once the worker
+    /// is shutting down.
+    pub(crate) async fn stopped(&self) -> tonic::Status {
+        let mut stopped = self.stopped.subscribe();
+        let stopped = async { stopped.wait_for(Option::is_some).await.ok()?.clone() };
+        tokio::select! {
+            Some(status) = stopped => status,
+            _ = self.shutdown.cancelled() => std::future::pending().await,
+        }
+    }
+
+    /// Restart polling after it was stopped, presumably once credentials have been refreshed
+    pub(crate) fn resume(&self) {
+        self.consecutive_failures.store(0, Ordering::Relaxed);
+        if self.stopped.send_replace(None).is_none() {
+            return;
+        }
+        info!(namespace = %self.namespace, "Resuming polling after it was stopped");
+        self.emit(PollAuthFailureEvent::Resumed);
+    }
+
+    fn emit(&self, event: PollAuthFailureEvent) {
+        if let Some(observer) = self.observer.as_ref() {
+            observer(&WorkerLifecycleEvent::PollAuthFailure(event));
+        }
+    }
+}
+
+#[cfg(test)]
+impl Default for PollAuthFailures {
+    fn default() -> Self {
+        Self::new(String::new(), String::new(), None, CancellationToken::new())
+    }
+}
+
+fn is_auth_failure(status: &tonic::Status) -> bool {
+    matches!(
+        status.code(),
+        Code::Unauthenticated | Code::PermissionDenied
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parking_lot::Mutex;
+
+    #[tokio::test]
+    async fn stops_after_consecutive_rejections_until_resumed() {
+        let events = Arc::new(Mutex::new(vec![]));
+        let auth = PollAuthFailures::new(
+            "ns".to_string(),
+            "me".to_string(),
+            Some({
+                let events = events.clone();
+                Arc::new(move |e: &WorkerLifecycleEvent| {
+                    if let WorkerLifecycleEvent::PollAuthFailure(e) = e {
+                        events.lock().push(e.clone())
+                    }
+                })
+            }),
+            CancellationToken::new(),
+        );
+        let attempts = &AtomicUsize::new(0);
+        let poll = move || async move {
+            match attempts.fetch_add(1, Ordering::Relaxed) {
+                n if n < AUTH_FAILURES_BEFORE_STOPPING => {
+                    Err(tonic::Status::permission_denied("bad key"))
+                }
+                _ => Ok("task"),
+            }
+        };
+        let resumed = async {
+            assert_eq!(auth.stopped().await.code(), Code::PermissionDenied);
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            // Nothing more is polled while stopped
+            assert_eq!(
+                attempts.load(Ordering::Relaxed),
+                AUTH_FAILURES_BEFORE_STOPPING
+            );
+            assert!(auth.stopped_at().is_some());
+            auth.resume();
+        };
+        let (polled, _) = tokio::join!(auth.poll("q", poll), resumed);
+        assert_eq!(polled.unwrap(), "task");
+        assert!(auth.stopped_at().is_none());
+        assert_eq!(
+            *events.lock(),
+            vec![
+                PollAuthFailureEvent::Stopped {
+                    task_queue: "q".to_string(),
+                    message: "bad key".to_string()
+                },
+                PollAuthFailureEvent::Resumed
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn other_results_reset_the_count() {
+        let auth = PollAuthFailures::new(
+            "ns".to_string(),
+            "me".to_string(),
+            None,
+            CancellationToken::new(),
+        );
+        for _ in 0..3 {
+            let attempts = &AtomicUsize::new(0);
+            let poll = move || async move {
+                match attempts.fetch_add(1, Ordering::Relaxed) {
+                    n if n + 1 < AUTH_FAILURES_BEFORE_STOPPING => {
+                        Err(tonic::Status::unauthenticated("expired"))
+                    }
+                    _ => Ok(()),
+                }
+            };
+            auth.poll("q", poll).await.unwrap();
+        }
+        assert!(auth.stopped_at().is_none());
+    }
+}
@@ -1,13 +1,28 @@
 use crate::{
     abstractions::{dbg_panic, MeteredPermitDealer, OwnedMeteredSemPermit},
-    pollers::{self, Poller},
+    pollers::{
+        self,
+        poll_stats::{PollOutcome, PollStatsTracker},
+        LabelledWFT, PollAuthFailures, PollError, Poller,
+    },
+    telemetry::metrics::MetricsContext,
     worker::client::WorkerClient,
 };
+use crossbeam_queue::SegQueue;
 use futures_util::{future::BoxFuture, stream::FuturesUnordered, FutureExt, StreamExt};
-use governor::{Quota, RateLimiter};
+use governor::{
+    clock::Clock,
+    state::{InMemoryState, NotKeyed},
+    Quota, RateLimiter,
+};
+use parking_lot::Mutex;
+use prost::Message;
 use std::{
+    any::Any,
     fmt::Debug,
-    future::Future,
+    future::{self, Future},
+    mem,
+    panic::AssertUnwindSafe,
     sync::{
         atomic::{AtomicBool, AtomicUsize, Ordering},
         Arc,
@@ -15,31 +30,328 @@ use std::{
     time::Duration,
 };
 use temporal_sdk_core_api::worker::{ActivitySlotKind, SlotKind, WorkflowSlotKind};
-use temporal_sdk_core_protos::temporal::api::{
-    taskqueue::v1::TaskQueue,
-    workflowservice::v1::{PollActivityTaskQueueResponse, PollWorkflowTaskQueueResponse},
+use temporal_sdk_core_protos::{
+    temporal::api::{
+        common::v1::WorkerVersionCapabilities,
+        enums::v1::TaskQueueKind,
+        taskqueue::v1::TaskQueue,
+        workflowservice::v1::{PollActivityTaskQueueResponse, PollWorkflowTaskQueueResponse},
+    },
+    TaskToken,
 };
 use tokio::{
-    sync::{
-        broadcast,
-        mpsc::{unbounded_channel, UnboundedReceiver},
-        Mutex,
-    },
+    sync::{watch, Notify},
     task::JoinHandle,
 };
 use tokio_util::sync::CancellationToken;
+use tonic::Code;
+
+/// How many buffered tasks may be handed out, one after another, while a buffered error waits
+const MAX_TASKS_AHEAD_OF_ERROR: usize = 10;
+
+/// Poll results land in separate lanes for successes and errors, so that a poller which fails
+/// quickly (and often) can't bury another poller's task behind a pile of errors. Tasks are
+/// preferred, but only up to [MAX_TASKS_AHEAD_OF_ERROR] at a time, so a steady stream of tasks
+/// can't hide errors forever either.
+///
+/// Lang may have many concurrent callers of [LongPollBuffer::poll], so the lanes are lock-free
+/// queues rather than a channel receiver behind a mutex, which all callers would queue up on.
+struct PollLanes<T, SK: SlotKind> {
+    /// Buffered tasks, along with their encoded size
+    results: SegQueue<((T, OwnedMeteredSemPermit<SK>), usize)>,
+    errors: SegQueue<PollError>,
+    /// How many tasks have been handed out in a row while an error was waiting
+    tasks_ahead_of_error: AtomicUsize,
+    /// Total encoded size of the buffered tasks
+    buffered_bytes: AtomicUsize,
+    /// While `buffered_bytes` exceeds this, pollers don't acquire permits for more polls.
+    /// `usize::MAX` if there is no limit.
+    max_buffered_bytes: AtomicUsize,
+    /// Number of poller tasks which have not exited, and thus may still push results
+    live_pollers: AtomicUsize,
+    /// Notified once for every pushed result, and for all waiters when a poller exits
+    pushed: Notify,
+    /// Notified for all waiters whenever a task is claimed or the byte limit changes
+    claimed: Notify,
+    /// Records how many polled tasks, and how many bytes of them, are waiting to be picked up
+    metrics: MetricsContext,
+}
+
+impl<T: PolledTask, SK: SlotKind> PollLanes<T, SK> {
+    fn pop(&self) -> Option<Result<(T, OwnedMeteredSemPermit<SK>), PollError>> {
+        let error_waiting = !self.errors.is_empty();
+        if error_waiting
+            && self.tasks_ahead_of_error.load(Ordering::SeqCst) >= MAX_TASKS_AHEAD_OF_ERROR
+        {
+            if let Some(e) = self.pop_error() {
+                return Some(Err(e));
+            }
+        }
+        if let Some((r, size)) = self.results.pop() {
+            let bytes = self.buffered_bytes.fetch_sub(size, Ordering::SeqCst) - size;
+            self.metrics.poll_buffer_unclaimed_tasks(self.results.len());
+            self.metrics.poll_buffer_unclaimed_bytes(bytes);
+            self.claimed.notify_waiters();
+            if error_waiting {
+                self.tasks_ahead_of_error.fetch_add(1, Ordering::SeqCst);
+            }
+            return Some(Ok(r));
+        }
+        self.pop_error().map(Err)
+    }
+
+    fn pop_error(&self) -> Option<PollError> {
+        let e = self.errors.pop()?;
+        self.tasks_ahead_of_error.store(0, Ordering::SeqCst);
+        Some(e)
+    }
+
+    fn push(&self, r: Result<(T, OwnedMeteredSemPermit<SK>), PollError>) {
+        match r {
+            Ok(r) => {
+                let size = r.0.buffered_size();
+                let bytes = self.buffered_bytes.fetch_add(size, Ordering::SeqCst) + size;
+                self.results.push((r, size));
+                self.metrics.poll_buffer_unclaimed_tasks(self.results.len());
+                self.metrics.poll_buffer_unclaimed_bytes(bytes);
+            }
+            Err(e) => self.errors.push(e),
+        }
+        self.pushed.notify_one();
+    }
+
+    /// Resolves once the buffered tasks take up no more than the byte limit
+    async fn under_byte_limit(&self) {
+        loop {
+            // Register interest before checking, so a claim racing with the check still wakes us
+            let claimed = self.claimed.notified();
+            tokio::pin!(claimed);
+            claimed.as_mut().enable();
+            if self.buffered_bytes.load(Ordering::SeqCst)
+                <= self.max_buffered_bytes.load(Ordering::SeqCst)
+            {
+                return;
+            }
+            claimed.await;
+        }
+    }
+}
+
+/// Marks a poller task as exited when dropped, waking any callers waiting on results so they can
+/// see that none may be coming.
+struct LivePollerGuard<T, SK: SlotKind>(Arc<PollLanes<T, SK>>);
+impl<T, SK: SlotKind> Drop for LivePollerGuard<T, SK> {
+    fn drop(&mut self) {
+        self.0.live_pollers.fetch_sub(1, Ordering::SeqCst);
+        self.0.pushed.notify_waiters();
+    }
+}
 
-type PollReceiver<T, SK> =
-    Mutex<UnboundedReceiver<pollers::Result<(T, OwnedMeteredSemPermit<SK>)>>>;
 pub(crate) struct LongPollBuffer<T, SK: SlotKind> {
-    buffered_polls: PollReceiver<T, SK>,
+    buffered_polls: Arc<PollLanes<T, SK>>,
     shutdown: CancellationToken,
-    join_handles: FuturesUnordered<JoinHandle<()>>,
-    /// Pollers won't actually start polling until initialized & value is sent
-    starter: broadcast::Sender<()>,
+    pool: Arc<PollerPool>,
+    /// Pollers won't actually start polling until initialized & true is sent
+    starter: watch::Sender<bool>,
     did_start: AtomicBool,
 }
 
+/// The poller tasks of one [LongPollBuffer], which may be added to or removed from while running
+struct PollerPool {
+    /// Spawns one more poller task, which must already be counted as running. Holds everything
+    /// polls are made with, including the client, so it is dropped once the pollers have stopped
+    /// for shutdown: [PollerScaler]s may keep the pool itself alive for longer.
+    spawn: Mutex<Option<Box<dyn Fn() -> JoinHandle<()> + Send + Sync>>>,
+    scaling: Arc<PollerScaling>,
+    join_handles: Mutex<FuturesUnordered<JoinHandle<()>>>,
+    shutdown: CancellationToken,
+}
+
+/// Shared between a [PollerPool] and its pollers, so that pollers can tell when to exit
+struct PollerScaling {
+    counts: Mutex<PollerCounts>,
+    /// Sent to whenever the target changes, so that pollers check whether they are surplus
+    target_changed: watch::Sender<()>,
+}
+
+struct PollerCounts {
+    target: usize,
+    /// Pollers which have not yet claimed an exit
+    running: usize,
+}
+
+impl PollerScaling {
+    fn has_surplus(&self) -> bool {
+        let counts = self.counts.lock();
+        counts.running > counts.target
+    }
+
+    /// If more pollers are running than the target, claims the exit of one of them
+    fn claim_exit(&self) -> bool {
+        let mut counts = self.counts.lock();
+        if counts.running > counts.target {
+            counts.running -= 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Resolves once the target has changed such that some poller should exit
+    async fn surplus(&self, target_changed: &mut watch::Receiver<()>) {
+        loop {
+            if target_changed.changed().await.is_err() {
+                // The pool is gone, and with it any way to change the target
+                return future::pending().await;
+            }
+            if self.has_surplus() {
+                return;
+            }
+        }
+    }
+}
+
+impl PollerPool {
+    fn set_num_pollers(&self, num_pollers: usize) {
+        // A buffer with no pollers would look shut down to anyone polling it
+        let num_pollers = num_pollers.max(1);
+        let mut join_handles = self.join_handles.lock();
+        if self.shutdown.is_cancelled() {
+            return;
+        }
+        // Forget pollers which have already exited, so repeated scaling doesn't pile them up
+        while let Some(Some(jh)) = join_handles.next().now_or_never() {
+            report_poller_exit(jh);
+        }
+        let to_spawn = {
+            let mut counts = self.scaling.counts.lock();
+            counts.target = num_pollers;
+            let to_spawn = num_pollers.saturating_sub(counts.running);
+            counts.running += to_spawn;
+            to_spawn
+        };
+        if let Some(spawn) = self.spawn.lock().as_ref() {
+            for _ in 0..to_spawn {
+                join_handles.push(spawn());
+            }
+        }
+        self.scaling.target_changed.send_replace(());
+    }
+}
+
+/// Handle for changing the number of pollers of a [LongPollBuffer] while it runs
+#[derive(Clone)]
+pub(crate) struct PollerScaler(Arc<PollerPool>);
+
+impl PollerScaler {
+    /// Run `num_pollers` (at least one) concurrent polls. Extra pollers are started immediately.
+    /// Surplus pollers exit as soon as they are not in the middle of a poll. Polls already in
+    /// flight are never interrupted.
+    pub(crate) fn set_num_pollers(&self, num_pollers: usize) {
+        self.0.set_num_pollers(num_pollers);
+    }
+}
+
+/// The rates at which activity tasks may be polled for, which can be changed while the worker
+/// runs. Cheap to clone, and clones share limits.
+#[derive(Clone)]
+pub(crate) struct ActivityRateLimits(Arc<ActivityRateLimitsInner>);
+
+struct ActivityRateLimitsInner {
+    /// Sent to server with each poll, which enforces it across every worker on the task queue
+    task_queue_per_second: Mutex<Option<f64>>,
+    /// Enforced locally, before each poll is issued
+    worker: watch::Sender<Option<Arc<WorkerRateLimiter>>>,
+}
+
+type WorkerRateLimiter = RateLimiter<NotKeyed, InMemoryState, TokioClock>;
+
+/// Tells the time by tokio's clock, so that rate limits follow it when time is paused in tests
+#[derive(Clone, Copy, Debug, Default)]
+struct TokioClock;
+
+impl Clock for TokioClock {
+    type Instant = std::time::Instant;
+
+    fn now(&self) -> Self::Instant {
+        tokio::time::Instant::now().into_std()
+    }
+}
+
+impl ActivityRateLimits {
+    pub(crate) fn new(
+        max_task_queue_per_second: Option<f64>,
+        max_worker_per_second: Option<f64>,
+    ) -> Self {
+        Self(Arc::new(ActivityRateLimitsInner {
+            task_queue_per_second: Mutex::new(max_task_queue_per_second),
+            worker: watch::channel(worker_rate_limiter(max_worker_per_second)).0,
+        }))
+    }
+
+    /// Applies from the next poll issued
+    pub(crate) fn set_task_queue_per_second(&self, per_second: Option<f64>) {
+        *self.0.task_queue_per_second.lock() = per_second;
+    }
+
+    /// Applies immediately, including to pollers already waiting on the old limit
+    pub(crate) fn set_worker_per_second(&self, per_second: Option<f64>) {
+        self.0.worker.send_replace(worker_rate_limiter(per_second));
+    }
+
+    fn task_queue_per_second(&self) -> Option<f64> {
+        *self.0.task_queue_per_second.lock()
+    }
+
+    /// Resolves once the worker's limit allows another poll
+    async fn until_worker_allows(&self) {
+        let mut limiter = self.0.worker.subscribe();
+        loop {
+            let Some(current) = limiter.borrow_and_update().clone() else {
+                return;
+            };
+            tokio::select! {
+                _ = until_ready(&current) => return,
+                _ = limiter.changed() => {}
+            }
+        }
+    }
+}
+
+/// Like [RateLimiter::until_ready], but waits on tokio's timer rather than the wall clock
+async fn until_ready(limiter: &WorkerRateLimiter) {
+    while let Err(not_until) = limiter.check() {
+        tokio::time::sleep(not_until.wait_time_from(TokioClock.now())).await;
+    }
+}
+
+fn worker_rate_limiter(per_second: Option<f64>) -> Option<Arc<WorkerRateLimiter>> {
+    per_second
+        .filter(|ps| ps.is_finite() && *ps > 0.0)
+        .and_then(|ps| Quota::with_period(Duration::from_secs_f64(ps.recip())))
+        .map(|q| Arc::new(RateLimiter::direct_with_clock(q, TokioClock)))
+}
+
+fn report_poller_exit(jh: Result<(), tokio::task::JoinError>) {
+    if let Err(e) = jh {
+        if e.is_panic() {
+            let as_panic = e.into_panic().downcast::<String>();
+            dbg_panic!(
+                "Poller task died or did not terminate cleanly: {:?}",
+                as_panic
+            );
+        }
+    }
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("<non-string panic>")
+}
+
 struct ActiveCounter<'a, F: Fn(usize)>(&'a AtomicUsize, Option<F>);
 impl<'a, F> ActiveCounter<'a, F>
 where
@@ -65,9 +377,88 @@ where
     }
 }
 
+/// What a [LongPollBuffer] needs to know about the responses it polls for, so that only real,
+/// usable tasks ever come out of it
+pub(crate) trait PolledTask {
+    /// True for the response server sends when a long poll times out without finding a task
+    fn is_empty_poll(&self) -> bool;
+    /// Why the response can't be processed, if it is missing something every task has
+    fn malformed_reason(&self) -> Option<&'static str>;
+    /// Identifies the task in logs: its token, and the id of the workflow it belongs to if that's
+    /// known
+    fn identity(&self) -> (TaskToken, Option<&str>);
+    /// Record that a poll came back empty
+    fn record_empty_poll(metrics: &MetricsContext);
+    /// How many bytes the response takes up while buffered, as near as can be told cheaply
+    fn buffered_size(&self) -> usize;
+}
+
+impl PolledTask for PollWorkflowTaskQueueResponse {
+    fn is_empty_poll(&self) -> bool {
+        self.task_token.is_empty()
+    }
+
+    fn malformed_reason(&self) -> Option<&'static str> {
+        if self.workflow_execution.is_none() {
+            Some("missing workflow execution")
+        } else if self.workflow_type.is_none() {
+            Some("missing workflow type")
+        } else if self.history.is_none() {
+            Some("missing history")
+        } else if self.workflow_execution_task_queue.is_none() {
+            Some("missing task queue")
+        } else {
+            None
+        }
+    }
+
+    fn identity(&self) -> (TaskToken, Option<&str>) {
+        let workflow_id = self
+            .workflow_execution
+            .as_ref()
+            .map(|we| we.workflow_id.as_str());
+        (TaskToken(self.task_token.clone()), workflow_id)
+    }
+
+    fn record_empty_poll(metrics: &MetricsContext) {
+        metrics.wf_tq_poll_empty();
+    }
+
+    fn buffered_size(&self) -> usize {
+        self.encoded_len()
+    }
+}
+
+impl PolledTask for PollActivityTaskQueueResponse {
+    fn is_empty_poll(&self) -> bool {
+        self.task_token.is_empty()
+    }
+
+    fn malformed_reason(&self) -> Option<&'static str> {
+        // Everything else about an activity task has a usable default
+        None
+    }
+
+    fn identity(&self) -> (TaskToken, Option<&str>) {
+        let workflow_id = self
+            .workflow_execution
+            .as_ref()
+            .map(|we| we.workflow_id.as_str());
+        (TaskToken(self.task_token.clone()), workflow_id)
+    }
+
+    fn record_empty_poll(metrics: &MetricsContext) {
+        metrics.act_poll_timeout();
+    }
+
+    fn buffered_size(&self) -> usize {
+        self.encoded_len()
+    }
+}
+
 impl<T, SK> LongPollBuffer<T, SK>
 where
-    T: Send + Debug + 'static,
+    T: PolledTask + Send + Debug + 'static,
     SK: SlotKind + 'static,
 {
     pub(crate) fn new<FT, DelayFut>(
@@ -77,106 +468,242 @@ where
         shutdown: CancellationToken,
         num_pollers_handler: Option<impl Fn(usize) + Send + Sync + 'static>,
         pre_permit_delay: Option<impl Fn() -> DelayFut + Send + Sync + 'static>,
+        metrics: MetricsContext,
     ) -> Self
     where
         FT: Future<Output = pollers::Result<T>> + Send,
         DelayFut: Future<Output = ()> + Send,
     {
-        let (tx, rx) = unbounded_channel();
-        let (starter, wait_for_start) = broadcast::channel(1);
+        let buffered_polls = Arc::new(PollLanes {
+            results: SegQueue::new(),
+            errors: SegQueue::new(),
+            tasks_ahead_of_error: AtomicUsize::new(0),
+            buffered_bytes: AtomicUsize::new(0),
+            max_buffered_bytes: AtomicUsize::new(usize::MAX),
+            live_pollers: AtomicUsize::new(0),
+            pushed: Notify::new(),
+            claimed: Notify::new(),
+            metrics,
+        });
+        let (starter, _) = watch::channel(false);
+        let scaling = Arc::new(PollerScaling {
+            counts: Mutex::new(PollerCounts {
+                target: 0,
+                running: 0,
+            }),
+            target_changed: watch::channel(()).0,
+        });
         let permit_dealer = Arc::new(permit_dealer);
         let active_pollers = Arc::new(AtomicUsize::new(0));
-        let join_handles = FuturesUnordered::new();
         let pf = Arc::new(poll_fn);
         let nph = num_pollers_handler.map(Arc::new);
         let pre_permit_delay = pre_permit_delay.map(Arc::new);
-        for _ in 0..max_pollers {
-            let tx = tx.clone();
-            let pf = pf.clone();
+        let spawn = {
+            let buffered_polls = buffered_polls.clone();
             let shutdown = shutdown.clone();
-            let ap = active_pollers.clone();
-            let permit_dealer = permit_dealer.clone();
-            let nph = nph.clone();
-            let pre_permit_delay = pre_permit_delay.clone();
-            let mut wait_for_start = wait_for_start.resubscribe();
-            let jh = tokio::spawn(async move {
-                tokio::select! {
-                    _ = wait_for_start.recv() => (),
-                    _ = shutdown.cancelled() => return,
-                }
-                drop(wait_for_start);
-
-                let nph = nph.as_ref().map(|a| a.as_ref());
-                loop {
-                    if shutdown.is_cancelled() {
-                        break;
+            let starter = starter.clone();
+            let scaling = scaling.clone();
+            move || {
+                buffered_polls.live_pollers.fetch_add(1, Ordering::SeqCst);
+                let live_guard = LivePollerGuard(buffered_polls.clone());
+                let pf = pf.clone();
+                let shutdown = shutdown.clone();
+                let ap = active_pollers.clone();
+                let permit_dealer = permit_dealer.clone();
+                let nph = nph.clone();
+                let pre_permit_delay = pre_permit_delay.clone();
+                let mut wait_for_start = starter.subscribe();
+                let scaling = scaling.clone();
+                let mut target_changed = scaling.target_changed.subscribe();
+                tokio::spawn(async move {
+                    tokio::select! {
+                        _ = wait_for_start.wait_for(|started| *started) => (),
+                        _ = shutdown.cancelled() => return,
                     }
-                    if let Some(ref ppd) = pre_permit_delay {
+                    drop(wait_for_start);
+
+                    let nph = nph.as_ref().map(|a| a.as_ref());
+                    let mut consecutive_panics = 0;
+                    'polls: loop {
+                        if shutdown.is_cancelled() || scaling.claim_exit() {
+                            break;
+                        }
+                        // Until a permit is acquired there's no poll to finish, so a surplus
+                        // poller can exit right away
+                        if let Some(ref ppd) = pre_permit_delay {
+                            tokio::select! {
+                                _ = ppd() => (),
+                                _ = scaling.surplus(&mut target_changed) => continue,
+                                _ = shutdown.cancelled() => break,
+                            }
+                        }
+                        // Buffered tasks hold on to their memory until claimed, so past the byte
+                        // limit nothing more is polled for until some are
                         tokio::select! {
-                            _ = ppd() => (),
+                            _ = live_guard.0.under_byte_limit() => (),
+                            _ = scaling.surplus(&mut target_changed) => continue,
                             _ = shutdown.cancelled() => break,
                         }
+                        let permit = tokio::select! {
+                            p = permit_dealer.acquire_owned() => p,
+                            _ = scaling.surplus(&mut target_changed) => continue,
+                            _ = shutdown.cancelled() => break,
+                        };
+                        let _active_guard = ActiveCounter::new(ap.as_ref(), nph);
+                        // Dropping the in-flight poll on shutdown drops the underlying RPC, which
+                        // resets its stream. Nothing between here and tonic may hold on to (or
+                        // spawn) the call, or shutdown would wait on the server to end the long
+                        // poll. Transient errors have already been retried by the client, per its
+                        // long poll retry config.
+                        let r = loop {
+                            let r = tokio::select! {
+                                r = AssertUnwindSafe(pf()).catch_unwind() => r,
+                                _ = shutdown.cancelled() => break 'polls,
+                            };
+                            // A panic would otherwise kill this detached task without anyone
+                            // seeing, and quietly cost the buffer a poller. Only the one poll is
+                            // given up on, and the next is backed off, in case every poll panics.
+                            let r = match r {
+                                Ok(r) => {
+                                    consecutive_panics = 0;
+                                    r
+                                }
+                                Err(panic) => {
+                                    consecutive_panics += 1;
+                                    error!(
+                                        panic = panic_message(panic.as_ref()),
+                                        consecutive_panics, "Poll panicked"
+                                    );
+                                    // Nothing is being polled for while backing off
+                                    drop((permit, _active_guard));
+                                    let delay = retry_in_place_delay(consecutive_panics);
+                                    tokio::select! {
+                                        _ = tokio::time::sleep(delay) => (),
+                                        _ = shutdown.cancelled() => break 'polls,
+                                    }
+                                    continue 'polls;
+                                }
+                            };
+                            match r {
+                                // The long poll timed out. Nobody is handed the empty response,
+                                // and the permit is kept for the next poll, so the slot it holds
+                                // still goes to a real task.
+                                Ok(resp) if resp.is_empty_poll() => {
+                                    trace!("Poll came back empty");
+                                    T::record_empty_poll(&live_guard.0.metrics);
+                                    if scaling.claim_exit() {
+                                        break 'polls;
+                                    }
+                                    // Keeps a server answering empty right away from hogging the
+                                    // runtime
+                                    tokio::task::yield_now().await;
+                                }
+                                Ok(resp) => match resp.malformed_reason() {
+                                    Some(reason) => {
+                                        // The rest of the response may hold a whole history page
+                                        let (task_token, workflow_id) = resp.identity();
+                                        warn!(
+                                            reason,
+                                            %task_token,
+                                            workflow_id,
+                                            "Server returned a malformed task"
+                                        );
+                                        break Err(PollError::MalformedResponse {
+                                            reason: reason.to_string(),
+                                        });
+                                    }
+                                    None => break Ok(resp),
+                                },
+                                Err(e) => break Err(e.into()),
+                            }
+                        };
+                        // Errors don't need the permit, so it's released for the next poll to use
+                        live_guard.0.push(r.map(|r| (r, permit)));
                     }
-                    let permit = tokio::select! {
-                        p = permit_dealer.acquire_owned() => p,
-                        _ = shutdown.cancelled() => break,
-                    };
-                    let _active_guard = ActiveCounter::new(ap.as_ref(), nph);
-                    let r = tokio::select! {
-                        r = pf() => r,
-                        _ = shutdown.cancelled() => break,
-                    };
-                    let _ = tx.send(r.map(|r| (r, permit)));
-                }
-            });
-            join_handles.push(jh);
-        }
+                })
+            }
+        };
+        let pool = Arc::new(PollerPool {
+            spawn: Mutex::new(Some(Box::new(spawn))),
+            scaling,
+            join_handles: Default::default(),
+            shutdown: shutdown.clone(),
+        });
+        pool.set_num_pollers(max_pollers);
         Self {
-            buffered_polls: Mutex::new(rx),
+            buffered_polls,
             shutdown,
-            join_handles,
+            pool,
             starter,
             did_start: AtomicBool::new(false),
         }
     }
+
+    /// Returns a handle which can change the number of pollers while this buffer runs
+    pub(crate) fn scaler(&self) -> PollerScaler {
+        PollerScaler(self.pool.clone())
+    }
+
+    /// While the tasks waiting to be claimed take up more than `max_bytes` (encoded), no more polls
+    /// are started. Polls already in flight are allowed to finish. `None` removes the limit.
+    pub(crate) fn set_max_buffered_bytes(&self, max_bytes: Option<usize>) {
+        self.buffered_polls
+            .max_buffered_bytes
+            .store(max_bytes.unwrap_or(usize::MAX), Ordering::SeqCst);
+        self.buffered_polls.claimed.notify_waiters();
+    }
 }
 
 #[async_trait::async_trait]
 impl<T, SK> Poller<(T, OwnedMeteredSemPermit<SK>)> for LongPollBuffer<T, SK>
 where
-    T: Send + Sync + Debug + 'static,
+    T: PolledTask + Send + Sync + Debug + 'static,
     SK: SlotKind + 'static,
 {
     /// Poll for the next item from this poller
     ///
-    /// Returns `None` if the poller has been shut down
+    /// Returns [PollError::ShutDown] if the poller has been shut down. Buffered tasks are returned
+    /// before buffered errors, but an error waits behind at most [MAX_TASKS_AHEAD_OF_ERROR] tasks,
+    /// and is surfaced as soon as there are no tasks waiting. Polls which came back empty are never
+    /// returned, and malformed tasks are returned as [PollError::MalformedResponse].
     #[instrument(name = "long_poll", level = "trace", skip(self))]
-    async fn poll(&self) -> Option<pollers::Result<(T, OwnedMeteredSemPermit<SK>)>> {
+    async fn poll(&self) -> Result<(T, OwnedMeteredSemPermit<SK>), PollError> {
         if !self.did_start.fetch_or(true, Ordering::Relaxed) {
-            let _ = self.starter.send(());
+            self.starter.send_replace(true);
         }
 
-        let mut locked = self.buffered_polls.lock().await;
-        (*locked).recv().await
+        let lanes = &self.buffered_polls;
+        loop {
+            // Register interest before checking the queues, so a push racing with the checks
+            // still wakes us
+            let pushed = lanes.pushed.notified();
+            tokio::pin!(pushed);
+            pushed.as_mut().enable();
+            if let Some(r) = lanes.pop() {
+                return r;
+            }
+            if lanes.live_pollers.load(Ordering::SeqCst) == 0 {
+                // Anything pushed before the last poller exited is visible now
+                return lanes.pop().unwrap_or(Err(PollError::ShutDown));
+            }
+            pushed.await;
+        }
     }
 
     fn notify_shutdown(&self) {
         self.shutdown.cancel();
     }
 
-    async fn shutdown(mut self) {
+    async fn shutdown(self) {
         self.notify_shutdown();
-        while let Some(jh) = self.join_handles.next().await {
-            if let Err(e) = jh {
-                if e.is_panic() {
-                    let as_panic = e.into_panic().downcast::<String>();
-                    dbg_panic!(
-                        "Poller task died or did not terminate cleanly: {:?}",
-                        as_panic
-                    );
-                }
-            }
+        // No more pollers can be spawned once shutdown is signalled
+        let mut join_handles = mem::take(&mut *self.pool.join_handles.lock());
+        while let Some(jh) = join_handles.next().await {
+            report_poller_exit(jh);
         }
+        // Nothing will poll again, so let go of the client right away, letting its connection
+        // close as soon as nobody else is using it
+        self.pool.spawn.lock().take();
     }
 
     async fn shutdown_box(self: Box<Self>) {
@@ -186,49 +713,67 @@ where
 }
 
 /// A poller capable of polling on a sticky and a nonsticky queue simultaneously for workflow tasks.
+/// Tasks are labelled with the queue they were polled from.
 #[derive(derive_more::Constructor)]
 pub(crate) struct WorkflowTaskPoller {
     normal_poller: PollWorkflowTaskBuffer,
     sticky_poller: Option<PollWorkflowTaskBuffer>,
 }
 
+type WFTPollResult<T> = Result<(T, OwnedMeteredSemPermit<WorkflowSlotKind>), PollError>;
+
+/// Tags the result of polling one of the buffers with the kind of queue that buffer polls
+fn label(
+    r: WFTPollResult<PollWorkflowTaskQueueResponse>,
+    kind: TaskQueueKind,
+) -> WFTPollResult<LabelledWFT> {
+    r.map(|(wft, permit)| ((wft, kind), permit))
+}
+
+impl WorkflowTaskPoller {
+    async fn poll_normal(&self) -> WFTPollResult<LabelledWFT> {
+        label(self.normal_poller.poll().await, TaskQueueKind::Normal)
+    }
+}
+
 #[async_trait::async_trait]
-impl
-    Poller<(
-        PollWorkflowTaskQueueResponse,
-        OwnedMeteredSemPermit<WorkflowSlotKind>,
-    )> for WorkflowTaskPoller
-{
-    async fn poll(
-        &self,
-    ) -> Option<
-        pollers::Result<(
-            PollWorkflowTaskQueueResponse,
-            OwnedMeteredSemPermit<WorkflowSlotKind>,
-        )>,
-    > {
-        if let Some(sq) = self.sticky_poller.as_ref() {
+impl Poller<(LabelledWFT, OwnedMeteredSemPermit<WorkflowSlotKind>)> for WorkflowTaskPoller {
+    async fn poll(&self) -> WFTPollResult<LabelledWFT> {
+        let Some(sq) = self.sticky_poller.as_ref() else {
+            return self.poll_normal().await;
+        };
+        // Both buffers hold on to what they've polled, so dropping either poll loses nothing
+        loop {
             tokio::select! {
-                r = self.normal_poller.poll() => r,
-                r = sq.poll() => r,
+                r = self.poll_normal() => return r,
+                r = sq.poll() => match r {
+                    // Server may lose track of the sticky queue, which is no reason to stop
+                    // taking tasks from the normal one. The sticky buffer backs off such polls.
+                    Err(PollError::TonicError(e)) if e.code() == Code::NotFound => {
+                        debug!(error = ?e, "Sticky queue not found, polling normal queue");
+                    }
+                    // The sticky poller is stopped first on shutdown
+                    Err(PollError::ShutDown) => return self.poll_normal().await,
+                    r => return label(r, TaskQueueKind::Sticky),
+                },
             }
-        } else {
-            self.normal_poller.poll().await
         }
     }
 
+    /// The sticky poller is stopped first, since tasks from either queue can still be completed
+    /// while the normal one winds down
     fn notify_shutdown(&self) {
-        self.normal_poller.notify_shutdown();
         if let Some(sq) = self.sticky_poller.as_ref() {
             sq.notify_shutdown();
         }
+        self.normal_poller.notify_shutdown();
     }
 
-    async fn shutdown(mut self) {
-        self.normal_poller.shutdown().await;
+    async fn shutdown(self) {
         if let Some(sq) = self.sticky_poller {
             sq.shutdown().await;
         }
+        self.normal_poller.shutdown().await;
     }
 
     async fn shutdown_box(self: Box<Self>) {
@@ -237,8 +782,126 @@ impl
     }
 }
 
+/// Consecutive decode failures after which we assume the channel itself is bad and replace it
+const DECODE_FAILURES_BEFORE_RECONNECT: usize = 5;
+/// Consecutive decode failures after which they are surfaced to lang, since reconnecting has not
+/// helped
+const DECODE_FAILURES_BEFORE_SURFACING: usize = 3 * DECODE_FAILURES_BEFORE_RECONNECT;
+
+/// Server (or something between us and it) may hand back a poll response which can't be decoded.
+/// Surfacing that as a poll error could take down the whole worker, but there's nothing wrong with
+/// the worker, so such polls are retried in place instead, with backoff. If they keep failing, lang
+/// is told with a retryable error, so that it doesn't shut down but knows polls aren't getting
+/// through. Shared by all pollers of one queue.
+struct DecodeFailureTracker {
+    client: Arc<dyn WorkerClient>,
+    metrics: MetricsContext,
+    method: &'static str,
+    consecutive_failures: AtomicUsize,
+}
+
+impl DecodeFailureTracker {
+    fn new(client: Arc<dyn WorkerClient>, metrics: MetricsContext, method: &'static str) -> Self {
+        Self {
+            client,
+            metrics,
+            method,
+            consecutive_failures: AtomicUsize::new(0),
+        }
+    }
+
+    /// Run the poll until it produces something other than a decode failure
+    async fn poll<T, Fut>(&self, poll: impl Fn() -> Fut) -> pollers::Result<T>
+    where
+        Fut: Future<Output = pollers::Result<T>>,
+    {
+        loop {
+            match poll().await {
+                Err(e) if is_decode_failure(&e) => {
+                    self.metrics.poll_response_decode_failure(self.method);
+                    let failures = self.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+                    if failures >= DECODE_FAILURES_BEFORE_SURFACING {
+                        // Start over, so later polls are retried again rather than all surfacing
+                        self.consecutive_failures.store(0, Ordering::Relaxed);
+                        return Err(tonic::Status::unavailable(format!(
+                            "Poll responses could not be decoded {failures} times in a row: {}",
+                            e.message()
+                        )));
+                    }
+                    warn!(
+                        method = self.method,
+                        failures,
+                        error = ?e,
+                        "Could not decode poll response, retrying"
+                    );
+                    if failures % DECODE_FAILURES_BEFORE_RECONNECT == 0 {
+                        if let Err(e) = self.client.reconnect().await {
+                            warn!(error = ?e, "Failed to reconnect after repeated decode failures");
+                        }
+                    }
+                    tokio::time::sleep(retry_in_place_delay(failures)).await;
+                }
+                r => {
+                    self.consecutive_failures.store(0, Ordering::Relaxed);
+                    return r;
+                }
+            }
+        }
+    }
+}
+
+const RETRY_INITIAL_DELAY: Duration = Duration::from_millis(100);
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(10);
+
+/// How long to wait before retrying a poll which failed in a way nothing else backs off from (ex:
+/// it panicked), after `failures` consecutive such failures. Doubles with every one, up to a limit.
+fn retry_in_place_delay(failures: usize) -> Duration {
+    let doublings = u32::try_from(failures.saturating_sub(1)).unwrap_or(u32::MAX);
+    RETRY_INITIAL_DELAY
+        .saturating_mul(2_u32.saturating_pow(doublings))
+        .min(RETRY_MAX_DELAY)
+}
+
+/// Server answers polls of a sticky queue it has lost track of with not found, right away.
+/// [WorkflowTaskPoller] keeps taking tasks from the normal queue when that happens, but without
+/// backing off, the sticky pollers would keep asking as fast as server can answer.
+struct StickyQueueMisses {
+    metrics: MetricsContext,
+    consecutive: AtomicUsize,
+}
+
+impl StickyQueueMisses {
+    fn new(metrics: MetricsContext) -> Self {
+        Self {
+            metrics,
+            consecutive: AtomicUsize::new(0),
+        }
+    }
+
+    /// Records the sticky poll's result, waiting out the backoff if the queue wasn't found
+    async fn after_poll<T>(&self, r: &pollers::Result<T>) {
+        match r {
+            Err(e) if e.code() == Code::NotFound => {
+                self.metrics.sticky_queue_not_found();
+                let misses = self.consecutive.fetch_add(1, Ordering::Relaxed) + 1;
+                tokio::time::sleep(retry_in_place_delay(misses)).await;
+            }
+            _ => self.consecutive.store(0, Ordering::Relaxed),
+        }
+    }
+}
+
+/// Tonic reports responses it could not decode as internal errors with this message prefix
+fn is_decode_failure(status: &tonic::Status) -> bool {
+    status.code() == Code::Internal
+        && status
+            .message()
+            .starts_with("failed to decode Protobuf message")
+}
+
 pub(crate) type PollWorkflowTaskBuffer =
     LongPollBuffer<PollWorkflowTaskQueueResponse, WorkflowSlotKind>;
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn new_workflow_task_buffer(
     client: Arc<dyn WorkerClient>,
     task_queue: TaskQueue,
@@ -246,54 +909,180 @@ pub(crate) fn new_workflow_task_buffer(
     permit_dealer: MeteredPermitDealer<WorkflowSlotKind>,
     shutdown: CancellationToken,
     num_pollers_handler: Option<impl Fn(usize) + Send + Sync + 'static>,
+    poll_stats: Option<Arc<PollStatsTracker>>,
+    metrics: MetricsContext,
+    auth_failures: Arc<PollAuthFailures>,
 ) -> PollWorkflowTaskBuffer {
+    let decode_failures = Arc::new(DecodeFailureTracker::new(
+        client.clone(),
+        metrics.clone(),
+        "PollWorkflowTaskQueue",
+    ));
+    let sticky_misses = (task_queue.kind == TaskQueueKind::Sticky as i32)
+        .then(|| Arc::new(StickyQueueMisses::new(metrics.clone())));
     LongPollBuffer::new(
         move || {
             let client = client.clone();
             let task_queue = task_queue.clone();
-            async move { client.poll_workflow_task(task_queue).await }
+            let poll_stats = poll_stats.clone();
+            let decode_failures = decode_failures.clone();
+            let auth_failures = auth_failures.clone();
+            let sticky_misses = sticky_misses.clone();
+            async move {
+                let r = auth_failures
+                    .poll(&task_queue.name, || {
+                        decode_failures.poll(|| client.poll_workflow_task(task_queue.clone()))
+                    })
+                    .await;
+                if let Some(ps) = poll_stats {
+                    ps.record(PollOutcome::of(&r, |r| r.task_token.is_empty()));
+                }
+                if let Some(sm) = sticky_misses {
+                    sm.after_poll(&r).await;
+                }
+                r
+            }
         },
         permit_dealer,
         concurrent_pollers,
         shutdown,
         num_pollers_handler,
         None::<fn() -> BoxFuture<'static, ()>>,
+        metrics,
     )
 }
 
+/// Which queue an activity poller requests tasks from, and how it identifies itself to that queue
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct PollOptions {
+    task_queue: String,
+    kind: TaskQueueKind,
+    versioning: Option<WorkerVersionCapabilities>,
+}
+
+/// Combinations of [PollOptions] which no server could ever serve tasks for
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub(crate) enum InvalidPollOptions {
+    /// No task queue name was given
+    #[error("A task queue name is required to poll.")]
+    EmptyTaskQueue,
+    /// The queue kind must be stated, since server would otherwise have to guess it
+    #[error("A task queue kind is required to poll.")]
+    UnspecifiedQueueKind,
+    /// Sticky queues only ever hold workflow tasks
+    #[error("Activities cannot be polled from a sticky task queue.")]
+    StickyActivityQueue,
+    /// Opting in to versioning means the server routes tasks by build id, so one must be set
+    #[error("A build id is required when polling with versioning enabled.")]
+    VersioningWithoutBuildId,
+}
+
+impl PollOptions {
+    /// Poll the normal queue named `task_queue`, identifying with the worker's own versioning
+    /// capabilities
+    #[cfg(test)]
+    pub(crate) fn normal(task_queue: String) -> Self {
+        Self {
+            task_queue,
+            kind: TaskQueueKind::Normal,
+            versioning: None,
+        }
+    }
+
+    /// Poll activities from `task_queue` of the given `kind`. If `versioning` is set, it replaces
+    /// the worker's own versioning capabilities in each poll.
+    pub(crate) fn for_activities(
+        task_queue: String,
+        kind: TaskQueueKind,
+        versioning: Option<WorkerVersionCapabilities>,
+    ) -> Result<Self, InvalidPollOptions> {
+        if task_queue.is_empty() {
+            return Err(InvalidPollOptions::EmptyTaskQueue);
+        }
+        match kind {
+            TaskQueueKind::Unspecified => return Err(InvalidPollOptions::UnspecifiedQueueKind),
+            TaskQueueKind::Sticky => return Err(InvalidPollOptions::StickyActivityQueue),
+            TaskQueueKind::Normal => {}
+        }
+        if versioning
+            .as_ref()
+            .is_some_and(|v| v.use_versioning && v.build_id.is_empty())
+        {
+            return Err(InvalidPollOptions::VersioningWithoutBuildId);
+        }
+        Ok(Self {
+            task_queue,
+            kind,
+            versioning,
+        })
+    }
+
+    /// The task queue to put in poll requests
+    pub(crate) fn task_queue(&self) -> TaskQueue {
+        TaskQueue {
+            name: self.task_queue.clone(),
+            kind: self.kind as i32,
+            normal_name: "".to_string(),
+        }
+    }
+
+    /// Versioning capabilities which replace the worker's own, if any
+    pub(crate) fn versioning(&self) -> Option<&WorkerVersionCapabilities> {
+        self.versioning.as_ref()
+    }
+}
+
 pub(crate) type PollActivityTaskBuffer =
     LongPollBuffer<PollActivityTaskQueueResponse, ActivitySlotKind>;
 #[allow(clippy::too_many_arguments)]
 pub(crate) fn new_activity_task_buffer(
     client: Arc<dyn WorkerClient>,
-    task_queue: String,
+    poll_options: PollOptions,
     concurrent_pollers: usize,
     semaphore: MeteredPermitDealer<ActivitySlotKind>,
-    max_tps: Option<f64>,
+    rate_limits: ActivityRateLimits,
     shutdown: CancellationToken,
     num_pollers_handler: Option<impl Fn(usize) + Send + Sync + 'static>,
-    max_worker_acts_per_sec: Option<f64>,
+    poll_stats: Option<Arc<PollStatsTracker>>,
+    metrics: MetricsContext,
+    auth_failures: Arc<PollAuthFailures>,
 ) -> PollActivityTaskBuffer {
-    let rate_limiter = max_worker_acts_per_sec.and_then(|ps| {
-        Quota::with_period(Duration::from_secs_f64(ps.recip()))
-            .map(|q| Arc::new(RateLimiter::direct(q)))
-    });
+    let decode_failures = Arc::new(DecodeFailureTracker::new(
+        client.clone(),
+        metrics.clone(),
+        "PollActivityTaskQueue",
+    ));
+    let poll_rate_limits = rate_limits.clone();
     LongPollBuffer::new(
         move || {
             let client = client.clone();
-            let task_queue = task_queue.clone();
-            async move { client.poll_activity_task(task_queue, max_tps).await }
+            let poll_options = poll_options.clone();
+            let poll_stats = poll_stats.clone();
+            let decode_failures = decode_failures.clone();
+            let auth_failures = auth_failures.clone();
+            let max_tps = poll_rate_limits.task_queue_per_second();
+            async move {
+                let r = auth_failures
+                    .poll(&poll_options.task_queue, || {
+                        decode_failures
+                            .poll(|| client.poll_activity_task(poll_options.clone(), max_tps))
+                    })
+                    .await;
+                if let Some(ps) = poll_stats {
+                    ps.record(PollOutcome::of(&r, |r| r.task_token.is_empty()));
+                }
+                r
+            }
         },
         semaphore,
         concurrent_pollers,
         shutdown,
         num_pollers_handler,
-        rate_limiter.map(|rl| {
-            move || {
-                let rl = rl.clone();
-                async move { rl.until_ready().await }.boxed()
-            }
+        Some(move || {
+            let rate_limits = rate_limits.clone();
+            async move { rate_limits.until_worker_allows().await }.boxed()
         }),
+        metrics,
     )
 }
 
@@ -312,9 +1101,9 @@ where
     PT: Poller<T> + Send + Sync + 'static,
     SK: SlotKind + 'static,
 {
-    async fn poll(&self) -> Option<pollers::Result<(T, OwnedMeteredSemPermit<SK>)>> {
+    async fn poll(&self) -> Result<(T, OwnedMeteredSemPermit<SK>), PollError> {
         let p = self.sem.acquire_owned().await;
-        self.inner.poll().await.map(|r| r.map(|r| (r, p)))
+        self.inner.poll().await.map(|r| (r, p))
     }
 
     fn notify_shutdown(&self) {
@@ -335,13 +1124,27 @@ mod tests {
     use super::*;
     use crate::{
         abstractions::tests::fixed_size_permit_dealer,
+        errors::{PollActivityError, PollWfError},
+        test_help::{test_worker_cfg, BufferedTelemetry},
         worker::client::mocks::mock_manual_workflow_client,
     };
     use futures_util::FutureExt;
     use std::time::Duration;
-    use temporal_sdk_core_protos::temporal::api::enums::v1::TaskQueueKind;
+    use temporal_sdk_core_api::telemetry::metrics::MetricUpdateVal;
     use tokio::{select, sync::mpsc::channel};
 
+    /// A workflow task with everything the buffer requires of one
+    fn wft(task_token: Vec<u8>) -> PollWorkflowTaskQueueResponse {
+        PollWorkflowTaskQueueResponse {
+            task_token,
+            workflow_execution: Some(Default::default()),
+            workflow_type: Some(Default::default()),
+            history: Some(Default::default()),
+            workflow_execution_task_queue: Some(Default::default()),
+            ..Default::default()
+        }
+    }
+
     #[tokio::test]
     async fn only_polls_once_with_1_poller() {
         let mut mock_client = mock_manual_workflow_client();
@@ -351,7 +1154,7 @@ mod tests {
             .returning(move |_| {
                 async {
                     tokio::time::sleep(Duration::from_millis(100)).await;
-                    Ok(Default::default())
+                    Ok(wft(vec![1]))
                 }
                 .boxed()
             });
@@ -367,6 +1170,9 @@ mod tests {
             fixed_size_permit_dealer(10),
             CancellationToken::new(),
             None::<fn(usize)>,
+            None,
+            MetricsContext::no_op(),
+            Default::default(),
         );
 
         // Poll a bunch of times, "interrupting" it each time, we should only actually have polled
@@ -390,7 +1196,1145 @@ mod tests {
         assert!(last_val);
         // Now we grab the buffered poll response, the poll task will go again but we don't grab it,
         // therefore we will have only polled twice.
-        pb.poll().await.unwrap().unwrap();
+        pb.poll().await.unwrap();
+        pb.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn unclaimed_tasks_are_recorded() {
+        let telem = BufferedTelemetry::new();
+        let metrics = MetricsContext::top_level("ns".to_string(), "tq".to_string(), &telem);
+        let mut mock_client = mock_manual_workflow_client();
+        mock_client
+            .expect_poll_workflow_task()
+            .returning(|_| async { Ok(wft(vec![1])) }.boxed());
+        // Two slots, so the pollers stop once they've buffered two tasks
+        let pb = new_workflow_task_buffer(
+            Arc::new(mock_client),
+            TaskQueue {
+                name: "sometq".to_string(),
+                kind: TaskQueueKind::Normal as i32,
+                normal_name: "".to_string(),
+            },
+            2,
+            fixed_size_permit_dealer(2),
+            CancellationToken::new(),
+            None::<fn(usize)>,
+            None,
+            metrics,
+            Default::default(),
+        );
+        // The first poll starts the pollers
+        let _first = pb.poll().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let _second = pb.poll().await.unwrap();
+
+        let unclaimed: Vec<_> = telem
+            .updates()
+            .into_iter()
+            .filter(|(name, _, _)| name.ends_with("poll_buffer_unclaimed_tasks"))
+            .map(|(_, attrs, update)| {
+                assert_eq!(attrs.get("task_queue").unwrap(), "tq");
+                update
+            })
+            .collect();
+        // However the pollers and the first poll raced, a task was left waiting at some point, and
+        // the buffer ended up empty
+        assert_matches!(unclaimed.last(), Some(MetricUpdateVal::Value(0)));
+        assert!(unclaimed
+            .iter()
+            .any(|u| matches!(u, MetricUpdateVal::Value(1))));
+        pb.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn empty_polls_are_polled_again_without_giving_up_the_permit() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let calls_clone = calls.clone();
+        let mut mock_client = mock_manual_workflow_client();
+        mock_client.expect_poll_workflow_task().returning(move |_| {
+            let call = calls_clone.fetch_add(1, Ordering::SeqCst);
+            async move {
+                match call {
+                    // Every other poll times out
+                    0..=5 if call % 2 == 0 => Ok(Default::default()),
+                    0..=5 => Ok(wft(vec![call as u8])),
+                    _ => future::pending().await,
+                }
+            }
+            .boxed()
+        });
+        let permits = fixed_size_permit_dealer(5);
+        let pb = new_workflow_task_buffer(
+            Arc::new(mock_client),
+            TaskQueue {
+                name: "sometq".to_string(),
+                kind: TaskQueueKind::Normal as i32,
+                normal_name: "".to_string(),
+            },
+            1,
+            permits.clone(),
+            CancellationToken::new(),
+            None::<fn(usize)>,
+            None,
+            MetricsContext::no_op(),
+            Default::default(),
+        );
+        let mut tasks = vec![];
+        for _ in 0..3 {
+            tasks.push(pb.poll().await.unwrap());
+        }
+        let tokens: Vec<_> = tasks.iter().map(|(t, _)| t.task_token.clone()).collect();
+        assert_eq!(tokens, [vec![1], vec![3], vec![5]]);
+        // Give the poller a chance to start its next poll
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 7);
+        // One permit is held by each task and one by the poll in flight. None went to empty polls.
+        assert_eq!(permits.available_permits(), Some(1));
+        drop(tasks);
+        assert_eq!(permits.available_permits(), Some(4));
+        pb.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn empty_activity_polls_never_reach_callers() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let calls_clone = calls.clone();
+        let mut mock_client = mock_manual_workflow_client();
+        mock_client
+            .expect_poll_activity_task()
+            .returning(move |_, _| {
+                let call = calls_clone.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    match call {
+                        0..=3 if call % 2 == 0 => Ok(Default::default()),
+                        0..=3 => Ok(PollActivityTaskQueueResponse {
+                            task_token: vec![call as u8],
+                            ..Default::default()
+                        }),
+                        _ => future::pending().await,
+                    }
+                }
+                .boxed()
+            });
+        let permits = fixed_size_permit_dealer(2);
+        let pb = new_activity_task_buffer(
+            Arc::new(mock_client),
+            PollOptions::normal("sometq".to_string()),
+            1,
+            permits.clone(),
+            ActivityRateLimits::new(None, None),
+            CancellationToken::new(),
+            None::<fn(usize)>,
+            None,
+            MetricsContext::no_op(),
+            Default::default(),
+        );
+        let (first, first_permit) = pb.poll().await.unwrap();
+        let (second, second_permit) = pb.poll().await.unwrap();
+        assert_eq!(first.task_token, vec![1]);
+        assert_eq!(second.task_token, vec![3]);
+        // Both slots are taken by real tasks, so nothing more is polled until one is freed
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+        assert_eq!(permits.available_permits(), Some(0));
+        drop((first_permit, second_permit));
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 5);
+        assert_eq!(permits.available_permits(), Some(1));
+        pb.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn polls_are_held_back_while_buffered_tasks_exceed_the_byte_limit() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let calls_clone = calls.clone();
+        let mut mock_client = mock_manual_workflow_client();
+        mock_client.expect_poll_workflow_task().returning(move |_| {
+            let call = calls_clone.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if call < 3 {
+                    // Stands in for a task carrying a big history page
+                    Ok(wft(vec![call as u8; 10_000]))
+                } else {
+                    future::pending().await
+                }
+            }
+            .boxed()
+        });
+        let permits = fixed_size_permit_dealer(10);
+        let pb = new_workflow_task_buffer(
+            Arc::new(mock_client),
+            TaskQueue {
+                name: "sometq".to_string(),
+                kind: TaskQueueKind::Normal as i32,
+                normal_name: "".to_string(),
+            },
+            1,
+            permits.clone(),
+            CancellationToken::new(),
+            None::<fn(usize)>,
+            None,
+            MetricsContext::no_op(),
+            Default::default(),
+        );
+        pb.set_max_buffered_bytes(Some(15_000));
+        // Polling once starts the pollers
+        assert!(pb.poll().now_or_never().is_none());
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        // The second task put the buffer past the limit, so no permit was taken for a third poll
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(permits.available_permits(), Some(8));
+
+        let (first, _permit) = pb.poll().await.unwrap();
+        assert_eq!(first.task_token[0], 0);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        // Claiming one brought the buffer back under the limit, until the third task arrived
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert_eq!(permits.available_permits(), Some(7));
+        pb.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn malformed_tasks_are_rejected_and_release_their_permit() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let calls_clone = calls.clone();
+        let mut mock_client = mock_manual_workflow_client();
+        mock_client.expect_poll_workflow_task().returning(move |_| {
+            let call = calls_clone.fetch_add(1, Ordering::SeqCst);
+            async move {
+                match call {
+                    0 => Ok(PollWorkflowTaskQueueResponse {
+                        workflow_execution: None,
+                        ..wft(vec![1])
+                    }),
+                    1 => Ok(wft(vec![2])),
+                    _ => future::pending().await,
+                }
+            }
+            .boxed()
+        });
+        let permits = fixed_size_permit_dealer(5);
+        let pb = new_workflow_task_buffer(
+            Arc::new(mock_client),
+            TaskQueue {
+                name: "sometq".to_string(),
+                kind: TaskQueueKind::Normal as i32,
+                normal_name: "".to_string(),
+            },
+            1,
+            permits.clone(),
+            CancellationToken::new(),
+            None::<fn(usize)>,
+            None,
+            MetricsContext::no_op(),
+            Default::default(),
+        );
+        assert_matches!(pb.poll().await, Err(PollError::MalformedResponse { reason })
+                        if reason == "missing workflow execution");
+        let (task, _permit) = pb.poll().await.unwrap();
+        assert_eq!(task.task_token, vec![2]);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        // Only the task and the poll in flight hold permits
+        assert_eq!(permits.available_permits(), Some(3));
+        pb.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn dropped_polls_do_not_cause_extra_server_polls() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let calls_clone = calls.clone();
+        let mut mock_client = mock_manual_workflow_client();
+        mock_client.expect_poll_workflow_task().returning(move |_| {
+            if calls_clone.fetch_add(1, Ordering::SeqCst) == 0 {
+                async {
+                    tokio::time::sleep(Duration::from_millis(200)).await;
+                    Ok(wft(vec![1]))
+                }
+                .boxed()
+            } else {
+                futures_util::future::pending().boxed()
+            }
+        });
+
+        // Server polls are only gated by slots, so however many pollers there are and however
+        // many times lang abandons a poll, one slot means one poll
+        let pb = new_workflow_task_buffer(
+            Arc::new(mock_client),
+            TaskQueue {
+                name: "sometq".to_string(),
+                kind: TaskQueueKind::Normal as i32,
+                normal_name: "".to_string(),
+            },
+            3,
+            fixed_size_permit_dealer(1),
+            CancellationToken::new(),
+            None::<fn(usize)>,
+            None,
+            MetricsContext::no_op(),
+            Default::default(),
+        );
+        for _ in 0..20 {
+            select! {
+                _ = tokio::time::sleep(Duration::from_millis(1)) => {}
+                _ = pb.poll() => panic!("Nothing should be ready yet"),
+            }
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        // The one task is still there for whoever polls next
+        let (task, _permit) = pb.poll().await.unwrap();
+        assert_eq!(task.task_token, vec![1]);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        pb.shutdown().await;
+    }
+
+    fn sticky_and_normal_pollers(
+        mock_client: Arc<dyn WorkerClient>,
+        max_nonsticky_polls: usize,
+        max_sticky_polls: usize,
+    ) -> WorkflowTaskPoller {
+        let buffer = |kind: TaskQueueKind, pollers| {
+            new_workflow_task_buffer(
+                mock_client.clone(),
+                TaskQueue {
+                    name: format!("{kind:?}"),
+                    kind: kind as i32,
+                    normal_name: "".to_string(),
+                },
+                pollers,
+                fixed_size_permit_dealer(10),
+                CancellationToken::new(),
+                None::<fn(usize)>,
+                None,
+                MetricsContext::no_op(),
+                Default::default(),
+            )
+        };
+        WorkflowTaskPoller::new(
+            buffer(TaskQueueKind::Normal, max_nonsticky_polls),
+            Some(buffer(TaskQueueKind::Sticky, max_sticky_polls)),
+        )
+    }
+
+    #[tokio::test]
+    async fn sticky_and_normal_polls_follow_configured_ratio() {
+        let cfg = test_worker_cfg()
+            .max_concurrent_wft_polls(5_usize)
+            .build()
+            .unwrap();
+        let polls: Arc<Mutex<Vec<i32>>> = Default::default();
+        let polls_clone = polls.clone();
+        let mut mock_client = mock_manual_workflow_client();
+        mock_client
+            .expect_poll_workflow_task()
+            .returning(move |tq| {
+                polls_clone.lock().push(tq.kind);
+                futures_util::future::pending().boxed()
+            });
+        let poller = sticky_and_normal_pollers(
+            Arc::new(mock_client),
+            cfg.max_nonsticky_polls(),
+            cfg.max_sticky_polls(),
+        );
+
+        assert!(poller.poll().now_or_never().is_none());
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let polls = polls.lock().clone();
+        let count = |kind: TaskQueueKind| polls.iter().filter(|k| **k == kind as i32).count();
+        assert_eq!(count(TaskQueueKind::Normal), 1);
+        assert_eq!(count(TaskQueueKind::Sticky), 4);
+        poller.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn missing_sticky_queue_falls_back_to_normal_queue() {
+        let normal_calls = Arc::new(AtomicUsize::new(0));
+        let normal_calls_clone = normal_calls.clone();
+        let mut mock_client = mock_manual_workflow_client();
+        mock_client
+            .expect_poll_workflow_task()
+            .returning(move |tq| {
+                if tq.kind == TaskQueueKind::Sticky as i32 {
+                    return async {
+                        tokio::time::sleep(Duration::from_millis(1)).await;
+                        Err(tonic::Status::not_found("sticky queue gone"))
+                    }
+                    .boxed();
+                }
+                if normal_calls_clone.fetch_add(1, Ordering::SeqCst) > 0 {
+                    return futures_util::future::pending().boxed();
+                }
+                async {
+                    // Long enough for a pile of sticky failures to come back first
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    Ok(wft(vec![1]))
+                }
+                .boxed()
+            });
+        let poller = sticky_and_normal_pollers(Arc::new(mock_client), 1, 2);
+
+        let ((task, kind), _permit) = poller.poll().await.unwrap();
+        assert_eq!(task.task_token, vec![1]);
+        assert_eq!(kind, TaskQueueKind::Normal);
+        poller.shutdown().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn missing_sticky_queue_is_backed_off_and_counted() {
+        let sticky_calls = Arc::new(AtomicUsize::new(0));
+        let sticky_calls_clone = sticky_calls.clone();
+        let mut mock_client = mock_manual_workflow_client();
+        mock_client
+            .expect_poll_workflow_task()
+            .returning(move |tq| {
+                if tq.kind == TaskQueueKind::Sticky as i32 {
+                    sticky_calls_clone.fetch_add(1, Ordering::SeqCst);
+                    return async { Err(tonic::Status::not_found("sticky queue gone")) }.boxed();
+                }
+                futures_util::future::pending().boxed()
+            });
+        let mock_client: Arc<dyn WorkerClient> = Arc::new(mock_client);
+        let telem = BufferedTelemetry::new();
+        let buffer = |kind: TaskQueueKind, metrics| {
+            new_workflow_task_buffer(
+                mock_client.clone(),
+                TaskQueue {
+                    name: format!("{kind:?}"),
+                    kind: kind as i32,
+                    normal_name: "".to_string(),
+                },
+                1,
+                fixed_size_permit_dealer(10),
+                CancellationToken::new(),
+                None::<fn(usize)>,
+                None,
+                metrics,
+                Default::default(),
+            )
+        };
+        let poller = WorkflowTaskPoller::new(
+            buffer(TaskQueueKind::Normal, MetricsContext::no_op()),
+            Some(buffer(
+                TaskQueueKind::Sticky,
+                MetricsContext::top_level("ns".to_string(), "tq".to_string(), &telem),
+            )),
+        );
+
+        // Sticky polls go out 100ms, 200ms, then 400ms apart, so four of them fit in a second
+        select! {
+            _ = poller.poll() => panic!("Nothing should come out of the poller"),
+            _ = tokio::time::sleep(Duration::from_secs(1)) => {}
+        }
+        assert_eq!(sticky_calls.load(Ordering::SeqCst), 4);
+        let not_found_count = telem
+            .updates()
+            .into_iter()
+            .filter(|(name, _, _)| name == "temporal_sticky_queue_not_found")
+            .count();
+        assert_eq!(not_found_count, 4);
+        poller.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn tasks_are_labelled_with_the_queue_they_came_from() {
+        let calls = [AtomicUsize::new(0), AtomicUsize::new(0)];
+        let mut mock_client = mock_manual_workflow_client();
+        mock_client
+            .expect_poll_workflow_task()
+            .returning(move |tq| {
+                let sticky = tq.kind == TaskQueueKind::Sticky as i32;
+                if calls[sticky as usize].fetch_add(1, Ordering::SeqCst) > 0 {
+                    return futures_util::future::pending().boxed();
+                }
+                // The normal queue's task comes back second
+                let (token, delay) = if sticky { (vec![2], 0) } else { (vec![1], 20) };
+                async move {
+                    tokio::time::sleep(Duration::from_millis(delay)).await;
+                    Ok(wft(token))
+                }
+                .boxed()
+            });
+        let poller = sticky_and_normal_pollers(Arc::new(mock_client), 1, 1);
+
+        let mut labels = vec![];
+        for _ in 0..2 {
+            let ((task, kind), _permit) = poller.poll().await.unwrap();
+            labels.push((task.task_token, kind));
+        }
+        assert_eq!(
+            labels,
+            [
+                (vec![2], TaskQueueKind::Sticky),
+                (vec![1], TaskQueueKind::Normal)
+            ]
+        );
+        poller.shutdown().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn panicking_poll_does_not_lose_the_poller() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let calls_clone = calls.clone();
+        let mut mock_client = mock_manual_workflow_client();
+        mock_client.expect_poll_workflow_task().returning(move |_| {
+            let call = calls_clone.fetch_add(1, Ordering::SeqCst);
+            async move {
+                match call {
+                    0 => panic!("Poll blew up"),
+                    1 => Ok(wft(vec![1])),
+                    _ => future::pending().await,
+                }
+            }
+            .boxed()
+        });
+
+        // With only one poller, losing it would leave the buffer looking shut down
+        let pb = new_workflow_task_buffer(
+            Arc::new(mock_client),
+            TaskQueue {
+                name: "sometq".to_string(),
+                kind: TaskQueueKind::Normal as i32,
+                normal_name: "".to_string(),
+            },
+            1,
+            fixed_size_permit_dealer(10),
+            CancellationToken::new(),
+            None::<fn(usize)>,
+            None,
+            MetricsContext::no_op(),
+            Default::default(),
+        );
+        let (task, _permit) = pb.poll().await.unwrap();
+        assert_eq!(task.task_token, vec![1]);
+        pb.shutdown().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn polls_which_keep_panicking_are_backed_off() {
+        const PANICS: usize = 4;
+        let mut mock_client = mock_manual_workflow_client();
+        let calls = AtomicUsize::new(0);
+        mock_client.expect_poll_workflow_task().returning(move |_| {
+            let call = calls.fetch_add(1, Ordering::SeqCst);
+            async move {
+                match call {
+                    c if c < PANICS => panic!("Poll blew up"),
+                    c if c == PANICS => Ok(wft(vec![1])),
+                    _ => future::pending().await,
+                }
+            }
+            .boxed()
+        });
+
+        let pb = new_workflow_task_buffer(
+            Arc::new(mock_client),
+            TaskQueue {
+                name: "sometq".to_string(),
+                kind: TaskQueueKind::Normal as i32,
+                normal_name: "".to_string(),
+            },
+            1,
+            fixed_size_permit_dealer(10),
+            CancellationToken::new(),
+            None::<fn(usize)>,
+            None,
+            MetricsContext::no_op(),
+            Default::default(),
+        );
+        let started = tokio::time::Instant::now();
+        let (task, _permit) = pb.poll().await.unwrap();
+        assert_eq!(task.task_token, vec![1]);
+        let backoff: Duration = (1..=PANICS).map(retry_in_place_delay).sum();
+        assert!(started.elapsed() >= backoff);
+        pb.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn poll_stats_track_scripted_poll_outcomes() {
+        // true = poll returns a task, false = poll comes back empty, None = poll errors
+        let script = [
+            Some(true),
+            Some(false),
+            Some(false),
+            Some(true),
+            Some(false),
+            None,
+        ];
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut mock_client = mock_manual_workflow_client();
+        mock_client.expect_poll_workflow_task().returning(move |_| {
+            match script.get(calls.fetch_add(1, Ordering::Relaxed)) {
+                Some(Some(has_task)) => {
+                    let resp = wft(if *has_task { vec![1] } else { vec![] });
+                    async move { Ok(resp) }.boxed()
+                }
+                Some(None) => async { Err(tonic::Status::unavailable("oh no")) }.boxed(),
+                // Once the script runs out, hang, so nothing else is counted
+                None => futures_util::future::pending().boxed(),
+            }
+        });
+
+        let poll_stats = Arc::new(PollStatsTracker::new(
+            Duration::from_secs(60),
+            MetricsContext::no_op(),
+        ));
+        let pb = new_workflow_task_buffer(
+            Arc::new(mock_client),
+            TaskQueue {
+                name: "sometq".to_string(),
+                kind: TaskQueueKind::Normal as i32,
+                normal_name: "".to_string(),
+            },
+            1,
+            fixed_size_permit_dealer(10),
+            CancellationToken::new(),
+            None::<fn(usize)>,
+            Some(poll_stats.clone()),
+            MetricsContext::no_op(),
+            Default::default(),
+        );
+        // Only the two tasks and the error come out of the buffer
+        for _ in 0..3 {
+            assert!(!matches!(pb.poll().await, Err(PollError::ShutDown)));
+        }
+
+        let stats = poll_stats.stats();
+        assert_eq!(stats.polls_issued, 6);
+        assert_eq!(stats.polls_with_task, 2);
+        assert_eq!(stats.polls_empty, 3);
+        assert!((stats.success_ratio() - 1.0 / 3.0).abs() < f64::EPSILON);
+        pb.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn pollers_can_be_scaled_while_running() {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let mut mock_client = mock_manual_workflow_client();
+        {
+            let in_flight = in_flight.clone();
+            let peak = peak.clone();
+            mock_client.expect_poll_workflow_task().returning(move |_| {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                let in_flight = in_flight.clone();
+                async move {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                    Ok(wft(vec![1]))
+                }
+                .boxed()
+            });
+        }
+
+        let pb = new_workflow_task_buffer(
+            Arc::new(mock_client),
+            TaskQueue {
+                name: "sometq".to_string(),
+                kind: TaskQueueKind::Normal as i32,
+                normal_name: "".to_string(),
+            },
+            1,
+            fixed_size_permit_dealer(10),
+            CancellationToken::new(),
+            None::<fn(usize)>,
+            None,
+            MetricsContext::no_op(),
+            Default::default(),
+        );
+        let scaler = pb.scaler();
+        let take_polls = |n| {
+            let pb = &pb;
+            async move {
+                for _ in 0..n {
+                    pb.poll().await.unwrap();
+                }
+            }
+        };
+
+        take_polls(3).await;
+        assert_eq!(peak.load(Ordering::SeqCst), 1);
+
+        scaler.set_num_pollers(4);
+        take_polls(8).await;
+        assert_eq!(peak.load(Ordering::SeqCst), 4);
+
+        scaler.set_num_pollers(1);
+        // Every poll in flight when scaling down is allowed to finish
+        take_polls(4).await;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(in_flight.load(Ordering::SeqCst), 1);
+        peak.store(1, Ordering::SeqCst);
+        take_polls(3).await;
+        assert_eq!(peak.load(Ordering::SeqCst), 1);
+        pb.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn buffered_tasks_are_delivered_before_buffered_errors() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let calls_clone = calls.clone();
+        let mut mock_client = mock_manual_workflow_client();
+        mock_client.expect_poll_workflow_task().returning(move |_| {
+            if calls_clone.fetch_add(1, Ordering::Relaxed) == 0 {
+                // One poller gets a task, but only after the other has already failed many times
+                async {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    Ok(wft(vec![1]))
+                }
+                .boxed()
+            } else {
+                async {
+                    tokio::time::sleep(Duration::from_millis(1)).await;
+                    Err(tonic::Status::unavailable("oh no"))
+                }
+                .boxed()
+            }
+        });
+
+        let pb = new_workflow_task_buffer(
+            Arc::new(mock_client),
+            TaskQueue {
+                name: "sometq".to_string(),
+                kind: TaskQueueKind::Normal as i32,
+                normal_name: "".to_string(),
+            },
+            2,
+            fixed_size_permit_dealer(10),
+            CancellationToken::new(),
+            None::<fn(usize)>,
+            None,
+            MetricsContext::no_op(),
+            Default::default(),
+        );
+        // Polling once starts the pollers. The failing poller has filled the buffer with errors by
+        // the time the task arrives, but the task still comes out first.
+        assert!(pb.poll().now_or_never().is_none());
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(calls.load(Ordering::Relaxed) > 2);
+        let (task, _) = pb.poll().await.unwrap();
+        assert_eq!(task.task_token, vec![1]);
+        // Errors are still surfaced once there are no tasks waiting
+        assert_matches!(pb.poll().await, Err(PollError::TonicError(_)));
+        pb.shutdown().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_steady_stream_of_tasks_does_not_hide_errors() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut mock_client = mock_manual_workflow_client();
+        mock_client.expect_poll_workflow_task().returning(move |_| {
+            // The first poll fails, and every one after it gets a task
+            if calls.fetch_add(1, Ordering::Relaxed) == 0 {
+                async { Err(tonic::Status::unavailable("oh no")) }.boxed()
+            } else {
+                async { Ok(wft(vec![1])) }.boxed()
+            }
+        });
+
+        let pb = new_workflow_task_buffer(
+            Arc::new(mock_client),
+            TaskQueue {
+                name: "sometq".to_string(),
+                kind: TaskQueueKind::Normal as i32,
+                normal_name: "".to_string(),
+            },
+            1,
+            fixed_size_permit_dealer(MAX_TASKS_AHEAD_OF_ERROR * 2),
+            CancellationToken::new(),
+            None::<fn(usize)>,
+            None,
+            MetricsContext::no_op(),
+            Default::default(),
+        );
+        // Let the buffer fill up with the error and as many tasks as there are permits
+        assert!(pb.poll().now_or_never().is_none());
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        // Hold on to the permits, so no new tasks arrive while draining
+        let mut tasks = vec![];
+        for _ in 0..MAX_TASKS_AHEAD_OF_ERROR {
+            tasks.push(pb.poll().await.unwrap());
+        }
+        assert_matches!(pb.poll().await, Err(PollError::TonicError(_)));
+        // With the error out of the way, the remaining tasks follow
+        assert_matches!(pb.poll().await, Ok(_));
+        pb.shutdown().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn decode_failures_are_retried_without_surfacing_errors() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let calls_clone = calls.clone();
+        let mut mock_client = mock_manual_workflow_client();
+        mock_client.expect_poll_workflow_task().returning(move |_| {
+            let call = calls_clone.fetch_add(1, Ordering::Relaxed);
+            async move {
+                if call < DECODE_FAILURES_BEFORE_RECONNECT {
+                    Err(tonic::Status::internal(
+                        "failed to decode Protobuf message: invalid wire type",
+                    ))
+                } else if call == DECODE_FAILURES_BEFORE_RECONNECT {
+                    Ok(wft(vec![1]))
+                } else {
+                    futures_util::future::pending().await
+                }
+            }
+            .boxed()
+        });
+        mock_client
+            .expect_reconnect()
+            .times(1)
+            .returning(|| async { Ok(()) }.boxed());
+
+        let pb = new_workflow_task_buffer(
+            Arc::new(mock_client),
+            TaskQueue {
+                name: "sometq".to_string(),
+                kind: TaskQueueKind::Normal as i32,
+                normal_name: "".to_string(),
+            },
+            1,
+            fixed_size_permit_dealer(10),
+            CancellationToken::new(),
+            None::<fn(usize)>,
+            None,
+            MetricsContext::no_op(),
+            Default::default(),
+        );
+        // The decode failures never come out of the buffer, only the good response does
+        let (task, _) = pb.poll().await.unwrap();
+        assert_eq!(task.task_token, vec![1]);
+        assert_eq!(
+            calls.load(Ordering::Relaxed),
+            DECODE_FAILURES_BEFORE_RECONNECT + 1
+        );
+        pb.shutdown().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn decode_failures_which_keep_happening_are_surfaced_as_retryable() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let calls_clone = calls.clone();
+        let mut mock_client = mock_manual_workflow_client();
+        mock_client.expect_poll_workflow_task().returning(move |_| {
+            calls_clone.fetch_add(1, Ordering::Relaxed);
+            async {
+                Err(tonic::Status::internal(
+                    "failed to decode Protobuf message: invalid wire type",
+                ))
+            }
+            .boxed()
+        });
+        mock_client
+            .expect_reconnect()
+            .returning(|| async { Ok(()) }.boxed());
+
+        let pb = new_workflow_task_buffer(
+            Arc::new(mock_client),
+            TaskQueue {
+                name: "sometq".to_string(),
+                kind: TaskQueueKind::Normal as i32,
+                normal_name: "".to_string(),
+            },
+            1,
+            fixed_size_permit_dealer(10),
+            CancellationToken::new(),
+            None::<fn(usize)>,
+            None,
+            MetricsContext::no_op(),
+            Default::default(),
+        );
+        let started = tokio::time::Instant::now();
+        let err = match pb.poll().await {
+            Err(PollError::TonicError(status)) => status,
+            other => panic!("Expected a poll error, got {:?}", other.map(|(t, _)| t)),
+        };
+        assert_matches!(PollWfError::from(err), PollWfError::RetryableTonicError(_));
+        assert!(calls.load(Ordering::Relaxed) >= DECODE_FAILURES_BEFORE_SURFACING);
+        let backoff: Duration = (1..DECODE_FAILURES_BEFORE_SURFACING)
+            .map(retry_in_place_delay)
+            .sum();
+        assert!(started.elapsed() >= backoff);
+        pb.shutdown().await;
+    }
+
+    #[test]
+    fn decode_failure_retries_back_off() {
+        assert_eq!(retry_in_place_delay(1), RETRY_INITIAL_DELAY);
+        assert_eq!(retry_in_place_delay(2), RETRY_INITIAL_DELAY * 2);
+        assert_eq!(
+            retry_in_place_delay(DECODE_FAILURES_BEFORE_SURFACING),
+            RETRY_MAX_DELAY
+        );
+        assert_eq!(retry_in_place_delay(usize::MAX), RETRY_MAX_DELAY);
+    }
+
+    #[tokio::test]
+    async fn other_internal_errors_are_not_treated_as_decode_failures() {
+        assert!(is_decode_failure(&tonic::Status::internal(
+            "failed to decode Protobuf message: buffer underflow"
+        )));
+        assert!(!is_decode_failure(&tonic::Status::internal("oh no")));
+        assert!(!is_decode_failure(&tonic::Status::unavailable(
+            "failed to decode Protobuf message"
+        )));
+    }
+
+    #[test]
+    fn poll_errors_tell_lang_whether_to_poll_again() {
+        let wf_err = |e: PollError| PollWfError::from(e);
+        let act_err = |e: PollError| PollActivityError::from(e);
+        assert_matches!(wf_err(PollError::ShutDown), PollWfError::ShutDown);
+        assert_matches!(
+            wf_err(tonic::Status::unavailable("down for a sec").into()),
+            PollWfError::RetryableTonicError(_)
+        );
+        assert_matches!(
+            wf_err(tonic::Status::permission_denied("go away").into()),
+            PollWfError::TonicError(_)
+        );
+        assert_matches!(
+            act_err(tonic::Status::resource_exhausted("slow down").into()),
+            PollActivityError::RetryableTonicError(_)
+        );
+        assert_matches!(
+            act_err(PollError::MalformedResponse {
+                reason: "missing task token".to_string()
+            }),
+            PollActivityError::MalformedResponse { reason } if reason == "missing task token"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn many_concurrent_callers_get_each_task_exactly_once() {
+        const NUM_TASKS: u32 = 2000;
+        const NUM_CALLERS: usize = 64;
+        let calls = Arc::new(AtomicUsize::new(0));
+        let calls_clone = calls.clone();
+        let mut mock_client = mock_manual_workflow_client();
+        mock_client.expect_poll_workflow_task().returning(move |_| {
+            let seq = calls_clone.fetch_add(1, Ordering::SeqCst) as u32;
+            async move {
+                if seq < NUM_TASKS {
+                    Ok(wft(seq.to_be_bytes().to_vec()))
+                } else {
+                    futures_util::future::pending().await
+                }
+            }
+            .boxed()
+        });
+
+        let pb = Arc::new(new_workflow_task_buffer(
+            Arc::new(mock_client),
+            TaskQueue {
+                name: "sometq".to_string(),
+                kind: TaskQueueKind::Normal as i32,
+                normal_name: "".to_string(),
+            },
+            8,
+            fixed_size_permit_dealer(NUM_TASKS as usize),
+            CancellationToken::new(),
+            None::<fn(usize)>,
+            None,
+            MetricsContext::no_op(),
+            Default::default(),
+        ));
+        let received = Arc::new(SegQueue::new());
+        let num_received = Arc::new(AtomicUsize::new(0));
+        let start = std::time::Instant::now();
+        let callers = (0..NUM_CALLERS).map(|_| {
+            let pb = pb.clone();
+            let received = received.clone();
+            let num_received = num_received.clone();
+            tokio::spawn(async move {
+                // Every caller keeps polling until shutdown, which must wake all of them
+                loop {
+                    let (task, _permit) = match pb.poll().await {
+                        Ok(r) => r,
+                        Err(PollError::ShutDown) => break,
+                        Err(e) => panic!("Mock polls never fail: {e:?}"),
+                    };
+                    received.push(u32::from_be_bytes(task.task_token.try_into().unwrap()));
+                    if num_received.fetch_add(1, Ordering::SeqCst) + 1 == NUM_TASKS as usize {
+                        pb.notify_shutdown();
+                    }
+                }
+            })
+        });
+        tokio::time::timeout(
+            Duration::from_secs(30),
+            futures_util::future::join_all(callers),
+        )
+        .await
+        .expect("All callers must see shutdown once every task is received");
+        debug!(elapsed = ?start.elapsed(), "Received all tasks");
+
+        let mut seqs: Vec<_> = std::iter::from_fn(|| received.pop()).collect();
+        seqs.sort_unstable();
+        assert_eq!(seqs, (0..NUM_TASKS).collect::<Vec<_>>());
+        Arc::try_unwrap(pb)
+            .unwrap_or_else(|_| panic!("Callers are done with the buffer"))
+            .shutdown()
+            .await;
+    }
+
+    #[tokio::test]
+    async fn shutdown_drops_in_flight_poll_promptly() {
+        struct SetOnDrop(Arc<AtomicBool>);
+        impl Drop for SetOnDrop {
+            fn drop(&mut self) {
+                self.0.store(true, Ordering::SeqCst);
+            }
+        }
+
+        let started = Arc::new(tokio::sync::Notify::new());
+        let poll_dropped = Arc::new(AtomicBool::new(false));
+        let mut mock_client = mock_manual_workflow_client();
+        let (started_clone, dropped_clone) = (started.clone(), poll_dropped.clone());
+        mock_client
+            .expect_poll_workflow_task()
+            .times(1)
+            .returning(move |_| {
+                let guard = SetOnDrop(dropped_clone.clone());
+                let started = started_clone.clone();
+                async move {
+                    let _guard = guard;
+                    started.notify_one();
+                    // Simulates a long poll the server won't end for a long while
+                    futures_util::future::pending().await
+                }
+                .boxed()
+            });
+
+        let client = Arc::new(mock_client);
+        let client_ref = Arc::downgrade(&client);
+        let pb = new_workflow_task_buffer(
+            client,
+            TaskQueue {
+                name: "sometq".to_string(),
+                kind: TaskQueueKind::Normal as i32,
+                normal_name: "".to_string(),
+            },
+            1,
+            fixed_size_permit_dealer(10),
+            CancellationToken::new(),
+            None::<fn(usize)>,
+            None,
+            MetricsContext::no_op(),
+            Default::default(),
+        );
+        // Outlives the buffer, as the worker's scalers do
+        let scaler = pb.scaler();
+        // Kick off polling, and wait until the poll is actually in flight
+        select! {
+            _ = pb.poll() => panic!("Poll should not resolve"),
+            _ = started.notified() => {}
+        }
+
+        tokio::time::timeout(Duration::from_millis(100), pb.shutdown())
+            .await
+            .expect("Shutdown must not wait on the in-flight poll");
+        assert!(poll_dropped.load(Ordering::SeqCst));
+        assert!(
+            client_ref.upgrade().is_none(),
+            "Nothing may hold on to the client once pollers have stopped"
+        );
+        // Scaling after shutdown starts nothing
+        scaler.set_num_pollers(2);
+    }
+
+    #[test]
+    fn invalid_activity_poll_options_are_rejected() {
+        let versioned = |build_id: &str| {
+            Some(WorkerVersionCapabilities {
+                build_id: build_id.to_string(),
+                use_versioning: true,
+            })
+        };
+        let cases = [
+            ("tq", TaskQueueKind::Normal, None, None),
+            ("tq", TaskQueueKind::Normal, versioned("1.0"), None),
+            (
+                "tq",
+                TaskQueueKind::Unspecified,
+                None,
+                Some(InvalidPollOptions::UnspecifiedQueueKind),
+            ),
+            (
+                "",
+                TaskQueueKind::Normal,
+                None,
+                Some(InvalidPollOptions::EmptyTaskQueue),
+            ),
+            (
+                "tq",
+                TaskQueueKind::Sticky,
+                None,
+                Some(InvalidPollOptions::StickyActivityQueue),
+            ),
+            (
+                "tq",
+                TaskQueueKind::Normal,
+                versioned(""),
+                Some(InvalidPollOptions::VersioningWithoutBuildId),
+            ),
+        ];
+        for (tq, kind, versioning, expected) in cases {
+            let res = PollOptions::for_activities(tq.to_string(), kind, versioning);
+            assert_eq!(res.err(), expected, "{tq:?} {kind:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn activity_polls_carry_queue_kind_and_versioning() {
+        let versioning = WorkerVersionCapabilities {
+            build_id: "some-build".to_string(),
+            use_versioning: true,
+        };
+        let expected_versioning = versioning.clone();
+        let mut mock_client = mock_manual_workflow_client();
+        mock_client
+            .expect_poll_activity_task()
+            .withf(move |opts, max_tps| {
+                opts.task_queue()
+                    == TaskQueue {
+                        name: "versioned-tq".to_string(),
+                        kind: TaskQueueKind::Normal as i32,
+                        normal_name: "".to_string(),
+                    }
+                    && opts.versioning() == Some(&expected_versioning)
+                    && *max_tps == Some(5.0)
+            })
+            .returning(|_, _| {
+                async {
+                    Ok(PollActivityTaskQueueResponse {
+                        task_token: vec![1],
+                        ..Default::default()
+                    })
+                }
+                .boxed()
+            });
+
+        let pb = new_activity_task_buffer(
+            Arc::new(mock_client),
+            PollOptions::for_activities(
+                "versioned-tq".to_string(),
+                TaskQueueKind::Normal,
+                Some(versioning),
+            )
+            .unwrap(),
+            1,
+            fixed_size_permit_dealer(10),
+            ActivityRateLimits::new(Some(5.0), None),
+            CancellationToken::new(),
+            None::<fn(usize)>,
+            None,
+            MetricsContext::no_op(),
+            Default::default(),
+        );
+        // A mismatched request would fail the expectation, rather than return the task
+        let (resp, _permit) = pb.poll().await.unwrap();
+        assert_eq!(resp.task_token, vec![1]);
         pb.shutdown().await;
     }
 }
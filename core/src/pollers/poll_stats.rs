@@ -0,0 +1,262 @@
+use crate::{pollers, telemetry::metrics::MetricsContext};
+use parking_lot::Mutex;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// Counts of poll outcomes for one queue over the worker's configured poll stats window
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PollStats {
+    /// Number of polls which completed within the window, whatever their outcome
+    pub polls_issued: u64,
+    /// Number of polls which returned a task
+    pub polls_with_task: u64,
+    /// Number of polls which timed out without returning a task
+    pub polls_empty: u64,
+}
+
+impl PollStats {
+    /// The fraction of issued polls which returned a task, or zero if no polls were issued
+    pub fn success_ratio(&self) -> f64 {
+        if self.polls_issued == 0 {
+            0.0
+        } else {
+            self.polls_with_task as f64 / self.polls_issued as f64
+        }
+    }
+}
+
+/// Poll stats for one of the task queues a worker polls
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TaskQueuePollStats {
+    /// Stats for workflow task polls, if the worker polls the queue for workflow tasks
+    pub workflow: Option<PollStats>,
+    /// Stats for activity task polls, if the worker polls the queue for activity tasks. Never set
+    /// for a sticky queue.
+    pub activity: Option<PollStats>,
+}
+
+/// The outcome of a single long poll
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PollOutcome {
+    Task,
+    Empty,
+    Error,
+}
+
+impl PollOutcome {
+    pub(crate) fn of<T>(res: &pollers::Result<T>, is_empty: impl FnOnce(&T) -> bool) -> Self {
+        match res {
+            Ok(r) if is_empty(r) => PollOutcome::Empty,
+            Ok(_) => PollOutcome::Task,
+            Err(_) => PollOutcome::Error,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct Bucket {
+    /// Seconds since the tracker was created that this bucket currently holds counts for
+    second: u64,
+    with_task: u64,
+    empty: u64,
+    errored: u64,
+}
+
+/// Tracks poll outcomes over a rolling window using a ring of one-second buckets
+pub(crate) struct PollStatsTracker {
+    started: Instant,
+    buckets: Mutex<Vec<Bucket>>,
+    metrics: MetricsContext,
+}
+
+impl PollStatsTracker {
+    /// `metrics` should already have the appropriate poller type attribute set
+    pub(crate) fn new(window: Duration, metrics: MetricsContext) -> Self {
+        let num_buckets = window.as_secs().max(1) as usize;
+        Self {
+            started: Instant::now(),
+            buckets: Mutex::new(vec![Bucket::default(); num_buckets]),
+            metrics,
+        }
+    }
+
+    pub(crate) fn record(&self, outcome: PollOutcome) {
+        self.record_at(Instant::now(), outcome);
+    }
+
+    pub(crate) fn stats(&self) -> PollStats {
+        self.stats_at(Instant::now())
+    }
+
+    fn record_at(&self, now: Instant, outcome: PollOutcome) {
+        let second = self.second_of(now);
+        let stats = {
+            let mut buckets = self.buckets.lock();
+            let num_buckets = buckets.len() as u64;
+            let bucket = &mut buckets[(second % num_buckets) as usize];
+            // The slot still holds counts from a previous trip around the ring, start it over
+            if bucket.second != second {
+                *bucket = Bucket {
+                    second,
+                    ..Default::default()
+                };
+            }
+            match outcome {
+                PollOutcome::Task => bucket.with_task += 1,
+                PollOutcome::Empty => bucket.empty += 1,
+                PollOutcome::Error => bucket.errored += 1,
+            }
+            Self::sum(&buckets, second)
+        };
+        self.metrics.poll_stats(stats);
+    }
+
+    fn stats_at(&self, now: Instant) -> PollStats {
+        let second = self.second_of(now);
+        Self::sum(&self.buckets.lock(), second)
+    }
+
+    fn second_of(&self, now: Instant) -> u64 {
+        now.saturating_duration_since(self.started).as_secs()
+    }
+
+    fn sum(buckets: &[Bucket], current_second: u64) -> PollStats {
+        let num_buckets = buckets.len() as u64;
+        buckets
+            .iter()
+            .filter(|b| b.second <= current_second && current_second - b.second < num_buckets)
+            .fold(PollStats::default(), |mut acc, b| {
+                acc.polls_issued += b.with_task + b.empty + b.errored;
+                acc.polls_with_task += b.with_task;
+                acc.polls_empty += b.empty;
+                acc
+            })
+    }
+}
+
+/// The poll stats trackers for every queue a worker polls. Trackers are absent when the
+/// corresponding queue isn't polled (or is mocked out).
+#[derive(Default)]
+pub(crate) struct WorkerPollStatsTrackers {
+    pub(crate) workflow: Option<Arc<PollStatsTracker>>,
+    pub(crate) sticky_workflow: Option<Arc<PollStatsTracker>>,
+    pub(crate) activity: Option<Arc<PollStatsTracker>>,
+}
+
+impl WorkerPollStatsTrackers {
+    /// Stats for the worker's normal task queue
+    pub(crate) fn normal_queue_stats(&self) -> TaskQueuePollStats {
+        TaskQueuePollStats {
+            workflow: self.workflow.as_ref().map(|t| t.stats()),
+            activity: self.activity.as_ref().map(|t| t.stats()),
+        }
+    }
+
+    /// Stats for the worker's sticky task queue
+    pub(crate) fn sticky_queue_stats(&self) -> TaskQueuePollStats {
+        TaskQueuePollStats {
+            workflow: self.sticky_workflow.as_ref().map(|t| t.stats()),
+            activity: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_help::BufferedTelemetry;
+    use temporal_sdk_core_api::telemetry::metrics::MetricUpdateVal;
+
+    fn tracker(window_secs: u64) -> PollStatsTracker {
+        PollStatsTracker::new(Duration::from_secs(window_secs), MetricsContext::no_op())
+    }
+
+    #[test]
+    fn computes_ratios_from_mixed_outcomes() {
+        let t = tracker(10);
+        let now = t.started;
+        for outcome in [
+            PollOutcome::Task,
+            PollOutcome::Empty,
+            PollOutcome::Empty,
+            PollOutcome::Task,
+            PollOutcome::Error,
+        ] {
+            t.record_at(now, outcome);
+        }
+        let stats = t.stats_at(now);
+        assert_eq!(
+            stats,
+            PollStats {
+                polls_issued: 5,
+                polls_with_task: 2,
+                polls_empty: 2,
+            }
+        );
+        assert_eq!(stats.success_ratio(), 0.4);
+    }
+
+    #[test]
+    fn old_outcomes_fall_out_of_window() {
+        let t = tracker(3);
+        let start = t.started;
+        t.record_at(start, PollOutcome::Empty);
+        t.record_at(start + Duration::from_secs(1), PollOutcome::Task);
+        t.record_at(start + Duration::from_secs(2), PollOutcome::Task);
+        assert_eq!(t.stats_at(start + Duration::from_secs(2)).polls_issued, 3);
+        // The empty poll from the first second is now outside the window
+        let stats = t.stats_at(start + Duration::from_secs(3));
+        assert_eq!(stats.polls_issued, 2);
+        assert_eq!(stats.success_ratio(), 1.0);
+        // Recording into a reused slot replaces the stale counts rather than adding to them
+        t.record_at(start + Duration::from_secs(3), PollOutcome::Empty);
+        let stats = t.stats_at(start + Duration::from_secs(3));
+        assert_eq!(stats.polls_issued, 3);
+        assert_eq!(stats.polls_empty, 1);
+        // Everything eventually ages out
+        assert_eq!(
+            t.stats_at(start + Duration::from_secs(100)),
+            PollStats::default()
+        );
+    }
+
+    #[test]
+    fn outcome_counts_are_recorded_as_gauges() {
+        let telem = BufferedTelemetry::new();
+        let t = PollStatsTracker::new(
+            Duration::from_secs(10),
+            MetricsContext::top_level("ns".to_string(), "tq".to_string(), &telem),
+        );
+        let now = t.started;
+        for outcome in [PollOutcome::Task, PollOutcome::Empty, PollOutcome::Error] {
+            t.record_at(now, outcome);
+        }
+        let last_value = |metric: &str| {
+            telem
+                .updates()
+                .into_iter()
+                .filter(|(name, _, _)| name.ends_with(metric))
+                .map(|(_, _, update)| update)
+                .last()
+        };
+        assert_matches!(
+            last_value("recent_polls_issued"),
+            Some(MetricUpdateVal::Value(3))
+        );
+        assert_matches!(
+            last_value("recent_polls_with_task"),
+            Some(MetricUpdateVal::Value(1))
+        );
+        assert_matches!(
+            last_value("recent_polls_empty"),
+            Some(MetricUpdateVal::Value(1))
+        );
+    }
+
+    #[test]
+    fn no_polls_is_zero_ratio() {
+        assert_eq!(tracker(5).stats().success_ratio(), 0.0);
+    }
+}
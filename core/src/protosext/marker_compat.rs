@@ -0,0 +1,129 @@
+//! Interpretation of markers recorded by other SDKs (Go and Java), so that histories migrated from
+//! them can be replayed. Core only ever records its own patch, local activity, and side effect
+//! markers, so any other marker in a history must have come from elsewhere.
+
+use std::collections::HashMap;
+use temporal_sdk_core_protos::{
+    constants::{LOCAL_ACTIVITY_MARKER_NAME, PATCH_MARKER_NAME, SIDE_EFFECT_MARKER_NAME},
+    temporal::api::common::v1::Payloads,
+};
+
+/// Marker name the Go and Java SDKs use to record `GetVersion` calls
+pub(crate) const VERSION_MARKER_NAME: &str = "Version";
+/// Details keys under which the Go and Java SDKs (respectively) store a version marker's change id
+const VERSION_MARKER_CHANGE_ID_KEYS: [&str; 2] = ["change-id", "changeId"];
+/// Marker names the Go and Java SDKs use to record side effects
+const SIDE_EFFECT_MARKER_NAMES: [&str; 2] = ["SideEffect", "MutableSideEffect"];
+
+/// What core makes of a marker, based on its name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MarkerKind {
+    /// A patch marker, whether recorded by core or a version marker from another SDK
+    Patch,
+    /// A local activity marker recorded by core
+    LocalActivity,
+    /// A side effect marker recorded by core
+    SideEffect,
+    /// A side effect recorded by another SDK. Its details are in that SDK's own format, so it
+    /// cannot correspond to any command core based SDKs send.
+    ForeignSideEffect,
+    /// A marker core has no interpretation for
+    Unknown,
+}
+
+impl MarkerKind {
+    pub(crate) fn from_name(marker_name: &str) -> Self {
+        match marker_name {
+            PATCH_MARKER_NAME | VERSION_MARKER_NAME => MarkerKind::Patch,
+            LOCAL_ACTIVITY_MARKER_NAME => MarkerKind::LocalActivity,
+            SIDE_EFFECT_MARKER_NAME => MarkerKind::SideEffect,
+            n if SIDE_EFFECT_MARKER_NAMES.contains(&n) => MarkerKind::ForeignSideEffect,
+            _ => MarkerKind::Unknown,
+        }
+    }
+}
+
+/// Decode the details of a Go or Java SDK version marker into the same `(patch_id, deprecated)`
+/// form core's own patch markers decode to. The version number itself has no equivalent - the
+/// marker having been recorded at all is what indicates the change was taken. Such markers are
+/// never deprecated, since those SDKs have no notion of deprecating a version.
+pub(crate) fn decode_version_marker_details(
+    details: &HashMap<String, Payloads>,
+) -> Option<(String, bool)> {
+    let change_id = VERSION_MARKER_CHANGE_ID_KEYS
+        .iter()
+        .find_map(|k| details.get(*k))?
+        .payloads
+        .first()?;
+    let change_id = json_or_plain_string(&change_id.data)?;
+    Some((change_id, false))
+}
+
+/// Other SDKs encode marker details with their data converter, which by default produces json.
+/// Fall back to the raw bytes in case a history was written with plain binary encoding.
+fn json_or_plain_string(data: &[u8]) -> Option<String> {
+    serde_json::from_slice(data)
+        .ok()
+        .or_else(|| String::from_utf8(data.to_vec()).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use temporal_sdk_core_protos::temporal::api::common::v1::Payload;
+
+    fn details(key: &str, data: &[u8]) -> HashMap<String, Payloads> {
+        HashMap::from([(
+            key.to_string(),
+            Payloads {
+                payloads: vec![Payload {
+                    metadata: Default::default(),
+                    data: data.to_vec(),
+                }],
+            },
+        )])
+    }
+
+    #[test]
+    fn classifies_marker_names() {
+        assert_eq!(MarkerKind::from_name("core_patch"), MarkerKind::Patch);
+        assert_eq!(MarkerKind::from_name("Version"), MarkerKind::Patch);
+        assert_eq!(
+            MarkerKind::from_name("core_local_activity"),
+            MarkerKind::LocalActivity
+        );
+        assert_eq!(
+            MarkerKind::from_name("core_side_effect"),
+            MarkerKind::SideEffect
+        );
+        assert_eq!(
+            MarkerKind::from_name("SideEffect"),
+            MarkerKind::ForeignSideEffect
+        );
+        assert_eq!(
+            MarkerKind::from_name("MutableSideEffect"),
+            MarkerKind::ForeignSideEffect
+        );
+        assert_eq!(MarkerKind::from_name("LocalActivity"), MarkerKind::Unknown);
+    }
+
+    #[test]
+    fn decodes_go_and_java_version_markers() {
+        assert_eq!(
+            decode_version_marker_details(&details("change-id", br#""go-change""#)),
+            Some(("go-change".to_string(), false))
+        );
+        assert_eq!(
+            decode_version_marker_details(&details("changeId", br#""java-change""#)),
+            Some(("java-change".to_string(), false))
+        );
+        assert_eq!(
+            decode_version_marker_details(&details("changeId", b"plain-change")),
+            Some(("plain-change".to_string(), false))
+        );
+        assert_eq!(
+            decode_version_marker_details(&details("version", b"1")),
+            None
+        );
+    }
+}
@@ -1,7 +1,11 @@
+pub(crate) mod marker_compat;
 pub(crate) mod protocol_messages;
 
 use crate::{
-    protosext::protocol_messages::IncomingProtocolMessage,
+    protosext::{
+        marker_compat::{decode_version_marker_details, MarkerKind, VERSION_MARKER_NAME},
+        protocol_messages::IncomingProtocolMessage,
+    },
     worker::{LocalActivityExecutionResult, LEGACY_QUERY_ID},
     CompleteActivityError, TaskToken,
 };
@@ -14,14 +18,14 @@ use std::{
     time::{Duration, SystemTime},
 };
 use temporal_sdk_core_protos::{
-    constants::{LOCAL_ACTIVITY_MARKER_NAME, PATCH_MARKER_NAME},
+    constants::{LOCAL_ACTIVITY_MARKER_NAME, PATCH_MARKER_NAME, SIDE_EFFECT_MARKER_NAME},
     coresdk::{
         activity_result::{activity_execution_result, activity_execution_result::Status},
         common::{
-            decode_change_marker_details, extract_local_activity_marker_data,
-            extract_local_activity_marker_details,
+            decode_change_marker_details, decode_side_effect_marker_details,
+            extract_local_activity_marker_data, extract_local_activity_marker_details,
         },
-        external_data::LocalActivityMarkerData,
+        external_data::{LocalActivityMarkerData, SideEffectMarkerData},
         workflow_activation::{
             query_to_job, workflow_activation_job, QueryWorkflow, WorkflowActivation,
             WorkflowActivationJob,
@@ -33,7 +37,7 @@ use temporal_sdk_core_protos::{
     },
     temporal::api::{
         common::v1::{Payload, RetryPolicy, WorkflowExecution},
-        enums::v1::EventType,
+        enums::v1::{EventType, TaskQueueKind},
         failure::v1::Failure,
         history::v1::{history_event, History, HistoryEvent, MarkerRecordedEventAttributes},
         query::v1::WorkflowQuery,
@@ -62,6 +66,9 @@ pub(crate) struct ValidPollWFTQResponse {
     pub(crate) query_requests: Vec<QueryWorkflow>,
     /// Protocol messages
     pub(crate) messages: Vec<IncomingProtocolMessage>,
+    /// The kind of queue the task was polled from. Unspecified for tasks which weren't polled from
+    /// either (ex: eager workflow starts).
+    pub(crate) task_queue_kind: TaskQueueKind,
 
     /// Zero-size field to prevent explicit construction
     _cant_construct_me: (),
@@ -131,6 +138,7 @@ impl TryFrom<PollWorkflowTaskQueueResponse> for ValidPollWFTQResponse {
                     legacy_query: query,
                     query_requests,
                     messages,
+                    task_queue_kind: TaskQueueKind::Unspecified,
                     _cant_construct_me: (),
                 })
             }
@@ -164,8 +172,15 @@ pub(crate) fn legacy_query_failure(fail: workflow_completion::Failure) -> QueryR
 
 pub(crate) trait HistoryEventExt {
     /// If this history event represents a `patched` marker, return the info about
-    /// it. Returns `None` if it is any other kind of event or marker.
+    /// it. Returns `None` if it is any other kind of event or marker. Version markers recorded by
+    /// other SDKs count as patch markers.
     fn get_patch_marker_details(&self) -> Option<(String, bool)>;
+    /// If this history event represents a side effect marker recorded by core, return its info
+    /// and recorded value. Returns `None` if it is any other kind of event or marker or the data is
+    /// invalid.
+    fn get_side_effect_marker_details(&self) -> Option<(SideEffectMarkerData, Option<Payload>)>;
+    /// If this history event is a marker, return what kind of marker it is.
+    fn marker_kind(&self) -> Option<MarkerKind>;
     /// If this history event represents a local activity marker, return true.
     fn is_local_activity_marker(&self) -> bool;
     /// If this history event represents a local activity marker, return the marker id info.
@@ -187,6 +202,13 @@ impl HistoryEventExt for HistoryEvent {
                         ..
                     },
                 )) if marker_name == PATCH_MARKER_NAME => decode_change_marker_details(details),
+                Some(history_event::Attributes::MarkerRecordedEventAttributes(
+                    MarkerRecordedEventAttributes {
+                        marker_name,
+                        details,
+                        ..
+                    },
+                )) if marker_name == VERSION_MARKER_NAME => decode_version_marker_details(details),
                 _ => None,
             }
         } else {
@@ -194,6 +216,30 @@ impl HistoryEventExt for HistoryEvent {
         }
     }
 
+    fn get_side_effect_marker_details(&self) -> Option<(SideEffectMarkerData, Option<Payload>)> {
+        match &self.attributes {
+            Some(history_event::Attributes::MarkerRecordedEventAttributes(
+                MarkerRecordedEventAttributes {
+                    marker_name,
+                    details,
+                    ..
+                },
+            )) if marker_name == SIDE_EFFECT_MARKER_NAME => {
+                decode_side_effect_marker_details(details)
+            }
+            _ => None,
+        }
+    }
+
+    fn marker_kind(&self) -> Option<MarkerKind> {
+        match &self.attributes {
+            Some(history_event::Attributes::MarkerRecordedEventAttributes(
+                MarkerRecordedEventAttributes { marker_name, .. },
+            )) => Some(MarkerKind::from_name(marker_name)),
+            _ => None,
+        }
+    }
+
     fn is_local_activity_marker(&self) -> bool {
         if self.event_type() == EventType::MarkerRecorded {
             return matches!(&self.attributes,
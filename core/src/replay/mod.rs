@@ -1,6 +1,13 @@
 //! This module implements support for creating special core instances and workers which can be used
 //! to replay canned histories. It should be used by Lang SDKs to provide replay capabilities to
 //! users during testing.
+//!
+//! Each history is served to the worker whole, exactly as server would deliver it on a first poll,
+//! and the worker produces activations for it one workflow task at a time. Commands lang sends are
+//! checked against the history as they would be for any cached run, so a mismatch fails the task
+//! for nondeterminism. Runs are evicted once their history is fully replayed, and the worker shuts
+//! itself down after the last one. See [ReplayWorkerInput::with_outcome_callback] to learn how each
+//! history's replay ended.
 
 use crate::{
     worker::{
@@ -11,17 +18,19 @@ use crate::{
 };
 use futures_util::{FutureExt, Stream, StreamExt};
 use parking_lot::Mutex;
+use prost::Message;
 use std::sync::OnceLock;
 use std::{
     pin::Pin,
     sync::Arc,
     task::{Context, Poll},
 };
-use temporal_sdk_core_api::worker::WorkerConfig;
+use temporal_sdk_core_api::worker::{ReplayProgress, WorkerConfig, WorkerLifecycleEvent};
 use temporal_sdk_core_protos::{
     coresdk::workflow_activation::remove_from_cache::EvictionReason,
     temporal::api::{
         common::v1::WorkflowExecution,
+        enums::v1::WorkflowTaskFailedCause,
         history::v1::History,
         workflowservice::v1::{
             RespondWorkflowTaskCompletedResponse, RespondWorkflowTaskFailedResponse,
@@ -43,6 +52,34 @@ pub struct ReplayWorkerInput<I> {
     history_stream: I,
     /// If specified use this as the basis for the internal mocked client
     pub(crate) client_override: Option<MockManualWorkerClient>,
+    outcome_callback: Option<OutcomeCallback>,
+}
+
+type OutcomeCallback = Arc<dyn Fn(&ReplayOutcome) + Send + Sync>;
+
+/// How replaying one history ended
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplayOutcome {
+    /// Lang completed every workflow task in the history without any being failed
+    Succeeded {
+        /// The workflow id the history was provided with
+        workflow_id: String,
+        /// The run id from the history's workflow execution started event
+        run_id: String,
+    },
+    /// A workflow task was failed while replaying the history, either by lang or because the
+    /// commands lang sent did not match the history
+    Failed {
+        /// The workflow id the history was provided with
+        workflow_id: String,
+        /// The run id from the history's workflow execution started event
+        run_id: String,
+        /// Why the task was failed. Mismatched commands are
+        /// [WorkflowTaskFailedCause::NonDeterministicError].
+        cause: WorkflowTaskFailedCause,
+        /// The message of the task failure
+        message: String,
+    },
 }
 
 impl<I> ReplayWorkerInput<I>
@@ -55,14 +92,45 @@ where
             config,
             history_stream,
             client_override: None,
+            outcome_callback: None,
         }
     }
 
+    /// Invoke `callback` with the outcome of each history once it has been replayed. A history
+    /// whose run is evicted for some other reason before reaching its end has no outcome.
+    pub fn with_outcome_callback(
+        mut self,
+        callback: impl Fn(&ReplayOutcome) + Send + Sync + 'static,
+    ) -> Self {
+        self.outcome_callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// Invoke `callback` as replay progresses through each history, every
+    /// [WorkerConfig::replay_progress_interval] events. A [WorkerConfig::lifecycle_observer] set on
+    /// the config still sees every event, progress reports included.
+    pub fn with_progress_callback(
+        mut self,
+        callback: impl Fn(&ReplayProgress) + Send + Sync + 'static,
+    ) -> Self {
+        let observer = self.config.lifecycle_observer.take();
+        self.config.lifecycle_observer = Some(Arc::new(move |e: &WorkerLifecycleEvent| {
+            if let WorkerLifecycleEvent::ReplayProgress(p) = e {
+                callback(p);
+            }
+            if let Some(observer) = observer.as_ref() {
+                observer(e);
+            }
+        }));
+        self
+    }
+
     pub(crate) fn into_core_worker(mut self) -> Result<Worker, anyhow::Error> {
         self.config.max_cached_workflows = 1;
         self.config.max_concurrent_wft_polls = 1;
         self.config.no_remote_activities = true;
         let historator = Historator::new(self.history_stream);
+        historator.dat.lock().outcome_callback = self.outcome_callback;
         let post_activate = historator.get_post_activate_hook();
         let shutdown_tok = historator.get_shutdown_setter();
         // Create a mock client which can be used by a replay worker to serve up canned histories.
@@ -78,6 +146,7 @@ where
         };
 
         let hist_allow_tx = historator.replay_done_tx.clone();
+        let fail_dat = historator.dat.clone();
         let historator = Arc::new(TokioMutex::new(historator));
 
         // TODO: Should use `new_with_pollers` and avoid re-doing mocking stuff
@@ -91,10 +160,12 @@ where
                 if let Some(history) = hlock.next().await {
                     let hist_info = HistoryInfo::new_from_history(&history.hist, None).unwrap();
                     let mut resp = hist_info.as_poll_wft_response();
-                    resp.workflow_execution = Some(WorkflowExecution {
+                    let execution = WorkflowExecution {
                         workflow_id: history.workflow_id,
-                        run_id: hist_info.orig_run_id().to_string(),
-                    });
+                        run_id: hist_info.run_id().to_string(),
+                    };
+                    hlock.dat.lock().replaying = Some(execution.clone());
+                    resp.workflow_execution = Some(execution);
                     Ok(resp)
                 } else {
                     if let Some(wc) = hlock.worker_closer.get() {
@@ -111,7 +182,15 @@ where
         });
         client
             .expect_fail_workflow_task()
-            .returning(move |_, _, _| {
+            .returning(move |_, cause, failure| {
+                fail_dat
+                    .lock()
+                    .finish(|workflow_id, run_id| ReplayOutcome::Failed {
+                        workflow_id,
+                        run_id,
+                        cause,
+                        message: failure.map(|f| f.message).unwrap_or_default(),
+                    });
                 hist_allow_tx.send("Failed".to_string()).unwrap();
                 async move { Ok(RespondWorkflowTaskFailedResponse::default()) }.boxed()
             });
@@ -129,6 +208,16 @@ pub struct HistoryForReplay {
     hist: History,
     workflow_id: String,
 }
+impl HistoryForReplay {
+    /// Decode a history from the protobuf encoding of a [History], ex: one fetched from server and
+    /// written out with [prost::Message::encode_to_vec].
+    pub fn from_encoded(
+        bytes: &[u8],
+        workflow_id: impl Into<String>,
+    ) -> Result<Self, prost::DecodeError> {
+        Ok(Self::new(History::decode(bytes)?, workflow_id.into()))
+    }
+}
 impl From<TestHistoryBuilder> for HistoryForReplay {
     fn from(thb: TestHistoryBuilder) -> Self {
         thb.get_full_history_info().unwrap().into()
@@ -204,8 +293,14 @@ impl Historator {
         &self,
     ) -> impl Fn(&Worker, PostActivateHookData) + Send + Sync {
         let done_tx = self.replay_done_tx.clone();
+        let dat = self.dat.clone();
         move |worker, data| {
             if !data.replaying {
+                dat.lock()
+                    .finish(|workflow_id, run_id| ReplayOutcome::Succeeded {
+                        workflow_id,
+                        run_id,
+                    });
                 worker.request_wf_eviction(
                     data.run_id,
                     "Always evict workflows after replay",
@@ -252,4 +347,19 @@ impl Stream for Historator {
 #[derive(Default)]
 struct HistoratorDat {
     all_dispatched: bool,
+    /// The execution of the history being replayed, until its outcome is known
+    replaying: Option<WorkflowExecution>,
+    outcome_callback: Option<OutcomeCallback>,
+}
+
+impl HistoratorDat {
+    /// Report the outcome of the history being replayed, if it hasn't been already
+    fn finish(&mut self, outcome: impl FnOnce(String, String) -> ReplayOutcome) {
+        let Some(execution) = self.replaying.take() else {
+            return;
+        };
+        if let Some(cb) = &self.outcome_callback {
+            cb(&outcome(execution.workflow_id, execution.run_id));
+        }
+    }
 }
@@ -0,0 +1,315 @@
+//! Emitting metrics the way they were named before being renamed to follow the naming shared
+//! across Temporal SDKs. See
+//! [temporal_sdk_core_api::telemetry::TelemetryOptions::metric_prefix_compat].
+//!
+//! Renaming a metric, or the keys of its attributes, silently breaks every dashboard and alert
+//! built on the old scheme. While the compatibility option is on, each instrument in
+//! [LEGACY_METRIC_NAMES] also gets a legacy twin, and every update is recorded to both, so users
+//! can move their queries over before the legacy scheme goes away. The twin is recorded exactly as
+//! before the renames: under the legacy name, with the attribute keys in [LEGACY_ATTRIBUTE_KEYS]
+//! given their legacy names. This is done where instruments and attributes are created, so nothing
+//! recording them has to know about it.
+//!
+//! Recording a metric twice under one name would double count it, so a metric whose attribute keys
+//! were renamed needs a legacy name too before they are emitted the old way.
+
+use std::{any::Any, sync::Arc, time::Duration};
+use temporal_sdk_core_api::telemetry::metrics::{
+    CoreMeter, Counter, CustomMetricAttributes, Gauge, GaugeF64, Histogram, HistogramDuration,
+    HistogramF64, MetricAttributes, MetricParameters, NewAttributes,
+};
+
+/// Every renamed metric, as `(canonical, legacy)`, without the metric prefix. Entries should be
+/// removed once their legacy name has been deprecated for long enough.
+pub(crate) const LEGACY_METRIC_NAMES: &[(&str, &str)] = &[(
+    "local_activity_execution_canceled",
+    "local_activity_execution_cancelled",
+)];
+
+/// Every renamed attribute key, as `(canonical, legacy)`. Only the legacy twins of renamed metrics
+/// are recorded with the legacy keys.
+pub(crate) const LEGACY_ATTRIBUTE_KEYS: &[(&str, &str)] = &[];
+
+type Renames = &'static [(&'static str, &'static str)];
+
+fn legacy_of(renames: Renames, canonical: &str) -> Option<&'static str> {
+    renames
+        .iter()
+        .find(|(c, _)| *c == canonical)
+        .map(|(_, legacy)| *legacy)
+}
+
+/// Wraps a meter so that renamed metrics are also created, and recorded, the way they were before
+/// being renamed
+#[derive(Debug)]
+pub(crate) struct LegacyNamesMeter<CM> {
+    meter: CM,
+    names: Renames,
+    keys: Renames,
+}
+
+impl<CM> LegacyNamesMeter<CM> {
+    pub(crate) fn new(meter: CM) -> Self {
+        Self::with_renames(meter, LEGACY_METRIC_NAMES, LEGACY_ATTRIBUTE_KEYS)
+    }
+
+    fn with_renames(meter: CM, names: Renames, keys: Renames) -> Self {
+        Self { meter, names, keys }
+    }
+
+    fn legacy_keys(&self, mut attribs: NewAttributes) -> NewAttributes {
+        for kv in attribs.attributes.iter_mut() {
+            if let Some(legacy) = legacy_of(self.keys, &kv.key) {
+                kv.key = legacy.to_string();
+            }
+        }
+        attribs
+    }
+}
+
+/// Attributes created once with canonical keys, for canonical instruments, and once with legacy
+/// keys, for legacy twins. Only used when some attribute keys have been renamed.
+#[derive(Debug)]
+struct DualAttributes {
+    canonical: MetricAttributes,
+    legacy: MetricAttributes,
+}
+
+impl CustomMetricAttributes for DualAttributes {
+    fn as_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync> {
+        self as Arc<dyn Any + Send + Sync>
+    }
+}
+
+fn dual(attributes: &MetricAttributes) -> Option<Arc<DualAttributes>> {
+    match attributes {
+        MetricAttributes::Dynamic(d) => d.clone().as_any().downcast().ok(),
+        _ => None,
+    }
+}
+
+/// An instrument which records everything to both the canonical and legacy instruments
+struct Both<I: ?Sized>(Arc<I>, Arc<I>);
+
+/// An instrument without a legacy twin, which needs the canonical half of [DualAttributes]
+struct Canonical<I: ?Sized>(Arc<I>);
+
+macro_rules! fan_out {
+    ($trait:ident, $method:ident, $val:ty) => {
+        impl $trait for Both<dyn $trait> {
+            fn $method(&self, value: $val, attributes: &MetricAttributes) {
+                match dual(attributes) {
+                    Some(attributes) => {
+                        self.0.$method(value, &attributes.canonical);
+                        self.1.$method(value, &attributes.legacy);
+                    }
+                    None => {
+                        self.0.$method(value, attributes);
+                        self.1.$method(value, attributes);
+                    }
+                }
+            }
+        }
+
+        impl $trait for Canonical<dyn $trait> {
+            fn $method(&self, value: $val, attributes: &MetricAttributes) {
+                match dual(attributes) {
+                    Some(attributes) => self.0.$method(value, &attributes.canonical),
+                    None => self.0.$method(value, attributes),
+                }
+            }
+        }
+    };
+}
+fan_out!(Counter, add, u64);
+fan_out!(Histogram, record, u64);
+fan_out!(HistogramF64, record, f64);
+fan_out!(HistogramDuration, record, Duration);
+fan_out!(Gauge, record, u64);
+fan_out!(GaugeF64, record, f64);
+
+/// Create the instrument, along with its legacy twin if it has one
+macro_rules! create_both {
+    ($self:ident, $params:ident, $method:ident, $trait:ident) => {
+        match legacy_of($self.names, &$params.name) {
+            Some(legacy) => {
+                let legacy_params = MetricParameters {
+                    name: legacy.into(),
+                    ..$params.clone()
+                };
+                Arc::new(Both::<dyn $trait>(
+                    $self.meter.$method($params),
+                    $self.meter.$method(legacy_params),
+                ))
+            }
+            None if $self.keys.is_empty() => $self.meter.$method($params),
+            None => Arc::new(Canonical::<dyn $trait>($self.meter.$method($params))),
+        }
+    };
+}
+
+impl<CM: CoreMeter> CoreMeter for LegacyNamesMeter<CM> {
+    fn new_attributes(&self, attribs: NewAttributes) -> MetricAttributes {
+        if self.keys.is_empty() {
+            return self.meter.new_attributes(attribs);
+        }
+        let legacy = self.meter.new_attributes(self.legacy_keys(attribs.clone()));
+        MetricAttributes::Dynamic(Arc::new(DualAttributes {
+            canonical: self.meter.new_attributes(attribs),
+            legacy,
+        }))
+    }
+
+    fn extend_attributes(
+        &self,
+        existing: MetricAttributes,
+        attribs: NewAttributes,
+    ) -> MetricAttributes {
+        let Some(existing) = dual(&existing) else {
+            return self.meter.extend_attributes(existing, attribs);
+        };
+        let legacy = self
+            .meter
+            .extend_attributes(existing.legacy.clone(), self.legacy_keys(attribs.clone()));
+        MetricAttributes::Dynamic(Arc::new(DualAttributes {
+            canonical: self
+                .meter
+                .extend_attributes(existing.canonical.clone(), attribs),
+            legacy,
+        }))
+    }
+
+    fn counter(&self, params: MetricParameters) -> Arc<dyn Counter> {
+        create_both!(self, params, counter, Counter)
+    }
+
+    fn histogram(&self, params: MetricParameters) -> Arc<dyn Histogram> {
+        create_both!(self, params, histogram, Histogram)
+    }
+
+    fn histogram_f64(&self, params: MetricParameters) -> Arc<dyn HistogramF64> {
+        create_both!(self, params, histogram_f64, HistogramF64)
+    }
+
+    fn histogram_duration(&self, params: MetricParameters) -> Arc<dyn HistogramDuration> {
+        create_both!(self, params, histogram_duration, HistogramDuration)
+    }
+
+    fn gauge(&self, params: MetricParameters) -> Arc<dyn Gauge> {
+        create_both!(self, params, gauge, Gauge)
+    }
+
+    fn gauge_f64(&self, params: MetricParameters) -> Arc<dyn GaugeF64> {
+        create_both!(self, params, gauge_f64, GaugeF64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_help::{BufferedTelemetry, MetricBuffer},
+        MetricsContext,
+    };
+    use std::collections::HashMap;
+    use temporal_sdk_core_api::telemetry::metrics::{MetricKeyValue, MetricUpdateVal};
+
+    /// Metric names recorded for one cancelled local activity, with `compat` set (if not `None`)
+    fn cancelled_la_updates(compat: Option<bool>) -> Vec<String> {
+        let telem = BufferedTelemetry::with_options(|o| {
+            if let Some(compat) = compat {
+                o.metric_prefix_compat(compat);
+            }
+        });
+        let metrics = MetricsContext::top_level("ns".to_string(), "tq".to_string(), &telem);
+        metrics.la_execution_cancelled();
+        telem
+            .updates()
+            .into_iter()
+            .map(|(name, attrs, update)| {
+                assert_eq!(attrs.get("task_queue").map(String::as_str), Some("tq"));
+                assert_matches!(update, MetricUpdateVal::Delta(1));
+                name
+            })
+            .collect()
+    }
+
+    #[rstest::rstest]
+    #[case::by_default(None)]
+    #[case::when_enabled(Some(true))]
+    fn renamed_metrics_are_also_emitted_under_legacy_names(#[case] compat: Option<bool>) {
+        assert_eq!(
+            cancelled_la_updates(compat),
+            vec![
+                "temporal_local_activity_execution_canceled",
+                "temporal_local_activity_execution_cancelled"
+            ]
+        );
+    }
+
+    #[test]
+    fn only_canonical_names_are_emitted_when_disabled() {
+        assert_eq!(
+            cancelled_la_updates(Some(false)),
+            vec!["temporal_local_activity_execution_canceled"]
+        );
+    }
+
+    #[test]
+    fn legacy_twins_are_recorded_with_legacy_attribute_keys() {
+        let buffer = MetricBuffer::default();
+        let meter = LegacyNamesMeter::with_renames(
+            buffer.meter(),
+            &[("polls", "poll_count")],
+            &[("queue", "tq")],
+        );
+        let attrs =
+            meter.new_attributes(NewAttributes::new(vec![MetricKeyValue::new("queue", "q")]));
+        let attrs = meter.extend_attributes(
+            attrs,
+            NewAttributes::new(vec![MetricKeyValue::new("kind", "sticky")]),
+        );
+        let params = |name: &'static str| MetricParameters {
+            name: name.into(),
+            description: "".into(),
+            unit: "".into(),
+        };
+        meter.counter(params("polls")).add(1, &attrs);
+        meter.counter(params("failures")).add(2, &attrs);
+
+        let attrs = |queue_key: &str| {
+            HashMap::from([
+                (queue_key.to_string(), "q".to_string()),
+                ("kind".to_string(), "sticky".to_string()),
+            ])
+        };
+        let updates: Vec<_> = buffer
+            .updates()
+            .into_iter()
+            .map(|(name, attrs, update)| {
+                let MetricUpdateVal::Delta(delta) = update else {
+                    panic!("Unexpected update {update:?}");
+                };
+                (name, attrs, delta)
+            })
+            .collect();
+        assert_eq!(
+            updates,
+            vec![
+                ("polls".to_string(), attrs("queue"), 1),
+                ("poll_count".to_string(), attrs("tq"), 1),
+                ("failures".to_string(), attrs("queue"), 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn legacy_names_are_distinct_from_every_canonical_name() {
+        for renames in [LEGACY_METRIC_NAMES, LEGACY_ATTRIBUTE_KEYS] {
+            for (canonical, legacy) in renames {
+                assert_ne!(canonical, legacy);
+                assert_eq!(legacy_of(renames, legacy), None);
+            }
+        }
+    }
+}
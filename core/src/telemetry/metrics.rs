@@ -1,4 +1,4 @@
-use crate::{abstractions::dbg_panic, telemetry::TelemetryInstance};
+use crate::{abstractions::dbg_panic, pollers::PollStats, telemetry::TelemetryInstance};
 
 use std::{
     fmt::{Debug, Display},
@@ -12,7 +12,10 @@ use temporal_sdk_core_api::telemetry::metrics::{
     MetricEvent, MetricKeyValue, MetricKind, MetricParameters, MetricUpdateVal, NewAttributes,
     NoOpCoreMeter,
 };
-use temporal_sdk_core_protos::temporal::api::enums::v1::WorkflowTaskFailedCause;
+use temporal_sdk_core_protos::temporal::api::{
+    enums::v1::{TaskQueueKind, WorkflowTaskFailedCause},
+    failure::v1::{failure::FailureInfo, Failure},
+};
 
 /// Used to track context associated with metrics, and record/update them
 ///
@@ -37,25 +40,52 @@ struct Instruments {
     wf_task_sched_to_start_latency: Arc<dyn HistogramDuration>,
     wf_task_replay_latency: Arc<dyn HistogramDuration>,
     wf_task_execution_latency: Arc<dyn HistogramDuration>,
+    wf_completion_verification_latency: Arc<dyn HistogramDuration>,
+    wf_replay_progress: Arc<dyn GaugeF64>,
     act_poll_no_task: Arc<dyn Counter>,
     act_task_received_counter: Arc<dyn Counter>,
+    act_task_duplicate_dropped: Arc<dyn Counter>,
+    act_heartbeat_channel_lost: Arc<dyn Counter>,
     act_execution_failed: Arc<dyn Counter>,
     act_sched_to_start_latency: Arc<dyn HistogramDuration>,
     act_exec_latency: Arc<dyn HistogramDuration>,
     act_exec_succeeded_latency: Arc<dyn HistogramDuration>,
+    act_heartbeats: Arc<dyn Counter>,
+    act_completion_gone: Arc<dyn Counter>,
     la_execution_cancelled: Arc<dyn Counter>,
     la_execution_failed: Arc<dyn Counter>,
     la_exec_latency: Arc<dyn HistogramDuration>,
     la_exec_succeeded_latency: Arc<dyn HistogramDuration>,
     la_total: Arc<dyn Counter>,
+    wf_history_fetch_limit_exceeded: Arc<dyn Counter>,
     worker_registered: Arc<dyn Counter>,
     num_pollers: Arc<dyn Gauge>,
+    poll_success_ratio: Arc<dyn GaugeF64>,
+    polls_issued: Arc<dyn Gauge>,
+    polls_with_task: Arc<dyn Gauge>,
+    polls_empty: Arc<dyn Gauge>,
     task_slots_available: Arc<dyn Gauge>,
     task_slots_used: Arc<dyn Gauge>,
+    poll_decode_failures: Arc<dyn Counter>,
+    sticky_cache_thrash: Arc<dyn Counter>,
+    sticky_cache_intake_delayed: Arc<dyn Counter>,
     sticky_cache_hit: Arc<dyn Counter>,
     sticky_cache_miss: Arc<dyn Counter>,
+    sticky_timeout_fallback: Arc<dyn Counter>,
+    sticky_queue_not_found: Arc<dyn Counter>,
+    suspected_dropped_jobs: Arc<dyn Counter>,
+    run_recoveries: Arc<dyn Counter>,
+    wft_delivered_near_deadline: Arc<dyn Counter>,
+    activity_task_queue_without_pollers: Arc<dyn Counter>,
     sticky_cache_size: Arc<dyn Gauge>,
+    sticky_cache_bytes: Arc<dyn Gauge>,
     sticky_cache_forced_evictions: Arc<dyn Counter>,
+    history_replay_queue_depth: Arc<dyn Gauge>,
+    history_replay_queue_expired: Arc<dyn Counter>,
+    payload_size: Arc<dyn Histogram>,
+    message_size: Arc<dyn Histogram>,
+    poll_buffer_unclaimed_tasks: Arc<dyn Gauge>,
+    poll_buffer_unclaimed_bytes: Arc<dyn Gauge>,
 }
 
 impl MetricsContext {
@@ -168,16 +198,54 @@ impl MetricsContext {
             .record(dur, &self.kvs);
     }
 
+    /// Record how long it took to verify a workflow task completion by replaying its run
+    pub(crate) fn wf_completion_verification_latency(&self, dur: Duration) {
+        self.instruments
+            .wf_completion_verification_latency
+            .record(dur, &self.kvs);
+    }
+
+    /// Record the fraction of a replaying run's known history which has been applied
+    pub(crate) fn wf_replay_progress(&self, fraction: f64) {
+        self.instruments
+            .wf_replay_progress
+            .record(fraction, &self.kvs);
+    }
+
     /// An activity long poll timed out
     pub(crate) fn act_poll_timeout(&self) {
         self.instruments.act_poll_no_task.add(1, &self.kvs);
     }
 
+    /// Count the heartbeats an activity recorded, once it has completed
+    pub(crate) fn act_heartbeats(&self, count: u64) {
+        self.instruments.act_heartbeats.add(count, &self.kvs);
+    }
+
+    /// An activity completion was dropped because server no longer knew about the activity
+    pub(crate) fn act_completion_gone(&self) {
+        self.instruments.act_completion_gone.add(1, &self.kvs);
+    }
+
     /// A count of activity tasks received
     pub(crate) fn act_task_received(&self) {
         self.instruments.act_task_received_counter.add(1, &self.kvs);
     }
 
+    /// An activity task was dropped because it had already been delivered
+    pub(crate) fn act_task_duplicate_dropped(&self) {
+        self.instruments
+            .act_task_duplicate_dropped
+            .add(1, &self.kvs);
+    }
+
+    /// An activity was cancelled because its heartbeats kept failing to reach server
+    pub(crate) fn act_heartbeat_channel_lost(&self) {
+        self.instruments
+            .act_heartbeat_channel_lost
+            .add(1, &self.kvs);
+    }
+
     /// An activity execution failed
     pub(crate) fn act_execution_failed(&self) {
         self.instruments.act_execution_failed.add(1, &self.kvs);
@@ -225,6 +293,13 @@ impl MetricsContext {
         self.instruments.la_total.add(1, &self.kvs);
     }
 
+    /// Rebuilding a run was abandoned because its history exceeded the configured fetch limit
+    pub(crate) fn wf_history_fetch_limit_exceeded(&self) {
+        self.instruments
+            .wf_history_fetch_limit_exceeded
+            .add(1, &self.kvs);
+    }
+
     /// A worker was registered
     pub(crate) fn worker_registered(&self) {
         self.instruments.worker_registered.add(1, &self.kvs);
@@ -247,6 +322,62 @@ impl MetricsContext {
         self.instruments.num_pollers.record(num as u64, &self.kvs);
     }
 
+    /// Record the counts of poll outcomes within the poll stats window, and the fraction of polls
+    /// which returned a task. Context should include poller type / task queue tag.
+    pub(crate) fn poll_stats(&self, stats: PollStats) {
+        self.instruments
+            .poll_success_ratio
+            .record(stats.success_ratio(), &self.kvs);
+        self.instruments
+            .polls_issued
+            .record(stats.polls_issued, &self.kvs);
+        self.instruments
+            .polls_with_task
+            .record(stats.polls_with_task, &self.kvs);
+        self.instruments
+            .polls_empty
+            .record(stats.polls_empty, &self.kvs);
+    }
+
+    /// Record the number of polled tasks waiting in a poll buffer for lang to pick them up. Context
+    /// should include poller type / task queue tag.
+    pub(crate) fn poll_buffer_unclaimed_tasks(&self, num: usize) {
+        self.instruments
+            .poll_buffer_unclaimed_tasks
+            .record(num as u64, &self.kvs);
+    }
+
+    /// Record the total encoded size of the polled tasks waiting in a poll buffer. Context should
+    /// include poller type / task queue tag.
+    pub(crate) fn poll_buffer_unclaimed_bytes(&self, bytes: usize) {
+        self.instruments
+            .poll_buffer_unclaimed_bytes
+            .record(bytes as u64, &self.kvs);
+    }
+
+    /// A poll response from server could not be decoded. Context should include poller type / task
+    /// queue tag.
+    pub(crate) fn poll_response_decode_failure(&self, method: &'static str) {
+        let kvs = self.meter.extend_attributes(
+            self.kvs.clone(),
+            vec![MetricKeyValue::new(KEY_SVC_METHOD, method)].into(),
+        );
+        self.instruments.poll_decode_failures.add(1, &kvs);
+    }
+
+    /// A run evicted to make room in the cache came back shortly afterward, needing its history
+    /// fetched again
+    pub(crate) fn cache_thrash(&self) {
+        self.instruments.sticky_cache_thrash.add(1, &self.kvs);
+    }
+
+    /// New runs began waiting for a cache slot because every cached run was busy
+    pub(crate) fn cache_intake_delayed(&self) {
+        self.instruments
+            .sticky_cache_intake_delayed
+            .add(1, &self.kvs);
+    }
+
     /// A workflow task found a cached workflow to run against
     pub(crate) fn sticky_cache_hit(&self) {
         self.instruments.sticky_cache_hit.add(1, &self.kvs);
@@ -257,17 +388,100 @@ impl MetricsContext {
         self.instruments.sticky_cache_miss.add(1, &self.kvs);
     }
 
+    /// A workflow task for a cached workflow arrived on the normal queue, most likely because
+    /// server gave up waiting for it to be picked up from the sticky queue
+    pub(crate) fn sticky_timeout_fallback(&self) {
+        self.instruments.sticky_timeout_fallback.add(1, &self.kvs);
+    }
+
+    /// Server did not know the worker's sticky queue when it was polled
+    pub(crate) fn sticky_queue_not_found(&self) {
+        self.instruments.sticky_queue_not_found.add(1, &self.kvs);
+    }
+
+    /// Lang completed too many activations in a row without reacting to their signals, updates or
+    /// cancellations
+    pub(crate) fn suspected_dropped_jobs(&self) {
+        self.instruments.suspected_dropped_jobs.add(1, &self.kvs);
+    }
+
+    /// A run whose cached state was suspected to be corrupt was recovered, for the given cause
+    pub(crate) fn run_recovered(&self, cause: &'static str) {
+        let kvs = self.meter.extend_attributes(
+            self.kvs.clone(),
+            vec![MetricKeyValue::new(KEY_RECOVERY_CAUSE, cause)].into(),
+        );
+        self.instruments.run_recoveries.add(1, &kvs);
+    }
+
+    /// An activation was delivered to lang with little of its workflow task's timeout remaining
+    pub(crate) fn wft_delivered_near_deadline(&self) {
+        self.instruments
+            .wft_delivered_near_deadline
+            .add(1, &self.kvs);
+    }
+
+    /// Server knows of nothing polling a task queue which a workflow scheduled an activity on
+    pub(crate) fn activity_task_queue_without_pollers(&self, target_queue: &str) {
+        let kvs = self.meter.extend_attributes(
+            self.kvs.clone(),
+            vec![MetricKeyValue::new(
+                KEY_TARGET_TASK_QUEUE,
+                target_queue.to_string(),
+            )]
+            .into(),
+        );
+        self.instruments
+            .activity_task_queue_without_pollers
+            .add(1, &kvs);
+    }
+
     /// Record current cache size (in number of wfs, not bytes)
     pub(crate) fn cache_size(&self, size: u64) {
         self.instruments.sticky_cache_size.record(size, &self.kvs);
     }
 
+    /// Record the approximate number of bytes retained by cached workflows
+    pub(crate) fn cache_bytes(&self, bytes: u64) {
+        self.instruments.sticky_cache_bytes.record(bytes, &self.kvs);
+    }
+
     /// Count a workflow being evicted from the cache
     pub(crate) fn forced_cache_eviction(&self) {
         self.instruments
             .sticky_cache_forced_evictions
             .add(1, &self.kvs);
     }
+
+    /// Record the number of workflow tasks waiting for a history replay slot
+    pub(crate) fn history_replay_queue_depth(&self, depth: u64) {
+        self.instruments
+            .history_replay_queue_depth
+            .record(depth, &self.kvs);
+    }
+
+    /// A workflow task was dropped after waiting past its timeout for a history replay slot
+    pub(crate) fn history_replay_queue_expired(&self) {
+        self.instruments
+            .history_replay_queue_expired
+            .add(1, &self.kvs);
+    }
+
+    /// Record the encoded size of a payload passing between lang and core. Context should include
+    /// the payload direction tag.
+    pub(crate) fn payload_size(&self, bytes: usize) {
+        self.instruments
+            .payload_size
+            .record(bytes as u64, &self.kvs);
+    }
+
+    /// Record the encoded size of a whole message passing between lang and core. Context should
+    /// include the message type tag.
+    pub(crate) fn message_size(&self, bytes: usize) {
+        self.instruments
+            .message_size
+            .record(bytes as u64, &self.kvs);
+    }
 }
 
 impl Instruments {
@@ -328,6 +542,18 @@ impl Instruments {
                 unit: "duration".into(),
                 description: "Histogram of workflow task execution (not replay) latencies".into(),
             }),
+            wf_completion_verification_latency: meter.histogram_duration(MetricParameters {
+                name: "workflow_completion_verification_latency".into(),
+                unit: "duration".into(),
+                description: "Histogram of the time spent replaying runs to verify workflow task \
+                              completions before sending them"
+                    .into(),
+            }),
+            wf_replay_progress: meter.gauge_f64(MetricParameters {
+                name: "workflow_replay_progress".into(),
+                description: "Fraction of known history events applied by a replaying run".into(),
+                unit: "".into(),
+            }),
             act_poll_no_task: meter.counter(MetricParameters {
                 name: "activity_poll_no_task".into(),
                 description: "Count of activity task queue poll timeouts (no new task)".into(),
@@ -338,6 +564,20 @@ impl Instruments {
                 description: "Count of activity task queue poll successes".into(),
                 unit: "".into(),
             }),
+            act_task_duplicate_dropped: meter.counter(MetricParameters {
+                name: "activity_task_duplicate_dropped".into(),
+                description:
+                    "Count of activity tasks dropped because they had already been delivered"
+                        .into(),
+                unit: "".into(),
+            }),
+            act_heartbeat_channel_lost: meter.counter(MetricParameters {
+                name: "activity_heartbeat_channel_lost".into(),
+                description: "Count of activities cancelled because their heartbeats kept failing \
+                              to reach server"
+                    .into(),
+                unit: "".into(),
+            }),
             act_execution_failed: meter.counter(MetricParameters {
                 name: "activity_execution_failed".into(),
                 description: "Count of activity task execution failures".into(),
@@ -359,8 +599,20 @@ impl Instruments {
                 description: "Histogram of activity execution latencies for successful activities"
                     .into(),
             }),
+            act_heartbeats: meter.counter(MetricParameters {
+                name: "activity_heartbeat".into(),
+                description: "Count of heartbeats recorded by activities".into(),
+                unit: "".into(),
+            }),
+            act_completion_gone: meter.counter(MetricParameters {
+                name: "activity_completion_gone".into(),
+                description:
+                    "Count of activity completions dropped because the activity no longer existed"
+                        .into(),
+                unit: "".into(),
+            }),
             la_execution_cancelled: meter.counter(MetricParameters {
-                name: "local_activity_execution_cancelled".into(),
+                name: "local_activity_execution_canceled".into(),
                 description: "Count of local activity executions that were cancelled".into(),
                 unit: "".into(),
             }),
@@ -386,6 +638,13 @@ impl Instruments {
                 description: "Count of local activities executed".into(),
                 unit: "".into(),
             }),
+            wf_history_fetch_limit_exceeded: meter.counter(MetricParameters {
+                name: "workflow_history_fetch_limit_exceeded".into(),
+                description: "Count of workflow tasks failed because fetching their history \
+                              would exceed the worker's configured limit"
+                    .into(),
+                unit: "".into(),
+            }),
             // name kept as worker start for compat with old sdk / what users expect
             worker_registered: meter.counter(MetricParameters {
                 name: "worker_start".into(),
@@ -397,6 +656,29 @@ impl Instruments {
                 description: "Current number of active pollers per queue type".into(),
                 unit: "".into(),
             }),
+            poll_success_ratio: meter.gauge_f64(MetricParameters {
+                name: "poll_success_ratio".into(),
+                description: "Fraction of recent polls which returned a task, per queue type"
+                    .into(),
+                unit: "".into(),
+            }),
+            polls_issued: meter.gauge(MetricParameters {
+                name: "recent_polls_issued".into(),
+                description: "Number of recent polls which completed, per queue type".into(),
+                unit: "".into(),
+            }),
+            polls_with_task: meter.gauge(MetricParameters {
+                name: "recent_polls_with_task".into(),
+                description: "Number of recent polls which returned a task, per queue type".into(),
+                unit: "".into(),
+            }),
+            polls_empty: meter.gauge(MetricParameters {
+                name: "recent_polls_empty".into(),
+                description: "Number of recent polls which timed out without a task, per queue \
+                              type"
+                    .into(),
+                unit: "".into(),
+            }),
             task_slots_available: meter.gauge(MetricParameters {
                 name: TASK_SLOTS_AVAILABLE_NAME.into(),
                 description: "Current number of available slots per task type".into(),
@@ -407,6 +689,23 @@ impl Instruments {
                 description: "Current number of used slots per task type".into(),
                 unit: "".into(),
             }),
+            poll_decode_failures: meter.counter(MetricParameters {
+                name: "poll_response_decode_failure".into(),
+                description: "Count of poll responses from server which could not be decoded"
+                    .into(),
+                unit: "".into(),
+            }),
+            sticky_cache_thrash: meter.counter(MetricParameters {
+                name: "sticky_cache_thrash".into(),
+                description: "Count of cache-full evictions followed shortly by a refetch".into(),
+                unit: "".into(),
+            }),
+            sticky_cache_intake_delayed: meter.counter(MetricParameters {
+                name: "sticky_cache_intake_delayed".into(),
+                description: "Count of times new workflows waited on a cache of busy workflows"
+                    .into(),
+                unit: "".into(),
+            }),
             sticky_cache_hit: meter.counter(MetricParameters {
                 name: "sticky_cache_hit".into(),
                 description: "Count of times the workflow cache was used for a new workflow task"
@@ -420,11 +719,95 @@ impl Instruments {
                         .into(),
                 unit: "".into(),
             }),
+            sticky_timeout_fallback: meter.counter(MetricParameters {
+                name: "sticky_timeout_fallback".into(),
+                description: "Count of workflow tasks for cached workflows which arrived on the \
+                              normal queue rather than the sticky one"
+                    .into(),
+                unit: "".into(),
+            }),
+            sticky_queue_not_found: meter.counter(MetricParameters {
+                name: "sticky_queue_not_found".into(),
+                description: "Count of sticky queue polls which server answered with not found"
+                    .into(),
+                unit: "".into(),
+            }),
+            suspected_dropped_jobs: meter.counter(MetricParameters {
+                name: "workflow_suspected_dropped_jobs".into(),
+                description: "Count of runs whose completions repeatedly ignored signals, updates \
+                              or cancellations"
+                    .into(),
+                unit: "".into(),
+            }),
+            run_recoveries: meter.counter(MetricParameters {
+                name: "workflow_run_recoveries".into(),
+                description: "Count of runs whose task was failed and sticky queue reset because \
+                              their cached state was suspected to be corrupt"
+                    .into(),
+                unit: "".into(),
+            }),
+            wft_delivered_near_deadline: meter.counter(MetricParameters {
+                name: "workflow_task_delivered_near_deadline".into(),
+                description: "Count of activations delivered with less than 20% of their \
+                              workflow task's timeout remaining"
+                    .into(),
+                unit: "".into(),
+            }),
+            activity_task_queue_without_pollers: meter.counter(MetricParameters {
+                name: "activity_task_queue_without_pollers".into(),
+                description: "Count of checks finding nothing polling a task queue which \
+                              activities were scheduled on"
+                    .into(),
+                unit: "".into(),
+            }),
             sticky_cache_size: meter.gauge(MetricParameters {
                 name: STICKY_CACHE_SIZE_NAME.into(),
                 description: "Current number of cached workflows".into(),
                 unit: "".into(),
             }),
+            sticky_cache_bytes: meter.gauge(MetricParameters {
+                name: "sticky_cache_bytes".into(),
+                description: "Approximate bytes of memory retained by cached workflows".into(),
+                unit: "".into(),
+            }),
+            history_replay_queue_depth: meter.gauge(MetricParameters {
+                name: "history_replay_queue_depth".into(),
+                description: "Current number of workflow tasks waiting to replay history".into(),
+                unit: "".into(),
+            }),
+            history_replay_queue_expired: meter.counter(MetricParameters {
+                name: "history_replay_queue_expired".into(),
+                description: "Count of workflow tasks dropped after waiting past their timeout \
+                              to replay history"
+                    .into(),
+                unit: "".into(),
+            }),
+            payload_size: meter.histogram(MetricParameters {
+                name: "payload_size".into(),
+                unit: "bytes".into(),
+                description: "Histogram of the sizes of payloads passed between lang and core"
+                    .into(),
+            }),
+            message_size: meter.histogram(MetricParameters {
+                name: "message_size".into(),
+                unit: "bytes".into(),
+                description: "Histogram of the encoded sizes of activations, activity tasks, and \
+                              their completions passed between lang and core"
+                    .into(),
+            }),
+            poll_buffer_unclaimed_tasks: meter.gauge(MetricParameters {
+                name: "poll_buffer_unclaimed_tasks".into(),
+                description: "Current number of polled tasks waiting to be picked up by lang"
+                    .into(),
+                unit: "".into(),
+            }),
+            poll_buffer_unclaimed_bytes: meter.gauge(MetricParameters {
+                name: "poll_buffer_unclaimed_bytes".into(),
+                description: "Current total encoded size of the polled tasks waiting to be \
+                              picked up by lang"
+                    .into(),
+                unit: "bytes".into(),
+            }),
             sticky_cache_forced_evictions: meter.counter(MetricParameters {
                 name: "sticky_cache_total_forced_eviction".into(),
                 description: "Count of evictions of cached workflows".into(),
@@ -442,6 +825,12 @@ const KEY_POLLER_TYPE: &str = "poller_type";
 const KEY_WORKER_TYPE: &str = "worker_type";
 const KEY_EAGER: &str = "eager";
 const KEY_TASK_FAILURE_TYPE: &str = "failure_reason";
+const KEY_SVC_METHOD: &str = "operation";
+const KEY_PAYLOAD_DIRECTION: &str = "direction";
+const KEY_TASK_QUEUE_KIND: &str = "task_queue_kind";
+const KEY_RECOVERY_CAUSE: &str = "cause";
+const KEY_TARGET_TASK_QUEUE: &str = "target_task_queue";
+const KEY_MESSAGE_TYPE: &str = "message_type";
 
 pub(crate) fn workflow_poller() -> MetricKeyValue {
     MetricKeyValue::new(KEY_POLLER_TYPE, "workflow_task")
@@ -473,6 +862,55 @@ pub(crate) fn local_activity_worker_type() -> MetricKeyValue {
 pub(crate) fn eager(is_eager: bool) -> MetricKeyValue {
     MetricKeyValue::new(KEY_EAGER, is_eager)
 }
+/// Whether a payload is being delivered to lang, or was sent by it
+pub(crate) enum PayloadDirection {
+    Inbound,
+    Outbound,
+}
+pub(crate) fn payload_direction(direction: PayloadDirection) -> MetricKeyValue {
+    let direction = match direction {
+        PayloadDirection::Inbound => "inbound",
+        PayloadDirection::Outbound => "outbound",
+    };
+    MetricKeyValue::new(KEY_PAYLOAD_DIRECTION, direction)
+}
+/// The kinds of message passing between lang and core whose sizes are recorded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MessageType {
+    WorkflowActivation,
+    WorkflowActivationCompletion,
+    ActivityTask,
+    ActivityTaskCompletion,
+}
+impl MessageType {
+    pub(crate) const ALL: [MessageType; 4] = [
+        MessageType::WorkflowActivation,
+        MessageType::WorkflowActivationCompletion,
+        MessageType::ActivityTask,
+        MessageType::ActivityTaskCompletion,
+    ];
+
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            MessageType::WorkflowActivation => "workflow_activation",
+            MessageType::WorkflowActivationCompletion => "workflow_activation_completion",
+            MessageType::ActivityTask => "activity_task",
+            MessageType::ActivityTaskCompletion => "activity_task_completion",
+        }
+    }
+}
+pub(crate) fn message_type(ty: MessageType) -> MetricKeyValue {
+    MetricKeyValue::new(KEY_MESSAGE_TYPE, ty.as_str())
+}
+/// Tags a workflow task with the kind of queue it was polled from
+pub(crate) fn task_queue_kind(kind: TaskQueueKind) -> MetricKeyValue {
+    let kind = match kind {
+        TaskQueueKind::Normal => "normal",
+        TaskQueueKind::Sticky => "sticky",
+        TaskQueueKind::Unspecified => "unspecified",
+    };
+    MetricKeyValue::new(KEY_TASK_QUEUE_KIND, kind)
+}
 pub(crate) enum FailureReason {
     Nondeterminism,
     Workflow,
@@ -497,6 +935,22 @@ impl From<WorkflowTaskFailedCause> for FailureReason {
 pub(crate) fn failure_reason(reason: FailureReason) -> MetricKeyValue {
     MetricKeyValue::new(KEY_TASK_FAILURE_TYPE, reason.to_string())
 }
+/// Tags an activity failure with the kind of failure lang reported, EX: `ApplicationFailure`
+pub(crate) fn activity_failure_type(failure: Option<&Failure>) -> MetricKeyValue {
+    let failure_type = match failure.and_then(|f| f.failure_info.as_ref()) {
+        Some(FailureInfo::ApplicationFailureInfo(_)) => "ApplicationFailure",
+        Some(FailureInfo::TimeoutFailureInfo(_)) => "TimeoutFailure",
+        Some(FailureInfo::CanceledFailureInfo(_)) => "CanceledFailure",
+        Some(FailureInfo::TerminatedFailureInfo(_)) => "TerminatedFailure",
+        Some(FailureInfo::ServerFailureInfo(_)) => "ServerFailure",
+        Some(FailureInfo::ResetWorkflowFailureInfo(_)) => "ResetWorkflowFailure",
+        Some(FailureInfo::ActivityFailureInfo(_)) => "ActivityFailure",
+        Some(FailureInfo::ChildWorkflowExecutionFailureInfo(_)) => "ChildWorkflowFailure",
+        Some(FailureInfo::NexusOperationExecutionFailureInfo(_)) => "NexusOperationFailure",
+        None => "Unknown",
+    };
+    MetricKeyValue::new(KEY_TASK_FAILURE_TYPE, failure_type)
+}
 
 /// The string name (which may be prefixed) for this metric
 pub const WORKFLOW_E2E_LATENCY_HISTOGRAM_NAME: &str = "workflow_endtoend_latency";
@@ -775,11 +1229,13 @@ where
     }
 }
 
+#[cfg(feature = "telemetry")]
 #[derive(Debug, derive_more::Constructor)]
 pub(crate) struct PrefixedMetricsMeter<CM> {
     prefix: String,
     meter: CM,
 }
+#[cfg(feature = "telemetry")]
 impl<CM: CoreMeter> CoreMeter for PrefixedMetricsMeter<CM> {
     fn new_attributes(&self, attribs: NewAttributes) -> MetricAttributes {
         self.meter.new_attributes(attribs)
@@ -824,12 +1280,85 @@ impl<CM: CoreMeter> CoreMeter for PrefixedMetricsMeter<CM> {
     }
 }
 
+/// Helpers for tests which play the lang side of a [MetricsCallBuffer]
+#[cfg(test)]
+pub(crate) mod buffered {
+    use super::*;
+    use std::{any::Any, collections::HashMap};
+    use temporal_sdk_core_api::telemetry::metrics::{
+        BufferInstrumentRef, CustomMetricAttributes, MetricValue,
+    };
+
+    #[derive(Debug, Clone)]
+    pub(crate) struct MetricName(pub(crate) String);
+    impl BufferInstrumentRef for MetricName {}
+
+    #[derive(Debug)]
+    pub(crate) struct ResolvedAttrs(HashMap<String, String>);
+    impl CustomMetricAttributes for ResolvedAttrs {
+        fn as_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync> {
+            self as Arc<dyn Any + Send + Sync>
+        }
+    }
+
+    pub(crate) fn resolved(attrs: &BufferAttributes) -> HashMap<String, String> {
+        attrs
+            .get()
+            .clone()
+            .as_any()
+            .downcast::<ResolvedAttrs>()
+            .unwrap()
+            .0
+            .clone()
+    }
+
+    /// Plays the lang side of the metrics buffer, returning every update with its metric name and
+    /// the attributes it was recorded with
+    pub(crate) fn buffered_updates(
+        events: Vec<MetricEvent<MetricName>>,
+    ) -> Vec<(String, HashMap<String, String>, MetricUpdateVal)> {
+        let mut updates = vec![];
+        for event in events {
+            match event {
+                MetricEvent::Create {
+                    params,
+                    populate_into,
+                    ..
+                } => {
+                    let _ = populate_into.set(Arc::new(MetricName(params.name.to_string())));
+                }
+                MetricEvent::CreateAttributes {
+                    populate_into,
+                    append_from,
+                    attributes,
+                } => {
+                    let mut attrs = append_from.as_ref().map(resolved).unwrap_or_default();
+                    attrs.extend(attributes.into_iter().map(|kv| {
+                        let val = match kv.value {
+                            MetricValue::String(s) => s,
+                            other => format!("{other:?}"),
+                        };
+                        (kv.key, val)
+                    }));
+                    let _ = populate_into.set(Arc::new(ResolvedAttrs(attrs)));
+                }
+                MetricEvent::Update {
+                    instrument,
+                    attributes,
+                    update,
+                } => updates.push((instrument.get().0.clone(), resolved(&attributes), update)),
+            }
+        }
+        updates
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::any::Any;
     use temporal_sdk_core_api::telemetry::{
-        metrics::{BufferInstrumentRef, CustomMetricAttributes},
+        metrics::{BufferInstrumentRef, CustomMetricAttributes, MetricValue},
         METRIC_PREFIX,
     };
     use tracing::subscriber::NoSubscriber;
@@ -865,6 +1394,7 @@ mod tests {
             Some(no_op_subscriber),
             None,
             METRIC_PREFIX.to_string(),
+            false,
             Some(call_buffer.clone()),
             true,
         );
@@ -886,7 +1416,7 @@ mod tests {
         a1.set(Arc::new(DummyCustomAttrs(1))).unwrap();
         // Verify all metrics are created. This number will need to get updated any time a metric
         // is added.
-        let num_metrics = 30;
+        let num_metrics = 54;
         #[allow(clippy::needless_range_loop)] // Sorry clippy, this reads easier.
         for metric_num in 1..=num_metrics {
             let hole = assert_matches!(&events[metric_num],
@@ -1071,4 +1601,74 @@ mod tests {
                && d == Duration::from_secs_f64(1.2)
         );
     }
+
+    #[test]
+    fn task_queue_kinds_are_not_conflated() {
+        let tags: Vec<_> = [
+            TaskQueueKind::Normal,
+            TaskQueueKind::Sticky,
+            TaskQueueKind::Unspecified,
+        ]
+        .into_iter()
+        .map(|k| match task_queue_kind(k).value {
+            MetricValue::String(s) => s,
+            other => panic!("Unexpected tag value {other:?}"),
+        })
+        .collect();
+        assert_eq!(tags, ["normal", "sticky", "unspecified"]);
+    }
+
+    #[tokio::test]
+    async fn client_request_metrics_tag_retry_attempts() {
+        use crate::test_help::BufferedTelemetry;
+        use temporal_client::{
+            ClientOptionsBuilder, ConnectionManager, RetryConfig, WorkflowService,
+        };
+        use temporal_sdk_core_protos::temporal::api::workflowservice::v1::DescribeNamespaceRequest;
+
+        let telem = BufferedTelemetry::new();
+        // Nothing listens here, so every attempt fails and is retried
+        let opts = ClientOptionsBuilder::default()
+            .target_url(url::Url::parse("http://127.0.0.1:1").unwrap())
+            .client_name("cute-kitty")
+            .client_version("0.1.0")
+            .skip_get_system_info(true)
+            .retry_config(RetryConfig {
+                initial_interval: Duration::from_millis(1),
+                randomization_factor: 0.0,
+                multiplier: 1.0,
+                max_interval: Duration::from_millis(1),
+                max_elapsed_time: None,
+                max_retries: 2,
+            })
+            .build()
+            .unwrap();
+        let mut client = opts
+            .connect_shared(
+                &ConnectionManager::default(),
+                "ns",
+                telem.get_temporal_metric_meter(),
+            )
+            .await
+            .unwrap();
+        WorkflowService::describe_namespace(
+            &mut client,
+            DescribeNamespaceRequest {
+                namespace: "ns".to_string(),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap_err();
+
+        let attempts: Vec<_> = telem
+            .updates()
+            .into_iter()
+            .filter(|(name, attrs, _)| {
+                name == "temporal_request" && attrs["operation"] == "DescribeNamespace"
+            })
+            .map(|(_, attrs, _)| attrs["is_retry"].clone())
+            .collect();
+        assert_eq!(attempts, ["Bool(false)", "Bool(true)", "Bool(true)"]);
+    }
 }
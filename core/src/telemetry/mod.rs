@@ -1,14 +1,19 @@
 //! This module helps with the initialization and management of telemetry. IE: Metrics and tracing.
 //! Logs from core are all traces, which may be exported to the console, in memory, or externally.
 
+#[cfg(feature = "telemetry")]
+mod legacy_metric_names;
+#[cfg(feature = "telemetry")]
 mod log_export;
 pub(crate) mod metrics;
+// Also built for tests, since dev-dependencies always turn the `telemetry` feature on
+#[cfg(any(test, not(feature = "telemetry")))]
+mod noop;
 #[cfg(feature = "otel")]
 mod otel;
 #[cfg(feature = "otel")]
 mod prometheus_server;
 
-#[cfg(feature = "otel")]
 pub use metrics::{
     default_buckets_for, MetricsCallBuffer, ACTIVITY_EXEC_LATENCY_HISTOGRAM_NAME,
     ACTIVITY_SCHED_TO_START_LATENCY_HISTOGRAM_NAME, WORKFLOW_E2E_LATENCY_HISTOGRAM_NAME,
@@ -18,29 +23,39 @@ pub use metrics::{
 #[cfg(feature = "otel")]
 pub use otel::{build_otlp_metric_exporter, start_prometheus_metric_exporter};
 
+#[cfg(feature = "telemetry")]
 pub use log_export::{CoreLogBuffer, CoreLogBufferedConsumer, CoreLogStreamConsumer};
+#[cfg(not(feature = "telemetry"))]
+pub use noop::{telemetry_init, TelemetryInstance};
 
-use crate::telemetry::{log_export::CoreLogConsumerLayer, metrics::PrefixedMetricsMeter};
+#[cfg(feature = "telemetry")]
+use crate::telemetry::{
+    legacy_metric_names::LegacyNamesMeter, log_export::CoreLogConsumerLayer,
+    metrics::PrefixedMetricsMeter,
+};
 use itertools::Itertools;
-use parking_lot::Mutex;
 use std::{
     cell::RefCell,
     collections::VecDeque,
-    env,
-    sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc,
-    },
-};
-use temporal_sdk_core_api::telemetry::{
-    metrics::{CoreMeter, MetricKeyValue, NewAttributes, TemporalMeter},
-    CoreLog, CoreTelemetry, Logger, TelemetryOptions,
+    sync::atomic::{AtomicBool, Ordering},
 };
+use temporal_sdk_core_api::telemetry::TelemetryOptions;
 use tracing::{Level, Subscriber};
-use tracing_subscriber::{layer::SubscriberExt, EnvFilter, Layer};
+#[cfg(feature = "telemetry")]
+use {
+    parking_lot::Mutex,
+    std::{env, sync::Arc},
+    temporal_sdk_core_api::telemetry::{
+        metrics::{CoreMeter, MetricKeyValue, NewAttributes, TemporalMeter},
+        CoreLog, CoreTelemetry, Logger,
+    },
+    tracing_subscriber::{layer::SubscriberExt, EnvFilter, Layer},
+};
 
+#[cfg(feature = "telemetry")]
 const TELEM_SERVICE_NAME: &str = "temporal-core-sdk";
 
+#[cfg(feature = "telemetry")]
 const FORWARD_LOG_BUFFER_SIZE: usize = 2048;
 
 /// Help you construct an [EnvFilter] compatible filter string which will forward all core module
@@ -52,8 +67,10 @@ pub fn construct_filter_string(core_level: Level, other_level: Level) -> String
 }
 
 /// Holds initialized tracing/metrics exporters, etc
+#[cfg(feature = "telemetry")]
 pub struct TelemetryInstance {
     metric_prefix: String,
+    metric_prefix_compat: bool,
     logs_out: Option<Mutex<CoreLogBuffer>>,
     metrics: Option<Arc<dyn CoreMeter + 'static>>,
     /// The tracing subscriber which is associated with this telemetry instance. May be `None` if
@@ -62,16 +79,19 @@ pub struct TelemetryInstance {
     attach_service_name: bool,
 }
 
+#[cfg(feature = "telemetry")]
 impl TelemetryInstance {
     fn new(
         trace_subscriber: Option<Arc<dyn Subscriber + Send + Sync>>,
         logs_out: Option<Mutex<CoreLogBuffer>>,
         metric_prefix: String,
+        metric_prefix_compat: bool,
         metrics: Option<Arc<dyn CoreMeter + 'static>>,
         attach_service_name: bool,
     ) -> Self {
         Self {
             metric_prefix,
+            metric_prefix_compat,
             logs_out,
             metrics,
             trace_subscriber,
@@ -93,17 +113,20 @@ impl TelemetryInstance {
     }
 
     /// Returns our wrapper for metric meters, including the `metric_prefix` from
-    /// [TelemetryOptions]. This should be used to initialize clients or for any other
-    /// temporal-owned metrics. User defined metrics should use [Self::get_metric_meter].
+    /// [TelemetryOptions], and emitting legacy metric names too if `metric_prefix_compat` is set.
+    /// This should be used to initialize clients or for any other temporal-owned metrics. User
+    /// defined metrics should use [Self::get_metric_meter].
     pub fn get_temporal_metric_meter(&self) -> Option<TemporalMeter> {
         self.metrics.clone().map(|m| {
             let kvs = self.default_kvs();
             let attribs = NewAttributes::new(kvs);
-            TemporalMeter::new(
-                Arc::new(PrefixedMetricsMeter::new(self.metric_prefix.clone(), m))
-                    as Arc<dyn CoreMeter>,
-                attribs,
-            )
+            let prefixed = PrefixedMetricsMeter::new(self.metric_prefix.clone(), m);
+            let meter = if self.metric_prefix_compat {
+                Arc::new(LegacyNamesMeter::new(prefixed)) as Arc<dyn CoreMeter>
+            } else {
+                Arc::new(prefixed) as Arc<dyn CoreMeter>
+            };
+            TemporalMeter::new(meter, attribs)
         })
     }
 
@@ -148,6 +171,7 @@ pub fn remove_trace_subscriber_for_current_thread() {
     SUB_GUARD.with(|sg| sg.take());
 }
 
+#[cfg(feature = "telemetry")]
 impl CoreTelemetry for TelemetryInstance {
     fn fetch_buffered_logs(&self) -> Vec<CoreLog> {
         if let Some(logs_out) = self.logs_out.as_ref() {
@@ -164,6 +188,7 @@ impl CoreTelemetry for TelemetryInstance {
 /// You should only call this once per unique [TelemetryOptions]
 ///
 /// See [TelemetryOptions] docs for more on configuration.
+#[cfg(feature = "telemetry")]
 pub fn telemetry_init(opts: TelemetryOptions) -> Result<TelemetryInstance, anyhow::Error> {
     let mut logs_out = None;
 
@@ -226,6 +251,7 @@ pub fn telemetry_init(opts: TelemetryOptions) -> Result<TelemetryInstance, anyho
         tracing_sub,
         logs_out,
         opts.metric_prefix,
+        opts.metric_prefix_compat,
         opts.metrics,
         opts.attach_service_name,
     ))
@@ -247,7 +273,7 @@ pub fn telemetry_init_global(opts: TelemetryOptions) -> Result<(), anyhow::Error
     Ok(())
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "telemetry"))]
 pub use test_initters::*;
 
 /// A trait for using [Display] on the contents of vecs, etc, which don't implement it.
@@ -276,7 +302,16 @@ where
     }
 }
 
-#[cfg(test)]
+impl<T> VecDisplayer for [T]
+where
+    T: std::fmt::Display,
+{
+    fn display(&self) -> String {
+        format!("[{}]", self.iter().format(","))
+    }
+}
+
+#[cfg(all(test, feature = "telemetry"))]
 pub mod test_initters {
     use super::*;
     use temporal_sdk_core_api::telemetry::TelemetryOptionsBuilder;
@@ -294,3 +329,29 @@ pub mod test_initters {
         .unwrap();
     }
 }
+
+#[cfg(all(test, feature = "telemetry"))]
+mod tests {
+    use super::*;
+    use crate::test_help::BufferedTelemetry;
+    use temporal_sdk_core_api::telemetry::metrics::MetricParameters;
+
+    #[test]
+    fn requested_logging_and_metrics_are_set_up() {
+        let telem = BufferedTelemetry::with_options(|o| {
+            o.logging(Logger::Forward {
+                filter: construct_filter_string(Level::INFO, Level::WARN),
+            });
+        });
+        assert!(telem.trace_subscriber().is_some());
+        let meter = telem.get_temporal_metric_meter().unwrap();
+        let attrs = meter.inner.new_attributes(meter.default_attribs.clone());
+        meter
+            .inner
+            .counter(MetricParameters::from("requests"))
+            .add(1, &attrs);
+        let updates = telem.updates();
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].0, "temporal_requests");
+    }
+}
@@ -0,0 +1,108 @@
+//! Stand-ins for telemetry initialization in builds without the `telemetry` feature. The rest of
+//! core records metrics and emits spans exactly as it otherwise would, but nothing is collected:
+//! there is never a trace subscriber, so `tracing` macros do nothing, and there is never a metric
+//! meter, so every metric handle core creates is a no-op.
+
+use std::sync::Arc;
+use temporal_sdk_core_api::telemetry::{
+    metrics::TemporalMeter, CoreLog, CoreTelemetry, TelemetryOptions,
+};
+use tracing::Subscriber;
+
+/// Telemetry in a build without the `telemetry` feature. Collects nothing.
+pub struct TelemetryInstance {
+    _priv: (),
+}
+
+impl TelemetryInstance {
+    /// Always `None`: this build has no tracing subscribers.
+    pub fn trace_subscriber(&self) -> Option<Arc<dyn Subscriber + Send + Sync>> {
+        None
+    }
+
+    /// Always `None`: this build has no metric meters, so core's metrics are no-ops.
+    pub fn get_temporal_metric_meter(&self) -> Option<TemporalMeter> {
+        None
+    }
+
+    /// Always `None`: this build has no metric meters.
+    pub fn get_metric_meter(&self) -> Option<TemporalMeter> {
+        None
+    }
+}
+
+impl CoreTelemetry for TelemetryInstance {
+    fn fetch_buffered_logs(&self) -> Vec<CoreLog> {
+        vec![]
+    }
+}
+
+/// Returns a [TelemetryInstance] which collects nothing, or an error if `opts` asks for logging or
+/// metrics, which this build can't provide. Silently dropping them would leave users wondering
+/// where their telemetry went.
+pub fn telemetry_init(opts: TelemetryOptions) -> Result<TelemetryInstance, anyhow::Error> {
+    if opts.logging.is_some() {
+        return Err(anyhow::anyhow!(
+            "Logging was requested, but core was built without the `telemetry` feature"
+        ));
+    }
+    if opts.metrics.is_some() {
+        return Err(anyhow::anyhow!(
+            "Metrics were requested, but core was built without the `telemetry` feature"
+        ));
+    }
+    Ok(TelemetryInstance { _priv: () })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::telemetry::{
+        construct_filter_string, metrics::buffered::MetricName, MetricsCallBuffer,
+    };
+    use temporal_sdk_core_api::telemetry::{
+        metrics::{CoreMeter, MetricCallBufferer},
+        Logger, TelemetryOptionsBuilder,
+    };
+    use tracing::Level;
+
+    #[test]
+    fn default_options_give_a_telemetry_instance_which_collects_nothing() {
+        let telem = telemetry_init(TelemetryOptionsBuilder::default().build().unwrap()).unwrap();
+        assert!(telem.trace_subscriber().is_none());
+        assert!(telem.get_temporal_metric_meter().is_none());
+        assert!(telem.get_metric_meter().is_none());
+        tracing::info!("Goes nowhere");
+        assert!(telem.fetch_buffered_logs().is_empty());
+    }
+
+    #[test]
+    fn requesting_logging_is_an_error() {
+        let err = telemetry_init(
+            TelemetryOptionsBuilder::default()
+                .logging(Logger::Forward {
+                    filter: construct_filter_string(Level::INFO, Level::WARN),
+                })
+                .build()
+                .unwrap(),
+        )
+        .err()
+        .unwrap();
+        assert!(err.to_string().contains("`telemetry` feature"));
+    }
+
+    #[test]
+    fn requesting_metrics_is_an_error() {
+        let call_buffer = Arc::new(MetricsCallBuffer::<MetricName>::new(100));
+        let err = telemetry_init(
+            TelemetryOptionsBuilder::default()
+                .metrics(call_buffer.clone() as Arc<dyn CoreMeter>)
+                .build()
+                .unwrap(),
+        )
+        .err()
+        .unwrap();
+        assert!(err.to_string().contains("`telemetry` feature"));
+        assert!(call_buffer.retrieve().is_empty());
+    }
+}
@@ -1,10 +1,14 @@
 pub(crate) use temporal_sdk_core_test_utils::canned_histories;
 
 use crate::{
-    pollers::{BoxedPoller, MockManualPoller, MockPoller},
+    pollers::{BoxedPoller, MockManualPoller, MockPoller, PollError},
     protosext::ValidPollWFTQResponse,
     replay::TestHistoryBuilder,
     sticky_q_name_for_worker,
+    telemetry::{
+        metrics::buffered::{buffered_updates, MetricName},
+        telemetry_init, MetricsCallBuffer, TelemetryInstance,
+    },
     worker::{
         client::{
             mocks::mock_workflow_client, MockWorkerClient, WorkerClient, WorkflowTaskCompletion,
@@ -17,7 +21,7 @@ use async_trait::async_trait;
 use bimap::BiMap;
 use futures_util::{future::BoxFuture, stream, stream::BoxStream, FutureExt, Stream, StreamExt};
 use mockall::TimesRange;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use std::{
     collections::{BTreeMap, HashMap, HashSet, VecDeque},
     fmt::Debug,
@@ -33,6 +37,11 @@ use std::{
 use temporal_sdk::interceptors::FailOnNondeterminismInterceptor;
 use temporal_sdk_core_api::{
     errors::{PollActivityError, PollWfError},
+    telemetry::{
+        metrics::{CoreMeter, MetricCallBufferer, MetricUpdateVal},
+        TelemetryOptionsBuilder,
+    },
+    worker::{LargePayloadStore, LargePayloadStoreError},
     Worker as WorkerTrait,
 };
 use temporal_sdk_core_protos::{
@@ -68,7 +77,9 @@ pub(crate) fn test_worker_cfg() -> WorkerConfigBuilder {
         .worker_build_id("test_bin_id")
         .ignore_evicts_on_shutdown(true)
         // Serial polling since it makes mocking much easier.
-        .max_concurrent_wft_polls(1_usize);
+        .max_concurrent_wft_polls(1_usize)
+        // Many tests send skeletal commands which are fine for mocked server interactions
+        .strict_command_validation(false);
     wcb
 }
 
@@ -157,6 +168,96 @@ pub(crate) fn build_fake_sdk(mock_cfg: MockPollCfg) -> temporal_sdk::Worker {
 }
 
 pub(crate) fn mock_worker(mocks: MocksHolder) -> Worker {
+    mock_worker_inner(mocks, None)
+}
+
+/// A metric buffer which tests read back the way lang would
+#[derive(Clone)]
+pub(crate) struct MetricBuffer(Arc<MetricsCallBuffer<MetricName>>);
+
+impl Default for MetricBuffer {
+    fn default() -> Self {
+        Self(Arc::new(MetricsCallBuffer::new(1000)))
+    }
+}
+
+impl MetricBuffer {
+    pub(crate) fn meter(&self) -> Arc<dyn CoreMeter> {
+        self.0.clone()
+    }
+
+    /// Every update recorded since the last call, with its metric name and attributes
+    pub(crate) fn updates(&self) -> Vec<(String, HashMap<String, String>, MetricUpdateVal)> {
+        buffered_updates(self.0.retrieve())
+    }
+}
+
+/// Telemetry whose metrics go to a [MetricBuffer]. Derefs to the [TelemetryInstance], so it can
+/// be given to [mock_worker_with_telemetry] and the like.
+pub(crate) struct BufferedTelemetry {
+    telem: TelemetryInstance,
+    buffer: MetricBuffer,
+}
+
+impl BufferedTelemetry {
+    pub(crate) fn new() -> Self {
+        Self::with_options(|_| {})
+    }
+
+    /// Lets the test set other telemetry options, such as logging, before initializing
+    pub(crate) fn with_options(opts: impl FnOnce(&mut TelemetryOptionsBuilder)) -> Self {
+        let buffer = MetricBuffer::default();
+        let mut builder = TelemetryOptionsBuilder::default();
+        opts(builder.metrics(buffer.meter()));
+        Self {
+            telem: telemetry_init(builder.build().unwrap()).unwrap(),
+            buffer,
+        }
+    }
+
+    /// See [MetricBuffer::updates]
+    pub(crate) fn updates(&self) -> Vec<(String, HashMap<String, String>, MetricUpdateVal)> {
+        self.buffer.updates()
+    }
+}
+
+impl Deref for BufferedTelemetry {
+    type Target = TelemetryInstance;
+
+    fn deref(&self) -> &Self::Target {
+        &self.telem
+    }
+}
+
+/// A [LargePayloadStore] which keeps everything in memory, keyed by digest
+#[derive(Default)]
+pub(crate) struct InMemoryStore {
+    pub(crate) entries: Mutex<HashMap<String, Vec<u8>>>,
+}
+
+#[async_trait]
+impl LargePayloadStore for InMemoryStore {
+    async fn put(&self, digest: &str, data: Vec<u8>) -> Result<(), LargePayloadStoreError> {
+        self.entries.lock().insert(digest.to_string(), data);
+        Ok(())
+    }
+
+    async fn get(&self, digest: &str) -> Result<Vec<u8>, LargePayloadStoreError> {
+        self.entries
+            .lock()
+            .get(digest)
+            .cloned()
+            .ok_or_else(|| format!("no entry for {digest}").into())
+    }
+}
+
+/// Like [mock_worker], but with the worker's metrics (and the rest of its telemetry) going to the
+/// provided instance
+pub(crate) fn mock_worker_with_telemetry(mocks: MocksHolder, telem: &TelemetryInstance) -> Worker {
+    mock_worker_inner(mocks, Some(telem))
+}
+
+fn mock_worker_inner(mocks: MocksHolder, telem: Option<&TelemetryInstance>) -> Worker {
     let sticky_q = sticky_q_name_for_worker("unit-test", &mocks.inputs.config);
     let act_poller = if mocks.inputs.config.no_remote_activities {
         None
@@ -171,7 +272,7 @@ pub(crate) fn mock_worker(mocks: MocksHolder) -> Worker {
             wft_stream: mocks.inputs.wft_stream,
             act_poller,
         },
-        None,
+        telem,
     )
 }
 
@@ -315,11 +416,11 @@ where
                 if let Some(f) = t.delay_until {
                     f.await;
                 }
-                Some(Ok(t.resp))
+                Ok(t.resp)
             }
             .boxed()
         } else {
-            async { None }.boxed()
+            async { Err(PollError::ShutDown) }.boxed()
         }
     });
     Box::new(mock_poller) as BoxedPoller<T>
@@ -930,6 +1031,7 @@ pub(crate) async fn poll_and_reply_clears_outstanding_evicts<'a>(
                 WorkflowActivationCompletion {
                     run_id: res.run_id.clone(),
                     status: Some(reply.clone()),
+                    ..Default::default()
                 }
             };
 
@@ -1,6 +1,7 @@
 mod activity_heartbeat_manager;
 mod activity_task_poller_stream;
 mod local_activities;
+mod recent_deliveries;
 
 pub(crate) use local_activities::{
     ExecutingLAId, LACompleteAction, LocalActRequest, LocalActivityExecutionResult,
@@ -12,14 +13,17 @@ use crate::{
         ClosableMeteredPermitDealer, MeteredPermitDealer, OwnedMeteredSemPermit,
         TrackedOwnedMeteredSemPermit, UsedMeteredSemPermit,
     },
-    pollers::BoxedActPoller,
-    telemetry::metrics::{activity_type, eager, workflow_type, MetricsContext},
+    pollers::{BoxedActPoller, PollError},
+    telemetry::metrics::{
+        activity_failure_type, activity_type, eager, workflow_type, MetricsContext,
+    },
     worker::{
         activities::{
             activity_heartbeat_manager::ActivityHeartbeatError,
             activity_task_poller_stream::new_activity_task_poller,
         },
         client::WorkerClient,
+        clock_skew::ClockSkewEstimator,
     },
     PollActivityError, TaskToken,
 };
@@ -30,6 +34,7 @@ use futures_util::{
     stream::{BoxStream, PollNext},
     Stream, StreamExt,
 };
+use recent_deliveries::{Delivery, RecentDeliveries};
 use std::{
     convert::TryInto,
     future,
@@ -39,7 +44,9 @@ use std::{
     },
     time::{Duration, Instant, SystemTime},
 };
-use temporal_sdk_core_api::worker::ActivitySlotKind;
+use temporal_sdk_core_api::{
+    errors::CompleteActivityError, worker::ActivitySlotKind, ActivityCompletionOutcome,
+};
 use temporal_sdk_core_protos::{
     coresdk::{
         activity_result::{self as ar, activity_execution_result as aer},
@@ -50,6 +57,7 @@ use temporal_sdk_core_protos::{
         failure::v1::{failure::FailureInfo, ApplicationFailureInfo, CanceledFailureInfo, Failure},
         workflowservice::v1::PollActivityTaskQueueResponse,
     },
+    ActivityId, RunId, WorkflowId,
 };
 use tokio::{
     join,
@@ -89,9 +97,11 @@ struct InFlightActInfo {
     activity_type: String,
     workflow_type: String,
     /// Only kept for logging reasons
-    workflow_id: String,
+    workflow_id: WorkflowId,
     /// Only kept for logging reasons
-    workflow_run_id: String,
+    workflow_run_id: RunId,
+    /// Only kept for logging reasons
+    activity_id: ActivityId,
     start_time: Instant,
     scheduled_time: Option<SystemTime>,
 }
@@ -112,6 +122,12 @@ struct RemoteInFlightActInfo {
     local_timeouts_task: Option<JoinHandle<()>>,
     /// Used to reset the local heartbeat timeout every time we record a heartbeat
     timeout_resetter: Option<Arc<Notify>>,
+    /// Handle to the task which warns if the activity runs past the slow activity threshold
+    slow_activity_watchdog: Option<JoinHandle<()>>,
+    /// Number of heartbeats lang has recorded for this activity
+    heartbeat_count: u64,
+    /// Which attempt of the activity this is, starting at 1
+    attempt: i32,
     /// The permit from the max concurrent semaphore
     _permit: UsedMeteredSemPermit<ActivitySlotKind>,
 }
@@ -125,8 +141,9 @@ impl RemoteInFlightActInfo {
             base: InFlightActInfo {
                 activity_type: poll_resp.activity_type.clone().unwrap_or_default().name,
                 workflow_type: poll_resp.workflow_type.clone().unwrap_or_default().name,
-                workflow_id: wec.workflow_id,
-                workflow_run_id: wec.run_id,
+                workflow_id: wec.workflow_id.into(),
+                workflow_run_id: wec.run_id.into(),
+                activity_id: poll_resp.activity_id.as_str().into(),
                 start_time: Instant::now(),
                 scheduled_time: poll_resp.scheduled_time.and_then(|i| i.try_into().ok()),
             },
@@ -135,6 +152,9 @@ impl RemoteInFlightActInfo {
             known_not_found: false,
             local_timeouts_task: None,
             timeout_resetter: None,
+            slow_activity_watchdog: None,
+            heartbeat_count: 0,
+            attempt: poll_resp.attempt,
             _permit: permit,
         }
     }
@@ -150,6 +170,8 @@ pub(crate) struct WorkerActivityTasks {
     activity_task_stream: Mutex<BoxStream<'static, Result<ActivityTask, PollActivityError>>>,
     /// Activities that have been issued to lang but not yet completed
     outstanding_activity_tasks: OutstandingActMap,
+    /// Activities recently issued to lang, so that repeated deliveries of them can be dropped
+    recent_deliveries: Arc<RecentDeliveries>,
     /// Ensures we don't exceed this worker's maximum concurrent activity limit for activities. This
     /// semaphore is used to limit eager activities but shares the same underlying
     /// [MeteredPermitDealer] that is used to limit the concurrency for non-eager activities.
@@ -166,6 +188,9 @@ pub(crate) struct WorkerActivityTasks {
 
     max_heartbeat_throttle_interval: Duration,
     default_heartbeat_throttle_interval: Duration,
+    slow_activity_log_threshold: Option<Duration>,
+    /// Used to correct server timestamps for local clock skew
+    clock_skew: Arc<ClockSkewEstimator>,
 
     /// Wakes every time an activity is removed from the outstanding map
     complete_notify: Arc<Notify>,
@@ -188,13 +213,17 @@ impl WorkerActivityTasks {
         metrics: MetricsContext,
         max_heartbeat_throttle_interval: Duration,
         default_heartbeat_throttle_interval: Duration,
+        heartbeat_failures_before_cancel: usize,
         graceful_shutdown: Option<Duration>,
         local_timeout_buffer: Duration,
+        slow_activity_log_threshold: Option<Duration>,
+        clock_skew: Arc<ClockSkewEstimator>,
     ) -> Self {
         let shutdown_initiated_token = CancellationToken::new();
         let outstanding_activity_tasks = Arc::new(DashMap::new());
+        let recent_deliveries = Arc::new(RecentDeliveries::default());
         let server_poller_stream =
-            new_activity_task_poller(poller, metrics.clone(), shutdown_initiated_token.clone());
+            new_activity_task_poller(poller, shutdown_initiated_token.clone());
         let (eager_activities_tx, eager_activities_rx) = unbounded_channel();
         let eager_activities_semaphore = ClosableMeteredPermitDealer::new_arc(Arc::new(semaphore));
 
@@ -206,7 +235,12 @@ impl WorkerActivityTasks {
             start_tasks_stream_complete.clone(),
         );
         let (cancels_tx, cancels_rx) = unbounded_channel();
-        let heartbeat_manager = ActivityHeartbeatManager::new(client, cancels_tx.clone());
+        let heartbeat_manager = ActivityHeartbeatManager::new(
+            client,
+            cancels_tx.clone(),
+            heartbeat_failures_before_cancel,
+            metrics.clone(),
+        );
         let complete_notify = Arc::new(Notify::new());
         let source_stream = stream::select_with_strategy(
             UnboundedReceiverStream::new(cancels_rx).map(ActivityTaskSource::from),
@@ -217,11 +251,14 @@ impl WorkerActivityTasks {
         let activity_task_stream = ActivityTaskStream {
             source_stream,
             outstanding_tasks: outstanding_activity_tasks.clone(),
+            recent_deliveries: recent_deliveries.clone(),
             start_tasks_stream_complete,
             complete_notify: complete_notify.clone(),
             grace_period: graceful_shutdown,
             cancels_tx,
             local_timeout_buffer,
+            slow_activity_log_threshold,
+            clock_skew: clock_skew.clone(),
             shutdown_initiated_token: shutdown_initiated_token.clone(),
             metrics: metrics.clone(),
         }
@@ -237,8 +274,11 @@ impl WorkerActivityTasks {
             metrics,
             max_heartbeat_throttle_interval,
             default_heartbeat_throttle_interval,
+            slow_activity_log_threshold,
+            clock_skew,
             poll_returned_shutdown_token: CancellationToken::new(),
             outstanding_activity_tasks,
+            recent_deliveries,
             completers_lock: Default::default(),
         }
     }
@@ -246,7 +286,7 @@ impl WorkerActivityTasks {
     /// Merges the server poll and eager [ActivityTask] sources
     fn merge_start_task_sources(
         non_poll_tasks_rx: UnboundedReceiver<TrackedPermittedTqResp>,
-        poller_stream: impl Stream<Item = Result<PermittedTqResp, tonic::Status>>,
+        poller_stream: impl Stream<Item = Result<PermittedTqResp, PollError>>,
         eager_activities_semaphore: Arc<ClosableMeteredPermitDealer<ActivitySlotKind>>,
         on_complete_token: CancellationToken,
     ) -> impl Stream<Item = Result<(PermittedTqResp, bool), PollActivityError>> {
@@ -317,103 +357,143 @@ impl WorkerActivityTasks {
         task_token: TaskToken,
         status: aer::Status,
         client: &dyn WorkerClient,
-    ) {
+    ) -> Result<ActivityCompletionOutcome, CompleteActivityError> {
         if let Some((_, act_info)) = self.outstanding_activity_tasks.remove(&task_token) {
+            self.recent_deliveries
+                .mark_completed(&task_token, act_info.issued_cancel_to_lang.is_some());
             let act_metrics = self.metrics.with_new_attrs([
                 activity_type(act_info.base.activity_type),
                 workflow_type(act_info.base.workflow_type),
             ]);
-            Span::current().record("workflow_id", act_info.base.workflow_id);
-            Span::current().record("run_id", act_info.base.workflow_run_id);
-            act_metrics.act_execution_latency(act_info.base.start_time.elapsed());
+            Span::current().record("workflow_id", act_info.base.workflow_id.as_str());
+            Span::current().record("run_id", act_info.base.workflow_run_id.as_str());
+            let elapsed = act_info.base.start_time.elapsed();
+            act_metrics.act_execution_latency(elapsed);
+            act_metrics.act_heartbeats(act_info.heartbeat_count);
+            if self
+                .slow_activity_log_threshold
+                .is_some_and(|threshold| elapsed > threshold)
+            {
+                warn!(
+                    workflow_id = %act_info.base.workflow_id,
+                    activity_id = %act_info.base.activity_id,
+                    ?elapsed,
+                    "Slow activity completed"
+                );
+            }
             let known_not_found = act_info.known_not_found;
 
             if let Some(jh) = act_info.local_timeouts_task {
                 jh.abort()
             };
+            if let Some(jh) = act_info.slow_activity_watchdog {
+                jh.abort()
+            };
             self.heartbeat_manager.evict(task_token.clone()).await;
 
             // No need to report activities which we already know the server doesn't care about
-            if !known_not_found {
-                let _flushing_guard = self.completers_lock.read().await;
-                let maybe_net_err = match status {
-                    aer::Status::WillCompleteAsync(_) => None,
-                    aer::Status::Completed(ar::Success { result }) => {
-                        if let Some(sched_time) = act_info
-                            .base
-                            .scheduled_time
-                            .and_then(|st| st.elapsed().ok())
-                        {
-                            act_metrics.act_execution_succeeded(sched_time);
-                        }
+            if known_not_found {
+                return Ok(ActivityCompletionOutcome::ActivityGone);
+            }
+            let _flushing_guard = self.completers_lock.read().await;
+            let maybe_net_err = match status {
+                aer::Status::WillCompleteAsync(_) => None,
+                aer::Status::Completed(ar::Success { result }) => {
+                    if let Some(sched_time) = act_info
+                        .base
+                        .scheduled_time
+                        .and_then(|st| self.clock_skew.elapsed_since(st))
+                    {
+                        act_metrics.act_execution_succeeded(sched_time);
+                    }
+                    let sent = Instant::now();
+                    let res = client
+                        .complete_activity_task(task_token.clone(), result.map(Into::into))
+                        .await;
+                    if res.is_ok() {
+                        self.clock_skew.observe_round_trip(sent.elapsed());
+                    }
+                    res.err()
+                }
+                aer::Status::Failed(ar::Failure { failure }) => {
+                    act_metrics
+                        .with_new_attrs([activity_failure_type(failure.as_ref())])
+                        .act_execution_failed();
+                    client
+                        .fail_activity_task(task_token.clone(), failure.map(Into::into))
+                        .await
+                        .err()
+                }
+                aer::Status::Cancelled(ar::Cancellation { failure }) => {
+                    if matches!(
+                        act_info.issued_cancel_to_lang,
+                        Some(ActivityCancelReason::WorkerShutdown),
+                    ) {
+                        // We report cancels for graceful shutdown as failures, so we
+                        // don't wait for the whole timeout to elapse, which is what would
+                        // happen anyway.
                         client
-                            .complete_activity_task(task_token.clone(), result.map(Into::into))
+                            .fail_activity_task(task_token.clone(), Some(worker_shutdown_failure()))
                             .await
                             .err()
-                    }
-                    aer::Status::Failed(ar::Failure { failure }) => {
-                        act_metrics.act_execution_failed();
+                    } else {
+                        let details = if let Some(Failure {
+                            failure_info:
+                                Some(FailureInfo::CanceledFailureInfo(CanceledFailureInfo { details })),
+                            ..
+                        }) = failure
+                        {
+                            details
+                        } else {
+                            warn!(task_token=?task_token,
+                                "Expected activity cancelled status with CanceledFailureInfo");
+                            None
+                        };
                         client
-                            .fail_activity_task(task_token.clone(), failure.map(Into::into))
+                            .cancel_activity_task(task_token.clone(), details.map(Into::into))
                             .await
                             .err()
                     }
-                    aer::Status::Cancelled(ar::Cancellation { failure }) => {
-                        if matches!(
-                            act_info.issued_cancel_to_lang,
-                            Some(ActivityCancelReason::WorkerShutdown),
-                        ) {
-                            // We report cancels for graceful shutdown as failures, so we
-                            // don't wait for the whole timeout to elapse, which is what would
-                            // happen anyway.
-                            client
-                                .fail_activity_task(
-                                    task_token.clone(),
-                                    Some(worker_shutdown_failure()),
-                                )
-                                .await
-                                .err()
-                        } else {
-                            let details = if let Some(Failure {
-                                failure_info:
-                                    Some(FailureInfo::CanceledFailureInfo(CanceledFailureInfo {
-                                        details,
-                                    })),
-                                ..
-                            }) = failure
-                            {
-                                details
-                            } else {
-                                warn!(task_token=?task_token,
-                                    "Expected activity cancelled status with CanceledFailureInfo");
-                                None
-                            };
-                            client
-                                .cancel_activity_task(task_token.clone(), details.map(Into::into))
-                                .await
-                                .err()
-                        }
-                    }
-                };
+                }
+            };
 
-                self.complete_notify.notify_waiters();
+            self.complete_notify.notify_waiters();
 
-                if let Some(e) = maybe_net_err {
-                    if e.code() == tonic::Code::NotFound {
-                        warn!(task_token=?task_token, details=?e, "Activity not found on \
-                        completion. This may happen if the activity has already been cancelled but \
-                        completed anyway.");
+            if let Some(e) = maybe_net_err {
+                if e.code() == tonic::Code::NotFound {
+                    // Server forgetting an activity we cancelled or which is on a retry attempt is
+                    // an expected race, since the attempt may have timed out or the workflow may
+                    // have closed in the meantime. On the first attempt it's more surprising, but
+                    // there's still nothing lang could do about it.
+                    if act_info.issued_cancel_to_lang.is_some() || act_info.attempt > 1 {
+                        info!(task_token=%task_token, details=?e, "Activity not found on \
+                              completion. It has most likely already been cancelled or timed \
+                              out, and the result has been dropped.");
                     } else {
-                        warn!(error=?e, "Network error while completing activity");
-                    };
+                        warn!(task_token=%task_token, details=?e, "Activity not found on \
+                              completion of its first attempt, which was neither cancelled nor \
+                              timed out. The result has been dropped.");
+                    }
+                    act_metrics.act_completion_gone();
+                    return Ok(ActivityCompletionOutcome::ActivityGone);
+                } else {
+                    warn!(error=?e, "Network error while completing activity");
                 };
             };
+        } else if self.recent_deliveries.completed_after_cancel(&task_token) {
+            // Lang may complete a cancelled activity more than once, ex: if it reports the cancel
+            // and then the activity function returns anyway. Core already cleaned it up.
+            debug!(task_token=%task_token, "Dropping late completion of a cancelled activity");
+            return Ok(ActivityCompletionOutcome::ActivityGone);
         } else {
-            warn!(
-                "Attempted to complete activity task {} but we were not tracking it",
-                &task_token
-            );
+            return Err(CompleteActivityError::InvalidCompletion {
+                reason: "No outstanding activity task has this token. It may already have been \
+                         completed."
+                    .to_string(),
+                task_token,
+            });
         }
+        Ok(ActivityCompletionOutcome::Accepted)
     }
 
     /// Attempt to record an activity heartbeat
@@ -422,9 +502,9 @@ impl WorkerActivityTasks {
         details: ActivityHeartbeat,
     ) -> Result<(), ActivityHeartbeatError> {
         // TODO: Propagate these back as cancels. Silent fails is too nonobvious
-        let at_info = self
+        let mut at_info = self
             .outstanding_activity_tasks
-            .get(&TaskToken(details.task_token.clone()))
+            .get_mut(&TaskToken(details.task_token.clone()))
             .ok_or(ActivityHeartbeatError::UnknownActivity)?;
         let heartbeat_timeout: Duration = at_info
             .heartbeat_timeout
@@ -445,6 +525,7 @@ impl WorkerActivityTasks {
         };
         let throttle_interval =
             std::cmp::min(throttle_interval, self.max_heartbeat_throttle_interval);
+        at_info.heartbeat_count += 1;
         self.heartbeat_manager
             .record(details, throttle_interval, at_info.timeout_resetter.clone())
     }
@@ -457,6 +538,13 @@ impl WorkerActivityTasks {
         }
     }
 
+    /// The type of an activity which has been issued to lang but not yet completed
+    pub(crate) fn activity_type(&self, task_token: &TaskToken) -> Option<String> {
+        self.outstanding_activity_tasks
+            .get(task_token)
+            .map(|i| i.base.activity_type.clone())
+    }
+
     #[cfg(test)]
     pub(crate) fn remaining_activity_capacity(&self) -> Option<usize> {
         self.eager_activities_semaphore.unused_permits()
@@ -466,12 +554,16 @@ impl WorkerActivityTasks {
 struct ActivityTaskStream<SrcStrm> {
     source_stream: SrcStrm,
     outstanding_tasks: OutstandingActMap,
+    recent_deliveries: Arc<RecentDeliveries>,
     start_tasks_stream_complete: CancellationToken,
     complete_notify: Arc<Notify>,
     grace_period: Option<Duration>,
     cancels_tx: UnboundedSender<PendingActivityCancel>,
     /// The extra time we'll wait for local timeouts before firing them, to avoid racing with server
     local_timeout_buffer: Duration,
+    slow_activity_log_threshold: Option<Duration>,
+    /// Used to correct server timestamps for local clock skew
+    clock_skew: Arc<ClockSkewEstimator>,
     /// Token which is cancelled once shutdown is beginning
     shutdown_initiated_token: CancellationToken,
     metrics: MetricsContext,
@@ -524,6 +616,17 @@ where
                             None
                         }
                     }
+                    ActivityTaskSource::PendingStart(Ok((task, _)))
+                        if is_redelivery(
+                            &task.resp,
+                            &self.recent_deliveries,
+                            &self.outstanding_tasks,
+                            &self.metrics,
+                        ) =>
+                    {
+                        // Dropping the task releases its slot
+                        None
+                    }
                     ActivityTaskSource::PendingStart(res) => {
                         Some(res.map(|(task, is_eager)| {
                             let mut activity_type_name = "";
@@ -546,6 +649,16 @@ where
                             if let Some(dur) = task.resp.sched_to_start() {
                                 self.metrics.act_sched_to_start_latency(dur);
                             };
+                            self.clock_skew
+                                .observe_task_start(task.resp.started_time.as_ref());
+                            // Time the activity has already been running, according to the
+                            // server, which local deadlines are shortened by
+                            let since_start = task
+                                .resp
+                                .started_time
+                                .and_then(|st| SystemTime::try_from(st).ok())
+                                .and_then(|st| self.clock_skew.elapsed_since(st))
+                                .unwrap_or_default();
 
                             let tt: TaskToken = task.resp.task_token.clone().into();
                             let outstanding_entry = self.outstanding_tasks.entry(tt.clone());
@@ -569,9 +682,8 @@ where
                             } else {
                                 // Fire off task to keep track of local timeouts. We do this so that
                                 // activities can still get cleaned up even if the user isn't
-                                // heartbeating. Schedule to closed is not tracked due to the
-                                // possibility of clock skew messing things up, and it's relative
-                                // unlikeliness compared to the other timeouts.
+                                // heartbeating. Schedule to closed is not tracked due to its
+                                // relative unlikeliness compared to the other timeouts.
                                 let local_timeout_buffer = self.local_timeout_buffer;
                                 static HEARTBEAT_TYPE: &str = "heartbeat";
                                 let timeout_at = [
@@ -586,6 +698,7 @@ where
                                 .min_by(|(_, d1), (_, d2)| d1.cmp(d2));
                                 if let Some((timeout_type, timeout_at)) = timeout_at {
                                     let sleep_time = timeout_at + local_timeout_buffer;
+                                    let first_sleep_time = sleep_time.saturating_sub(since_start);
                                     let cancel_tx = cancels_tx.clone();
                                     let resetter = if timeout_type == HEARTBEAT_TYPE {
                                         Some(Arc::new(Notify::new()))
@@ -596,14 +709,16 @@ where
                                     outstanding_info.local_timeouts_task =
                                         Some(tokio::task::spawn(async move {
                                             if let Some(rs) = resetter_clone {
+                                                // Heartbeats restart the timeout in full
+                                                let mut sleep_for = first_sleep_time;
                                                 loop {
                                                     tokio::select! {
-                                                        _ = rs.notified() => continue,
-                                                        _ = tokio::time::sleep(sleep_time) => break,
+                                                        _ = rs.notified() => sleep_for = sleep_time,
+                                                        _ = tokio::time::sleep(sleep_for) => break,
                                                     }
                                                 }
                                             } else {
-                                                tokio::time::sleep(sleep_time).await;
+                                                tokio::time::sleep(first_sleep_time).await;
                                             }
                                             debug!(
                                                 task_token=%tt,
@@ -619,6 +734,22 @@ where
                                     outstanding_info.timeout_resetter = resetter;
                                 }
                             }
+                            if let Some(threshold) = self.slow_activity_log_threshold {
+                                let workflow_id = outstanding_info.base.workflow_id.clone();
+                                let activity_id = outstanding_info.base.activity_id.clone();
+                                outstanding_info.slow_activity_watchdog =
+                                    Some(tokio::task::spawn(async move {
+                                        tokio::time::sleep(threshold.saturating_sub(since_start))
+                                            .await;
+                                        warn!(
+                                            %workflow_id,
+                                            %activity_id,
+                                            elapsed = ?threshold,
+                                            "Activity is still running past the slow activity \
+                                             threshold"
+                                        );
+                                    }));
+                            }
 
                             ActivityTask::start_from_poll_resp(task.resp)
                         }))
@@ -696,6 +827,34 @@ pub(crate) struct TrackedPermittedTqResp {
     pub(crate) resp: PollActivityTaskQueueResponse,
 }
 
+/// Returns true if this task was already issued to lang, in which case it must be dropped rather
+/// than executed again
+fn is_redelivery(
+    resp: &PollActivityTaskQueueResponse,
+    recent_deliveries: &RecentDeliveries,
+    outstanding_tasks: &OutstandingActMap,
+    metrics: &MetricsContext,
+) -> bool {
+    let tt = TaskToken(resp.task_token.clone());
+    let start_to_close = resp
+        .start_to_close_timeout
+        .and_then(|d| Duration::try_from(d).ok());
+    match recent_deliveries.observe(&tt, start_to_close) {
+        // An activity which outlives the window is still caught by the outstanding map
+        Delivery::First if !outstanding_tasks.contains_key(&tt) => false,
+        Delivery::RepeatedAfterCompletion => {
+            debug!(task_token=%tt, "Dropping redelivery of already completed activity task");
+            true
+        }
+        _ => {
+            warn!(task_token=%tt, activity_id=%resp.activity_id,
+                  "Dropping redelivery of activity task which is still running");
+            metrics.act_task_duplicate_dropped();
+            true
+        }
+    }
+}
+
 fn worker_shutdown_failure() -> Failure {
     Failure {
         message: "Worker is shutting down and this activity did not complete in time".to_string(),
@@ -717,10 +876,20 @@ fn worker_shutdown_failure() -> Failure {
 mod tests {
     use super::*;
     use crate::{
-        abstractions::tests::fixed_size_permit_dealer, pollers::new_activity_task_buffer,
-        prost_dur, worker::client::mocks::mock_workflow_client,
+        abstractions::tests::fixed_size_permit_dealer,
+        pollers::{new_activity_task_buffer, ActivityRateLimits, PollOptions},
+        prost_dur,
+        telemetry::construct_filter_string,
+        test_help::BufferedTelemetry,
+        worker::client::mocks::{mock_manual_workflow_client, mock_workflow_client},
+    };
+    use futures_util::FutureExt;
+    use temporal_sdk_core_api::telemetry::{metrics::MetricUpdateVal, CoreTelemetry, Logger};
+    use temporal_sdk_core_protos::{
+        coresdk::activity_result::ActivityExecutionResult,
+        temporal::api::common::v1::{ActivityType, WorkflowExecution, WorkflowType},
     };
-    use temporal_sdk_core_protos::coresdk::activity_result::ActivityExecutionResult;
+    use tracing::Level;
 
     #[tokio::test]
     async fn per_worker_ratelimit() {
@@ -754,13 +923,15 @@ mod tests {
         let shutdown_token = CancellationToken::new();
         let ap = new_activity_task_buffer(
             mock_client.clone(),
-            "tq".to_string(),
+            PollOptions::normal("tq".to_string()),
             5, // Lots of concurrent pollers, to ensure we don't poll to much when that's the case
             sem.clone(),
-            None,
+            ActivityRateLimits::new(None, Some(2.0)),
             shutdown_token.clone(),
             None::<fn(usize)>,
-            Some(2.0),
+            None,
+            MetricsContext::no_op(),
+            Default::default(),
         );
         let atm = WorkerActivityTasks::new(
             sem.clone(),
@@ -769,8 +940,11 @@ mod tests {
             MetricsContext::no_op(),
             Duration::from_secs(1),
             Duration::from_secs(1),
+            5,
             None,
             Duration::from_secs(5),
+            None,
+            Default::default(),
         );
         let start = Instant::now();
         let t1 = atm.poll().await.unwrap();
@@ -786,13 +960,15 @@ mod tests {
             ActivityExecutionResult::ok(vec![1].into()).status.unwrap(),
             mock_client.as_ref(),
         )
-        .await;
+        .await
+        .unwrap();
         atm.complete(
             TaskToken(t2.task_token),
             ActivityExecutionResult::ok(vec![1].into()).status.unwrap(),
             mock_client.as_ref(),
         )
-        .await;
+        .await
+        .unwrap();
         atm.initiate_shutdown();
         assert_matches!(atm.poll().await.unwrap_err(), PollActivityError::ShutDown);
         atm.shutdown().await;
@@ -843,13 +1019,15 @@ mod tests {
         let shutdown_token = CancellationToken::new();
         let ap = new_activity_task_buffer(
             mock_client.clone(),
-            "tq".to_string(),
+            PollOptions::normal("tq".to_string()),
             1,
             sem.clone(),
-            None,
+            ActivityRateLimits::new(None, None),
             shutdown_token.clone(),
             None::<fn(usize)>,
             None,
+            MetricsContext::no_op(),
+            Default::default(),
         );
         let atm = WorkerActivityTasks::new(
             sem.clone(),
@@ -858,8 +1036,11 @@ mod tests {
             MetricsContext::no_op(),
             Duration::from_secs(1),
             Duration::from_secs(1),
+            5,
             None,
             Duration::from_millis(100), // Short buffer for unit test
+            None,
+            Default::default(),
         );
 
         for _ in 1..=3 {
@@ -878,7 +1059,8 @@ mod tests {
                     .unwrap(),
                 mock_client.as_ref(),
             )
-            .await;
+            .await
+            .unwrap();
         }
 
         atm.initiate_shutdown();
@@ -914,13 +1096,15 @@ mod tests {
         let shutdown_token = CancellationToken::new();
         let ap = new_activity_task_buffer(
             mock_client.clone(),
-            "tq".to_string(),
+            PollOptions::normal("tq".to_string()),
             1,
             sem.clone(),
-            None,
+            ActivityRateLimits::new(None, None),
             shutdown_token.clone(),
             None::<fn(usize)>,
             None,
+            MetricsContext::no_op(),
+            Default::default(),
         );
         let atm = WorkerActivityTasks::new(
             sem.clone(),
@@ -929,8 +1113,11 @@ mod tests {
             MetricsContext::no_op(),
             Duration::from_secs(1),
             Duration::from_secs(1),
+            5,
             None,
             Duration::from_millis(0), // No buffer in this test
+            None,
+            Default::default(),
         );
 
         let t = atm.poll().await.unwrap();
@@ -964,10 +1151,301 @@ mod tests {
                 .unwrap(),
             mock_client.as_ref(),
         )
-        .await;
+        .await
+        .unwrap();
+
+        atm.initiate_shutdown();
+        assert_matches!(atm.poll().await.unwrap_err(), PollActivityError::ShutDown);
+        atm.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn local_timeout_corrected_for_clock_skew() {
+        // The local clock runs a minute ahead of the server's, and the server started the activity
+        // a second before we received it.
+        let skew = Duration::from_secs(60);
+        let clock_skew = Arc::new(ClockSkewEstimator::default());
+        let now = SystemTime::now();
+        clock_skew.observe_at(now, now - skew);
+        let mut mock_client = mock_workflow_client();
+        mock_client
+            .expect_poll_activity_task()
+            .times(1)
+            .returning(move |_, _| {
+                Ok(PollActivityTaskQueueResponse {
+                    task_token: vec![1],
+                    activity_id: "act1".to_string(),
+                    started_time: Some((SystemTime::now() - skew - Duration::from_secs(1)).into()),
+                    start_to_close_timeout: Some(prost_dur!(from_secs(2))),
+                    ..Default::default()
+                })
+            });
+        mock_client
+            .expect_poll_activity_task()
+            .returning(|_, _| Ok(Default::default()));
+        let mock_client = Arc::new(mock_client);
+        let sem = fixed_size_permit_dealer(1);
+        let ap = new_activity_task_buffer(
+            mock_client.clone(),
+            PollOptions::normal("tq".to_string()),
+            1,
+            sem.clone(),
+            ActivityRateLimits::new(None, None),
+            CancellationToken::new(),
+            None::<fn(usize)>,
+            None,
+            MetricsContext::no_op(),
+            Default::default(),
+        );
+        let atm = WorkerActivityTasks::new(
+            sem.clone(),
+            Box::new(ap),
+            mock_client.clone(),
+            MetricsContext::no_op(),
+            Duration::from_secs(1),
+            Duration::from_secs(1),
+            5,
+            None,
+            Duration::from_millis(0),
+            None,
+            clock_skew.clone(),
+        );
+
+        let t = atm.poll().await.unwrap();
+        let start = Instant::now();
+        let should_timeout = atm.poll().await.unwrap();
+        assert!(should_timeout.is_timeout());
+        // Uncorrected, the skew would make the activity look long overdue. Ignoring the time it
+        // had already been running would make us wait the full two seconds.
+        let elapsed = start.elapsed();
+        assert!(elapsed > Duration::from_millis(500), "{elapsed:?}");
+        assert!(elapsed < Duration::from_millis(1800), "{elapsed:?}");
+        let local_ahead = clock_skew.estimate().unwrap().local_ahead_secs;
+        assert!((60.0..61.0).contains(&local_ahead), "{local_ahead}");
+
+        atm.complete(
+            TaskToken(t.task_token),
+            ActivityExecutionResult::fail("unimportant".into())
+                .status
+                .unwrap(),
+            mock_client.as_ref(),
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn activity_type_metrics_and_slow_activity_logging() {
+        let telem = BufferedTelemetry::with_options(|o| {
+            o.logging(Logger::Forward {
+                filter: construct_filter_string(Level::WARN, Level::WARN),
+            });
+        });
+        let _g = tracing::subscriber::set_default(telem.trace_subscriber().unwrap());
+        let metrics = MetricsContext::top_level("ns".to_string(), "tq".to_string(), &telem);
+
+        let mut mock_client = mock_manual_workflow_client();
+        let mut polled = false;
+        mock_client
+            .expect_poll_activity_task()
+            .returning(move |_, _| {
+                if std::mem::replace(&mut polled, true) {
+                    return future::pending().boxed();
+                }
+                async {
+                    Ok(PollActivityTaskQueueResponse {
+                        task_token: vec![1],
+                        activity_id: "act1".to_string(),
+                        activity_type: Some(ActivityType {
+                            name: "slowpoke".to_string(),
+                        }),
+                        workflow_type: Some(WorkflowType {
+                            name: "wftype".to_string(),
+                        }),
+                        workflow_execution: Some(WorkflowExecution {
+                            workflow_id: "wfid".to_string(),
+                            run_id: "runid".to_string(),
+                        }),
+                        ..Default::default()
+                    })
+                }
+                .boxed()
+            });
+        mock_client
+            .expect_record_activity_heartbeat()
+            .returning(|_, _| async { Ok(Default::default()) }.boxed());
+        mock_client
+            .expect_fail_activity_task()
+            .times(1)
+            .returning(|_, _| async { Ok(Default::default()) }.boxed());
+        let mock_client = Arc::new(mock_client);
+        let sem = fixed_size_permit_dealer(1);
+        let shutdown_token = CancellationToken::new();
+        let ap = new_activity_task_buffer(
+            mock_client.clone(),
+            PollOptions::normal("tq".to_string()),
+            1,
+            sem.clone(),
+            ActivityRateLimits::new(None, None),
+            shutdown_token.clone(),
+            None::<fn(usize)>,
+            None,
+            MetricsContext::no_op(),
+            Default::default(),
+        );
+        let atm = WorkerActivityTasks::new(
+            sem.clone(),
+            Box::new(ap),
+            mock_client.clone(),
+            metrics,
+            Duration::from_secs(1),
+            Duration::from_secs(1),
+            5,
+            None,
+            Duration::from_secs(5),
+            Some(Duration::from_secs(10)),
+            Default::default(),
+        );
+
+        let t = atm.poll().await.unwrap();
+        for _ in 1..=2 {
+            atm.record_heartbeat(ActivityHeartbeat {
+                task_token: t.task_token.clone(),
+                details: vec![],
+            })
+            .unwrap();
+        }
+        tokio::time::sleep(Duration::from_secs(11)).await;
+        let logs = telem.fetch_buffered_logs();
+        let slow_log = logs
+            .iter()
+            .find(|l| l.message.contains("slow activity threshold"))
+            .expect("Slow activity must be logged while still running");
+        assert_eq!(slow_log.fields.get("workflow_id"), Some(&"wfid".into()));
+        assert_eq!(slow_log.fields.get("activity_id"), Some(&"act1".into()));
 
+        atm.complete(
+            TaskToken(t.task_token),
+            ActivityExecutionResult::fail("boom".into()).status.unwrap(),
+            mock_client.as_ref(),
+        )
+        .await
+        .unwrap();
+        shutdown_token.cancel();
         atm.initiate_shutdown();
         assert_matches!(atm.poll().await.unwrap_err(), PollActivityError::ShutDown);
         atm.shutdown().await;
+
+        let updates = telem.updates();
+        let update_for = |name: &str| {
+            updates
+                .iter()
+                .find(|(n, _, _)| n.ends_with(name))
+                .unwrap_or_else(|| panic!("No update recorded for {name}"))
+        };
+        let (_, attrs, update) = update_for("activity_heartbeat");
+        assert_eq!(attrs.get("activity_type").unwrap(), "slowpoke");
+        assert_matches!(update, MetricUpdateVal::Delta(2));
+        let (_, attrs, update) = update_for("activity_execution_failed");
+        assert_eq!(attrs.get("activity_type").unwrap(), "slowpoke");
+        assert_eq!(attrs.get("failure_reason").unwrap(), "ApplicationFailure");
+        assert_matches!(update, MetricUpdateVal::Delta(1));
+        let (_, attrs, _) = update_for("activity_execution_latency");
+        assert_eq!(attrs.get("activity_type").unwrap(), "slowpoke");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn redelivered_tasks_are_dropped() {
+        let telem = BufferedTelemetry::new();
+        let metrics = MetricsContext::top_level("ns".to_string(), "tq".to_string(), &telem);
+
+        // The same task is delivered twice while it runs, then once more after it completes
+        let completed = Arc::new(Notify::new());
+        let completed_clone = completed.clone();
+        let mut polls = 0;
+        let mut mock_client = mock_manual_workflow_client();
+        mock_client
+            .expect_poll_activity_task()
+            .returning(move |_, _| {
+                polls += 1;
+                let completed = completed_clone.clone();
+                let resp = PollActivityTaskQueueResponse {
+                    task_token: vec![1],
+                    activity_id: "act1".to_string(),
+                    start_to_close_timeout: Some(prost_dur!(from_secs(60))),
+                    ..Default::default()
+                };
+                match polls {
+                    1 | 2 => async move { Ok(resp) }.boxed(),
+                    3 => async move {
+                        completed.notified().await;
+                        Ok(resp)
+                    }
+                    .boxed(),
+                    _ => future::pending().boxed(),
+                }
+            });
+        mock_client
+            .expect_fail_activity_task()
+            .times(1)
+            .returning(|_, _| async { Ok(Default::default()) }.boxed());
+        let mock_client = Arc::new(mock_client);
+        let sem = fixed_size_permit_dealer(5);
+        let shutdown_token = CancellationToken::new();
+        let ap = new_activity_task_buffer(
+            mock_client.clone(),
+            PollOptions::normal("tq".to_string()),
+            1,
+            sem.clone(),
+            ActivityRateLimits::new(None, None),
+            shutdown_token.clone(),
+            None::<fn(usize)>,
+            None,
+            MetricsContext::no_op(),
+            Default::default(),
+        );
+        let atm = WorkerActivityTasks::new(
+            sem.clone(),
+            Box::new(ap),
+            mock_client.clone(),
+            metrics,
+            Duration::from_secs(1),
+            Duration::from_secs(1),
+            5,
+            None,
+            Duration::from_secs(5),
+            None,
+            Default::default(),
+        );
+
+        let t = atm.poll().await.unwrap();
+        assert_eq!(t.task_token, vec![1]);
+        tokio::time::timeout(Duration::from_secs(1), atm.poll())
+            .await
+            .expect_err("Redelivery while running must not be issued");
+        atm.complete(
+            TaskToken(t.task_token),
+            ActivityExecutionResult::fail("boom".into()).status.unwrap(),
+            mock_client.as_ref(),
+        )
+        .await
+        .unwrap();
+        completed.notify_one();
+        tokio::time::timeout(Duration::from_secs(1), atm.poll())
+            .await
+            .expect_err("Redelivery after completion must not be issued");
+
+        shutdown_token.cancel();
+        atm.initiate_shutdown();
+        assert_matches!(atm.poll().await.unwrap_err(), PollActivityError::ShutDown);
+        atm.shutdown().await;
+
+        let dupe_updates: Vec<_> = telem
+            .updates()
+            .into_iter()
+            .filter(|(n, _, _)| n.ends_with("activity_task_duplicate_dropped"))
+            .collect();
+        assert_eq!(dupe_updates.len(), 1);
+        assert_matches!(dupe_updates[0].2, MetricUpdateVal::Delta(1));
     }
 }
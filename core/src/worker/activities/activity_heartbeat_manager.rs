@@ -1,5 +1,6 @@
 use crate::{
     abstractions::take_cell::TakeCell,
+    telemetry::metrics::MetricsContext,
     worker::{activities::PendingActivityCancel, client::WorkerClient},
     TaskToken,
 };
@@ -40,7 +41,11 @@ enum HeartbeatAction {
         token: TaskToken,
         on_complete: Arc<Notify>,
     },
-    CompleteReport(TaskToken),
+    CompleteReport {
+        task_token: TaskToken,
+        /// Set if the report failed to reach server
+        failed: bool,
+    },
     CompleteThrottle(TaskToken),
 }
 
@@ -82,12 +87,16 @@ impl ActivityHeartbeatManager {
     /// Creates a new instance of an activity heartbeat manager and returns a handle to the user,
     /// which allows to send new heartbeats and initiate the shutdown.
     /// Returns the manager and a channel that buffers cancellation notifications to be sent to Lang.
+    /// Activities whose heartbeats fail `failures_before_cancel` times in a row are cancelled, see
+    /// [temporal_sdk_core_api::worker::WorkerConfig::heartbeat_failures_before_cancel].
     pub(super) fn new(
         client: Arc<dyn WorkerClient>,
         cancels_tx: UnboundedSender<PendingActivityCancel>,
+        failures_before_cancel: usize,
+        metrics: MetricsContext,
     ) -> Self {
         let (heartbeat_stream_state, heartbeat_tx_source, shutdown_token) =
-            HeartbeatStreamState::new();
+            HeartbeatStreamState::new(cancels_tx.clone(), failures_before_cancel, metrics);
         let heartbeat_tx = heartbeat_tx_source.clone();
 
         let join_handle = tokio::spawn(
@@ -100,7 +109,19 @@ impl ActivityHeartbeatManager {
                         biased;
 
                         _ = hb_states.cancellation_token.cancelled() => {
-                            return None
+                            // Throttled details would otherwise never make it to server
+                            if let Some(flush) = hb_states.next_shutdown_flush() {
+                                return Some((Some(flush), hb_states));
+                            }
+                            if !hb_states.has_details_behind_in_flight_report() {
+                                return None;
+                            }
+                            // Keep handling completions until the reports holding up the last
+                            // details are done, at which point those details are flushed too
+                            match hb_states.incoming_hbs.recv().await {
+                                None => return None,
+                                Some(hb) => hb,
+                            }
                         }
                         hb = hb_states.incoming_hbs.recv() => match hb {
                             None => return None,
@@ -111,7 +132,9 @@ impl ActivityHeartbeatManager {
                     Some((
                         match hb {
                             HeartbeatAction::SendHeartbeat(hb) => hb_states.record(hb),
-                            HeartbeatAction::CompleteReport(tt) => hb_states.handle_report_completed(tt),
+                            HeartbeatAction::CompleteReport { task_token, failed } => {
+                                hb_states.handle_report_completed(task_token, failed)
+                            }
                             HeartbeatAction::CompleteThrottle(tt) => hb_states.handle_throttle_completed(tt),
                             HeartbeatAction::Evict{ token, on_complete } => hb_states.evict(token, on_complete),
                         },
@@ -136,6 +159,7 @@ impl ActivityHeartbeatManager {
                             };
                             }
                             HeartbeatExecutorAction::Report { task_token: tt, details } => {
+                                let mut failed = false;
                                 match sg
                                     .record_activity_heartbeat(tt.clone(), details.into_payloads())
                                     .await
@@ -166,10 +190,15 @@ impl ActivityHeartbeatManager {
                                             .expect("Receive half of heartbeat cancels not blocked");
                                     }
                                     Err(e) => {
-                                        warn!("Error when recording heartbeat: {:?}", e);
+                                        warn!(task_token = %tt, error = ?e,
+                                              "Error when recording heartbeat");
+                                        failed = true;
                                     }
                                 };
-                                let _ = heartbeat_tx.send(HeartbeatAction::CompleteReport(tt));
+                                let _ = heartbeat_tx.send(HeartbeatAction::CompleteReport {
+                                    task_token: tt,
+                                    failed,
+                                });
                             }
                         }
                     }
@@ -220,7 +249,8 @@ impl ActivityHeartbeatManager {
     }
 
     /// Initiates shutdown procedure by stopping lifecycle loop and awaiting for all in-flight
-    /// heartbeat requests to be flushed to the server.
+    /// heartbeat requests, and any details still waiting out their throttle or behind an in-flight
+    /// request, to be flushed to the server.
     pub(super) async fn shutdown(&self) {
         self.shutdown_token.cancel();
         if let Some(h) = self.join_handle.take_once() {
@@ -263,10 +293,25 @@ impl ActivityHeartbeatState {
     }
 }
 
+/// An evicted task whose last details must reach server before eviction is considered complete
 #[derive(Debug)]
+struct PendingFlush {
+    on_complete: Arc<Notify>,
+    /// Details recorded while a report was already in flight. They're sent once that report
+    /// completes, rather than alongside it, so they can't be overtaken by the older details.
+    details: Option<Vec<Payload>>,
+}
+
 struct HeartbeatStreamState {
     tt_to_state: HashMap<TaskToken, ActivityHeartbeatState>,
-    tt_needs_flush: HashMap<TaskToken, Arc<Notify>>,
+    tt_needs_flush: HashMap<TaskToken, PendingFlush>,
+    /// Number of reports in a row which failed to reach server, for each task with any. Kept
+    /// separately from the task's state, which is forgotten whenever lang goes a throttle interval
+    /// without heartbeating.
+    tt_consecutive_failures: HashMap<TaskToken, usize>,
+    failures_before_cancel: usize,
+    cancels_tx: UnboundedSender<PendingActivityCancel>,
+    metrics: MetricsContext,
     incoming_hbs: UnboundedReceiver<HeartbeatAction>,
     /// Token that can be used to cancel the entire stream.
     /// Requests to the server are not cancelled with this token.
@@ -274,7 +319,11 @@ struct HeartbeatStreamState {
 }
 
 impl HeartbeatStreamState {
-    fn new() -> (Self, UnboundedSender<HeartbeatAction>, CancellationToken) {
+    fn new(
+        cancels_tx: UnboundedSender<PendingActivityCancel>,
+        failures_before_cancel: usize,
+        metrics: MetricsContext,
+    ) -> (Self, UnboundedSender<HeartbeatAction>, CancellationToken) {
         let (heartbeat_tx, incoming_hbs) = unbounded_channel();
         let cancellation_token = CancellationToken::new();
         (
@@ -282,6 +331,10 @@ impl HeartbeatStreamState {
                 cancellation_token: cancellation_token.clone(),
                 tt_to_state: Default::default(),
                 tt_needs_flush: Default::default(),
+                tt_consecutive_failures: Default::default(),
+                failures_before_cancel,
+                cancels_tx,
+                metrics,
                 incoming_hbs,
             },
             heartbeat_tx,
@@ -320,9 +373,27 @@ impl HeartbeatStreamState {
     }
 
     /// Heartbeat report to server completed
-    fn handle_report_completed(&mut self, tt: TaskToken) -> Option<HeartbeatExecutorAction> {
-        if let Some(not) = self.tt_needs_flush.remove(&tt) {
-            not.notify_one();
+    fn handle_report_completed(
+        &mut self,
+        tt: TaskToken,
+        failed: bool,
+    ) -> Option<HeartbeatExecutorAction> {
+        match self.tt_needs_flush.entry(tt.clone()) {
+            Entry::Occupied(mut e) => {
+                if let Some(details) = e.get_mut().details.take() {
+                    return Some(HeartbeatExecutorAction::Report {
+                        task_token: tt,
+                        details,
+                    });
+                }
+                e.remove().on_complete.notify_one();
+                // Evicted tasks have no state left to throttle with
+                return None;
+            }
+            Entry::Vacant(_) => {}
+        }
+        if self.tt_to_state.contains_key(&tt) {
+            self.count_report_outcome(&tt, failed);
         }
         if let Some(st) = self.tt_to_state.get_mut(&tt) {
             st.is_record_in_flight = false;
@@ -342,6 +413,29 @@ impl HeartbeatStreamState {
         }
     }
 
+    /// Once too many reports in a row have failed, the activity can't learn of being cancelled,
+    /// so it's presumed it should stop and is cancelled. Only done once per run of failures.
+    fn count_report_outcome(&mut self, tt: &TaskToken, failed: bool) {
+        if !failed {
+            self.tt_consecutive_failures.remove(tt);
+            return;
+        }
+        let failures = self.tt_consecutive_failures.entry(tt.clone()).or_default();
+        *failures += 1;
+        if self.failures_before_cancel == 0 || *failures != self.failures_before_cancel {
+            return;
+        }
+        warn!(task_token = %tt, failures = *failures,
+              "Activity heartbeats keep failing to reach server, cancelling the activity");
+        self.metrics.act_heartbeat_channel_lost();
+        self.cancels_tx
+            .send(PendingActivityCancel::new(
+                tt.clone(),
+                ActivityCancelReason::HeartbeatChannelLost,
+            ))
+            .expect("Receive half of heartbeat cancels not blocked");
+    }
+
     /// Throttling completed, report or stop tracking task token
     fn handle_throttle_completed(&mut self, tt: TaskToken) -> Option<HeartbeatExecutorAction> {
         match self.tt_to_state.entry(tt.clone()) {
@@ -367,28 +461,71 @@ impl HeartbeatStreamState {
         }
     }
 
+    /// Stop tracking one task whose recorded details are waiting out its throttle, and report
+    /// them. Called until there are none left on shutdown, so that no recorded details are lost.
+    /// Tasks with a report in flight are skipped, since their details can't be sent alongside it.
+    /// They're flushed once that report completes, see [Self::has_details_behind_in_flight_report].
+    fn next_shutdown_flush(&mut self) -> Option<HeartbeatExecutorAction> {
+        let tt = self
+            .tt_to_state
+            .iter()
+            .find(|(_, st)| st.last_recorded_details.is_some() && !st.is_record_in_flight)
+            .map(|(tt, _)| tt.clone())?;
+        let details = self.tt_to_state.remove(&tt)?.last_recorded_details?;
+        Some(HeartbeatExecutorAction::Report {
+            task_token: tt,
+            details,
+        })
+    }
+
+    /// True if any recorded details, including those of evicted tasks, are waiting on a report
+    /// which is still in flight. Shutdown must wait for those reports to complete, and their
+    /// outcomes to be handled, before the details can be flushed.
+    fn has_details_behind_in_flight_report(&self) -> bool {
+        !self.tt_needs_flush.is_empty()
+            || self
+                .tt_to_state
+                .values()
+                .any(|st| st.last_recorded_details.is_some() && st.is_record_in_flight)
+    }
+
     /// Activity should not be tracked anymore, cancel throttle timer if running.
     ///
     /// Will return a report action if there are recorded details present, to ensure we flush the
-    /// latest details before we cease tracking this activity.
+    /// latest details before we cease tracking this activity. If a report is already in flight,
+    /// the details are sent after it completes. `on_complete` is notified once nothing more will
+    /// be reported for the task, so its terminal response can't race any heartbeat.
     fn evict(
         &mut self,
         tt: TaskToken,
         on_complete: Arc<Notify>,
     ) -> Option<HeartbeatExecutorAction> {
+        self.tt_consecutive_failures.remove(&tt);
         if let Some(state) = self.tt_to_state.remove(&tt) {
             if let Some(cancel_tok) = state.throttled_cancellation_token {
                 cancel_tok.cancel();
             }
-            if let Some(last_deets) = state.last_recorded_details {
-                self.tt_needs_flush.insert(tt.clone(), on_complete);
+            if state.is_record_in_flight {
+                self.tt_needs_flush.insert(
+                    tt,
+                    PendingFlush {
+                        on_complete,
+                        details: state.last_recorded_details,
+                    },
+                );
+                return None;
+            } else if let Some(last_deets) = state.last_recorded_details {
+                self.tt_needs_flush.insert(
+                    tt.clone(),
+                    PendingFlush {
+                        on_complete,
+                        details: None,
+                    },
+                );
                 return Some(HeartbeatExecutorAction::Report {
                     task_token: tt,
                     details: last_deets,
                 });
-            } else if state.is_record_in_flight {
-                self.tt_needs_flush.insert(tt, on_complete);
-                return None;
             }
         }
         // Since there's nothing to flush immediately report back that eviction is finished
@@ -401,8 +538,14 @@ impl HeartbeatStreamState {
 mod test {
     use super::*;
 
-    use crate::worker::client::mocks::mock_workflow_client;
-    use std::time::Duration;
+    use crate::worker::client::mocks::{mock_manual_workflow_client, mock_workflow_client};
+    use futures_util::FutureExt;
+    use parking_lot::Mutex;
+    use std::{
+        collections::VecDeque,
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
     use temporal_sdk_core_protos::temporal::api::{
         common::v1::Payload, workflowservice::v1::RecordActivityTaskHeartbeatResponse,
     };
@@ -418,7 +561,12 @@ mod test {
             .returning(|_, _| Ok(RecordActivityTaskHeartbeatResponse::default()))
             .times(2);
         let (cancel_tx, _cancel_rx) = unbounded_channel();
-        let hm = ActivityHeartbeatManager::new(Arc::new(mock_client), cancel_tx);
+        let hm = ActivityHeartbeatManager::new(
+            Arc::new(mock_client),
+            cancel_tx,
+            5,
+            MetricsContext::no_op(),
+        );
         let fake_task_token = vec![1, 2, 3];
         // Send 2 heartbeat requests for 20ms apart.
         // The first heartbeat should be sent right away, and
@@ -440,7 +588,12 @@ mod test {
             .returning(|_, _| Ok(RecordActivityTaskHeartbeatResponse::default()))
             .times(3);
         let (cancel_tx, _cancel_rx) = unbounded_channel();
-        let hm = ActivityHeartbeatManager::new(Arc::new(mock_client), cancel_tx);
+        let hm = ActivityHeartbeatManager::new(
+            Arc::new(mock_client),
+            cancel_tx,
+            5,
+            MetricsContext::no_op(),
+        );
         let fake_task_token = vec![1, 2, 3];
         // Heartbeats always get sent if recorded less frequently than the throttle interval
         for i in 0_u8..3 {
@@ -457,11 +610,17 @@ mod test {
         mock_client
             .expect_record_activity_heartbeat()
             .returning(|_, _| Ok(RecordActivityTaskHeartbeatResponse::default()))
-            .times(1);
+            .times(2);
         let (cancel_tx, _cancel_rx) = unbounded_channel();
-        let hm = ActivityHeartbeatManager::new(Arc::new(mock_client), cancel_tx);
+        let hm = ActivityHeartbeatManager::new(
+            Arc::new(mock_client),
+            cancel_tx,
+            5,
+            MetricsContext::no_op(),
+        );
         let fake_task_token = vec![1, 2, 3];
-        // Send a whole bunch of heartbeats very fast. We should still only send one total.
+        // Send a whole bunch of heartbeats very fast. We should still only send the first, and then
+        // the last when shutdown flushes it.
         for i in 0_u8..50 {
             record_heartbeat(&hm, fake_task_token.clone(), i, Duration::from_millis(2000));
             // Let it propagate
@@ -479,7 +638,12 @@ mod test {
             .returning(|_, _| Ok(RecordActivityTaskHeartbeatResponse::default()))
             .times(2);
         let (cancel_tx, _cancel_rx) = unbounded_channel();
-        let hm = ActivityHeartbeatManager::new(Arc::new(mock_client), cancel_tx);
+        let hm = ActivityHeartbeatManager::new(
+            Arc::new(mock_client),
+            cancel_tx,
+            5,
+            MetricsContext::no_op(),
+        );
         let fake_task_token = vec![1, 2, 3];
         record_heartbeat(&hm, fake_task_token.clone(), 0, Duration::from_millis(100));
         sleep(Duration::from_millis(500)).await;
@@ -497,7 +661,12 @@ mod test {
             .returning(|_, _| Ok(RecordActivityTaskHeartbeatResponse::default()))
             .times(2);
         let (cancel_tx, _cancel_rx) = unbounded_channel();
-        let hm = ActivityHeartbeatManager::new(Arc::new(mock_client), cancel_tx);
+        let hm = ActivityHeartbeatManager::new(
+            Arc::new(mock_client),
+            cancel_tx,
+            5,
+            MetricsContext::no_op(),
+        );
         let fake_task_token = vec![1, 2, 3];
         record_heartbeat(&hm, fake_task_token.clone(), 0, Duration::from_millis(100));
         // Let it propagate
@@ -518,13 +687,166 @@ mod test {
             .returning(|_, _| Ok(RecordActivityTaskHeartbeatResponse::default()))
             .times(1);
         let (cancel_tx, _cancel_rx) = unbounded_channel();
-        let hm = ActivityHeartbeatManager::new(Arc::new(mock_client), cancel_tx);
+        let hm = ActivityHeartbeatManager::new(
+            Arc::new(mock_client),
+            cancel_tx,
+            5,
+            MetricsContext::no_op(),
+        );
         let fake_task_token = vec![1, 2, 3];
         record_heartbeat(&hm, fake_task_token.clone(), 0, Duration::from_millis(100));
         hm.evict(fake_task_token.clone().into()).await;
         hm.shutdown().await;
     }
 
+    /// Details recorded while a report is in flight must be flushed after it rather than
+    /// alongside it, and eviction (which precedes the task's terminal response) must wait for them
+    #[tokio::test]
+    async fn evict_flushes_after_in_flight_report() {
+        let mut mock_client = mock_manual_workflow_client();
+        let reported = Arc::new(Mutex::new(vec![]));
+        let reported_c = reported.clone();
+        mock_client
+            .expect_record_activity_heartbeat()
+            .returning(move |_, details| {
+                let reported = reported_c.clone();
+                async move {
+                    sleep(Duration::from_millis(50)).await;
+                    reported.lock().push(details.unwrap().payloads[0].data[0]);
+                    Ok(RecordActivityTaskHeartbeatResponse::default())
+                }
+                .boxed()
+            })
+            .times(2);
+        let (cancel_tx, _cancel_rx) = unbounded_channel();
+        let hm = ActivityHeartbeatManager::new(
+            Arc::new(mock_client),
+            cancel_tx,
+            5,
+            MetricsContext::no_op(),
+        );
+        let fake_task_token = vec![1, 2, 3];
+        record_heartbeat(&hm, fake_task_token.clone(), 0, Duration::from_millis(100));
+        // Let the first report get in flight
+        sleep(Duration::from_millis(10)).await;
+        record_heartbeat(&hm, fake_task_token.clone(), 1, Duration::from_millis(100));
+        hm.evict(fake_task_token.into()).await;
+        assert_eq!(reported.lock().as_slice(), &[0, 1]);
+        hm.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn shutdown_flushes_throttled_details() {
+        let mut mock_client = mock_workflow_client();
+        let reported = Arc::new(Mutex::new(vec![]));
+        let reported_c = reported.clone();
+        mock_client
+            .expect_record_activity_heartbeat()
+            .returning(move |_, details| {
+                reported_c.lock().push(details.unwrap().payloads[0].data[0]);
+                Ok(RecordActivityTaskHeartbeatResponse::default())
+            })
+            .times(2);
+        let (cancel_tx, _cancel_rx) = unbounded_channel();
+        let hm = ActivityHeartbeatManager::new(
+            Arc::new(mock_client),
+            cancel_tx,
+            5,
+            MetricsContext::no_op(),
+        );
+        let fake_task_token = vec![1, 2, 3];
+        // As for a 10s heartbeat timeout
+        for i in 0_u8..10 {
+            record_heartbeat(&hm, fake_task_token.clone(), i, Duration::from_secs(8));
+            sleep(Duration::from_millis(10)).await;
+        }
+        hm.shutdown().await;
+        assert_eq!(reported.lock().as_slice(), &[0, 9]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn shutdown_flushes_details_recorded_during_in_flight_report() {
+        let mut mock_client = mock_manual_workflow_client();
+        let reported = Arc::new(Mutex::new(vec![]));
+        let reported_c = reported.clone();
+        let calls = Arc::new(AtomicUsize::new(0));
+        mock_client
+            .expect_record_activity_heartbeat()
+            .returning(move |_, details| {
+                let reported = reported_c.clone();
+                let first = calls.fetch_add(1, Ordering::SeqCst) == 0;
+                async move {
+                    sleep(Duration::from_millis(50)).await;
+                    reported.lock().push(details.unwrap().payloads[0].data[0]);
+                    // The report in flight at shutdown fails, which mustn't lose what follows it
+                    if first {
+                        Err(tonic::Status::unavailable("partitioned"))
+                    } else {
+                        Ok(RecordActivityTaskHeartbeatResponse::default())
+                    }
+                }
+                .boxed()
+            })
+            .times(2);
+        let (cancel_tx, _cancel_rx) = unbounded_channel();
+        let hm = ActivityHeartbeatManager::new(
+            Arc::new(mock_client),
+            cancel_tx,
+            5,
+            MetricsContext::no_op(),
+        );
+        let fake_task_token = vec![1, 2, 3];
+        record_heartbeat(&hm, fake_task_token.clone(), 0, Duration::from_secs(8));
+        // Let the first report get in flight
+        sleep(Duration::from_millis(10)).await;
+        record_heartbeat(&hm, fake_task_token, 1, Duration::from_secs(8));
+        sleep(Duration::from_millis(10)).await;
+        hm.shutdown().await;
+        assert_eq!(reported.lock().as_slice(), &[0, 1]);
+    }
+
+    #[tokio::test]
+    async fn repeated_heartbeat_failures_cancel_the_activity() {
+        let unavailable = || Err(tonic::Status::unavailable("partitioned"));
+        let results = Mutex::new(VecDeque::from([
+            unavailable(),
+            unavailable(),
+            // Reaching server resets the count
+            Ok(RecordActivityTaskHeartbeatResponse::default()),
+            unavailable(),
+            unavailable(),
+            unavailable(),
+            unavailable(),
+        ]));
+        let mut mock_client = mock_workflow_client();
+        mock_client
+            .expect_record_activity_heartbeat()
+            .returning(move |_, _| results.lock().pop_front().unwrap())
+            .times(7);
+        let (cancel_tx, mut cancel_rx) = unbounded_channel();
+        let hm = ActivityHeartbeatManager::new(
+            Arc::new(mock_client),
+            cancel_tx,
+            3,
+            MetricsContext::no_op(),
+        );
+        let fake_task_token = vec![1, 2, 3];
+        for i in 0_u8..7 {
+            record_heartbeat(&hm, fake_task_token.clone(), i, Duration::from_millis(10));
+            sleep(Duration::from_millis(20)).await;
+            // Only the third failure in a row cancels, and only once
+            let cancel = cancel_rx.try_recv().ok();
+            if i == 5 {
+                let cancel = cancel.expect("Activity is cancelled");
+                assert_eq!(cancel.task_token, TaskToken(fake_task_token.clone()));
+                assert_eq!(cancel.reason, ActivityCancelReason::HeartbeatChannelLost);
+            } else {
+                assert!(cancel.is_none(), "Unexpected cancel after heartbeat {i}");
+            }
+        }
+        hm.shutdown().await;
+    }
+
     fn record_heartbeat(
         hm: &ActivityHeartbeatManager,
         task_token: Vec<u8>,
@@ -261,6 +261,15 @@ impl LocalActivityManager {
         )
     }
 
+    /// The type of a local activity which has been issued to lang but not yet completed
+    pub(crate) fn activity_type(&self, task_token: &TaskToken) -> Option<String> {
+        self.dat
+            .lock()
+            .outstanding_activity_tasks
+            .get(task_token)
+            .map(|i| i.la_info.schedule_cmd.activity_type.clone())
+    }
+
     #[cfg(test)]
     pub(crate) fn num_outstanding(&self) -> usize {
         self.dat.lock().outstanding_activity_tasks.len()
@@ -431,12 +440,16 @@ impl LocalActivityManager {
 
         // It is important that there are no await points after receiving from the channel, as
         // it would mean dropping this future would cause us to drop the activity request.
-        let (new_la, attempt) = match new_or_retry {
+        let (new_la, attempt, previous_retry_delay) = match new_or_retry {
             NewOrRetry::New(n) => {
                 let explicit_attempt_num_or_1 = n.schedule_cmd.attempt.max(1);
-                (n, explicit_attempt_num_or_1)
+                (n, explicit_attempt_num_or_1, None)
             }
-            NewOrRetry::Retry { in_flight, attempt } => (in_flight, attempt),
+            NewOrRetry::Retry {
+                in_flight,
+                attempt,
+                backoff,
+            } => (in_flight, attempt, Some(backoff)),
         };
         let la_info_for_in_flight_map = new_la.clone();
         let id = ExecutingLAId {
@@ -526,6 +539,7 @@ impl LocalActivityManager {
                 heartbeat_timeout: None,
                 retry_policy: Some(sa.retry_policy),
                 is_local: true,
+                previous_retry_delay: previous_retry_delay.and_then(|d| d.try_into().ok()),
             })),
         }))
     }
@@ -676,6 +690,7 @@ impl LocalActivityManager {
                                 .send(NewOrRetry::Retry {
                                     in_flight: info.la_info,
                                     attempt: info.attempt + 1,
+                                    backoff: backoff_dur,
                                 })
                                 .expect("Receive half of LA request channel cannot be dropped");
                         });
@@ -819,6 +834,8 @@ enum NewOrRetry {
     Retry {
         in_flight: NewLocalAct,
         attempt: u32,
+        /// How long the retry was delayed after the previous attempt failed
+        backoff: Duration,
     },
 }
 
@@ -1260,6 +1277,56 @@ mod tests {
         assert_eq!(lam.num_outstanding(), 1);
     }
 
+    #[tokio::test]
+    async fn retried_attempt_reports_the_delay_before_it() {
+        let lam = LocalActivityManager::test(1);
+        lam.enqueue([NewLocalAct {
+            schedule_cmd: ValidScheduleLA {
+                seq: 1,
+                activity_id: 1.to_string(),
+                retry_policy: RetryPolicy {
+                    initial_interval: Some(prost_dur!(from_secs(10))),
+                    backoff_coefficient: 1.0,
+                    ..Default::default()
+                },
+                local_retry_threshold: Duration::from_secs(500),
+                ..Default::default()
+            },
+            workflow_type: "".to_string(),
+            workflow_exec_info: Default::default(),
+            schedule_time: SystemTime::now(),
+        }
+        .into()]);
+        let previous_retry_delay = |task: ActivityTask| match task.variant {
+            Some(activity_task::Variant::Start(s)) => s.previous_retry_delay,
+            _ => panic!("Expected a start task"),
+        };
+
+        let next = lam.next_pending().await.unwrap().unwrap();
+        let tt = TaskToken(next.task_token.clone());
+        assert_eq!(previous_retry_delay(next), None);
+        // The failure's requested delay overrides the retry policy's
+        lam.complete(
+            &tt,
+            LocalActivityExecutionResult::Failed(ActFail {
+                failure: Some(Failure {
+                    failure_info: Some(FailureInfo::ApplicationFailureInfo(
+                        ApplicationFailureInfo {
+                            next_retry_delay: Some(prost_dur!(from_millis(10))),
+                            ..Default::default()
+                        },
+                    )),
+                    ..Default::default()
+                }),
+            }),
+        );
+        let next = lam.next_pending().await.unwrap().unwrap();
+        assert_eq!(
+            previous_retry_delay(next),
+            Some(prost_dur!(from_millis(10)))
+        );
+    }
+
     #[tokio::test]
     async fn sched_to_start_timeout() {
         let lam = LocalActivityManager::test(1);
@@ -0,0 +1,223 @@
+//! Remembers which activity tasks were recently delivered to lang. The server delivers tasks at
+//! least once, so the same task token may be polled again (ex: when the matching service retries
+//! a dispatch), and must not be executed twice.
+
+use crate::TaskToken;
+use parking_lot::Mutex;
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+/// How long a delivery is remembered if the task has no start-to-close timeout
+const DEFAULT_WINDOW: Duration = Duration::from_secs(5 * 60);
+/// Longest any delivery is remembered, however long the task's timeout
+const MAX_WINDOW: Duration = Duration::from_secs(60 * 60);
+/// Most deliveries remembered at once. Beyond this the oldest are forgotten first.
+const MAX_TRACKED: usize = 10_000;
+
+/// Whether a task has been delivered before
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Delivery {
+    /// Not delivered within the window
+    First,
+    /// Delivered before, and not yet completed
+    Repeated,
+    /// Delivered before, and already completed
+    RepeatedAfterCompletion,
+}
+
+/// A set of recently delivered task tokens, each remembered for about as long as its task could
+/// still be running
+#[derive(Default)]
+pub(super) struct RecentDeliveries {
+    state: Mutex<DeliveriesState>,
+}
+
+#[derive(Default)]
+struct DeliveriesState {
+    entries: HashMap<TaskToken, DeliveryEntry>,
+    /// Tokens in the order they were delivered, along with when, so expired or excess entries can
+    /// be forgotten oldest first
+    order: VecDeque<(TaskToken, Instant)>,
+}
+
+struct DeliveryEntry {
+    delivered_at: Instant,
+    expires_at: Instant,
+    completed: bool,
+    /// Whether lang had been asked to cancel the task before it completed
+    cancelled: bool,
+}
+
+impl RecentDeliveries {
+    /// Record that the task with token `tt` is being delivered, and report whether it already was.
+    /// It is remembered for `start_to_close`, if set.
+    pub(super) fn observe(&self, tt: &TaskToken, start_to_close: Option<Duration>) -> Delivery {
+        self.observe_at(tt, start_to_close, Instant::now())
+    }
+
+    fn observe_at(
+        &self,
+        tt: &TaskToken,
+        start_to_close: Option<Duration>,
+        now: Instant,
+    ) -> Delivery {
+        let mut state = self.state.lock();
+        state.forget_expired(now);
+        if let Some(entry) = state.entries.get(tt) {
+            return if entry.completed {
+                Delivery::RepeatedAfterCompletion
+            } else {
+                Delivery::Repeated
+            };
+        }
+        let window = start_to_close
+            .filter(|d| !d.is_zero())
+            .unwrap_or(DEFAULT_WINDOW)
+            .min(MAX_WINDOW);
+        state.entries.insert(
+            tt.clone(),
+            DeliveryEntry {
+                delivered_at: now,
+                expires_at: now + window,
+                completed: false,
+                cancelled: false,
+            },
+        );
+        state.order.push_back((tt.clone(), now));
+        while state.entries.len() > MAX_TRACKED {
+            state.forget_oldest();
+        }
+        Delivery::First
+    }
+
+    /// Record that the task with token `tt` has completed, so that any further deliveries of it
+    /// can be dropped without complaint. `cancelled` is whether lang had been asked to cancel it.
+    pub(super) fn mark_completed(&self, tt: &TaskToken, cancelled: bool) {
+        if let Some(entry) = self.state.lock().entries.get_mut(tt) {
+            entry.completed = true;
+            entry.cancelled = cancelled;
+        }
+    }
+
+    /// True if the task with token `tt` was cancelled and has since been completed. Lang may
+    /// still be finishing up such a task after core has cleaned it up, so a late completion of it
+    /// is expected.
+    pub(super) fn completed_after_cancel(&self, tt: &TaskToken) -> bool {
+        self.state
+            .lock()
+            .entries
+            .get(tt)
+            .is_some_and(|e| e.completed && e.cancelled)
+    }
+}
+
+impl DeliveriesState {
+    /// Forget expired deliveries at the front of the queue. Windows vary by task, so some expired
+    /// entries may linger behind an unexpired one, but never beyond [MAX_TRACKED] of them.
+    fn forget_expired(&mut self, now: Instant) {
+        while let Some((tt, _)) = self.order.front() {
+            match self.entries.get(tt) {
+                Some(entry) if entry.expires_at > now => break,
+                _ => self.forget_oldest(),
+            }
+        }
+    }
+
+    fn forget_oldest(&mut self) {
+        if let Some((tt, delivered_at)) = self.order.pop_front() {
+            // The token may have expired and been delivered again since this was queued, in
+            // which case the entry belongs to the later delivery
+            if self
+                .entries
+                .get(&tt)
+                .is_some_and(|e| e.delivered_at == delivered_at)
+            {
+                self.entries.remove(&tt);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tt(i: u8) -> TaskToken {
+        TaskToken(vec![i])
+    }
+
+    #[test]
+    fn repeats_are_detected_until_completion() {
+        let rd = RecentDeliveries::default();
+        let now = Instant::now();
+        assert_eq!(rd.observe_at(&tt(1), None, now), Delivery::First);
+        assert_eq!(rd.observe_at(&tt(2), None, now), Delivery::First);
+        assert_eq!(rd.observe_at(&tt(1), None, now), Delivery::Repeated);
+        rd.mark_completed(&tt(1), false);
+        assert_eq!(
+            rd.observe_at(&tt(1), None, now),
+            Delivery::RepeatedAfterCompletion
+        );
+        assert_eq!(rd.observe_at(&tt(2), None, now), Delivery::Repeated);
+    }
+
+    #[test]
+    fn completions_after_cancel_are_remembered() {
+        let rd = RecentDeliveries::default();
+        let now = Instant::now();
+        rd.observe_at(&tt(1), None, now);
+        rd.observe_at(&tt(2), None, now);
+        assert!(!rd.completed_after_cancel(&tt(1)));
+        rd.mark_completed(&tt(1), true);
+        rd.mark_completed(&tt(2), false);
+        assert!(rd.completed_after_cancel(&tt(1)));
+        assert!(!rd.completed_after_cancel(&tt(2)));
+        assert!(!rd.completed_after_cancel(&tt(3)));
+    }
+
+    #[test]
+    fn deliveries_expire_after_start_to_close() {
+        let rd = RecentDeliveries::default();
+        let now = Instant::now();
+        let stc = Some(Duration::from_secs(30));
+        assert_eq!(rd.observe_at(&tt(1), stc, now), Delivery::First);
+        let later = now + Duration::from_secs(29);
+        assert_eq!(rd.observe_at(&tt(1), stc, later), Delivery::Repeated);
+        let expired = now + Duration::from_secs(31);
+        assert_eq!(rd.observe_at(&tt(1), stc, expired), Delivery::First);
+        // The re-delivery is remembered for its own window, not the first one's
+        assert_eq!(
+            rd.observe_at(&tt(1), stc, expired + Duration::from_secs(10)),
+            Delivery::Repeated
+        );
+    }
+
+    #[test]
+    fn window_is_capped() {
+        let rd = RecentDeliveries::default();
+        let now = Instant::now();
+        let stc = Some(Duration::from_secs(24 * 60 * 60));
+        rd.observe_at(&tt(1), stc, now);
+        assert_eq!(
+            rd.observe_at(&tt(1), stc, now + MAX_WINDOW + Duration::from_secs(1)),
+            Delivery::First
+        );
+    }
+
+    #[test]
+    fn oldest_deliveries_are_forgotten_beyond_capacity() {
+        let rd = RecentDeliveries::default();
+        let now = Instant::now();
+        for i in 0..=MAX_TRACKED {
+            let tt = TaskToken(i.to_le_bytes().to_vec());
+            rd.observe_at(&tt, None, now);
+        }
+        assert_eq!(rd.state.lock().entries.len(), MAX_TRACKED);
+        let first = TaskToken(0usize.to_le_bytes().to_vec());
+        let last = TaskToken(MAX_TRACKED.to_le_bytes().to_vec());
+        assert_eq!(rd.observe_at(&last, None, now), Delivery::Repeated);
+        assert_eq!(rd.observe_at(&first, None, now), Delivery::First);
+    }
+}
@@ -1,9 +1,15 @@
 //! Worker-specific client needs
 
+mod failover;
 pub(crate) mod mocks;
+
+use crate::pollers::PollOptions;
+pub(crate) use failover::{FailoverWorkerClient, FAILOVER_RESOLVE_INTERVAL};
 use parking_lot::RwLock;
 use std::sync::Arc;
-use temporal_client::{Client, Namespace, RetryClient, SlotManager, WorkflowService};
+use temporal_client::{
+    Client, Namespace, RetryClient, SlotManager, TaskQueueDescription, WorkflowService,
+};
 use temporal_sdk_core_protos::{
     coresdk::workflow_commands::QueryResult,
     temporal::api::{
@@ -12,7 +18,7 @@ use temporal_sdk_core_protos::{
             MeteringMetadata, Payloads, WorkerVersionCapabilities, WorkerVersionStamp,
             WorkflowExecution,
         },
-        enums::v1::{TaskQueueKind, WorkflowTaskFailedCause},
+        enums::v1::{TaskQueueType, WorkflowTaskFailedCause},
         failure::v1::Failure,
         protocol::v1::Message as ProtocolMessage,
         query::v1::WorkflowQueryResult,
@@ -20,18 +26,19 @@ use temporal_sdk_core_protos::{
         taskqueue::v1::{StickyExecutionAttributes, TaskQueue, TaskQueueMetadata},
         workflowservice::v1::{get_system_info_response::Capabilities, *},
     },
-    TaskToken,
+    RunId, TaskQueueName, TaskToken, WorkflowId,
 };
+use tokio_util::sync::CancellationToken;
 
 type Result<T, E = tonic::Status> = std::result::Result<T, E>;
 
 /// Contains everything a worker needs to interact with the server
 pub(crate) struct WorkerClientBag {
     replaceable_client: RwLock<RetryClient<Client>>,
-    namespace: String,
-    identity: String,
-    worker_build_id: String,
-    use_versioning: bool,
+    stamps: TaskStamps,
+    /// Aborts client-style calls (history fetches and the like) which would otherwise hold up
+    /// shutdown. Task completions are never aborted, so results still reach the server.
+    calls_cancel: CancellationToken,
 }
 
 impl WorkerClientBag {
@@ -44,10 +51,13 @@ impl WorkerClientBag {
     ) -> Self {
         Self {
             replaceable_client: RwLock::new(client),
-            namespace,
-            identity,
-            worker_build_id,
-            use_versioning,
+            stamps: TaskStamps {
+                namespace,
+                identity,
+                worker_build_id,
+                use_versioning,
+            },
+            calls_cancel: CancellationToken::new(),
         }
     }
 
@@ -55,38 +65,254 @@ impl WorkerClientBag {
         self.replaceable_client.read().clone()
     }
 
-    fn default_capabilities(&self) -> Capabilities {
-        self.capabilities().unwrap_or_default()
+    fn cancellable_client(&self) -> RetryClient<Client> {
+        self.cloned_client()
+            .with_cancellation(self.calls_cancel.clone())
+    }
+
+    fn build_id_versioning(&self) -> bool {
+        self.capabilities()
+            .unwrap_or_default()
+            .build_id_based_versioning
     }
 
     fn binary_checksum(&self) -> String {
-        if self.default_capabilities().build_id_based_versioning {
+        self.stamps.binary_checksum(self.build_id_versioning())
+    }
+
+    fn worker_version_stamp(&self) -> Option<WorkerVersionStamp> {
+        self.stamps.worker_version_stamp(self.build_id_versioning())
+    }
+}
+
+/// How a worker identifies itself to server in the requests it makes about its tasks
+#[derive(Debug, Clone)]
+struct TaskStamps {
+    namespace: String,
+    identity: String,
+    worker_build_id: String,
+    use_versioning: bool,
+}
+
+impl TaskStamps {
+    /// Servers which version by build id want it in the version fields rather than as a checksum
+    fn binary_checksum(&self, build_id_versioning: bool) -> String {
+        if build_id_versioning {
             "".to_string()
         } else {
             self.worker_build_id.clone()
         }
     }
 
-    fn worker_version_capabilities(&self) -> Option<WorkerVersionCapabilities> {
-        if self.default_capabilities().build_id_based_versioning {
-            Some(WorkerVersionCapabilities {
-                build_id: self.worker_build_id.clone(),
-                use_versioning: self.use_versioning,
-            })
-        } else {
-            None
-        }
+    fn worker_version_capabilities(
+        &self,
+        build_id_versioning: bool,
+    ) -> Option<WorkerVersionCapabilities> {
+        build_id_versioning.then(|| WorkerVersionCapabilities {
+            build_id: self.worker_build_id.clone(),
+            use_versioning: self.use_versioning,
+        })
     }
 
-    fn worker_version_stamp(&self) -> Option<WorkerVersionStamp> {
-        if self.default_capabilities().build_id_based_versioning {
-            Some(WorkerVersionStamp {
-                build_id: self.worker_build_id.clone(),
-                use_versioning: self.use_versioning,
-            })
-        } else {
-            None
-        }
+    fn worker_version_stamp(&self, build_id_versioning: bool) -> Option<WorkerVersionStamp> {
+        build_id_versioning.then(|| WorkerVersionStamp {
+            build_id: self.worker_build_id.clone(),
+            use_versioning: self.use_versioning,
+        })
+    }
+
+    async fn poll_workflow_task(
+        &self,
+        rpcs: &impl TaskRpcs,
+        build_id_versioning: bool,
+        task_queue: TaskQueue,
+    ) -> Result<PollWorkflowTaskQueueResponse> {
+        rpcs.poll_workflow_task_queue(PollWorkflowTaskQueueRequest {
+            namespace: self.namespace.clone(),
+            task_queue: Some(task_queue),
+            identity: self.identity.clone(),
+            binary_checksum: self.binary_checksum(build_id_versioning),
+            worker_version_capabilities: self.worker_version_capabilities(build_id_versioning),
+        })
+        .await
+    }
+
+    async fn poll_activity_task(
+        &self,
+        rpcs: &impl TaskRpcs,
+        build_id_versioning: bool,
+        options: PollOptions,
+        max_tasks_per_sec: Option<f64>,
+    ) -> Result<PollActivityTaskQueueResponse> {
+        rpcs.poll_activity_task_queue(PollActivityTaskQueueRequest {
+            namespace: self.namespace.clone(),
+            task_queue: Some(options.task_queue()),
+            identity: self.identity.clone(),
+            task_queue_metadata: max_tasks_per_sec.map(|tps| TaskQueueMetadata {
+                max_tasks_per_second: Some(tps),
+            }),
+            worker_version_capabilities: options
+                .versioning()
+                .cloned()
+                .or_else(|| self.worker_version_capabilities(build_id_versioning)),
+        })
+        .await
+    }
+
+    async fn complete_workflow_task(
+        &self,
+        rpcs: &impl TaskRpcs,
+        build_id_versioning: bool,
+        request: WorkflowTaskCompletion,
+    ) -> Result<RespondWorkflowTaskCompletedResponse> {
+        rpcs.respond_workflow_task_completed(RespondWorkflowTaskCompletedRequest {
+            task_token: request.task_token.into(),
+            commands: request.commands,
+            messages: request.messages,
+            identity: self.identity.clone(),
+            sticky_attributes: request.sticky_attributes,
+            return_new_workflow_task: request.return_new_workflow_task,
+            force_create_new_workflow_task: request.force_create_new_workflow_task,
+            worker_version_stamp: self.worker_version_stamp(build_id_versioning),
+            binary_checksum: self.binary_checksum(build_id_versioning),
+            query_results: request
+                .query_responses
+                .into_iter()
+                .map(|qr| {
+                    let (id, completed_type, query_result, error_message) = qr.into_components();
+                    (
+                        id,
+                        WorkflowQueryResult {
+                            result_type: completed_type as i32,
+                            answer: query_result,
+                            error_message,
+                        },
+                    )
+                })
+                .collect(),
+            namespace: self.namespace.clone(),
+            sdk_metadata: Some(request.sdk_metadata),
+            metering_metadata: Some(request.metering_metadata),
+            capabilities: None,
+        })
+        .await
+    }
+
+    async fn complete_activity_task(
+        &self,
+        rpcs: &impl TaskRpcs,
+        build_id_versioning: bool,
+        task_token: TaskToken,
+        result: Option<Payloads>,
+    ) -> Result<RespondActivityTaskCompletedResponse> {
+        rpcs.respond_activity_task_completed(RespondActivityTaskCompletedRequest {
+            task_token: task_token.0,
+            result,
+            identity: self.identity.clone(),
+            namespace: self.namespace.clone(),
+            worker_version: self.worker_version_stamp(build_id_versioning),
+        })
+        .await
+    }
+
+    async fn record_activity_heartbeat(
+        &self,
+        rpcs: &impl TaskRpcs,
+        task_token: TaskToken,
+        details: Option<Payloads>,
+    ) -> Result<RecordActivityTaskHeartbeatResponse> {
+        rpcs.record_activity_task_heartbeat(RecordActivityTaskHeartbeatRequest {
+            task_token: task_token.0,
+            details,
+            identity: self.identity.clone(),
+            namespace: self.namespace.clone(),
+        })
+        .await
+    }
+}
+
+/// The calls through which workers get and complete their tasks. Split from [WorkflowService] so
+/// that tests can set expectations on exactly what [TaskStamps] sends.
+#[cfg_attr(test, mockall::automock)]
+#[async_trait::async_trait]
+trait TaskRpcs: Send + Sync {
+    async fn poll_workflow_task_queue(
+        &self,
+        request: PollWorkflowTaskQueueRequest,
+    ) -> Result<PollWorkflowTaskQueueResponse>;
+    async fn poll_activity_task_queue(
+        &self,
+        request: PollActivityTaskQueueRequest,
+    ) -> Result<PollActivityTaskQueueResponse>;
+    async fn respond_workflow_task_completed(
+        &self,
+        request: RespondWorkflowTaskCompletedRequest,
+    ) -> Result<RespondWorkflowTaskCompletedResponse>;
+    async fn respond_activity_task_completed(
+        &self,
+        request: RespondActivityTaskCompletedRequest,
+    ) -> Result<RespondActivityTaskCompletedResponse>;
+    async fn record_activity_task_heartbeat(
+        &self,
+        request: RecordActivityTaskHeartbeatRequest,
+    ) -> Result<RecordActivityTaskHeartbeatResponse>;
+}
+
+#[async_trait::async_trait]
+impl TaskRpcs for RetryClient<Client> {
+    async fn poll_workflow_task_queue(
+        &self,
+        request: PollWorkflowTaskQueueRequest,
+    ) -> Result<PollWorkflowTaskQueueResponse> {
+        Ok(
+            WorkflowService::poll_workflow_task_queue(&mut self.clone(), request)
+                .await?
+                .into_inner(),
+        )
+    }
+
+    async fn poll_activity_task_queue(
+        &self,
+        request: PollActivityTaskQueueRequest,
+    ) -> Result<PollActivityTaskQueueResponse> {
+        Ok(
+            WorkflowService::poll_activity_task_queue(&mut self.clone(), request)
+                .await?
+                .into_inner(),
+        )
+    }
+
+    async fn respond_workflow_task_completed(
+        &self,
+        request: RespondWorkflowTaskCompletedRequest,
+    ) -> Result<RespondWorkflowTaskCompletedResponse> {
+        Ok(
+            WorkflowService::respond_workflow_task_completed(&mut self.clone(), request)
+                .await?
+                .into_inner(),
+        )
+    }
+
+    async fn respond_activity_task_completed(
+        &self,
+        request: RespondActivityTaskCompletedRequest,
+    ) -> Result<RespondActivityTaskCompletedResponse> {
+        Ok(
+            WorkflowService::respond_activity_task_completed(&mut self.clone(), request)
+                .await?
+                .into_inner(),
+        )
+    }
+
+    async fn record_activity_task_heartbeat(
+        &self,
+        request: RecordActivityTaskHeartbeatRequest,
+    ) -> Result<RecordActivityTaskHeartbeatResponse> {
+        Ok(
+            WorkflowService::record_activity_task_heartbeat(&mut self.clone(), request)
+                .await?
+                .into_inner(),
+        )
     }
 }
 
@@ -101,7 +327,7 @@ pub(crate) trait WorkerClient: Sync + Send {
     ) -> Result<PollWorkflowTaskQueueResponse>;
     async fn poll_activity_task(
         &self,
-        task_queue: String,
+        options: PollOptions,
         max_tasks_per_sec: Option<f64>,
     ) -> Result<PollActivityTaskQueueResponse>;
     async fn complete_workflow_task(
@@ -134,10 +360,17 @@ pub(crate) trait WorkerClient: Sync + Send {
         cause: WorkflowTaskFailedCause,
         failure: Option<Failure>,
     ) -> Result<RespondWorkflowTaskFailedResponse>;
+    /// Have server send the run's future tasks to the normal queue rather than this worker's
+    /// sticky one
+    async fn reset_sticky_task_queue(
+        &self,
+        workflow_id: WorkflowId,
+        run_id: RunId,
+    ) -> Result<ResetStickyTaskQueueResponse>;
     async fn get_workflow_execution_history(
         &self,
-        workflow_id: String,
-        run_id: Option<String>,
+        workflow_id: WorkflowId,
+        run_id: Option<RunId>,
         page_token: Vec<u8>,
     ) -> Result<GetWorkflowExecutionHistoryResponse>;
     async fn respond_legacy_query(
@@ -146,11 +379,26 @@ pub(crate) trait WorkerClient: Sync + Send {
         query_result: QueryResult,
     ) -> Result<RespondQueryTaskCompletedResponse>;
     async fn describe_namespace(&self) -> Result<DescribeNamespaceResponse>;
-    async fn shutdown_worker(&self, sticky_task_queue: String) -> Result<ShutdownWorkerResponse>;
+    async fn describe_task_queue(
+        &self,
+        task_queue: TaskQueueName,
+        task_queue_type: TaskQueueType,
+    ) -> Result<TaskQueueDescription>;
+    async fn shutdown_worker(
+        &self,
+        sticky_task_queue: TaskQueueName,
+    ) -> Result<ShutdownWorkerResponse>;
+    /// Replace the underlying client with one using a freshly established channel
+    async fn reconnect(&self) -> Result<()>;
+    /// Abort any in-flight history fetches, namespace lookups, or sticky queue shutdown calls.
+    /// They will fail with a cancelled status.
+    fn cancel_outstanding_calls(&self);
 
     fn replace_client(&self, new_client: RetryClient<Client>);
     fn capabilities(&self) -> Option<Capabilities>;
     fn workers(&self) -> Arc<SlotManager>;
+    /// Number of connections to server the client's calls are spread over
+    fn connection_count(&self) -> usize;
     fn is_mock(&self) -> bool;
 }
 
@@ -160,86 +408,37 @@ impl WorkerClient for WorkerClientBag {
         &self,
         task_queue: TaskQueue,
     ) -> Result<PollWorkflowTaskQueueResponse> {
-        let request = PollWorkflowTaskQueueRequest {
-            namespace: self.namespace.clone(),
-            task_queue: Some(task_queue),
-            identity: self.identity.clone(),
-            binary_checksum: self.binary_checksum(),
-            worker_version_capabilities: self.worker_version_capabilities(),
-        };
-
-        Ok(self
-            .cloned_client()
-            .poll_workflow_task_queue(request)
-            .await?
-            .into_inner())
+        self.stamps
+            .poll_workflow_task(
+                &self.cloned_client(),
+                self.build_id_versioning(),
+                task_queue,
+            )
+            .await
     }
 
     async fn poll_activity_task(
         &self,
-        task_queue: String,
+        options: PollOptions,
         max_tasks_per_sec: Option<f64>,
     ) -> Result<PollActivityTaskQueueResponse> {
-        let request = PollActivityTaskQueueRequest {
-            namespace: self.namespace.clone(),
-            task_queue: Some(TaskQueue {
-                name: task_queue,
-                kind: TaskQueueKind::Normal as i32,
-                normal_name: "".to_string(),
-            }),
-            identity: self.identity.clone(),
-            task_queue_metadata: max_tasks_per_sec.map(|tps| TaskQueueMetadata {
-                max_tasks_per_second: Some(tps),
-            }),
-            worker_version_capabilities: self.worker_version_capabilities(),
-        };
-
-        Ok(self
-            .cloned_client()
-            .poll_activity_task_queue(request)
-            .await?
-            .into_inner())
+        self.stamps
+            .poll_activity_task(
+                &self.cloned_client(),
+                self.build_id_versioning(),
+                options,
+                max_tasks_per_sec,
+            )
+            .await
     }
 
     async fn complete_workflow_task(
         &self,
         request: WorkflowTaskCompletion,
     ) -> Result<RespondWorkflowTaskCompletedResponse> {
-        let request = RespondWorkflowTaskCompletedRequest {
-            task_token: request.task_token.into(),
-            commands: request.commands,
-            messages: request.messages,
-            identity: self.identity.clone(),
-            sticky_attributes: request.sticky_attributes,
-            return_new_workflow_task: request.return_new_workflow_task,
-            force_create_new_workflow_task: request.force_create_new_workflow_task,
-            worker_version_stamp: self.worker_version_stamp(),
-            binary_checksum: self.binary_checksum(),
-            query_results: request
-                .query_responses
-                .into_iter()
-                .map(|qr| {
-                    let (id, completed_type, query_result, error_message) = qr.into_components();
-                    (
-                        id,
-                        WorkflowQueryResult {
-                            result_type: completed_type as i32,
-                            answer: query_result,
-                            error_message,
-                        },
-                    )
-                })
-                .collect(),
-            namespace: self.namespace.clone(),
-            sdk_metadata: Some(request.sdk_metadata),
-            metering_metadata: Some(request.metering_metadata),
-            capabilities: None,
-        };
-        Ok(self
-            .cloned_client()
-            .respond_workflow_task_completed(request)
-            .await?
-            .into_inner())
+        self.stamps
+            .complete_workflow_task(&self.cloned_client(), self.build_id_versioning(), request)
+            .await
     }
 
     async fn complete_activity_task(
@@ -247,17 +446,14 @@ impl WorkerClient for WorkerClientBag {
         task_token: TaskToken,
         result: Option<Payloads>,
     ) -> Result<RespondActivityTaskCompletedResponse> {
-        Ok(self
-            .cloned_client()
-            .respond_activity_task_completed(RespondActivityTaskCompletedRequest {
-                task_token: task_token.0,
+        self.stamps
+            .complete_activity_task(
+                &self.cloned_client(),
+                self.build_id_versioning(),
+                task_token,
                 result,
-                identity: self.identity.clone(),
-                namespace: self.namespace.clone(),
-                worker_version: self.worker_version_stamp(),
-            })
-            .await?
-            .into_inner())
+            )
+            .await
     }
 
     async fn record_activity_heartbeat(
@@ -265,16 +461,9 @@ impl WorkerClient for WorkerClientBag {
         task_token: TaskToken,
         details: Option<Payloads>,
     ) -> Result<RecordActivityTaskHeartbeatResponse> {
-        Ok(self
-            .cloned_client()
-            .record_activity_task_heartbeat(RecordActivityTaskHeartbeatRequest {
-                task_token: task_token.0,
-                details,
-                identity: self.identity.clone(),
-                namespace: self.namespace.clone(),
-            })
-            .await?
-            .into_inner())
+        self.stamps
+            .record_activity_heartbeat(&self.cloned_client(), task_token, details)
+            .await
     }
 
     async fn cancel_activity_task(
@@ -287,8 +476,8 @@ impl WorkerClient for WorkerClientBag {
             .respond_activity_task_canceled(RespondActivityTaskCanceledRequest {
                 task_token: task_token.0,
                 details,
-                identity: self.identity.clone(),
-                namespace: self.namespace.clone(),
+                identity: self.stamps.identity.clone(),
+                namespace: self.stamps.namespace.clone(),
                 worker_version: self.worker_version_stamp(),
             })
             .await?
@@ -305,8 +494,8 @@ impl WorkerClient for WorkerClientBag {
             .respond_activity_task_failed(RespondActivityTaskFailedRequest {
                 task_token: task_token.0,
                 failure,
-                identity: self.identity.clone(),
-                namespace: self.namespace.clone(),
+                identity: self.stamps.identity.clone(),
+                namespace: self.stamps.namespace.clone(),
                 // TODO: Implement - https://github.com/temporalio/sdk-core/issues/293
                 last_heartbeat_details: None,
                 worker_version: self.worker_version_stamp(),
@@ -325,9 +514,9 @@ impl WorkerClient for WorkerClientBag {
             task_token: task_token.0,
             cause: cause as i32,
             failure,
-            identity: self.identity.clone(),
+            identity: self.stamps.identity.clone(),
             binary_checksum: self.binary_checksum(),
-            namespace: self.namespace.clone(),
+            namespace: self.stamps.namespace.clone(),
             messages: vec![],
             worker_version: self.worker_version_stamp(),
         };
@@ -338,19 +527,32 @@ impl WorkerClient for WorkerClientBag {
             .into_inner())
     }
 
+    async fn reset_sticky_task_queue(
+        &self,
+        workflow_id: WorkflowId,
+        run_id: RunId,
+    ) -> Result<ResetStickyTaskQueueResponse> {
+        temporal_client::WorkflowClientTrait::reset_sticky_task_queue(
+            &self.cloned_client(),
+            workflow_id.into(),
+            run_id.into(),
+        )
+        .await
+    }
+
     async fn get_workflow_execution_history(
         &self,
-        workflow_id: String,
-        run_id: Option<String>,
+        workflow_id: WorkflowId,
+        run_id: Option<RunId>,
         page_token: Vec<u8>,
     ) -> Result<GetWorkflowExecutionHistoryResponse> {
         Ok(self
-            .cloned_client()
+            .cancellable_client()
             .get_workflow_execution_history(GetWorkflowExecutionHistoryRequest {
-                namespace: self.namespace.clone(),
+                namespace: self.stamps.namespace.clone(),
                 execution: Some(WorkflowExecution {
-                    workflow_id,
-                    run_id: run_id.unwrap_or_default(),
+                    workflow_id: workflow_id.into(),
+                    run_id: run_id.map(Into::into).unwrap_or_default(),
                 }),
                 next_page_token: page_token,
                 ..Default::default()
@@ -372,7 +574,7 @@ impl WorkerClient for WorkerClientBag {
                 completed_type: completed_type as i32,
                 query_result,
                 error_message,
-                namespace: self.namespace.clone(),
+                namespace: self.stamps.namespace.clone(),
             })
             .await?
             .into_inner())
@@ -380,27 +582,60 @@ impl WorkerClient for WorkerClientBag {
 
     async fn describe_namespace(&self) -> Result<DescribeNamespaceResponse> {
         temporal_client::WorkflowClientTrait::describe_namespace(
-            &self.cloned_client(),
-            Namespace::Name(self.namespace.clone()),
+            &self.cancellable_client(),
+            Namespace::Name(self.stamps.namespace.clone()),
+        )
+        .await
+    }
+
+    async fn describe_task_queue(
+        &self,
+        task_queue: TaskQueueName,
+        task_queue_type: TaskQueueType,
+    ) -> Result<TaskQueueDescription> {
+        temporal_client::WorkflowClientTrait::describe_task_queue(
+            &self.cancellable_client(),
+            task_queue.into(),
+            task_queue_type,
         )
         .await
     }
 
-    async fn shutdown_worker(&self, sticky_task_queue: String) -> Result<ShutdownWorkerResponse> {
+    async fn shutdown_worker(
+        &self,
+        sticky_task_queue: TaskQueueName,
+    ) -> Result<ShutdownWorkerResponse> {
         let request = ShutdownWorkerRequest {
-            namespace: self.namespace.clone(),
-            identity: self.identity.clone(),
-            sticky_task_queue,
+            namespace: self.stamps.namespace.clone(),
+            identity: self.stamps.identity.clone(),
+            sticky_task_queue: sticky_task_queue.into(),
             reason: "graceful shutdown".to_string(),
         };
 
         Ok(
-            WorkflowService::shutdown_worker(&mut self.cloned_client(), request)
+            WorkflowService::shutdown_worker(&mut self.cancellable_client(), request)
                 .await?
                 .into_inner(),
         )
     }
 
+    async fn reconnect(&self) -> Result<()> {
+        let current = self.replaceable_client.read().clone();
+        let fresh = current
+            .get_client()
+            .reconnect()
+            .await
+            .map_err(|e| tonic::Status::unavailable(format!("Failed to reconnect: {e}")))?;
+        // Whatever retry configs or cancellation the current client was given (possibly by lang,
+        // through `replace_client`) must survive the reconnect
+        self.replace_client(current.wrapping(fresh));
+        Ok(())
+    }
+
+    fn cancel_outstanding_calls(&self) {
+        self.calls_cancel.cancel();
+    }
+
     fn replace_client(&self, new_client: RetryClient<Client>) {
         let mut replaceable_client = self.replaceable_client.write();
         *replaceable_client = new_client;
@@ -416,6 +651,11 @@ impl WorkerClient for WorkerClientBag {
         client.get_client().inner().workers()
     }
 
+    fn connection_count(&self) -> usize {
+        let client = self.replaceable_client.read();
+        client.get_client().inner().connection_count()
+    }
+
     fn is_mock(&self) -> bool {
         false
     }
@@ -444,3 +684,125 @@ pub(crate) struct WorkflowTaskCompletion {
     /// Metering info
     pub(crate) metering_metadata: MeteringMetadata,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_help::test_worker_cfg, worker_identity};
+
+    fn stamps(identity: &str) -> TaskStamps {
+        TaskStamps {
+            namespace: "default".to_string(),
+            identity: identity.to_string(),
+            worker_build_id: "test_bin_id".to_string(),
+            use_versioning: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn every_task_request_carries_identity_and_checksum() {
+        let config = test_worker_cfg()
+            .client_identity_override(Some("explicit-identity".to_string()))
+            .max_outstanding_workflow_tasks(1_usize)
+            .build()
+            .unwrap();
+        // The worker's override wins over the client's own identity
+        let identity = worker_identity(&config, "client-identity");
+        assert_eq!(identity, "explicit-identity");
+        let stamps = stamps(&identity);
+
+        let mut rpcs = MockTaskRpcs::new();
+        rpcs.expect_poll_workflow_task_queue()
+            .withf(|r| r.identity == "explicit-identity" && r.binary_checksum == "test_bin_id")
+            .times(1)
+            .returning(|_| Ok(Default::default()));
+        rpcs.expect_respond_workflow_task_completed()
+            .withf(|r| r.identity == "explicit-identity" && r.binary_checksum == "test_bin_id")
+            .times(1)
+            .returning(|_| Ok(Default::default()));
+        rpcs.expect_poll_activity_task_queue()
+            .withf(|r| r.identity == "explicit-identity")
+            .times(1)
+            .returning(|_| Ok(Default::default()));
+        rpcs.expect_record_activity_task_heartbeat()
+            .withf(|r| r.identity == "explicit-identity")
+            .times(1)
+            .returning(|_| Ok(Default::default()));
+        rpcs.expect_respond_activity_task_completed()
+            .withf(|r| r.identity == "explicit-identity")
+            .times(1)
+            .returning(|_| Ok(Default::default()));
+
+        stamps
+            .poll_workflow_task(&rpcs, false, TaskQueue::default())
+            .await
+            .unwrap();
+        stamps
+            .complete_workflow_task(
+                &rpcs,
+                false,
+                WorkflowTaskCompletion {
+                    task_token: TaskToken(vec![1]),
+                    commands: vec![],
+                    messages: vec![],
+                    sticky_attributes: None,
+                    query_responses: vec![],
+                    return_new_workflow_task: false,
+                    force_create_new_workflow_task: false,
+                    sdk_metadata: Default::default(),
+                    metering_metadata: Default::default(),
+                },
+            )
+            .await
+            .unwrap();
+        stamps
+            .poll_activity_task(&rpcs, false, PollOptions::normal("q".to_string()), None)
+            .await
+            .unwrap();
+        stamps
+            .record_activity_heartbeat(&rpcs, TaskToken(vec![1]), None)
+            .await
+            .unwrap();
+        stamps
+            .complete_activity_task(&rpcs, false, TaskToken(vec![1]), None)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn build_id_moves_from_checksum_to_version_when_server_versions_by_it() {
+        let mut rpcs = MockTaskRpcs::new();
+        rpcs.expect_poll_workflow_task_queue()
+            .withf(|r| {
+                r.binary_checksum.is_empty()
+                    && r.worker_version_capabilities
+                        .as_ref()
+                        .map(|c| c.build_id.as_str())
+                        == Some("test_bin_id")
+            })
+            .times(1)
+            .returning(|_| Ok(Default::default()));
+        stamps("ident")
+            .poll_workflow_task(&rpcs, true, TaskQueue::default())
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn identity_defaults_to_pid_at_host() {
+        let config = test_worker_cfg()
+            .max_outstanding_workflow_tasks(1_usize)
+            .build()
+            .unwrap();
+        let identity = worker_identity(&config, "");
+        let (pid, host) = identity.split_once('@').unwrap();
+        assert_eq!(pid, std::process::id().to_string());
+        assert!(!host.is_empty());
+
+        // An identity set on the client is kept when the worker doesn't override it
+        assert_eq!(
+            worker_identity(&config, "client-identity"),
+            "client-identity"
+        );
+    }
+}
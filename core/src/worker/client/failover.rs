@@ -0,0 +1,635 @@
+//! Riding out failovers of global namespaces. See
+//! [temporal_sdk_core_api::worker::NamespaceFailoverEvent].
+//!
+//! Server rejects calls made against a cluster the namespace isn't active on with a
+//! [NamespaceNotActiveFailure] attached to the error. The first call to see one pauses every call
+//! made through the client, reconnects so the endpoint is resolved again, and describes the
+//! namespace to learn where it is now active. Paused calls, and the ones which were rejected, are
+//! then issued again. Rejections of calls issued before the failover was handled don't start
+//! another one, since they're only reporting the same failover late.
+//!
+//! Issuing a call again needs a copy of its arguments, which for completions can be large. Once
+//! describing the namespace has shown it isn't global, and so can't fail over, calls give their
+//! arguments up on the first attempt instead.
+
+use super::{Result, WorkerClient, WorkflowTaskCompletion};
+use crate::pollers::PollOptions;
+use prost::Message;
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use temporal_client::{Client, RetryClient, SlotManager, TaskQueueDescription};
+use temporal_sdk_core_api::worker::{
+    NamespaceFailoverEvent, WorkerLifecycleEvent, WorkerLifecycleObserver,
+};
+use temporal_sdk_core_protos::{
+    coresdk::workflow_commands::QueryResult,
+    google::rpc::Status as RpcStatus,
+    temporal::api::{
+        common::v1::Payloads,
+        enums::v1::{TaskQueueType, WorkflowTaskFailedCause},
+        errordetails::v1::NamespaceNotActiveFailure,
+        failure::v1::Failure,
+        taskqueue::v1::TaskQueue,
+        workflowservice::v1::{get_system_info_response::Capabilities, *},
+    },
+    RunId, TaskQueueName, TaskToken, WorkflowId,
+};
+use tokio::sync::watch;
+
+/// How long to wait before reconnecting, giving whatever routes the endpoint (ex: DNS) time to
+/// point at the newly active cluster. Also the wait between attempts at describing the namespace.
+pub(crate) const FAILOVER_RESOLVE_INTERVAL: Duration = Duration::from_secs(1);
+/// Most attempts at describing the namespace after reconnecting. Calls resume even if all fail.
+const MAX_DESCRIBE_ATTEMPTS: usize = 5;
+/// Most times a call other than a poll is issued again after failovers. Polls are issued again as
+/// many times as it takes.
+const MAX_REISSUES: usize = 3;
+
+/// Wraps the client a worker uses to talk to server, pausing and re-issuing calls while the
+/// worker's namespace fails over to another cluster
+pub(crate) struct FailoverWorkerClient {
+    inner: Arc<dyn WorkerClient>,
+    observer: Option<WorkerLifecycleObserver>,
+    resolve_interval: Duration,
+    state: watch::Sender<FailoverState>,
+    /// Cleared when describing the namespace shows it isn't global, and set again by any failover
+    may_fail_over: AtomicBool,
+}
+
+#[derive(Clone, Copy, Default)]
+struct FailoverState {
+    /// Set while a failover is being handled, during which no calls are issued
+    in_progress: bool,
+    /// Number of failovers handled so far
+    handled: u64,
+}
+
+/// Resumes calls when dropped, so that a failover abandoned part way (ex: because the call which
+/// started handling it was dropped) doesn't leave every call paused
+struct ResumeOnDrop<'a>(&'a watch::Sender<FailoverState>);
+impl Drop for ResumeOnDrop<'_> {
+    fn drop(&mut self) {
+        self.0.send_modify(|s| {
+            s.in_progress = false;
+            s.handled += 1;
+        });
+    }
+}
+
+impl FailoverWorkerClient {
+    pub(crate) fn new(
+        inner: Arc<dyn WorkerClient>,
+        observer: Option<WorkerLifecycleObserver>,
+        resolve_interval: Duration,
+    ) -> Self {
+        Self {
+            inner,
+            observer,
+            resolve_interval,
+            state: watch::channel(FailoverState::default()).0,
+            may_fail_over: AtomicBool::new(true),
+        }
+    }
+
+    /// Issue `call` with `args`, and again after each failover it is rejected by, up to
+    /// `max_reissues` times. `args` are only copied for attempts which may need issuing again.
+    async fn call<A, T, Fut>(
+        &self,
+        max_reissues: Option<usize>,
+        args: A,
+        call: impl Fn(A) -> Fut,
+    ) -> Result<T>
+    where
+        A: Clone,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut args = Some(args);
+        let mut reissues = 0;
+        loop {
+            let issued_after = self.until_resumed().await;
+            let may_reissue = max_reissues.map_or(true, |max| reissues < max);
+            let attempt_args = if may_reissue && self.may_fail_over.load(Ordering::Relaxed) {
+                args.clone()
+            } else {
+                args.take()
+            }
+            .expect("Arguments are only given up by the last attempt");
+            match call(attempt_args).await {
+                Err(e) => match namespace_not_active(&e) {
+                    Some(failure) if may_reissue => {
+                        self.fail_over(failure, issued_after).await;
+                        if args.is_none() {
+                            // The namespace seemed unable to fail over, so this call can't be
+                            // issued again. Later ones will be.
+                            return Err(e);
+                        }
+                        reissues += 1;
+                    }
+                    _ => return Err(e),
+                },
+                r => return r,
+            }
+        }
+    }
+
+    /// Resolves once no failover is being handled, with the number handled so far
+    async fn until_resumed(&self) -> u64 {
+        let mut state = self.state.subscribe();
+        let resumed = *state
+            .wait_for(|s| !s.in_progress)
+            .await
+            .expect("Sender lives as long as the client");
+        resumed.handled
+    }
+
+    /// Handle a failover reported by a call issued once `issued_after` failovers were handled,
+    /// unless another call is already handling it (or already has)
+    async fn fail_over(&self, failure: NamespaceNotActiveFailure, issued_after: u64) {
+        self.may_fail_over.store(true, Ordering::Relaxed);
+        let claimed = self.state.send_if_modified(|s| {
+            if s.in_progress || s.handled != issued_after {
+                return false;
+            }
+            s.in_progress = true;
+            true
+        });
+        if !claimed {
+            return;
+        }
+        let _resume = ResumeOnDrop(&self.state);
+        warn!(
+            namespace = %failure.namespace,
+            current_cluster = %failure.current_cluster,
+            active_cluster = %failure.active_cluster,
+            "Namespace is not active on the cluster this worker is connected to. Pausing calls \
+             until the worker has reconnected."
+        );
+        self.emit(NamespaceFailoverEvent::Detected {
+            current_cluster: failure.current_cluster,
+            active_cluster: failure.active_cluster,
+        });
+
+        tokio::time::sleep(self.resolve_interval).await;
+        if let Err(e) = self.inner.reconnect().await {
+            warn!(error = ?e, "Failed to reconnect after namespace failover");
+        }
+        let mut active_cluster = String::new();
+        for attempt in 1..=MAX_DESCRIBE_ATTEMPTS {
+            match self.inner.describe_namespace().await {
+                Ok(resp) => {
+                    active_cluster = resp
+                        .replication_config
+                        .map(|c| c.active_cluster_name)
+                        .unwrap_or_default();
+                    break;
+                }
+                Err(e) => {
+                    debug!(attempt, error = ?e, "Could not describe namespace after failover");
+                    if attempt < MAX_DESCRIBE_ATTEMPTS {
+                        tokio::time::sleep(self.resolve_interval).await;
+                    }
+                }
+            }
+        }
+        info!(%active_cluster, "Resuming calls after namespace failover");
+        self.emit(NamespaceFailoverEvent::Resumed { active_cluster });
+    }
+
+    fn emit(&self, event: NamespaceFailoverEvent) {
+        if let Some(observer) = self.observer.as_ref() {
+            observer(&WorkerLifecycleEvent::NamespaceFailover(event));
+        }
+    }
+}
+
+/// Server attaches a [NamespaceNotActiveFailure] to the `google.rpc.Status` details of the error
+/// when a call is made against a cluster the namespace isn't active on
+fn namespace_not_active(status: &tonic::Status) -> Option<NamespaceNotActiveFailure> {
+    let details = RpcStatus::decode(status.details()).ok()?;
+    details
+        .details
+        .iter()
+        .filter(|any| {
+            any.type_url
+                .ends_with("temporal.api.errordetails.v1.NamespaceNotActiveFailure")
+        })
+        .find_map(|any| NamespaceNotActiveFailure::decode(any.value.as_slice()).ok())
+}
+
+#[async_trait::async_trait]
+impl WorkerClient for FailoverWorkerClient {
+    async fn poll_workflow_task(
+        &self,
+        task_queue: TaskQueue,
+    ) -> Result<PollWorkflowTaskQueueResponse> {
+        self.call(None, task_queue, |tq| self.inner.poll_workflow_task(tq))
+            .await
+    }
+
+    async fn poll_activity_task(
+        &self,
+        options: PollOptions,
+        max_tasks_per_sec: Option<f64>,
+    ) -> Result<PollActivityTaskQueueResponse> {
+        self.call(None, options, |opts| {
+            self.inner.poll_activity_task(opts, max_tasks_per_sec)
+        })
+        .await
+    }
+
+    async fn complete_workflow_task(
+        &self,
+        request: WorkflowTaskCompletion,
+    ) -> Result<RespondWorkflowTaskCompletedResponse> {
+        self.call(Some(MAX_REISSUES), request, |req| {
+            self.inner.complete_workflow_task(req)
+        })
+        .await
+    }
+
+    async fn complete_activity_task(
+        &self,
+        task_token: TaskToken,
+        result: Option<Payloads>,
+    ) -> Result<RespondActivityTaskCompletedResponse> {
+        self.call(
+            Some(MAX_REISSUES),
+            (task_token, result),
+            |(task_token, result)| self.inner.complete_activity_task(task_token, result),
+        )
+        .await
+    }
+
+    async fn record_activity_heartbeat(
+        &self,
+        task_token: TaskToken,
+        details: Option<Payloads>,
+    ) -> Result<RecordActivityTaskHeartbeatResponse> {
+        self.call(
+            Some(MAX_REISSUES),
+            (task_token, details),
+            |(task_token, details)| self.inner.record_activity_heartbeat(task_token, details),
+        )
+        .await
+    }
+
+    async fn cancel_activity_task(
+        &self,
+        task_token: TaskToken,
+        details: Option<Payloads>,
+    ) -> Result<RespondActivityTaskCanceledResponse> {
+        self.call(
+            Some(MAX_REISSUES),
+            (task_token, details),
+            |(task_token, details)| self.inner.cancel_activity_task(task_token, details),
+        )
+        .await
+    }
+
+    async fn fail_activity_task(
+        &self,
+        task_token: TaskToken,
+        failure: Option<Failure>,
+    ) -> Result<RespondActivityTaskFailedResponse> {
+        self.call(
+            Some(MAX_REISSUES),
+            (task_token, failure),
+            |(task_token, failure)| self.inner.fail_activity_task(task_token, failure),
+        )
+        .await
+    }
+
+    async fn fail_workflow_task(
+        &self,
+        task_token: TaskToken,
+        cause: WorkflowTaskFailedCause,
+        failure: Option<Failure>,
+    ) -> Result<RespondWorkflowTaskFailedResponse> {
+        self.call(
+            Some(MAX_REISSUES),
+            (task_token, failure),
+            |(task_token, failure)| self.inner.fail_workflow_task(task_token, cause, failure),
+        )
+        .await
+    }
+
+    async fn reset_sticky_task_queue(
+        &self,
+        workflow_id: WorkflowId,
+        run_id: RunId,
+    ) -> Result<ResetStickyTaskQueueResponse> {
+        self.call(
+            Some(MAX_REISSUES),
+            (workflow_id, run_id),
+            |(workflow_id, run_id)| self.inner.reset_sticky_task_queue(workflow_id, run_id),
+        )
+        .await
+    }
+
+    async fn get_workflow_execution_history(
+        &self,
+        workflow_id: WorkflowId,
+        run_id: Option<RunId>,
+        page_token: Vec<u8>,
+    ) -> Result<GetWorkflowExecutionHistoryResponse> {
+        self.call(
+            Some(MAX_REISSUES),
+            (workflow_id, run_id, page_token),
+            |(workflow_id, run_id, page_token)| {
+                self.inner
+                    .get_workflow_execution_history(workflow_id, run_id, page_token)
+            },
+        )
+        .await
+    }
+
+    async fn respond_legacy_query(
+        &self,
+        task_token: TaskToken,
+        query_result: QueryResult,
+    ) -> Result<RespondQueryTaskCompletedResponse> {
+        self.call(
+            Some(MAX_REISSUES),
+            (task_token, query_result),
+            |(task_token, query_result)| self.inner.respond_legacy_query(task_token, query_result),
+        )
+        .await
+    }
+
+    async fn describe_namespace(&self) -> Result<DescribeNamespaceResponse> {
+        // Served by passive clusters too, and used to handle failovers
+        let resp = self.inner.describe_namespace().await;
+        if let Ok(r) = &resp {
+            self.may_fail_over
+                .store(r.is_global_namespace, Ordering::Relaxed);
+        }
+        resp
+    }
+
+    async fn describe_task_queue(
+        &self,
+        task_queue: TaskQueueName,
+        task_queue_type: TaskQueueType,
+    ) -> Result<TaskQueueDescription> {
+        self.call(Some(MAX_REISSUES), task_queue, |tq| {
+            self.inner.describe_task_queue(tq, task_queue_type)
+        })
+        .await
+    }
+
+    async fn shutdown_worker(
+        &self,
+        sticky_task_queue: TaskQueueName,
+    ) -> Result<ShutdownWorkerResponse> {
+        self.call(Some(MAX_REISSUES), sticky_task_queue, |tq| {
+            self.inner.shutdown_worker(tq)
+        })
+        .await
+    }
+
+    async fn reconnect(&self) -> Result<()> {
+        self.inner.reconnect().await
+    }
+
+    fn cancel_outstanding_calls(&self) {
+        self.inner.cancel_outstanding_calls()
+    }
+
+    fn replace_client(&self, new_client: RetryClient<Client>) {
+        self.inner.replace_client(new_client)
+    }
+
+    fn capabilities(&self) -> Option<Capabilities> {
+        self.inner.capabilities()
+    }
+
+    fn workers(&self) -> Arc<SlotManager> {
+        self.inner.workers()
+    }
+
+    fn connection_count(&self) -> usize {
+        self.inner.connection_count()
+    }
+
+    fn is_mock(&self) -> bool {
+        self.inner.is_mock()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::worker::client::mocks::mock_workflow_client;
+    use parking_lot::Mutex;
+    use temporal_sdk_core_protos::{
+        temporal::api::replication::v1::NamespaceReplicationConfig, utilities::pack_any,
+    };
+    use tonic::Code;
+
+    fn not_active() -> tonic::Status {
+        let failure = NamespaceNotActiveFailure {
+            namespace: "ns".to_string(),
+            current_cluster: "east".to_string(),
+            active_cluster: "west".to_string(),
+        };
+        let details = RpcStatus {
+            code: Code::FailedPrecondition as i32,
+            message: "namespace not active".to_string(),
+            details: vec![pack_any(
+                "type.googleapis.com/temporal.api.errordetails.v1.NamespaceNotActiveFailure"
+                    .to_string(),
+                &failure,
+            )
+            .unwrap()],
+        };
+        tonic::Status::with_details(
+            Code::FailedPrecondition,
+            "namespace not active",
+            details.encode_to_vec().into(),
+        )
+    }
+
+    fn described_as_active_on(cluster: &'static str) -> Result<DescribeNamespaceResponse> {
+        Ok(DescribeNamespaceResponse {
+            replication_config: Some(NamespaceReplicationConfig {
+                active_cluster_name: cluster.to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+    }
+
+    fn recording_client(
+        inner: impl WorkerClient + 'static,
+    ) -> (
+        FailoverWorkerClient,
+        Arc<Mutex<Vec<NamespaceFailoverEvent>>>,
+    ) {
+        let events = Arc::new(Mutex::new(vec![]));
+        let events_c = events.clone();
+        let client = FailoverWorkerClient::new(
+            Arc::new(inner),
+            Some(Arc::new(move |e: &WorkerLifecycleEvent| {
+                if let WorkerLifecycleEvent::NamespaceFailover(e) = e {
+                    events_c.lock().push(e.clone())
+                }
+            })),
+            Duration::ZERO,
+        );
+        (client, events)
+    }
+
+    #[tokio::test]
+    async fn polls_resume_once_the_namespace_is_resolved_again() {
+        let mut mock_client = mock_workflow_client();
+        let mut calls = 0;
+        mock_client
+            .expect_poll_activity_task()
+            .times(2)
+            .returning(move |_, _| {
+                calls += 1;
+                if calls == 1 {
+                    Err(not_active())
+                } else {
+                    Ok(PollActivityTaskQueueResponse {
+                        task_token: vec![1],
+                        ..Default::default()
+                    })
+                }
+            });
+        mock_client.expect_reconnect().times(1).returning(|| Ok(()));
+        mock_client
+            .expect_describe_namespace()
+            .times(1)
+            .returning(|| described_as_active_on("west"));
+        let (client, events) = recording_client(mock_client);
+
+        let task = client
+            .poll_activity_task(PollOptions::normal("tq".to_string()), None)
+            .await
+            .unwrap();
+        assert_eq!(task.task_token, vec![1]);
+        assert_eq!(
+            *events.lock(),
+            [
+                NamespaceFailoverEvent::Detected {
+                    current_cluster: "east".to_string(),
+                    active_cluster: "west".to_string(),
+                },
+                NamespaceFailoverEvent::Resumed {
+                    active_cluster: "west".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn completions_are_sent_again_after_a_failover() {
+        let mut mock_client = mock_workflow_client();
+        let mut calls = 0;
+        mock_client
+            .expect_complete_activity_task()
+            .times(2)
+            .returning(move |_, _| {
+                calls += 1;
+                if calls == 1 {
+                    Err(not_active())
+                } else {
+                    Ok(Default::default())
+                }
+            });
+        // Describing fails for a while, which doesn't keep calls paused for good
+        let mut describes = 0;
+        mock_client
+            .expect_describe_namespace()
+            .times(2)
+            .returning(move || {
+                describes += 1;
+                if describes == 1 {
+                    Err(tonic::Status::unavailable("still failing over"))
+                } else {
+                    described_as_active_on("west")
+                }
+            });
+        mock_client.expect_reconnect().returning(|| Ok(()));
+        let (client, events) = recording_client(mock_client);
+
+        client
+            .complete_activity_task(TaskToken(vec![1]), None)
+            .await
+            .unwrap();
+        assert_eq!(events.lock().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn calls_in_namespaces_which_cant_fail_over_are_issued_once() {
+        let mut mock_client = mock_workflow_client();
+        mock_client
+            .expect_complete_activity_task()
+            .times(1)
+            .returning(|_, _| Err(not_active()));
+        mock_client
+            .expect_describe_namespace()
+            .times(2)
+            .returning(|| described_as_active_on("west"));
+        mock_client.expect_reconnect().times(1).returning(|| Ok(()));
+        let (client, events) = recording_client(mock_client);
+
+        assert!(
+            !client
+                .describe_namespace()
+                .await
+                .unwrap()
+                .is_global_namespace
+        );
+        // Server disagreeing is still handled as a failover, but the call can't be issued again
+        let err = client
+            .complete_activity_task(TaskToken(vec![1]), None)
+            .await
+            .unwrap_err();
+        assert!(namespace_not_active(&err).is_some());
+        assert_eq!(events.lock().len(), 2);
+        assert!(client.may_fail_over.load(Ordering::Relaxed));
+    }
+
+    #[tokio::test]
+    async fn other_errors_and_repeated_failovers_are_surfaced() {
+        let mut mock_client = mock_workflow_client();
+        mock_client
+            .expect_fail_activity_task()
+            .times(MAX_REISSUES + 1)
+            .returning(|_, _| Err(not_active()));
+        mock_client
+            .expect_record_activity_heartbeat()
+            .times(1)
+            .returning(|_, _| Err(tonic::Status::failed_precondition("something else")));
+        mock_client
+            .expect_reconnect()
+            .times(MAX_REISSUES)
+            .returning(|| Ok(()));
+        mock_client
+            .expect_describe_namespace()
+            .times(MAX_REISSUES)
+            .returning(|| described_as_active_on("west"));
+        let (client, events) = recording_client(mock_client);
+
+        let err = client
+            .fail_activity_task(TaskToken(vec![1]), None)
+            .await
+            .unwrap_err();
+        assert!(namespace_not_active(&err).is_some());
+        assert_eq!(events.lock().len(), 2 * MAX_REISSUES);
+        let err = client
+            .record_activity_heartbeat(TaskToken(vec![1]), None)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::FailedPrecondition);
+        assert_eq!(events.lock().len(), 2 * MAX_REISSUES);
+    }
+}
@@ -1,5 +1,5 @@
 use super::*;
-use futures_util::Future;
+use futures_util::{Future, FutureExt};
 use std::sync::Arc;
 use std::sync::LazyLock;
 use temporal_client::SlotManager;
@@ -32,6 +32,9 @@ pub(crate) fn mock_workflow_client() -> MockWorkerClient {
     r.expect_is_mock().returning(|| true);
     r.expect_shutdown_worker()
         .returning(|_| Ok(ShutdownWorkerResponse {}));
+    r.expect_reset_sticky_task_queue()
+        .returning(|_, _| Ok(ResetStickyTaskQueueResponse {}));
+    r.expect_cancel_outstanding_calls().returning(|| ());
     r
 }
 
@@ -43,6 +46,9 @@ pub(crate) fn mock_manual_workflow_client() -> MockManualWorkerClient {
     r.expect_workers()
         .returning(|| DEFAULT_WORKERS_REGISTRY.clone());
     r.expect_is_mock().returning(|| true);
+    r.expect_reset_sticky_task_queue()
+        .returning(|_, _| async { Ok(ResetStickyTaskQueueResponse {}) }.boxed());
+    r.expect_cancel_outstanding_calls().returning(|| ());
     r
 }
 
@@ -57,7 +63,7 @@ mockall::mock! {
             -> impl Future<Output = Result<PollWorkflowTaskQueueResponse>> + Send + 'b
             where 'a: 'b, Self: 'b;
 
-        fn poll_activity_task<'a, 'b>(&self, task_queue: String, max_tasks_per_sec: Option<f64>)
+        fn poll_activity_task<'a, 'b>(&self, options: PollOptions, max_tasks_per_sec: Option<f64>)
             -> impl Future<Output = Result<PollActivityTaskQueueResponse>> + Send + 'b
             where 'a: 'b, Self: 'b;
 
@@ -96,6 +102,13 @@ mockall::mock! {
         ) -> impl Future<Output = Result<RespondWorkflowTaskFailedResponse>> + Send + 'b
             where 'a: 'b, Self: 'b;
 
+        fn reset_sticky_task_queue<'a, 'b>(
+            &self,
+            workflow_id: WorkflowId,
+            run_id: RunId,
+        ) -> impl Future<Output = Result<ResetStickyTaskQueueResponse>> + Send + 'b
+            where 'a: 'b, Self: 'b;
+
         fn record_activity_heartbeat<'a, 'b>(
            &self,
            task_token: TaskToken,
@@ -105,8 +118,8 @@ mockall::mock! {
 
         fn get_workflow_execution_history<'a, 'b>(
             &self,
-            workflow_id: String,
-            run_id: Option<String>,
+            workflow_id: WorkflowId,
+            run_id: Option<RunId>,
             page_token: Vec<u8>
         ) -> impl Future<Output = Result<GetWorkflowExecutionHistoryResponse>> + Send + 'b
             where 'a: 'b, Self: 'b;
@@ -122,12 +135,25 @@ mockall::mock! {
           impl Future<Output = Result<DescribeNamespaceResponse>> + Send + 'b
           where 'a: 'b, Self: 'b;
 
-        fn shutdown_worker<'a, 'b>(&self, sticky_task_queue: String) -> impl Future<Output = Result<ShutdownWorkerResponse>> + Send + 'b
+        fn describe_task_queue<'a, 'b>(
+            &self,
+            task_queue: TaskQueueName,
+            task_queue_type: TaskQueueType,
+        ) -> impl Future<Output = Result<TaskQueueDescription>> + Send + 'b
             where 'a: 'b, Self: 'b;
 
+        fn shutdown_worker<'a, 'b>(&self, sticky_task_queue: TaskQueueName) -> impl Future<Output = Result<ShutdownWorkerResponse>> + Send + 'b
+            where 'a: 'b, Self: 'b;
+
+        fn reconnect<'a, 'b>(&self) -> impl Future<Output = Result<()>> + Send + 'b
+            where 'a: 'b, Self: 'b;
+
+        fn cancel_outstanding_calls(&self);
+
         fn replace_client(&self, new_client: RetryClient<Client>);
         fn capabilities(&self) -> Option<Capabilities>;
         fn workers(&self) -> Arc<SlotManager>;
+        fn connection_count(&self) -> usize;
         fn is_mock(&self) -> bool;
     }
 }
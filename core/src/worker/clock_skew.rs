@@ -0,0 +1,228 @@
+//! Tracks how far the local clock is from the server's. Deadlines for tasks are anchored to
+//! server timestamps, but measured against the local clock, so a skewed worker clock would
+//! otherwise make timeouts fire early or late.
+//!
+//! A task reaches us some time after server stamped it as started, so comparing the two clocks
+//! also measures how long the response took to arrive. As NTP does, we take that to be half the
+//! round trip time of a request to server, which we measure from task completions.
+
+use parking_lot::Mutex;
+use prost_types::Timestamp;
+use std::time::{Duration, SystemTime};
+
+/// Weight given to each new sample in the smoothed estimate
+const SMOOTHING_FACTOR: f64 = 0.2;
+/// Skew beyond which we warn, since it is large enough to noticeably distort deadlines
+const WARN_THRESHOLD_SECS: f64 = 5.0;
+
+/// The estimated difference between the local clock and the server's clock
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClockSkewEstimate {
+    /// How many seconds the local clock is ahead of the server's. Negative if it is behind. Since
+    /// it is measured from task delivery, it is corrected for network latency by subtracting half
+    /// the round trip time of recent completions, once any have been made. Any asymmetry between
+    /// the request and response legs is still included.
+    pub local_ahead_secs: f64,
+    /// Number of tasks the estimate was derived from
+    pub samples: u64,
+}
+
+/// Maintains a smoothed estimate of clock skew, from comparing the local time at which each task
+/// was received with the server's recorded start time for that task.
+#[derive(Default)]
+pub(crate) struct ClockSkewEstimator {
+    state: Mutex<EstimatorState>,
+}
+
+#[derive(Default)]
+struct EstimatorState {
+    estimate: Option<ClockSkewEstimate>,
+    /// Smoothed round trip time of requests to server, in seconds
+    round_trip_secs: Option<f64>,
+    /// Set once we've warned about the current skew, so we don't warn on every task
+    warned: bool,
+}
+
+impl ClockSkewEstimator {
+    /// Record a task which was just received, and which the server says started at `started`
+    pub(crate) fn observe_task_start(&self, started: Option<&Timestamp>) {
+        if let Some(started) = started.and_then(|t| SystemTime::try_from(*t).ok()) {
+            self.observe_at(SystemTime::now(), started);
+        }
+    }
+
+    /// Record how long a (non long-poll) request to server took, from sending it to receiving the
+    /// response
+    pub(crate) fn observe_round_trip(&self, round_trip: Duration) {
+        let sample = round_trip.as_secs_f64();
+        let mut state = self.state.lock();
+        state.round_trip_secs = Some(match state.round_trip_secs {
+            None => sample,
+            Some(rtt) => rtt + SMOOTHING_FACTOR * (sample - rtt),
+        });
+    }
+
+    pub(super) fn observe_at(&self, received: SystemTime, started: SystemTime) {
+        let mut state = self.state.lock();
+        // The task spent about half a round trip on its way to us after server stamped it
+        let sample = signed_secs_between(started, received)
+            - state.round_trip_secs.unwrap_or_default() / 2.0;
+        let estimate = match state.estimate {
+            None => ClockSkewEstimate {
+                local_ahead_secs: sample,
+                samples: 1,
+            },
+            Some(e) => ClockSkewEstimate {
+                local_ahead_secs: e.local_ahead_secs
+                    + SMOOTHING_FACTOR * (sample - e.local_ahead_secs),
+                samples: e.samples + 1,
+            },
+        };
+        state.estimate = Some(estimate);
+        let magnitude = estimate.local_ahead_secs.abs();
+        if magnitude > WARN_THRESHOLD_SECS && !state.warned {
+            state.warned = true;
+            warn!(
+                local_ahead_secs = estimate.local_ahead_secs,
+                "Local clock appears to be skewed from the server's clock. Deadlines will be \
+                 corrected for it, but the worker's clock should be synchronized."
+            );
+        } else if magnitude < WARN_THRESHOLD_SECS / 2.0 {
+            state.warned = false;
+        }
+    }
+
+    /// Returns the current estimate, if any tasks have been observed
+    pub(crate) fn estimate(&self) -> Option<ClockSkewEstimate> {
+        self.state.lock().estimate
+    }
+
+    /// Forget the current estimate, as when changing which server we talk to
+    pub(crate) fn reset(&self) {
+        *self.state.lock() = EstimatorState::default();
+    }
+
+    /// Translate a time read from the server's clock into the equivalent local time
+    pub(crate) fn to_local(&self, server_time: SystemTime) -> SystemTime {
+        let skew = self
+            .estimate()
+            .map(|e| e.local_ahead_secs)
+            .unwrap_or_default();
+        let offset = Duration::from_secs_f64(skew.abs());
+        if skew >= 0.0 {
+            server_time + offset
+        } else {
+            server_time - offset
+        }
+    }
+
+    /// How long ago, by the local clock, the server recorded `server_time`. Returns `None` if it
+    /// is in the future even after correcting for skew.
+    pub(crate) fn elapsed_since(&self, server_time: SystemTime) -> Option<Duration> {
+        self.elapsed_between(server_time, SystemTime::now())
+    }
+
+    fn elapsed_between(&self, server_time: SystemTime, now: SystemTime) -> Option<Duration> {
+        now.duration_since(self.to_local(server_time)).ok()
+    }
+}
+
+fn signed_secs_between(earlier: SystemTime, later: SystemTime) -> f64 {
+    match later.duration_since(earlier) {
+        Ok(d) => d.as_secs_f64(),
+        Err(e) => -e.duration().as_secs_f64(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server_time(secs: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000 + secs)
+    }
+
+    #[test]
+    fn no_samples_means_no_correction() {
+        let est = ClockSkewEstimator::default();
+        assert_eq!(est.estimate(), None);
+        assert_eq!(est.to_local(server_time(10)), server_time(10));
+    }
+
+    #[test]
+    fn local_clock_ahead_is_corrected() {
+        let est = ClockSkewEstimator::default();
+        // The local clock reads 30s later than the server's
+        est.observe_at(server_time(30), server_time(0));
+        assert_eq!(
+            est.estimate(),
+            Some(ClockSkewEstimate {
+                local_ahead_secs: 30.0,
+                samples: 1
+            })
+        );
+        assert_eq!(est.to_local(server_time(100)), server_time(130));
+        // A task which the server started 10s ago, by its clock, has been running 10s, not 40s
+        assert_eq!(
+            est.elapsed_between(server_time(100), server_time(140)),
+            Some(Duration::from_secs(10))
+        );
+    }
+
+    #[test]
+    fn local_clock_behind_is_corrected() {
+        let est = ClockSkewEstimator::default();
+        est.observe_at(server_time(0), server_time(20));
+        assert_eq!(est.estimate().unwrap().local_ahead_secs, -20.0);
+        // Uncorrected, this start time would appear to be in the future
+        assert_eq!(
+            est.elapsed_between(server_time(100), server_time(85)),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(est.elapsed_between(server_time(100), server_time(75)), None);
+    }
+
+    #[test]
+    fn estimate_is_smoothed() {
+        let est = ClockSkewEstimator::default();
+        est.observe_at(server_time(10), server_time(0));
+        // One outlier moves the estimate only part of the way
+        est.observe_at(server_time(60), server_time(0));
+        let e = est.estimate().unwrap();
+        assert_eq!(e.samples, 2);
+        assert!((e.local_ahead_secs - 20.0).abs() < 1e-9);
+        for _ in 0..50 {
+            est.observe_at(server_time(60), server_time(0));
+        }
+        assert!((est.estimate().unwrap().local_ahead_secs - 60.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn network_latency_is_taken_out_of_samples() {
+        let est = ClockSkewEstimator::default();
+        // Before any round trips have been timed, latency can't be told apart from skew
+        est.observe_at(server_time(31), server_time(0));
+        assert_eq!(est.estimate().unwrap().local_ahead_secs, 31.0);
+        est.reset();
+        est.observe_round_trip(Duration::from_secs(2));
+        est.observe_at(server_time(31), server_time(0));
+        assert_eq!(est.estimate().unwrap().local_ahead_secs, 30.0);
+        // With synchronized clocks, only the latency separates the two times
+        est.reset();
+        est.observe_round_trip(Duration::from_millis(400));
+        est.observe_at(server_time(0) + Duration::from_millis(200), server_time(0));
+        assert!(est.estimate().unwrap().local_ahead_secs.abs() < 1e-9);
+    }
+
+    #[test]
+    fn reset_clears_estimate() {
+        let est = ClockSkewEstimator::default();
+        est.observe_round_trip(Duration::from_secs(2));
+        est.observe_at(server_time(30), server_time(0));
+        est.reset();
+        assert_eq!(est.estimate(), None);
+        // Round trips to a different server may take a different time
+        est.observe_at(server_time(30), server_time(0));
+        assert_eq!(est.estimate().unwrap().local_ahead_secs, 30.0);
+    }
+}
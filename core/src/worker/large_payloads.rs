@@ -0,0 +1,240 @@
+//! Passing activity results too large for server through a [LargePayloadStore] instead. See
+//! [temporal_sdk_core_api::worker::WorkerConfig::large_payload_store].
+//!
+//! An oversized result is encoded whole (metadata included), stored under the SHA-256 digest of
+//! that encoding, and replaced with a reference payload carrying the digest. Before an activity
+//! resolution containing a reference is delivered to lang, the stored payload is fetched, checked
+//! against the digest, and put back in its place. History only ever records the reference, so
+//! replays resolve it the same way.
+
+use prost::Message;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use temporal_sdk_core_api::worker::{
+    LargePayloadStore, LargePayloadStoreError, LARGE_PAYLOAD_REFERENCE_ENCODING,
+};
+use temporal_sdk_core_protos::{
+    coresdk::{
+        activity_result::{self, activity_execution_result, activity_resolution},
+        workflow_activation::{workflow_activation_job, WorkflowActivation},
+    },
+    temporal::api::{
+        common::v1::Payload,
+        failure::v1::{failure::FailureInfo, ApplicationFailureInfo, Failure},
+    },
+};
+
+/// The application failure type of activities whose oversized result could not be stored
+pub(crate) const LARGE_PAYLOAD_STORE_FAILURE_TYPE: &str = "LargePayloadStoreFailed";
+
+/// Store the result of a successful activity completion, returning the completion with the result
+/// replaced by a reference to it. Other completions are returned as is.
+pub(crate) async fn offload_activity_result(
+    store: &dyn LargePayloadStore,
+    mut status: activity_execution_result::Status,
+) -> Result<activity_execution_result::Status, LargePayloadStoreError> {
+    if let activity_execution_result::Status::Completed(activity_result::Success {
+        result: Some(result),
+    }) = &mut status
+    {
+        *result = offload(store, result).await?;
+    }
+    Ok(status)
+}
+
+/// The retryable failure an activity result which could not be stored is replaced with
+pub(crate) fn store_failure(err: &LargePayloadStoreError) -> activity_execution_result::Status {
+    activity_execution_result::Status::Failed(activity_result::Failure {
+        failure: Some(Failure {
+            message: format!("Could not store oversized activity result: {err}"),
+            failure_info: Some(FailureInfo::ApplicationFailureInfo(
+                ApplicationFailureInfo {
+                    r#type: LARGE_PAYLOAD_STORE_FAILURE_TYPE.to_string(),
+                    ..Default::default()
+                },
+            )),
+            ..Default::default()
+        }),
+    })
+}
+
+/// Replace every reference in the activation's activity resolutions with the payload it refers to
+pub(crate) async fn resolve_references(
+    store: &dyn LargePayloadStore,
+    act: &mut WorkflowActivation,
+) -> Result<(), LargePayloadStoreError> {
+    for job in act.jobs.iter_mut() {
+        let Some(workflow_activation_job::Variant::ResolveActivity(r)) = job.variant.as_mut()
+        else {
+            continue;
+        };
+        if let Some(activity_resolution::Status::Completed(activity_result::Success {
+            result: Some(result),
+        })) = r.result.as_mut().and_then(|r| r.status.as_mut())
+        {
+            if let Some(digest) = reference_digest(result) {
+                *result = fetch(store, &digest).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn offload(
+    store: &dyn LargePayloadStore,
+    payload: &Payload,
+) -> Result<Payload, LargePayloadStoreError> {
+    let data = payload.encode_to_vec();
+    let digest = hex_digest(&data);
+    store.put(&digest, data).await?;
+    Ok(Payload {
+        metadata: HashMap::from([(
+            "encoding".to_string(),
+            LARGE_PAYLOAD_REFERENCE_ENCODING.as_bytes().to_vec(),
+        )]),
+        data: digest.into_bytes(),
+    })
+}
+
+async fn fetch(
+    store: &dyn LargePayloadStore,
+    digest: &str,
+) -> Result<Payload, LargePayloadStoreError> {
+    let data = store.get(digest).await?;
+    if hex_digest(&data) != digest {
+        return Err(
+            format!("Large payload store returned the wrong data for digest {digest}").into(),
+        );
+    }
+    Ok(Payload::decode(data.as_slice())?)
+}
+
+/// The digest a payload refers to, if it is a reference
+fn reference_digest(payload: &Payload) -> Option<String> {
+    if payload.metadata.get("encoding")?.as_slice() != LARGE_PAYLOAD_REFERENCE_ENCODING.as_bytes() {
+        return None;
+    }
+    String::from_utf8(payload.data.clone()).ok()
+}
+
+fn hex_digest(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_help::InMemoryStore;
+    use temporal_sdk_core_protos::coresdk::{
+        activity_result::ActivityResolution,
+        workflow_activation::{ResolveActivity, WorkflowActivationJob},
+        AsJsonPayloadExt,
+    };
+
+    fn resolution_activation(result: Payload) -> WorkflowActivation {
+        WorkflowActivation {
+            jobs: vec![WorkflowActivationJob {
+                variant: Some(
+                    ResolveActivity {
+                        seq: 1,
+                        result: Some(ActivityResolution {
+                            status: Some(activity_resolution::Status::Completed(
+                                activity_result::Success {
+                                    result: Some(result),
+                                },
+                            )),
+                        }),
+                        is_local: false,
+                    }
+                    .into(),
+                ),
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn resolved_result(act: &WorkflowActivation) -> &Payload {
+        match act.jobs[0].variant.as_ref() {
+            Some(workflow_activation_job::Variant::ResolveActivity(ResolveActivity {
+                result:
+                    Some(ActivityResolution {
+                        status:
+                            Some(activity_resolution::Status::Completed(activity_result::Success {
+                                result: Some(p),
+                            })),
+                    }),
+                ..
+            })) => p,
+            other => panic!("Unexpected job {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn offloaded_results_round_trip() {
+        let store = InMemoryStore::default();
+        let big = "x".repeat(10_000).as_json_payload().unwrap();
+        let status = offload_activity_result(
+            &store,
+            activity_execution_result::Status::Completed(activity_result::Success {
+                result: Some(big.clone()),
+            }),
+        )
+        .await
+        .unwrap();
+        let activity_execution_result::Status::Completed(activity_result::Success {
+            result: Some(reference),
+        }) = status
+        else {
+            panic!("Completion should still be a success");
+        };
+        assert!(reference.data.len() < 100);
+        let digest = reference_digest(&reference).unwrap();
+        assert_eq!(
+            store.entries.lock().get(&digest),
+            Some(&big.encode_to_vec())
+        );
+
+        let mut act = resolution_activation(reference);
+        resolve_references(&store, &mut act).await.unwrap();
+        assert_eq!(resolved_result(&act), &big);
+    }
+
+    #[tokio::test]
+    async fn ordinary_payloads_pass_through() {
+        let store = InMemoryStore::default();
+        let small = "hi".as_json_payload().unwrap();
+        let mut act = resolution_activation(small.clone());
+        resolve_references(&store, &mut act).await.unwrap();
+        assert_eq!(resolved_result(&act), &small);
+
+        let failed = store_failure(&"nope".into());
+        assert_eq!(
+            offload_activity_result(&store, failed.clone())
+                .await
+                .unwrap(),
+            failed
+        );
+        assert!(store.entries.lock().is_empty());
+    }
+
+    #[tokio::test]
+    async fn missing_or_tampered_entries_fail_resolution() {
+        let store = InMemoryStore::default();
+        let reference = offload(&store, &"big".as_json_payload().unwrap())
+            .await
+            .unwrap();
+        let digest = reference_digest(&reference).unwrap();
+        store
+            .entries
+            .lock()
+            .insert(digest, b"something else".to_vec());
+        let mut act = resolution_activation(reference);
+        resolve_references(&store, &mut act).await.unwrap_err();
+
+        store.entries.lock().clear();
+        resolve_references(&store, &mut act).await.unwrap_err();
+    }
+}
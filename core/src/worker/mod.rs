@@ -1,9 +1,13 @@
 mod activities;
 pub(crate) mod client;
+mod clock_skew;
+mod large_payloads;
+mod payload_limits;
 mod slot_provider;
 pub(crate) mod tuner;
 mod workflow;
 
+pub use clock_skew::ClockSkewEstimate;
 pub use temporal_sdk_core_api::worker::{WorkerConfig, WorkerConfigBuilder};
 pub use tuner::{
     FixedSizeSlotSupplier, RealSysInfo, ResourceBasedSlotsOptions,
@@ -15,32 +19,38 @@ pub(crate) use activities::{
     ExecutingLAId, LocalActRequest, LocalActivityExecutionResult, LocalActivityResolution,
     NewLocalAct,
 };
-pub(crate) use workflow::{wft_poller::new_wft_poller, LEGACY_QUERY_ID};
+pub(crate) use workflow::{wft_poller::new_wft_poller, CacheSnapshot, LEGACY_QUERY_ID};
+pub use workflow::{HotRuns, RunProcessingStats};
 
 use crate::{
-    abstractions::{dbg_panic, MeteredPermitDealer},
+    abstractions::{dbg_panic, MeteredPermitDealer, WorkerSlotUsage},
     errors::CompleteWfError,
     pollers::{
-        new_activity_task_buffer, new_workflow_task_buffer, BoxedActPoller, WorkflowTaskPoller,
+        new_activity_task_buffer, new_workflow_task_buffer, ActivityRateLimits, BoxedActPoller,
+        InvalidPollOptions, PollAuthFailures, PollOptions, PollStatsTracker, PollerScaler,
+        TaskQueuePollStats, WorkerPollStatsTrackers, WorkflowTaskPoller,
     },
     protosext::validate_activity_completion,
     telemetry::{
         metrics::{
             activity_poller, activity_worker_type, workflow_poller, workflow_sticky_poller,
-            workflow_worker_type, MetricsContext,
+            workflow_worker_type, MessageType, MetricsContext,
         },
         TelemetryInstance,
     },
     worker::{
         activities::{LACompleteAction, LocalActivityManager, NextPendingLAAction},
         client::WorkerClient,
-        workflow::{LAReqSink, LocalResolution, WorkflowBasics, Workflows},
+        payload_limits::PayloadSizeGuard,
+        workflow::{LAReqSink, LocalResolution, WorkflowBasics, Workflows, CACHE_SNAPSHOT_VERSION},
     },
     ActivityHeartbeat, CompleteActivityError, PollActivityError, PollWfError, WorkerTrait,
 };
 use activities::WorkerActivityTasks;
-use futures_util::{stream, StreamExt};
+use clock_skew::ClockSkewEstimator;
+use futures_util::{stream, FutureExt, StreamExt};
 use parking_lot::Mutex;
+use prost::Message;
 use slot_provider::SlotProvider;
 use std::{
     convert::TryInto,
@@ -55,13 +65,13 @@ use temporal_client::{ConfiguredClient, TemporalServiceClientWithMetrics, Worker
 use temporal_sdk_core_protos::{
     coresdk::{
         activity_result::activity_execution_result,
-        activity_task::ActivityTask,
+        activity_task::{self, ActivityTask},
         workflow_activation::{remove_from_cache::EvictionReason, WorkflowActivation},
         workflow_completion::WorkflowActivationCompletion,
         ActivityTaskCompletion,
     },
     temporal::api::{
-        enums::v1::TaskQueueKind,
+        enums::v1::{TaskQueueKind, TaskQueueType},
         taskqueue::v1::{StickyExecutionAttributes, TaskQueue},
         workflowservice::v1::get_system_info_response,
     },
@@ -70,11 +80,16 @@ use temporal_sdk_core_protos::{
 use tokio::sync::{mpsc::unbounded_channel, watch};
 use tokio_stream::wrappers::UnboundedReceiverStream;
 use tokio_util::sync::CancellationToken;
+use tracing::Span;
 
 use crate::{
     abstractions::PermitDealerContextData, telemetry::metrics::local_activity_worker_type,
 };
-use temporal_sdk_core_api::errors::WorkerValidationError;
+use temporal_sdk_core_api::{
+    errors::WorkerValidationError,
+    worker::{ActivitySlotKind, WorkflowSlotKind},
+    ActivityCompletionOutcome,
+};
 #[cfg(test)]
 use {
     crate::{
@@ -108,6 +123,38 @@ pub struct Worker {
     local_activities_complete: Arc<AtomicBool>,
     /// Used to track all permits have been released
     all_permits_tracker: tokio::sync::Mutex<AllPermitsTracker>,
+    /// Rolling poll outcome stats for each polled queue
+    poll_stats: WorkerPollStatsTrackers,
+    /// Estimated skew between the local clock and the server's, from received task start times
+    clock_skew: Arc<ClockSkewEstimator>,
+    /// Handles on the permit dealers for task slots, used to adjust slot targets at runtime
+    slot_dealers: SlotDealers,
+    /// Handles on the pollers of each polled queue, used to adjust poller counts at runtime
+    poller_scalers: PollerScalers,
+    /// Stops every poller when server keeps rejecting the worker's credentials
+    poll_auth_failures: Arc<PollAuthFailures>,
+    /// Set once the cache has been exported for another worker to take over, in which case the
+    /// sticky queue must be left alone at shutdown
+    handing_over: AtomicBool,
+    /// Records the sizes of activity payloads, and enforces the outbound limit
+    payload_sizes: PayloadSizeGuard,
+    /// An error hit while filling a batch of activity tasks, held back until the next batch poll
+    /// so that the tasks already gathered could be returned
+    batch_poll_error: Mutex<Option<PollActivityError>>,
+}
+
+struct SlotDealers {
+    workflow: MeteredPermitDealer<WorkflowSlotKind>,
+    activity: MeteredPermitDealer<ActivitySlotKind>,
+}
+
+/// Only set for queues which are really polled (ie: not mocked)
+#[derive(Default)]
+struct PollerScalers {
+    workflow: Option<PollerScaler>,
+    sticky_workflow: Option<PollerScaler>,
+    activity: Option<PollerScaler>,
+    activity_rate_limits: Option<ActivityRateLimits>,
 }
 
 struct AllPermitsTracker {
@@ -127,19 +174,35 @@ impl AllPermitsTracker {
 #[async_trait::async_trait]
 impl WorkerTrait for Worker {
     async fn validate(&self) -> Result<(), WorkerValidationError> {
-        self.verify_namespace_exists().await?;
+        self.verify_namespace().await?;
+        if self.config.warn_if_no_activity_pollers && self.config.no_remote_activities {
+            self.warn_if_no_activity_pollers().await;
+        }
         Ok(())
     }
 
     async fn poll_workflow_activation(&self) -> Result<WorkflowActivation, PollWfError> {
+        if self.config.activity_only {
+            return Err(PollWfError::WorkerConfiguredWithoutThisTaskType);
+        }
         self.next_workflow_activation().await
     }
 
-    #[instrument(skip(self))]
+    #[instrument(skip(self),
+        fields(activity_id, workflow_id, run_id, attempt, task_token,
+               task_queue=%self.config.task_queue))]
     async fn poll_activity_task(&self) -> Result<ActivityTask, PollActivityError> {
         loop {
             match self.activity_poll().await.transpose() {
-                Some(r) => break r,
+                Some(r) => {
+                    if let Ok(task) = &r {
+                        record_activity_span_fields(&Span::current(), task);
+                        self.payload_sizes
+                            .record_inbound(payload_limits::activity_task_payloads(task));
+                        self.record_activity_task_size(task);
+                    }
+                    break r;
+                }
                 None => {
                     tokio::task::yield_now().await;
                     continue;
@@ -148,6 +211,32 @@ impl WorkerTrait for Worker {
         }
     }
 
+    async fn poll_activity_tasks_batch(
+        &self,
+        max_tasks: usize,
+        max_wait: Duration,
+    ) -> Result<Vec<ActivityTask>, PollActivityError> {
+        if let Some(err) = self.batch_poll_error.lock().take() {
+            return Err(err);
+        }
+        let Ok(first) = tokio::time::timeout(max_wait, self.poll_activity_task()).await else {
+            return Ok(vec![]);
+        };
+        let mut tasks = vec![first?];
+        while tasks.len() < max_tasks {
+            // Polls are cancel-safe, so abandoning one which isn't immediately ready loses nothing
+            match self.poll_activity_task().now_or_never() {
+                Some(Ok(task)) => tasks.push(task),
+                Some(Err(err)) => {
+                    *self.batch_poll_error.lock() = Some(err);
+                    break;
+                }
+                None => break,
+            }
+        }
+        Ok(tasks)
+    }
+
     async fn complete_workflow_activation(
         &self,
         completion: WorkflowActivationCompletion,
@@ -158,8 +247,21 @@ impl WorkerTrait for Worker {
     async fn complete_activity_task(
         &self,
         completion: ActivityTaskCompletion,
-    ) -> Result<(), CompleteActivityError> {
+    ) -> Result<ActivityCompletionOutcome, CompleteActivityError> {
+        let size = completion.encoded_len();
         let task_token = TaskToken(completion.task_token);
+        if self
+            .payload_sizes
+            .record_message(MessageType::ActivityTaskCompletion, size)
+        {
+            warn!(
+                activity_type = %self
+                    .outstanding_activity_type(&task_token)
+                    .unwrap_or_default(),
+                size,
+                "Activity completion exceeds the message size warning threshold"
+            );
+        }
         let status = if let Some(s) = completion.result.and_then(|r| r.status) {
             s
         } else {
@@ -172,6 +274,18 @@ impl WorkerTrait for Worker {
         self.complete_activity(task_token, status).await
     }
 
+    async fn complete_activity_tasks_batch(
+        &self,
+        completions: Vec<ActivityTaskCompletion>,
+    ) -> Vec<Result<ActivityCompletionOutcome, CompleteActivityError>> {
+        futures_util::future::join_all(
+            completions
+                .into_iter()
+                .map(|c| self.complete_activity_task(c)),
+        )
+        .await
+    }
+
     fn record_activity_heartbeat(&self, details: ActivityHeartbeat) {
         self.record_heartbeat(details);
     }
@@ -255,6 +369,8 @@ impl Worker {
         let slot_provider = (*worker_key).and_then(|k| self.client.workers().unregister(k));
         self.client
             .replace_client(super::init_worker_client(&self.config, new_client));
+        // The new client may connect to a server with an entirely different clock
+        self.clock_skew.reset();
         *worker_key =
             slot_provider.and_then(|slot_provider| self.client.workers().register(slot_provider));
     }
@@ -286,10 +402,13 @@ impl Worker {
             .unwrap_or_else(|| Arc::new(TunerBuilder::from_config(&config).build()));
 
         metrics.worker_registered();
+        let payload_sizes = PayloadSizeGuard::new(&config, &metrics);
         if let Some(meter) = meter {
             tuner.attach_metrics(meter.clone());
         }
         let shutdown_token = CancellationToken::new();
+        // Activity-only workers have no use for a sticky queue
+        let sticky_queue_name = sticky_queue_name.filter(|_| !config.activity_only);
         let slot_context_data = Arc::new(PermitDealerContextData {
             task_queue: config.task_queue.clone(),
             worker_identity: config.client_identity_override.clone().unwrap_or_default(),
@@ -315,75 +434,135 @@ impl Worker {
             slot_context_data.clone(),
         );
         let act_permits = act_slots.get_extant_count_rcv();
+        let slot_dealers = SlotDealers {
+            workflow: wft_slots.clone(),
+            activity: act_slots.clone(),
+        };
         let (external_wft_tx, external_wft_rx) = unbounded_channel();
+        let mut poll_stats = WorkerPollStatsTrackers::default();
+        let mut poller_scalers = PollerScalers::default();
+        let clock_skew = Arc::new(ClockSkewEstimator::default());
+        let poll_auth_failures = Arc::new(PollAuthFailures::new(
+            config.namespace.clone(),
+            slot_context_data.worker_identity.clone(),
+            config.lifecycle_observer.clone(),
+            shutdown_token.clone(),
+        ));
         let (wft_stream, act_poller) = match task_pollers {
             TaskPollers::Real => {
-                let max_nonsticky_polls = if sticky_queue_name.is_some() {
-                    config.max_nonsticky_polls()
-                } else {
-                    config.max_concurrent_wft_polls
-                };
-                let max_sticky_polls = config.max_sticky_polls();
-                let wft_metrics = metrics.with_new_attrs([workflow_poller()]);
-                let wf_task_poll_buffer = new_workflow_task_buffer(
-                    client.clone(),
-                    TaskQueue {
-                        name: config.task_queue.clone(),
-                        kind: TaskQueueKind::Normal as i32,
-                        normal_name: "".to_string(),
-                    },
-                    max_nonsticky_polls,
-                    wft_slots.clone(),
-                    shutdown_token.child_token(),
-                    Some(move |np| {
-                        wft_metrics.record_num_pollers(np);
-                    }),
-                );
-                let sticky_queue_poller = sticky_queue_name.as_ref().map(|sqn| {
-                    let sticky_metrics = metrics.with_new_attrs([workflow_sticky_poller()]);
-                    new_workflow_task_buffer(
+                // Workflow pollers aren't built at all for activity-only workers
+                let wft_stream = (!config.activity_only).then(|| {
+                    let max_nonsticky_polls = if sticky_queue_name.is_some() {
+                        config.max_nonsticky_polls()
+                    } else {
+                        config.max_concurrent_wft_polls
+                    };
+                    let max_sticky_polls = config.max_sticky_polls();
+                    let wft_metrics = metrics.with_new_attrs([workflow_poller()]);
+                    let wft_poll_stats = Arc::new(PollStatsTracker::new(
+                        config.poll_stats_window,
+                        wft_metrics.clone(),
+                    ));
+                    poll_stats.workflow = Some(wft_poll_stats.clone());
+                    let wf_task_poll_buffer = new_workflow_task_buffer(
                         client.clone(),
                         TaskQueue {
-                            name: sqn.clone(),
-                            kind: TaskQueueKind::Sticky as i32,
-                            normal_name: config.task_queue.clone(),
+                            name: config.task_queue.clone(),
+                            kind: TaskQueueKind::Normal as i32,
+                            normal_name: "".to_string(),
                         },
-                        max_sticky_polls,
-                        wft_slots.clone().into_sticky(),
+                        max_nonsticky_polls,
+                        wft_slots.clone(),
                         shutdown_token.child_token(),
-                        Some(move |np| {
-                            sticky_metrics.record_num_pollers(np);
+                        Some({
+                            let wft_metrics = wft_metrics.clone();
+                            move |np| wft_metrics.record_num_pollers(np)
                         }),
-                    )
+                        Some(wft_poll_stats),
+                        wft_metrics,
+                        poll_auth_failures.clone(),
+                    );
+                    wf_task_poll_buffer.set_max_buffered_bytes(config.max_buffered_poll_bytes);
+                    poller_scalers.workflow = Some(wf_task_poll_buffer.scaler());
+                    let sticky_queue_poller = sticky_queue_name.as_ref().map(|sqn| {
+                        let sticky_metrics = metrics.with_new_attrs([workflow_sticky_poller()]);
+                        let sticky_poll_stats = Arc::new(PollStatsTracker::new(
+                            config.poll_stats_window,
+                            sticky_metrics.clone(),
+                        ));
+                        poll_stats.sticky_workflow = Some(sticky_poll_stats.clone());
+                        let buffer = new_workflow_task_buffer(
+                            client.clone(),
+                            TaskQueue {
+                                name: sqn.clone(),
+                                kind: TaskQueueKind::Sticky as i32,
+                                normal_name: config.task_queue.clone(),
+                            },
+                            max_sticky_polls,
+                            wft_slots.clone().into_sticky(),
+                            shutdown_token.child_token(),
+                            Some({
+                                let sticky_metrics = sticky_metrics.clone();
+                                move |np| sticky_metrics.record_num_pollers(np)
+                            }),
+                            Some(sticky_poll_stats),
+                            sticky_metrics,
+                            poll_auth_failures.clone(),
+                        );
+                        buffer.set_max_buffered_bytes(config.max_buffered_poll_bytes);
+                        poller_scalers.sticky_workflow = Some(buffer.scaler());
+                        buffer
+                    });
+                    let wf_task_poll_buffer = Box::new(WorkflowTaskPoller::new(
+                        wf_task_poll_buffer,
+                        sticky_queue_poller,
+                    ));
+                    new_wft_poller(wf_task_poll_buffer, metrics.clone(), clock_skew.clone())
                 });
                 let act_poll_buffer = if config.no_remote_activities {
                     None
                 } else {
                     let act_metrics = metrics.with_new_attrs([activity_poller()]);
+                    let act_poll_stats = Arc::new(PollStatsTracker::new(
+                        config.poll_stats_window,
+                        act_metrics.clone(),
+                    ));
+                    poll_stats.activity = Some(act_poll_stats.clone());
+                    let rate_limits = ActivityRateLimits::new(
+                        config.max_task_queue_activities_per_second,
+                        config.max_worker_activities_per_second,
+                    );
                     let ap = new_activity_task_buffer(
                         client.clone(),
-                        config.task_queue.clone(),
+                        activity_poll_options(&config)
+                            .expect("Activity poll options are checked before creating workers"),
                         config.max_concurrent_at_polls,
                         act_slots.clone(),
-                        config.max_task_queue_activities_per_second,
+                        rate_limits.clone(),
                         shutdown_token.child_token(),
-                        Some(move |np| act_metrics.record_num_pollers(np)),
-                        config.max_worker_activities_per_second,
+                        Some({
+                            let act_metrics = act_metrics.clone();
+                            move |np| act_metrics.record_num_pollers(np)
+                        }),
+                        Some(act_poll_stats),
+                        act_metrics,
+                        poll_auth_failures.clone(),
                     );
+                    ap.set_max_buffered_bytes(config.max_buffered_poll_bytes);
+                    poller_scalers.activity = Some(ap.scaler());
+                    poller_scalers.activity_rate_limits = Some(rate_limits);
                     Some(Box::from(ap) as BoxedActPoller)
                 };
-                let wf_task_poll_buffer = Box::new(WorkflowTaskPoller::new(
-                    wf_task_poll_buffer,
-                    sticky_queue_poller,
-                ));
-                let wft_stream = new_wft_poller(wf_task_poll_buffer, metrics.clone());
-                let wft_stream = if !client.is_mock() {
+                let wft_stream = match wft_stream {
                     // Some replay tests combine a mock client with real pollers,
                     // and they don't need to use the external stream
-                    stream::select(wft_stream, UnboundedReceiverStream::new(external_wft_rx))
-                        .left_stream()
-                } else {
-                    wft_stream.right_stream()
+                    Some(wft_stream) if !client.is_mock() => {
+                        stream::select(wft_stream, UnboundedReceiverStream::new(external_wft_rx))
+                            .left_stream()
+                            .left_stream()
+                    }
+                    Some(wft_stream) => wft_stream.right_stream().left_stream(),
+                    None => stream::empty().right_stream(),
                 };
 
                 #[cfg(test)]
@@ -402,7 +581,7 @@ impl Worker {
                     let wft_semaphore = wft_semaphore.clone();
                     async move {
                         let permit = wft_semaphore.acquire_owned().await;
-                        s.map(|s| (s, permit))
+                        s.map(|s| (s, permit)).map_err(Into::into)
                     }
                 });
                 let wfs = wfs.right_stream();
@@ -432,8 +611,11 @@ impl Worker {
                 metrics.clone(),
                 config.max_heartbeat_throttle_interval,
                 config.default_heartbeat_throttle_interval,
+                config.heartbeat_failures_before_cancel,
                 config.graceful_shutdown_period,
                 config.local_timeout_buffer_for_activities,
+                config.slow_activity_log_threshold,
+                clock_skew.clone(),
             )
         });
         let poll_on_non_local_activities = at_task_mgr.is_some();
@@ -447,7 +629,12 @@ impl Worker {
             wft_slots.clone(),
             external_wft_tx,
         );
-        let worker_key = Mutex::new(client.workers().register(Box::new(provider)));
+        // Eager workflow tasks must not be handed to a worker which can't process them
+        let worker_key = Mutex::new(if config.activity_only {
+            None
+        } else {
+            client.workers().register(Box::new(provider))
+        });
         Self {
             worker_key,
             client: client.clone(),
@@ -457,6 +644,8 @@ impl Worker {
                     metrics,
                     shutdown_token.child_token(),
                     client.capabilities().unwrap_or_default(),
+                    poll_auth_failures.clone(),
+                    clock_skew.clone(),
                 ),
                 sticky_queue_name.map(|sq| StickyExecutionAttributes {
                     worker_task_queue: Some(TaskQueue {
@@ -480,6 +669,7 @@ impl Worker {
                 at_task_mgr.as_ref().and_then(|mgr| {
                     match config.max_task_queue_activities_per_second {
                         Some(persec) if persec > 0.0 => None,
+                        _ if config.max_eager_activities_per_workflow_task == 0 => None,
                         _ => Some(mgr.get_handle_for_workflows()),
                     }
                 }),
@@ -498,6 +688,14 @@ impl Worker {
                 act_permits,
                 la_permits,
             }),
+            poll_stats,
+            clock_skew,
+            slot_dealers,
+            poller_scalers,
+            poll_auth_failures,
+            handing_over: Default::default(),
+            payload_sizes,
+            batch_poll_error: Default::default(),
         }
     }
 
@@ -505,13 +703,36 @@ impl Worker {
     /// completed
     async fn shutdown(&self) {
         self.initiate_shutdown();
-        if let Some(name) = self.workflows.get_sticky_queue_name() {
+        let drain = self.drain_for_shutdown();
+        let Some(grace_period) = self.config.graceful_shutdown_period else {
+            return drain.await;
+        };
+        tokio::pin!(drain);
+        tokio::select! {
+            _ = &mut drain => {}
+            _ = tokio::time::sleep(grace_period) => {
+                // Past this point client calls (ex: history fetches) are only holding up shutdown
+                self.client.cancel_outstanding_calls();
+                drain.await;
+            }
+        }
+    }
+
+    /// Waits for everything outstanding in the worker to finish during shutdown
+    async fn drain_for_shutdown(&self) {
+        let sticky_queue = self
+            .workflows
+            .get_sticky_queue_name()
+            .filter(|_| !self.handing_over.load(Ordering::Acquire));
+        if let Some(name) = sticky_queue {
             // This is a best effort call and we can still shutdown the worker if it fails
-            match self.client.shutdown_worker(name).await {
+            match self.client.shutdown_worker(name.into()).await {
                 Err(err)
                     if !matches!(
                         err.code(),
-                        tonic::Code::Unimplemented | tonic::Code::Unavailable
+                        tonic::Code::Unimplemented
+                            | tonic::Code::Unavailable
+                            | tonic::Code::Cancelled
                     ) =>
                 {
                     warn!("Failed to shutdown sticky queue  {:?}", err);
@@ -550,10 +771,157 @@ impl Worker {
         }
     }
 
+    /// Serialize this worker's workflow cache, so that a worker started in its place (see
+    /// [crate::init_worker_with_cache_snapshot]) can pick up the cached runs without replaying
+    /// them from scratch. Should be called just before shutting down, once no more tasks are being
+    /// polled. The sticky queue is then left for the new worker to poll rather than shut down.
+    ///
+    /// Runs' full histories are only included if [WorkerConfig::retain_history_for_handover] is
+    /// set. Otherwise the new worker still polls the same sticky queue, but must fetch history.
+    pub async fn export_cache_snapshot(&self) -> Vec<u8> {
+        self.handing_over.store(true, Ordering::Release);
+        CacheSnapshot {
+            version: CACHE_SNAPSHOT_VERSION,
+            namespace: self.config.namespace.clone(),
+            task_queue: self.config.task_queue.clone(),
+            sticky_queue: self.workflows.get_sticky_queue_name(),
+            runs: self.workflows.export_run_snapshots().await,
+            checksum: vec![],
+        }
+        .encode_with_checksum()
+    }
+
+    /// Take over the runs in a snapshot exported by another worker. Must be called before polling.
+    pub(crate) fn import_cache_snapshot(&self, snapshot: CacheSnapshot) {
+        self.workflows.import_run_snapshots(snapshot.runs);
+    }
+
     pub(crate) fn shutdown_token(&self) -> CancellationToken {
         self.shutdown_token.clone()
     }
 
+    /// Returns counts of poll outcomes for `task_queue`, over the rolling window configured by
+    /// [WorkerConfig::poll_stats_window]. Useful for external poller autoscaling. The queue may be
+    /// the worker's normal task queue or its sticky queue. Returns `None` for any other queue.
+    pub fn poll_stats(&self, task_queue: &str) -> Option<TaskQueuePollStats> {
+        if task_queue == self.config.task_queue {
+            Some(self.poll_stats.normal_queue_stats())
+        } else if self.workflows.get_sticky_queue_name().as_deref() == Some(task_queue) {
+            Some(self.poll_stats.sticky_queue_stats())
+        } else {
+            None
+        }
+    }
+
+    /// Restart polling after it stopped because server kept rejecting the worker's credentials,
+    /// see [PollWfError::AuthFailure]. Credentials should be refreshed first, ex: with
+    /// [Self::replace_client], or polling will just stop again. Does nothing if polling hasn't
+    /// stopped.
+    pub fn resume_polling(&self) {
+        self.poll_auth_failures.resume();
+    }
+
+    /// Returns the estimated skew between this worker's clock and the server's, which is applied
+    /// when computing deadlines for tasks. `None` until the worker has received a task.
+    pub fn clock_skew(&self) -> Option<ClockSkewEstimate> {
+        self.clock_skew.estimate()
+    }
+
+    /// Limit the number of workflow task slots which may be in use at once to `target`, or remove
+    /// any such limit with `None`. Lowering the target below current usage never revokes
+    /// outstanding work: new workflow tasks are withheld until completions bring usage under the
+    /// target. Raising it takes effect immediately. The target cannot raise the limit above what
+    /// the worker's slot supplier allows.
+    pub fn set_workflow_slot_target(&self, target: Option<usize>) {
+        self.slot_dealers.workflow.set_slot_target(target);
+    }
+
+    /// Like [Self::set_workflow_slot_target], but for activity task slots
+    pub fn set_activity_slot_target(&self, target: Option<usize>) {
+        self.slot_dealers.activity.set_slot_target(target);
+    }
+
+    /// Change the number of concurrent workflow task polls, initially
+    /// [WorkerConfig::max_concurrent_wft_polls], while the worker runs. They are split between the
+    /// normal and sticky queues in the configured ratio. Lowering the number never interrupts
+    /// polls which are already in flight: surplus pollers exit when their current poll completes.
+    pub fn set_workflow_task_pollers(&self, num_pollers: usize) {
+        let Some(normal) = self.poller_scalers.workflow.as_ref() else {
+            return;
+        };
+        if let Some(sticky) = self.poller_scalers.sticky_workflow.as_ref() {
+            let mut split = self.config.clone();
+            split.max_concurrent_wft_polls = num_pollers;
+            normal.set_num_pollers(split.max_nonsticky_polls());
+            sticky.set_num_pollers(split.max_sticky_polls());
+        } else {
+            normal.set_num_pollers(num_pollers);
+        }
+    }
+
+    /// Like [Self::set_workflow_task_pollers], but for activity task polls, initially
+    /// [WorkerConfig::max_concurrent_at_polls]
+    pub fn set_activity_task_pollers(&self, num_pollers: usize) {
+        if let Some(scaler) = self.poller_scalers.activity.as_ref() {
+            scaler.set_num_pollers(num_pollers);
+        }
+    }
+
+    /// Change [WorkerConfig::max_worker_activities_per_second] while the worker runs, or remove
+    /// the limit with `None`. Pollers already waiting on the old limit are held to the new one.
+    /// Rates which aren't positive are ignored.
+    pub fn set_max_worker_activities_per_second(&self, per_second: Option<f64>) {
+        if let Some(limits) = self.valid_activity_rate_limits(per_second) {
+            limits.set_worker_per_second(per_second);
+        }
+    }
+
+    /// Change [WorkerConfig::max_task_queue_activities_per_second] while the worker runs, or
+    /// remove the limit with `None`. Server is told the rate with each poll, so the change applies
+    /// from the next one. Whether eager activity execution is used is still decided by the
+    /// initial configuration. Rates which aren't positive are ignored.
+    pub fn set_max_task_queue_activities_per_second(&self, per_second: Option<f64>) {
+        if let Some(limits) = self.valid_activity_rate_limits(per_second) {
+            limits.set_task_queue_per_second(per_second);
+        }
+    }
+
+    fn valid_activity_rate_limits(&self, per_second: Option<f64>) -> Option<&ActivityRateLimits> {
+        if per_second.is_some_and(|ps| !(ps.is_finite() && ps > 0.0)) {
+            warn!(
+                per_second,
+                "Ignoring activity rate limit which isn't positive"
+            );
+            return None;
+        }
+        self.poller_scalers.activity_rate_limits.as_ref()
+    }
+
+    /// Returns the number of connections to server this worker's client spreads its calls over.
+    /// If the client was made with `ClientOptions::connect_shared`, they are shared with other
+    /// workers.
+    pub fn server_connection_count(&self) -> usize {
+        self.client.connection_count()
+    }
+
+    /// Returns the current slot targets and usage for each type of task this worker processes
+    pub fn slot_usage(&self) -> WorkerSlotUsage {
+        WorkerSlotUsage {
+            workflow: self.slot_dealers.workflow.slot_usage(),
+            activity: self
+                .at_task_mgr
+                .as_ref()
+                .map(|_| self.slot_dealers.activity.slot_usage()),
+        }
+    }
+
+    /// Returns up to `k` of this worker's cached runs which have needed the most processing, by
+    /// each of the measures in [RunProcessingStats]. Useful for finding the run responsible when
+    /// one is hogging the worker. A run's counts start from zero each time it enters the cache.
+    pub fn hot_runs(&self, k: usize) -> HotRuns {
+        self.workflows.hot_runs(k)
+    }
+
     /// Returns number of currently cached workflows
     pub async fn cached_workflows(&self) -> usize {
         self.workflows
@@ -563,6 +931,16 @@ impl Worker {
             .unwrap_or_default()
     }
 
+    /// Returns the approximate number of bytes retained by currently cached workflows, which
+    /// [WorkerConfig::max_cache_bytes] bounds
+    pub async fn cached_workflow_bytes(&self) -> usize {
+        self.workflows
+            .get_state_info()
+            .await
+            .map(|r| r.cached_workflow_bytes)
+            .unwrap_or_default()
+    }
+
     /// Returns number of currently outstanding workflow tasks
     #[cfg(test)]
     pub(crate) async fn outstanding_workflow_tasks(&self) -> usize {
@@ -594,6 +972,9 @@ impl Worker {
         if local_activities_complete && non_local_activities_complete {
             return Err(PollActivityError::ShutDown);
         }
+        if let Some(status) = self.poll_auth_failures.stopped_at() {
+            return Err(PollActivityError::AuthFailure(status));
+        }
         let act_mgr_poll = async {
             if non_local_activities_complete {
                 future::pending::<()>().await;
@@ -636,14 +1017,29 @@ impl Worker {
             }
         };
 
+        let saturated = async {
+            match (self.config.poll_saturation_timeout, &self.at_task_mgr) {
+                (Some(wait), Some(_)) => {
+                    let (outstanding, limit) =
+                        self.slot_dealers.activity.saturated_after(wait).await;
+                    Err(PollActivityError::WorkerSaturated { outstanding, limit })
+                }
+                _ => future::pending().await,
+            }
+        };
+
         let r = tokio::select! {
             biased;
 
             r = local_activities_poll => r,
             r = act_mgr_poll => r,
+            r = saturated => r,
+            status = self.poll_auth_failures.stopped() => {
+                Err(PollActivityError::AuthFailure(status))
+            }
         };
-        // Since we consider network errors (at this level) fatal, we want to start shutdown if one
-        // is encountered
+        // Since we consider non-retryable network errors (at this level) fatal, we want to start
+        // shutdown if one is encountered
         if matches!(r, Err(PollActivityError::TonicError(_))) {
             self.initiate_shutdown();
         }
@@ -661,38 +1057,75 @@ impl Worker {
     }
 
     #[instrument(skip(self, task_token, status),
-        fields(task_token=%&task_token, status=%&status,
+        fields(task_token=%task_token.hashed(), status=%&status,
                task_queue=%self.config.task_queue, workflow_id, run_id))]
     pub(crate) async fn complete_activity(
         &self,
         task_token: TaskToken,
         status: activity_execution_result::Status,
-    ) -> Result<(), CompleteActivityError> {
+    ) -> Result<ActivityCompletionOutcome, CompleteActivityError> {
         validate_activity_completion(&status)?;
+        let mut status = status;
+        if let Err(oversized) = self
+            .payload_sizes
+            .check_outbound(payload_limits::activity_result_payload(&status))
+        {
+            status = if let Some(store) = self.config.large_payload_store.as_deref() {
+                large_payloads::offload_activity_result(store, status)
+                    .await
+                    .unwrap_or_else(|e| {
+                        warn!(error = %e, "Could not store oversized activity result");
+                        large_payloads::store_failure(&e)
+                    })
+            } else {
+                warn!(
+                    size = oversized.size,
+                    limit = oversized.limit,
+                    "Activity result payload exceeds the worker's limit, failing the activity"
+                );
+                oversized.as_activity_failure()
+            };
+        }
         if task_token.is_local_activity_task() {
             let as_la_res: LocalActivityExecutionResult = status.try_into()?;
             self.complete_local_act(task_token, as_la_res);
-            return Ok(());
+            return Ok(ActivityCompletionOutcome::Accepted);
         }
 
         if let Some(atm) = &self.at_task_mgr {
-            atm.complete(task_token, status, &*self.client).await;
+            atm.complete(task_token, status, &*self.client).await
         } else {
             error!(
                 "Tried to complete activity {} on a worker that does not have an activity manager",
                 task_token
             );
+            Ok(ActivityCompletionOutcome::Accepted)
         }
-        Ok(())
     }
 
-    #[instrument(skip(self), fields(run_id, workflow_id, task_queue=%self.config.task_queue))]
+    #[instrument(skip(self),
+        fields(run_id, workflow_id, correlation_id, attempt, task_token,
+               task_queue=%self.config.task_queue))]
     pub(crate) async fn next_workflow_activation(&self) -> Result<WorkflowActivation, PollWfError> {
+        if let Some(status) = self.poll_auth_failures.stopped_at() {
+            return Err(PollWfError::AuthFailure(status));
+        }
         let r = self.workflows.next_workflow_activation().await;
         // In the event workflows are shutdown or erroring, begin shutdown of everything else. Once
         // they are shut down, tell the local activity manager that, so that it can know to cancel
-        // any remaining outstanding LAs and shutdown.
+        // any remaining outstanding LAs and shutdown. Lang is expected to poll again after
+        // saturation, retryable errors, and malformed responses, so they aren't that kind of error.
+        // Nor are auth failures, which lang may recover from by refreshing credentials.
         if let Err(ref e) = r {
+            if matches!(
+                e,
+                PollWfError::WorkerSaturated { .. }
+                    | PollWfError::RetryableTonicError(_)
+                    | PollWfError::MalformedResponse { .. }
+                    | PollWfError::AuthFailure(_)
+            ) {
+                return r;
+            }
             // This is covering the situation where WFT pollers dying is the reason for shutdown
             self.initiate_shutdown();
             if matches!(e, PollWfError::ShutDown) {
@@ -703,8 +1136,8 @@ impl Worker {
     }
 
     #[instrument(skip(self, completion),
-        fields(completion=%&completion, run_id=%completion.run_id, workflow_id,
-               task_queue=%self.config.task_queue))]
+        fields(completion=%&completion, run_id=%completion.run_id, workflow_id, correlation_id,
+               attempt, task_token, task_queue=%self.config.task_queue))]
     pub(crate) async fn complete_workflow_activation(
         &self,
         completion: WorkflowActivationCompletion,
@@ -721,6 +1154,33 @@ impl Worker {
         Ok(())
     }
 
+    fn record_activity_task_size(&self, task: &ActivityTask) {
+        let size = task.encoded_len();
+        if !self
+            .payload_sizes
+            .record_message(MessageType::ActivityTask, size)
+        {
+            return;
+        }
+        let activity_type = match &task.variant {
+            Some(activity_task::Variant::Start(s)) => Some(s.activity_type.clone()),
+            _ => self.outstanding_activity_type(&TaskToken(task.task_token.clone())),
+        };
+        warn!(
+            activity_type = activity_type.as_deref().unwrap_or_default(),
+            size, "Activity task exceeds the message size warning threshold"
+        );
+    }
+
+    /// The type of an activity (local or not) which has been issued to lang but not yet completed
+    fn outstanding_activity_type(&self, task_token: &TaskToken) -> Option<String> {
+        if task_token.is_local_activity_task() {
+            self.local_act_mgr.activity_type(task_token)
+        } else {
+            self.at_task_mgr.as_ref()?.activity_type(task_token)
+        }
+    }
+
     /// Request a workflow eviction
     pub(crate) fn request_wf_eviction(
         &self,
@@ -767,11 +1227,26 @@ impl Worker {
         self.workflows.notify_of_local_result(run_id, res);
     }
 
-    async fn verify_namespace_exists(&self) -> Result<(), WorkerValidationError> {
-        if let Err(e) = self.client.describe_namespace().await {
+    /// Verify the namespace exists, and hasn't marked this worker's build id as a bad binary
+    async fn verify_namespace(&self) -> Result<(), WorkerValidationError> {
+        match self.client.describe_namespace().await {
+            Ok(resp) => {
+                let bad_binary = resp
+                    .config
+                    .and_then(|c| c.bad_binaries)
+                    .and_then(|mut bb| bb.binaries.remove(&self.config.worker_build_id));
+                if let Some(info) = bad_binary {
+                    return Err(WorkerValidationError::BadBinary {
+                        build_id: self.config.worker_build_id.clone(),
+                        namespace: self.config.namespace.clone(),
+                        reason: info.reason,
+                    });
+                }
+            }
             // Ignore if unimplemented since we wouldn't want to fail against an old server, for
             // example.
-            if e.code() != tonic::Code::Unimplemented {
+            Err(e) if e.code() == tonic::Code::Unimplemented => {}
+            Err(e) => {
                 return Err(WorkerValidationError::NamespaceDescribeError {
                     source: e,
                     namespace: self.config.namespace.clone(),
@@ -780,6 +1255,34 @@ impl Worker {
         }
         Ok(())
     }
+
+    /// Warn if nothing polls this worker's task queue for activity tasks. Returns true if it
+    /// warned. Failing to ask is not a validation failure, since the check is only advisory.
+    async fn warn_if_no_activity_pollers(&self) -> bool {
+        match self
+            .client
+            .describe_task_queue(
+                self.config.task_queue.as_str().into(),
+                TaskQueueType::Activity,
+            )
+            .await
+        {
+            Ok(desc) if desc.pollers.is_empty() => {
+                warn!(
+                    task_queue = %self.config.task_queue,
+                    "This worker doesn't poll for activity tasks, and server knows of no other \
+                     worker which does. Activities scheduled on this task queue will not run \
+                     until one starts."
+                );
+                true
+            }
+            Ok(_) => false,
+            Err(e) => {
+                debug!(error = ?e, "Could not describe task queue to check for activity pollers");
+                false
+            }
+        }
+    }
 }
 
 pub(crate) struct PostActivateHookData<'a> {
@@ -787,17 +1290,46 @@ pub(crate) struct PostActivateHookData<'a> {
     pub(crate) replaying: bool,
 }
 
+/// Where a worker with this config polls for activity tasks. Its own versioning capabilities are
+/// used, since the config has no others. Fails if no server could serve such polls.
+pub(crate) fn activity_poll_options(
+    config: &WorkerConfig,
+) -> Result<PollOptions, InvalidPollOptions> {
+    PollOptions::for_activities(config.task_queue.clone(), TaskQueueKind::Normal, None)
+}
+
 fn build_wf_basics(
     config: WorkerConfig,
     metrics: MetricsContext,
     shutdown_token: CancellationToken,
     server_capabilities: get_system_info_response::Capabilities,
+    poll_auth_failures: Arc<PollAuthFailures>,
+    clock_skew: Arc<ClockSkewEstimator>,
 ) -> WorkflowBasics {
     WorkflowBasics {
         worker_config: Arc::new(config),
         shutdown_token,
         metrics,
         server_capabilities,
+        run_stats: Default::default(),
+        poll_auth_failures,
+        clock_skew,
+    }
+}
+
+/// Tag a span with the details of an activity task just handed to lang
+fn record_activity_span_fields(span: &Span, task: &ActivityTask) {
+    span.record(
+        "task_token",
+        TaskToken(task.task_token.clone()).hashed().as_str(),
+    );
+    if let Some(activity_task::Variant::Start(start)) = &task.variant {
+        span.record("activity_id", start.activity_id.as_str());
+        span.record("attempt", start.attempt);
+        if let Some(we) = &start.workflow_execution {
+            span.record("workflow_id", we.workflow_id.as_str());
+            span.record("run_id", we.run_id.as_str());
+        }
     }
 }
 
@@ -814,11 +1346,29 @@ pub(crate) enum TaskPollers {
 mod tests {
     use super::*;
     use crate::{
-        advance_fut, test_help::test_worker_cfg, worker::client::mocks::mock_workflow_client,
+        advance_fut,
+        test_help::{test_worker_cfg, TEST_Q},
+        worker::client::mocks::mock_workflow_client,
     };
     use futures_util::FutureExt;
+    use std::collections::HashMap;
+    use temporal_client::{TaskQueueDescription, TaskQueuePoller};
+    use temporal_sdk_core_protos::temporal::api::{
+        namespace::v1::{BadBinaries, BadBinaryInfo, NamespaceConfig},
+        workflowservice::v1::{DescribeNamespaceResponse, PollActivityTaskQueueResponse},
+    };
 
-    use temporal_sdk_core_protos::temporal::api::workflowservice::v1::PollActivityTaskQueueResponse;
+    #[test]
+    fn activities_are_polled_from_the_normal_configured_queue() {
+        let opts = activity_poll_options(&test_worker_cfg().build().unwrap()).unwrap();
+        assert_eq!(opts.task_queue().name, TEST_Q);
+        assert_eq!(opts.task_queue().kind, TaskQueueKind::Normal as i32);
+        assert_eq!(opts.versioning(), None);
+        assert_eq!(
+            activity_poll_options(&test_worker_cfg().task_queue("").build().unwrap()).err(),
+            Some(InvalidPollOptions::EmptyTaskQueue)
+        );
+    }
 
     #[tokio::test]
     async fn activity_timeouts_maintain_permit() {
@@ -880,4 +1430,111 @@ mod tests {
             .build()
             .is_err());
     }
+
+    #[rstest::rstest]
+    #[case::other_binary_is_bad("some_other_bin_id", false)]
+    #[case::this_binary_is_bad("test_bin_id", true)]
+    #[tokio::test]
+    async fn validation_rejects_bad_binary(#[case] bad_binary: &'static str, #[case] is_err: bool) {
+        let mut mock_client = mock_workflow_client();
+        mock_client.expect_describe_namespace().returning(move || {
+            Ok(DescribeNamespaceResponse {
+                config: Some(NamespaceConfig {
+                    bad_binaries: Some(BadBinaries {
+                        binaries: HashMap::from([(
+                            bad_binary.to_string(),
+                            BadBinaryInfo {
+                                reason: "corrupts state".to_string(),
+                                ..Default::default()
+                            },
+                        )]),
+                    }),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+        });
+        let worker = Worker::new_test(test_worker_cfg().build().unwrap(), mock_client);
+        let res = worker.validate().await;
+        if is_err {
+            assert_matches!(
+                res,
+                Err(WorkerValidationError::BadBinary { build_id, reason, .. })
+                    if build_id == "test_bin_id" && reason == "corrupts state"
+            );
+        } else {
+            res.unwrap();
+        }
+    }
+
+    #[rstest::rstest]
+    #[case::enabled_without_activity_polling(true, true, 1)]
+    #[case::disabled(false, true, 0)]
+    #[case::worker_polls_activities_itself(true, false, 0)]
+    #[tokio::test]
+    async fn validation_checks_for_activity_pollers_only_if_asked(
+        #[case] warn_if_no_pollers: bool,
+        #[case] no_remote_activities: bool,
+        #[case] expected_describes: usize,
+    ) {
+        let mut mock_client = mock_workflow_client();
+        mock_client
+            .expect_describe_namespace()
+            .returning(|| Ok(Default::default()));
+        mock_client
+            .expect_describe_task_queue()
+            .withf(|tq, tq_type| tq == "q" && *tq_type == TaskQueueType::Activity)
+            .times(expected_describes)
+            .returning(|_, _| Ok(Default::default()));
+        let cfg = test_worker_cfg()
+            .task_queue("q")
+            .warn_if_no_activity_pollers(warn_if_no_pollers)
+            .no_remote_activities(no_remote_activities)
+            .build()
+            .unwrap();
+        Worker::new_test(cfg, mock_client).validate().await.unwrap();
+    }
+
+    #[rstest::rstest]
+    #[case::no_pollers(Ok(vec![]), true)]
+    #[case::other_worker_polls(Ok(vec!["activity-worker"]), false)]
+    #[case::describe_fails(Err(tonic::Code::PermissionDenied), false)]
+    #[tokio::test]
+    async fn warns_when_nothing_polls_for_activities(
+        #[case] pollers: Result<Vec<&'static str>, tonic::Code>,
+        #[case] warns: bool,
+    ) {
+        let mut mock_client = mock_workflow_client();
+        mock_client
+            .expect_describe_task_queue()
+            .returning(move |_, _| match &pollers {
+                Ok(identities) => Ok(TaskQueueDescription {
+                    pollers: identities
+                        .iter()
+                        .map(|i| TaskQueuePoller {
+                            identity: i.to_string(),
+                            ..Default::default()
+                        })
+                        .collect(),
+                    stats: None,
+                }),
+                Err(code) => Err(tonic::Status::new(*code, "nope")),
+            });
+        let cfg = test_worker_cfg()
+            .no_remote_activities(true)
+            .warn_if_no_activity_pollers(true)
+            .build()
+            .unwrap();
+        let worker = Worker::new_test(cfg, mock_client);
+        assert_eq!(worker.warn_if_no_activity_pollers().await, warns);
+    }
+
+    #[test]
+    fn activity_only_without_remote_activities_is_err() {
+        assert!(test_worker_cfg()
+            .activity_only(true)
+            .no_remote_activities(true)
+            .build()
+            .is_err());
+    }
 }
@@ -0,0 +1,177 @@
+//! Measuring, and optionally capping, the sizes of the payloads passing between lang and core.
+//! Every payload delivered to lang (inbound) or sent by it (outbound) is recorded in the
+//! `payload_size` histogram, which carries the worker's namespace. Outbound payloads larger than
+//! the worker's [WorkerConfig::outbound_payload_limit] are refused before anything is sent to
+//! server.
+//!
+//! The encoded sizes of the whole messages carrying those payloads (activations, activity tasks,
+//! and their completions) are recorded in the `message_size` histogram, tagged with the message
+//! type. Messages over [WorkerConfig::message_size_warn_threshold] are reported to the caller so
+//! it can log a warning naming the workflow or activity type.
+
+use crate::telemetry::metrics::{
+    message_type, payload_direction, MessageType, MetricsContext, PayloadDirection,
+};
+use prost::Message;
+use temporal_sdk_core_api::worker::WorkerConfig;
+use temporal_sdk_core_protos::{
+    coresdk::{
+        activity_result::{self, activity_execution_result, activity_resolution},
+        activity_task::{activity_task, ActivityTask},
+        child_workflow::child_workflow_result,
+        workflow_activation::{workflow_activation_job, WorkflowActivation},
+    },
+    temporal::api::{
+        common::v1::Payload,
+        failure::v1::{failure::FailureInfo, ApplicationFailureInfo, Failure},
+    },
+};
+
+/// A payload lang tried to send which is larger than its worker allows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct OversizedPayload {
+    pub(crate) size: usize,
+    pub(crate) limit: usize,
+}
+
+impl OversizedPayload {
+    /// The non-retryable failure an activity result containing this payload is replaced with
+    pub(crate) fn as_activity_failure(&self) -> activity_execution_result::Status {
+        let failure = Failure {
+            message: format!(
+                "Activity result payload of {} bytes exceeds the worker's limit of {} bytes",
+                self.size, self.limit
+            ),
+            failure_info: Some(FailureInfo::ApplicationFailureInfo(
+                ApplicationFailureInfo {
+                    r#type: PAYLOAD_TOO_LARGE_FAILURE_TYPE.to_string(),
+                    non_retryable: true,
+                    ..Default::default()
+                },
+            )),
+            ..Default::default()
+        };
+        activity_execution_result::Status::Failed(activity_result::Failure {
+            failure: Some(failure),
+        })
+    }
+}
+
+/// The application failure type of activity results replaced because they were too large
+pub(crate) const PAYLOAD_TOO_LARGE_FAILURE_TYPE: &str = "PayloadSizeLimitExceeded";
+
+/// Records payload sizes for one worker, and enforces its outbound limit
+#[derive(Clone)]
+pub(crate) struct PayloadSizeGuard {
+    outbound_limit: Option<usize>,
+    inbound_metrics: MetricsContext,
+    outbound_metrics: MetricsContext,
+    message_warn_threshold: Option<usize>,
+    /// Indexed by [MessageType]
+    message_metrics: [MetricsContext; 4],
+}
+
+impl PayloadSizeGuard {
+    pub(crate) fn new(config: &WorkerConfig, metrics: &MetricsContext) -> Self {
+        Self {
+            outbound_limit: config.outbound_payload_limit,
+            inbound_metrics: metrics.with_new_attrs([payload_direction(PayloadDirection::Inbound)]),
+            outbound_metrics: metrics
+                .with_new_attrs([payload_direction(PayloadDirection::Outbound)]),
+            message_warn_threshold: config.message_size_warn_threshold,
+            message_metrics: MessageType::ALL.map(|ty| metrics.with_new_attrs([message_type(ty)])),
+        }
+    }
+
+    /// Record the encoded size of a message passing between lang and core. Returns true if it
+    /// exceeds the warning threshold, in which case the caller should log it.
+    pub(crate) fn record_message(&self, ty: MessageType, size: usize) -> bool {
+        self.message_metrics[ty as usize].message_size(size);
+        self.message_warn_threshold.is_some_and(|t| size > t)
+    }
+
+    /// Record the sizes of payloads about to be delivered to lang
+    pub(crate) fn record_inbound<'a>(&self, payloads: impl IntoIterator<Item = &'a Payload>) {
+        for p in payloads {
+            self.inbound_metrics.payload_size(p.encoded_len());
+        }
+    }
+
+    /// Record the sizes of payloads lang sent, failing on the first which exceeds the limit. All
+    /// of them are recorded either way.
+    pub(crate) fn check_outbound<'a>(
+        &self,
+        payloads: impl IntoIterator<Item = &'a Payload>,
+    ) -> Result<(), OversizedPayload> {
+        let mut res = Ok(());
+        for p in payloads {
+            let size = p.encoded_len();
+            self.outbound_metrics.payload_size(size);
+            match self.outbound_limit {
+                Some(limit) if size > limit && res.is_ok() => {
+                    res = Err(OversizedPayload { size, limit });
+                }
+                _ => {}
+            }
+        }
+        res
+    }
+}
+
+/// The user payloads (arguments, results, signal & query inputs) of an activation
+pub(crate) fn activation_payloads(act: &WorkflowActivation) -> impl Iterator<Item = &Payload> {
+    act.jobs.iter().filter_map(|j| j.variant.as_ref()).flat_map(
+        |v| -> Box<dyn Iterator<Item = &Payload> + '_> {
+            match v {
+                workflow_activation_job::Variant::InitializeWorkflow(i) => {
+                    Box::new(i.arguments.iter())
+                }
+                workflow_activation_job::Variant::SignalWorkflow(s) => Box::new(s.input.iter()),
+                workflow_activation_job::Variant::QueryWorkflow(q) => Box::new(q.arguments.iter()),
+                workflow_activation_job::Variant::DoUpdate(u) => Box::new(u.input.iter()),
+                workflow_activation_job::Variant::ResolveSideEffect(r) => Box::new(r.result.iter()),
+                workflow_activation_job::Variant::ResolveActivity(r) => Box::new(
+                    r.result
+                        .as_ref()
+                        .and_then(|r| r.status.as_ref())
+                        .and_then(|s| match s {
+                            activity_resolution::Status::Completed(c) => c.result.as_ref(),
+                            _ => None,
+                        })
+                        .into_iter(),
+                ),
+                workflow_activation_job::Variant::ResolveChildWorkflowExecution(r) => Box::new(
+                    r.result
+                        .as_ref()
+                        .and_then(|r| r.status.as_ref())
+                        .and_then(|s| match s {
+                            child_workflow_result::Status::Completed(c) => c.result.as_ref(),
+                            _ => None,
+                        })
+                        .into_iter(),
+                ),
+                _ => Box::new(std::iter::empty()),
+            }
+        },
+    )
+}
+
+/// The input and heartbeat details of an activity task
+pub(crate) fn activity_task_payloads(task: &ActivityTask) -> impl Iterator<Item = &Payload> {
+    match &task.variant {
+        Some(activity_task::Variant::Start(s)) => Some(s.input.iter().chain(&s.heartbeat_details)),
+        _ => None,
+    }
+    .into_iter()
+    .flatten()
+}
+
+/// The result of a successful activity completion
+pub(crate) fn activity_result_payload(
+    status: &activity_execution_result::Status,
+) -> Option<&Payload> {
+    match status {
+        activity_execution_result::Status::Completed(c) => c.result.as_ref(),
+        _ => None,
+    }
+}
@@ -0,0 +1,218 @@
+//! Gives up on activations lang takes too long to complete. See
+//! [temporal_sdk_core_api::worker::WorkerConfig::activation_deadline].
+//!
+//! Lang completing an activation is the only thing which moves its run along, so a workflow whose
+//! code deadlocks (or busy loops) would otherwise hold its task until server times it out, and the
+//! run would be stuck timing out every task it is given after that without anything saying why. A
+//! timer is started for each activation handed to lang. If it expires before lang completes the
+//! activation, the activation's task is failed as if lang had failed it, which evicts the run.
+//!
+//! A completion and an expiring timer may race. Whichever claims the activation's entry first
+//! wins: a completion cancels a timer which hasn't expired, and is rejected if the timer has.
+//! Either way only one of them is ever applied to the run.
+
+use crate::worker::workflow::workflow_stream::{LocalInput, LocalInputs};
+use futures_util::future::{AbortHandle, Abortable};
+use parking_lot::Mutex;
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use temporal_sdk_core_api::worker::ActivationDeadline;
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::Span;
+
+/// How much sooner than the workflow task timeout activations expire when their deadline derives
+/// from it, so the failure reaches server before the task times out anyway
+const WFT_TIMEOUT_MARGIN: Duration = Duration::from_secs(1);
+
+/// Sent to the stream when an activation's deadline passes
+#[derive(Debug)]
+pub(super) struct ActivationDeadlineMsg {
+    pub(super) run_id: String,
+    pub(super) correlation_id: String,
+    pub(super) waited: Duration,
+}
+
+enum Timer {
+    Pending(AbortHandle),
+    /// The activation has been given up on, and its task is being failed
+    Expired,
+}
+
+type Timers = Arc<Mutex<HashMap<String, (String, Timer)>>>;
+
+pub(super) struct ActivationDeadlines {
+    configured: ActivationDeadline,
+    local_tx: UnboundedSender<LocalInput>,
+    /// The timer of the activation lang holds for each run, by run id, along with the activation's
+    /// correlation id
+    timers: Timers,
+}
+
+impl ActivationDeadlines {
+    pub(super) fn new(
+        configured: ActivationDeadline,
+        local_tx: UnboundedSender<LocalInput>,
+    ) -> Self {
+        Self {
+            configured,
+            local_tx,
+            timers: Default::default(),
+        }
+    }
+
+    /// Start the timer of an activation handed to lang. `wft_timeout` is the run's workflow task
+    /// timeout, which the deadline may be derived from.
+    pub(super) fn start(&self, run_id: &str, correlation_id: &str, wft_timeout: Option<Duration>) {
+        let waited = match self.configured {
+            ActivationDeadline::Disabled => return,
+            ActivationDeadline::FromWorkflowTaskTimeout => match wft_timeout {
+                Some(t) => derived_deadline(t),
+                None => return,
+            },
+            ActivationDeadline::Fixed(d) => d,
+        };
+        let (handle, reg) = AbortHandle::new_pair();
+        // The entry must be in place before the timer can expire
+        let timer = (correlation_id.to_string(), Timer::Pending(handle));
+        if let Some((_, Timer::Pending(old))) = self.timers.lock().insert(run_id.to_string(), timer)
+        {
+            old.abort();
+        }
+        let timers = self.timers.clone();
+        let tx = self.local_tx.clone();
+        let msg = ActivationDeadlineMsg {
+            run_id: run_id.to_string(),
+            correlation_id: correlation_id.to_string(),
+            waited,
+        };
+        let span = Span::current();
+        tokio::spawn(Abortable::new(
+            async move {
+                tokio::time::sleep(waited).await;
+                if claim_expired(&timers, &msg.run_id, &msg.correlation_id) {
+                    let _ = tx.send(LocalInput {
+                        input: LocalInputs::ActivationDeadline(msg),
+                        span,
+                    });
+                }
+            },
+            reg,
+        ));
+    }
+
+    /// Called as lang completes an activation of the run, cancelling its timer. Returns false if
+    /// the activation's deadline has already passed, in which case the completion must be
+    /// rejected. Completions which echo the correlation id of some other activation are left for
+    /// completion processing to reject, but cancel the timer all the same. Otherwise it would be
+    /// left pending even when no activation of the run is outstanding any more, and lang sending a
+    /// completion at all shows it isn't stuck.
+    pub(super) fn complete(&self, run_id: &str, correlation_id: &str) -> bool {
+        let mut timers = self.timers.lock();
+        match timers.get(run_id) {
+            Some((_, Timer::Expired)) => false,
+            Some((_, Timer::Pending(_))) => {
+                if let Some((_, Timer::Pending(timer))) = timers.remove(run_id) {
+                    timer.abort();
+                }
+                true
+            }
+            None => true,
+        }
+    }
+
+    /// Forget an activation which was given up on, once its task has been failed
+    pub(super) fn forget_expired(&self, run_id: &str, correlation_id: &str) {
+        let mut timers = self.timers.lock();
+        if matches!(timers.get(run_id), Some((id, Timer::Expired)) if id == correlation_id) {
+            timers.remove(run_id);
+        }
+    }
+}
+
+/// Marks the activation expired, unless lang completed it (or another was issued) first
+fn claim_expired(timers: &Timers, run_id: &str, correlation_id: &str) -> bool {
+    match timers.lock().get_mut(run_id) {
+        Some((id, timer @ Timer::Pending(_))) if id == correlation_id => {
+            *timer = Timer::Expired;
+            true
+        }
+        _ => false,
+    }
+}
+
+fn derived_deadline(wft_timeout: Duration) -> Duration {
+    wft_timeout
+        .saturating_sub(WFT_TIMEOUT_MARGIN)
+        .max(wft_timeout / 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc::unbounded_channel;
+
+    #[test]
+    fn deadlines_derive_from_the_wft_timeout() {
+        assert_eq!(
+            derived_deadline(Duration::from_secs(10)),
+            Duration::from_secs(9)
+        );
+        // Short timeouts aren't left with next to no time
+        assert_eq!(
+            derived_deadline(Duration::from_secs(1)),
+            Duration::from_millis(500)
+        );
+    }
+
+    #[tokio::test]
+    async fn completions_and_expiry_never_both_win() {
+        let (tx, mut rx) = unbounded_channel();
+        let deadlines =
+            ActivationDeadlines::new(ActivationDeadline::Fixed(Duration::from_millis(10)), tx);
+
+        deadlines.start("run", "act-1", None);
+        assert!(deadlines.complete("run", "act-1"));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(rx.try_recv().is_err());
+
+        deadlines.start("run", "act-2", None);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_matches!(
+            rx.try_recv().unwrap().input,
+            LocalInputs::ActivationDeadline(ActivationDeadlineMsg { correlation_id, .. })
+                if correlation_id == "act-2"
+        );
+        // Lang's late completion loses, whether it echoes the correlation id or not
+        assert!(!deadlines.complete("run", "act-2"));
+        assert!(!deadlines.complete("run", ""));
+        deadlines.forget_expired("run", "act-2");
+        assert!(deadlines.complete("run", ""));
+    }
+
+    #[tokio::test]
+    async fn completions_echoing_another_activation_cancel_the_timer() {
+        let (tx, mut rx) = unbounded_channel();
+        let deadlines =
+            ActivationDeadlines::new(ActivationDeadline::Fixed(Duration::from_millis(10)), tx);
+
+        deadlines.start("run", "act-1", None);
+        assert!(deadlines.complete("run", "act-0"));
+        assert!(deadlines.timers.lock().is_empty());
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn deadlines_are_opt_in() {
+        let (tx, _rx) = unbounded_channel();
+        let deadlines = ActivationDeadlines::new(ActivationDeadline::default(), tx);
+        deadlines.start("run", "act-1", Some(Duration::from_secs(10)));
+        assert!(deadlines.timers.lock().is_empty());
+
+        let (tx, _rx) = unbounded_channel();
+        let deadlines = ActivationDeadlines::new(ActivationDeadline::FromWorkflowTaskTimeout, tx);
+        deadlines.start("run", "act-1", None);
+        assert!(deadlines.timers.lock().is_empty());
+        deadlines.start("run", "act-1", Some(Duration::from_secs(10)));
+        assert!(!deadlines.timers.lock().is_empty());
+    }
+}
@@ -0,0 +1,213 @@
+//! Warns about activities scheduled on task queues nothing polls. See
+//! [temporal_sdk_core_api::worker::WorkerConfig::activity_task_queue_check_ttl].
+//!
+//! Server accepts activities on any task queue, so a misspelled one is only noticed once the
+//! activity times out. Workers don't know what else is deployed, but server knows which queues
+//! have been polled recently, so each queue other than the worker's own is described and a warning
+//! is issued if nothing polls it. Results are cached per queue, so a queue is described (and warned
+//! about) at most once per interval no matter how many activities are scheduled on it.
+//!
+//! Checks run in the background, so completing a workflow task never waits on them, and a queue
+//! is never described again while a description of it is still in flight.
+
+use crate::{worker::client::WorkerClient, MetricsContext};
+use parking_lot::Mutex;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use temporal_sdk_core_protos::temporal::api::{
+    command::v1::{command::Attributes, Command},
+    enums::v1::TaskQueueType,
+};
+
+pub(super) struct ActivityQueueChecker {
+    client: Arc<dyn WorkerClient>,
+    ttl: Duration,
+    metrics: MetricsContext,
+    state: Mutex<CheckState>,
+}
+
+#[derive(Default)]
+struct CheckState {
+    /// Whether each described queue had pollers, and when that was learned
+    checked: HashMap<String, (bool, Instant)>,
+    /// Queues currently being described
+    in_flight: HashSet<String>,
+}
+
+impl ActivityQueueChecker {
+    pub(super) fn new(
+        client: Arc<dyn WorkerClient>,
+        ttl: Duration,
+        metrics: MetricsContext,
+    ) -> Self {
+        Self {
+            client,
+            ttl,
+            metrics,
+            state: Default::default(),
+        }
+    }
+
+    /// The task queues, other than `own_queue`, which the commands schedule activities on. An
+    /// activity without a task queue is scheduled on the workflow's own.
+    pub(super) fn queues_to_check(own_queue: &str, commands: &[Command]) -> HashSet<String> {
+        commands
+            .iter()
+            .filter_map(|c| match c.attributes.as_ref() {
+                Some(Attributes::ScheduleActivityTaskCommandAttributes(attrs)) => {
+                    attrs.task_queue.as_ref().map(|q| q.name.as_str())
+                }
+                _ => None,
+            })
+            .filter(|q| !q.is_empty() && *q != own_queue)
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    /// Like [Self::check], without waiting for the descriptions
+    pub(super) fn spawn_check(self: &Arc<Self>, queues: HashSet<String>) {
+        let claimed = self.claim(queues);
+        if claimed.is_empty() {
+            return;
+        }
+        let this = self.clone();
+        tokio::spawn(async move {
+            this.describe(claimed).await;
+        });
+    }
+
+    /// Describe every queue whose cached result has expired and which isn't already being
+    /// described, warning about those nothing polls. Returns the queues which were warned about.
+    pub(super) async fn check(&self, queues: HashSet<String>) -> Vec<String> {
+        let claimed = self.claim(queues);
+        self.describe(claimed).await
+    }
+
+    /// Mark the queues which need describing as in flight, and return them
+    fn claim(&self, queues: HashSet<String>) -> Vec<String> {
+        let mut state = self.state.lock();
+        let ttl = self.ttl;
+        state.checked.retain(|_, (_, at)| at.elapsed() < ttl);
+        let claimed: Vec<_> = queues
+            .into_iter()
+            .filter(|q| !state.checked.contains_key(q) && !state.in_flight.contains(q))
+            .collect();
+        state.in_flight.extend(claimed.iter().cloned());
+        claimed
+    }
+
+    async fn describe(&self, queues: Vec<String>) -> Vec<String> {
+        let mut warned = vec![];
+        for queue in queues {
+            let has_pollers = match self
+                .client
+                .describe_task_queue(queue.as_str().into(), TaskQueueType::Activity)
+                .await
+            {
+                Ok(desc) => !desc.pollers.is_empty(),
+                Err(e) => {
+                    // Failing to ask isn't reason to warn, and asking again right away would likely
+                    // fail the same way
+                    debug!(error = ?e, task_queue = %queue,
+                           "Could not describe task queue to check for activity pollers");
+                    true
+                }
+            };
+            if !has_pollers {
+                warn!(
+                    task_queue = %queue,
+                    "An activity was scheduled on a task queue which server knows of no worker \
+                     polling. It will not run until one starts. Check the queue name is spelled \
+                     correctly."
+                );
+                self.metrics.activity_task_queue_without_pollers(&queue);
+                warned.push(queue.clone());
+            }
+            let mut state = self.state.lock();
+            state.in_flight.remove(&queue);
+            state.checked.insert(queue, (has_pollers, Instant::now()));
+        }
+        warned
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::worker::client::mocks::mock_workflow_client;
+    use temporal_client::{TaskQueueDescription, TaskQueuePoller};
+    use temporal_sdk_core_protos::temporal::api::command::v1::ScheduleActivityTaskCommandAttributes;
+
+    fn checker(known_queue: &'static str, ttl: Duration, describes: usize) -> ActivityQueueChecker {
+        let mut mock_client = mock_workflow_client();
+        mock_client
+            .expect_describe_task_queue()
+            .times(describes)
+            .returning(move |tq, _| {
+                Ok(TaskQueueDescription {
+                    pollers: if tq == known_queue {
+                        vec![TaskQueuePoller {
+                            identity: "activity-worker".to_string(),
+                            ..Default::default()
+                        }]
+                    } else {
+                        vec![]
+                    },
+                    stats: None,
+                })
+            });
+        ActivityQueueChecker::new(Arc::new(mock_client), ttl, MetricsContext::no_op())
+    }
+
+    fn queues(names: &[&str]) -> HashSet<String> {
+        names.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn only_other_queues_are_checked() {
+        let cmd = |tq: &str| -> Command {
+            Attributes::ScheduleActivityTaskCommandAttributes(
+                ScheduleActivityTaskCommandAttributes {
+                    task_queue: Some(tq.to_string().into()),
+                    ..Default::default()
+                },
+            )
+            .into()
+        };
+        let cmds = [cmd("own"), cmd(""), cmd("other"), cmd("other")];
+        assert_eq!(
+            ActivityQueueChecker::queues_to_check("own", &cmds),
+            queues(&["other"])
+        );
+    }
+
+    #[tokio::test]
+    async fn warns_about_queues_nothing_polls() {
+        let checker = checker("known", Duration::from_secs(60), 2);
+        assert_eq!(
+            checker.check(queues(&["known", "typo"])).await,
+            vec!["typo".to_string()]
+        );
+        // Both results are cached, so neither is described (or warned about) again
+        assert!(checker.check(queues(&["known", "typo"])).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn queues_being_described_are_not_described_again() {
+        let checker = checker("known", Duration::from_secs(60), 1);
+        let claimed = checker.claim(queues(&["typo"]));
+        // Another completion scheduling on the same queue while the first check is in flight
+        assert!(checker.check(queues(&["typo"])).await.is_empty());
+        assert_eq!(checker.describe(claimed).await, vec!["typo".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn queues_are_described_again_once_the_result_expires() {
+        let checker = checker("known", Duration::ZERO, 2);
+        assert!(checker.check(queues(&["known"])).await.is_empty());
+        assert!(checker.check(queues(&["known"])).await.is_empty());
+    }
+}
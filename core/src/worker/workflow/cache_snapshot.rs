@@ -0,0 +1,252 @@
+//! Snapshots of a worker's workflow cache, which let a worker in a new process (ex: during a
+//! rolling deploy) pick up the runs cached by the worker it replaces. The new worker polls the old
+//! one's sticky queue, and for runs whose history the snapshot carries, applies the partial
+//! histories server sends there on top of it rather than fetching the whole history again.
+
+use super::PreparedWFT;
+use prost::Message;
+use sha2::{Digest, Sha256};
+use std::mem;
+use temporal_sdk_core_protos::temporal::api::{
+    enums::v1::EventType,
+    history::v1::{history_event::Attributes, HistoryEvent},
+};
+
+/// The schema version written into every snapshot. Bump whenever the meaning of an existing field
+/// changes, so that older workers reject the snapshot rather than misinterpret it.
+pub(crate) const CACHE_SNAPSHOT_VERSION: u32 = 1;
+
+/// Everything needed for a new worker to take over from the one a snapshot was exported from
+#[derive(Clone, PartialEq, Message)]
+pub(crate) struct CacheSnapshot {
+    /// Always the first field decoded, so that it can be checked whatever else has changed
+    #[prost(uint32, tag = "1")]
+    pub(crate) version: u32,
+    #[prost(string, tag = "2")]
+    pub(crate) namespace: String,
+    #[prost(string, tag = "3")]
+    pub(crate) task_queue: String,
+    #[prost(string, optional, tag = "4")]
+    pub(crate) sticky_queue: Option<String>,
+    #[prost(message, repeated, tag = "5")]
+    pub(crate) runs: Vec<RunSnapshot>,
+    /// SHA-256 of the snapshot as encoded with this field empty. Guards against importing a
+    /// snapshot which was truncated or otherwise damaged on its way to the new worker, which can
+    /// still decode.
+    #[prost(bytes = "vec", tag = "6")]
+    pub(crate) checksum: Vec<u8>,
+}
+
+/// One cached run
+#[derive(Clone, PartialEq, Message)]
+pub(crate) struct RunSnapshot {
+    #[prost(string, tag = "1")]
+    pub(crate) workflow_id: String,
+    #[prost(string, tag = "2")]
+    pub(crate) run_id: String,
+    /// The id of the last event the run had processed
+    #[prost(int64, tag = "3")]
+    pub(crate) last_event_id: i64,
+    /// Every event the run had processed, from the start of history. Empty if the exporting
+    /// worker did not retain history.
+    #[prost(message, repeated, tag = "4")]
+    pub(crate) events: Vec<HistoryEvent>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum CacheSnapshotError {
+    #[error("Cache snapshot could not be decoded: {0}")]
+    Malformed(#[from] prost::DecodeError),
+    #[error("Cache snapshot does not match its checksum")]
+    Corrupted,
+    #[error("Cache snapshot has unknown schema version {found}")]
+    UnsupportedVersion { found: u32 },
+    #[error("Cache snapshot was exported by a worker for {namespace}/{task_queue}")]
+    WrongTaskQueue {
+        namespace: String,
+        task_queue: String,
+    },
+    #[error("Cache snapshot history for run {run_id} is not contiguous from the workflow's start")]
+    BrokenHistory { run_id: String },
+}
+
+impl CacheSnapshot {
+    /// Encode the snapshot for export, along with the checksum [Self::decode_for] verifies
+    pub(crate) fn encode_with_checksum(mut self) -> Vec<u8> {
+        self.checksum = self.compute_checksum();
+        self.encode_to_vec()
+    }
+
+    /// Decode a snapshot, rejecting it unless it was exported by a worker of the same schema
+    /// version, for the same namespace and task queue, and arrived intact
+    pub(crate) fn decode_for(
+        bytes: &[u8],
+        namespace: &str,
+        task_queue: &str,
+    ) -> Result<Self, CacheSnapshotError> {
+        let mut snapshot = Self::decode(bytes)?;
+        if snapshot.version != CACHE_SNAPSHOT_VERSION {
+            return Err(CacheSnapshotError::UnsupportedVersion {
+                found: snapshot.version,
+            });
+        }
+        let checksum = mem::take(&mut snapshot.checksum);
+        if snapshot.compute_checksum() != checksum {
+            return Err(CacheSnapshotError::Corrupted);
+        }
+        if snapshot.namespace != namespace || snapshot.task_queue != task_queue {
+            return Err(CacheSnapshotError::WrongTaskQueue {
+                namespace: snapshot.namespace,
+                task_queue: snapshot.task_queue,
+            });
+        }
+        for run in &snapshot.runs {
+            let starts_workflow = run.events.first().map_or(true, |e| {
+                e.event_type() == EventType::WorkflowExecutionStarted
+            });
+            let contiguous = run
+                .events
+                .iter()
+                .zip(1..)
+                .all(|(e, expected_id)| e.event_id == expected_id);
+            let ends_at_last = run
+                .events
+                .last()
+                .map_or(true, |e| e.event_id == run.last_event_id);
+            if !starts_workflow || !contiguous || !ends_at_last {
+                return Err(CacheSnapshotError::BrokenHistory {
+                    run_id: run.run_id.clone(),
+                });
+            }
+        }
+        Ok(snapshot)
+    }
+
+    /// Must be called with `checksum` empty
+    fn compute_checksum(&self) -> Vec<u8> {
+        Sha256::digest(self.encode_to_vec()).to_vec()
+    }
+}
+
+impl RunSnapshot {
+    /// If this snapshot's history leads directly into the (partial) history of `wft`, returns the
+    /// events which need to precede it to make up the whole history. Otherwise history must be
+    /// fetched as usual, ex: if the exporting worker didn't retain history, or server has since
+    /// discarded events the snapshot saw (as it does for rejected speculative tasks).
+    pub(super) fn history_preceding(self, wft: &PreparedWFT) -> Option<Vec<HistoryEvent>> {
+        let first_new_event = wft.update.first_event_id()?;
+        let continues = self.workflow_id == wft.execution.workflow_id
+            && self.started_workflow_type() == Some(wft.workflow_type.as_str())
+            && self.last_event_id + 1 == first_new_event;
+        continues.then_some(self.events)
+    }
+
+    /// The workflow type the history starts, if the snapshot carries history
+    fn started_workflow_type(&self) -> Option<&str> {
+        match self.events.first()?.attributes.as_ref()? {
+            Attributes::WorkflowExecutionStartedEventAttributes(a) => {
+                a.workflow_type.as_ref().map(|t| t.name.as_str())
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(event_id: i64) -> HistoryEvent {
+        HistoryEvent {
+            event_id,
+            event_type: if event_id == 1 {
+                EventType::WorkflowExecutionStarted
+            } else {
+                EventType::WorkflowTaskScheduled
+            } as i32,
+            ..Default::default()
+        }
+    }
+
+    fn snapshot(events: Vec<HistoryEvent>, last_event_id: i64) -> CacheSnapshot {
+        CacheSnapshot {
+            version: CACHE_SNAPSHOT_VERSION,
+            namespace: "ns".to_string(),
+            task_queue: "tq".to_string(),
+            sticky_queue: Some("sticky".to_string()),
+            runs: vec![RunSnapshot {
+                workflow_id: "wf".to_string(),
+                run_id: "run".to_string(),
+                last_event_id,
+                events,
+            }],
+            checksum: vec![],
+        }
+    }
+
+    #[test]
+    fn roundtrips() {
+        let s = snapshot(vec![event(1), event(2), event(3)], 3);
+        let decoded =
+            CacheSnapshot::decode_for(&s.clone().encode_with_checksum(), "ns", "tq").unwrap();
+        assert_eq!(decoded, s);
+        // Metadata-only runs are fine too
+        let s = snapshot(vec![], 3);
+        CacheSnapshot::decode_for(&s.encode_with_checksum(), "ns", "tq").unwrap();
+    }
+
+    #[test]
+    fn incompatible_snapshots_are_rejected() {
+        let mut s = snapshot(vec![], 3);
+        s.version = CACHE_SNAPSHOT_VERSION + 1;
+        assert!(matches!(
+            CacheSnapshot::decode_for(&s.encode_with_checksum(), "ns", "tq"),
+            Err(CacheSnapshotError::UnsupportedVersion { .. })
+        ));
+        let s = snapshot(vec![], 3);
+        assert!(matches!(
+            CacheSnapshot::decode_for(&s.encode_with_checksum(), "ns", "other_tq"),
+            Err(CacheSnapshotError::WrongTaskQueue { .. })
+        ));
+        assert!(matches!(
+            CacheSnapshot::decode_for(b"not a snapshot", "ns", "tq"),
+            Err(CacheSnapshotError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn damaged_snapshots_are_rejected() {
+        let bytes = snapshot(vec![event(1), event(2), event(3)], 3).encode_with_checksum();
+        // Still decodes, but the history has lost its last event
+        let mut damaged = CacheSnapshot::decode(bytes.as_slice()).unwrap();
+        damaged.runs[0].events.pop();
+        damaged.runs[0].last_event_id = 2;
+        assert!(matches!(
+            CacheSnapshot::decode_for(&damaged.encode_to_vec(), "ns", "tq"),
+            Err(CacheSnapshotError::Corrupted)
+        ));
+        // Snapshots without a checksum can't be verified either
+        let s = snapshot(vec![], 3);
+        assert!(matches!(
+            CacheSnapshot::decode_for(&s.encode_to_vec(), "ns", "tq"),
+            Err(CacheSnapshotError::Corrupted)
+        ));
+    }
+
+    #[test]
+    fn broken_histories_are_rejected() {
+        let mut not_from_start = vec![event(1), event(2)];
+        not_from_start[0].event_type = EventType::WorkflowTaskScheduled as i32;
+        for s in [
+            snapshot(vec![event(1), event(3)], 3),
+            snapshot(vec![event(2), event(3)], 3),
+            snapshot(vec![event(1), event(2)], 3),
+            snapshot(not_from_start, 2),
+        ] {
+            assert!(matches!(
+                CacheSnapshot::decode_for(&s.encode_with_checksum(), "ns", "tq"),
+                Err(CacheSnapshotError::BrokenHistory { .. })
+            ));
+        }
+    }
+}
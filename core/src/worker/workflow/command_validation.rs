@@ -0,0 +1,393 @@
+//! Client-side checks of the commands lang sends us. Server rejects commands which are missing
+//! required fields, but it does so by failing the entire workflow task with a cause that rarely
+//! points at the actual problem. We catch these before anything is sent and name the command &
+//! field responsible.
+//!
+//! Search attribute and memo upserts are always checked, since server rejecting those can leave
+//! the workflow stuck retrying the same task. Everything else is only checked when strict
+//! validation is enabled.
+//!
+//! Marker commands (side effects and patches) are checked, in strict mode, against the size server
+//! allows for a marker's details.
+//!
+//! Payload sizes are always checked, against the limit for the worker's namespace (if any).
+
+use crate::worker::{payload_limits::PayloadSizeGuard, workflow::WFCommand};
+use prost::Message;
+use prost_types::Duration as PbDuration;
+use std::collections::HashMap;
+use temporal_sdk_core_api::errors::CompleteWfError;
+use temporal_sdk_core_protos::{
+    coresdk::{
+        common::{build_has_change_marker_details, build_side_effect_marker_details},
+        external_data::SideEffectMarkerData,
+        workflow_commands::{query_result, update_response},
+    },
+    temporal::api::common::v1::{Payload, Payloads},
+};
+
+/// Describes why a single command failed validation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CommandViolation {
+    command: &'static str,
+    field: &'static str,
+    reason: &'static str,
+}
+
+const MUST_BE_NONEMPTY: &str = "must not be empty";
+const KEYS_MUST_BE_NONEMPTY: &str = "must not contain empty keys";
+const MARKER_TOO_LARGE: &str = "makes the marker's details larger than server allows";
+
+/// The largest a marker's details may be once encoded. This is server's default blob size limit,
+/// which it applies to the details of every `RecordMarker` command.
+const MAX_MARKER_DETAILS_BYTES: usize = 2 * 1024 * 1024;
+
+/// Check every command in a successful completion, returning an error for the first one which
+/// server would reject. Only upserts are checked unless `strict` is set.
+pub(super) fn validate_commands(
+    run_id: &str,
+    correlation_id: &str,
+    commands: &[WFCommand],
+    strict: bool,
+) -> Result<(), CompleteWfError> {
+    for cmd in commands {
+        if let Err(v) = validate_command(cmd, strict) {
+            return Err(CompleteWfError::InvalidCommand {
+                command: v.command,
+                field: v.field,
+                reason: v.reason,
+                run_id: run_id.to_string(),
+                correlation_id: correlation_id.to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Record the sizes of the payloads in every command of a successful completion, returning an
+/// error for the first command carrying one larger than the worker allows
+pub(super) fn check_payload_sizes(
+    run_id: &str,
+    correlation_id: &str,
+    commands: &[WFCommand],
+    guard: &PayloadSizeGuard,
+) -> Result<(), CompleteWfError> {
+    let mut res = Ok(());
+    for cmd in commands {
+        let Some((command, payloads)) = command_payloads(cmd) else {
+            continue;
+        };
+        if let Err(oversized) = guard.check_outbound(payloads) {
+            if res.is_ok() {
+                res = Err(CompleteWfError::PayloadTooLarge {
+                    command,
+                    size: oversized.size,
+                    limit: oversized.limit,
+                    run_id: run_id.to_string(),
+                    correlation_id: correlation_id.to_string(),
+                });
+            }
+        }
+    }
+    res
+}
+
+/// The name of a command and the user payloads (arguments, results, signal inputs) it carries,
+/// for those commands which carry any
+fn command_payloads(cmd: &WFCommand) -> Option<(&'static str, Vec<&Payload>)> {
+    Some(match cmd {
+        WFCommand::AddActivity(a) => ("ScheduleActivity", a.arguments.iter().collect()),
+        WFCommand::AddLocalActivity(a) => ("ScheduleLocalActivity", a.arguments.iter().collect()),
+        WFCommand::CompleteWorkflow(c) => ("CompleteWorkflowExecution", c.result.iter().collect()),
+        WFCommand::ContinueAsNew(c) => (
+            "ContinueAsNewWorkflowExecution",
+            c.arguments.iter().collect(),
+        ),
+        WFCommand::AddChildWorkflow(c) => ("StartChildWorkflowExecution", c.input.iter().collect()),
+        WFCommand::RecordSideEffect(s) => ("RecordSideEffect", s.result.iter().collect()),
+        WFCommand::SignalExternalWorkflow(s) => {
+            ("SignalExternalWorkflowExecution", s.args.iter().collect())
+        }
+        WFCommand::QueryResponse(q) => match &q.variant {
+            Some(query_result::Variant::Succeeded(s)) => {
+                ("QueryResult", s.response.iter().collect())
+            }
+            _ => return None,
+        },
+        WFCommand::UpdateResponse(u) => match &u.response {
+            Some(update_response::Response::Completed(p)) => ("UpdateResponse", vec![p]),
+            _ => return None,
+        },
+        _ => return None,
+    })
+}
+
+fn validate_command(cmd: &WFCommand, strict: bool) -> Result<(), CommandViolation> {
+    let violation = |command, field, reason| {
+        Err(CommandViolation {
+            command,
+            field,
+            reason,
+        })
+    };
+    match cmd {
+        WFCommand::UpsertSearchAttributes(u) => {
+            let cmd = "UpsertWorkflowSearchAttributes";
+            if u.search_attributes.is_empty() {
+                return violation(cmd, "search_attributes", MUST_BE_NONEMPTY);
+            }
+            if u.search_attributes.contains_key("") {
+                return violation(cmd, "search_attributes", KEYS_MUST_BE_NONEMPTY);
+            }
+        }
+        WFCommand::ModifyWorkflowProperties(m) => {
+            let (cmd, field) = ("ModifyWorkflowProperties", "upserted_memo");
+            match m.upserted_memo.as_ref().map(|memo| &memo.fields) {
+                Some(f) if f.contains_key("") => {
+                    return violation(cmd, field, KEYS_MUST_BE_NONEMPTY)
+                }
+                Some(f) if !f.is_empty() => {}
+                _ => return violation(cmd, field, MUST_BE_NONEMPTY),
+            }
+        }
+        _ if !strict => {}
+        // An empty task queue is fine for activities and children, it means "use the workflow's
+        // task queue".
+        WFCommand::AddActivity(a) => {
+            if a.activity_id.is_empty() {
+                return violation("ScheduleActivity", "activity_id", MUST_BE_NONEMPTY);
+            }
+            if a.activity_type.is_empty() {
+                return violation("ScheduleActivity", "activity_type", MUST_BE_NONEMPTY);
+            }
+        }
+        WFCommand::AddLocalActivity(a) => {
+            if a.activity_type.is_empty() {
+                return violation("ScheduleLocalActivity", "activity_type", MUST_BE_NONEMPTY);
+            }
+        }
+        WFCommand::AddTimer(t) => match &t.start_to_fire_timeout {
+            None => return violation("StartTimer", "start_to_fire_timeout", "must be set"),
+            Some(d) if !is_positive(d) => {
+                return violation("StartTimer", "start_to_fire_timeout", "must be positive")
+            }
+            _ => {}
+        },
+        WFCommand::AddChildWorkflow(c) => {
+            if c.workflow_id.is_empty() {
+                return violation(
+                    "StartChildWorkflowExecution",
+                    "workflow_id",
+                    MUST_BE_NONEMPTY,
+                );
+            }
+            if c.workflow_type.is_empty() {
+                return violation(
+                    "StartChildWorkflowExecution",
+                    "workflow_type",
+                    MUST_BE_NONEMPTY,
+                );
+            }
+        }
+        WFCommand::SignalExternalWorkflow(s) => {
+            if s.signal_name.is_empty() {
+                return violation(
+                    "SignalExternalWorkflowExecution",
+                    "signal_name",
+                    MUST_BE_NONEMPTY,
+                );
+            }
+        }
+        WFCommand::SetPatchMarker(p) => {
+            if p.patch_id.is_empty() {
+                return violation("SetPatchMarker", "patch_id", MUST_BE_NONEMPTY);
+            }
+            // Building these only fails if the patch id can't be serialized, which server would
+            // never see either, so there's nothing to measure.
+            if let Ok(details) = build_has_change_marker_details(&p.patch_id, p.deprecated) {
+                if marker_details_size(&details) > MAX_MARKER_DETAILS_BYTES {
+                    return violation("SetPatchMarker", "patch_id", MARKER_TOO_LARGE);
+                }
+            }
+        }
+        WFCommand::RecordSideEffect(s) => {
+            let details = build_side_effect_marker_details(
+                SideEffectMarkerData {
+                    seq: s.seq,
+                    mutable_id: s.mutable_id.clone(),
+                },
+                s.result.clone(),
+            );
+            if marker_details_size(&details) > MAX_MARKER_DETAILS_BYTES {
+                return violation("RecordSideEffect", "result", MARKER_TOO_LARGE);
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn is_positive(d: &PbDuration) -> bool {
+    d.seconds > 0 || (d.seconds == 0 && d.nanos > 0)
+}
+
+/// The encoded size of a marker's details, as they'll be sent to server
+fn marker_details_size(details: &HashMap<String, Payloads>) -> usize {
+    details.iter().map(|(k, v)| k.len() + v.encoded_len()).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use std::collections::HashMap;
+    use temporal_sdk_core_protos::{
+        coresdk::workflow_commands::{
+            ModifyWorkflowProperties, RecordSideEffect, ScheduleActivity, ScheduleLocalActivity,
+            SetPatchMarker, SignalExternalWorkflowExecution, StartChildWorkflowExecution,
+            StartTimer, UpsertWorkflowSearchAttributes,
+        },
+        temporal::api::common::v1::Memo,
+    };
+
+    fn activity(id: &str, ty: &str) -> WFCommand {
+        WFCommand::AddActivity(ScheduleActivity {
+            activity_id: id.to_string(),
+            activity_type: ty.to_string(),
+            ..Default::default()
+        })
+    }
+
+    fn timer(d: Option<PbDuration>) -> WFCommand {
+        WFCommand::AddTimer(StartTimer {
+            seq: 1,
+            start_to_fire_timeout: d,
+            ..Default::default()
+        })
+    }
+
+    fn child(id: &str, ty: &str) -> WFCommand {
+        WFCommand::AddChildWorkflow(StartChildWorkflowExecution {
+            workflow_id: id.to_string(),
+            workflow_type: ty.to_string(),
+            ..Default::default()
+        })
+    }
+
+    fn keyed(keys: &[&str]) -> HashMap<String, Payload> {
+        keys.iter()
+            .map(|k| (k.to_string(), Payload::default()))
+            .collect()
+    }
+
+    fn upsert_sas(keys: &[&str]) -> WFCommand {
+        WFCommand::UpsertSearchAttributes(UpsertWorkflowSearchAttributes {
+            search_attributes: keyed(keys),
+        })
+    }
+
+    fn upsert_memo(keys: Option<&[&str]>) -> WFCommand {
+        WFCommand::ModifyWorkflowProperties(ModifyWorkflowProperties {
+            upserted_memo: keys.map(|k| Memo::from(keyed(k))),
+        })
+    }
+
+    fn side_effect(result_bytes: usize) -> WFCommand {
+        WFCommand::RecordSideEffect(RecordSideEffect {
+            seq: 1,
+            result: Some(Payload {
+                data: vec![0; result_bytes],
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+    }
+
+    fn patch(id_len: usize) -> WFCommand {
+        WFCommand::SetPatchMarker(SetPatchMarker {
+            patch_id: "p".repeat(id_len),
+            ..Default::default()
+        })
+    }
+
+    fn dur(seconds: i64, nanos: i32) -> Option<PbDuration> {
+        Some(PbDuration { seconds, nanos })
+    }
+
+    #[rstest]
+    #[case::activity_ok(activity("1", "echo"), None)]
+    #[case::activity_no_id(activity("", "echo"), Some("activity_id"))]
+    #[case::activity_no_type(activity("1", ""), Some("activity_type"))]
+    #[case::local_activity_no_type(
+        WFCommand::AddLocalActivity(ScheduleLocalActivity::default()),
+        Some("activity_type")
+    )]
+    #[case::timer_ok(timer(dur(1, 0)), None)]
+    #[case::timer_subsecond_ok(timer(dur(0, 1)), None)]
+    #[case::timer_unset(timer(None), Some("start_to_fire_timeout"))]
+    #[case::timer_zero(timer(dur(0, 0)), Some("start_to_fire_timeout"))]
+    #[case::timer_negative(timer(dur(-1, 500)), Some("start_to_fire_timeout"))]
+    #[case::child_ok(child("wid", "wtype"), None)]
+    #[case::child_no_id(child("", "wtype"), Some("workflow_id"))]
+    #[case::child_no_type(child("wid", ""), Some("workflow_type"))]
+    #[case::signal_no_name(
+        WFCommand::SignalExternalWorkflow(SignalExternalWorkflowExecution::default()),
+        Some("signal_name")
+    )]
+    #[case::patch_no_id(patch(0), Some("patch_id"))]
+    #[case::patch_ok(patch(10), None)]
+    #[case::patch_too_large(patch(MAX_MARKER_DETAILS_BYTES), Some("patch_id"))]
+    #[case::side_effect_ok(side_effect(1024), None)]
+    #[case::side_effect_too_large(side_effect(MAX_MARKER_DETAILS_BYTES), Some("result"))]
+    #[case::search_attrs_ok(upsert_sas(&["foo"]), None)]
+    #[case::search_attrs_empty(upsert_sas(&[]), Some("search_attributes"))]
+    #[case::search_attrs_empty_key(upsert_sas(&["foo", ""]), Some("search_attributes"))]
+    #[case::memo_ok(upsert_memo(Some(&["foo"])), None)]
+    #[case::memo_unset(upsert_memo(None), Some("upserted_memo"))]
+    #[case::memo_empty(upsert_memo(Some(&[])), Some("upserted_memo"))]
+    #[case::memo_empty_key(upsert_memo(Some(&[""])), Some("upserted_memo"))]
+    #[case::unchecked_command(WFCommand::NoCommandsFromLang, None)]
+    fn validates_required_fields(#[case] cmd: WFCommand, #[case] bad_field: Option<&str>) {
+        assert_eq!(
+            validate_command(&cmd, true).err().map(|v| v.field),
+            bad_field
+        );
+    }
+
+    #[rstest]
+    #[case::activity_no_id(activity("", "echo"), None)]
+    #[case::timer_unset(timer(None), None)]
+    #[case::side_effect_too_large(side_effect(MAX_MARKER_DETAILS_BYTES), None)]
+    #[case::search_attrs_empty(upsert_sas(&[]), Some("search_attributes"))]
+    #[case::memo_empty_key(upsert_memo(Some(&[""])), Some("upserted_memo"))]
+    fn only_upserts_are_validated_when_not_strict(
+        #[case] cmd: WFCommand,
+        #[case] bad_field: Option<&str>,
+    ) {
+        assert_eq!(
+            validate_command(&cmd, false).err().map(|v| v.field),
+            bad_field
+        );
+    }
+
+    #[test]
+    fn first_violation_is_reported_with_run_id() {
+        let err = validate_commands(
+            "run",
+            "corr",
+            &[activity("1", "echo"), timer(None), child("", "")],
+            true,
+        )
+        .unwrap_err();
+        assert_matches!(
+            err,
+            CompleteWfError::InvalidCommand {
+                command: "StartTimer",
+                field: "start_to_fire_timeout",
+                run_id,
+                correlation_id,
+                ..
+            } if run_id == "run" && correlation_id == "corr"
+        );
+    }
+}
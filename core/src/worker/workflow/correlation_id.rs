@@ -0,0 +1,54 @@
+//! Correlation ids, which identify a single activation (and its completion) so that lang and core
+//! logs about it can be matched up. Run ids are no good for this, since a run sees many
+//! activations. The ids are [ULIDs](https://github.com/ulid/spec): 26 characters of Crockford's
+//! base32 encoding a millisecond timestamp followed by 80 random bits, so they sort by creation
+//! time.
+
+use rand::Rng;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const CROCKFORD_ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const RANDOM_BITS: u32 = 80;
+
+/// Generate a new correlation id for an activation
+pub(super) fn new_correlation_id() -> String {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    ulid(millis, rand::thread_rng().gen())
+}
+
+/// Encode a ULID from the low 48 bits of `millis` and the low 80 bits of `random`
+fn ulid(millis: u64, random: u128) -> String {
+    let value = (u128::from(millis & 0xFFFF_FFFF_FFFF) << RANDOM_BITS)
+        | (random & ((1 << RANDOM_BITS) - 1));
+    (0..26)
+        .rev()
+        .map(|i| CROCKFORD_ALPHABET[((value >> (5 * i)) & 0x1F) as usize] as char)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_ulids() {
+        assert_eq!(ulid(0, 0), "00000000000000000000000000");
+        assert_eq!(ulid(u64::MAX, u128::MAX), "7ZZZZZZZZZZZZZZZZZZZZZZZZZ");
+        // The timestamp of the spec's example ULID
+        assert_eq!(&ulid(1469918176385, 0)[..10], "01ARYZ6S41");
+        assert!(ulid(1, 0) > ulid(0, u128::MAX));
+    }
+
+    #[test]
+    fn new_ids_are_unique_ulids() {
+        let (a, b) = (new_correlation_id(), new_correlation_id());
+        assert_ne!(a, b);
+        for id in [a, b] {
+            assert_eq!(id.len(), 26);
+            assert!(id.bytes().all(|c| CROCKFORD_ALPHABET.contains(&c)));
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
     telemetry::VecDisplayer,
-    worker::workflow::{OutgoingJob, WFCommand, WorkflowStartedInfo},
+    worker::workflow::{job_ordinal, OutgoingJob, WFCommand, WorkflowStartedInfo},
 };
 use prost_types::Timestamp;
 use std::{
@@ -8,7 +8,9 @@ use std::{
     sync::mpsc::{self, Receiver, Sender},
 };
 use temporal_sdk_core_protos::{
-    coresdk::workflow_activation::{start_workflow_from_attribs, WorkflowActivationJob},
+    coresdk::workflow_activation::{
+        start_workflow_from_attribs, workflow_activation_job, WorkflowActivationJob,
+    },
     temporal::api::{common::v1::Payload, history::v1::WorkflowExecutionStartedEventAttributes},
     utilities::TryIntoOrNone,
 };
@@ -21,6 +23,13 @@ pub(crate) struct DrivenWorkflow {
     incoming_commands: Receiver<Vec<WFCommand>>,
     /// Outgoing activation jobs that need to be sent to the lang sdk
     outgoing_wf_activation_jobs: Vec<OutgoingJob>,
+    /// The randomness seed in the most recent activation sent to lang which carried one
+    delivered_randomness_seed: Option<u64>,
+    /// Set once server has accepted the run's terminal command. Lang is done with the run by then,
+    /// so any jobs produced afterwards (ex: by stale local activity resolutions) are discarded.
+    terminal: bool,
+    /// Number of jobs discarded because they were sent after the run became terminal
+    discarded_jobs: usize,
 }
 
 impl DrivenWorkflow {
@@ -32,6 +41,9 @@ impl DrivenWorkflow {
                 search_attribute_modifications: Default::default(),
                 incoming_commands: rx,
                 outgoing_wf_activation_jobs: vec![],
+                delivered_randomness_seed: None,
+                terminal: false,
+                discarded_jobs: 0,
             },
             tx,
         )
@@ -40,19 +52,26 @@ impl DrivenWorkflow {
     pub(super) fn start(
         &mut self,
         workflow_id: String,
+        run_id: &str,
         randomness_seed: u64,
         start_time: Timestamp,
         attribs: WorkflowExecutionStartedEventAttributes,
     ) {
-        debug!(run_id = %attribs.original_execution_run_id, "Driven WF start");
+        debug!(run_id = %attribs.original_execution_run_id, workflow_id = %workflow_id,
+               "Driven WF start");
         let started_info = WorkflowStartedInfo {
             workflow_task_timeout: attribs.workflow_task_timeout.try_into_or_none(),
             memo: attribs.memo.clone(),
             search_attrs: attribs.search_attributes.clone(),
             retry_policy: attribs.retry_policy.clone(),
+            cron_schedule: attribs.cron_schedule.clone(),
+            execution_expiration_time: attribs
+                .workflow_execution_expiration_time
+                .try_into_or_none(),
         };
         self.send_job(
-            start_workflow_from_attribs(attribs, workflow_id, randomness_seed, start_time).into(),
+            start_workflow_from_attribs(attribs, workflow_id, run_id, randomness_seed, start_time)
+                .into(),
         );
         self.started_attrs = Some(started_info);
     }
@@ -62,22 +81,88 @@ impl DrivenWorkflow {
         self.started_attrs.as_ref()
     }
 
-    /// Enqueue a new job to be sent to the driven workflow
+    /// Enqueue a new job to be sent to the driven workflow. Does nothing once the run is terminal.
     pub(super) fn send_job(&mut self, job: OutgoingJob) {
+        if self.terminal {
+            self.discarded_jobs += 1;
+            debug!(job = %job.variant, discarded = self.discarded_jobs,
+                   "Discarding job sent to run after its completion");
+            return;
+        }
+        debug!(job = %job.variant, "Queueing job for driven WF");
         self.outgoing_wf_activation_jobs.push(job);
     }
 
+    /// Record that server accepted the run's terminal command. Pending jobs are discarded, as are
+    /// any sent from now on, so that nothing more is delivered to lang before the run is evicted.
+    pub(super) fn mark_terminal(&mut self) {
+        if self.terminal {
+            return;
+        }
+        self.terminal = true;
+        if !self.outgoing_wf_activation_jobs.is_empty() {
+            debug!(
+                jobs = self.outgoing_wf_activation_jobs.len(),
+                "Discarding jobs pending for run after its completion"
+            );
+            self.discarded_jobs += self.outgoing_wf_activation_jobs.len();
+            self.outgoing_wf_activation_jobs = vec![];
+        }
+    }
+
+    /// Number of jobs discarded because they arrived after [Self::mark_terminal]
+    #[cfg(test)]
+    pub(super) fn discarded_jobs(&self) -> usize {
+        self.discarded_jobs
+    }
+
     /// Observe pending jobs
     pub(super) fn peek_pending_jobs(&self) -> &[OutgoingJob] {
         self.outgoing_wf_activation_jobs.as_slice()
     }
 
-    /// Drain all pending jobs, so that they may be sent to the driven workflow
-    pub(super) fn drain_jobs(&mut self) -> Vec<WorkflowActivationJob> {
+    /// Drain pending jobs in delivery order (see [job_ordinal]), so that they may be sent to the
+    /// driven workflow. The pending job buffer keeps its capacity, so it is reused for the run's
+    /// later activations, and jobs are moved (not cloned) into the exactly-sized output.
+    ///
+    /// Ordering is stable, so jobs of the same class keep the order they were sent in, which is
+    /// the order their events appear in history. Signals and updates therefore always precede a
+    /// cancellation which arrived in the same task.
+    ///
+    /// If there are more than `max_jobs`, only the first that many are drained. The rest stay
+    /// pending, ahead of any jobs of their class added later, and so go out in the run's next
+    /// activation.
+    pub(super) fn drain_jobs(&mut self, max_jobs: Option<usize>) -> Vec<WorkflowActivationJob> {
         self.outgoing_wf_activation_jobs
-            .drain(..)
+            .sort_by_key(|j| job_ordinal(&j.variant));
+        let num_jobs = max_jobs.map_or(self.outgoing_wf_activation_jobs.len(), |max| {
+            max.min(self.outgoing_wf_activation_jobs.len())
+        });
+        let jobs: Vec<WorkflowActivationJob> = self
+            .outgoing_wf_activation_jobs
+            .drain(..num_jobs)
             .map(Into::into)
-            .collect()
+            .collect();
+        if !jobs.is_empty() {
+            debug!(jobs = %jobs.display(), pending = self.outgoing_wf_activation_jobs.len(),
+                   "Draining jobs for driven WF");
+        }
+        let seed = jobs.iter().rev().find_map(|j| match &j.variant {
+            Some(workflow_activation_job::Variant::InitializeWorkflow(i)) => {
+                Some(i.randomness_seed)
+            }
+            Some(workflow_activation_job::Variant::UpdateRandomSeed(u)) => Some(u.randomness_seed),
+            _ => None,
+        });
+        if seed.is_some() {
+            self.delivered_randomness_seed = seed;
+        }
+        jobs
+    }
+
+    /// The randomness seed lang should currently be using, as of the activations drained so far
+    pub(super) fn delivered_randomness_seed(&self) -> Option<u64> {
+        self.delivered_randomness_seed
     }
 
     /// Obtain any output from the workflow's recent execution(s). Because the lang sdk is
@@ -102,7 +187,7 @@ impl DrivenWorkflow {
         let mut retme = self
             .started_attrs
             .as_ref()
-            .and_then(|si| si.search_attrs.as_ref().map(|sa| sa.indexed_fields.clone()))
+            .and_then(|si| si.search_attrs.clone().map(HashMap::from))
             .unwrap_or_default();
         retme.extend(
             self.search_attribute_modifications
@@ -112,3 +197,158 @@ impl DrivenWorkflow {
         retme
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::seq::SliceRandom;
+    use temporal_sdk_core_protos::coresdk::{
+        workflow_activation::{
+            CancelWorkflow, DoUpdate, FireTimer, NotifyHasPatch, ResolveActivity, SignalWorkflow,
+            UpdateRandomSeed,
+        },
+        workflow_commands::StartTimer,
+    };
+
+    fn label(j: &WorkflowActivationJob) -> String {
+        match j.variant.as_ref().unwrap() {
+            workflow_activation_job::Variant::SignalWorkflow(s) => {
+                format!("SignalWorkflow({})", s.signal_name)
+            }
+            workflow_activation_job::Variant::DoUpdate(u) => format!("DoUpdate({})", u.id),
+            workflow_activation_job::Variant::ResolveActivity(r) if r.is_local => {
+                format!("ResolveLocalActivity({})", r.seq)
+            }
+            workflow_activation_job::Variant::ResolveActivity(r) => {
+                format!("ResolveActivity({})", r.seq)
+            }
+            other => other.to_string(),
+        }
+    }
+
+    /// The class each job belongs in, in delivery order
+    fn class(label: &str) -> usize {
+        [
+            "NotifyHasPatch",
+            "UpdateRandomSeed",
+            "SignalWorkflow|DoUpdate",
+            "FireTimer|ResolveActivity|CancelWorkflow",
+            "ResolveLocalActivity",
+        ]
+        .iter()
+        .position(|c| c.split('|').any(|prefix| label.starts_with(prefix)))
+        .unwrap()
+    }
+
+    fn mixed_jobs() -> Vec<workflow_activation_job::Variant> {
+        let signal = |name: &str| -> workflow_activation_job::Variant {
+            SignalWorkflow {
+                signal_name: name.to_string(),
+                ..Default::default()
+            }
+            .into()
+        };
+        let activity = |seq, is_local| -> workflow_activation_job::Variant {
+            ResolveActivity {
+                seq,
+                is_local,
+                ..Default::default()
+            }
+            .into()
+        };
+        vec![
+            NotifyHasPatch::default().into(),
+            UpdateRandomSeed::default().into(),
+            signal("a"),
+            signal("b"),
+            DoUpdate {
+                id: "u".to_string(),
+                ..Default::default()
+            }
+            .into(),
+            signal("c"),
+            FireTimer { seq: 1 }.into(),
+            activity(2, false),
+            CancelWorkflow::default().into(),
+            FireTimer { seq: 3 }.into(),
+            activity(4, true),
+            activity(5, true),
+        ]
+    }
+
+    #[test]
+    fn drained_jobs_are_ordered_by_class_then_arrival() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..50 {
+            let mut sent = mixed_jobs();
+            sent.shuffle(&mut rng);
+            let (mut wf, _tx) = DrivenWorkflow::new();
+            let mut expected = vec![];
+            for v in sent {
+                expected.push(label(&WorkflowActivationJob {
+                    variant: Some(v.clone()),
+                }));
+                wf.send_job(v.into());
+            }
+            expected.sort_by_key(|l| class(l));
+
+            let drained: Vec<_> = wf.drain_jobs(None).iter().map(label).collect();
+            assert_eq!(drained, expected);
+            let cancel = drained.iter().position(|l| l == "CancelWorkflow").unwrap();
+            assert!(!drained[cancel..]
+                .iter()
+                .any(|l| l.starts_with("SignalWorkflow") || l.starts_with("DoUpdate")));
+        }
+    }
+
+    #[test]
+    fn capped_drains_continue_in_the_same_order() {
+        let (mut wf, _tx) = DrivenWorkflow::new();
+        for v in mixed_jobs().into_iter().rev() {
+            wf.send_job(v.into());
+        }
+        let mut drained = vec![];
+        while !wf.peek_pending_jobs().is_empty() {
+            let batch = wf.drain_jobs(Some(5));
+            assert!(batch.len() <= 5);
+            drained.extend(batch.iter().map(label));
+        }
+        assert_eq!(
+            drained,
+            [
+                "NotifyHasPatch",
+                "UpdateRandomSeed",
+                "SignalWorkflow(c)",
+                "DoUpdate(u)",
+                "SignalWorkflow(b)",
+                "SignalWorkflow(a)",
+                "FireTimer(3)",
+                "CancelWorkflow",
+                "ResolveActivity(2)",
+                "FireTimer(1)",
+                "ResolveLocalActivity(5)",
+                "ResolveLocalActivity(4)",
+            ]
+        );
+    }
+
+    #[test]
+    fn fetching_output_never_waits_on_lang() {
+        let (mut wf, tx) = DrivenWorkflow::new();
+        assert_matches!(
+            wf.fetch_workflow_iteration_output().as_slice(),
+            [WFCommand::NoCommandsFromLang]
+        );
+        tx.send(vec![StartTimer::default().into()]).unwrap();
+        assert_matches!(
+            wf.fetch_workflow_iteration_output().as_slice(),
+            [WFCommand::AddTimer(_)]
+        );
+        // Nor does it matter if the sending side is gone
+        drop(tx);
+        assert_matches!(
+            wf.fetch_workflow_iteration_output().as_slice(),
+            [WFCommand::NoCommandsFromLang]
+        );
+    }
+}
@@ -0,0 +1,157 @@
+//! Noticing when lang appears to be dropping jobs. A lang bug (ex: a signal handler registered
+//! under the wrong name) can lead it to complete every activation for a run without doing anything,
+//! in which case the run silently stops making progress.
+//! See [temporal_sdk_core_api::worker::WorkerConfig::dropped_job_detection_threshold].
+//!
+//! Only completions of activations containing jobs workflows must answer with commands (updates,
+//! cancellation) are counted. Activations which only resolve timers, activities and the like are
+//! ignored, since it's normal for a workflow to wait through many of those for some condition.
+//! Signals are too, since a signal handler which only updates workflow state is routine.
+
+use std::collections::BTreeSet;
+use temporal_sdk_core_protos::coresdk::workflow_activation::{
+    workflow_activation_job, WorkflowActivation,
+};
+
+/// Names of the jobs in the activation which lang must react to with commands
+pub(super) fn actionable_jobs(act: &WorkflowActivation) -> impl Iterator<Item = &'static str> + '_ {
+    act.jobs.iter().filter_map(|j| match j.variant.as_ref()? {
+        workflow_activation_job::Variant::DoUpdate(_) => Some("DoUpdate"),
+        workflow_activation_job::Variant::CancelWorkflow(_) => Some("CancelWorkflow"),
+        _ => None,
+    })
+}
+
+/// Counts, for one run, consecutive completions which ignored actionable jobs
+#[derive(Debug, Default)]
+pub(super) struct NoopCompletionTracker {
+    threshold: Option<usize>,
+    /// Actionable jobs in the outstanding activation
+    outstanding: BTreeSet<&'static str>,
+    consecutive: usize,
+    /// Actionable jobs across the current streak of ignoring completions
+    ignored: BTreeSet<&'static str>,
+}
+
+impl NoopCompletionTracker {
+    pub(super) fn new(threshold: Option<usize>) -> Self {
+        Self {
+            threshold,
+            ..Default::default()
+        }
+    }
+
+    /// Record the jobs of an activation just issued to lang
+    pub(super) fn activation_issued(&mut self, act: &WorkflowActivation) {
+        if self.threshold.is_some() {
+            self.outstanding = actionable_jobs(act).collect();
+        }
+    }
+
+    /// Record lang's completion of the outstanding activation. Returns the actionable jobs ignored
+    /// in a row if this completion brought the streak to the threshold.
+    pub(super) fn completed(&mut self, did_something: bool) -> Option<Vec<&'static str>> {
+        let threshold = self.threshold?;
+        let outstanding = std::mem::take(&mut self.outstanding);
+        if did_something {
+            self.consecutive = 0;
+            self.ignored.clear();
+            return None;
+        }
+        if outstanding.is_empty() {
+            return None;
+        }
+        self.consecutive += 1;
+        self.ignored.extend(outstanding);
+        (self.consecutive == threshold).then(|| self.ignored.iter().copied().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use temporal_sdk_core_protos::coresdk::workflow_activation::{
+        CancelWorkflow, DoUpdate, FireTimer, SignalWorkflow, WorkflowActivationJob,
+    };
+
+    fn act(jobs: Vec<workflow_activation_job::Variant>) -> WorkflowActivation {
+        WorkflowActivation {
+            jobs: jobs
+                .into_iter()
+                .map(|v| WorkflowActivationJob { variant: Some(v) })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn signal() -> WorkflowActivation {
+        act(vec![SignalWorkflow::default().into()])
+    }
+
+    fn update() -> WorkflowActivation {
+        act(vec![DoUpdate::default().into()])
+    }
+
+    fn timer() -> WorkflowActivation {
+        act(vec![FireTimer::default().into()])
+    }
+
+    #[test]
+    fn trips_once_per_streak_of_ignored_jobs() {
+        let mut tracker = NoopCompletionTracker::new(Some(3));
+        for _ in 0..2 {
+            tracker.activation_issued(&update());
+            assert_eq!(tracker.completed(false), None);
+        }
+        tracker.activation_issued(&act(vec![CancelWorkflow::default().into()]));
+        assert_eq!(
+            tracker.completed(false),
+            Some(vec!["CancelWorkflow", "DoUpdate"])
+        );
+        tracker.activation_issued(&update());
+        assert_eq!(tracker.completed(false), None);
+
+        // Doing anything starts a new streak
+        tracker.activation_issued(&update());
+        assert_eq!(tracker.completed(true), None);
+        for _ in 0..2 {
+            tracker.activation_issued(&update());
+            assert_eq!(tracker.completed(false), None);
+        }
+        tracker.activation_issued(&update());
+        assert_eq!(tracker.completed(false), Some(vec!["DoUpdate"]));
+    }
+
+    #[test]
+    fn ignoring_signals_is_not_counted() {
+        let mut tracker = NoopCompletionTracker::new(Some(2));
+        for _ in 0..10 {
+            tracker.activation_issued(&signal());
+            assert_eq!(tracker.completed(false), None);
+        }
+        assert_eq!(tracker.consecutive, 0);
+    }
+
+    #[test]
+    fn waiting_through_resolutions_is_not_counted() {
+        let mut tracker = NoopCompletionTracker::new(Some(2));
+        tracker.activation_issued(&update());
+        assert_eq!(tracker.completed(false), None);
+        for _ in 0..10 {
+            tracker.activation_issued(&timer());
+            assert_eq!(tracker.completed(false), None);
+        }
+        // A reactivation with nothing actionable pending isn't counted either
+        assert_eq!(tracker.completed(false), None);
+        assert_eq!(tracker.consecutive, 1);
+    }
+
+    #[test]
+    fn disabled_never_trips() {
+        let mut tracker = NoopCompletionTracker::new(None);
+        for _ in 0..10 {
+            tracker.activation_issued(&update());
+            assert_eq!(tracker.completed(false), None);
+        }
+    }
+}
@@ -7,22 +7,32 @@ use crate::{
 };
 use futures_util::{future::BoxFuture, FutureExt, Stream, TryFutureExt};
 use itertools::Itertools;
+use prost::Message;
 use std::{
     collections::VecDeque,
     fmt::Debug,
     future::Future,
     mem,
     mem::transmute,
+    ops::Range,
     pin::Pin,
     sync::{Arc, LazyLock},
     task::{Context, Poll},
 };
-use temporal_sdk_core_protos::temporal::api::{
-    enums::v1::EventType,
-    history::v1::{
-        history_event, history_event::Attributes, History, HistoryEvent,
-        WorkflowTaskCompletedEventAttributes,
+use temporal_sdk_core_protos::{
+    temporal::api::{
+        enums::v1::EventType,
+        history::v1::{
+            history_event, history_event::Attributes, History, HistoryEvent,
+            WorkflowTaskCompletedEventAttributes,
+        },
+        workflowservice::v1::GetWorkflowExecutionHistoryResponse,
     },
+    RunId, WorkflowId,
+};
+use tokio::{
+    sync::{OwnedSemaphorePermit, Semaphore},
+    task::JoinHandle,
 };
 use tracing::Instrument;
 
@@ -31,6 +41,35 @@ static EMPTY_FETCH_ERR: LazyLock<tonic::Status> =
 static EMPTY_TASK_ERR: LazyLock<tonic::Status> = LazyLock::new(|| {
     tonic::Status::unknown("Received an empty workflow task with no queries or history")
 });
+/// Attached as the details of the error [history_too_large_err] produces, so it can be recognized
+/// without depending on its message, and never confused with a resource exhausted error from the
+/// server.
+const HISTORY_TOO_LARGE_DETAILS: &[u8] = b"temporal-sdk-core/history-fetch-limit-exceeded";
+
+fn history_too_large_err(fetched_bytes: usize, limit: usize) -> tonic::Status {
+    tonic::Status::with_details(
+        tonic::Code::ResourceExhausted,
+        format!(
+            "History too large for this worker's configured limit: fetched {fetched_bytes} bytes \
+             of it, but max_history_fetch_bytes is {limit}. Route this workflow to workers with a \
+             higher limit."
+        ),
+        HISTORY_TOO_LARGE_DETAILS.into(),
+    )
+}
+
+/// True if fetching history failed because it exceeded
+/// [temporal_sdk_core_api::worker::WorkerConfig::max_history_fetch_bytes]
+pub(super) fn is_history_too_large(err: &tonic::Status) -> bool {
+    err.code() == tonic::Code::ResourceExhausted && err.details() == HISTORY_TOO_LARGE_DETAILS
+}
+
+fn incomplete_history_err(last_event_id: i64, wft_started_event_id: i64) -> tonic::Status {
+    tonic::Status::data_loss(format!(
+        "Incomplete history from server: history ends at event {last_event_id}, before the \
+         workflow task started event {wft_started_event_id}"
+    ))
+}
 
 /// Represents one or more complete WFT sequences. History events are expected to be consumed from
 /// it and applied to the state machines via [HistoryUpdate::take_next_wft_sequence]
@@ -47,6 +86,19 @@ pub(crate) struct HistoryUpdate {
     /// additional updates should be made.
     has_last_wft: bool,
     wft_count: usize,
+    /// Approximate memory held by `events`, kept up to date as they are consumed
+    retained_bytes: usize,
+    /// How much history the paginator this update came from had fetched when extracting it
+    pub(crate) fetch_progress: FetchProgress,
+}
+
+/// How much of a run's history a [HistoryPaginator] has fetched so far
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FetchProgress {
+    /// Number of history pages fetched
+    pub(crate) pages_fetched: u64,
+    /// The id of the last event received, either with the task or in a fetched page
+    pub(crate) last_known_event_id: i64,
 }
 
 impl Debug for HistoryUpdate {
@@ -83,18 +135,44 @@ pub(crate) enum NextWFT {
 #[derive(derive_more::Debug)]
 #[debug("HistoryPaginator(run_id: {run_id})")]
 pub(crate) struct HistoryPaginator {
-    pub(crate) wf_id: String,
-    pub(crate) run_id: String,
+    pub(crate) wf_id: WorkflowId,
+    pub(crate) run_id: RunId,
     pub(crate) previous_wft_started_id: i64,
     pub(crate) wft_started_event_id: i64,
     id_of_last_event_in_last_extracted_update: Option<i64>,
 
     client: Arc<dyn WorkerClient>,
+    /// See [temporal_sdk_core_api::worker::WorkerConfig::max_history_fetch_bytes]
+    max_fetch_bytes: Option<usize>,
+    /// Encoded size of the pages fetched so far
+    fetched_bytes: usize,
+    /// Shared by every paginator of the worker, so that page fetches, prefetches included, stay
+    /// within [temporal_sdk_core_api::worker::WorkerConfig::fetching_concurrency]. Unlimited if
+    /// unset.
+    fetch_permits: Option<Arc<Semaphore>>,
     event_queue: VecDeque<HistoryEvent>,
     next_page_token: NextPageToken,
     /// These are events that should be returned once pagination has finished. This only happens
     /// during cache misses, where we got a partial task but need to fetch history from the start.
     final_events: Vec<HistoryEvent>,
+    fetch_progress: FetchProgress,
+    /// The next page, being fetched while the run applies the last update extracted. Dropping the
+    /// paginator (ex: because the run was evicted or failed) cancels it.
+    prefetch: Option<Prefetch>,
+}
+
+/// A fetch of the page after the last one extracted. Only one page is ever fetched ahead, which
+/// bounds how much history is held on top of what the run is applying. Holds a fetch permit until
+/// the page arrives.
+struct Prefetch {
+    page_token: Vec<u8>,
+    fetch: JoinHandle<Result<GetWorkflowExecutionHistoryResponse, tonic::Status>>,
+}
+
+impl Drop for Prefetch {
+    fn drop(&mut self) {
+        self.fetch.abort();
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -120,26 +198,49 @@ impl From<Vec<u8>> for NextPageToken {
 
 impl HistoryPaginator {
     /// Use a new poll response to create a new [WFTPaginator], returning it and the
-    /// [PreparedWFT] extracted from it that can be fed into workflow state.
+    /// [PreparedWFT] extracted from it that can be fed into workflow state. Rebuilding the run
+    /// fails once fetching its history takes more than `max_fetch_bytes`. Each page fetched holds
+    /// one of `fetch_permits` while in flight.
     pub(super) async fn from_poll(
         wft: ValidPollWFTQResponse,
         client: Arc<dyn WorkerClient>,
+        max_fetch_bytes: Option<usize>,
+        fetch_permits: Arc<Semaphore>,
     ) -> Result<(Self, PreparedWFT), tonic::Status> {
         let empty_hist = wft.history.events.is_empty();
+        let last_event_id = wft.history.events.last().map(|e| e.event_id);
         let npt = if empty_hist {
             NextPageToken::FetchFromStart
         } else {
             wft.next_page_token.into()
         };
+        // Server has (ex: when retrying a task after a transient failure) been seen to send a
+        // complete-looking history which stops short of the task's own started event. Only the
+        // missing tail is kept from a refetch, since the events we have are queued first.
+        let missing_tail = matches!(npt, NextPageToken::Done)
+            && last_event_id.is_some_and(|id| id < wft.started_event_id);
+        if missing_tail {
+            warn!(
+                run_id=%wft.workflow_execution.run_id,
+                last_event_id,
+                started_event_id = wft.started_event_id,
+                "Workflow task history ends before its started event, refetching it"
+            );
+        }
         let mut paginator = HistoryPaginator::new(
             wft.history,
             wft.previous_started_event_id,
             wft.started_event_id,
-            wft.workflow_execution.workflow_id.clone(),
-            wft.workflow_execution.run_id.clone(),
+            wft.workflow_execution.workflow_id.as_str().into(),
+            wft.workflow_execution.run_id.as_str().into(),
             npt,
             client,
         );
+        paginator.max_fetch_bytes = max_fetch_bytes;
+        paginator.fetch_permits = Some(fetch_permits);
+        if missing_tail {
+            paginator.next_page_token = NextPageToken::FetchFromStart;
+        }
         if empty_hist && wft.legacy_query.is_none() && wft.query_requests.is_empty() {
             return Err(EMPTY_TASK_ERR.clone());
         }
@@ -163,6 +264,7 @@ impl HistoryPaginator {
             query_requests: wft.query_requests,
             update,
             messages: wft.messages,
+            task_queue_kind: wft.task_queue_kind,
         };
         Ok((paginator, prepared))
     }
@@ -171,9 +273,26 @@ impl HistoryPaginator {
         mut req: Box<CacheMissFetchReq>,
         client: Arc<dyn WorkerClient>,
     ) -> Result<PermittedWFT, tonic::Status> {
+        // History handed over from another worker already covers everything before the task's
+        // own events, so there's nothing to fetch
+        let (event_queue, next_page_token, final_events) = match req.handed_over_history.take() {
+            Some(preceding) => (
+                preceding
+                    .into_iter()
+                    .chain(mem::take(&mut req.original_wft.work.update.events))
+                    .collect(),
+                NextPageToken::Done,
+                vec![],
+            ),
+            None => (
+                Default::default(),
+                NextPageToken::FetchFromStart,
+                mem::take(&mut req.original_wft.work.update.events),
+            ),
+        };
         let mut paginator = Self {
-            wf_id: req.original_wft.work.execution.workflow_id.clone(),
-            run_id: req.original_wft.work.execution.run_id.clone(),
+            wf_id: req.original_wft.work.execution.workflow_id.as_str().into(),
+            run_id: req.original_wft.work.execution.run_id.as_str().into(),
             previous_wft_started_id: req.original_wft.work.update.previous_wft_started_id,
             wft_started_event_id: req.original_wft.work.update.wft_started_id,
             id_of_last_event_in_last_extracted_update: req
@@ -181,9 +300,14 @@ impl HistoryPaginator {
                 .paginator
                 .id_of_last_event_in_last_extracted_update,
             client,
-            event_queue: Default::default(),
-            next_page_token: NextPageToken::FetchFromStart,
-            final_events: req.original_wft.work.update.events,
+            max_fetch_bytes: req.original_wft.paginator.max_fetch_bytes,
+            fetched_bytes: 0,
+            fetch_permits: req.original_wft.paginator.fetch_permits.clone(),
+            event_queue,
+            next_page_token,
+            fetch_progress: req.original_wft.paginator.fetch_progress,
+            final_events,
+            prefetch: None,
         };
         let first_update = paginator.extract_next_update().await?;
         req.original_wft.work.update = first_update;
@@ -195,12 +319,20 @@ impl HistoryPaginator {
         initial_history: History,
         previous_wft_started_id: i64,
         wft_started_event_id: i64,
-        wf_id: String,
-        run_id: String,
+        wf_id: WorkflowId,
+        run_id: RunId,
         next_page_token: impl Into<NextPageToken>,
         client: Arc<dyn WorkerClient>,
     ) -> Self {
         let next_page_token = next_page_token.into();
+        let fetch_progress = FetchProgress {
+            pages_fetched: 0,
+            last_known_event_id: initial_history
+                .events
+                .last()
+                .map(|e| e.event_id)
+                .unwrap_or_default(),
+        };
         let (event_queue, final_events) =
             if matches!(next_page_token, NextPageToken::FetchFromStart) {
                 (VecDeque::new(), initial_history.events)
@@ -209,6 +341,9 @@ impl HistoryPaginator {
             };
         Self {
             client,
+            max_fetch_bytes: None,
+            fetched_bytes: 0,
+            fetch_permits: None,
             event_queue,
             wf_id,
             run_id,
@@ -217,6 +352,8 @@ impl HistoryPaginator {
             previous_wft_started_id,
             wft_started_event_id,
             id_of_last_event_in_last_extracted_update: None,
+            fetch_progress,
+            prefetch: None,
         }
     }
 
@@ -248,8 +385,8 @@ impl HistoryPaginator {
             // an update at that time. But, if the page has a next page token, we *cannot* conclude
             // we are done with replay until we fetch that page. So, we have to wait until the next
             // extraction to determine (after fetching the next page and finding it to be empty)
-            // that we are done. Fetching the page eagerly is another option, but would be wasteful
-            // the overwhelming majority of the time.
+            // that we are done. The next page is already being prefetched by then, but waiting for
+            // it before returning would hold up every update just to handle this case.
             let already_sent_update_with_enough_events = self
                 .id_of_last_event_in_last_extracted_update
                 .unwrap_or_default()
@@ -257,33 +394,51 @@ impl HistoryPaginator {
             if current_events.is_empty() && no_next_page && already_sent_update_with_enough_events {
                 // We must return an empty update which also says is contains the final WFT so we
                 // know we're done with replay.
-                return Ok(HistoryUpdate::from_events(
+                let mut update = HistoryUpdate::from_events(
                     [],
                     self.previous_wft_started_id,
                     self.wft_started_event_id,
                     true,
                 )
-                .0);
+                .0;
+                update.fetch_progress = self.fetch_progress;
+                return Ok(update);
             }
 
-            if current_events.is_empty() || (no_next_page && !seen_enough_events) {
-                // If next page fetching happened, and we still ended up with no or insufficient
-                // events, something is wrong. We're expecting there to be more events to be able to
-                // extract this update, but server isn't giving us any. We have no choice except to
-                // give up and evict.
+            // Otherwise, if next page fetching happened and we still ended up with no or
+            // insufficient events, something is wrong. We're expecting there to be more events to
+            // be able to extract this update, but server isn't giving us any. We have no choice
+            // except to give up and evict. Server sending nothing at all is reported separately
+            // from history which stops short of the task, so the latter names where it stopped.
+            if current_events.is_empty() {
                 error!(
-                    current_events=?current_events,
                     no_next_page,
-                    seen_enough_events,
-                    "We expected to be able to fetch more events but server says there are none"
+                    last_extracted = self.id_of_last_event_in_last_extracted_update,
+                    wft_started_event_id = self.wft_started_event_id,
+                    "We expected to be able to fetch more events but server sent none"
                 );
                 return Err(EMPTY_FETCH_ERR.clone());
             }
+            if no_next_page && !seen_enough_events {
+                error!(
+                    current_events=?current_events,
+                    wft_started_event_id = self.wft_started_event_id,
+                    "We expected to be able to fetch more events but server says there are none"
+                );
+                let last_event_id = current_events
+                    .back()
+                    .map(|e| e.event_id)
+                    .unwrap_or_default();
+                return Err(incomplete_history_err(
+                    last_event_id,
+                    self.wft_started_event_id,
+                ));
+            }
             let first_event_id = current_events.front().unwrap().event_id;
             // We only *really* have the last WFT if the events go all the way up to at least the
             // WFT started event id. Otherwise we somehow still have partial history.
             let no_more = matches!(self.next_page_token, NextPageToken::Done) && seen_enough_events;
-            let (update, extra) = HistoryUpdate::from_events(
+            let (mut update, extra) = HistoryUpdate::from_events(
                 current_events,
                 self.previous_wft_started_id,
                 self.wft_started_event_id,
@@ -312,8 +467,10 @@ impl HistoryPaginator {
             }
             self.id_of_last_event_in_last_extracted_update =
                 update.events.last().map(|e| e.event_id);
+            update.fetch_progress = self.fetch_progress;
             #[cfg(debug_assertions)]
             update.assert_contiguous();
+            self.start_prefetch();
             return Ok(update);
         }
     }
@@ -325,17 +482,46 @@ impl HistoryPaginator {
             let npt = match mem::replace(&mut self.next_page_token, NextPageToken::Done) {
                 // If the last page token we got was empty, we're done.
                 NextPageToken::Done => break None,
-                NextPageToken::FetchFromStart => vec![],
+                NextPageToken::FetchFromStart => {
+                    // Whatever was extracted before came from another source (ex: a sticky task's
+                    // partial history), so says nothing about how far the fetched history goes
+                    self.id_of_last_event_in_last_extracted_update = None;
+                    vec![]
+                }
                 NextPageToken::Next(v) => v,
             };
-            debug!(run_id=%self.run_id, "Fetching new history page");
-            let fetch_res = self
-                .client
-                .get_workflow_execution_history(self.wf_id.clone(), Some(self.run_id.clone()), npt)
-                .instrument(span!(tracing::Level::TRACE, "fetch_history_in_paginator"))
-                .await?;
+            debug!(
+                run_id=%self.run_id,
+                pages_fetched = self.fetch_progress.pages_fetched,
+                last_known_event_id = self.fetch_progress.last_known_event_id,
+                "Fetching new history page"
+            );
+            let fetch_res = match self.prefetch.take() {
+                Some(mut prefetch) if prefetch.page_token == npt => {
+                    (&mut prefetch.fetch).await.unwrap_or_else(|e| {
+                        Err(tonic::Status::internal(format!(
+                            "History page prefetch failed: {e}"
+                        )))
+                    })?
+                }
+                _ => {
+                    let _permit = self.fetch_permit().await;
+                    self.fetch_page(npt).await?
+                }
+            };
+            self.fetched_bytes += fetch_res.encoded_len();
+            if let Some(limit) = self.max_fetch_bytes {
+                if self.fetched_bytes > limit {
+                    return Err(history_too_large_err(self.fetched_bytes, limit));
+                }
+            }
 
             self.next_page_token = fetch_res.next_page_token.into();
+            self.fetch_progress.pages_fetched += 1;
+            if let Some(last) = fetch_res.history.as_ref().and_then(|h| h.events.last()) {
+                self.fetch_progress.last_known_event_id =
+                    self.fetch_progress.last_known_event_id.max(last.event_id);
+            }
 
             let history_is_empty = fetch_res
                 .history
@@ -377,6 +563,55 @@ impl HistoryPaginator {
         };
         Ok(!matches!(&self.next_page_token, NextPageToken::Done))
     }
+
+    /// Start fetching the next page, if there is one, so it downloads while the run applies what
+    /// it has already been given rather than after it asks for more. Prefetches never wait for a
+    /// fetch permit: if none is free, the page is fetched once the run asks for it instead.
+    fn start_prefetch(&mut self) {
+        if self.prefetch.is_some() {
+            return;
+        }
+        let NextPageToken::Next(page_token) = &self.next_page_token else {
+            return;
+        };
+        let permit = match &self.fetch_permits {
+            Some(permits) => match permits.clone().try_acquire_owned() {
+                Ok(permit) => Some(permit),
+                Err(_) => return,
+            },
+            None => None,
+        };
+        let fetch = self.fetch_page(page_token.clone());
+        self.prefetch = Some(Prefetch {
+            page_token: page_token.clone(),
+            fetch: tokio::spawn(async move {
+                let _permit = permit;
+                fetch.await
+            }),
+        });
+    }
+
+    /// Wait for a permit to fetch a page, if fetches are limited
+    async fn fetch_permit(&self) -> Option<OwnedSemaphorePermit> {
+        let permits = self.fetch_permits.clone()?;
+        permits.acquire_owned().await.ok()
+    }
+
+    fn fetch_page(
+        &self,
+        page_token: Vec<u8>,
+    ) -> impl Future<Output = Result<GetWorkflowExecutionHistoryResponse, tonic::Status>> + 'static
+    {
+        let client = self.client.clone();
+        let wf_id = self.wf_id.clone();
+        let run_id = self.run_id.clone();
+        async move {
+            client
+                .get_workflow_execution_history(wf_id, Some(run_id), page_token)
+                .await
+        }
+        .instrument(span!(tracing::Level::TRACE, "fetch_history_in_paginator"))
+    }
 }
 
 #[pin_project::pin_project]
@@ -441,9 +676,21 @@ impl HistoryUpdate {
             wft_started_id: -1,
             has_last_wft: false,
             wft_count: 0,
+            retained_bytes: 0,
+            fetch_progress: Default::default(),
         }
     }
 
+    /// The id of the last event known to exist in history as of this update
+    pub(crate) fn last_known_event_id(&self) -> i64 {
+        self.events
+            .last()
+            .map(|e| e.event_id)
+            .unwrap_or_default()
+            .max(self.wft_started_id)
+            .max(self.fetch_progress.last_known_event_id)
+    }
+
     pub(crate) fn is_real(&self) -> bool {
         self.previous_wft_started_id >= 0
     }
@@ -489,11 +736,13 @@ impl HistoryUpdate {
             return if has_last_wft {
                 (
                     Self {
+                        retained_bytes: approx_events_bytes(&all_events),
                         events: all_events,
                         previous_wft_started_id,
                         wft_started_id,
                         has_last_wft,
                         wft_count: 1,
+                        fetch_progress: Default::default(),
                     },
                     vec![],
                 )
@@ -505,6 +754,8 @@ impl HistoryUpdate {
                         wft_started_id,
                         has_last_wft,
                         wft_count: 0,
+                        retained_bytes: 0,
+                        fetch_progress: Default::default(),
                     },
                     all_events,
                 )
@@ -537,11 +788,13 @@ impl HistoryUpdate {
 
         (
             Self {
+                retained_bytes: approx_events_bytes(&all_events),
                 events: all_events,
                 previous_wft_started_id,
                 wft_started_id,
                 has_last_wft,
                 wft_count,
+                fetch_progress: Default::default(),
             },
             remaining_events,
         )
@@ -559,12 +812,15 @@ impl HistoryUpdate {
     where
         <I as IntoIterator>::IntoIter: Send + 'static,
     {
+        let events: Vec<_> = events.into_iter().collect();
         Self {
-            events: events.into_iter().collect(),
+            retained_bytes: approx_events_bytes(&events),
+            events,
             previous_wft_started_id,
             wft_started_id,
             has_last_wft: true,
             wft_count: 0,
+            fetch_progress: Default::default(),
         }
     }
 
@@ -578,7 +834,7 @@ impl HistoryUpdate {
     pub(crate) fn take_next_wft_sequence(&mut self, from_wft_started_id: i64) -> NextWFT {
         // First, drop any events from the queue which are earlier than the passed-in id.
         if let Some(ix_first_relevant) = self.starting_index_after_skipping(from_wft_started_id) {
-            self.events.drain(0..ix_first_relevant);
+            self.release(0..ix_first_relevant);
         }
         let next_wft_ix =
             find_end_index_of_next_wft_seq(&self.events, from_wft_started_id, self.has_last_wft);
@@ -605,11 +861,26 @@ impl HistoryUpdate {
 
     fn build_next_wft(&mut self, drain_this_much: usize) -> NextWFT {
         NextWFT::WFT(
-            self.events.drain(0..=drain_this_much).collect(),
+            self.release(0..drain_this_much + 1),
             self.events.is_empty() && self.has_last_wft,
         )
     }
 
+    /// Remove and return the events in `range`, no longer counting them as retained
+    fn release(&mut self, range: Range<usize>) -> Vec<HistoryEvent> {
+        let released: Vec<_> = self.events.drain(range).collect();
+        self.retained_bytes = self
+            .retained_bytes
+            .saturating_sub(approx_events_bytes(&released));
+        released
+    }
+
+    /// Returns the approximate number of bytes of memory held by the events in this update which
+    /// have not yet been consumed
+    pub(crate) fn approx_retained_bytes(&self) -> usize {
+        self.retained_bytes
+    }
+
     /// Lets the caller peek ahead at the next WFT sequence that will be returned by
     /// [take_next_wft_sequence]. Will always return the first available WFT sequence if that has
     /// not been called first. May also return an empty iterator or incomplete sequence if we are at
@@ -688,6 +959,15 @@ impl NextWFTSeqEndIndex {
     }
 }
 
+/// Approximates the memory held by `events` as their in-memory size plus their encoded size, the
+/// latter standing in for everything they own on the heap.
+fn approx_events_bytes(events: &[HistoryEvent]) -> usize {
+    events
+        .iter()
+        .map(|e| mem::size_of::<HistoryEvent>() + e.encoded_len())
+        .sum()
+}
+
 /// Discovers the index of the last event in next WFT sequence within the passed-in slice
 fn find_end_index_of_next_wft_seq(
     events: &[HistoryEvent],
@@ -795,10 +1075,13 @@ mod tests {
     use crate::{
         replay::{HistoryInfo, TestHistoryBuilder},
         test_help::{canned_histories, hist_to_poll_resp, mock_sdk_cfg, MockPollCfg, ResponseType},
-        worker::client::mocks::mock_workflow_client,
+        worker::client::mocks::{mock_manual_workflow_client, mock_workflow_client},
     };
     use futures_util::{StreamExt, TryStreamExt};
-    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
     use temporal_client::WorkflowOptions;
     use temporal_sdk::WfContext;
     use temporal_sdk_core_protos::{
@@ -986,8 +1269,8 @@ mod tests {
             },
             0,
             wft_started,
-            "wfid".to_string(),
-            "runid".to_string(),
+            "wfid".into(),
+            "runid".into(),
             vec![1],
             Arc::new(mock_client),
         )
@@ -1048,6 +1331,148 @@ mod tests {
         });
     }
 
+    #[tokio::test]
+    async fn rebuilds_fail_once_fetched_history_exceeds_the_limit() {
+        let mut paginator = paginator_setup(canned_histories::long_sequential_timers(100), 10);
+        // Each page of ten events takes a few hundred bytes, so only a few fit
+        paginator.max_fetch_bytes = Some(2_000);
+        let err = loop {
+            if let Err(e) = paginator.extract_next_update().await {
+                break e;
+            }
+        };
+        assert!(is_history_too_large(&err));
+        assert!(err
+            .message()
+            .starts_with("History too large for this worker's configured limit"));
+        assert!(paginator.fetched_bytes > 2_000);
+        // Nothing but the limit is mistaken for it, even with the same message
+        assert!(!is_history_too_large(&tonic::Status::resource_exhausted(
+            "namespace rate limit exceeded"
+        )));
+        assert!(!is_history_too_large(&tonic::Status::resource_exhausted(
+            err.message()
+        )));
+    }
+
+    const PAGE_LATENCY: Duration = Duration::from_millis(100);
+
+    /// Like [paginator_setup], but each page takes [PAGE_LATENCY] to arrive. Also returns how many
+    /// pages have arrived.
+    fn slow_paginator_setup(
+        history: TestHistoryBuilder,
+        chunk_size: usize,
+    ) -> (HistoryPaginator, Arc<AtomicUsize>) {
+        let hinfo = history.get_full_history_info().unwrap();
+        let wft_started = hinfo.workflow_task_started_event_id();
+        let full_hist = hinfo.into_events();
+        let initial_hist = full_hist.chunks(chunk_size).next().unwrap().to_vec();
+        let pages_arrived = Arc::new(AtomicUsize::new(0));
+        let mut mock_client = mock_manual_workflow_client();
+        mock_client
+            .expect_get_workflow_execution_history()
+            .returning({
+                let pages_arrived = pages_arrived.clone();
+                move |_, _, passed_npt| {
+                    let page = passed_npt[0] as usize;
+                    let mut hist_chunks = full_hist.chunks(chunk_size).skip(page).peekable();
+                    let events = hist_chunks.next().unwrap_or_default().to_vec();
+                    let next_page_token = if hist_chunks.peek().is_none() {
+                        vec![]
+                    } else {
+                        vec![page as u8 + 1]
+                    };
+                    let pages_arrived = pages_arrived.clone();
+                    async move {
+                        tokio::time::sleep(PAGE_LATENCY).await;
+                        pages_arrived.fetch_add(1, Ordering::Relaxed);
+                        Ok(GetWorkflowExecutionHistoryResponse {
+                            history: Some(History { events }),
+                            next_page_token,
+                            ..Default::default()
+                        })
+                    }
+                    .boxed()
+                }
+            });
+        let paginator = HistoryPaginator::new(
+            History {
+                events: initial_hist,
+            },
+            0,
+            wft_started,
+            "wfid".into(),
+            "runid".into(),
+            vec![1],
+            Arc::new(mock_client),
+        );
+        (paginator, pages_arrived)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn next_page_is_fetched_while_the_last_update_is_applied() {
+        // Pages hold several WFTs, so each extraction only waits on one page
+        let (mut paginator, pages_arrived) =
+            slow_paginator_setup(canned_histories::long_sequential_timers(100), 20);
+        let started = tokio::time::Instant::now();
+        let mut update = paginator.extract_next_update().await.unwrap();
+        let mut updates_applied = 0;
+        let mut last_started_id = 0;
+        loop {
+            match update.take_next_wft_sequence(last_started_id) {
+                NextWFT::WFT(seq, _) => {
+                    last_started_id = seq.last().unwrap().event_id;
+                }
+                NextWFT::NeedFetch => {
+                    // Applying an update takes as long as fetching a page
+                    tokio::time::sleep(PAGE_LATENCY).await;
+                    updates_applied += 1;
+                    update = paginator.extract_next_update().await.unwrap();
+                }
+                NextWFT::ReplayOver => break,
+            }
+        }
+        let elapsed = started.elapsed();
+        let fetched = pages_arrived.load(Ordering::Relaxed) as u32;
+        // Fetching and applying one after the other would take the sum of their time. Only the
+        // first fetch, which precedes anything to apply, isn't overlapped.
+        assert!(updates_applied > 10);
+        assert!(elapsed < PAGE_LATENCY * (fetched + updates_applied) * 3 / 4);
+        assert!(elapsed <= PAGE_LATENCY * (updates_applied + 2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn prefetch_is_cancelled_with_the_paginator() {
+        let (mut paginator, pages_arrived) =
+            slow_paginator_setup(canned_histories::long_sequential_timers(100), 20);
+        paginator.extract_next_update().await.unwrap();
+        assert!(paginator.prefetch.is_some());
+        let arrived = pages_arrived.load(Ordering::Relaxed);
+        // The run is evicted while applying the update
+        drop(paginator);
+        tokio::time::sleep(PAGE_LATENCY * 2).await;
+        assert_eq!(pages_arrived.load(Ordering::Relaxed), arrived);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn prefetches_hold_a_fetch_permit_but_never_wait_for_one() {
+        let (mut paginator, pages_arrived) =
+            slow_paginator_setup(canned_histories::long_sequential_timers(100), 20);
+        let permits = Arc::new(Semaphore::new(1));
+        paginator.fetch_permits = Some(permits.clone());
+        let held = permits.clone().try_acquire_owned().unwrap();
+        paginator.start_prefetch();
+        assert!(paginator.prefetch.is_none());
+
+        drop(held);
+        paginator.start_prefetch();
+        assert!(paginator.prefetch.is_some());
+        assert_eq!(permits.available_permits(), 0);
+        tokio::time::sleep(PAGE_LATENCY * 2).await;
+        assert_eq!(pages_arrived.load(Ordering::Relaxed), 1);
+        assert_eq!(permits.available_permits(), 1);
+    }
+
     fn three_wfts_then_heartbeats() -> TestHistoryBuilder {
         let mut t = TestHistoryBuilder::default();
         // Start with two complete normal WFTs
@@ -1178,8 +1603,8 @@ mod tests {
             partial_task.into(),
             prev_started_wft_id,
             wft_started_id,
-            "wfid".to_string(),
-            "runid".to_string(),
+            "wfid".into(),
+            "runid".into(),
             // A cache miss means we'll try to fetch from start
             NextPageToken::FetchFromStart,
             Arc::new(mock_client),
@@ -1236,8 +1661,8 @@ mod tests {
             partial_task.into(),
             prev_started_wft_id,
             wft_started_id,
-            "wfid".to_string(),
-            "runid".to_string(),
+            "wfid".into(),
+            "runid".into(),
             // A cache miss means we'll try to fetch from start
             NextPageToken::FetchFromStart,
             Arc::new(mock_client),
@@ -1246,6 +1671,32 @@ mod tests {
         assert_matches!(err.code(), tonic::Code::Unknown);
     }
 
+    #[tokio::test]
+    async fn blank_fetch_from_start_is_not_the_end_of_history() {
+        let timer_hist = canned_histories::single_timer("t");
+        let partial_task = timer_hist.get_one_wft(2).unwrap();
+        let prev_started_wft_id = partial_task.previous_started_event_id();
+        let wft_started_id = partial_task.workflow_task_started_event_id();
+        let mut mock_client = mock_workflow_client();
+        mock_client
+            .expect_get_workflow_execution_history()
+            .returning(move |_, _, _| Ok(Default::default()));
+
+        let mut paginator = HistoryPaginator::new(
+            partial_task.into(),
+            prev_started_wft_id,
+            wft_started_id,
+            "wfid".into(),
+            "runid".into(),
+            NextPageToken::FetchFromStart,
+            Arc::new(mock_client),
+        );
+        // As if the partial history had already been handed out before the cache miss
+        paginator.id_of_last_event_in_last_extracted_update = Some(wft_started_id);
+        let err = paginator.extract_next_update().await.unwrap_err();
+        assert_eq!(err.message(), EMPTY_FETCH_ERR.message());
+    }
+
     #[tokio::test]
     async fn handles_empty_page_with_next_token() {
         let timer_hist = canned_histories::single_timer("t");
@@ -1275,8 +1726,8 @@ mod tests {
             partial_task.into(),
             prev_started_wft_id,
             wft_started_id,
-            "wfid".to_string(),
-            "runid".to_string(),
+            "wfid".into(),
+            "runid".into(),
             // A cache miss means we'll try to fetch from start
             NextPageToken::FetchFromStart,
             Arc::new(mock_client),
@@ -1289,6 +1740,75 @@ mod tests {
         assert_matches!(update.take_next_wft_sequence(8), NextWFT::ReplayOver);
     }
 
+    /// A poll response for the single timer history whose events stop short of the final WFT
+    /// started event, with no page token
+    fn poll_missing_wft_started() -> (TestHistoryBuilder, ValidPollWFTQResponse) {
+        let timer_hist = canned_histories::single_timer("t");
+        let mut resp = hist_to_poll_resp(&timer_hist, "wfid", ResponseType::AllHistory).resp;
+        let history = resp.history.as_mut().unwrap();
+        history.events.pop();
+        assert_eq!(history.events.last().unwrap().event_id, 6);
+        resp.next_page_token = vec![];
+        (timer_hist, resp.try_into().unwrap())
+    }
+
+    #[tokio::test]
+    async fn refetches_history_missing_wft_started() {
+        let (timer_hist, wft) = poll_missing_wft_started();
+        let full_resp: GetWorkflowExecutionHistoryResponse =
+            timer_hist.get_full_history_info().unwrap().into();
+        let mut mock_client = mock_workflow_client();
+        mock_client
+            .expect_get_workflow_execution_history()
+            .withf(|_, _, npt| npt.is_empty())
+            .returning(move |_, _, _| Ok(full_resp.clone()))
+            .times(1);
+
+        let (_, mut prepared) = HistoryPaginator::from_poll(
+            wft,
+            Arc::new(mock_client),
+            None,
+            Arc::new(Semaphore::new(1)),
+        )
+        .await
+        .unwrap();
+        let seq = prepared.update.take_next_wft_sequence(0).unwrap_events();
+        assert_eq!(seq.last().unwrap().event_id, 3);
+        let seq = prepared.update.take_next_wft_sequence(3).unwrap_events();
+        assert_eq!(seq.last().unwrap().event_id, 8);
+        assert_matches!(
+            prepared.update.take_next_wft_sequence(8),
+            NextWFT::ReplayOver
+        );
+    }
+
+    #[tokio::test]
+    async fn history_still_missing_wft_started_after_refetch_is_incomplete() {
+        let (_, wft) = poll_missing_wft_started();
+        let truncated = wft.history.clone();
+        let mut mock_client = mock_workflow_client();
+        mock_client
+            .expect_get_workflow_execution_history()
+            .returning(move |_, _, _| {
+                Ok(GetWorkflowExecutionHistoryResponse {
+                    history: Some(truncated.clone()),
+                    ..Default::default()
+                })
+            })
+            .times(1);
+
+        let err = HistoryPaginator::from_poll(
+            wft,
+            Arc::new(mock_client),
+            None,
+            Arc::new(Semaphore::new(1)),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::DataLoss);
+        assert!(err.message().starts_with("Incomplete history from server"));
+    }
+
     // TODO: Test we dont re-feed pointless updates if fetching returns <= events we already
     //   processed
 
@@ -1324,8 +1844,8 @@ mod tests {
             workflow_task.into(),
             prev_started_wft_id,
             wft_started_id,
-            "wfid".to_string(),
-            "runid".to_string(),
+            "wfid".into(),
+            "runid".into(),
             NextPageToken::FetchFromStart,
             Arc::new(mock_client),
         );
@@ -1335,6 +1855,10 @@ mod tests {
         let seq = update.take_next_wft_sequence(3).unwrap_events();
         assert_eq!(seq.last().unwrap().event_id, 8);
         assert_matches!(update.take_next_wft_sequence(8), NextWFT::ReplayOver);
+        // The empty page the token pointed to is the legitimate end of history
+        let update = paginator.extract_next_update().await.unwrap();
+        assert!(update.events.is_empty());
+        assert!(update.has_last_wft);
     }
 
     #[tokio::test]
@@ -1546,8 +2070,8 @@ mod tests {
             },
             3,
             15,
-            "wfid".to_string(),
-            "runid".to_string(),
+            "wfid".into(),
+            "runid".into(),
             vec![1],
             Arc::new(mock_client),
         );
@@ -1745,8 +2269,8 @@ mod tests {
             workflow_task.into(),
             prev_started_wft_id,
             wft_started_id,
-            "wfid".to_string(),
-            "runid".to_string(),
+            "wfid".into(),
+            "runid".into(),
             NextPageToken::FetchFromStart,
             Arc::new(mock_client),
         );
@@ -1777,8 +2301,8 @@ mod tests {
             workflow_task.into(),
             prev_started_wft_id,
             wft_started_id,
-            "wfid".to_string(),
-            "runid".to_string(),
+            "wfid".into(),
+            "runid".into(),
             NextPageToken::Done,
             Arc::new(mock_client),
         );
@@ -1820,8 +2344,8 @@ mod tests {
             // Pretend we have already processed first WFT
             3,
             6,
-            "wfid".to_string(),
-            "runid".to_string(),
+            "wfid".into(),
+            "runid".into(),
             NextPageToken::Next(vec![1]),
             Arc::new(mock_client),
         );
@@ -1871,8 +2395,8 @@ mod tests {
             incremental_task.history.unwrap(),
             6,
             9,
-            "wfid".to_string(),
-            "runid".to_string(),
+            "wfid".into(),
+            "runid".into(),
             NextPageToken::FetchFromStart,
             Arc::new(mock_client),
         );
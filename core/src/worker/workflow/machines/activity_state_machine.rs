@@ -10,12 +10,18 @@ use crate::{
     worker::workflow::{machines::HistEventData, InternalFlagsRef},
 };
 use rustfsm::{fsm, MachineError, StateMachine, TransitionResult};
-use std::convert::{TryFrom, TryInto};
+use std::{
+    convert::{TryFrom, TryInto},
+    time::SystemTime,
+};
+use temporal_sdk_core_api::worker::ActivityDefaults;
 use temporal_sdk_core_protos::{
+    constants::WORKFLOW_TIME_REMAINING_HEADER,
     coresdk::{
         activity_result::{self as ar, activity_resolution, ActivityResolution, Cancellation},
         workflow_activation::ResolveActivity,
         workflow_commands::{ActivityCancellationType, ScheduleActivity},
+        AsJsonPayloadExt,
     },
     temporal::api::{
         command::v1::{
@@ -32,6 +38,7 @@ use temporal_sdk_core_protos::{
         },
         sdk::v1::UserMetadata,
     },
+    InvalidTimeField,
 };
 
 fsm! {
@@ -114,11 +121,12 @@ impl ActivityMachine {
         attrs: ScheduleActivity,
         internal_flags: InternalFlagsRef,
         use_compatible_version: bool,
-    ) -> NewMachineWithCommand {
+    ) -> Result<NewMachineWithCommand, InvalidTimeField> {
         let user_metadata = attrs.summary.clone().map(|x| UserMetadata {
             summary: Some(x),
             details: None,
         });
+        let cmd_attrs = schedule_activity_cmd_to_api(attrs.clone(), use_compatible_version)?;
         let mut s = Self::from_parts(
             Created {}.into(),
             SharedState {
@@ -135,16 +143,13 @@ impl ActivityMachine {
             .expect("Scheduling activities doesn't fail");
         let command = Command {
             command_type: CommandType::ScheduleActivityTask as i32,
-            attributes: Some(schedule_activity_cmd_to_api(
-                s.shared_state().attrs.clone(),
-                use_compatible_version,
-            )),
+            attributes: Some(cmd_attrs),
             user_metadata,
         };
-        NewMachineWithCommand {
+        Ok(NewMachineWithCommand {
             command,
             machine: s.into(),
-        }
+        })
     }
 
     fn machine_responses_from_cancel_request(&self, cancel_cmd: Command) -> Vec<MachineResponse> {
@@ -807,6 +812,58 @@ fn convert_payloads(
     })
 }
 
+/// Fill in any fields of a schedule activity command which lang left unset with the worker's
+/// configured defaults. Fields lang did set are never touched.
+pub(super) fn apply_activity_defaults(attrs: &mut ScheduleActivity, defaults: &ActivityDefaults) {
+    let seq = attrs.seq;
+    if attrs.retry_policy.is_none() && defaults.retry_policy.is_some() {
+        debug!(seq, "Applying worker default retry policy to activity");
+        attrs.retry_policy.clone_from(&defaults.retry_policy);
+    }
+    if attrs.schedule_to_close_timeout.is_none() {
+        if let Some(d) = defaults.schedule_to_close_timeout {
+            debug!(
+                seq,
+                "Applying worker default schedule to close timeout to activity"
+            );
+            // Defaults too long to convert are refused when the worker config is built
+            attrs.schedule_to_close_timeout = d.try_into().ok();
+        }
+    }
+    if attrs.task_queue.is_empty() {
+        if let Some(tq) = defaults.task_queue.as_ref() {
+            debug!(seq, task_queue = %tq, "Applying worker default task queue to activity");
+            attrs.task_queue.clone_from(tq);
+        }
+    }
+    if attrs.heartbeat_timeout.is_none() {
+        if let Some(d) = defaults.heartbeat_timeout {
+            debug!(seq, "Applying worker default heartbeat timeout to activity");
+            attrs.heartbeat_timeout = d.try_into().ok();
+        }
+    }
+}
+
+/// Attach how long the scheduling workflow has left, as of `now`, before its execution times out
+/// at `expiration`. Nothing is attached for workflows without an execution timeout, and a value
+/// lang already put under the header is kept.
+pub(super) fn attach_workflow_time_remaining(
+    attrs: &mut ScheduleActivity,
+    expiration: Option<SystemTime>,
+    now: SystemTime,
+) {
+    let Some(expiration) = expiration else {
+        return;
+    };
+    let remaining = expiration.duration_since(now).unwrap_or_default();
+    if let Ok(payload) = (remaining.as_millis() as u64).as_json_payload() {
+        attrs
+            .headers
+            .entry(WORKFLOW_TIME_REMAINING_HEADER.to_string())
+            .or_insert(payload);
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -816,10 +873,15 @@ mod test {
         test_help::{build_fake_sdk, MockPollCfg, ResponseType},
         worker::workflow::{machines::Machines, OutgoingJob},
     };
-    use std::{cell::RefCell, mem::discriminant, rc::Rc};
+    use std::{cell::RefCell, collections::HashMap, mem::discriminant, rc::Rc, time::Duration};
     use temporal_sdk::{ActivityOptions, CancellableFuture, WfContext, WorkflowFunction};
+    use temporal_sdk_core_api::worker::ActivityDefaultsBuilder;
     use temporal_sdk_core_protos::{
-        coresdk::workflow_activation::{workflow_activation_job, WorkflowActivationJob},
+        coresdk::{
+            workflow_activation::{workflow_activation_job, WorkflowActivationJob},
+            FromJsonPayloadExt,
+        },
+        temporal::api::common::v1::RetryPolicy,
         DEFAULT_WORKFLOW_TYPE,
     };
     use temporal_sdk_core_test_utils::interceptors::ActivationAssertionsInterceptor;
@@ -906,7 +968,8 @@ mod test {
             },
             Rc::new(RefCell::new(InternalFlags::new(&Default::default()))),
             true,
-        );
+        )
+        .unwrap();
         let mut s = if let Machines::ActivityMachine(am) = s.machine {
             am
         } else {
@@ -930,4 +993,115 @@ mod test {
         let curstate = s.state();
         assert!(matches!(curstate, &ActivityMachineState::Canceled(_)));
     }
+
+    fn full_defaults() -> ActivityDefaults {
+        ActivityDefaultsBuilder::default()
+            .retry_policy(RetryPolicy {
+                maximum_attempts: 3,
+                ..Default::default()
+            })
+            .schedule_to_close_timeout(Duration::from_secs(60))
+            .task_queue("default-tq")
+            .heartbeat_timeout(Duration::from_secs(5))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn activity_defaults_fill_unset_fields() {
+        let mut attrs = ScheduleActivity::default();
+        apply_activity_defaults(&mut attrs, &full_defaults());
+        assert_eq!(attrs.retry_policy.unwrap().maximum_attempts, 3);
+        assert_eq!(
+            attrs.schedule_to_close_timeout,
+            Duration::from_secs(60).try_into().ok()
+        );
+        assert_eq!(attrs.task_queue, "default-tq");
+        assert_eq!(
+            attrs.heartbeat_timeout,
+            Duration::from_secs(5).try_into().ok()
+        );
+    }
+
+    #[test]
+    fn lang_activity_fields_take_precedence_over_defaults() {
+        let mut attrs = ScheduleActivity {
+            retry_policy: Some(RetryPolicy {
+                maximum_attempts: 7,
+                ..Default::default()
+            }),
+            task_queue: "lang-tq".to_string(),
+            ..Default::default()
+        };
+        apply_activity_defaults(&mut attrs, &full_defaults());
+        // Fields lang set are left alone, while the rest still get the worker's defaults
+        assert_eq!(attrs.retry_policy.unwrap().maximum_attempts, 7);
+        assert_eq!(attrs.task_queue, "lang-tq");
+        assert_eq!(
+            attrs.schedule_to_close_timeout,
+            Duration::from_secs(60).try_into().ok()
+        );
+        assert_eq!(
+            attrs.heartbeat_timeout,
+            Duration::from_secs(5).try_into().ok()
+        );
+
+        let mut attrs = ScheduleActivity {
+            schedule_to_close_timeout: Duration::from_secs(1).try_into().ok(),
+            heartbeat_timeout: Duration::from_secs(2).try_into().ok(),
+            ..Default::default()
+        };
+        apply_activity_defaults(&mut attrs, &full_defaults());
+        assert_eq!(
+            attrs.schedule_to_close_timeout,
+            Duration::from_secs(1).try_into().ok()
+        );
+        assert_eq!(
+            attrs.heartbeat_timeout,
+            Duration::from_secs(2).try_into().ok()
+        );
+    }
+
+    #[test]
+    fn workflow_time_remaining_is_attached_when_workflow_can_expire() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let remaining = |attrs: &ScheduleActivity| {
+            attrs
+                .headers
+                .get(WORKFLOW_TIME_REMAINING_HEADER)
+                .map(|p| u64::from_json_payload(p).unwrap())
+        };
+
+        let mut attrs = ScheduleActivity::default();
+        attach_workflow_time_remaining(&mut attrs, Some(now + Duration::from_millis(90_500)), now);
+        assert_eq!(remaining(&attrs), Some(90_500));
+        // Already past the deadline (ex: while the workflow task timing out is being retried)
+        let mut attrs = ScheduleActivity::default();
+        attach_workflow_time_remaining(&mut attrs, Some(now - Duration::from_secs(5)), now);
+        assert_eq!(remaining(&attrs), Some(0));
+        // Lang's own value wins
+        let mut attrs = ScheduleActivity {
+            headers: HashMap::from([(
+                WORKFLOW_TIME_REMAINING_HEADER.to_string(),
+                7_u64.as_json_payload().unwrap(),
+            )]),
+            ..Default::default()
+        };
+        attach_workflow_time_remaining(&mut attrs, Some(now + Duration::from_secs(60)), now);
+        assert_eq!(remaining(&attrs), Some(7));
+    }
+
+    #[test]
+    fn no_workflow_time_remaining_without_execution_timeout() {
+        let mut attrs = ScheduleActivity::default();
+        attach_workflow_time_remaining(&mut attrs, None, SystemTime::now());
+        assert_eq!(attrs, ScheduleActivity::default());
+    }
+
+    #[test]
+    fn no_activity_defaults_leaves_command_unset() {
+        let mut attrs = ScheduleActivity::default();
+        apply_activity_defaults(&mut attrs, &ActivityDefaults::default());
+        assert_eq!(attrs, ScheduleActivity::default());
+    }
 }
@@ -68,7 +68,7 @@ fsm! {
         shared on_child_workflow_execution_failed) --> Failed;
     Started --(ChildWorkflowExecutionTimedOut(RetryState),
         shared on_child_workflow_execution_timed_out) --> TimedOut;
-    Started --(ChildWorkflowExecutionCancelled,
+    Started --(ChildWorkflowExecutionCancelled(Option<Payloads>),
         on_child_workflow_execution_cancelled) --> Cancelled;
     Started --(ChildWorkflowExecutionTerminated,
         shared on_child_workflow_execution_terminated) --> Terminated;
@@ -82,7 +82,7 @@ fsm! {
 
     // Ignore any spurious cancellations after resolution
     Cancelled --(Cancel) --> Cancelled;
-    Cancelled --(ChildWorkflowExecutionCancelled,
+    Cancelled --(ChildWorkflowExecutionCancelled(Option<Payloads>),
         on_child_workflow_execution_cancelled) --> Cancelled;
     // Completions of any kind after cancellation are acceptable for abandoned children
     Cancelled --(ChildWorkflowExecutionCompleted(Option<Payloads>),
@@ -112,8 +112,9 @@ pub(super) enum ChildWorkflowCommand {
     Complete(Option<Payloads>),
     #[display("Fail")]
     Fail(Failure),
+    /// Carries the details the child was cancelled with, if any
     #[display("Cancel")]
-    Cancel,
+    Cancel(Option<Payloads>),
     #[display("StartFail")]
     StartFail(StartChildWorkflowExecutionFailedCause),
     #[display("StartCancel")]
@@ -145,6 +146,7 @@ fn completion_of_not_abandoned_err() -> WFMachinesError {
 impl Cancelled {
     pub(super) fn on_child_workflow_execution_cancelled(
         self,
+        _: Option<Payloads>,
     ) -> ChildWorkflowMachineTransition<Cancelled> {
         if self.seen_cancelled_event {
             ChildWorkflowMachineTransition::Err(WFMachinesError::Fatal(
@@ -355,9 +357,12 @@ impl Started {
             TimedOut::default(),
         )
     }
-    fn on_child_workflow_execution_cancelled(self) -> ChildWorkflowMachineTransition<Cancelled> {
+    fn on_child_workflow_execution_cancelled(
+        self,
+        details: Option<Payloads>,
+    ) -> ChildWorkflowMachineTransition<Cancelled> {
         ChildWorkflowMachineTransition::ok(
-            vec![ChildWorkflowCommand::Cancel],
+            vec![ChildWorkflowCommand::Cancel(details)],
             Cancelled {
                 seen_cancelled_event: true,
             },
@@ -477,14 +482,12 @@ impl ChildWorkflowMachine {
         }
     }
 
-    fn resolve_cancelled_msg(&self) -> ResolveChildWorkflowExecution {
+    fn resolve_cancelled_msg(&self, details: Option<Payloads>) -> ResolveChildWorkflowExecution {
         let failure = Failure {
             message: "Child Workflow execution cancelled".to_owned(),
             cause: Some(Box::new(Failure {
                 failure_info: Some(FailureInfo::CanceledFailureInfo(
-                    failure::CanceledFailureInfo {
-                        ..Default::default()
-                    },
+                    failure::CanceledFailureInfo { details },
                 )),
                 ..Default::default()
             })),
@@ -615,7 +618,17 @@ impl TryFrom<HistEventData> for ChildWorkflowMachineEvents {
             Ok(EventType::ChildWorkflowExecutionTerminated) => {
                 Self::ChildWorkflowExecutionTerminated
             }
-            Ok(EventType::ChildWorkflowExecutionCanceled) => Self::ChildWorkflowExecutionCancelled,
+            Ok(EventType::ChildWorkflowExecutionCanceled) => {
+                let details = match e.attributes {
+                    Some(
+                        history_event::Attributes::ChildWorkflowExecutionCanceledEventAttributes(
+                            attrs,
+                        ),
+                    ) => attrs.details,
+                    _ => None,
+                };
+                Self::ChildWorkflowExecutionCancelled(details)
+            }
             _ => {
                 return Err(WFMachinesError::Nondeterminism(format!(
                     "Child workflow machine does not handle this event: {e:?}"
@@ -687,8 +700,8 @@ impl WFMachinesAdapter for ChildWorkflowMachine {
                 }
                 .into()]
             }
-            ChildWorkflowCommand::Cancel => {
-                vec![self.resolve_cancelled_msg().into()]
+            ChildWorkflowCommand::Cancel(details) => {
+                vec![self.resolve_cancelled_msg(details).into()]
             }
             ChildWorkflowCommand::IssueCancelAfterStarted { reason } => {
                 let mut resps = vec![];
@@ -711,7 +724,7 @@ impl WFMachinesAdapter for ChildWorkflowMachine {
                     ChildWorkflowCancellationType::Abandon
                         | ChildWorkflowCancellationType::TryCancel
                 ) {
-                    resps.push(self.resolve_cancelled_msg().into())
+                    resps.push(self.resolve_cancelled_msg(None).into())
                 }
                 resps
             }
@@ -791,18 +804,24 @@ mod test {
     use crate::{
         internal_flags::InternalFlags,
         replay::TestHistoryBuilder,
-        test_help::{build_fake_sdk, canned_histories, MockPollCfg},
+        test_help::{build_fake_sdk, canned_histories, MockPollCfg, ResponseType},
     };
     use anyhow::anyhow;
+    use parking_lot::Mutex;
     use rstest::{fixture, rstest};
-    use std::{cell::RefCell, mem::discriminant, rc::Rc};
+    use std::{cell::RefCell, mem::discriminant, rc::Rc, sync::Arc};
     use temporal_sdk::{CancellableFuture, ChildWorkflowOptions, WfContext, WorkflowResult};
     use temporal_sdk_core_protos::{
         coresdk::{
             child_workflow::child_workflow_result,
             workflow_activation::resolve_child_workflow_execution_start::Status as StartStatus,
         },
-        temporal::api::history::v1::StartChildWorkflowExecutionInitiatedEventAttributes,
+        temporal::api::history::v1::{
+            ChildWorkflowExecutionCanceledEventAttributes,
+            ChildWorkflowExecutionTerminatedEventAttributes,
+            ChildWorkflowExecutionTimedOutEventAttributes,
+            StartChildWorkflowExecutionInitiatedEventAttributes,
+        },
         DEFAULT_WORKFLOW_TYPE,
     };
 
@@ -897,8 +916,7 @@ mod test {
         worker.run().await.unwrap();
     }
 
-    #[tokio::test]
-    async fn single_child_workflow_start_fail() {
+    fn child_workflow_start_fail_hist() -> TestHistoryBuilder {
         let child_wf_id = "child-id-1";
         let mut t = TestHistoryBuilder::default();
         t.add_by_type(EventType::WorkflowExecutionStarted);
@@ -917,8 +935,12 @@ mod test {
         });
         t.add_full_wf_task();
         t.add_workflow_execution_completed();
+        t
+    }
 
-        let mut mock_cfg = MockPollCfg::from_hist_builder(t);
+    #[tokio::test]
+    async fn single_child_workflow_start_fail() {
+        let mut mock_cfg = MockPollCfg::from_hist_builder(child_workflow_start_fail_hist());
         mock_cfg.completion_asserts_from_expectations(|mut asserts| {
             asserts
                 .then(|wft| {
@@ -942,6 +964,214 @@ mod test {
         worker.run().await.unwrap();
     }
 
+    /// Replaying a whole history resolves the child exactly as it was resolved live
+    #[rstest]
+    #[case::success(Expectation::Success)]
+    #[case::failure(Expectation::Failure)]
+    #[case::start_failure(Expectation::StartFailure)]
+    #[tokio::test]
+    async fn child_workflow_histories_replay(#[case] expectation: Expectation) {
+        let mut t = match expectation {
+            Expectation::Success => canned_histories::single_child_workflow("child-id-1"),
+            Expectation::Failure => canned_histories::single_child_workflow_fail("child-id-1"),
+            Expectation::StartFailure => child_workflow_start_fail_hist(),
+        };
+        t.set_wf_input(Payload::from([expectation as u8]));
+        let mut worker = build_fake_sdk(MockPollCfg::from_resps(t, [ResponseType::AllHistory]));
+        worker.register_wf(DEFAULT_WORKFLOW_TYPE, parent_wf);
+        worker.run().await.unwrap();
+    }
+
+    #[derive(Clone, Copy, Debug)]
+    enum ChildOutcome {
+        Failed,
+        TimedOut,
+        Terminated,
+        Cancelled,
+    }
+
+    fn child_own_failure() -> Failure {
+        Failure {
+            message: "child went wrong".to_string(),
+            cause: Some(Box::new(Failure {
+                message: "root cause".to_string(),
+                ..Default::default()
+            })),
+            failure_info: Some(FailureInfo::ApplicationFailureInfo(
+                failure::ApplicationFailureInfo {
+                    r#type: "ChildError".to_string(),
+                    ..Default::default()
+                },
+            )),
+            ..Default::default()
+        }
+    }
+
+    fn child_outcome_hist(outcome: ChildOutcome) -> TestHistoryBuilder {
+        let mut t = TestHistoryBuilder::default();
+        t.add_by_type(EventType::WorkflowExecutionStarted);
+        t.add_full_wf_task();
+        let initiated_event_id = t.add(StartChildWorkflowExecutionInitiatedEventAttributes {
+            workflow_id: "child-id-1".to_owned(),
+            workflow_type: Some("child".into()),
+            ..Default::default()
+        });
+        let workflow_execution = Some(WorkflowExecution {
+            workflow_id: "child-id-1".to_owned(),
+            run_id: "child-run-1".to_owned(),
+        });
+        let started_event_id = t.add(
+            history_event::Attributes::ChildWorkflowExecutionStartedEventAttributes(
+                ChildWorkflowExecutionStartedEventAttributes {
+                    initiated_event_id,
+                    workflow_execution: workflow_execution.clone(),
+                    ..Default::default()
+                },
+            ),
+        );
+        t.add_full_wf_task();
+        let terminal_attrs = match outcome {
+            ChildOutcome::Failed => {
+                history_event::Attributes::ChildWorkflowExecutionFailedEventAttributes(
+                    ChildWorkflowExecutionFailedEventAttributes {
+                        failure: Some(child_own_failure()),
+                        workflow_execution,
+                        initiated_event_id,
+                        started_event_id,
+                        retry_state: RetryState::MaximumAttemptsReached as i32,
+                        ..Default::default()
+                    },
+                )
+            }
+            ChildOutcome::TimedOut => {
+                history_event::Attributes::ChildWorkflowExecutionTimedOutEventAttributes(
+                    ChildWorkflowExecutionTimedOutEventAttributes {
+                        workflow_execution,
+                        initiated_event_id,
+                        started_event_id,
+                        retry_state: RetryState::Timeout as i32,
+                        ..Default::default()
+                    },
+                )
+            }
+            ChildOutcome::Terminated => {
+                history_event::Attributes::ChildWorkflowExecutionTerminatedEventAttributes(
+                    ChildWorkflowExecutionTerminatedEventAttributes {
+                        workflow_execution,
+                        initiated_event_id,
+                        started_event_id,
+                        ..Default::default()
+                    },
+                )
+            }
+            ChildOutcome::Cancelled => {
+                history_event::Attributes::ChildWorkflowExecutionCanceledEventAttributes(
+                    ChildWorkflowExecutionCanceledEventAttributes {
+                        details: Some(Payloads::from(Payload::from(b"bye".as_slice()))),
+                        workflow_execution,
+                        initiated_event_id,
+                        started_event_id,
+                        ..Default::default()
+                    },
+                )
+            }
+        };
+        t.add(terminal_attrs);
+        t.add_full_wf_task();
+        t.add_workflow_execution_completed();
+        t
+    }
+
+    #[rstest]
+    #[case::failed(ChildOutcome::Failed)]
+    #[case::timed_out(ChildOutcome::TimedOut)]
+    #[case::terminated(ChildOutcome::Terminated)]
+    #[case::cancelled(ChildOutcome::Cancelled)]
+    #[tokio::test]
+    async fn child_terminal_outcomes_resolve_with_structured_failures(
+        #[case] outcome: ChildOutcome,
+    ) {
+        let mock_cfg = MockPollCfg::from_hist_builder(child_outcome_hist(outcome));
+        let mut worker = build_fake_sdk(mock_cfg);
+        let resolution = Arc::new(Mutex::new(None));
+        let resolution_clone = resolution.clone();
+        worker.register_wf(DEFAULT_WORKFLOW_TYPE, move |ctx: WfContext| {
+            let resolution = resolution_clone.clone();
+            async move {
+                let child = ctx.child_workflow(ChildWorkflowOptions {
+                    workflow_id: "child-id-1".to_string(),
+                    workflow_type: "child".to_string(),
+                    ..Default::default()
+                });
+                let started = child
+                    .start(&ctx)
+                    .await
+                    .into_started()
+                    .expect("Child must start");
+                *resolution.lock() = started.result().await.status;
+                Ok(().into())
+            }
+        });
+        worker.run().await.unwrap();
+
+        let failure = match (outcome, resolution.lock().take()) {
+            (
+                ChildOutcome::Cancelled,
+                Some(child_workflow_result::Status::Cancelled(wfr::Cancellation { failure })),
+            ) => failure,
+            (
+                ChildOutcome::Failed | ChildOutcome::TimedOut | ChildOutcome::Terminated,
+                Some(child_workflow_result::Status::Failed(wfr::Failure { failure })),
+            ) => failure,
+            (o, r) => panic!("Unexpected resolution for {o:?}: {r:?}"),
+        }
+        .expect("Resolution must carry a failure");
+
+        let expected_retry_state = match outcome {
+            ChildOutcome::Failed => RetryState::MaximumAttemptsReached,
+            ChildOutcome::TimedOut => RetryState::Timeout,
+            ChildOutcome::Terminated | ChildOutcome::Cancelled => RetryState::NonRetryableFailure,
+        };
+        assert_eq!(
+            failure.failure_info,
+            Some(FailureInfo::ChildWorkflowExecutionFailureInfo(
+                failure::ChildWorkflowExecutionFailureInfo {
+                    namespace: "".to_string(),
+                    workflow_execution: Some(WorkflowExecution {
+                        workflow_id: "child-id-1".to_string(),
+                        run_id: "child-run-1".to_string(),
+                    }),
+                    workflow_type: Some(WorkflowType {
+                        name: "child".to_string()
+                    }),
+                    initiated_event_id: 5,
+                    started_event_id: 6,
+                    retry_state: expected_retry_state as i32,
+                }
+            ))
+        );
+        let cause = *failure.cause.expect("Failure must have a cause");
+        match outcome {
+            // The child's own failure chain is passed through untouched
+            ChildOutcome::Failed => assert_eq!(cause, child_own_failure()),
+            ChildOutcome::TimedOut => {
+                assert_matches!(cause.failure_info, Some(FailureInfo::TimeoutFailureInfo(_)))
+            }
+            ChildOutcome::Terminated => assert_matches!(
+                cause.failure_info,
+                Some(FailureInfo::TerminatedFailureInfo(_))
+            ),
+            ChildOutcome::Cancelled => assert_eq!(
+                cause.failure_info,
+                Some(FailureInfo::CanceledFailureInfo(
+                    failure::CanceledFailureInfo {
+                        details: Some(Payloads::from(Payload::from(b"bye".as_slice()))),
+                    }
+                ))
+            ),
+        }
+    }
+
     async fn cancel_before_send_wf(ctx: WfContext) -> WorkflowResult<()> {
         let workflow_id = "child-id-1";
         let child = ctx.child_workflow(ChildWorkflowOptions {
@@ -8,7 +8,7 @@ use std::convert::TryFrom;
 use temporal_sdk_core_protos::{
     coresdk::workflow_commands::ContinueAsNewWorkflowExecution,
     temporal::api::{
-        command::v1::{continue_as_new_cmd_to_api, Command},
+        command::v1::{command::Attributes, continue_as_new_cmd_to_api, Command},
         enums::v1::{CommandType, EventType},
     },
 };
@@ -30,16 +30,23 @@ fsm! {
 #[derive(Debug, derive_more::Display)]
 pub(super) enum ContinueAsNewWorkflowCommand {}
 
+/// Create a continue as new command. `cron_schedule` is the current execution's schedule, which
+/// the new run is started with.
 pub(super) fn continue_as_new(
     attribs: ContinueAsNewWorkflowExecution,
     use_compatible_version: bool,
+    cron_schedule: String,
 ) -> NewMachineWithCommand {
     let mut machine = ContinueAsNewWorkflowMachine::from_parts(Created {}.into(), ());
     OnEventWrapper::on_event_mut(&mut machine, ContinueAsNewWorkflowMachineEvents::Schedule)
         .expect("Scheduling continue as new machine doesn't fail");
+    let mut attributes = continue_as_new_cmd_to_api(attribs, use_compatible_version);
+    if let Attributes::ContinueAsNewWorkflowExecutionCommandAttributes(a) = &mut attributes {
+        a.cron_schedule = cron_schedule;
+    }
     let command = Command {
         command_type: CommandType::ContinueAsNewWorkflowExecution as i32,
-        attributes: Some(continue_as_new_cmd_to_api(attribs, use_compatible_version)),
+        attributes: Some(attributes),
         user_metadata: Default::default(),
     };
     NewMachineWithCommand {
@@ -115,10 +122,22 @@ impl Cancellable for ContinueAsNewWorkflowMachine {}
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test_help::{build_fake_sdk, canned_histories, MockPollCfg};
+    use crate::{
+        replay::{default_wes_attribs, TestHistoryBuilder},
+        test_help::{build_fake_sdk, canned_histories, MockPollCfg, ResponseType},
+    };
+    use rstest::rstest;
     use std::time::Duration;
     use temporal_sdk::{WfContext, WfExitValue, WorkflowResult};
-    use temporal_sdk_core_protos::DEFAULT_WORKFLOW_TYPE;
+    use temporal_sdk_core_protos::{
+        coresdk::{
+            workflow_activation::{workflow_activation_job, InitializeWorkflow},
+            AsJsonPayloadExt,
+        },
+        temporal::api::history::v1::WorkflowExecutionStartedEventAttributes,
+        DEFAULT_WORKFLOW_TYPE,
+    };
+    use temporal_sdk_core_test_utils::interceptors::ActivationAssertionsInterceptor;
 
     async fn wf_with_timer(ctx: WfContext) -> WorkflowResult<()> {
         ctx.timer(Duration::from_millis(500)).await;
@@ -153,4 +172,74 @@ mod tests {
         worker.register_wf(DEFAULT_WORKFLOW_TYPE, wf_with_timer);
         worker.run().await.unwrap();
     }
+
+    #[rstest]
+    #[tokio::test]
+    async fn repeatedly_continued_runs(#[values(false, true)] replaying: bool) {
+        // The second and third runs of a workflow which continues as new after a timer, each
+        // continuing from the run before
+        for (continued_from, next_run) in [("run-1", "run-2"), ("run-2", "run-3")] {
+            let mut t = TestHistoryBuilder::default();
+            t.add(WorkflowExecutionStartedEventAttributes {
+                continued_execution_run_id: continued_from.to_string(),
+                last_completion_result: Some(continued_from.as_json_payload().unwrap().into()),
+                ..default_wes_attribs()
+            });
+            t.add_full_wf_task();
+            let timer_started_event_id = t.add_by_type(EventType::TimerStarted);
+            t.add_timer_fired(timer_started_event_id, "1".to_string());
+            t.add_full_wf_task();
+            t.add_continued_as_new();
+
+            let mock_cfg = if replaying {
+                MockPollCfg::from_resps(t, [ResponseType::AllHistory])
+            } else {
+                let mut mock_cfg = MockPollCfg::from_hist_builder(t);
+                mock_cfg.completion_asserts_from_expectations(|mut asserts| {
+                    asserts
+                        .then(|wft| {
+                            assert_eq!(wft.commands.len(), 1);
+                            assert_matches!(
+                                wft.commands[0].command_type(),
+                                CommandType::StartTimer
+                            );
+                        })
+                        .then(|wft| {
+                            assert_eq!(wft.commands.len(), 1);
+                            assert_matches!(
+                                wft.commands[0].command_type(),
+                                CommandType::ContinueAsNewWorkflowExecution
+                            );
+                        });
+                });
+                mock_cfg
+            };
+
+            let mut aai = ActivationAssertionsInterceptor::default();
+            aai.then(move |act| {
+                assert_matches!(
+                    act.jobs[0].variant.as_ref().unwrap(),
+                    workflow_activation_job::Variant::InitializeWorkflow(InitializeWorkflow {
+                        continued_from_execution_run_id,
+                        last_completion_result: Some(result),
+                        ..
+                    }) if continued_from_execution_run_id == continued_from
+                        && result.payloads == [continued_from.as_json_payload().unwrap()]
+                );
+            });
+
+            let mut worker = build_fake_sdk(mock_cfg);
+            worker.set_worker_interceptor(aai);
+            worker.register_wf(DEFAULT_WORKFLOW_TYPE, move |ctx: WfContext| async move {
+                ctx.timer(Duration::from_millis(500)).await;
+                Ok(WfExitValue::continue_as_new(
+                    ContinueAsNewWorkflowExecution {
+                        arguments: vec![next_run.as_json_payload()?],
+                        ..Default::default()
+                    },
+                ))
+            });
+            worker.run().await.unwrap();
+        }
+    }
 }
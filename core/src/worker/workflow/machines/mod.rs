@@ -10,6 +10,7 @@ mod fail_workflow_state_machine;
 mod local_activity_state_machine;
 mod modify_workflow_properties_state_machine;
 mod patch_state_machine;
+mod side_effect_state_machine;
 mod signal_external_state_machine;
 mod timer_state_machine;
 mod update_state_machine;
@@ -36,6 +37,7 @@ use local_activity_state_machine::LocalActivityMachine;
 use modify_workflow_properties_state_machine::ModifyWorkflowPropertiesMachine;
 use patch_state_machine::PatchMachine;
 use rustfsm::{MachineError, StateMachine};
+use side_effect_state_machine::SideEffectMachine;
 use signal_external_state_machine::SignalExternalMachine;
 use std::{
     convert::{TryFrom, TryInto},
@@ -66,6 +68,7 @@ enum Machines {
     FailWorkflowMachine,
     LocalActivityMachine,
     PatchMachine,
+    SideEffectMachine,
     SignalExternalMachine,
     TimerMachine,
     WorkflowTaskMachine,
@@ -102,7 +105,10 @@ trait TemporalStateMachine {
     fn is_final_state(&self) -> bool;
 
     /// Returns a friendly name for the type of this machine
-    fn name(&self) -> &str;
+    fn name(&self) -> &'static str;
+
+    /// Returns the name of the machine's current state
+    fn state_name(&self) -> &'static str;
 }
 
 impl<SM> TemporalStateMachine for SM
@@ -193,8 +199,12 @@ where
         self.has_reached_final_state()
     }
 
-    fn name(&self) -> &str {
-        self.name()
+    fn name(&self) -> &'static str {
+        StateMachine::static_name(self)
+    }
+
+    fn state_name(&self) -> &'static str {
+        StateMachine::state_name(self)
     }
 }
 
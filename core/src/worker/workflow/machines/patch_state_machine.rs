@@ -34,7 +34,7 @@ use crate::{
 use anyhow::Context;
 use rustfsm::{fsm, StateMachine, TransitionResult};
 use std::{
-    collections::{BTreeSet, HashMap},
+    collections::{BTreeMap, BTreeSet},
     convert::TryFrom,
 };
 use temporal_sdk_core_protos::{
@@ -149,7 +149,7 @@ pub(super) fn has_change<'a>(
             vec![]
         } else {
             let indexed_fields = {
-                let mut m = HashMap::new();
+                let mut m = BTreeMap::new();
                 m.insert(VERSION_SEARCH_ATTR_KEY.to_string(), serialized);
                 m
             };
@@ -278,12 +278,13 @@ mod tests {
     use crate::{
         internal_flags::CoreInternalFlags,
         replay::TestHistoryBuilder,
-        test_help::{build_fake_sdk, MockPollCfg, ResponseType},
+        test_help::{build_fake_sdk, build_mock_pollers, mock_worker, MockPollCfg, ResponseType},
         worker::workflow::machines::patch_state_machine::VERSION_SEARCH_ATTR_KEY,
     };
     use rstest::rstest;
     use std::{
-        collections::{hash_map::RandomState, HashSet, VecDeque},
+        collections::{hash_map::RandomState, HashMap, HashSet, VecDeque},
+        sync::Arc,
         time::Duration,
     };
     use temporal_sdk::{ActivityOptions, WfContext};
@@ -300,11 +301,12 @@ mod tests {
                 ScheduleActivityTaskCommandAttributes,
                 UpsertWorkflowSearchAttributesCommandAttributes,
             },
-            common::v1::ActivityType,
+            common::v1::{ActivityType, Payloads},
             enums::v1::{CommandType, EventType},
             history::v1::{
                 ActivityTaskCompletedEventAttributes, ActivityTaskScheduledEventAttributes,
-                ActivityTaskStartedEventAttributes, TimerFiredEventAttributes,
+                ActivityTaskStartedEventAttributes, MarkerRecordedEventAttributes,
+                TimerFiredEventAttributes, UpsertWorkflowSearchAttributesEventAttributes,
             },
         },
         DEFAULT_WORKFLOW_TYPE,
@@ -807,4 +809,187 @@ mod tests {
         });
         worker.run().await.unwrap();
     }
+
+    #[rstest]
+    #[tokio::test]
+    async fn patch_upserts_stay_next_to_their_markers(#[values(false, true)] replaying: bool) {
+        let mut t = TestHistoryBuilder::default();
+        t.add_by_type(EventType::WorkflowExecutionStarted);
+        t.add_full_wf_task();
+        t.set_flags_first_wft(
+            &[CoreInternalFlags::UpsertSearchAttributeOnPatch as u32],
+            &[],
+        );
+        t.add(UpsertWorkflowSearchAttributesEventAttributes {
+            workflow_task_completed_event_id: 4,
+            ..Default::default()
+        });
+        t.add_has_change_marker(MY_PATCH_ID, false);
+        t.add_upsert_search_attrs_for_patch(&[MY_PATCH_ID.to_string()]);
+        let scheduled_event_id = t.add(ActivityTaskScheduledEventAttributes {
+            activity_id: "1".to_string(),
+            activity_type: Some(ActivityType {
+                name: "had_change".to_string(),
+            }),
+            ..Default::default()
+        });
+        let started_event_id = t.add(ActivityTaskStartedEventAttributes {
+            scheduled_event_id,
+            ..Default::default()
+        });
+        t.add(ActivityTaskCompletedEventAttributes {
+            scheduled_event_id,
+            started_event_id,
+            ..Default::default()
+        });
+        t.add_full_wf_task();
+        t.add_workflow_execution_completed();
+
+        let mock_cfg = if replaying {
+            MockPollCfg::from_resps(t, [ResponseType::AllHistory])
+        } else {
+            let mut mock_cfg = MockPollCfg::from_hist_builder(t);
+            mock_cfg.completion_asserts_from_expectations(|mut asserts| {
+                asserts.then(|wft| {
+                    let types: Vec<_> = wft.commands.iter().map(|c| c.command_type()).collect();
+                    assert_eq!(
+                        types,
+                        [
+                            CommandType::UpsertWorkflowSearchAttributes,
+                            CommandType::RecordMarker,
+                            CommandType::UpsertWorkflowSearchAttributes,
+                            CommandType::ScheduleActivityTask,
+                        ]
+                    );
+                });
+            });
+            mock_cfg
+        };
+
+        let mut worker = build_fake_sdk(mock_cfg);
+        worker.register_wf(DEFAULT_WORKFLOW_TYPE, |ctx: WfContext| async move {
+            ctx.upsert_search_attributes([("user_attr".to_string(), "hi".as_json_payload()?)]);
+            assert!(ctx.patched(MY_PATCH_ID));
+            ctx.activity(ActivityOptions {
+                activity_type: "had_change".to_string(),
+                start_to_close_timeout: Some(Duration::from_secs(1)),
+                ..Default::default()
+            })
+            .await;
+            Ok(().into())
+        });
+        worker.run().await.unwrap();
+    }
+
+    fn foreign_marker(
+        name: &str,
+        details: HashMap<String, Payloads>,
+    ) -> MarkerRecordedEventAttributes {
+        MarkerRecordedEventAttributes {
+            marker_name: name.to_string(),
+            details,
+            workflow_task_completed_event_id: 4,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn replays_java_sdk_version_marker_as_patch() {
+        let mut t = TestHistoryBuilder::default();
+        t.add_by_type(EventType::WorkflowExecutionStarted);
+        t.add_full_wf_task();
+        // What the Java SDK records for `Workflow.getVersion(MY_PATCH_ID, DEFAULT_VERSION, 1)`
+        t.add(foreign_marker(
+            "Version",
+            HashMap::from([
+                (
+                    "changeId".to_string(),
+                    MY_PATCH_ID.as_json_payload().unwrap().into(),
+                ),
+                (
+                    "version".to_string(),
+                    1_i32.as_json_payload().unwrap().into(),
+                ),
+            ]),
+        ));
+        let scheduled_event_id = t.add(ActivityTaskScheduledEventAttributes {
+            activity_id: "had_change".to_string(),
+            ..Default::default()
+        });
+        let started_event_id = t.add(ActivityTaskStartedEventAttributes {
+            scheduled_event_id,
+            ..Default::default()
+        });
+        t.add(ActivityTaskCompletedEventAttributes {
+            scheduled_event_id,
+            started_event_id,
+            ..Default::default()
+        });
+        t.add_full_wf_task();
+        t.add_workflow_execution_completed();
+
+        let mock_cfg = MockPollCfg::from_resps(t, [ResponseType::AllHistory]);
+        let mut aai = ActivationAssertionsInterceptor::default();
+        aai.then(|a| {
+            assert_matches!(
+                a.jobs.as_slice(),
+                [_, WorkflowActivationJob {
+                    variant: Some(workflow_activation_job::Variant::NotifyHasPatch(
+                        NotifyHasPatch { patch_id }
+                    ))
+                }] if patch_id == MY_PATCH_ID
+            );
+        });
+        let mut worker = build_fake_sdk(mock_cfg);
+        worker.set_worker_interceptor(aai);
+        worker.register_wf(DEFAULT_WORKFLOW_TYPE, |mut ctx: WfContext| async move {
+            assert!(v2(&mut ctx).await);
+            Ok(().into())
+        });
+        worker.run().await.unwrap();
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn foreign_side_effect_markers_are_skipped_and_unknown_ones_rejected_if_strict(
+        #[values(false, true)] strict: bool,
+    ) {
+        let mut t = TestHistoryBuilder::default();
+        t.add_by_type(EventType::WorkflowExecutionStarted);
+        t.add_full_wf_task();
+        t.add(foreign_marker(
+            "SideEffect",
+            HashMap::from([(
+                "data".to_string(),
+                "whatever".as_json_payload().unwrap().into(),
+            )]),
+        ));
+        t.add(foreign_marker("SomeMarkerNobodyKnows", HashMap::new()));
+        let timer_started_event_id = t.add_by_type(EventType::TimerStarted);
+        t.add(TimerFiredEventAttributes {
+            started_event_id: timer_started_event_id,
+            timer_id: "1".to_string(),
+        });
+        t.add_full_wf_task();
+        t.add_workflow_execution_completed();
+
+        let mut mock_cfg = MockPollCfg::from_resps(t, [ResponseType::AllHistory]);
+        if strict {
+            mock_cfg.num_expected_fails = 1;
+        }
+        let mut mock = build_mock_pollers(mock_cfg);
+        mock.worker_cfg(|c| {
+            c.max_cached_workflows = 1;
+            c.strict_marker_replay = strict;
+        });
+        let mut worker = temporal_sdk::Worker::new_from_core(
+            Arc::new(mock_worker(mock)),
+            "replay_q".to_string(),
+        );
+        worker.register_wf(DEFAULT_WORKFLOW_TYPE, |ctx: WfContext| async move {
+            ctx.timer(ONE_SECOND).await;
+            Ok(().into())
+        });
+        worker.run().await.unwrap();
+    }
 }
@@ -0,0 +1,299 @@
+//! Side effects let workflow code record a nondeterministic value (ex: a random id) once, and see
+//! the same value every time it is replayed. Lang runs the side effect and sends its value with a
+//! `RecordSideEffect` command, which core records as a marker. When replaying, core finds those
+//! markers while peeking ahead at the next workflow task and resolves the side effects with the
+//! recorded values before lang reaches them, so they are never run again. Lang still sends the
+//! command on replay, which is then matched against the marker like any other command.
+//!
+//! Mutable side effects run every time they are reached, but only record a marker when their value
+//! differs from the last one recorded under the same id. That comparison happens in
+//! [super::WorkflowMachines] - a machine is only created when a marker is to be recorded.
+
+use super::{
+    workflow_machines::MachineResponse, Cancellable, EventInfo, NewMachineWithCommand,
+    WFMachinesAdapter, WFMachinesError,
+};
+use crate::{protosext::HistoryEventExt, worker::workflow::machines::HistEventData};
+use rustfsm::{fsm, StateMachine, TransitionResult};
+use std::convert::TryFrom;
+use temporal_sdk_core_protos::{
+    constants::SIDE_EFFECT_MARKER_NAME,
+    coresdk::{
+        common::build_side_effect_marker_details, external_data::SideEffectMarkerData,
+        workflow_commands::RecordSideEffect,
+    },
+    temporal::api::{
+        command::v1::{Command, RecordMarkerCommandAttributes},
+        enums::v1::CommandType,
+    },
+};
+
+fsm! {
+    pub(super) name SideEffectMachine;
+    command SideEffectCommand;
+    error WFMachinesError;
+    shared_state SharedState;
+
+    // The value is already known when the machine is created, so there is nothing to resolve.
+    // All that's left is checking that the recorded marker is the one we expect.
+    Created --(CommandRecordMarker) --> MarkerCommandCreated;
+    MarkerCommandCreated --(MarkerRecorded(SideEffectMarkerData), shared on_marker_recorded)
+        --> MarkerRecorded;
+}
+
+#[derive(Clone)]
+pub(super) struct SharedState {
+    seq: u32,
+    mutable_id: String,
+}
+
+#[derive(Debug, derive_more::Display)]
+pub(super) enum SideEffectCommand {}
+
+/// Instantiates a SideEffectMachine and packs it together with the marker command recording the
+/// side effect's value
+pub(super) fn side_effect(attrs: RecordSideEffect) -> NewMachineWithCommand {
+    let shared_state = SharedState {
+        seq: attrs.seq,
+        mutable_id: attrs.mutable_id.clone(),
+    };
+    let command = Command {
+        command_type: CommandType::RecordMarker as i32,
+        attributes: Some(
+            RecordMarkerCommandAttributes {
+                marker_name: SIDE_EFFECT_MARKER_NAME.to_string(),
+                details: build_side_effect_marker_details(
+                    SideEffectMarkerData {
+                        seq: attrs.seq,
+                        mutable_id: attrs.mutable_id,
+                    },
+                    attrs.result,
+                ),
+                header: None,
+                failure: None,
+            }
+            .into(),
+        ),
+        user_metadata: Default::default(),
+    };
+    NewMachineWithCommand {
+        command,
+        machine: SideEffectMachine::from_parts(Created {}.into(), shared_state).into(),
+    }
+}
+
+#[derive(Default, Clone)]
+pub(super) struct Created {}
+
+#[derive(Default, Clone)]
+pub(super) struct MarkerCommandCreated {}
+
+impl From<Created> for MarkerCommandCreated {
+    fn from(_: Created) -> Self {
+        Self::default()
+    }
+}
+
+impl MarkerCommandCreated {
+    pub(super) fn on_marker_recorded(
+        self,
+        dat: &mut SharedState,
+        recorded: SideEffectMarkerData,
+    ) -> SideEffectMachineTransition<MarkerRecorded> {
+        if recorded.seq != dat.seq || recorded.mutable_id != dat.mutable_id {
+            return TransitionResult::Err(WFMachinesError::Nondeterminism(format!(
+                "Side effect marker with seq {} and mutable id '{}' does not match expected seq \
+                 {} and mutable id '{}'",
+                recorded.seq, recorded.mutable_id, dat.seq, dat.mutable_id
+            )));
+        }
+        TransitionResult::default()
+    }
+}
+
+#[derive(Default, Clone)]
+pub(super) struct MarkerRecorded {}
+
+impl WFMachinesAdapter for SideEffectMachine {
+    fn adapt_response(
+        &self,
+        _my_command: Self::Command,
+        _event_info: Option<EventInfo>,
+    ) -> Result<Vec<MachineResponse>, WFMachinesError> {
+        panic!("Side effect machine does not produce commands")
+    }
+}
+
+impl Cancellable for SideEffectMachine {}
+
+impl TryFrom<CommandType> for SideEffectMachineEvents {
+    type Error = ();
+
+    fn try_from(c: CommandType) -> Result<Self, Self::Error> {
+        Ok(match c {
+            CommandType::RecordMarker => Self::CommandRecordMarker,
+            _ => return Err(()),
+        })
+    }
+}
+
+impl TryFrom<HistEventData> for SideEffectMachineEvents {
+    type Error = WFMachinesError;
+
+    fn try_from(e: HistEventData) -> Result<Self, Self::Error> {
+        let e = e.event;
+        match e.get_side_effect_marker_details() {
+            Some((dat, _)) => Ok(Self::MarkerRecorded(dat)),
+            _ => Err(WFMachinesError::Nondeterminism(format!(
+                "Side effect machine cannot handle this event: {e}"
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        replay::TestHistoryBuilder,
+        test_help::{build_fake_sdk, MockPollCfg, ResponseType},
+    };
+    use parking_lot::Mutex;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+    use temporal_sdk::WfContext;
+    use temporal_sdk_core_protos::{
+        coresdk::common::decode_side_effect_marker_details,
+        temporal::api::{command::v1::command, common::v1::Payload, enums::v1::EventType},
+        DEFAULT_WORKFLOW_TYPE,
+    };
+
+    fn payload(data: &str) -> Payload {
+        Payload {
+            data: data.as_bytes().to_vec(),
+            ..Default::default()
+        }
+    }
+
+    /// Runs two side effects and three mutable side effects with the same id, the second of which
+    /// produces the same value as the first. Returns how many times side effect closures were run,
+    /// and the values the workflow saw.
+    async fn run_side_effects_wf(mock_cfg: MockPollCfg) -> (usize, Vec<Payload>) {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let seen = Arc::new(Mutex::new(vec![]));
+        let mut worker = build_fake_sdk(mock_cfg);
+        let (runs_c, seen_c) = (runs.clone(), seen.clone());
+        worker.register_wf(DEFAULT_WORKFLOW_TYPE, move |ctx: WfContext| {
+            let (runs, seen) = (runs_c.clone(), seen_c.clone());
+            async move {
+                let run = |val: &'static str| {
+                    let runs = runs.clone();
+                    move || {
+                        runs.fetch_add(1, Ordering::SeqCst);
+                        payload(val)
+                    }
+                };
+                let vals = [
+                    ctx.side_effect(run("one")),
+                    ctx.side_effect(run("two")),
+                    ctx.mutable_side_effect("m", run("a")),
+                    ctx.mutable_side_effect("m", run("a")),
+                    ctx.mutable_side_effect("m", run("b")),
+                ];
+                seen.lock().extend(vals);
+                Ok(().into())
+            }
+        });
+        worker.run().await.unwrap();
+        let seen = seen.lock().clone();
+        (runs.load(Ordering::SeqCst), seen)
+    }
+
+    fn expected_values() -> Vec<Payload> {
+        ["one", "two", "a", "a", "b"].map(payload).to_vec()
+    }
+
+    #[tokio::test]
+    async fn side_effects_are_recorded_as_markers() {
+        let mut t = TestHistoryBuilder::default();
+        t.add_by_type(EventType::WorkflowExecutionStarted);
+        t.add_full_wf_task();
+        t.add_workflow_execution_completed();
+
+        let mut mock_cfg = MockPollCfg::from_hist_builder(t);
+        mock_cfg.completion_asserts_from_expectations(|mut asserts| {
+            asserts.then(|wft| {
+                let markers: Vec<_> = wft
+                    .commands
+                    .iter()
+                    .filter_map(|c| match &c.attributes {
+                        Some(command::Attributes::RecordMarkerCommandAttributes(m)) => {
+                            assert_eq!(m.marker_name, SIDE_EFFECT_MARKER_NAME);
+                            let (dat, result) =
+                                decode_side_effect_marker_details(&m.details).unwrap();
+                            Some((dat.seq, dat.mutable_id, result.unwrap().data))
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                // The unchanged mutable side effect (seq 4) records nothing
+                assert_eq!(
+                    markers,
+                    [
+                        (1, "".to_string(), b"one".to_vec()),
+                        (2, "".to_string(), b"two".to_vec()),
+                        (3, "m".to_string(), b"a".to_vec()),
+                        (5, "m".to_string(), b"b".to_vec()),
+                    ]
+                );
+                assert_eq!(wft.commands.len(), 5);
+                assert_eq!(
+                    wft.commands[4].command_type(),
+                    CommandType::CompleteWorkflowExecution
+                );
+            });
+        });
+
+        let (runs, seen) = run_side_effects_wf(mock_cfg).await;
+        assert_eq!(runs, 5);
+        assert_eq!(seen, expected_values());
+    }
+
+    #[tokio::test]
+    async fn replay_uses_recorded_side_effect_values() {
+        let mut t = TestHistoryBuilder::default();
+        t.add_by_type(EventType::WorkflowExecutionStarted);
+        t.add_full_wf_task();
+        t.add_side_effect_marker(1, "", payload("one"));
+        t.add_side_effect_marker(2, "", payload("two"));
+        t.add_side_effect_marker(3, "m", payload("a"));
+        t.add_side_effect_marker(5, "m", payload("b"));
+        t.add_workflow_execution_completed();
+
+        // Any marker command beyond those in history would fail the task as nondeterministic
+        let mock_cfg = MockPollCfg::from_resps(t, [ResponseType::AllHistory]);
+        let (runs, seen) = run_side_effects_wf(mock_cfg).await;
+        assert_eq!(runs, 0);
+        assert_eq!(seen, expected_values());
+    }
+
+    #[tokio::test]
+    async fn mismatched_side_effect_marker_is_nondeterministic() {
+        let mut t = TestHistoryBuilder::default();
+        t.add_by_type(EventType::WorkflowExecutionStarted);
+        t.add_full_wf_task();
+        t.add_side_effect_marker(1, "other", payload("one"));
+        t.add_workflow_execution_completed();
+
+        let mut mock_cfg = MockPollCfg::from_resps(t, [ResponseType::AllHistory]);
+        mock_cfg.num_expected_fails = 1;
+        let mut worker = build_fake_sdk(mock_cfg);
+        worker.register_wf(DEFAULT_WORKFLOW_TYPE, |ctx: WfContext| async move {
+            ctx.side_effect(|| payload("one"));
+            Ok(().into())
+        });
+        worker.run().await.unwrap();
+    }
+}
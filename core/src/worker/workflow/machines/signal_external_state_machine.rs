@@ -50,6 +50,7 @@ fsm! {
     // Ignore any spurious cancellations after resolution
     Cancelled --(Cancel) --> Cancelled;
     Signaled --(Cancel) --> Signaled;
+    Failed --(Cancel) --> Failed;
 }
 
 #[derive(Default, Clone)]
@@ -299,12 +300,13 @@ mod tests {
     use super::*;
     use crate::{
         replay::TestHistoryBuilder,
-        test_help::{build_fake_sdk, MockPollCfg},
+        test_help::{build_fake_sdk, MockPollCfg, ResponseType},
     };
     use std::mem::discriminant;
     use temporal_sdk::{CancellableFuture, SignalWorkflowOptions, WfContext, WorkflowResult};
     use temporal_sdk_core_protos::{
         coresdk::workflow_activation::{workflow_activation_job, WorkflowActivationJob},
+        temporal::api::history::v1::SignalExternalWorkflowExecutionFailedEventAttributes,
         DEFAULT_WORKFLOW_TYPE,
     };
     use temporal_sdk_core_test_utils::interceptors::ActivationAssertionsInterceptor;
@@ -370,6 +372,110 @@ mod tests {
         worker.run().await.unwrap();
     }
 
+    #[rstest::rstest]
+    #[tokio::test]
+    async fn failure_cause_is_surfaced(
+        #[values(
+            SignalExternalWorkflowExecutionFailedCause::ExternalWorkflowExecutionNotFound,
+            SignalExternalWorkflowExecutionFailedCause::SignalCountLimitExceeded
+        )]
+        cause: SignalExternalWorkflowExecutionFailedCause,
+        #[values(false, true)] replaying: bool,
+    ) {
+        let mut t = TestHistoryBuilder::default();
+        t.add_by_type(EventType::WorkflowExecutionStarted);
+        t.add_full_wf_task();
+        let initiated_event_id = t.add_signal_wf(SIGNAME, "fake_wid", "fake_rid");
+        t.add(SignalExternalWorkflowExecutionFailedEventAttributes {
+            initiated_event_id,
+            cause: cause as i32,
+            ..Default::default()
+        });
+        t.add_full_wf_task();
+        t.add_workflow_execution_completed();
+        let mock_cfg = if replaying {
+            MockPollCfg::from_resps(t, [ResponseType::AllHistory])
+        } else {
+            MockPollCfg::from_hist_builder(t)
+        };
+
+        let mut worker = build_fake_sdk(mock_cfg);
+        worker.register_wf(DEFAULT_WORKFLOW_TYPE, move |ctx: WfContext| async move {
+            let failure = ctx
+                .signal_workflow(SignalWorkflowOptions::new(
+                    "fake_wid",
+                    "fake_rid",
+                    SIGNAME,
+                    [b"hi!"],
+                ))
+                .await
+                .expect_err("Signal must fail");
+            // Lang tells causes apart by the failure's type, ex: to raise a not-found error
+            assert_matches!(
+                failure.failure_info,
+                Some(FailureInfo::ApplicationFailureInfo(ApplicationFailureInfo { r#type, .. }))
+                    if r#type == cause.to_string()
+            );
+            Ok(().into())
+        });
+        worker.run().await.unwrap();
+    }
+
+    #[rstest::rstest]
+    #[tokio::test]
+    async fn sent_signal_resolves_after_sender_is_cancelled(
+        #[values(false, true)] replaying: bool,
+    ) {
+        let mut t = TestHistoryBuilder::default();
+        t.add_by_type(EventType::WorkflowExecutionStarted);
+        t.add_full_wf_task();
+        let id = t.add_signal_wf(SIGNAME, "fake_wid", "fake_rid");
+        t.add_cancel_requested();
+        t.add_full_wf_task();
+        t.add_external_signal_completed(id);
+        t.add_full_wf_task();
+        t.add_workflow_execution_completed();
+        let mock_cfg = if replaying {
+            MockPollCfg::from_resps(t, [ResponseType::AllHistory])
+        } else {
+            let mut mock_cfg = MockPollCfg::from_hist_builder(t);
+            mock_cfg.completion_asserts_from_expectations(|mut asserts| {
+                asserts
+                    .then(|wft| {
+                        assert_eq!(
+                            wft.commands[0].command_type(),
+                            CommandType::SignalExternalWorkflowExecution
+                        );
+                    })
+                    // The signal is already on its way, so cancelling it sends nothing
+                    .then(|wft| assert_eq!(wft.commands.len(), 0))
+                    .then(|wft| {
+                        assert_eq!(
+                            wft.commands[0].command_type(),
+                            CommandType::CompleteWorkflowExecution
+                        );
+                    });
+            });
+            mock_cfg
+        };
+
+        let mut worker = build_fake_sdk(mock_cfg);
+        worker.register_wf(DEFAULT_WORKFLOW_TYPE, |ctx: WfContext| async move {
+            let sig = ctx.signal_workflow(SignalWorkflowOptions::new(
+                "fake_wid",
+                "fake_rid",
+                SIGNAME,
+                [b"hi!"],
+            ));
+            ctx.cancelled().await;
+            sig.cancel(&ctx);
+            // Rather than hanging, the signal resolves with whatever server made of it
+            sig.await.expect("Signal was delivered");
+            Ok(().into())
+        });
+        worker.run().await.unwrap();
+    }
+
     #[tokio::test]
     async fn cancels_before_sending() {
         let mut t = TestHistoryBuilder::default();
@@ -423,6 +529,7 @@ mod tests {
         for state in [
             SignalExternalMachineState::Cancelled(Cancelled {}),
             Signaled {}.into(),
+            Failed {}.into(),
         ] {
             let mut s = SignalExternalMachine::from_parts(state.clone(), Default::default());
             let cmds = s.cancel().unwrap();
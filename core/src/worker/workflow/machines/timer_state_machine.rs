@@ -19,6 +19,7 @@ use temporal_sdk_core_protos::{
         history::v1::{history_event, TimerFiredEventAttributes},
         sdk::v1::UserMetadata,
     },
+    InvalidTimeField, WfDuration,
 };
 
 fsm! {
@@ -39,8 +40,13 @@ fsm! {
     CancelTimerCommandCreated --(Cancel) --> CancelTimerCommandCreated;
     CancelTimerCommandCreated
         --(CommandCancelTimer, on_command_cancel_timer) --> CancelTimerCommandSent;
+    // The timer may fire before server sees our cancel, in which case the fire wins
+    CancelTimerCommandCreated
+        --(TimerFired(TimerFiredEventAttributes), shared on_timer_fired) --> Fired;
 
     CancelTimerCommandSent --(TimerCanceled) --> Canceled;
+    CancelTimerCommandSent
+        --(TimerFired(TimerFiredEventAttributes), shared on_timer_fired) --> Fired;
 
     // Ignore any spurious cancellations after resolution
     Canceled --(Cancel) --> Canceled;
@@ -60,15 +66,43 @@ pub(super) enum TimerMachineCommand {
 pub(super) struct SharedState {
     attrs: StartTimer,
     cancelled_before_sent: bool,
+    /// Set if the timer fired after we had already issued a cancel for it. Lang resolved the timer
+    /// as cancelled when it asked for that, so it must not also be told the timer fired, and the
+    /// cancel command is now moot.
+    fired_after_cancel: bool,
 }
 
-/// Creates a new, scheduled, timer as a [CancellableCommand]
-pub(super) fn new_timer(attribs: StartTimer) -> NewMachineWithCommand {
+impl SharedState {
+    fn check_fired_timer_id(
+        &self,
+        attrs: &TimerFiredEventAttributes,
+    ) -> Result<(), WFMachinesError> {
+        if self.attrs.seq.to_string() == attrs.timer_id {
+            Ok(())
+        } else {
+            Err(WFMachinesError::Nondeterminism(format!(
+                "Timer fired event did not have expected timer id {}, it was {}!",
+                self.attrs.seq, attrs.timer_id
+            )))
+        }
+    }
+}
+
+/// Creates a new, scheduled, timer as a [CancellableCommand]. Fails if the timer's duration is
+/// unset or invalid.
+pub(super) fn new_timer(
+    mut attribs: StartTimer,
+) -> Result<NewMachineWithCommand, InvalidTimeField> {
+    let start_to_fire = WfDuration::required(
+        "start_to_fire_timeout",
+        attribs.start_to_fire_timeout.as_ref(),
+    )?;
+    attribs.start_to_fire_timeout = Some(start_to_fire.into());
     let (timer, add_cmd) = TimerMachine::new_scheduled(attribs);
-    NewMachineWithCommand {
+    Ok(NewMachineWithCommand {
         command: add_cmd,
         machine: timer.into(),
-    }
+    })
 }
 
 impl TimerMachine {
@@ -97,6 +131,7 @@ impl TimerMachine {
             SharedState {
                 attrs: attribs,
                 cancelled_before_sent: false,
+                fired_after_cancel: false,
             },
         )
     }
@@ -158,11 +193,44 @@ impl CancelTimerCommandCreated {
     pub(super) fn on_command_cancel_timer(self) -> TimerMachineTransition<CancelTimerCommandSent> {
         TransitionResult::ok(vec![], CancelTimerCommandSent::default())
     }
+
+    pub(super) fn on_timer_fired(
+        self,
+        dat: &mut SharedState,
+        attrs: TimerFiredEventAttributes,
+    ) -> TimerMachineTransition<Fired> {
+        fired_after_cancel(dat, attrs)
+    }
 }
 
 #[derive(Default, Clone)]
 pub(super) struct CancelTimerCommandSent {}
 
+impl CancelTimerCommandSent {
+    pub(super) fn on_timer_fired(
+        self,
+        dat: &mut SharedState,
+        attrs: TimerFiredEventAttributes,
+    ) -> TimerMachineTransition<Fired> {
+        fired_after_cancel(dat, attrs)
+    }
+}
+
+/// The timer fired, but only after we issued a cancel for it. No job is produced, since lang has
+/// already resolved the timer as cancelled.
+fn fired_after_cancel(
+    dat: &mut SharedState,
+    attrs: TimerFiredEventAttributes,
+) -> TimerMachineTransition<Fired> {
+    match dat.check_fired_timer_id(&attrs) {
+        Ok(()) => {
+            dat.fired_after_cancel = true;
+            TransitionResult::default()
+        }
+        Err(e) => TransitionResult::Err(e),
+    }
+}
+
 #[derive(Default, Clone)]
 pub(super) struct Canceled {}
 
@@ -201,13 +269,9 @@ impl StartCommandRecorded {
         dat: &mut SharedState,
         attrs: TimerFiredEventAttributes,
     ) -> TimerMachineTransition<Fired> {
-        if dat.attrs.seq.to_string() == attrs.timer_id {
-            TransitionResult::ok(vec![TimerMachineCommand::Complete], Fired::default())
-        } else {
-            TransitionResult::Err(WFMachinesError::Nondeterminism(format!(
-                "Timer fired event did not have expected timer id {}, it was {}!",
-                dat.attrs.seq, attrs.timer_id
-            )))
+        match dat.check_fired_timer_id(&attrs) {
+            Ok(()) => TransitionResult::ok(vec![TimerMachineCommand::Complete], Fired::default()),
+            Err(e) => TransitionResult::Err(e),
         }
     }
 
@@ -258,7 +322,9 @@ impl Cancellable for TimerMachine {
     }
 
     fn was_cancelled_before_sent_to_server(&self) -> bool {
-        self.shared_state().cancelled_before_sent
+        // A cancel superseded by the timer firing is treated the same way, so that the cancel
+        // command is neither sent nor expected to match any event
+        self.shared_state().cancelled_before_sent || self.shared_state().fired_after_cancel
     }
 }
 
@@ -267,10 +333,11 @@ mod test {
     use super::*;
     use crate::{
         replay::TestHistoryBuilder,
-        test_help::{build_fake_sdk, canned_histories, MockPollCfg},
+        test_help::{build_fake_sdk, canned_histories, MockPollCfg, ResponseType},
     };
+    use rstest::rstest;
     use std::{mem::discriminant, time::Duration};
-    use temporal_sdk::{CancellableFuture, WfContext, WorkflowResult};
+    use temporal_sdk::{CancellableFuture, TimerResult, WfContext, WorkflowResult};
     use temporal_sdk_core_protos::{
         temporal::api::{enums::v1::WorkflowTaskFailedCause, failure::v1::Failure},
         DEFAULT_WORKFLOW_TYPE,
@@ -385,6 +452,73 @@ mod test {
         worker.run().await.unwrap();
     }
 
+    #[rstest]
+    #[tokio::test]
+    async fn fired_cancelled_and_never_sent_timers(#[values(false, true)] replaying: bool) {
+        // Timer 1 is cancelled before it is ever sent, so the timers in history are 2 and 3
+        let t = canned_histories::cancel_timer("3", "2");
+        let mock_cfg = if replaying {
+            MockPollCfg::from_resps(t, [ResponseType::AllHistory])
+        } else {
+            let mut mock_cfg = MockPollCfg::from_hist_builder(t);
+            mock_cfg.completion_asserts_from_expectations(|mut asserts| {
+                asserts
+                    .then(move |wft| {
+                        let types: Vec<_> = wft.commands.iter().map(|c| c.command_type()).collect();
+                        assert_eq!(types, [CommandType::StartTimer, CommandType::StartTimer]);
+                    })
+                    .then(move |wft| {
+                        let types: Vec<_> = wft.commands.iter().map(|c| c.command_type()).collect();
+                        assert_eq!(
+                            types,
+                            [
+                                CommandType::CancelTimer,
+                                CommandType::CompleteWorkflowExecution
+                            ]
+                        );
+                    });
+            });
+            mock_cfg
+        };
+
+        let mut worker = build_fake_sdk(mock_cfg);
+        worker.register_wf(DEFAULT_WORKFLOW_TYPE, |ctx: WfContext| async move {
+            let never_sent = ctx.timer(Duration::from_secs(500));
+            never_sent.cancel(&ctx);
+            assert_matches!(never_sent.await, TimerResult::Cancelled);
+            let cancelled = ctx.timer(Duration::from_secs(500));
+            assert_matches!(ctx.timer(Duration::from_secs(5)).await, TimerResult::Fired);
+            cancelled.cancel(&ctx);
+            assert_matches!(cancelled.await, TimerResult::Cancelled);
+            Ok(().into())
+        });
+        worker.run().await.unwrap();
+    }
+
+    #[test]
+    fn fire_after_cancel_issued_produces_no_job() {
+        for state in [
+            TimerMachineState::CancelTimerCommandCreated(Default::default()),
+            CancelTimerCommandSent {}.into(),
+        ] {
+            let mut s = TimerMachine::from_parts(state, Default::default());
+            let cmds = OnEventWrapper::on_event_mut(
+                &mut s,
+                TimerMachineEvents::TimerFired(TimerFiredEventAttributes {
+                    timer_id: "0".to_string(),
+                    ..Default::default()
+                }),
+            )
+            .unwrap();
+            assert_eq!(cmds.len(), 0);
+            assert_eq!(
+                discriminant(&TimerMachineState::from(Fired {})),
+                discriminant(s.state())
+            );
+            assert!(s.was_cancelled_before_sent_to_server());
+        }
+    }
+
     #[test]
     fn cancels_ignored_terminal() {
         for state in [TimerMachineState::Canceled(Canceled {}), Fired {}.into()] {
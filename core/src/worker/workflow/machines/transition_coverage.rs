@@ -81,8 +81,9 @@ mod machine_coverage_report {
         fail_workflow_state_machine::FailWorkflowMachine,
         local_activity_state_machine::LocalActivityMachine,
         modify_workflow_properties_state_machine::ModifyWorkflowPropertiesMachine,
-        patch_state_machine::PatchMachine, signal_external_state_machine::SignalExternalMachine,
-        timer_state_machine::TimerMachine, update_state_machine::UpdateMachine,
+        patch_state_machine::PatchMachine, side_effect_state_machine::SideEffectMachine,
+        signal_external_state_machine::SignalExternalMachine, timer_state_machine::TimerMachine,
+        update_state_machine::UpdateMachine,
         upsert_search_attributes_state_machine::UpsertSearchAttributesMachine,
         workflow_task_state_machine::WorkflowTaskMachine,
     };
@@ -117,6 +118,7 @@ mod machine_coverage_report {
         let mut cont_as_new = ContinueAsNewWorkflowMachine::visualizer().to_owned();
         let mut cancel_wf = CancelWorkflowMachine::visualizer().to_owned();
         let mut version = PatchMachine::visualizer().to_owned();
+        let mut side_effect = SideEffectMachine::visualizer().to_owned();
         let mut signal_ext = SignalExternalMachine::visualizer().to_owned();
         let mut cancel_ext = CancelExternalMachine::visualizer().to_owned();
         let mut la_mach = LocalActivityMachine::visualizer().to_owned();
@@ -140,6 +142,7 @@ mod machine_coverage_report {
                 }
                 m @ "CancelWorkflowMachine" => cover_transitions(m, &mut cancel_wf, coverage),
                 m @ "PatchMachine" => cover_transitions(m, &mut version, coverage),
+                m @ "SideEffectMachine" => cover_transitions(m, &mut side_effect, coverage),
                 m @ "SignalExternalMachine" => cover_transitions(m, &mut signal_ext, coverage),
                 m @ "CancelExternalMachine" => cover_transitions(m, &mut cancel_ext, coverage),
                 m @ "LocalActivityMachine" => cover_transitions(m, &mut la_mach, coverage),
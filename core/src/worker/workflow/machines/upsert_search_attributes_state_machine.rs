@@ -196,7 +196,10 @@ mod tests {
         },
     };
     use rustfsm::StateMachine;
-    use std::collections::HashMap;
+    use std::{
+        collections::{BTreeMap, HashMap},
+        time::Duration,
+    };
     use temporal_sdk::WfContext;
     use temporal_sdk_core_api::Worker;
     use temporal_sdk_core_protos::{
@@ -210,11 +213,16 @@ mod tests {
             command::v1::command::Attributes,
             common::v1::Payload,
             enums::v1::EventType,
-            history::v1::{HistoryEvent, UpsertWorkflowSearchAttributesEventAttributes},
+            history::v1::{
+                HistoryEvent, TimerFiredEventAttributes, TimerStartedEventAttributes,
+                UpsertWorkflowSearchAttributesEventAttributes,
+            },
         },
         DEFAULT_WORKFLOW_TYPE,
     };
-    use temporal_sdk_core_test_utils::WorkerTestHelpers;
+    use temporal_sdk_core_test_utils::{
+        interceptors::ActivationAssertionsInterceptor, WorkerTestHelpers,
+    };
 
     #[tokio::test]
     async fn upsert_search_attrs_from_workflow() {
@@ -267,6 +275,68 @@ mod tests {
         worker.run().await.unwrap();
     }
 
+    #[tokio::test]
+    async fn replays_upserts_without_activation_jobs() {
+        let search_attrs = |key: &str| SearchAttributes {
+            indexed_fields: BTreeMap::from([(key.to_string(), key.as_json_payload().unwrap())]),
+        };
+        let mut t = TestHistoryBuilder::default();
+        t.add_by_type(EventType::WorkflowExecutionStarted);
+        t.add_full_wf_task();
+        t.add(UpsertWorkflowSearchAttributesEventAttributes {
+            workflow_task_completed_event_id: 4,
+            search_attributes: Some(search_attrs("first")),
+        });
+        let timer_started = t.add(TimerStartedEventAttributes {
+            workflow_task_completed_event_id: 4,
+            timer_id: "1".to_string(),
+            ..Default::default()
+        });
+        t.add(TimerFiredEventAttributes {
+            started_event_id: timer_started,
+            timer_id: "1".to_string(),
+        });
+        t.add_full_wf_task();
+        t.add(UpsertWorkflowSearchAttributesEventAttributes {
+            workflow_task_completed_event_id: 10,
+            search_attributes: Some(search_attrs("second")),
+        });
+        t.add_workflow_execution_completed();
+
+        // Lang only hears about the timer, never the upsert events
+        let mut aai = ActivationAssertionsInterceptor::default();
+        aai.then(|a| {
+            assert_matches!(
+                a.jobs.as_slice(),
+                [WorkflowActivationJob {
+                    variant: Some(workflow_activation_job::Variant::InitializeWorkflow(_)),
+                }]
+            );
+        })
+        .then(|a| {
+            assert_matches!(
+                a.jobs.as_slice(),
+                [WorkflowActivationJob {
+                    variant: Some(workflow_activation_job::Variant::FireTimer(_)),
+                }]
+            );
+        });
+
+        let mock_cfg = MockPollCfg::from_resps(t, [ResponseType::AllHistory]);
+        let mut worker = build_fake_sdk(mock_cfg);
+        worker.set_worker_interceptor(aai);
+        worker.register_wf(DEFAULT_WORKFLOW_TYPE, move |ctx: WfContext| async move {
+            let upsert = |key: &str| {
+                ctx.upsert_search_attributes(search_attrs(key).indexed_fields);
+            };
+            upsert("first");
+            ctx.timer(Duration::from_secs(1)).await;
+            upsert("second");
+            Ok(().into())
+        });
+        worker.run().await.unwrap();
+    }
+
     #[rstest::rstest]
     fn upsert_search_attrs_sm() {
         let mut sm = UpsertSearchAttributesMachine::from_parts(Created {}.into(), SharedState {});
@@ -274,7 +344,7 @@ mod tests {
         let sa_attribs = UpsertWorkflowSearchAttributesEventAttributes {
             workflow_task_completed_event_id: 0,
             search_attributes: Some(SearchAttributes {
-                indexed_fields: HashMap::from([("Yo".to_string(), Payload::default())]),
+                indexed_fields: BTreeMap::from([("Yo".to_string(), Payload::default())]),
             }),
         };
         let recorded_history_event = HistoryEvent {
@@ -1,21 +1,30 @@
 mod local_acts;
+mod transition_log;
 
 use super::{
     cancel_external_state_machine::new_external_cancel,
     cancel_workflow_state_machine::cancel_workflow,
     complete_workflow_state_machine::complete_workflow,
     continue_as_new_workflow_state_machine::continue_as_new,
-    fail_workflow_state_machine::fail_workflow, local_activity_state_machine::new_local_activity,
-    patch_state_machine::has_change, signal_external_state_machine::new_external_signal,
-    timer_state_machine::new_timer, upsert_search_attributes_state_machine::upsert_search_attrs,
-    workflow_machines::local_acts::LocalActivityData,
-    workflow_task_state_machine::WorkflowTaskMachine, Machines, NewMachineWithCommand,
-    TemporalStateMachine,
+    fail_workflow_state_machine::fail_workflow,
+    local_activity_state_machine::new_local_activity,
+    patch_state_machine::has_change,
+    side_effect_state_machine::side_effect,
+    signal_external_state_machine::new_external_signal,
+    timer_state_machine::new_timer,
+    upsert_search_attributes_state_machine::upsert_search_attrs,
+    workflow_machines::{
+        local_acts::LocalActivityData,
+        transition_log::{Transition, TransitionLog, TransitionTrigger, ERROR_TAIL_LEN},
+    },
+    workflow_task_state_machine::WorkflowTaskMachine,
+    Machines, NewMachineWithCommand, TemporalStateMachine,
 };
 use crate::{
     abstractions::dbg_panic,
     internal_flags::{CoreInternalFlags, InternalFlags},
     protosext::{
+        marker_compat::MarkerKind,
         protocol_messages::{IncomingProtocolMessage, IncomingProtocolMessageBody},
         CompleteLocalActivityData, HistoryEventExt, ValidScheduleLA,
     },
@@ -24,10 +33,13 @@ use crate::{
         workflow::{
             history_update::NextWFT,
             machines::{
-                activity_state_machine::ActivityMachine,
+                activity_state_machine::{
+                    apply_activity_defaults, attach_workflow_time_remaining, ActivityMachine,
+                },
                 child_workflow_state_machine::ChildWorkflowMachine,
                 modify_workflow_properties_state_machine::modify_workflow_properties,
-                patch_state_machine::VERSION_SEARCH_ATTR_KEY, update_state_machine::UpdateMachine,
+                patch_state_machine::VERSION_SEARCH_ATTR_KEY,
+                update_state_machine::UpdateMachine,
                 upsert_search_attributes_state_machine::upsert_search_attrs_internal,
                 HistEventData,
             },
@@ -38,35 +50,43 @@ use crate::{
     },
 };
 use anyhow::Context;
+use prost::Message as _;
 use siphasher::sip::SipHasher13;
 use slotmap::{SlotMap, SparseSecondaryMap};
 use std::{
     cell::RefCell,
-    collections::{HashMap, VecDeque},
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
     convert::TryInto,
     hash::{Hash, Hasher},
     iter::Peekable,
+    mem,
     rc::Rc,
     sync::Arc,
     time::{Duration, Instant, SystemTime},
 };
-use temporal_sdk_core_api::worker::WorkerConfig;
+use temporal_sdk_core_api::worker::{ReplayProgress, WorkerConfig, WorkerLifecycleEvent};
 use temporal_sdk_core_protos::{
     coresdk::{
         common::{NamespacedWorkflowExecution, VersioningIntent},
         workflow_activation,
         workflow_activation::{
-            workflow_activation_job, NotifyHasPatch, UpdateRandomSeed, WorkflowActivation,
+            workflow_activation_job, ActivationMetadata, NotifyHasPatch, ResolveSideEffect,
+            UpdateRandomSeed, WorkflowActivation,
         },
         workflow_commands::ContinueAsNewWorkflowExecution,
     },
+    rng::random_checksum,
     temporal::api::{
         command::v1::{command::Attributes as ProtoCmdAttrs, Command as ProtoCommand},
-        enums::v1::EventType,
-        history::v1::{history_event, HistoryEvent},
+        common::v1::Payload,
+        enums::v1::{CommandType, EventType},
+        failure::v1::Failure,
+        history::v1::{command_event_types, history_event, HistoryEvent},
         protocol::v1::{message::SequencingId, Message as ProtocolMessage},
         sdk::v1::WorkflowTaskCompletedMetadata,
     },
+    utilities::TryIntoOrNone,
+    WfDuration, WfTimestamp,
 };
 
 type Result<T, E = WFMachinesError> = std::result::Result<T, E>;
@@ -92,6 +112,14 @@ pub(crate) struct WorkflowMachines {
     /// queries) to receive a history with no new workflow tasks. If the last history we processed
     /// also had no new tasks, we need a way to know not to apply the same events over again.
     pub(crate) last_processed_event: i64,
+    /// The highest event id known to exist in history, from the task itself and any pages fetched
+    /// for it so far. Used to report replay progress.
+    last_known_event_id: i64,
+    /// How many extra history pages have been fetched for the current workflow task
+    history_pages_fetched: u64,
+    /// Every event applied so far, kept only if [WorkerConfig::retain_history_for_handover] or
+    /// [WorkerConfig::verify_completions_by_replay] is set
+    retained_history: Option<Vec<HistoryEvent>>,
     /// True if the workflow is replaying from history
     pub(crate) replaying: bool,
     /// Workflow identifier
@@ -103,7 +131,8 @@ pub(crate) struct WorkflowMachines {
     /// Is set to true once we've seen the final event in workflow history, to avoid accidentally
     /// re-applying the final workflow task.
     pub(crate) have_seen_terminal_event: bool,
-    /// The time the workflow execution began, as told by the WEStarted event
+    /// The time the workflow execution began, as told by the WEStarted event, plus any backoff
+    /// before its first workflow task
     workflow_start_time: Option<SystemTime>,
     /// The time the workflow execution finished, as determined by when the machines handled
     /// a terminal workflow command. If this is `Some`, you know the workflow is ended.
@@ -120,6 +149,9 @@ pub(crate) struct WorkflowMachines {
     history_size_bytes: u64,
     /// Set on each WFT started event
     continue_as_new_suggested: bool,
+    /// Details of the most recent WFT, as seen in its scheduled & started events. History size is
+    /// tracked separately above.
+    wft_metadata: WftMetadata,
     /// Set if the current WFT is already complete and that completion event had a build id in it.
     current_wft_build_id: Option<String>,
 
@@ -128,8 +160,9 @@ pub(crate) struct WorkflowMachines {
     /// command from lang.
     machine_is_core_created: SparseSecondaryMap<MachineKey, ()>,
     /// A mapping for accessing machines associated to a particular event, where the key is the id
-    /// of the initiating event for that machine.
-    machines_by_event_id: HashMap<i64, MachineKey>,
+    /// of the initiating event for that machine. Ordered so that anything walking it does so in
+    /// history order.
+    machines_by_event_id: BTreeMap<i64, MachineKey>,
     /// A mapping for accessing machines that were created as a result of protocol messages. The
     /// key is the protocol's instance id.
     machines_by_protocol_instance_id: HashMap<String, MachineKey>,
@@ -148,6 +181,8 @@ pub(crate) struct WorkflowMachines {
 
     /// Information about patch markers we have already seen while replaying history
     encountered_patch_markers: HashMap<String, ChangeInfo>,
+    /// The last value recorded for each mutable side effect, by mutable id
+    mutable_side_effects: HashMap<String, Option<Payload>>,
 
     /// Contains extra local-activity related data
     local_activity_data: LocalActivityData,
@@ -158,6 +193,11 @@ pub(crate) struct WorkflowMachines {
     /// Metrics context
     pub(crate) metrics: MetricsContext,
     worker_config: Arc<WorkerConfig>,
+    /// Whether first workflow tasks may skip the scans for completed tasks and markers (see
+    /// [Self::can_apply_as_first_wft]). Only turned off by tests comparing the two.
+    first_wft_fast_path: bool,
+    /// The run's most recent machine transitions
+    transition_log: TransitionLog,
 }
 
 #[derive(Debug, derive_more::Display)]
@@ -174,11 +214,27 @@ enum MachineAssociatedCommand {
     FakeLocalActivityMarker(u32),
 }
 
+impl MachineAssociatedCommand {
+    fn command_type(&self) -> CommandType {
+        match self {
+            MachineAssociatedCommand::Real(c) => c.command_type(),
+            MachineAssociatedCommand::FakeLocalActivityMarker(_) => CommandType::RecordMarker,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 struct ChangeInfo {
     created_command: bool,
 }
 
+#[derive(Debug, Default, Clone, Copy)]
+struct WftMetadata {
+    attempt: u32,
+    scheduled_time: Option<WfTimestamp>,
+    started_time: Option<WfTimestamp>,
+}
+
 /// Returned by [TemporalStateMachine]s when handling events
 #[derive(Debug, derive_more::Display)]
 #[must_use]
@@ -206,7 +262,10 @@ pub(super) enum MachineResponse {
         time: SystemTime,
     },
     #[display("UpdateRunIdOnWorkflowReset({run_id})")]
-    UpdateRunIdOnWorkflowReset { run_id: String },
+    UpdateRunIdOnWorkflowReset {
+        run_id: String,
+        failure: Option<Failure>,
+    },
 
     /// Queue a local activity to be processed by the worker
     #[display("QueueLocalActivity")]
@@ -236,6 +295,8 @@ where
 impl WorkflowMachines {
     pub(crate) fn new(basics: RunBasics, driven_wf: DrivenWorkflow) -> Self {
         let replaying = basics.history.previous_wft_started_id > 0;
+        let last_known_event_id = basics.history.last_known_event_id();
+        let history_pages_fetched = basics.history.fetch_progress.pages_fetched;
         let mut observed_internal_flags = InternalFlags::new(basics.capabilities);
         // Peek ahead to determine used flags in the first WFT.
         if let Some(attrs) = basics.history.peek_next_wft_completed(0) {
@@ -254,6 +315,11 @@ impl WorkflowMachines {
             current_started_event_id: 0,
             next_started_event_id: 0,
             last_processed_event: 0,
+            last_known_event_id,
+            history_pages_fetched,
+            retained_history: (basics.worker_config.retain_history_for_handover
+                || basics.worker_config.verify_completions_by_replay)
+                .then(Vec::new),
             workflow_start_time: None,
             workflow_end_time: None,
             wft_start_time: None,
@@ -261,6 +327,7 @@ impl WorkflowMachines {
             observed_internal_flags: Rc::new(RefCell::new(observed_internal_flags)),
             history_size_bytes: 0,
             continue_as_new_suggested: false,
+            wft_metadata: Default::default(),
             current_wft_build_id: None,
             all_machines: Default::default(),
             machine_is_core_created: Default::default(),
@@ -271,9 +338,12 @@ impl WorkflowMachines {
             current_wf_task_commands: Default::default(),
             message_outbox: Default::default(),
             encountered_patch_markers: Default::default(),
+            mutable_side_effects: Default::default(),
             local_activity_data: LocalActivityData::default(),
             have_seen_terminal_event: false,
+            transition_log: TransitionLog::new(basics.worker_config.machine_transition_log_size),
             worker_config: basics.worker_config,
+            first_wft_fast_path: true,
         }
     }
 
@@ -282,8 +352,18 @@ impl WorkflowMachines {
         self.workflow_end_time.is_some()
     }
 
-    /// Returns the total time it took to execute the workflow. Returns `None` if workflow is
-    /// incomplete, or time went backwards.
+    /// Record that server accepted the completion carrying this run's terminal command. Jobs
+    /// produced for the run from now on are never delivered to lang.
+    pub(crate) fn mark_terminal_acknowledged(&mut self) {
+        debug_assert!(
+            self.workflow_is_finished(),
+            "Only a finished workflow's completion can be acknowledged"
+        );
+        self.drive_me.mark_terminal();
+    }
+
+    /// Returns the total time it took to execute the workflow, not counting any delay before its
+    /// first workflow task. Returns `None` if workflow is incomplete, or time went backwards.
     pub(crate) fn total_runtime(&self) -> Option<Duration> {
         self.workflow_start_time
             .zip(self.workflow_end_time)
@@ -305,6 +385,8 @@ impl WorkflowMachines {
     }
 
     pub(crate) fn new_history_from_server(&mut self, update: HistoryUpdate) -> Result<()> {
+        self.last_known_event_id = self.last_known_event_id.max(update.last_known_event_id());
+        self.history_pages_fetched = update.fetch_progress.pages_fetched;
         self.last_history_from_server = update;
         self.replaying = self.last_history_from_server.previous_wft_started_id > 0;
         self.apply_next_wft_from_history()?;
@@ -329,20 +411,30 @@ impl WorkflowMachines {
             }) => {
                 let act_id = CommandID::LocalActivity(seq);
                 let mk = self.get_machine_key(act_id)?;
-                let mach = self.machine_mut(mk);
-                if let Machines::LocalActivityMachine(ref mut lam) = *mach {
-                    let resps =
-                        lam.try_resolve(result, runtime, attempt, backoff, original_schedule_time)?;
-                    if resps.is_empty() {
-                        result_important = false;
-                    }
-                    self.process_machine_responses(mk, resps)?;
-                } else {
-                    return Err(WFMachinesError::Nondeterminism(format!(
-                        "Command matching activity with seq num {seq} existed but was not a \
-                        local activity!"
-                    )));
+                let resps = self.drive_machine(
+                    mk,
+                    TransitionTrigger::LocalActivityResolution { seq },
+                    |mach| {
+                        if let Machines::LocalActivityMachine(lam) = mach {
+                            lam.try_resolve(
+                                result,
+                                runtime,
+                                attempt,
+                                backoff,
+                                original_schedule_time,
+                            )
+                        } else {
+                            Err(WFMachinesError::Nondeterminism(format!(
+                                "Command matching activity with seq num {seq} existed but was not \
+                                 a local activity!"
+                            )))
+                        }
+                    },
+                )?;
+                if resps.is_empty() {
+                    result_important = false;
                 }
+                self.process_machine_responses(mk, resps)?;
                 self.local_activity_data.done_executing(seq);
             }
         }
@@ -369,6 +461,18 @@ impl WorkflowMachines {
         self.current_started_event_id
     }
 
+    /// The events applied so far, if they are being retained for handover to another worker
+    pub(crate) fn retained_history(&self) -> Option<&[HistoryEvent]> {
+        self.retained_history.as_deref()
+    }
+
+    /// Drop the retained history, unless it is also needed for handover
+    pub(crate) fn stop_retaining_history_for_verification(&mut self) {
+        if !self.worker_config.retain_history_for_handover {
+            self.retained_history = None;
+        }
+    }
+
     pub(crate) fn prepare_for_wft_response(&mut self) -> MachinesWFTResponseContent {
         MachinesWFTResponseContent {
             replaying: self.replaying,
@@ -407,7 +511,9 @@ impl WorkflowMachines {
     /// The job list may be empty, in which case it is expected the caller handles what to do in a
     /// "no work" situation. Possibly, it may know about some work the machines don't, like queries.
     pub(crate) fn get_wf_activation(&mut self) -> WorkflowActivation {
-        let jobs = self.drive_me.drain_jobs();
+        let jobs = self
+            .drive_me
+            .drain_jobs(self.worker_config.max_jobs_per_activation);
         // Even though technically we may have satisfied all the criteria to be done with replay,
         // query only activations are always "replaying" to keep things sane.
         let all_query = jobs.iter().all(|j| {
@@ -436,6 +542,14 @@ impl WorkflowMachines {
             history_size_bytes: self.history_size_bytes,
             continue_as_new_suggested: self.continue_as_new_suggested,
             build_id_for_current_task,
+            metadata: Some(ActivationMetadata {
+                attempt: self.wft_metadata.attempt,
+                scheduled_time: self.wft_metadata.scheduled_time.map(Into::into),
+                started_time: self.wft_metadata.started_time.map(Into::into),
+                history_size_bytes: self.history_size_bytes,
+            }),
+            // Assigned by the run once it issues the activation
+            correlation_id: String::new(),
         }
     }
 
@@ -443,6 +557,66 @@ impl WorkflowMachines {
         !self.drive_me.peek_pending_jobs().is_empty()
     }
 
+    pub(crate) fn num_pending_jobs(&self) -> usize {
+        self.drive_me.peek_pending_jobs().len()
+    }
+
+    /// Describes the current state of every machine, followed by the run's recent transitions
+    pub(crate) fn dump_machine_state(&self) -> String {
+        let mut dump = format!("Machines of run {}:", self.run_id);
+        for (k, m) in self.all_machines.iter() {
+            dump.push_str(&format!("\n  {}({k:?}) in {}", m.name(), m.state_name()));
+        }
+        dump.push_str("\nRecent machine transitions:");
+        for t in self.transition_log.tail(usize::MAX) {
+            dump.push_str(&format!("\n  {t}"));
+        }
+        dump
+    }
+
+    /// Appends the latest machine transitions to nondeterminism errors, since what led up to the
+    /// mismatch is often needed to make sense of it. Other errors are returned as is.
+    pub(crate) fn with_recent_transitions(&self, err: WFMachinesError) -> WFMachinesError {
+        match err {
+            WFMachinesError::Nondeterminism(mut msg) if self.transition_log.is_enabled() => {
+                msg.push_str("\nRecent machine transitions:");
+                for t in self.transition_log.tail(ERROR_TAIL_LEN) {
+                    msg.push_str(&format!("\n  {t}"));
+                }
+                WFMachinesError::Nondeterminism(msg)
+            }
+            e => e,
+        }
+    }
+
+    /// Returns the approximate number of bytes held by the parts of this run's state which grow
+    /// with its history: state machines (which are kept for the life of the run), events not yet
+    /// applied, commands not yet sent, and jobs not yet given to lang.
+    pub(crate) fn approx_retained_bytes(&self) -> usize {
+        let command_bytes: usize = self
+            .commands
+            .iter()
+            .chain(self.current_wf_task_commands.iter())
+            .map(|c| {
+                mem::size_of::<CommandAndMachine>()
+                    + match &c.command {
+                        MachineAssociatedCommand::Real(cmd) => cmd.encoded_len(),
+                        MachineAssociatedCommand::FakeLocalActivityMarker(_) => 0,
+                    }
+            })
+            .sum();
+        let job_bytes: usize = self
+            .drive_me
+            .peek_pending_jobs()
+            .iter()
+            .map(|j| mem::size_of::<OutgoingJob>() + j.variant.encoded_len())
+            .sum();
+        self.all_machines.len() * mem::size_of::<Machines>()
+            + self.last_history_from_server.approx_retained_bytes()
+            + command_bytes
+            + job_bytes
+    }
+
     pub(crate) fn has_pending_la_resolutions(&self) -> bool {
         self.drive_me
             .peek_pending_jobs()
@@ -450,6 +624,15 @@ impl WorkflowMachines {
             .any(|v| v.variant.is_local_activity_resolution())
     }
 
+    /// Returns true if there are pending jobs which were derived from history, as opposed to local
+    /// activity resolutions which may have been produced locally.
+    pub(crate) fn has_pending_history_jobs(&self) -> bool {
+        self.drive_me
+            .peek_pending_jobs()
+            .iter()
+            .any(|v| !v.variant.is_local_activity_resolution())
+    }
+
     pub(crate) fn get_metadata_for_wft_complete(&mut self) -> WorkflowTaskCompletedMetadata {
         // If this worker has a build ID and we're completing the task, we want to say our ID is the
         // current build ID, so that if we get a query before any new history, we properly can
@@ -487,19 +670,80 @@ impl WorkflowMachines {
         self.last_processed_event -= 2;
         // Then, we have to drop any state machines (which should only be one workflow task machine)
         // we may have created when servicing the speculative task.
-        // Remove when https://github.com/rust-lang/rust/issues/59618 is stable
-        let remove_these: Vec<_> = self
+        let remove_these = self
             .machines_by_event_id
-            .iter()
-            .filter(|(mid, _)| **mid > self.last_processed_event)
-            .map(|(mid, mkey)| (*mid, *mkey))
-            .collect();
-        for (mid, mkey) in remove_these {
-            self.machines_by_event_id.remove(&mid);
+            .split_off(&(self.last_processed_event + 1));
+        for mkey in remove_these.into_values() {
             self.all_machines.remove(mkey);
         }
     }
 
+    /// If strict command validation is enabled and lang reported a random checksum, check that it
+    /// was computed from the randomness seed lang was most recently given. A mismatch means lang's
+    /// random number generator wasn't (re)seeded properly, which would silently break replay.
+    pub(crate) fn check_random_checksum(&self, checksum: u64) -> Result<(), String> {
+        if checksum == 0 || !self.worker_config.strict_command_validation {
+            return Ok(());
+        }
+        let Some(seed) = self.drive_me.delivered_randomness_seed() else {
+            return Ok(());
+        };
+        let expected = random_checksum(seed);
+        if checksum != expected {
+            return Err(format!(
+                "Lang reported random checksum {checksum:#x}, but the last randomness seed it was \
+                 given has checksum {expected:#x}. Lang's random number generator must be seeded \
+                 from the latest InitializeWorkflow or UpdateRandomSeed job."
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check that every command lang sent which refers to an earlier one (ex: cancelling a timer)
+    /// refers to a command this run has actually seen, either previously or earlier in the same
+    /// completion. Returns a description of the first that doesn't, without touching any machines.
+    pub(crate) fn check_command_references(&self, commands: &[WFCommand]) -> Result<(), String> {
+        let mut introduced = HashSet::new();
+        for (i, cmd) in commands.iter().enumerate() {
+            let referenced = match cmd {
+                WFCommand::AddTimer(t) => {
+                    introduced.insert(CommandID::Timer(t.seq));
+                    continue;
+                }
+                WFCommand::AddActivity(a) => {
+                    introduced.insert(CommandID::Activity(a.seq));
+                    continue;
+                }
+                WFCommand::AddLocalActivity(a) => {
+                    introduced.insert(CommandID::LocalActivity(a.seq));
+                    continue;
+                }
+                WFCommand::AddChildWorkflow(c) => {
+                    introduced.insert(CommandID::ChildWorkflowStart(c.seq));
+                    continue;
+                }
+                WFCommand::SignalExternalWorkflow(s) => {
+                    introduced.insert(CommandID::SignalExternal(s.seq));
+                    continue;
+                }
+                WFCommand::CancelTimer(t) => CommandID::Timer(t.seq),
+                WFCommand::RequestCancelActivity(a) => CommandID::Activity(a.seq),
+                WFCommand::RequestCancelLocalActivity(a) => CommandID::LocalActivity(a.seq),
+                WFCommand::CancelChild(c) => CommandID::ChildWorkflowStart(c.child_workflow_seq),
+                WFCommand::CancelSignalWorkflow(s) => CommandID::SignalExternal(s.seq),
+                _ => continue,
+            };
+            if !self.id_to_machine.contains_key(&referenced) && !introduced.contains(&referenced) {
+                return Err(format!(
+                    "Command {i} ({cmd}) refers to {referenced:?}, which run {} never issued. \
+                     The completion may contain commands meant for another run.",
+                    self.run_id
+                ));
+            }
+        }
+        Ok(())
+    }
+
     /// Iterate the state machines, which consists of grabbing any pending outgoing commands from
     /// the workflow code, handling them, and preparing them to be sent off to the server.
     pub(crate) fn iterate_machines(&mut self) -> Result<()> {
@@ -553,6 +797,11 @@ impl WorkflowMachines {
             }
         };
         let num_events_to_process = events.len();
+        if let Some(retained) = self.retained_history.as_mut() {
+            retained.extend(events.iter().cloned());
+        }
+
+        let first_wft = self.can_apply_as_first_wft(&events, has_final_event);
 
         // Process any WFT completed events in the next sequence, as well as peek ahead to the
         // subsequent one to properly apply flags & any other data. Macro used to avoid self
@@ -567,20 +816,23 @@ impl WorkflowMachines {
                 }
             }};
         }
-        let mut peeked_events = events.iter().peekable();
-        while let Some(event) = peeked_events.next() {
-            if let Some(history_event::Attributes::WorkflowTaskCompletedEventAttributes(ref wtc)) =
-                event.attributes
-            {
-                apply_wft_complete_data!(self, wtc);
-            }
-            if peeked_events.peek().is_none() {
-                if let Some(wtc) = self
-                    .last_history_from_server
-                    .peek_next_wft_completed(event.event_id)
+        if !first_wft {
+            let mut peeked_events = events.iter().peekable();
+            while let Some(event) = peeked_events.next() {
+                if let Some(history_event::Attributes::WorkflowTaskCompletedEventAttributes(
+                    ref wtc,
+                )) = event.attributes
                 {
                     apply_wft_complete_data!(self, wtc);
                 }
+                if peeked_events.peek().is_none() {
+                    if let Some(wtc) = self
+                        .last_history_from_server
+                        .peek_next_wft_completed(event.event_id)
+                    {
+                        apply_wft_complete_data!(self, wtc);
+                    }
+                }
             }
         }
 
@@ -690,6 +942,9 @@ impl WorkflowMachines {
                 do_handle_event = true;
             }
             self.last_processed_event = eid;
+            if self.replaying {
+                self.maybe_report_replay_progress();
+            }
         }
 
         // Needed to delay mutation of self until after we've iterated over peeked events.
@@ -709,10 +964,13 @@ impl WorkflowMachines {
         // Alternatively, lookahead can seemingly be avoided if we were to consider the commands
         // that follow a WFT to be _part of_ that wft rather than the next one. That change might
         // make sense to do, and maybe simplifies things slightly, but is a substantial alteration.
-        for e in self
-            .last_history_from_server
-            .peek_next_wft_sequence(last_handled_wft_started_id)
-        {
+        let lookahead = if first_wft {
+            &[][..]
+        } else {
+            self.last_history_from_server
+                .peek_next_wft_sequence(last_handled_wft_started_id)
+        };
+        for e in lookahead {
             if let Some((patch_id, _)) = e.get_patch_marker_details() {
                 self.encountered_patch_markers.insert(
                     patch_id.clone(),
@@ -725,6 +983,15 @@ impl WorkflowMachines {
                     workflow_activation_job::Variant::NotifyHasPatch(NotifyHasPatch { patch_id })
                         .into(),
                 );
+            } else if let Some((se_dat, result)) = e.get_side_effect_marker_details() {
+                // Found a side effect marker, so lang should use the recorded value
+                self.drive_me.send_job(
+                    workflow_activation_job::Variant::ResolveSideEffect(ResolveSideEffect {
+                        seq: se_dat.seq,
+                        result,
+                    })
+                    .into(),
+                );
             } else if e.is_local_activity_marker() {
                 if let Some(la_dat) = e.clone().into_local_activity_marker_details() {
                     if let Ok(mk) =
@@ -759,10 +1026,19 @@ impl WorkflowMachines {
         for action in delayed_actions {
             match action {
                 DelayedAction::WakeLa(mk, la_dat) => {
-                    let mach = self.machine_mut(mk);
-                    if let Machines::LocalActivityMachine(ref mut lam) = *mach {
+                    if let Machines::LocalActivityMachine(lam) = self.machine(mk) {
                         if lam.will_accept_resolve_marker() {
-                            let resps = lam.try_resolve_with_dat(la_dat.into())?;
+                            let seq = la_dat.marker_dat.seq;
+                            let resps = self.drive_machine(
+                                mk,
+                                TransitionTrigger::LocalActivityResolution { seq },
+                                |mach| match mach {
+                                    Machines::LocalActivityMachine(lam) => {
+                                        lam.try_resolve_with_dat(la_dat.into())
+                                    }
+                                    _ => unreachable!("Machine was just checked"),
+                                },
+                            )?;
                             self.process_machine_responses(mk, resps)?;
                         } else {
                             self.local_activity_data.insert_peeked_marker(la_dat);
@@ -782,6 +1058,85 @@ impl WorkflowMachines {
         Ok(num_events_to_process)
     }
 
+    /// Returns true if `events` are the entire first workflow task of a brand new workflow and
+    /// nothing else: execution started, task scheduled, task started. There can be no completed
+    /// tasks or markers around such a task, so [Self::apply_next_wft_from_history] skips scanning
+    /// for them, and only applies the events themselves.
+    fn can_apply_as_first_wft(&self, events: &[HistoryEvent], has_final_event: bool) -> bool {
+        const FIRST_WFT: [EventType; 3] = [
+            EventType::WorkflowExecutionStarted,
+            EventType::WorkflowTaskScheduled,
+            EventType::WorkflowTaskStarted,
+        ];
+        self.first_wft_fast_path
+            && has_final_event
+            && self.last_processed_event == 0
+            && self.protocol_msgs.is_empty()
+            && events.len() == FIRST_WFT.len()
+            && events
+                .iter()
+                .zip(FIRST_WFT)
+                .enumerate()
+                .all(|(ix, (e, et))| {
+                    e.event_id == ix as i64 + 1 && e.event_type() == et && e.attributes.is_some()
+                })
+    }
+
+    /// Report how far replay has gotten, every `replay_progress_interval` events
+    fn maybe_report_replay_progress(&mut self) {
+        let interval = self.worker_config.replay_progress_interval as i64;
+        if interval == 0 || self.last_processed_event % interval != 0 {
+            return;
+        }
+        self.last_known_event_id = self.last_known_event_id.max(self.last_processed_event);
+        let progress = ReplayProgress {
+            workflow_id: self.workflow_id.clone(),
+            run_id: self.run_id.clone(),
+            events_applied: self.last_processed_event as u64,
+            total_known_events: self.last_known_event_id as u64,
+            history_pages_fetched: self.history_pages_fetched,
+        };
+        debug!(
+            events_applied = progress.events_applied,
+            total_known_events = progress.total_known_events,
+            history_pages_fetched = progress.history_pages_fetched,
+            "Replay progress"
+        );
+        self.metrics.wf_replay_progress(
+            progress.events_applied as f64 / progress.total_known_events as f64,
+        );
+        if let Some(observer) = self.worker_config.lifecycle_observer.as_ref() {
+            observer(&WorkerLifecycleEvent::ReplayProgress(progress));
+        }
+    }
+
+    /// Record the details of the current WFT which lang sees on its activations. These always come
+    /// from history, so that they look the same whether or not the task is being replayed.
+    ///
+    /// The times are only informational, so an invalid one is left out of the metadata rather than
+    /// failing the task.
+    fn note_wft_event(&mut self, event: &HistoryEvent) {
+        let event_time = || {
+            WfTimestamp::optional("event_time", event.event_time.as_ref()).unwrap_or_else(|e| {
+                warn!(event = %event, "Ignoring invalid workflow task event time: {e}");
+                None
+            })
+        };
+        match event.attributes {
+            Some(history_event::Attributes::WorkflowTaskScheduledEventAttributes(ref attrs)) => {
+                self.wft_metadata.attempt = u32::try_from(attrs.attempt).unwrap_or_default();
+                self.wft_metadata.scheduled_time = event_time();
+            }
+            Some(history_event::Attributes::WorkflowTaskStartedEventAttributes(ref attrs)) => {
+                self.history_size_bytes =
+                    u64::try_from(attrs.history_size_bytes).unwrap_or_default();
+                self.continue_as_new_suggested = attrs.suggest_continue_as_new;
+                self.wft_metadata.started_time = event_time();
+            }
+            _ => {}
+        }
+    }
+
     /// Handle a single event from the workflow history.
     ///
     /// This function will attempt to apply the event to the workflow state machines. If there is
@@ -828,16 +1183,18 @@ impl WorkflowMachines {
             };
         }
 
+        if event.is_metadata_event() {
+            debug!("Skipping metadata event");
+            return Ok(EventHandlingOutcome::SkipEvent {
+                skip_next_event: false,
+            });
+        }
+
         if event.is_command_event() {
             return self.handle_command_event(event_dat, next_event);
         }
 
-        if let Some(history_event::Attributes::WorkflowTaskStartedEventAttributes(ref attrs)) =
-            event.attributes
-        {
-            self.history_size_bytes = u64::try_from(attrs.history_size_bytes).unwrap_or_default();
-            self.continue_as_new_suggested = attrs.suggest_continue_as_new;
-        }
+        self.note_wft_event(event);
 
         if let Some(initial_cmd_id) = event.get_initial_command_event_id() {
             let mkey = self
@@ -873,6 +1230,22 @@ impl WorkflowMachines {
     ) -> Result<EventHandlingOutcome> {
         let event = &event_dat.event;
 
+        match event.marker_kind() {
+            Some(MarkerKind::ForeignSideEffect) => {
+                debug!("Skipping side effect marker recorded by another SDK");
+                return Ok(EventHandlingOutcome::SkipEvent {
+                    skip_next_event: false,
+                });
+            }
+            Some(MarkerKind::Unknown) if !self.worker_config.strict_marker_replay => {
+                debug!(event = %event, "Skipping unknown marker");
+                return Ok(EventHandlingOutcome::SkipEvent {
+                    skip_next_event: false,
+                });
+            }
+            _ => {}
+        }
+
         if event.is_local_activity_marker() {
             let deets = event.extract_local_activity_marker_data().ok_or_else(|| {
                 WFMachinesError::Fatal(format!("Local activity marker was unparsable: {event:?}"))
@@ -919,6 +1292,15 @@ impl WorkflowMachines {
                 .was_cancelled_before_sent_to_server();
 
             if !canceled_before_sent {
+                let command_type = command.command.command_type();
+                if !command_may_produce(command_type, event.event_type()) {
+                    return Err(WFMachinesError::Nondeterminism(format!(
+                        "History event {event_id} is {:?}, but the workflow produced a {:?} \
+                         command",
+                        event.event_type(),
+                        command_type
+                    )));
+                }
                 // Feed the machine the event
                 self.submachine_handle_event(command.machine, event_dat)?;
                 break command;
@@ -942,9 +1324,22 @@ impl WorkflowMachines {
                     attrs,
                 )) = event_dat.event.attributes
                 {
+                    // Executions started with a delay, or backing off ahead of a retry or cron
+                    // run, do nothing until their first workflow task, so that wait isn't part
+                    // of their runtime.
+                    let backoff = WfDuration::optional(
+                        "first_workflow_task_backoff",
+                        attrs.first_workflow_task_backoff.as_ref(),
+                    )
+                    .map_err(|e| {
+                        WFMachinesError::Fatal(format!(
+                            "Invalid WorkflowExecutionStarted event (id {event_id}): {e}"
+                        ))
+                    })?;
                     if let Some(st) = event_dat.event.event_time {
                         let as_systime: SystemTime = st.try_into()?;
-                        self.workflow_start_time = Some(as_systime);
+                        self.workflow_start_time =
+                            Some(as_systime + backoff.map(Duration::from).unwrap_or_default());
                         // Set the workflow time to be the event time of the first event, so that
                         // if there is a query issued before first WFT started event, there is some
                         // workflow time set.
@@ -953,6 +1348,7 @@ impl WorkflowMachines {
                     // Notify the lang sdk that it's time to kick off a workflow
                     self.drive_me.start(
                         self.workflow_id.clone(),
+                        &self.run_id,
                         str_to_randomness_seed(&attrs.original_execution_run_id),
                         event_dat.event.event_time.unwrap_or_default(),
                         attrs,
@@ -977,7 +1373,9 @@ impl WorkflowMachines {
                     self.drive_me
                         .send_job(workflow_activation::SignalWorkflow::from(attrs).into());
                 } else {
-                    // err
+                    return Err(WFMachinesError::Fatal(format!(
+                        "WorkflowExecutionSignaled event did not have appropriate attributes: {event_dat}"
+                    )));
                 }
             }
             Ok(EventType::WorkflowExecutionCancelRequested) => {
@@ -990,7 +1388,9 @@ impl WorkflowMachines {
                     self.drive_me
                         .send_job(workflow_activation::CancelWorkflow::from(attrs).into());
                 } else {
-                    // err
+                    return Err(WFMachinesError::Fatal(format!(
+                        "WorkflowExecutionCancelRequested event did not have appropriate attributes: {event_dat}"
+                    )));
                 }
             }
             _ => {
@@ -1013,10 +1413,36 @@ impl WorkflowMachines {
     /// Wrapper for calling [TemporalStateMachine::handle_event] which appropriately takes action
     /// on the returned machine responses
     fn submachine_handle_event(&mut self, sm: MachineKey, event: HistEventData) -> Result<()> {
-        let machine_responses = self.machine_mut(sm).handle_event(event)?;
+        let trigger = TransitionTrigger::Event {
+            id: event.event.event_id,
+            event_type: event.event.event_type(),
+        };
+        let machine_responses = self.drive_machine(sm, trigger, |m| m.handle_event(event))?;
         self.process_machine_responses(sm, machine_responses)?;
         Ok(())
     }
+
+    /// Hand some input to a machine with `f`, recording the transition it made if it succeeded
+    fn drive_machine<T>(
+        &mut self,
+        mk: MachineKey,
+        trigger: TransitionTrigger,
+        f: impl FnOnce(&mut Machines) -> Result<T>,
+    ) -> Result<T> {
+        let machine = self.machine_mut(mk);
+        let from = machine.state_name();
+        let r = f(machine)?;
+        let (machine_name, to) = (machine.name(), machine.state_name());
+        self.transition_log.record(Transition {
+            wft_started_id: self.current_started_event_id,
+            machine: mk,
+            machine_name,
+            from,
+            to,
+            trigger,
+        });
+        Ok(r)
+    }
     /// Handle a single protocol message delivered in a workflow task.
     ///
     /// This function will attempt to apply the message to a corresponding state machine for the
@@ -1057,6 +1483,13 @@ impl WorkflowMachines {
     /// Transfer commands from `current_wf_task_commands` to `commands`, so they may be sent off
     /// to the server. While doing so, [TemporalStateMachine::handle_command] is called on the
     /// machine associated with the command.
+    ///
+    /// Commands are sent in the order they were queued for the task, which must be the same
+    /// whether executing or replaying, since replay matches commands against history in order:
+    /// * Commands from lang keep the order lang sent them in.
+    /// * The search attribute upsert for a patch immediately follows that patch's marker.
+    /// * Commands a machine originates while handling its own command (ex: cancelling an external
+    ///   child) follow everything already queued for the task.
     fn prepare_commands(&mut self) -> Result<()> {
         // It's possible we might prepare commands more than once before completing a WFT. (Because
         // of local activities, of course). Some commands might have since been cancelled that we
@@ -1076,9 +1509,11 @@ impl WorkflowMachines {
             {
                 match &c.command {
                     MachineAssociatedCommand::Real(cmd) => {
-                        let machine_responses = self
-                            .machine_mut(c.machine)
-                            .handle_command(cmd.command_type())?;
+                        let ct = cmd.command_type();
+                        let machine_responses =
+                            self.drive_machine(c.machine, TransitionTrigger::Command(ct), |m| {
+                                m.handle_command(ct)
+                            })?;
                         self.process_machine_responses(c.machine, machine_responses)?;
                     }
                     MachineAssociatedCommand::FakeLocalActivityMarker(_) => {}
@@ -1124,10 +1559,14 @@ impl WorkflowMachines {
                 } => {
                     self.task_started(task_started_event_id, time)?;
                 }
-                MachineResponse::UpdateRunIdOnWorkflowReset { run_id: new_run_id } => {
+                MachineResponse::UpdateRunIdOnWorkflowReset {
+                    run_id: new_run_id,
+                    failure,
+                } => {
                     self.drive_me.send_job(
                         workflow_activation_job::Variant::UpdateRandomSeed(UpdateRandomSeed {
                             randomness_seed: str_to_randomness_seed(&new_run_id),
+                            reset_failure: failure,
                         })
                         .into(),
                     );
@@ -1189,30 +1628,44 @@ impl WorkflowMachines {
                     // activations during replay that didn't happen during execution, just like
                     // we sometimes pre-resolve activities when first requested.
                     if let Some(preres) = self.local_activity_data.take_preresolution(seq) {
-                        if let Machines::LocalActivityMachine(lam) = self.machine_mut(smk) {
-                            let more_responses = lam.try_resolve_with_dat(preres)?;
-                            self.process_machine_responses(smk, more_responses)?;
-                        } else {
-                            panic!("A non local-activity machine returned a request cancel LA response");
-                        }
+                        let more_responses = self.drive_machine(
+                            smk,
+                            TransitionTrigger::LocalActivityResolution { seq },
+                            |mach| match mach {
+                                Machines::LocalActivityMachine(lam) => {
+                                    lam.try_resolve_with_dat(preres)
+                                }
+                                _ => panic!(
+                                    "A non local-activity machine returned a request cancel LA \
+                                     response"
+                                ),
+                            },
+                        )?;
+                        self.process_machine_responses(smk, more_responses)?;
                     }
                     // If it's in the request queue, just rip it out.
                     else if let Some(removed_act) =
                         self.local_activity_data.remove_from_queue(seq)
                     {
                         // We removed it. Notify the machine that the activity cancelled.
-                        if let Machines::LocalActivityMachine(lam) = self.machine_mut(smk) {
-                            let more_responses = lam.try_resolve(
-                                LocalActivityExecutionResult::empty_cancel(),
-                                Duration::from_secs(0),
-                                removed_act.attempt,
-                                None,
-                                removed_act.original_schedule_time,
-                            )?;
-                            self.process_machine_responses(smk, more_responses)?;
-                        } else {
-                            panic!("A non local-activity machine returned a request cancel LA response");
-                        }
+                        let more_responses = self.drive_machine(
+                            smk,
+                            TransitionTrigger::LocalActivityResolution { seq },
+                            |mach| match mach {
+                                Machines::LocalActivityMachine(lam) => lam.try_resolve(
+                                    LocalActivityExecutionResult::empty_cancel(),
+                                    Duration::from_secs(0),
+                                    removed_act.attempt,
+                                    None,
+                                    removed_act.original_schedule_time,
+                                ),
+                                _ => panic!(
+                                    "A non local-activity machine returned a request cancel LA \
+                                     response"
+                                ),
+                            },
+                        )?;
+                        self.process_machine_responses(smk, more_responses)?;
                     } else {
                         // Finally, if we know about the LA at all, it's currently running, so
                         // queue the cancel request to be given to the LA manager.
@@ -1246,11 +1699,20 @@ impl WorkflowMachines {
         // which were waiting for a marker to instead decide to execute the LA since it clearly
         // will not be resolved via marker.
         if !self.replaying {
+            let la_machines: Vec<_> = self
+                .all_machines
+                .iter()
+                .filter(|(_, m)| matches!(m, Machines::LocalActivityMachine(_)))
+                .map(|(k, _)| k)
+                .collect();
             let mut resps = vec![];
-            for (k, mach) in self.all_machines.iter_mut() {
-                if let Machines::LocalActivityMachine(lam) = mach {
-                    resps.push((k, lam.encountered_non_replay_wft()?));
-                }
+            for k in la_machines {
+                let resp_set =
+                    self.drive_machine(k, TransitionTrigger::NonReplayWft, |m| match m {
+                        Machines::LocalActivityMachine(lam) => lam.encountered_non_replay_wft(),
+                        _ => unreachable!("Only local activity machines were collected"),
+                    })?;
+                resps.push((k, resp_set));
             }
             for (mkey, resp_set) in resps {
                 self.process_machine_responses(mkey, resp_set)?;
@@ -1270,7 +1732,12 @@ impl WorkflowMachines {
             match cmd {
                 WFCommand::AddTimer(attrs) => {
                     let seq = attrs.seq;
-                    self.add_cmd_to_wf_task(new_timer(attrs), CommandID::Timer(seq).into());
+                    let timer = new_timer(attrs).map_err(|e| {
+                        WFMachinesError::Fatal(format!(
+                            "Invalid start timer request (seq {seq}): {e}"
+                        ))
+                    })?;
+                    self.add_cmd_to_wf_task(timer, CommandID::Timer(seq).into());
                 }
                 WFCommand::UpsertSearchAttributes(attrs) => {
                     self.drive_me
@@ -1287,20 +1754,33 @@ impl WorkflowMachines {
                 WFCommand::CancelTimer(attrs) => {
                     self.process_cancellation(CommandID::Timer(attrs.seq))?;
                 }
-                WFCommand::AddActivity(attrs) => {
+                WFCommand::AddActivity(mut attrs) => {
+                    apply_activity_defaults(&mut attrs, &self.worker_config.activity_defaults);
+                    if self.worker_config.attach_workflow_time_remaining {
+                        if let Some(now) = self.current_wf_time {
+                            let expiration = self
+                                .drive_me
+                                .get_started_info()
+                                .and_then(|i| i.execution_expiration_time);
+                            attach_workflow_time_remaining(&mut attrs, expiration, now);
+                        }
+                    }
                     let seq = attrs.seq;
                     let use_compat = self.determine_use_compatible_flag(
                         attrs.versioning_intent(),
                         &attrs.task_queue,
                     );
-                    self.add_cmd_to_wf_task(
-                        ActivityMachine::new_scheduled(
-                            attrs,
-                            self.observed_internal_flags.clone(),
-                            use_compat,
-                        ),
-                        CommandID::Activity(seq).into(),
-                    );
+                    let activity = ActivityMachine::new_scheduled(
+                        attrs,
+                        self.observed_internal_flags.clone(),
+                        use_compat,
+                    )
+                    .map_err(|e| {
+                        WFMachinesError::Fatal(format!(
+                            "Invalid schedule activity request (seq {seq}): {e}"
+                        ))
+                    })?;
+                    self.add_cmd_to_wf_task(activity, CommandID::Activity(seq).into());
                 }
                 WFCommand::AddLocalActivity(attrs) => {
                     let seq = attrs.seq;
@@ -1340,7 +1820,14 @@ impl WorkflowMachines {
                         attrs.versioning_intent(),
                         &attrs.task_queue,
                     );
-                    self.add_terminal_command(continue_as_new(attrs, use_compat));
+                    // Lang has no way to set a cron schedule on continue as new, so a cron
+                    // workflow always stays on its schedule
+                    let cron_schedule = self
+                        .drive_me
+                        .get_started_info()
+                        .map(|si| si.cron_schedule.clone())
+                        .unwrap_or_default();
+                    self.add_terminal_command(continue_as_new(attrs, use_compat, cron_schedule));
                 }
                 WFCommand::CancelWorkflow(attrs) => {
                     self.add_terminal_command(cancel_workflow(attrs));
@@ -1376,6 +1863,19 @@ impl WorkflowMachines {
                         }
                     }
                 }
+                WFCommand::RecordSideEffect(attrs) => {
+                    // Mutable side effects only record a marker when their value changed
+                    if !attrs.mutable_id.is_empty() {
+                        match self.mutable_side_effects.get(&attrs.mutable_id) {
+                            Some(last) if *last == attrs.result => continue,
+                            _ => {
+                                self.mutable_side_effects
+                                    .insert(attrs.mutable_id.clone(), attrs.result.clone());
+                            }
+                        }
+                    }
+                    self.add_cmd_to_wf_task(side_effect(attrs), CommandIdKind::NeverResolves);
+                }
                 WFCommand::AddChildWorkflow(attrs) => {
                     let seq = attrs.seq;
                     let use_compat = self.determine_use_compatible_flag(
@@ -1433,17 +1933,19 @@ impl WorkflowMachines {
                 }
                 WFCommand::UpdateResponse(ur) => {
                     let m_key = self.get_machine_by_msg(&ur.protocol_instance_id)?;
-                    let mach = self.machine_mut(m_key);
-                    if let Machines::UpdateMachine(m) = mach {
-                        let resps = m.handle_response(ur)?;
-                        self.process_machine_responses(m_key, resps)?;
-                    } else {
-                        return Err(WFMachinesError::Nondeterminism(format!(
-                            "Tried to handle an update response for \
-                             update with instance id {} but it was not found!",
-                            &ur.protocol_instance_id
-                        )));
-                    }
+                    let resps =
+                        self.drive_machine(m_key, TransitionTrigger::UpdateResponse, |mach| {
+                            if let Machines::UpdateMachine(m) = mach {
+                                m.handle_response(ur)
+                            } else {
+                                Err(WFMachinesError::Nondeterminism(format!(
+                                    "Tried to handle an update response for \
+                                     update with instance id {} but it was not found!",
+                                    &ur.protocol_instance_id
+                                )))
+                            }
+                        })?;
+                    self.process_machine_responses(m_key, resps)?;
                 }
                 WFCommand::NoCommandsFromLang => (),
             }
@@ -1455,8 +1957,7 @@ impl WorkflowMachines {
     /// be included in the activation
     fn process_cancellation(&mut self, id: CommandID) -> Result<()> {
         let m_key = self.get_machine_key(id)?;
-        let mach = self.machine_mut(m_key);
-        let machine_resps = mach.cancel()?;
+        let machine_resps = self.drive_machine(m_key, TransitionTrigger::Cancel, |m| m.cancel())?;
         debug!(machine_responses = %machine_resps.display(), cmd_id = ?id,
                "Cancel request responses");
         self.process_machine_resps_impl(m_key, machine_resps)
@@ -1543,6 +2044,18 @@ impl WorkflowMachines {
             if attrs.retry_policy.is_none() {
                 attrs.retry_policy.clone_from(&started_info.retry_policy);
             }
+            // The new run inherits this execution's expiration time, so it can't usefully run for
+            // longer than the time remaining until then. A zero timeout would mean "unlimited", so
+            // in the unlikely event nothing remains, the execution timing out takes care of it.
+            if let (Some(expiration), Some(now)) =
+                (started_info.execution_expiration_time, self.current_wf_time)
+            {
+                let remaining = expiration.duration_since(now).unwrap_or_default();
+                let run_timeout: Option<Duration> = attrs.workflow_run_timeout.try_into_or_none();
+                if !remaining.is_zero() && run_timeout.is_some_and(|rt| rt > remaining) {
+                    attrs.workflow_run_timeout = remaining.try_into().ok();
+                }
+            }
         }
         if attrs.search_attributes.is_empty() {
             attrs.search_attributes = self.drive_me.get_current_search_attributes();
@@ -1603,6 +2116,13 @@ fn str_to_randomness_seed(run_id: &str) -> u64 {
     s.finish()
 }
 
+/// Returns false if a command of type `command_type` can never be recorded as an event of type
+/// `event_type`, which means the workflow produced different commands than it did originally.
+/// Types this doesn't know are left for the command's machine to reject.
+fn command_may_produce(command_type: CommandType, event_type: EventType) -> bool {
+    command_event_types(command_type).map_or(true, |expected| expected.contains(&event_type))
+}
+
 #[must_use]
 enum EventHandlingOutcome {
     SkipEvent { skip_next_event: bool },
@@ -1680,3 +2200,303 @@ enum CommandIdKind {
     /// A command which is fire-and-forget (ex: Upsert search attribs)
     NeverResolves,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_help::{canned_histories, test_worker_cfg},
+        worker::client::mocks::DEFAULT_TEST_CAPABILITIES,
+    };
+    use rstest::rstest;
+    use std::sync::mpsc::Sender;
+    use temporal_sdk_core_protos::{
+        coresdk::workflow_commands::StartTimer, default_wes_attribs, TestHistoryBuilder,
+    };
+
+    fn first_wft_history(signal_first: bool) -> TestHistoryBuilder {
+        let mut t = TestHistoryBuilder::default();
+        t.add_by_type(EventType::WorkflowExecutionStarted);
+        if signal_first {
+            t.add_we_signaled("sig", vec![]);
+        }
+        t.add_workflow_task_scheduled_and_started();
+        t
+    }
+
+    fn machines_after_first_wft(
+        t: &TestHistoryBuilder,
+        previous_wft_started_id: i64,
+        fast_path: bool,
+    ) -> WorkflowMachines {
+        let cfg = test_worker_cfg().build().unwrap();
+        machines_and_sink_after_first_wft(t, previous_wft_started_id, fast_path, cfg).0
+    }
+
+    fn machines_and_sink_after_first_wft(
+        t: &TestHistoryBuilder,
+        previous_wft_started_id: i64,
+        fast_path: bool,
+        cfg: WorkerConfig,
+    ) -> (WorkflowMachines, Sender<Vec<WFCommand>>) {
+        let events = t.get_full_history_info().unwrap().events().to_vec();
+        let wft_started_id = events.last().unwrap().event_id;
+        let (driven, sink) = DrivenWorkflow::new();
+        let mut wfm = WorkflowMachines::new(
+            RunBasics {
+                worker_config: Arc::new(cfg),
+                workflow_id: "wfid".to_string(),
+                workflow_type: "wftype".to_string(),
+                run_id: "runid".to_string(),
+                history: HistoryUpdate::dummy(),
+                metrics: MetricsContext::no_op(),
+                capabilities: DEFAULT_TEST_CAPABILITIES,
+            },
+            driven,
+        );
+        wfm.first_wft_fast_path = fast_path;
+        let (update, _) =
+            HistoryUpdate::from_events(events, previous_wft_started_id, wft_started_id, true);
+        wfm.new_work_from_server(update, vec![]).unwrap();
+        (wfm, sink)
+    }
+
+    #[rstest]
+    #[case::new_workflow(false, 0)]
+    #[case::replaying_first_task(false, 3)]
+    #[case::signal_before_first_task(true, 0)]
+    fn first_wft_fast_path_matches_general_path(
+        #[case] signal_first: bool,
+        #[case] previous_wft_started_id: i64,
+    ) {
+        let t = first_wft_history(signal_first);
+        let mut fast = machines_after_first_wft(&t, previous_wft_started_id, true);
+        let mut slow = machines_after_first_wft(&t, previous_wft_started_id, false);
+
+        assert_eq!(fast.last_processed_event, slow.last_processed_event);
+        assert_eq!(
+            fast.get_last_wft_started_id(),
+            slow.get_last_wft_started_id()
+        );
+        assert_eq!(fast.next_started_event_id, slow.next_started_event_id);
+        assert_eq!(fast.replaying, slow.replaying);
+        assert_eq!(fast.current_wf_time, slow.current_wf_time);
+        assert_eq!(fast.history_size_bytes, slow.history_size_bytes);
+        assert_eq!(fast.machines_by_event_id, slow.machines_by_event_id);
+        let fast_act = fast.get_wf_activation();
+        assert_matches!(
+            fast_act.jobs[0].variant,
+            Some(workflow_activation_job::Variant::InitializeWorkflow(_))
+        );
+        assert_eq!(fast_act, slow.get_wf_activation());
+    }
+
+    #[test]
+    fn first_wft_backoff_is_excluded_from_runtime_and_given_to_lang() {
+        let backoff = Duration::from_secs(60 * 60);
+        let mut t = TestHistoryBuilder::default();
+        let mut wes_attrs = default_wes_attribs();
+        wes_attrs.first_workflow_task_backoff = Some(backoff.try_into().unwrap());
+        t.add(wes_attrs);
+        t.add_workflow_task_scheduled_and_started();
+        // The execution was started with a delay, long before its first workflow task
+        let start_time = SystemTime::now() - backoff - Duration::from_secs(5);
+        t.modify_event(1, |e| e.event_time = Some(start_time.into()));
+        let mut wfm = machines_after_first_wft(&t, 0, true);
+
+        assert_eq!(wfm.workflow_start_time, Some(start_time + backoff));
+        wfm.workflow_end_time = Some(start_time + backoff + Duration::from_secs(5));
+        assert_eq!(wfm.total_runtime(), Some(Duration::from_secs(5)));
+        let act = wfm.get_wf_activation();
+        assert_matches!(
+            &act.jobs[0].variant,
+            Some(workflow_activation_job::Variant::InitializeWorkflow(iw))
+                if iw.cron_schedule_to_schedule_interval == Some(backoff.try_into().unwrap())
+        );
+    }
+
+    #[test]
+    fn jobs_after_acknowledged_completion_are_not_delivered() {
+        let mut wfm = machines_after_first_wft(&first_wft_history(false), 0, true);
+        wfm.get_wf_activation();
+        // Something is still pending when lang completes the workflow
+        wfm.drive_me
+            .send_job(workflow_activation::SignalWorkflow::default().into());
+        wfm.workflow_end_time = Some(SystemTime::now());
+        wfm.mark_terminal_acknowledged();
+        assert!(!wfm.has_pending_jobs());
+
+        // A late signal, ex: from a stale buffered resolution, goes nowhere
+        wfm.drive_me
+            .send_job(workflow_activation::SignalWorkflow::default().into());
+        assert!(!wfm.has_pending_jobs());
+        assert!(wfm.get_wf_activation().jobs.is_empty());
+        assert_eq!(wfm.drive_me.discarded_jobs(), 2);
+    }
+
+    #[test]
+    fn transitions_are_logged_across_tasks() {
+        // Replay the whole history, so both tasks are applied without talking to server
+        let t = canned_histories::single_timer("1");
+        let cfg = test_worker_cfg().build().unwrap();
+        let (mut wfm, sink) = machines_and_sink_after_first_wft(&t, 8, true, cfg);
+        wfm.get_wf_activation();
+        sink.send(vec![WFCommand::AddTimer(StartTimer {
+            seq: 1,
+            start_to_fire_timeout: Some(Duration::from_secs(1).try_into().unwrap()),
+        })])
+        .unwrap();
+        wfm.iterate_machines().unwrap();
+        wfm.apply_next_wft_from_history().unwrap();
+        assert_matches!(
+            wfm.get_wf_activation().jobs[0].variant,
+            Some(workflow_activation_job::Variant::FireTimer(_))
+        );
+
+        let timer_transitions: Vec<_> = wfm
+            .transition_log
+            .tail(usize::MAX)
+            .filter(|t| t.machine_name == "TimerMachine")
+            .map(|t| (t.wft_started_id, t.from, t.to, t.trigger))
+            .collect();
+        assert_eq!(
+            timer_transitions,
+            [
+                (
+                    3,
+                    "StartCommandCreated",
+                    "StartCommandCreated",
+                    TransitionTrigger::Command(CommandType::StartTimer)
+                ),
+                (
+                    3,
+                    "StartCommandCreated",
+                    "StartCommandRecorded",
+                    TransitionTrigger::Event {
+                        id: 5,
+                        event_type: EventType::TimerStarted
+                    }
+                ),
+                (
+                    3,
+                    "StartCommandRecorded",
+                    "Fired",
+                    TransitionTrigger::Event {
+                        id: 6,
+                        event_type: EventType::TimerFired
+                    }
+                ),
+            ]
+        );
+        // Both tasks' workflow task machines were driven through their events too
+        let wft_events: Vec<_> = wfm
+            .transition_log
+            .tail(usize::MAX)
+            .filter(|t| t.machine_name == "WorkflowTaskMachine")
+            .map(|t| match t.trigger {
+                TransitionTrigger::Event { id, .. } => id,
+                other => panic!("Unexpected trigger {other:?}"),
+            })
+            .collect();
+        assert_eq!(wft_events, [2, 3, 4, 7, 8]);
+
+        let dump = wfm.dump_machine_state();
+        assert!(dump.contains("TimerMachine"), "{dump}");
+        assert!(
+            dump.contains("StartCommandRecorded -> Fired on event 6 (TimerFired)"),
+            "{dump}"
+        );
+        assert_matches!(
+            wfm.with_recent_transitions(WFMachinesError::Nondeterminism("oh no".to_string())),
+            WFMachinesError::Nondeterminism(msg)
+                if msg.starts_with("oh no\nRecent machine transitions:")
+                    && msg.lines().count() == 1 + 1 + 8
+        );
+    }
+
+    #[test]
+    fn nondeterminism_errors_carry_the_logged_transitions() {
+        // Replays a timer whose id doesn't match the one the workflow starts
+        let t = canned_histories::single_timer("badid");
+        let cfg = test_worker_cfg().build().unwrap();
+        let (mut wfm, sink) = machines_and_sink_after_first_wft(&t, 8, true, cfg);
+        wfm.get_wf_activation();
+        sink.send(vec![WFCommand::AddTimer(StartTimer {
+            seq: 1,
+            start_to_fire_timeout: Some(Duration::from_secs(1).try_into().unwrap()),
+        })])
+        .unwrap();
+        wfm.iterate_machines().unwrap();
+        let err = wfm.apply_next_wft_from_history().unwrap_err();
+        let err = wfm.with_recent_transitions(err);
+
+        // The timer got as far as being started, and the failed transition isn't logged
+        let timer_transitions: Vec<_> = wfm
+            .transition_log
+            .tail(usize::MAX)
+            .filter(|t| t.machine_name == "TimerMachine")
+            .map(|t| (t.from, t.to, t.trigger))
+            .collect();
+        assert_eq!(
+            timer_transitions,
+            [
+                (
+                    "StartCommandCreated",
+                    "StartCommandCreated",
+                    TransitionTrigger::Command(CommandType::StartTimer)
+                ),
+                (
+                    "StartCommandCreated",
+                    "StartCommandRecorded",
+                    TransitionTrigger::Event {
+                        id: 5,
+                        event_type: EventType::TimerStarted
+                    }
+                ),
+            ]
+        );
+        let logged: String = wfm
+            .transition_log
+            .tail(ERROR_TAIL_LEN)
+            .map(|t| format!("\n  {t}"))
+            .collect();
+        assert_matches!(
+            err,
+            WFMachinesError::Nondeterminism(msg)
+                if msg.contains("Timer fired event did not have expected timer id 1")
+                    && msg.ends_with(&format!("\nRecent machine transitions:{logged}"))
+        );
+
+        let dump = wfm.dump_machine_state();
+        let logged: String = wfm
+            .transition_log
+            .tail(usize::MAX)
+            .map(|t| format!("\n  {t}"))
+            .collect();
+        assert!(dump.starts_with("Machines of run runid:"), "{dump}");
+        assert!(
+            dump.lines().any(
+                |l| l.starts_with("  TimerMachine(") && l.ends_with(" in StartCommandRecorded")
+            ),
+            "{dump}"
+        );
+        assert!(
+            dump.ends_with(&format!("\nRecent machine transitions:{logged}")),
+            "{dump}"
+        );
+    }
+
+    #[test]
+    fn transition_log_can_be_disabled() {
+        let cfg = test_worker_cfg()
+            .machine_transition_log_size(0)
+            .build()
+            .unwrap();
+        let (wfm, _) = machines_and_sink_after_first_wft(&first_wft_history(false), 0, true, cfg);
+        assert_eq!(wfm.transition_log.tail(usize::MAX).count(), 0);
+        assert_matches!(
+            wfm.with_recent_transitions(WFMachinesError::Nondeterminism("oh no".to_string())),
+            WFMachinesError::Nondeterminism(msg) if msg == "oh no"
+        );
+    }
+}
@@ -4,7 +4,7 @@ use crate::{
     worker::{ExecutingLAId, LocalActRequest, NewLocalAct},
 };
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet},
     time::SystemTime,
 };
 use temporal_sdk_core_protos::temporal::api::common::v1::WorkflowExecution;
@@ -16,10 +16,10 @@ pub(super) struct LocalActivityData {
     /// Queued cancels that need to be dispatched
     cancel_requests: Vec<ExecutingLAId>,
     /// Seq #s of local activities which we have sent to be executed but have not yet resolved
-    executing: HashSet<u32>,
+    executing: BTreeSet<u32>,
     /// Maps local activity sequence numbers to their resolutions as found when looking ahead at
-    /// next WFT
-    preresolutions: HashMap<u32, ResolveDat>,
+    /// next WFT. Ordered by sequence number, which is the order lang requested them in.
+    preresolutions: BTreeMap<u32, ResolveDat>,
     /// Set true if the workflow is terminating
     am_terminating: bool,
 }
@@ -0,0 +1,130 @@
+//! A bounded log of each run's most recent machine transitions. When a nondeterminism error fires
+//! the final mismatch alone often isn't enough to tell what went wrong, so the latest transitions
+//! are included in the error, and all of them in [super::WorkflowMachines::dump_machine_state].
+//! See [temporal_sdk_core_api::worker::WorkerConfig::machine_transition_log_size].
+//!
+//! Entries are recorded for every event, command, and other input handed to a machine, so they
+//! hold only static names and ids. Nothing is formatted until the log is dumped.
+
+use super::MachineKey;
+use std::{
+    collections::VecDeque,
+    fmt::{Display, Formatter},
+};
+use temporal_sdk_core_protos::temporal::api::enums::v1::{CommandType, EventType};
+
+/// How many of the latest transitions are included in nondeterminism errors
+pub(super) const ERROR_TAIL_LEN: usize = 20;
+
+/// What a machine was handling when it transitioned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum TransitionTrigger {
+    Event { id: i64, event_type: EventType },
+    Command(CommandType),
+    Cancel,
+    LocalActivityResolution { seq: u32 },
+    UpdateResponse,
+    NonReplayWft,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Transition {
+    /// The started event id of the workflow task being processed
+    pub(super) wft_started_id: i64,
+    pub(super) machine: MachineKey,
+    pub(super) machine_name: &'static str,
+    pub(super) from: &'static str,
+    pub(super) to: &'static str,
+    pub(super) trigger: TransitionTrigger,
+}
+
+impl Display for Transition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "[wft {}] {}({:?}) {} -> {} on ",
+            self.wft_started_id, self.machine_name, self.machine, self.from, self.to
+        )?;
+        match self.trigger {
+            TransitionTrigger::Event { id, event_type } => {
+                write!(f, "event {id} ({event_type:?})")
+            }
+            TransitionTrigger::Command(ct) => write!(f, "command {ct:?}"),
+            TransitionTrigger::Cancel => f.write_str("cancel"),
+            TransitionTrigger::LocalActivityResolution { seq } => {
+                write!(f, "local activity {seq} resolution")
+            }
+            TransitionTrigger::UpdateResponse => f.write_str("update response"),
+            TransitionTrigger::NonReplayWft => f.write_str("non-replay workflow task"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(super) struct TransitionLog {
+    capacity: usize,
+    entries: VecDeque<Transition>,
+}
+
+impl TransitionLog {
+    pub(super) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: VecDeque::new(),
+        }
+    }
+
+    pub(super) fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    pub(super) fn record(&mut self, transition: Transition) {
+        if !self.is_enabled() {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(transition);
+    }
+
+    /// The latest `n` transitions, oldest first
+    pub(super) fn tail(&self, n: usize) -> impl Iterator<Item = &Transition> {
+        self.entries
+            .iter()
+            .skip(self.entries.len().saturating_sub(n))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slotmap::KeyData;
+
+    fn transition(wft_started_id: i64) -> Transition {
+        Transition {
+            wft_started_id,
+            machine: KeyData::from_ffi(1).into(),
+            machine_name: "TimerMachine",
+            from: "Created",
+            to: "Fired",
+            trigger: TransitionTrigger::Cancel,
+        }
+    }
+
+    #[test]
+    fn keeps_only_the_latest_transitions() {
+        let mut log = TransitionLog::new(3);
+        for i in 0..5 {
+            log.record(transition(i));
+        }
+        let ids: Vec<_> = log.tail(usize::MAX).map(|t| t.wft_started_id).collect();
+        assert_eq!(ids, [2, 3, 4]);
+        let ids: Vec<_> = log.tail(2).map(|t| t.wft_started_id).collect();
+        assert_eq!(ids, [3, 4]);
+
+        let mut disabled = TransitionLog::new(0);
+        disabled.record(transition(1));
+        assert_eq!(disabled.tail(usize::MAX).count(), 0);
+    }
+}
@@ -11,6 +11,7 @@ use std::{
 };
 use temporal_sdk_core_protos::temporal::api::{
     enums::v1::{CommandType, EventType, WorkflowTaskFailedCause},
+    failure::v1::Failure,
     history::v1::history_event::Attributes::WorkflowTaskFailedEventAttributes,
 };
 
@@ -50,7 +51,10 @@ pub(super) enum WFTaskMachineCommand {
         time: SystemTime,
     },
     #[display("RunIdOnWorkflowResetUpdate({run_id})")]
-    RunIdOnWorkflowResetUpdate { run_id: String },
+    RunIdOnWorkflowResetUpdate {
+        run_id: String,
+        failure: Option<Failure>,
+    },
 }
 
 impl WFMachinesAdapter for WorkflowTaskMachine {
@@ -83,8 +87,11 @@ impl WFMachinesAdapter for WorkflowTaskMachine {
                     time,
                 }])
             }
-            WFTaskMachineCommand::RunIdOnWorkflowResetUpdate { run_id } => {
-                Ok(vec![MachineResponse::UpdateRunIdOnWorkflowReset { run_id }])
+            WFTaskMachineCommand::RunIdOnWorkflowResetUpdate { run_id, failure } => {
+                Ok(vec![MachineResponse::UpdateRunIdOnWorkflowReset {
+                    run_id,
+                    failure,
+                }])
             }
         }
     }
@@ -123,12 +130,12 @@ impl TryFrom<HistEventData> for WorkflowTaskMachineEvents {
             EventType::WorkflowTaskFailed => {
                 if let Some(attributes) = e.attributes {
                     Self::WorkflowTaskFailed(WFTFailedDat {
-                        new_run_id: match attributes {
+                        reset: match attributes {
                             WorkflowTaskFailedEventAttributes(a) => {
                                 let cause = WorkflowTaskFailedCause::try_from(a.cause);
                                 match cause {
                                     Ok(WorkflowTaskFailedCause::ResetWorkflow) => {
-                                        Some(a.new_run_id)
+                                        Some((a.new_run_id, a.failure))
                                     }
                                     _ => None,
                                 }
@@ -184,7 +191,9 @@ pub(super) struct WFTStartedDat {
 }
 
 pub(super) struct WFTFailedDat {
-    new_run_id: Option<String>,
+    /// Set if the task failed because the workflow was reset: the id of the run created by the
+    /// reset, and the failure server recorded for it
+    reset: Option<(String, Option<Failure>)>,
 }
 
 impl Scheduled {
@@ -234,8 +243,10 @@ impl Started {
         self,
         data: WFTFailedDat,
     ) -> WorkflowTaskMachineTransition<Failed> {
-        let commands = match data.new_run_id {
-            Some(run_id) => vec![WFTaskMachineCommand::RunIdOnWorkflowResetUpdate { run_id }],
+        let commands = match data.reset {
+            Some((run_id, failure)) => {
+                vec![WFTaskMachineCommand::RunIdOnWorkflowResetUpdate { run_id, failure }]
+            }
             None => vec![],
         };
         TransitionResult::commands(commands)
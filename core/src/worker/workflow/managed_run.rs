@@ -1,17 +1,23 @@
 use crate::{
-    abstractions::dbg_panic,
+    abstractions::{dbg_panic, UsedMeteredSemPermit},
     internal_flags::CoreInternalFlags,
     protosext::{protocol_messages::IncomingProtocolMessage, WorkflowActivationExt},
     telemetry::metrics,
     worker::{
         workflow::{
+            cache_snapshot::RunSnapshot,
+            correlation_id::new_correlation_id,
+            dropped_jobs::NoopCompletionTracker,
             history_update::HistoryPaginator,
             machines::{MachinesWFTResponseContent, WorkflowMachines},
+            ready_activations::WftDeadline,
+            replay_verification::ReplayVerifier,
+            run_stats::RunStats,
             ActivationAction, ActivationCompleteOutcome, ActivationCompleteResult,
             ActivationOrAuto, BufferedTasks, DrivenWorkflow, EvictionRequestResult,
             FailedActivationWFTReport, HeartbeatTimeoutMsg, HistoryUpdate,
             LocalActivityRequestSink, LocalResolution, NextPageReq, OutstandingActivation,
-            OutstandingTask, PermittedWFT, RequestEvictMsg, RunBasics,
+            OutstandingTask, PermittedWFT, RecoveryCause, RequestEvictMsg, RunBasics, RunRecovery,
             ServerCommandsWithWorkflowInfo, WFCommand, WFMachinesError, WFTReportStatus,
             WorkflowTaskInfo, WFT_HEARTBEAT_TIMEOUT_FRACTION,
         },
@@ -28,19 +34,24 @@ use std::{
     sync::{mpsc::Sender, Arc},
     time::{Duration, Instant},
 };
-use temporal_sdk_core_api::{errors::WorkflowErrorType, worker::WorkerConfig};
+use temporal_sdk_core_api::{
+    errors::{CompleteWfError, WorkflowErrorType},
+    worker::{WorkerConfig, WorkflowSlotKind},
+};
 use temporal_sdk_core_protos::{
     coresdk::{
         workflow_activation::{
             create_evict_activation, query_to_job, remove_from_cache::EvictionReason,
             workflow_activation_job, WorkflowActivation,
         },
-        workflow_commands::{FailWorkflowExecution, QueryResult},
+        workflow_commands::{query_result, FailWorkflowExecution, QueryResult},
         workflow_completion,
     },
     temporal::api::{
-        command::v1::command::Attributes as CmdAttribs, enums::v1::WorkflowTaskFailedCause,
+        command::v1::{command::Attributes as CmdAttribs, Command as ProtoCommand},
+        enums::v1::WorkflowTaskFailedCause,
         failure::v1::Failure,
+        history::v1::{history_event, WorkflowTaskFailedEventAttributes},
     },
     TaskToken,
 };
@@ -50,6 +61,11 @@ use tracing::Span;
 type Result<T, E = WFMachinesError> = std::result::Result<T, E>;
 pub(super) type RunUpdateAct = Option<ActivationOrAuto>;
 
+/// How many reactivations lang may be granted while holding one workflow task. Once it has had
+/// this many, further requests are ignored and the task is completed, so that a lang which keeps
+/// asking can't spin on the run, or hold the task until it times out.
+const MAX_REACTIVATIONS_PER_WFT: usize = 10;
+
 /// Manages access to a specific workflow run. Everything inside is entirely synchronous and should
 /// remain that way.
 #[derive(derive_more::Debug)]
@@ -79,6 +95,8 @@ pub(super) struct ManagedRun {
     wft: Option<OutstandingTask>,
     /// An outstanding activation to lang
     activation: Option<OutstandingActivation>,
+    /// Correlation id of the most recently issued activation
+    activation_correlation_id: String,
     /// Contains buffered poll responses from the server that apply to this run. This can happen
     /// when:
     ///   * Lang takes too long to complete a task and the task times out
@@ -89,6 +107,12 @@ pub(super) struct ManagedRun {
     task_buffer: BufferedTasks,
     /// Is set if an eviction has been requested for this run
     trying_to_evict: Option<RequestEvictMsg>,
+    /// Set when lang has been granted a reactivation it asked for in a completion, until the
+    /// activation is issued
+    reactivation_pending: bool,
+    /// Set while lang answers a legacy query which arrived while the run's WFT was outstanding.
+    /// See [WorkerConfig::eager_query_answers].
+    eager_query: Option<EagerQuery>,
 
     /// We track if we have recorded useful debugging values onto a certain span yet, to overcome
     /// duplicating field values. Remove this once https://github.com/tokio-rs/tracing/issues/2334
@@ -99,16 +123,20 @@ pub(super) struct ManagedRun {
     paginator: Option<HistoryPaginator>,
     completion_waiting_on_page_fetch: Option<RunActivationCompletion>,
     config: Arc<WorkerConfig>,
+    /// Counts how much processing this run needs, see [super::RunStatsRegistry]
+    stats: Arc<RunStats>,
+    noop_completions: NoopCompletionTracker,
 }
 impl ManagedRun {
     pub(super) fn new(
         basics: RunBasics,
         wft: PermittedWFT,
         local_activity_request_sink: Rc<dyn LocalActivityRequestSink>,
+        stats: Arc<RunStats>,
     ) -> (Self, RunUpdateAct) {
         let metrics = basics.metrics.clone();
         let config = basics.worker_config.clone();
-        let wfm = WorkflowManager::new(basics);
+        let wfm = WorkflowManager::new(basics, stats.clone());
         let mut me = Self {
             wfm,
             local_activity_request_sink,
@@ -116,18 +144,29 @@ impl ManagedRun {
             am_broken: false,
             wft: None,
             activation: None,
+            activation_correlation_id: String::new(),
             task_buffer: Default::default(),
             trying_to_evict: None,
+            reactivation_pending: false,
+            eager_query: None,
             recorded_span_ids: Default::default(),
             metrics,
             paginator: None,
             completion_waiting_on_page_fetch: None,
+            noop_completions: NoopCompletionTracker::new(config.dropped_job_detection_threshold),
             config,
+            stats,
         };
         let rua = me.incoming_wft(wft);
         (me, rua)
     }
 
+    /// Returns the approximate number of bytes of memory this run retains which vary with its
+    /// history, used to bound the cache by size
+    pub(super) fn approx_retained_bytes(&self) -> usize {
+        self.wfm.machines.approx_retained_bytes() + self.task_buffer.approx_retained_bytes()
+    }
+
     /// Returns true if there are pending jobs that need to be sent to lang.
     pub(super) fn more_pending_work(&self) -> bool {
         // We don't want to consider there to be more local-only work to be done if there is
@@ -135,7 +174,7 @@ impl ManagedRun {
         // complete a local activity while waiting for server to send us the next WFT.
         // Activating lang would be harmful at this stage, as there might be work returned
         // in that next WFT which should be part of the next activation.
-        self.wft.is_some() && self.wfm.machines.has_pending_jobs()
+        self.wft.is_some() && (self.wfm.machines.has_pending_jobs() || self.reactivation_pending)
     }
 
     pub(super) fn have_seen_terminal_event(&self) -> bool {
@@ -146,6 +185,56 @@ impl ManagedRun {
         self.wfm.machines.workflow_is_finished()
     }
 
+    /// Describes this run for handover to another worker. Runs which are broken, or have nothing
+    /// left to do, aren't worth handing over.
+    pub(super) fn cache_snapshot(&self) -> Option<RunSnapshot> {
+        if self.am_broken || self.workflow_is_finished() {
+            return None;
+        }
+        let machines = &self.wfm.machines;
+        Some(RunSnapshot {
+            workflow_id: machines.workflow_id.clone(),
+            run_id: machines.run_id.clone(),
+            last_event_id: machines.last_processed_event,
+            events: machines
+                .retained_history()
+                .filter(|h| {
+                    h.last()
+                        .is_some_and(|e| e.event_id == machines.last_processed_event)
+                })
+                .map(<[_]>::to_vec)
+                .unwrap_or_default(),
+        })
+    }
+
+    /// When the outstanding workflow task, if there is one, will time out on server
+    pub(super) fn wft_deadline(&self) -> Option<WftDeadline> {
+        let wft = self.wft.as_ref()?;
+        let timeout = self
+            .wfm
+            .machines
+            .get_started_info()?
+            .workflow_task_timeout?;
+        Some(WftDeadline {
+            deadline: wft.start_time + timeout,
+            timeout,
+        })
+    }
+
+    pub(super) fn workflow_type(&self) -> &str {
+        &self.wfm.machines.workflow_type
+    }
+
+    /// Describes the run's machines and their recent transitions. Only meant for debugging.
+    pub(super) fn dump_machine_state(&self) -> String {
+        self.wfm.machines.dump_machine_state()
+    }
+
+    /// True while the run is still being fed history it has already processed before
+    pub(super) fn is_replaying(&self) -> bool {
+        self.wfm.machines.replaying
+    }
+
     /// Returns a ref to info about the currently tracked workflow task, if any.
     pub(super) fn wft(&self) -> Option<&OutstandingTask> {
         self.wft.as_ref()
@@ -156,6 +245,12 @@ impl ManagedRun {
         self.activation.as_ref()
     }
 
+    /// Returns the correlation id of the most recently issued activation, which is the outstanding
+    /// one if there is one
+    pub(super) fn activation_correlation_id(&self) -> &str {
+        &self.activation_correlation_id
+    }
+
     /// Returns this run's eviction reason if it is going to be evicted
     pub(super) fn trying_to_evict(&self) -> Option<&RequestEvictMsg> {
         self.trying_to_evict.as_ref()
@@ -163,10 +258,46 @@ impl ManagedRun {
 
     /// Called whenever a new workflow task is obtained for this run
     pub(super) fn incoming_wft(&mut self, pwft: PermittedWFT) -> RunUpdateAct {
+        if self.config.eager_query_answers && self.wft.is_some() && pwft.work.is_query_only() {
+            return self.eager_query_activation(pwft);
+        }
         let res = self._incoming_wft(pwft);
         self.update_to_acts(res.map(Into::into))
     }
 
+    /// Issues an activation answering the legacy query in `pwft` without waiting for the run's
+    /// outstanding WFT to finish. The query's own task token and permit are kept aside until it
+    /// has been answered, so the outstanding WFT is left as it was.
+    fn eager_query_activation(&mut self, pwft: PermittedWFT) -> RunUpdateAct {
+        let work = pwft.work;
+        let Some(query) = work.legacy_query else {
+            dbg_panic!("Query-only task had no legacy query");
+            return None;
+        };
+        debug!(run_id = %self.run_id(), task_token = %&work.task_token,
+               "Answering query while the run's workflow task is outstanding");
+        self.eager_query = Some(EagerQuery {
+            task_token: work.task_token,
+            _permit: pwft.permit,
+            heartbeat_due: false,
+        });
+        let mut activation = self.wfm.machines.get_wf_activation();
+        activation.jobs.push(
+            workflow_activation_job::Variant::QueryWorkflow(query_to_job(
+                LEGACY_QUERY_ID.to_string(),
+                query,
+            ))
+            .into(),
+        );
+        self.update_to_acts(Ok(Some(ActivationOrAuto::LangActivation(activation)).into()))
+    }
+
+    /// True while a legacy query answered ahead of the outstanding WFT is with lang, see
+    /// [Self::eager_query_activation]
+    pub(super) fn answering_eager_query(&self) -> bool {
+        self.eager_query.is_some()
+    }
+
     fn _incoming_wft(
         &mut self,
         pwft: PermittedWFT,
@@ -189,6 +320,7 @@ impl ManagedRun {
             attempt: work.attempt,
             task_token: work.task_token,
             wf_id: work.execution.workflow_id.clone(),
+            wf_type: work.workflow_type.clone(),
         };
 
         let legacy_query_from_poll = work
@@ -217,10 +349,43 @@ impl ManagedRun {
         self.wft = Some(OutstandingTask {
             info: wft_info,
             pending_queries,
+            dispatched_queries: vec![],
             start_time,
             permit: pwft.permit,
+            reactivations: 0,
         });
 
+        if let Some(failed) =
+            bad_binary_failure(&work.update, &self.config.worker_build_id, self.run_id())
+        {
+            error!(
+                run_id = %self.run_id(),
+                build_id = %self.config.worker_build_id,
+                cause = ?failed.cause(),
+                "Server rejected this worker's build id as a bad binary for this run. Evicting \
+                 it rather than processing it. Remove the build id from the namespace's bad \
+                 binaries, or deploy a different build."
+            );
+            // The task is left for server to time out and hand to another worker, since failing
+            // it would only count against the run. Nothing from it has been applied, so the
+            // eviction goes out as soon as this update is processed.
+            if !self.pending_work_is_legacy_query() {
+                if let Some(wft) = self.wft.as_mut() {
+                    wft.pending_queries.clear();
+                }
+            }
+            self.trying_to_evict = Some(RequestEvictMsg {
+                run_id: self.run_id().to_string(),
+                message: format!(
+                    "Server rejected build id {} as a bad binary",
+                    self.config.worker_build_id
+                ),
+                reason: EvictionReason::Fatal,
+                auto_reply_fail_tt: None,
+            });
+            return Ok(None);
+        }
+
         if was_legacy_query
             && work.update.wft_started_id == 0
             && work.update.previous_wft_started_id < self.wfm.machines.get_last_wft_started_id()
@@ -234,6 +399,8 @@ impl ManagedRun {
         // The update field is only populated in the event we hit the cache
         let activation = if work.update.is_real() {
             self.metrics.sticky_cache_hit();
+            self.stats
+                .record_history_fetch(work.update.approx_retained_bytes());
             self.wfm.new_work_from_server(work.update, work.messages)?
         } else {
             let r = self.wfm.get_next_activation()?;
@@ -295,6 +462,7 @@ impl ManagedRun {
         // Only record latency metrics if we genuinely reported to server
         if let WFTReportStatus::Reported {
             reset_last_started_to,
+            completion_accepted,
         } = report_status
         {
             if let Some(ot) = &retme {
@@ -303,6 +471,11 @@ impl ManagedRun {
             if let Some(id) = reset_last_started_to {
                 self.wfm.machines.reset_last_started_id(id);
             }
+            // Server must have accepted the completion, since a failed or rejected one leaves the
+            // workflow running
+            if completion_accepted && self.workflow_is_finished() {
+                self.wfm.machines.mark_terminal_acknowledged();
+            }
             // Tell the LA manager that we're done with the WFT
             self.local_activity_request_sink.sink_reqs(vec![
                 LocalActRequest::IndicateWorkflowTaskCompleted(self.wfm.machines.run_id.clone()),
@@ -335,10 +508,14 @@ impl ManagedRun {
             return Ok(None);
         }
 
-        if self.wfm.machines.has_pending_jobs() && !self.am_broken {
-            Ok(Some(ActivationOrAuto::LangActivation(
-                self.wfm.get_next_activation()?,
-            )))
+        if (self.wfm.machines.has_pending_jobs() || self.reactivation_pending) && !self.am_broken {
+            let mut activation = self.wfm.get_next_activation()?;
+            if activation.jobs.is_empty() {
+                // Only a reactivation lang asked for can have no jobs, and those are never granted
+                // during replay. Assembling the activation would otherwise consider it query-only.
+                activation.is_replaying = false;
+            }
+            Ok(Some(ActivationOrAuto::LangActivation(activation)))
         } else {
             if !self.am_broken {
                 let has_pending_queries = self
@@ -353,8 +530,8 @@ impl ManagedRun {
                 }
             }
             if let Some(wte) = self.trying_to_evict.clone() {
-                let act =
-                    create_evict_activation(self.run_id().to_string(), wte.message, wte.reason);
+                self.check_no_jobs_lost_to_eviction();
+                let act = create_evict_activation(self.run_id().into(), wte.message, wte.reason);
                 Ok(Some(ActivationOrAuto::LangActivation(act)))
             } else {
                 Ok(None)
@@ -372,6 +549,8 @@ impl ManagedRun {
         &mut self,
         mut commands: Vec<WFCommand>,
         used_flags: Vec<u32>,
+        random_checksum: u64,
+        request_reactivation: bool,
         resp_chan: Option<oneshot::Sender<ActivationCompleteResult>>,
     ) -> Result<RunUpdateAct, Box<NextPageReq>> {
         let activation_was_only_eviction = self.activation_is_eviction();
@@ -399,6 +578,7 @@ impl ManagedRun {
                     tt,
                     WorkflowTaskFailedCause::Unspecified,
                     Failure::application_failure(reason, true).into(),
+                    None,
                 ))
             } else {
                 ActivationCompleteOutcome::DoNothing
@@ -407,6 +587,25 @@ impl ManagedRun {
             return Ok(None);
         };
 
+        if let Err(reason) = self
+            .wfm
+            .machines
+            .check_command_references(&commands)
+            .and_then(|_| self.wfm.machines.check_random_checksum(random_checksum))
+        {
+            warn!(run_id=%self.run_id(), reason=%reason, "Rejecting completion");
+            let run_id = self.run_id().to_string();
+            self.reply_to_complete(
+                ActivationCompleteOutcome::Rejected(CompleteWfError::MalformedWorkflowCompletion {
+                    reason,
+                    run_id,
+                    correlation_id: self.activation_correlation_id.clone(),
+                }),
+                resp_chan,
+            );
+            return Ok(None);
+        }
+
         // If the only command from the activation is a legacy query response, that means we need
         // to respond differently than a typical activation.
         if matches!(&commands.as_slice(),
@@ -416,6 +615,10 @@ impl ManagedRun {
                 WFCommand::QueryResponse(qr) => qr,
                 _ => unreachable!("We just verified this is the only command"),
             };
+            let task_token = self
+                .eager_query
+                .as_ref()
+                .map_or(task_token, |q| q.task_token.clone());
             self.reply_to_complete(
                 ActivationCompleteOutcome::ReportWFTSuccess(ServerCommandsWithWorkflowInfo {
                     task_token,
@@ -428,10 +631,35 @@ impl ManagedRun {
             Ok(None)
         } else {
             let (commands, query_responses) = self.preprocess_command_sequence(commands);
+            let dispatched_queries = self
+                .wft
+                .as_mut()
+                .map(|wft| mem::take(&mut wft.dispatched_queries))
+                .unwrap_or_default();
+            let query_responses = reconcile_query_responses(dispatched_queries, query_responses);
 
             if activation_was_only_eviction && !commands.is_empty() {
                 dbg_panic!("Reply to an eviction included commands");
             }
+            if let Some(ignored) = self
+                .noop_completions
+                .completed(!commands.is_empty() || request_reactivation)
+            {
+                let message =
+                    format!(
+                    "Lang completed {} activations in a row without any commands, despite them \
+                     containing {ignored:?} jobs. Lang may be dropping them.",
+                    self.config.dropped_job_detection_threshold.unwrap_or_default()
+                );
+                error!(run_id=%self.run_id(), "{message}");
+                self.metrics.suspected_dropped_jobs();
+                if self.config.fail_wft_on_dropped_jobs {
+                    return Ok(self.update_to_acts(Err(RunUpdateErr {
+                        source: WFMachinesError::Fatal(message),
+                        complete_resp_chan: resp_chan,
+                    })));
+                }
+            }
 
             let rac = RunActivationCompletion {
                 task_token,
@@ -441,6 +669,7 @@ impl ManagedRun {
                 has_pending_query,
                 query_responses,
                 used_flags,
+                request_reactivation,
                 resp_chan,
             };
 
@@ -521,6 +750,8 @@ impl ManagedRun {
         paginator: HistoryPaginator,
     ) -> Result<Option<FulfillableActivationComplete>, RunUpdateErr> {
         self.paginator = Some(paginator);
+        self.stats
+            .record_history_fetch(update.approx_retained_bytes());
         if let Some(d) = self.completion_waiting_on_page_fetch.take() {
             self._process_completion(d, Some(update))
         } else {
@@ -547,7 +778,9 @@ impl ManagedRun {
         is_auto_fail: bool,
         resp_chan: Option<oneshot::Sender<ActivationCompleteResult>>,
     ) -> RunUpdateAct {
-        let tt = if let Some(tt) = self.wft.as_ref().map(|t| t.info.task_token.clone()) {
+        let tt = if let Some(q) = self.eager_query.as_ref() {
+            q.task_token.clone()
+        } else if let Some(tt) = self.wft.as_ref().map(|t| t.info.task_token.clone()) {
             tt
         } else {
             dbg_panic!(
@@ -557,9 +790,13 @@ impl ManagedRun {
             self.reply_to_complete(ActivationCompleteOutcome::DoNothing, resp_chan);
             return None;
         };
-
+        // Any queries lang was handed alongside this activation will not be answered. The server
+        // will deliver them again along with the retried task, so there's nothing to answer here.
+        if let Some(wft) = self.wft.as_mut() {
+            wft.dispatched_queries.clear();
+        }
         let message = format!("Workflow activation completion failed: {:?}", &failure);
-        // We don't want to fail queries that could otherwise be retried
+        // We don't want to evict runs because of a query failure that could otherwise be retried
         let is_no_report_query_fail = self.pending_work_is_legacy_query()
             && is_auto_fail
             && matches!(
@@ -586,14 +823,12 @@ impl ManagedRun {
             (should_report, rur)
         };
 
+        // Server never retries a legacy query, so whatever the reason for the failure, the querier
+        // must be answered or they'll wait until their own timeout.
         let outcome = if self.pending_work_is_legacy_query() {
-            if is_no_report_query_fail {
-                ActivationCompleteOutcome::WFTFailedDontReport
-            } else {
-                ActivationCompleteOutcome::ReportWFTFail(
-                    FailedActivationWFTReport::ReportLegacyQueryFailure(tt, failure),
-                )
-            }
+            ActivationCompleteOutcome::ReportWFTFail(
+                FailedActivationWFTReport::ReportLegacyQueryFailure(tt, failure),
+            )
         } else if should_report {
             // Check if we should fail the workflow instead of the WFT because of user's preferences
             if matches!(cause, WorkflowTaskFailedCause::NonDeterministicError)
@@ -609,6 +844,8 @@ impl ManagedRun {
                             failure: failure.failure,
                         })],
                         vec![],
+                        0,
+                        false,
                         resp_chan,
                     )
                     .unwrap_or_else(|e| {
@@ -616,8 +853,16 @@ impl ManagedRun {
                         None
                     });
             } else {
+                // Core itself failed to make sense of the run, so its cached state is suspect.
+                // Nondeterminism is the workflow code's doing, and would recur on any worker.
+                let recovery = (self.am_broken
+                    && cause != WorkflowTaskFailedCause::NonDeterministicError)
+                    .then(|| RunRecovery {
+                        workflow_id: self.wfm.machines.workflow_id.as_str().into(),
+                        cause: RecoveryCause::FatalMachineError,
+                    });
                 ActivationCompleteOutcome::ReportWFTFail(FailedActivationWFTReport::Report(
-                    tt, cause, failure,
+                    tt, cause, failure, recovery,
                 ))
             }
         } else {
@@ -645,6 +890,9 @@ impl ManagedRun {
     ) -> (bool, BufferedTasks) {
         let evict = if self.activation().map(pred).unwrap_or_default() {
             let act = self.activation.take();
+            if matches!(act, Some(OutstandingActivation::LegacyQuery)) {
+                self.finish_eager_query();
+            }
             act.map(|a| matches!(a, OutstandingActivation::Eviction))
                 .unwrap_or_default()
         } else {
@@ -658,6 +906,27 @@ impl ManagedRun {
         (evict, buffered)
     }
 
+    /// Forgets the query answered ahead of the outstanding WFT, if there was one. If the WFT's
+    /// heartbeat came due meanwhile, it is started again with the deadline it already missed, so
+    /// that it fires right away.
+    fn finish_eager_query(&mut self) {
+        let Some(query) = self.eager_query.take() else {
+            return;
+        };
+        if !query.heartbeat_due {
+            return;
+        }
+        let run_id = self.run_id().to_string();
+        if let (Some(wft), Some(wait_dat)) = (self.wft.as_ref(), self.waiting_on_la.as_mut()) {
+            wait_dat.hb_timeout_handle = sink_heartbeat_timeout_start(
+                run_id,
+                self.local_activity_request_sink.as_ref(),
+                wft.start_time,
+                wait_dat.wft_timeout,
+            );
+        }
+    }
+
     /// Called when local activities resolve
     pub(super) fn local_resolution(&mut self, res: LocalResolution) -> RunUpdateAct {
         let res = self._local_resolution(res);
@@ -682,6 +951,7 @@ impl ManagedRun {
         };
 
         self.wfm.machines.add_lang_used_flags(completion.used_flags);
+        let had_commands = !completion.commands.is_empty();
 
         // If this is just bookkeeping after a reply to an eviction activation, we can bypass
         // everything, since there is no reason to continue trying to update machines.
@@ -690,7 +960,7 @@ impl ManagedRun {
                 completion.resp_chan,
                 data,
                 false,
-            )));
+            )?));
         }
 
         let outcome = (|| {
@@ -726,11 +996,25 @@ impl ManagedRun {
         })();
 
         match outcome {
+            Ok(None)
+                if completion.request_reactivation
+                    && self.grant_reactivation(had_commands, &data) =>
+            {
+                // Hold on to the task rather than completing it, the reactivation is issued once
+                // this activation is finished
+                Ok(Some(FulfillableActivationComplete {
+                    result: ActivationCompleteResult {
+                        outcome: ActivationCompleteOutcome::DoNothing,
+                        replaying: false,
+                    },
+                    resp_chan: completion.resp_chan,
+                }))
+            }
             Ok(None) => Ok(Some(self.prepare_complete_resp(
                 completion.resp_chan,
                 data,
                 false,
-            ))),
+            )?)),
             Ok(Some((start_t, wft_timeout))) => {
                 if let Some(wola) = self.waiting_on_la.as_mut() {
                     wola.hb_timeout_handle.abort();
@@ -769,7 +1053,7 @@ impl ManagedRun {
                         resp_chan,
                         completion_dat,
                         false,
-                    )));
+                    )?));
                 }
             }
         }
@@ -777,33 +1061,39 @@ impl ManagedRun {
     }
 
     pub(super) fn heartbeat_timeout(&mut self) -> RunUpdateAct {
-        let maybe_act = if self._heartbeat_timeout() {
-            Some(ActivationOrAuto::Autocomplete {
+        // The WFT can't be completed while lang is answering a query ahead of it, that has to wait
+        // until lang is done
+        if let Some(query) = self.eager_query.as_mut() {
+            query.heartbeat_due = true;
+            return None;
+        }
+        let maybe_act = match self._heartbeat_timeout() {
+            Ok(true) => Some(ActivationOrAuto::Autocomplete {
                 run_id: self.wfm.machines.run_id.clone(),
-            })
-        } else {
-            None
+            }),
+            Ok(false) => None,
+            Err(e) => return self.update_to_acts(Err(e)),
         };
         self.update_to_acts(Ok(maybe_act.into()))
     }
     /// Returns `true` if autocompletion should be issued, which will actually cause us to end up
     /// in [completion] again, at which point we'll start a new heartbeat timeout, which will
     /// immediately trigger and thus finish the completion, forcing a new task as it should.
-    fn _heartbeat_timeout(&mut self) -> bool {
+    fn _heartbeat_timeout(&mut self) -> Result<bool, RunUpdateErr> {
         if let Some(ref mut wait_dat) = self.waiting_on_la {
             // Cancel the heartbeat timeout
             wait_dat.hb_timeout_handle.abort();
             if let Some((completion_dat, resp_chan)) = wait_dat.completion_dat.take() {
-                let compl = self.prepare_complete_resp(resp_chan, completion_dat, true);
+                let compl = self.prepare_complete_resp(resp_chan, completion_dat, true)?;
                 // Immediately fulfill the completion since the run update will already have
                 // been replied to
                 compl.fulfill();
             } else {
                 // Auto-reply WFT complete
-                return true;
+                return Ok(true);
             }
         }
-        false
+        Ok(false)
     }
 
     /// Returns true if the managed run has any form of pending work
@@ -840,16 +1130,90 @@ impl ManagedRun {
     ) -> Option<PermittedWFT> {
         let about_to_issue_evict = self.trying_to_evict.is_some();
         let has_activation = self.activation().is_some();
-        if has_activation || about_to_issue_evict || self.more_pending_work() {
+        let is_query_only = work.work.is_query_only();
+        // Queries are deferred while there is an outstanding WFT, since its completion, and the
+        // commands it contains, hasn't been acknowledged by server yet. Unless they're to be
+        // answered eagerly, in which case the WFT is left be and they're answered right away.
+        let defer_query = is_query_only && self.wft.is_some() && !self.config.eager_query_answers;
+        // When answering eagerly, a new WFT must not overtake queries which were deferred behind
+        // the completion it came back from.
+        let queries_ahead = !is_query_only
+            && self.config.eager_query_answers
+            && self.task_buffer.has_query_only_tasks();
+        if has_activation
+            || about_to_issue_evict
+            || self.more_pending_work()
+            || defer_query
+            || queries_ahead
+        {
             debug!(run_id = %self.run_id(),
                    "Got new WFT for a run with outstanding work, buffering it act: {:?} wft: {:?} about to evict: {:?}", &self.activation(), &self.wft, about_to_issue_evict);
             self.task_buffer.buffer(work);
+            // If the completion is only being held while local activities run, there's no need
+            // to make the query wait for them too. Verified completions aren't sent early, since
+            // a failed verification fails the task, which can't be reported from here.
+            let completion_held_for_las = self
+                .waiting_on_la
+                .as_ref()
+                .is_some_and(|w| w.completion_dat.is_some());
+            if is_query_only
+                && self.config.eager_query_answers
+                && !self.config.verify_completions_by_replay
+                && completion_held_for_las
+            {
+                debug!(run_id = %self.run_id(),
+                       "Sending completion held for local activities early to answer query");
+                if let Err(e) = self._heartbeat_timeout() {
+                    dbg_panic!("Unverified completion failed to send early: {:?}", e.source);
+                }
+            }
             None
         } else {
             Some(work)
         }
     }
 
+    /// How long queries for this run may stay deferred. See [WorkerConfig::query_deferral_timeout].
+    pub(super) fn query_deferral_timeout(&self) -> Duration {
+        query_deferral_timeout(
+            self.config.query_deferral_timeout,
+            self.wfm
+                .machines
+                .get_started_info()
+                .and_then(|i| i.workflow_task_timeout),
+        )
+    }
+
+    /// Removes any buffered query-only tasks which have been deferred for at least
+    /// [Self::query_deferral_timeout], producing an action to fail them.
+    pub(super) fn fail_expired_deferred_queries(&mut self) -> RunUpdateAct {
+        let expired = self
+            .task_buffer
+            .take_expired_queries(self.query_deferral_timeout());
+        if expired.is_empty() {
+            return None;
+        }
+        Some(ActivationOrAuto::FailDeferredQueries {
+            run_id: self.run_id().to_string(),
+            task_tokens: expired.into_iter().map(|t| t.work.task_token).collect(),
+        })
+    }
+
+    /// Removes every buffered task, producing an action to fail them with `reason`. Queriers are
+    /// answered and server is told about the tasks, rather than both being left to time out.
+    pub(super) fn fail_buffered_tasks(&mut self, reason: &'static str) -> RunUpdateAct {
+        let tasks = self.task_buffer.take_all();
+        if tasks.is_empty() {
+            return None;
+        }
+        debug!(run_id=%self.run_id(), num_tasks=tasks.len(), "Failing buffered tasks");
+        Some(ActivationOrAuto::FailUnstartedTasks {
+            run_id: self.run_id().to_string(),
+            tasks: tasks.into_iter().map(|t| t.work).collect(),
+            reason,
+        })
+    }
+
     /// Returns true if there is a buffered workflow task for this run.
     pub(super) fn has_buffered_wft(&self) -> bool {
         self.task_buffer.has_tasks()
@@ -890,6 +1254,8 @@ impl ManagedRun {
                 }
             }
 
+            // A reactivation lang asked for is moot now
+            self.reactivation_pending = false;
             self.trying_to_evict = Some(info);
             EvictionRequestResult::EvictionRequested(attempts, self.check_more_activations())
         } else {
@@ -907,8 +1273,9 @@ impl ManagedRun {
             self.recorded_span_ids.insert(spid);
 
             span.record("run_id", self.run_id());
-            if let Some(wid) = self.wft().map(|wft| &wft.info.wf_id) {
-                span.record("workflow_id", wid.as_str());
+            span.record("correlation_id", self.activation_correlation_id.as_str());
+            if let Some(wft) = self.wft() {
+                wft.info.record_span_fields(span);
             }
         }
     }
@@ -931,9 +1298,10 @@ impl ManagedRun {
                         }
                     }
                 }
+                let reactivating = maybe_act.is_some() && mem::take(&mut self.reactivation_pending);
                 let r = match maybe_act {
                     Some(ActivationOrAuto::LangActivation(activation)) => {
-                        if activation.jobs.is_empty() {
+                        if activation.jobs.is_empty() && !reactivating {
                             dbg_panic!("Should not send lang activation with no jobs");
                         }
                         Some(ActivationOrAuto::LangActivation(activation))
@@ -950,13 +1318,26 @@ impl ManagedRun {
                     a @ Some(
                         ActivationOrAuto::Autocomplete { .. } | ActivationOrAuto::AutoFail { .. },
                     ) => a,
+                    Some(ActivationOrAuto::FailDeferredQueries { .. }) => {
+                        dbg_panic!("Deferred queries should never be failed as a run update");
+                        None
+                    }
+                    Some(ActivationOrAuto::FailStuckActivation(_)) => {
+                        dbg_panic!("Stuck activations should never be failed as a run update");
+                        None
+                    }
+                    Some(ActivationOrAuto::FailUnstartedTasks { .. }) => {
+                        dbg_panic!("Unstarted tasks should never be failed as a run update");
+                        None
+                    }
                     None => {
                         if let Some(reason) = self.trying_to_evict.as_ref() {
                             // If we had nothing to do, but we're trying to evict, just do that now
                             // as long as there's no other outstanding work.
                             if self.activation.is_none() && !self.more_pending_work() {
+                                self.check_no_jobs_lost_to_eviction();
                                 let mut evict_act = create_evict_activation(
-                                    self.run_id().to_string(),
+                                    self.run_id().into(),
                                     reason.message.clone(),
                                     reason.reason,
                                 );
@@ -975,7 +1356,7 @@ impl ManagedRun {
                     f.fulfill();
                 }
 
-                match r {
+                let r = match r {
                     // After each run update, check if it's ready to handle any buffered task
                     None | Some(ActivationOrAuto::Autocomplete { .. })
                         if !self.has_any_pending_work(false, true) =>
@@ -986,12 +1367,40 @@ impl ManagedRun {
                             None
                         }
                     }
-                    Some(r) => {
+                    // Queries which had to wait for an activation can be answered eagerly once
+                    // it's done, even though the WFT is still outstanding
+                    None if self.config.eager_query_answers
+                        && self.wft.is_some()
+                        && self.activation.is_none()
+                        && self.trying_to_evict.is_none()
+                        && !self.more_pending_work()
+                        && self.task_buffer.has_query_only_tasks() =>
+                    {
+                        self.task_buffer
+                            .get_next_wft()
+                            .and_then(|q| self.incoming_wft(q))
+                    }
+                    Some(mut r) => {
+                        if let ActivationOrAuto::LangActivation(act)
+                        | ActivationOrAuto::ReadyForQueries(act) = &mut r
+                        {
+                            act.correlation_id = new_correlation_id();
+                            self.activation_correlation_id = act.correlation_id.clone();
+                        }
                         self.insert_outstanding_activation(&r);
+                        if let ActivationOrAuto::LangActivation(act) = &r {
+                            self.stats.record_activation(act.jobs.len());
+                            self.noop_completions.activation_issued(act);
+                            self.wfm.activation_issued(act);
+                        }
                         Some(r)
                     }
                     None => None,
-                }
+                };
+                self.stats.set_buffered_jobs(
+                    self.wfm.machines.num_pending_jobs() + self.task_buffer.num_query_only_tasks(),
+                );
+                r
             }
             Err(fail) => {
                 self.am_broken = true;
@@ -1035,6 +1444,18 @@ impl ManagedRun {
             ActivationOrAuto::Autocomplete { .. } | ActivationOrAuto::AutoFail { .. } => {
                 OutstandingActivation::Autocomplete
             }
+            ActivationOrAuto::FailDeferredQueries { .. } => {
+                dbg_panic!("Failing deferred queries involves no activation");
+                return;
+            }
+            ActivationOrAuto::FailStuckActivation(_) => {
+                dbg_panic!("Failing a stuck activation involves no new activation");
+                return;
+            }
+            ActivationOrAuto::FailUnstartedTasks { .. } => {
+                dbg_panic!("Failing unstarted tasks involves no activation");
+                return;
+            }
         };
         if let Some(old_act) = self.activation {
             // This is a panic because we have screwed up core logic if this is violated. It must be
@@ -1047,12 +1468,37 @@ impl ManagedRun {
         self.activation = Some(act_type);
     }
 
+    /// Decide whether lang, having completed an activation without any commands, gets the
+    /// reactivation it asked for. If so, marks it pending.
+    fn grant_reactivation(&mut self, had_commands: bool, data: &CompletionDataForWFT) -> bool {
+        let Some(wft) = self.wft.as_mut() else {
+            return false;
+        };
+        if had_commands
+            || data.has_pending_query
+            || !data.query_responses.is_empty()
+            || self.wfm.machines.replaying
+            || self.wfm.machines.have_seen_terminal_event
+            || self.trying_to_evict.is_some()
+        {
+            return false;
+        }
+        if wft.reactivations >= MAX_REACTIVATIONS_PER_WFT {
+            warn!(run_id=%self.wfm.machines.run_id, "Lang has requested too many reactivations \
+                  during one workflow task, completing the task instead");
+            return false;
+        }
+        wft.reactivations += 1;
+        self.reactivation_pending = true;
+        true
+    }
+
     fn prepare_complete_resp(
         &mut self,
         resp_chan: Option<oneshot::Sender<ActivationCompleteResult>>,
         data: CompletionDataForWFT,
         due_to_heartbeat_timeout: bool,
-    ) -> FulfillableActivationComplete {
+    ) -> Result<FulfillableActivationComplete, RunUpdateErr> {
         let mut machines_wft_response = self.wfm.prepare_for_wft_response();
         if data.activation_was_eviction
             && (machines_wft_response.commands().peek().is_some()
@@ -1088,6 +1534,7 @@ impl ManagedRun {
             force_new_wft = true;
         }
 
+        let replaying = machines_wft_response.replaying;
         let outcome = if should_respond || has_query_responses {
             // If we broke there could be commands or messages in the pipe that we didn't
             // get a chance to handle properly during replay. Don't send them.
@@ -1099,6 +1546,15 @@ impl ManagedRun {
                     machines_wft_response.messages(),
                 )
             };
+            let sdk_metadata = machines_wft_response.metadata_for_complete();
+            if should_respond {
+                if let Err(e) = self.wfm.verify_completion(&commands) {
+                    return Err(RunUpdateErr {
+                        source: e,
+                        complete_resp_chan: resp_chan,
+                    });
+                }
+            }
 
             // Record metrics for any outgoing terminal commands
             for cmd in commands.iter() {
@@ -1126,19 +1582,16 @@ impl ManagedRun {
                     commands,
                     messages,
                     query_responses,
-                    sdk_metadata: machines_wft_response.metadata_for_complete(),
+                    sdk_metadata,
                 },
             })
         } else {
             ActivationCompleteOutcome::DoNothing
         };
-        FulfillableActivationComplete {
-            result: ActivationCompleteResult {
-                outcome,
-                replaying: machines_wft_response.replaying,
-            },
+        Ok(FulfillableActivationComplete {
+            result: ActivationCompleteResult { outcome, replaying },
             resp_chan,
-        }
+        })
     }
 
     /// Pump some local activity requests into the sink, applying any immediate results to the
@@ -1182,6 +1635,21 @@ impl ManagedRun {
         }
     }
 
+    /// Jobs still pending when the eviction activation is issued are dropped along with the run.
+    /// Jobs derived from history would be re-derived when the run is rebuilt, but they are always
+    /// delivered ahead of an eviction, so any left over here indicate a bug. Local activity
+    /// resolutions may be dropped, since outstanding local activities are cancelled on eviction
+    /// and run again after the rebuild. Broken runs are exempt, as their task is failed and will
+    /// be retried from scratch.
+    fn check_no_jobs_lost_to_eviction(&self) {
+        if !self.am_broken && self.wfm.machines.has_pending_history_jobs() {
+            dbg_panic!(
+                "Run {} is being evicted with undelivered jobs which came from history",
+                self.run_id()
+            );
+        }
+    }
+
     /// Returns true if the handle is currently processing a WFT which contains a legacy query.
     fn pending_work_is_legacy_query(&self) -> bool {
         // Either we know because there is a pending legacy query, or it's already been drained and
@@ -1232,9 +1700,11 @@ fn preprocess_command_sequence(commands: Vec<WFCommand>) -> (Vec<WFCommand>, Vec
             }
         })
         .collect();
-    if let Some(first_terminal) = terminals.into_iter().next() {
+    let mut terminals = terminals.into_iter();
+    if let Some(first_terminal) = terminals.next() {
         commands.push(first_terminal);
     }
+    warn_of_dropped_commands(terminals.count());
     (commands, query_results)
 }
 
@@ -1243,6 +1713,7 @@ fn preprocess_command_sequence_old_behavior(
 ) -> (Vec<WFCommand>, Vec<QueryResult>) {
     let mut query_results = vec![];
     let mut seen_terminal = false;
+    let mut dropped = 0;
 
     let commands: Vec<_> = commands
         .into_iter()
@@ -1251,6 +1722,7 @@ fn preprocess_command_sequence_old_behavior(
                 query_results.push(qr);
                 None
             } else if seen_terminal {
+                dropped += 1;
                 None
             } else {
                 if c.is_terminal() {
@@ -1260,9 +1732,66 @@ fn preprocess_command_sequence_old_behavior(
             }
         })
         .collect();
+    warn_of_dropped_commands(dropped);
     (commands, query_results)
 }
 
+fn warn_of_dropped_commands(dropped: usize) {
+    if dropped > 0 {
+        warn!(
+            dropped,
+            "Workflow completion contained commands which cannot follow its terminal command, \
+             and were dropped rather than sent"
+        );
+    }
+}
+
+/// Server's own default workflow task timeout, used to bound query deferral for runs whose timeout
+/// isn't known
+const DEFAULT_QUERY_DEFERRAL_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn query_deferral_timeout(configured: Option<Duration>, wft_timeout: Option<Duration>) -> Duration {
+    configured
+        .or(wft_timeout)
+        .unwrap_or(DEFAULT_QUERY_DEFERRAL_TIMEOUT)
+}
+
+/// Makes sure every query which was dispatched to lang has exactly one result. Dispatched queries
+/// that lang didn't answer are automatically failed, so the querier doesn't hang until it times
+/// out, and results for queries that were never dispatched are dropped.
+fn reconcile_query_responses(
+    dispatched: Vec<String>,
+    responses: Vec<QueryResult>,
+) -> Vec<QueryResult> {
+    let mut unanswered: HashSet<_> = dispatched.iter().collect();
+    let mut reconciled: Vec<_> = responses
+        .into_iter()
+        .filter(|qr| {
+            if unanswered.remove(&qr.query_id) {
+                true
+            } else {
+                warn!(query_id=%qr.query_id,
+                      "Lang provided a result for a query which was not dispatched to it, \
+                       or a duplicate result. Dropping it.");
+                false
+            }
+        })
+        .collect();
+    // Iterate the dispatched list rather than the set to keep the auto-failures in a stable order
+    for query_id in dispatched.iter().filter(|q| unanswered.contains(q)) {
+        warn!(query_id=%query_id, "Lang did not provide a result for dispatched query");
+        reconciled.push(QueryResult {
+            query_id: query_id.clone(),
+            variant: Some(query_result::Variant::Failed(Failure::application_failure(
+                "Workflow activation completion did not include a result for this query"
+                    .to_string(),
+                false,
+            ))),
+        });
+    }
+    reconciled
+}
+
 /// Drains pending queries from the workflow task and appends them to the activation's jobs
 fn put_queries_in_act(act: &mut WorkflowActivation, wft: &mut OutstandingTask) {
     // Nothing to do if there are no pending queries
@@ -1278,12 +1807,50 @@ fn put_queries_in_act(act: &mut WorkflowActivation, wft: &mut OutstandingTask) {
     }
 
     debug!(queries=?wft.pending_queries, "Dispatching queries");
+    wft.dispatched_queries.extend(
+        wft.pending_queries
+            .iter()
+            .filter(|q| q.query_id != LEGACY_QUERY_ID)
+            .map(|q| q.query_id.clone()),
+    );
     let query_jobs = wft
         .pending_queries
         .drain(..)
         .map(|q| workflow_activation_job::Variant::QueryWorkflow(q).into());
     act.jobs.extend(query_jobs);
 }
+
+/// If server failed or reset away from a workflow task completed by `build_id` because it is a bad
+/// binary since this run's last completed workflow task, returns that failure. Older history is
+/// ignored, so that a run which server has since moved past is not refused forever. The reset
+/// recorded in the history of the run a reset created, `run_id` itself, is not a reason to refuse
+/// that run.
+fn bad_binary_failure<'a>(
+    update: &'a HistoryUpdate,
+    build_id: &str,
+    run_id: &str,
+) -> Option<&'a WorkflowTaskFailedEventAttributes> {
+    if build_id.is_empty() {
+        return None;
+    }
+    update
+        .get_events()
+        .iter()
+        .filter(|e| e.event_id > update.previous_wft_started_id)
+        .filter_map(|e| match &e.attributes {
+            Some(history_event::Attributes::WorkflowTaskFailedEventAttributes(a)) => Some(a),
+            _ => None,
+        })
+        .find(|a| {
+            let rejected = match a.cause() {
+                WorkflowTaskFailedCause::BadBinary => true,
+                WorkflowTaskFailedCause::ResetWorkflow => a.new_run_id != run_id,
+                _ => false,
+            };
+            rejected && a.binary_checksum == build_id
+        })
+}
+
 fn sink_heartbeat_timeout_start(
     run_id: String,
     sink: &dyn LocalActivityRequestSink,
@@ -1304,6 +1871,15 @@ fn sink_heartbeat_timeout_start(
     abort_handle
 }
 
+/// A legacy query being answered while the run's WFT is outstanding
+struct EagerQuery {
+    task_token: TaskToken,
+    /// The query task's own permit, freed once it has been answered
+    _permit: UsedMeteredSemPermit<WorkflowSlotKind>,
+    /// Set if the WFT's heartbeat came due while lang was answering the query
+    heartbeat_due: bool,
+}
+
 /// If an activation completion needed to wait on LA completions (or heartbeat timeout) we use
 /// this struct to store the data we need to finish the completion once that has happened
 struct WaitingOnLAs {
@@ -1333,20 +1909,47 @@ struct WorkflowManager {
     /// Is always `Some` in normal operation. Optional to allow for unit testing with the test
     /// workflow driver, which does not need to complete activations the normal way.
     command_sink: Option<Sender<Vec<WFCommand>>>,
+    stats: Arc<RunStats>,
+    /// See [WorkerConfig::verify_completions_by_replay]
+    verifier: Option<ReplayVerifier>,
 }
 
 impl WorkflowManager {
     /// Create a new workflow manager given workflow history and execution info as would be found
     /// in [PollWorkflowTaskQueueResponse]
-    fn new(basics: RunBasics) -> Self {
+    fn new(basics: RunBasics, stats: Arc<RunStats>) -> Self {
+        let verifier = basics
+            .worker_config
+            .verify_completions_by_replay
+            .then(|| ReplayVerifier::new(&basics));
         let (wfb, cmd_sink) = DrivenWorkflow::new();
         let state_machines = WorkflowMachines::new(basics, wfb);
         Self {
             machines: state_machines,
             command_sink: Some(cmd_sink),
+            stats,
+            verifier,
         }
     }
 
+    /// Run `f` against the machines, counting the time it takes towards the run's machine time
+    fn timed<T>(&mut self, f: impl FnOnce(&mut WorkflowMachines) -> T) -> T {
+        let started = Instant::now();
+        let r = f(&mut self.machines);
+        self.stats.record_machine_time(started.elapsed());
+        r
+    }
+
+    /// Like [Self::timed], for operations which can fail. Nondeterminism errors get the run's
+    /// recent machine transitions added to them.
+    fn timed_fallible<T>(
+        &mut self,
+        f: impl FnOnce(&mut WorkflowMachines) -> Result<T>,
+    ) -> Result<T> {
+        self.timed(f)
+            .map_err(|e| self.machines.with_recent_transitions(e))
+    }
+
     /// Given info that was just obtained from a new WFT from server, pipe it into this workflow's
     /// machines.
     ///
@@ -1357,14 +1960,17 @@ impl WorkflowManager {
         update: HistoryUpdate,
         messages: Vec<IncomingProtocolMessage>,
     ) -> Result<WorkflowActivation> {
-        self.machines.new_work_from_server(update, messages)?;
+        if let Some(v) = self.verifier.as_mut() {
+            v.new_task(&messages);
+        }
+        self.timed_fallible(|m| m.new_work_from_server(update, messages))?;
         self.get_next_activation()
     }
 
     /// Update the machines with some events from fetching another page of history. Does *not*
     /// attempt to pull the next activation, unlike [Self::new_work_from_server].
     fn feed_history_from_new_page(&mut self, update: HistoryUpdate) -> Result<()> {
-        self.machines.new_history_from_server(update)
+        self.timed_fallible(|m| m.new_history_from_server(update))
     }
 
     /// Let this workflow know that something we've been waiting locally on has resolved, like a
@@ -1373,7 +1979,10 @@ impl WorkflowManager {
     /// Returns true if the resolution did anything. EX: If the activity is already canceled and
     /// used the TryCancel or Abandon modes, the resolution is uninteresting.
     fn notify_of_local_result(&mut self, resolved: LocalResolution) -> Result<bool> {
-        self.machines.local_resolution(resolved)
+        if let Some(v) = self.verifier.as_mut() {
+            v.local_resolution(&resolved);
+        }
+        self.timed_fallible(|m| m.local_resolution(resolved))
     }
 
     /// Fetch the next workflow activation for this workflow if one is required. Doing so will apply
@@ -1383,13 +1992,13 @@ impl WorkflowManager {
     /// to the server.
     fn get_next_activation(&mut self) -> Result<WorkflowActivation> {
         // First check if there are already some pending jobs, which can be a result of replay.
-        let activation = self.machines.get_wf_activation();
-        if !activation.jobs.is_empty() {
-            return Ok(activation);
-        }
-
-        self.machines.apply_next_wft_from_history()?;
-        Ok(self.machines.get_wf_activation())
+        // Checking before assembling avoids building (and throwing away) an empty activation.
+        self.timed_fallible(|m| {
+            if !m.has_pending_jobs() {
+                m.apply_next_wft_from_history()?;
+            }
+            Ok(m.get_wf_activation())
+        })
     }
 
     /// Returns true if machines are ready to apply the next WFT sequence, false if events will need
@@ -1406,23 +2015,46 @@ impl WorkflowManager {
         if self.machines.has_pending_jobs() {
             return Ok(true);
         }
-        loop {
-            let consumed_events = self.machines.apply_next_wft_from_history()?;
-
-            if consumed_events == 0 || !self.machines.replaying || self.machines.has_pending_jobs()
-            {
-                // Keep applying tasks while there are events, we are still replaying, and there are
-                // no jobs
-                break;
+        self.timed_fallible(|m| {
+            loop {
+                let consumed_events = m.apply_next_wft_from_history()?;
+
+                if consumed_events == 0 || !m.replaying || m.has_pending_jobs() {
+                    // Keep applying tasks while there are events, we are still replaying, and
+                    // there are no jobs
+                    break;
+                }
             }
-        }
-        Ok(self.machines.has_pending_jobs())
+            Ok(m.has_pending_jobs())
+        })
     }
 
     /// Must be called when we're ready to respond to a WFT after handling catching up on replay
     /// and handling all activation completions from lang.
     fn prepare_for_wft_response(&mut self) -> MachinesWFTResponseContent {
-        self.machines.prepare_for_wft_response()
+        self.timed(|m| m.prepare_for_wft_response())
+    }
+
+    /// Note that lang was given an activation, so that a later verification can check replay
+    /// gives it the same one
+    fn activation_issued(&mut self, act: &WorkflowActivation) {
+        if let Some(v) = self.verifier.as_mut() {
+            v.activation_issued(act);
+        }
+    }
+
+    /// If completions are being verified, check that replaying the run arrives at the commands
+    /// about to be sent
+    fn verify_completion(&mut self, commands: &[ProtoCommand]) -> Result<()> {
+        let Some(v) = self.verifier.as_mut() else {
+            return Ok(());
+        };
+        let res = v.verify(&self.machines, commands);
+        if v.stopped() {
+            self.verifier = None;
+            self.machines.stop_retaining_history_for_verification();
+        }
+        res
     }
 
     /// Remove and return all queued local activities. Once this is called, they need to be
@@ -1434,13 +2066,15 @@ impl WorkflowManager {
     /// Feed the workflow machines new commands issued by the executing workflow code, and iterate
     /// the machines.
     fn push_commands_and_iterate(&mut self, cmds: Vec<WFCommand>) -> Result<()> {
+        if let Some(v) = self.verifier.as_mut() {
+            v.commands_pushed(&cmds);
+        }
         if let Some(cs) = self.command_sink.as_mut() {
             cs.send(cmds).map_err(|_| {
                 WFMachinesError::Fatal("Internal error buffering workflow commands".to_string())
             })?;
         }
-        self.machines.iterate_machines()?;
-        Ok(())
+        self.timed_fallible(|m| m.iterate_machines())
     }
 }
 
@@ -1466,6 +2100,7 @@ struct RunActivationCompletion {
     has_pending_query: bool,
     query_responses: Vec<QueryResult>,
     used_flags: Vec<u32>,
+    request_reactivation: bool,
     /// Used to notify the worker when the completion is done processing and the completion can
     /// unblock. Must always be `Some` when initialized.
     resp_chan: Option<oneshot::Sender<ActivationCompleteResult>>,
@@ -1494,8 +2129,15 @@ impl From<WFMachinesError> for RunUpdateErr {
 
 #[cfg(test)]
 mod tests {
-    use crate::worker::workflow::WFCommand;
+    use crate::worker::workflow::{HistoryUpdate, WFCommand};
     use std::mem::{discriminant, Discriminant};
+    use temporal_sdk_core_protos::{
+        temporal::api::{
+            enums::v1::{EventType, WorkflowTaskFailedCause},
+            history::v1::WorkflowTaskFailedEventAttributes,
+        },
+        TestHistoryBuilder,
+    };
 
     use command_utils::*;
 
@@ -1583,6 +2225,79 @@ mod tests {
         assert_eq!(query_responses_out.len(), expected_queries_out);
     }
 
+    #[test]
+    fn query_deferral_is_bounded_by_the_wft_timeout_unless_configured() {
+        use super::{query_deferral_timeout, DEFAULT_QUERY_DEFERRAL_TIMEOUT};
+        use std::time::Duration;
+
+        let (configured, wft_timeout) = (Duration::from_secs(1), Duration::from_secs(3));
+        assert_eq!(
+            query_deferral_timeout(Some(configured), Some(wft_timeout)),
+            configured
+        );
+        assert_eq!(query_deferral_timeout(None, Some(wft_timeout)), wft_timeout);
+        assert_eq!(
+            query_deferral_timeout(None, None),
+            DEFAULT_QUERY_DEFERRAL_TIMEOUT
+        );
+    }
+
+    #[test]
+    fn query_results_are_reconciled_with_dispatched_queries() {
+        use temporal_sdk_core_protos::coresdk::workflow_commands::{query_result, QueryResult};
+
+        let answer = |query_id: &str| QueryResult {
+            query_id: query_id.to_string(),
+            variant: Some(query_result::Variant::Succeeded(Default::default())),
+        };
+        let reconciled = super::reconcile_query_responses(
+            vec!["q1".to_string(), "q2".to_string()],
+            vec![answer("q1"), answer("unknown"), answer("q1")],
+        );
+        // A result for an unknown (or already answered) query is dropped rather than failing the
+        // task, and an unanswered query is failed on its own
+        assert_matches!(
+            reconciled.as_slice(),
+            [
+                QueryResult { query_id: q1, variant: Some(query_result::Variant::Succeeded(_)) },
+                QueryResult { query_id: q2, variant: Some(query_result::Variant::Failed(_)) },
+            ] if q1 == "q1" && q2 == "q2"
+        );
+    }
+
+    #[rstest::rstest]
+    #[case::bad_binary(WorkflowTaskFailedCause::BadBinary, "", true)]
+    #[case::reset_of_another_run(WorkflowTaskFailedCause::ResetWorkflow, "other-run", true)]
+    #[case::the_reset_run_itself(WorkflowTaskFailedCause::ResetWorkflow, "run", false)]
+    #[case::unrelated_failure(WorkflowTaskFailedCause::Unspecified, "", false)]
+    #[test]
+    fn bad_binary_failures_exclude_the_reset_run(
+        #[case] cause: WorkflowTaskFailedCause,
+        #[case] new_run_id: &str,
+        #[case] is_rejected: bool,
+    ) {
+        let mut t = TestHistoryBuilder::default();
+        t.add_by_type(EventType::WorkflowExecutionStarted);
+        t.add_full_wf_task();
+        t.add_workflow_task_scheduled_and_started();
+        let scheduled_event_id = t.current_event_id() - 1;
+        t.add(WorkflowTaskFailedEventAttributes {
+            scheduled_event_id,
+            cause: cause as i32,
+            binary_checksum: "bad-build".to_string(),
+            new_run_id: new_run_id.to_string(),
+            ..Default::default()
+        });
+        t.add_workflow_task_scheduled_and_started();
+        let update = HistoryUpdate::from(t.get_full_history_info().unwrap());
+
+        assert_eq!(
+            super::bad_binary_failure(&update, "bad-build", "run").is_some(),
+            is_rejected
+        );
+        assert!(super::bad_binary_failure(&update, "good-build", "run").is_none());
+    }
+
     mod command_utils {
         use temporal_sdk_core_protos::coresdk::workflow_commands::{
             CancelWorkflowExecution, CompleteWorkflowExecution, QueryResult, UpdateResponse,
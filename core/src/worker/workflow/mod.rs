@@ -2,17 +2,32 @@
 //! lion's share of the complexity in Core). See the `ARCHITECTURE.md` file in the repo root for
 //! a diagram of the internals.
 
+mod activation_deadlines;
+mod activity_queue_check;
+mod cache_snapshot;
+mod command_validation;
+mod correlation_id;
 mod driven_workflow;
+mod dropped_jobs;
 mod history_update;
 mod machines;
 mod managed_run;
+mod ready_activations;
+mod recent_completions;
+mod rejected_commands;
+mod replay_limiter;
+mod replay_verification;
 mod run_cache;
+mod run_stats;
 mod wft_extraction;
 pub(crate) mod wft_poller;
 mod workflow_stream;
 
+pub(crate) use cache_snapshot::{CacheSnapshot, CACHE_SNAPSHOT_VERSION};
 pub(crate) use driven_workflow::DrivenWorkflow;
 pub(crate) use history_update::HistoryUpdate;
+pub(crate) use run_stats::RunStatsRegistry;
+pub use run_stats::{HotRuns, RunProcessingStats};
 
 use crate::{
     abstractions::{
@@ -20,14 +35,27 @@ use crate::{
         UsedMeteredSemPermit,
     },
     internal_flags::InternalFlags,
+    pollers::PollAuthFailures,
     protosext::{legacy_query_failure, protocol_messages::IncomingProtocolMessage},
-    telemetry::{set_trace_subscriber_for_current_thread, TelemetryInstance, VecDisplayer},
+    telemetry::{
+        metrics::MessageType, set_trace_subscriber_for_current_thread, TelemetryInstance,
+        VecDisplayer,
+    },
     worker::{
         activities::{ActivitiesFromWFTsHandle, LocalActivityManager, TrackedPermittedTqResp},
         client::{WorkerClient, WorkflowTaskCompletion},
+        clock_skew::ClockSkewEstimator,
+        large_payloads,
+        payload_limits::{self, PayloadSizeGuard},
         workflow::{
+            activation_deadlines::{ActivationDeadlineMsg, ActivationDeadlines},
+            activity_queue_check::ActivityQueueChecker,
+            cache_snapshot::RunSnapshot,
             history_update::HistoryPaginator,
             managed_run::RunUpdateAct,
+            ready_activations::{ReadyActivation, ReadyActivations},
+            recent_completions::{RecentCompletions, Retry},
+            rejected_commands::RejectedCommand,
             wft_extraction::{HistoryFetchReq, WFTExtractor, WFTStreamIn},
             wft_poller::validate_wft,
             workflow_stream::{LocalInput, LocalInputs, WFStream},
@@ -38,25 +66,26 @@ use crate::{
     MetricsContext,
 };
 use anyhow::anyhow;
-use futures_util::{future::abortable, stream, stream::BoxStream, Stream, StreamExt};
+use futures_util::{future::abortable, stream, stream::BoxStream, FutureExt, Stream, StreamExt};
 use itertools::Itertools;
+use prost::Message;
 use prost_types::TimestampError;
 use std::{
     cell::RefCell,
     collections::VecDeque,
     fmt::Debug,
-    future::Future,
+    future::{self, Future},
     mem,
     ops::DerefMut,
     rc::Rc,
     result,
     sync::{atomic, atomic::AtomicBool, Arc},
     thread,
-    time::{Duration, Instant},
+    time::{Duration, Instant, SystemTime},
 };
 use temporal_sdk_core_api::{
     errors::{CompleteWfError, PollWfError},
-    worker::{ActivitySlotKind, WorkerConfig, WorkflowSlotKind},
+    worker::{ActivitySlotKind, LargePayloadStore, WorkerConfig, WorkflowSlotKind},
 };
 use temporal_sdk_core_protos::{
     coresdk::{
@@ -73,19 +102,21 @@ use temporal_sdk_core_protos::{
     temporal::api::{
         command::v1::{command::Attributes, Command as ProtoCommand, Command},
         common::v1::{Memo, MeteringMetadata, RetryPolicy, SearchAttributes, WorkflowExecution},
-        enums::v1::WorkflowTaskFailedCause,
+        enums::v1::{TaskQueueKind, WorkflowTaskFailedCause},
+        failure::v1::Failure as ProtoFailure,
+        history::v1::HistoryEvent,
         protocol::v1::Message as ProtocolMessage,
         query::v1::WorkflowQuery,
         sdk::v1::WorkflowTaskCompletedMetadata,
         taskqueue::v1::StickyExecutionAttributes,
         workflowservice::v1::{get_system_info_response, PollActivityTaskQueueResponse},
     },
-    TaskToken,
+    TaskToken, WorkflowId,
 };
 use tokio::{
     sync::{
         mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
-        oneshot,
+        oneshot, Semaphore,
     },
     task::{spawn_blocking, LocalSet},
 };
@@ -97,10 +128,9 @@ pub(crate) const LEGACY_QUERY_ID: &str = "legacy_query";
 /// What percentage of a WFT timeout we are willing to wait before sending a WFT heartbeat when
 /// necessary.
 const WFT_HEARTBEAT_TIMEOUT_FRACTION: f32 = 0.8;
-const MAX_EAGER_ACTIVITY_RESERVATIONS_PER_WORKFLOW_TASK: usize = 3;
 
 type Result<T, E = WFMachinesError> = result::Result<T, E>;
-type BoxedActivationStream = BoxStream<'static, Result<ActivationOrAuto, PollWfError>>;
+type BoxedActivationStream = BoxStream<'static, Result<ReadyActivation, PollWfError>>;
 type InternalFlagsRef = Rc<RefCell<InternalFlags>>;
 
 /// Centralizes all state related to workflows and workflow tasks
@@ -112,6 +142,8 @@ pub(crate) struct Workflows {
         BoxedActivationStream,
         // Used to indicate polling may begin
         Option<oneshot::Sender<()>>,
+        // Activations taken from the stream but not yet delivered
+        ReadyActivations,
     )>,
     client: Arc<dyn WorkerClient>,
     /// Will be populated when this worker is using a cache and should complete WFTs with a sticky
@@ -119,10 +151,36 @@ pub(crate) struct Workflows {
     sticky_attrs: Option<StickyExecutionAttributes>,
     /// If set, can be used to reserve activity task slots for eager-return of new activity tasks.
     activity_tasks_handle: Option<ActivitiesFromWFTsHandle>,
+    /// See [WorkerConfig::max_eager_activities_per_workflow_task]
+    max_eager_activities: usize,
     /// Ensures we stay at or below this worker's maximum concurrent workflow task limit
     wft_semaphore: MeteredPermitDealer<WorkflowSlotKind>,
     local_act_mgr: Arc<LocalActivityManager>,
     ever_polled: AtomicBool,
+    /// See [WorkerConfig::strict_command_validation]
+    strict_command_validation: bool,
+    /// See [WorkerConfig::max_history_fetch_bytes]
+    max_history_fetch_bytes: Option<usize>,
+    /// Held by each history page fetch. See [WorkerConfig::fetching_concurrency]
+    history_fetch_permits: Arc<Semaphore>,
+    /// See [WorkerConfig::poll_saturation_timeout]
+    poll_saturation_timeout: Option<Duration>,
+    payload_sizes: PayloadSizeGuard,
+    /// See [WorkerConfig::large_payload_store]
+    large_payload_store: Option<Arc<dyn LargePayloadStore>>,
+    run_stats: RunStatsRegistry,
+    metrics: MetricsContext,
+    /// The last completion applied for each run, so lang can safely retry one it lost the result
+    /// of
+    recent_completions: RecentCompletions,
+    /// See [WorkerConfig::activity_task_queue_check_ttl]
+    activity_queue_checker: Option<Arc<ActivityQueueChecker>>,
+    /// See [WorkerConfig::activation_deadline]
+    activation_deadlines: ActivationDeadlines,
+    /// See [temporal_sdk_core_api::worker::PollAuthFailureEvent]
+    poll_auth_failures: Arc<PollAuthFailures>,
+    /// Fed the round trip times of workflow task completions
+    clock_skew: Arc<ClockSkewEstimator>,
 }
 
 pub(crate) struct WorkflowBasics {
@@ -130,6 +188,9 @@ pub(crate) struct WorkflowBasics {
     pub(crate) shutdown_token: CancellationToken,
     pub(crate) metrics: MetricsContext,
     pub(crate) server_capabilities: get_system_info_response::Capabilities,
+    pub(crate) run_stats: RunStatsRegistry,
+    pub(crate) poll_auth_failures: Arc<PollAuthFailures>,
+    pub(crate) clock_skew: Arc<ClockSkewEstimator>,
 }
 
 pub(crate) struct RunBasics<'a> {
@@ -160,9 +221,31 @@ impl Workflows {
         let (fetch_tx, fetch_rx) = unbounded_channel();
         let shutdown_tok = basics.shutdown_token.clone();
         let task_queue = basics.worker_config.task_queue.clone();
+        let strict_command_validation = basics.worker_config.strict_command_validation;
+        let max_history_fetch_bytes = basics.worker_config.max_history_fetch_bytes;
+        let history_fetch_permits =
+            Arc::new(Semaphore::new(basics.worker_config.fetching_concurrency));
+        let max_eager_activities = basics.worker_config.max_eager_activities_per_workflow_task;
+        let poll_saturation_timeout = basics.worker_config.poll_saturation_timeout;
+        let payload_sizes = PayloadSizeGuard::new(&basics.worker_config, &basics.metrics);
+        let large_payload_store = basics.worker_config.large_payload_store.clone();
+        let activation_delivery_order = basics.worker_config.activation_delivery_order;
+        let metrics = basics.metrics.clone();
+        let run_stats = basics.run_stats.clone();
+        let poll_auth_failures = basics.poll_auth_failures.clone();
+        let clock_skew = basics.clock_skew.clone();
+        let activity_queue_checker = basics
+            .worker_config
+            .activity_task_queue_check_ttl
+            .map(|ttl| ActivityQueueChecker::new(client.clone(), ttl, metrics.clone()))
+            .map(Arc::new);
+        let activation_deadlines =
+            ActivationDeadlines::new(basics.worker_config.activation_deadline, local_tx.clone());
         let extracted_wft_stream = WFTExtractor::build(
             client.clone(),
             basics.worker_config.fetching_concurrency,
+            max_history_fetch_bytes,
+            history_fetch_permits.clone(),
             wft_stream,
             UnboundedReceiverStream::new(fetch_rx),
         );
@@ -170,6 +253,7 @@ impl Workflows {
             UnboundedReceiverStream::new(local_rx),
             UnboundedReceiverStream::new(heartbeat_timeout_rx).map(Into::into),
         );
+        let stream_local_tx = local_tx.clone();
         let (activation_tx, activation_rx) = unbounded_channel();
         let (start_polling_tx, start_polling_rx) = oneshot::channel();
         // We must spawn a task to constantly poll the activation stream, because otherwise
@@ -191,6 +275,7 @@ impl Workflows {
                         basics,
                         extracted_wft_stream,
                         locals_stream,
+                        stream_local_tx,
                         local_activity_request_sink,
                     );
 
@@ -238,31 +323,134 @@ impl Workflows {
             activation_stream: tokio::sync::Mutex::new((
                 UnboundedReceiverStream::new(activation_rx).boxed(),
                 Some(start_polling_tx),
+                ReadyActivations::new(activation_delivery_order),
             )),
             client,
             sticky_attrs,
             activity_tasks_handle,
+            max_eager_activities,
             wft_semaphore,
             local_act_mgr,
             ever_polled: AtomicBool::new(false),
+            strict_command_validation,
+            max_history_fetch_bytes,
+            history_fetch_permits,
+            poll_saturation_timeout,
+            payload_sizes,
+            large_payload_store,
+            run_stats,
+            metrics,
+            recent_completions: Default::default(),
+            activity_queue_checker,
+            activation_deadlines,
+            poll_auth_failures,
+            clock_skew,
         }
     }
 
     pub(super) async fn next_workflow_activation(&self) -> Result<WorkflowActivation, PollWfError> {
         self.ever_polled.store(true, atomic::Ordering::Release);
         loop {
-            let al = {
+            let ready = {
                 let mut lock = self.activation_stream.lock().await;
-                let (ref mut stream, ref mut beginner) = lock.deref_mut();
+                let (ref mut stream, ref mut beginner, ref mut buffered) = lock.deref_mut();
                 if let Some(beginner) = beginner.take() {
                     let _ = beginner.send(());
                 }
-                stream.next().await.unwrap_or(Err(PollWfError::ShutDown))?
+                // Take everything which is already ready, so it can all be considered for
+                // delivery in the configured order
+                while let Some(Some(ready)) = stream.next().now_or_never() {
+                    buffered.push(ready?);
+                }
+                match buffered.pop() {
+                    Some(ready) => ready,
+                    None => {
+                        let saturated = async {
+                            match self.poll_saturation_timeout {
+                                Some(wait) => self.wft_semaphore.saturated_after(wait).await,
+                                None => future::pending().await,
+                            }
+                        };
+                        tokio::select! {
+                            biased;
+
+                            next = stream.next() => next.unwrap_or(Err(PollWfError::ShutDown))?,
+                            (outstanding, limit) = saturated => {
+                                return Err(PollWfError::WorkerSaturated { outstanding, limit });
+                            }
+                            status = self.poll_auth_failures.stopped() => {
+                                return Err(PollWfError::AuthFailure(status));
+                            }
+                        }
+                    }
+                }
             };
-            match al {
+            match ready.act {
                 ActivationOrAuto::LangActivation(mut act)
                 | ActivationOrAuto::ReadyForQueries(mut act) => {
+                    if ready
+                        .wft_deadline
+                        .is_some_and(|d| d.is_near(Instant::now()))
+                    {
+                        self.metrics.wft_delivered_near_deadline();
+                    }
                     prepare_to_ship_activation(&mut act);
+                    let span = Span::current();
+                    span.record("run_id", act.run_id.as_str());
+                    span.record("correlation_id", act.correlation_id.as_str());
+                    if let Some(info) = &ready.wft_info {
+                        info.record_span_fields(&span);
+                    }
+                    if let Some(store) = self.large_payload_store.as_deref() {
+                        if let Err(e) = large_payloads::resolve_references(store, &mut act).await {
+                            // Lang never sees this activation. Failing its task lets server retry
+                            // it, by which time the store may be reachable again.
+                            let err = WFMachinesError::Fatal(format!(
+                                "Could not fetch stored activity result: {e}"
+                            ));
+                            warn!(error = %err, "Failing workflow task");
+                            if let Err(e) = self
+                                .activation_completed(
+                                    WorkflowActivationCompletion {
+                                        run_id: act.run_id,
+                                        status: Some(err.as_failure().into()),
+                                        ..Default::default()
+                                    },
+                                    true,
+                                    Option::<Box<dyn Fn(PostActivateHookData) + Send>>::None,
+                                )
+                                .await
+                            {
+                                error!(error=?e, "Error while auto-failing workflow task");
+                            }
+                            continue;
+                        }
+                    }
+                    self.payload_sizes
+                        .record_inbound(payload_limits::activation_payloads(&act));
+                    let size = act.encoded_len();
+                    if self
+                        .payload_sizes
+                        .record_message(MessageType::WorkflowActivation, size)
+                    {
+                        warn!(
+                            run_id = %act.run_id,
+                            workflow_type = ready
+                                .wft_info
+                                .as_ref()
+                                .map(|i| i.wf_type.as_str())
+                                .unwrap_or_default(),
+                            size,
+                            "Activation exceeds the message size warning threshold"
+                        );
+                    }
+                    if !act.is_only_eviction() {
+                        self.activation_deadlines.start(
+                            &act.run_id,
+                            &act.correlation_id,
+                            ready.wft_deadline.map(|d| d.timeout),
+                        );
+                    }
                     debug!(activation=%act, "Sending activation to lang");
                     break Ok(act);
                 }
@@ -274,6 +462,7 @@ impl Workflows {
                                 status: Some(
                                     workflow_completion::Success::from_variants(vec![]).into(),
                                 ),
+                                ..Default::default()
                             },
                             true,
                             // We need to say a type, but the type is irrelevant, so imagine some
@@ -294,6 +483,7 @@ impl Workflows {
                             WorkflowActivationCompletion {
                                 run_id,
                                 status: Some(machines_err.as_failure().into()),
+                                ..Default::default()
                             },
                             true,
                             Option::<Box<dyn Fn(PostActivateHookData) + Send>>::None,
@@ -303,6 +493,93 @@ impl Workflows {
                         error!(error=?e, "Error while auto-failing workflow task");
                     }
                 }
+                ActivationOrAuto::FailStuckActivation(expired) => {
+                    error!(run_id=%expired.run_id, correlation_id=%expired.correlation_id,
+                           waited=?expired.waited,
+                           "Potential deadlock detected: lang did not complete an activation in \
+                            time. Failing its workflow task and evicting the run.");
+                    let failure = Failure {
+                        failure: Some(ProtoFailure::application_failure(
+                            format!(
+                                "Potential deadlock detected: workflow activation was not \
+                                 completed within {:?}",
+                                expired.waited
+                            ),
+                            false,
+                        )),
+                        force_cause: WorkflowTaskFailedCause::WorkflowWorkerUnhandledFailure as i32,
+                        ..Default::default()
+                    };
+                    if let Err(e) = self
+                        .activation_completed(
+                            WorkflowActivationCompletion {
+                                run_id: expired.run_id.clone(),
+                                status: Some(failure.into()),
+                                correlation_id: expired.correlation_id.clone(),
+                            },
+                            true,
+                            Option::<Box<dyn Fn(PostActivateHookData) + Send>>::None,
+                        )
+                        .await
+                    {
+                        error!(error=?e, "Error while failing stuck workflow task");
+                    }
+                    self.activation_deadlines
+                        .forget_expired(&expired.run_id, &expired.correlation_id);
+                }
+                ActivationOrAuto::FailDeferredQueries {
+                    run_id,
+                    task_tokens,
+                } => {
+                    warn!(run_id=%run_id, num_queries=task_tokens.len(),
+                          "Failing queries deferred too long behind an unacknowledged completion");
+                    for tt in task_tokens {
+                        let failure = Failure {
+                            failure: Some(ProtoFailure::application_failure(
+                                "Query was deferred until the workflow's last completion was \
+                                 acknowledged by server, but that took too long"
+                                    .to_string(),
+                                false,
+                            )),
+                            ..Default::default()
+                        };
+                        self.respond_legacy_query(tt, legacy_query_failure(failure))
+                            .await;
+                    }
+                }
+                ActivationOrAuto::FailUnstartedTasks {
+                    run_id,
+                    tasks,
+                    reason,
+                } => {
+                    warn!(run_id=%run_id, num_tasks=tasks.len(), reason,
+                          "Failing tasks which will never be processed");
+                    for task in tasks {
+                        let failure = ProtoFailure::application_failure(reason.to_string(), false);
+                        let res = if task.legacy_query.is_some() {
+                            self.respond_legacy_query(
+                                task.task_token,
+                                legacy_query_failure(failure.into()),
+                            )
+                            .await;
+                            Ok(())
+                        } else {
+                            self.client
+                                .fail_workflow_task(
+                                    task.task_token,
+                                    WorkflowTaskFailedCause::Unspecified,
+                                    Some(failure),
+                                )
+                                .await
+                                .map(|_| ())
+                        };
+                        if let Err(e) = res {
+                            warn!(run_id=%run_id, error=%e, "Failed to fail unprocessed task");
+                        }
+                    }
+                    // The stream must check whether it's done again now that these are gone
+                    self.send_get_state_info_msg();
+                }
             }
         }
     }
@@ -318,12 +595,50 @@ impl Workflows {
         post_activate_hook: Option<impl Fn(PostActivateHookData)>,
     ) -> Result<(), CompleteWfError> {
         let is_empty_completion = completion.is_empty();
+        let size = completion.encoded_len();
+        let size_warning = self
+            .payload_sizes
+            .record_message(MessageType::WorkflowActivationCompletion, size)
+            .then_some(size);
+        let remembered = (!is_autocomplete && RecentCompletions::would_remember(size))
+            .then(|| completion.clone());
         let completion = validate_completion(completion, is_autocomplete)?;
+        if let ValidatedCompletion::Success {
+            run_id,
+            commands,
+            correlation_id,
+            ..
+        } = &completion
+        {
+            command_validation::validate_commands(
+                run_id,
+                correlation_id,
+                commands,
+                self.strict_command_validation,
+            )?;
+            command_validation::check_payload_sizes(
+                run_id,
+                correlation_id,
+                commands,
+                &self.payload_sizes,
+            )?;
+        }
         let run_id = completion.run_id().to_string();
+        let correlation_id = completion.correlation_id().to_string();
+        if !is_autocomplete && !self.activation_deadlines.complete(&run_id, &correlation_id) {
+            return Err(CompleteWfError::InvalidCompletion {
+                reason: "The activation was not completed before its deadline, so its workflow \
+                         task has been failed"
+                    .to_string(),
+                correlation_id,
+                run_id,
+            });
+        }
         let (tx, rx) = oneshot::channel();
         let was_sent = self.send_local(WFActCompleteMsg {
             completion,
             response_tx: Some(tx),
+            size_warning,
         });
         if !was_sent {
             if is_empty_completion {
@@ -347,8 +662,20 @@ impl Workflows {
             );
             return Ok(());
         };
+        let remembered = match completion_outcome.outcome {
+            ActivationCompleteOutcome::Rejected(_) => remembered,
+            _ => {
+                if let Some(c) = remembered {
+                    self.recent_completions.record(c, size);
+                } else if !is_autocomplete {
+                    self.recent_completions.forget(&run_id);
+                }
+                None
+            }
+        };
 
         let mut wft_from_complete = None;
+        let mut rejected_command = None;
         let wft_report_status = match completion_outcome.outcome {
             ActivationCompleteOutcome::ReportWFTSuccess(report) => match report {
                 ServerCommandsWithWorkflowInfo {
@@ -364,9 +691,14 @@ impl Workflows {
                 } => {
                     let reserved_act_permits =
                         self.reserve_activity_slots_for_outgoing_commands(commands.as_mut_slice());
+                    let queues_to_check = self.activity_queue_checker.as_ref().map(|_| {
+                        ActivityQueueChecker::queues_to_check(&self.task_queue, &commands)
+                    });
                     debug!(commands=%commands.display(), query_responses=%query_responses.display(),
                            messages=%messages.display(), force_new_wft,
                            "Sending responses to server");
+                    let command_types: Vec<_> = commands.iter().map(|c| c.command_type()).collect();
+                    let failable_task_token = task_token.clone();
                     let mut completion = WorkflowTaskCompletion {
                         task_token,
                         commands,
@@ -392,20 +724,42 @@ impl Workflows {
                     completion.sticky_attributes = sticky_attrs;
 
                     let mut reset_last_started_to = None;
-                    self.handle_wft_reporting_errs(&run_id, || async {
-                        let response = self.client.complete_workflow_task(completion).await?;
-                        if response.reset_history_event_id > 0 {
-                            reset_last_started_to = Some(response.reset_history_event_id);
-                        }
-                        if let Some(wft) = response.workflow_task {
-                            wft_from_complete = Some(validate_wft(wft)?);
-                        }
-                        self.handle_eager_activities(reserved_act_permits, response.activity_tasks);
-                        Ok(())
-                    })
-                    .await;
+                    let completion_accepted = self
+                        .handle_wft_reporting_errs(&run_id, || async {
+                            let sent = Instant::now();
+                            let response = self
+                                .client
+                                .complete_workflow_task(completion)
+                                .await
+                                .inspect_err(|e| {
+                                    rejected_command =
+                                        RejectedCommand::from_status(e, &command_types);
+                                })?;
+                            self.clock_skew.observe_round_trip(sent.elapsed());
+                            if response.reset_history_event_id > 0 {
+                                reset_last_started_to = Some(response.reset_history_event_id);
+                            }
+                            if let Some(wft) = response.workflow_task {
+                                wft_from_complete = Some(validate_wft(wft)?);
+                            }
+                            self.handle_eager_activities(
+                                reserved_act_permits,
+                                response.activity_tasks,
+                            );
+                            Ok(())
+                        })
+                        .await;
+                    if let Some(rejected) = rejected_command.as_ref() {
+                        self.fail_wft_for_rejected_command(&run_id, failable_task_token, rejected)
+                            .await;
+                    } else if let (Some(checker), Some(queues)) =
+                        (self.activity_queue_checker.as_ref(), queues_to_check)
+                    {
+                        checker.spawn_check(queues);
+                    }
                     WFTReportStatus::Reported {
                         reset_last_started_to,
+                        completion_accepted,
                     }
                 }
                 ServerCommandsWithWorkflowInfo {
@@ -415,11 +769,12 @@ impl Workflows {
                     self.respond_legacy_query(task_token, *result).await;
                     WFTReportStatus::Reported {
                         reset_last_started_to: None,
+                        completion_accepted: false,
                     }
                 }
             },
             ActivationCompleteOutcome::ReportWFTFail(outcome) => match outcome {
-                FailedActivationWFTReport::Report(tt, cause, failure) => {
+                FailedActivationWFTReport::Report(tt, cause, failure, recovery) => {
                     warn!(run_id=%run_id, failure=?failure, "Failing workflow task");
                     self.handle_wft_reporting_errs(&run_id, || async {
                         self.client
@@ -427,8 +782,12 @@ impl Workflows {
                             .await
                     })
                     .await;
+                    if let Some(recovery) = recovery {
+                        self.recover_run(&run_id, recovery).await;
+                    }
                     WFTReportStatus::Reported {
                         reset_last_started_to: None,
+                        completion_accepted: false,
                     }
                 }
                 FailedActivationWFTReport::ReportLegacyQueryFailure(task_token, failure) => {
@@ -437,15 +796,43 @@ impl Workflows {
                         .await;
                     WFTReportStatus::Reported {
                         reset_last_started_to: None,
+                        completion_accepted: false,
                     }
                 }
             },
             ActivationCompleteOutcome::WFTFailedDontReport => WFTReportStatus::DropWft,
             ActivationCompleteOutcome::DoNothing => WFTReportStatus::NotReported,
+            ActivationCompleteOutcome::Rejected(e) => {
+                let Some(remembered) = remembered else {
+                    return Err(e);
+                };
+                return match self.recent_completions.check(&remembered) {
+                    Retry::Identical => {
+                        debug!(run_id=%run_id, "Accepting retry of an already applied completion");
+                        Ok(())
+                    }
+                    Retry::Conflicting => Err(CompleteWfError::InvalidCompletion {
+                        reason: format!(
+                            "A different completion was already applied for activation {}",
+                            remembered.correlation_id
+                        ),
+                        run_id,
+                        correlation_id: remembered.correlation_id,
+                    }),
+                    Retry::Unrelated => Err(e),
+                };
+            }
         };
 
         let maybe_pwft = if let Some(wft) = wft_from_complete {
-            match HistoryPaginator::from_poll(wft, self.client.clone()).await {
+            match HistoryPaginator::from_poll(
+                wft,
+                self.client.clone(),
+                self.max_history_fetch_bytes,
+                self.history_fetch_permits.clone(),
+            )
+            .await
+            {
                 Ok((paginator, wft)) => Some(WFTWithPaginator { wft, paginator }),
                 Err(e) => {
                     self.request_eviction(
@@ -468,13 +855,44 @@ impl Workflows {
         }
 
         self.post_activation(PostActivationMsg {
-            run_id,
+            run_id: run_id.clone(),
             wft_report_status,
             wft_from_complete: maybe_pwft,
             is_autocomplete,
         });
 
-        Ok(())
+        match rejected_command {
+            Some(rejected) => Err(CompleteWfError::CommandRejectedByServer {
+                cause: rejected.cause,
+                command_index: rejected.command_index,
+                message: rejected.message,
+                run_id,
+                correlation_id,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Server rejected the completion of the task because a command's attributes were invalid,
+    /// which it would do again however often it was retried. Failing the task with the cause server
+    /// gave records why in history.
+    async fn fail_wft_for_rejected_command(
+        &self,
+        run_id: &str,
+        task_token: TaskToken,
+        rejected: &RejectedCommand,
+    ) {
+        warn!(run_id, cause=?rejected.cause, command_index=?rejected.command_index,
+              message=%rejected.message, "Server rejected a command, failing workflow task");
+        let failure = ProtoFailure::application_failure(rejected.message.clone(), false);
+        if let Err(e) = self
+            .client
+            .fail_workflow_task(task_token, rejected.cause, Some(failure))
+            .await
+        {
+            // Server may already have failed the task itself
+            debug!(run_id, error=%e, "Could not fail task with rejected command");
+        }
     }
 
     /// Tell workflow that a local activity has finished with the provided result
@@ -518,6 +936,29 @@ impl Workflows {
         async move { rx.await.ok() }
     }
 
+    /// Snapshot every healthy cached run, for handover to another worker. Empty if nothing has
+    /// been polled yet, or workflow state is shut down.
+    pub(super) async fn export_run_snapshots(&self) -> Vec<RunSnapshot> {
+        if !self.ever_polled.load(atomic::Ordering::Acquire) {
+            // The processing stream doesn't start until the first poll, so nothing can be cached
+            return vec![];
+        }
+        let (tx, rx) = oneshot::channel();
+        self.send_local(ExportCacheSnapshotMsg { response_tx: tx });
+        rx.await.unwrap_or_default()
+    }
+
+    /// Make runs handed over from another worker available to pick up without fetching history.
+    /// Must be called before the first poll to be sure it's seen before any task for them.
+    pub(super) fn import_run_snapshots(&self, runs: Vec<RunSnapshot>) {
+        self.send_local(ImportCacheSnapshotMsg { runs });
+    }
+
+    /// Returns up to `k` of the cached runs which have needed the most processing, by each measure
+    pub(super) fn hot_runs(&self, k: usize) -> HotRuns {
+        self.run_stats.hot_runs(k)
+    }
+
     pub(super) fn available_wft_permits(&self) -> Option<usize> {
         self.wft_semaphore.available_permits()
     }
@@ -562,9 +1003,35 @@ impl Workflows {
         self.send_local(Box::new(msg));
     }
 
+    /// Finish recovering a run whose cached state is suspected to be corrupt. By the time this is
+    /// called its task has been failed and its eviction requested, so what's left is to reset its
+    /// sticky queue. Server then sends the next task to the normal queue, with full history, where
+    /// any worker (not only this one, whose cache can't be trusted) may pick it up.
+    async fn recover_run(&self, run_id: &str, recovery: RunRecovery) {
+        warn!(run_id, cause = recovery.cause.as_str(), "Recovering run");
+        self.metrics.run_recovered(recovery.cause.as_str());
+        if self.sticky_attrs.is_none() {
+            return;
+        }
+        if let Err(e) = self
+            .client
+            .reset_sticky_task_queue(recovery.workflow_id, run_id.into())
+            .await
+        {
+            // The run is evicted regardless, so at worst its next task comes to this worker on
+            // the sticky queue, misses the cache, and fetches full history anyway
+            warn!(error = %e, run_id, "Failed to reset sticky queue of recovering run");
+        }
+    }
+
     /// Handle server errors from either completing or failing a workflow task. Un-handleable errors
     /// trigger a workflow eviction and are logged.
-    async fn handle_wft_reporting_errs<T, Fut>(&self, run_id: &str, completer: impl FnOnce() -> Fut)
+    /// Returns true if the report succeeded
+    async fn handle_wft_reporting_errs<T, Fut>(
+        &self,
+        run_id: &str,
+        completer: impl FnOnce() -> Fut,
+    ) -> bool
     where
         Fut: Future<Output = Result<T, tonic::Status>>,
     {
@@ -589,7 +1056,9 @@ impl Workflows {
         }
         if let Some(reason) = should_evict {
             self.request_eviction(run_id, "Error reporting WFT to server", reason);
+            return false;
         }
+        true
     }
 
     /// Sends a message to the workflow processing stream. Returns true if the message was sent
@@ -681,9 +1150,7 @@ impl Workflows {
                         .as_ref()
                         .map(|q| q.name == self.task_queue)
                         .unwrap_or_default();
-                    if same_task_queue
-                        && reserved.len() < MAX_EAGER_ACTIVITY_RESERVATIONS_PER_WORKFLOW_TASK
-                    {
+                    if same_task_queue && reserved.len() < self.max_eager_activities {
                         if let Some(p) = self
                             .activity_tasks_handle
                             .as_ref()
@@ -730,6 +1197,9 @@ impl Workflows {
 #[must_use]
 struct CacheMissFetchReq {
     original_wft: PermittedWFT,
+    /// History handed over from another worker which precedes that in the task. If set, nothing
+    /// actually needs to be fetched.
+    handed_over_history: Option<Vec<HistoryEvent>>,
 }
 /// Bubbled up from inside workflow state if we're trying to apply the next workflow task but it
 /// isn't in memory
@@ -742,7 +1212,7 @@ struct NextPageReq {
 
 #[derive(Debug)]
 struct WFStreamOutput {
-    activations: VecDeque<ActivationOrAuto>,
+    activations: VecDeque<ReadyActivation>,
     fetch_histories: VecDeque<HistoryFetchReq>,
 }
 
@@ -761,6 +1231,41 @@ enum ActivationOrAuto {
         run_id: String,
         machines_err: WFMachinesError,
     },
+    /// Lang did not complete an activation before its deadline, so its task is failed without
+    /// waiting any longer
+    #[display("FailStuckActivation(run_id={})", _0.run_id)]
+    FailStuckActivation(ActivationDeadlineMsg),
+    /// Legacy queries which were deferred for too long, and are failed without involving lang
+    #[display("FailDeferredQueries(run_id={run_id})")]
+    FailDeferredQueries {
+        run_id: String,
+        task_tokens: Vec<TaskToken>,
+    },
+    /// Tasks which will never be processed, ex: because they were still waiting for a cache slot
+    /// when everything else had shut down. They are failed (or, for legacy queries, answered with
+    /// a failure) to let server retry them without waiting for them to time out. `reason` is the
+    /// failure message.
+    #[display("FailUnstartedTasks(run_id={run_id})")]
+    FailUnstartedTasks {
+        run_id: String,
+        tasks: Vec<PreparedWFT>,
+        reason: &'static str,
+    },
+}
+
+impl ActivationOrAuto {
+    fn run_id(&self) -> &str {
+        match self {
+            ActivationOrAuto::LangActivation(act) | ActivationOrAuto::ReadyForQueries(act) => {
+                &act.run_id
+            }
+            ActivationOrAuto::FailStuckActivation(expired) => &expired.run_id,
+            ActivationOrAuto::Autocomplete { run_id }
+            | ActivationOrAuto::AutoFail { run_id, .. }
+            | ActivationOrAuto::FailDeferredQueries { run_id, .. }
+            | ActivationOrAuto::FailUnstartedTasks { run_id, .. } => run_id,
+        }
+    }
 }
 
 /// A WFT which is considered to be using a slot for metrics purposes and being or about to be
@@ -790,6 +1295,7 @@ struct PreparedWFT {
     query_requests: Vec<QueryWorkflow>,
     update: HistoryUpdate,
     messages: Vec<IncomingProtocolMessage>,
+    task_queue_kind: TaskQueueKind,
 }
 
 impl PreparedWFT {
@@ -824,10 +1330,15 @@ struct OutstandingTask {
     info: WorkflowTaskInfo,
     /// Set if the outstanding task has quer(ies) which must be fulfilled upon finishing replay
     pending_queries: Vec<QueryWorkflow>,
+    /// Ids of (non-legacy) queries which have been dispatched to lang, and which the completion of
+    /// the activation they were sent in must contain results for
+    dispatched_queries: Vec<String>,
     start_time: Instant,
     /// The WFT permit owned by this task, ensures we don't exceed max concurrent WFT, and makes
     /// sure the permit is automatically freed when we delete the task.
     permit: UsedMeteredSemPermit<WorkflowSlotKind>,
+    /// Number of reactivations lang has requested (and been granted) while holding this task
+    reactivations: usize,
 }
 
 impl OutstandingTask {
@@ -861,14 +1372,52 @@ struct WorkflowTaskInfo {
     /// shared repository, or refcounts, or whatever, for strings like these that get duped all
     /// sorts of places.
     wf_id: String,
+    wf_type: String,
+}
+
+impl WorkflowTaskInfo {
+    fn record_span_fields(&self, span: &Span) {
+        span.record("workflow_id", self.wf_id.as_str());
+        span.record("attempt", self.attempt);
+        span.record("task_token", self.task_token.hashed().as_str());
+    }
 }
 
 #[derive(Debug)]
 enum FailedActivationWFTReport {
-    Report(TaskToken, WorkflowTaskFailedCause, Failure),
+    Report(
+        TaskToken,
+        WorkflowTaskFailedCause,
+        Failure,
+        Option<RunRecovery>,
+    ),
     ReportLegacyQueryFailure(TaskToken, Failure),
 }
 
+/// A run whose cached state core suspects is corrupt, which must be recovered once its task has
+/// been failed. See [Workflows::recover_run].
+#[derive(Debug)]
+struct RunRecovery {
+    workflow_id: WorkflowId,
+    cause: RecoveryCause,
+}
+
+/// Why core suspects the cached state of a run is corrupt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RecoveryCause {
+    /// Applying history or commands to the run's machines failed for a reason other than
+    /// nondeterminism
+    FatalMachineError,
+}
+
+impl RecoveryCause {
+    fn as_str(self) -> &'static str {
+        match self {
+            RecoveryCause::FatalMachineError => "fatal_machine_error",
+        }
+    }
+}
+
 #[derive(Debug)]
 struct ServerCommandsWithWorkflowInfo {
     task_token: TaskToken,
@@ -909,6 +1458,7 @@ impl EvictionRequestResult {
 #[allow(dead_code)] // Not always used in non-test
 pub(crate) struct WorkflowStateInfo {
     pub(crate) cached_workflows: usize,
+    pub(crate) cached_workflow_bytes: usize,
     pub(crate) outstanding_wft: usize,
 }
 
@@ -916,6 +1466,9 @@ pub(crate) struct WorkflowStateInfo {
 struct WFActCompleteMsg {
     completion: ValidatedCompletion,
     response_tx: Option<oneshot::Sender<ActivationCompleteResult>>,
+    /// The encoded size of the completion, if it exceeded the message size warning threshold. The
+    /// warning is logged once the run, and so its workflow type, has been found.
+    size_warning: Option<usize>,
 }
 #[derive(Debug)]
 struct LocalResolutionMsg {
@@ -948,6 +1501,14 @@ pub(crate) struct HeartbeatTimeoutMsg {
 struct GetStateInfoMsg {
     response_tx: oneshot::Sender<WorkflowStateInfo>,
 }
+#[derive(Debug)]
+struct ExportCacheSnapshotMsg {
+    response_tx: oneshot::Sender<Vec<RunSnapshot>>,
+}
+#[derive(Debug)]
+struct ImportCacheSnapshotMsg {
+    runs: Vec<RunSnapshot>,
+}
 
 /// Each activation completion produces one of these
 #[derive(Debug)]
@@ -969,12 +1530,18 @@ enum ActivationCompleteOutcome {
     /// The workflow task failed, but we shouldn't report it. EX: We have failed 2 or more attempts
     /// in a row.
     WFTFailedDontReport,
+    /// The completion is inconsistent with the run it names and was not applied, so the
+    /// activation remains outstanding. The error is returned to lang.
+    Rejected(CompleteWfError),
 }
 /// Did we report, or not, completion of a WFT to server?
 #[derive(Debug, Copy, Clone)]
 enum WFTReportStatus {
     Reported {
         reset_last_started_to: Option<i64>,
+        /// True only if server accepted a completion of the WFT. False if reporting failed, or if
+        /// what was reported was a failure or a legacy query response.
+        completion_accepted: bool,
     },
     /// The WFT completion was not reported when finishing the activation, because there's still
     /// work to be done. EX: Running LAs.
@@ -991,11 +1558,18 @@ struct BufferedTasks {
     /// For query only tasks, multiple may be received concurrently and it's OK to buffer more
     /// than one - however they must all be handled before applying the next "real" wft (after the
     /// current one has been processed).
-    query_only_tasks: VecDeque<PermittedWFT>,
+    query_only_tasks: VecDeque<DeferredQuery>,
     /// These are query-only tasks for the *buffered* wft, if any. They will all be discarded if
     /// a buffered wft is replaced before being handled. They move to `query_only_tasks` once the
     /// buffered task is taken.
-    query_only_tasks_for_buffered: VecDeque<PermittedWFT>,
+    query_only_tasks_for_buffered: VecDeque<DeferredQuery>,
+}
+
+/// A buffered query-only task, and when we started deferring it
+#[derive(Debug)]
+struct DeferredQuery {
+    task: PermittedWFT,
+    deferred_at: Instant,
 }
 
 impl BufferedTasks {
@@ -1005,10 +1579,14 @@ impl BufferedTasks {
     /// one buffered, the old one will be overriden, and all queries will be invalidated.
     fn buffer(&mut self, task: PermittedWFT) {
         if task.work.is_query_only() {
+            let query = DeferredQuery {
+                task,
+                deferred_at: Instant::now(),
+            };
             if self.wft.is_none() {
-                self.query_only_tasks.push_back(task);
+                self.query_only_tasks.push_back(query);
             } else {
-                self.query_only_tasks_for_buffered.push_back(task);
+                self.query_only_tasks_for_buffered.push_back(query);
             }
         } else {
             if self.wft.is_some() {
@@ -1022,12 +1600,56 @@ impl BufferedTasks {
         self.wft.is_some() || !self.query_only_tasks.is_empty()
     }
 
+    /// Returns true if there are query-only tasks which must be handled before the next WFT
+    fn has_query_only_tasks(&self) -> bool {
+        !self.query_only_tasks.is_empty()
+    }
+
+    fn num_query_only_tasks(&self) -> usize {
+        self.query_only_tasks.len() + self.query_only_tasks_for_buffered.len()
+    }
+
+    /// Approximate bytes of history held by the buffered tasks
+    fn approx_retained_bytes(&self) -> usize {
+        self.wft
+            .iter()
+            .chain(self.query_only_tasks.iter().map(|q| &q.task))
+            .chain(self.query_only_tasks_for_buffered.iter().map(|q| &q.task))
+            .map(|t| t.work.update.approx_retained_bytes())
+            .sum()
+    }
+
+    /// Remove and return every buffered task, query-only or not
+    fn take_all(&mut self) -> Vec<PermittedWFT> {
+        let queries = mem::take(&mut self.query_only_tasks)
+            .into_iter()
+            .chain(mem::take(&mut self.query_only_tasks_for_buffered))
+            .map(|q| q.task);
+        self.wft.take().into_iter().chain(queries).collect()
+    }
+
+    /// Remove and return all query-only tasks which have been buffered for at least `timeout`
+    fn take_expired_queries(&mut self, timeout: Duration) -> Vec<PermittedWFT> {
+        let mut expired = vec![];
+        for queue in [
+            &mut self.query_only_tasks,
+            &mut self.query_only_tasks_for_buffered,
+        ] {
+            let (old, remaining) = mem::take(queue)
+                .into_iter()
+                .partition::<VecDeque<_>, _>(|q| q.deferred_at.elapsed() >= timeout);
+            *queue = remaining;
+            expired.extend(old.into_iter().map(|q| q.task));
+        }
+        expired
+    }
+
     /// Remove and return the next WFT from the buffer that should be applied. Queries are returned
     /// first for the current workflow task, if there are any. If not, the next WFT that would
     /// advance history is returned.
     fn get_next_wft(&mut self) -> Option<PermittedWFT> {
         if let Some(q) = self.query_only_tasks.pop_front() {
-            return Some(q);
+            return Some(q.task);
         }
         if let Some(t) = self.wft.take() {
             self.query_only_tasks = mem::take(&mut self.query_only_tasks_for_buffered);
@@ -1041,6 +1663,7 @@ fn validate_completion(
     completion: WorkflowActivationCompletion,
     is_autocomplete: bool,
 ) -> Result<ValidatedCompletion, CompleteWfError> {
+    let correlation_id = completion.correlation_id;
     match completion.status {
         Some(workflow_activation_completion::Status::Successful(success)) => {
             // Convert to wf commands
@@ -1054,6 +1677,7 @@ fn validate_completion(
                              an empty variant"
                         .to_owned(),
                     run_id: completion.run_id.clone(),
+                    correlation_id: correlation_id.clone(),
                 })?;
 
             if commands.len() > 1
@@ -1068,6 +1692,7 @@ fn validate_completion(
                          lang SDK. Commands: {commands:?}"
                     ),
                     run_id: completion.run_id,
+                    correlation_id,
                 });
             }
 
@@ -1075,6 +1700,9 @@ fn validate_completion(
                 run_id: completion.run_id,
                 commands,
                 used_flags: success.used_internal_flags,
+                random_checksum: success.random_checksum,
+                request_reactivation: success.request_reactivation,
+                correlation_id,
             })
         }
         Some(workflow_activation_completion::Status::Failed(failure)) => {
@@ -1082,11 +1710,13 @@ fn validate_completion(
                 run_id: completion.run_id,
                 failure,
                 is_autocomplete,
+                correlation_id,
             })
         }
         None => Err(CompleteWfError::MalformedWorkflowCompletion {
             reason: "Workflow completion had empty status field".to_owned(),
             run_id: completion.run_id,
+            correlation_id,
         }),
     }
 }
@@ -1098,11 +1728,15 @@ enum ValidatedCompletion {
         run_id: String,
         commands: Vec<WFCommand>,
         used_flags: Vec<u32>,
+        random_checksum: u64,
+        request_reactivation: bool,
+        correlation_id: String,
     },
     Fail {
         run_id: String,
         failure: Failure,
         is_autocomplete: bool,
+        correlation_id: String,
     },
 }
 
@@ -1113,9 +1747,17 @@ impl ValidatedCompletion {
             ValidatedCompletion::Fail { run_id, .. } => run_id,
         }
     }
+
+    /// The correlation id lang echoed, empty if it didn't
+    fn correlation_id(&self) -> &str {
+        match self {
+            ValidatedCompletion::Success { correlation_id, .. } => correlation_id,
+            ValidatedCompletion::Fail { correlation_id, .. } => correlation_id,
+        }
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) enum LocalResolution {
     LocalActivity(LocalActivityResolution),
 }
@@ -1135,7 +1777,7 @@ struct EmptyWorkflowCommandErr;
 
 /// [DrivenWorkflow]s respond with these when called, to indicate what they want to do next.
 /// EX: Create a new timer, complete the workflow, etc.
-#[derive(Debug, derive_more::From, derive_more::Display)]
+#[derive(Debug, Clone, derive_more::From, derive_more::Display)]
 #[allow(clippy::large_enum_variant)]
 enum WFCommand {
     /// Returned when we need to wait for the lang sdk to send us something
@@ -1152,6 +1794,7 @@ enum WFCommand {
     ContinueAsNew(ContinueAsNewWorkflowExecution),
     CancelWorkflow(CancelWorkflowExecution),
     SetPatchMarker(SetPatchMarker),
+    RecordSideEffect(RecordSideEffect),
     AddChildWorkflow(StartChildWorkflowExecution),
     CancelChild(CancelChildWorkflowExecution),
     RequestCancelExternalWorkflow(RequestCancelExternalWorkflowExecution),
@@ -1183,6 +1826,7 @@ impl TryFrom<WorkflowCommand> for WFCommand {
             }
             workflow_command::Variant::CancelWorkflowExecution(s) => Ok(Self::CancelWorkflow(s)),
             workflow_command::Variant::SetPatchMarker(s) => Ok(Self::SetPatchMarker(s)),
+            workflow_command::Variant::RecordSideEffect(s) => Ok(Self::RecordSideEffect(s)),
             workflow_command::Variant::StartChildWorkflowExecution(s) => {
                 Ok(Self::AddChildWorkflow(s))
             }
@@ -1244,6 +1888,10 @@ struct WorkflowStartedInfo {
     memo: Option<Memo>,
     search_attrs: Option<SearchAttributes>,
     retry_policy: Option<RetryPolicy>,
+    /// Empty if this is not a cron workflow
+    cron_schedule: String,
+    /// When the whole execution, including any retries and continuations, times out
+    execution_expiration_time: Option<SystemTime>,
 }
 
 /// Wraps outgoing activation job protos with some internal details core might care about
@@ -1291,6 +1939,7 @@ impl WFMachinesError {
                 ),
             ),
             force_cause: WorkflowTaskFailedCause::from(self.evict_reason()) as i32,
+            force_eviction: false,
         }
     }
 }
@@ -1329,6 +1978,7 @@ impl LocalActivityRequestSink for LAReqSink {
 /// activations must uphold.
 ///
 /// ## Job Ordering
+/// See [job_ordinal]. In short:
 /// 1. init workflow
 /// 2. patches
 /// 3. random-seed-updates
@@ -1342,6 +1992,7 @@ impl LocalActivityRequestSink for LAReqSink {
 ///
 /// ## Invariants:
 /// * Queries always go in their own activation
+/// * Evictions always go alone
 fn prepare_to_ship_activation(wfa: &mut WorkflowActivation) {
     let any_job_is_query = wfa.jobs.iter().any(|j| {
         matches!(
@@ -1361,37 +2012,57 @@ fn prepare_to_ship_activation(wfa: &mut WorkflowActivation) {
             &wfa
         );
     }
+    let has_eviction = wfa.jobs.iter().any(|j| {
+        matches!(
+            j.variant,
+            Some(workflow_activation_job::Variant::RemoveFromCache(_))
+        )
+    });
+    if has_eviction && wfa.jobs.len() > 1 {
+        dbg_panic!(
+            "About to issue an activation that contains an eviction with other jobs: {:?}",
+            &wfa
+        );
+    }
+    // The sort is stable, so jobs with the same ordinal (ex: timer fires and activity
+    // resolutions) stay in the order their events appear in history. Lang relies on that to
+    // unblock coroutines in the same order on every replay.
     wfa.jobs.sort_by(|j1, j2| {
         // Unwrapping is fine here since we'll never issue empty variants
         let j1v = j1.variant.as_ref().unwrap();
         let j2v = j2.variant.as_ref().unwrap();
-        fn variant_ordinal(v: &workflow_activation_job::Variant) -> u8 {
-            match v {
-                workflow_activation_job::Variant::InitializeWorkflow(_) => 0,
-                workflow_activation_job::Variant::NotifyHasPatch(_) => 1,
-                workflow_activation_job::Variant::UpdateRandomSeed(_) => 2,
-                workflow_activation_job::Variant::SignalWorkflow(_) => 3,
-                workflow_activation_job::Variant::DoUpdate(_) => 3,
-                workflow_activation_job::Variant::ResolveActivity(ra) if ra.is_local => 5,
-                // In principle we should never actually need to sort these with the others, since
-                // queries always get their own activation, but, maintaining the semantic is
-                // reasonable.
-                workflow_activation_job::Variant::QueryWorkflow(_) => 6,
-                // Also shouldn't ever end up anywhere but the end by construction, but no harm in
-                // double-checking.
-                workflow_activation_job::Variant::RemoveFromCache(_) => 7,
-                _ => 4,
-            }
-        }
-        variant_ordinal(j1v).cmp(&variant_ordinal(j2v))
+        job_ordinal(j1v).cmp(&job_ordinal(j2v))
     });
 }
 
+/// Where a job goes in an activation relative to other kinds of jobs. Lower goes first.
+fn job_ordinal(v: &workflow_activation_job::Variant) -> u8 {
+    match v {
+        workflow_activation_job::Variant::InitializeWorkflow(_) => 0,
+        workflow_activation_job::Variant::NotifyHasPatch(_) => 1,
+        workflow_activation_job::Variant::ResolveSideEffect(_) => 1,
+        workflow_activation_job::Variant::UpdateRandomSeed(_) => 2,
+        workflow_activation_job::Variant::SignalWorkflow(_) => 3,
+        workflow_activation_job::Variant::DoUpdate(_) => 3,
+        workflow_activation_job::Variant::ResolveActivity(ra) if ra.is_local => 5,
+        // In principle we should never actually need to sort these with the others, since
+        // queries always get their own activation, but, maintaining the semantic is
+        // reasonable.
+        workflow_activation_job::Variant::QueryWorkflow(_) => 6,
+        // Also shouldn't ever end up anywhere but the end by construction, but no harm in
+        // double-checking.
+        workflow_activation_job::Variant::RemoveFromCache(_) => 7,
+        _ => 4,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use itertools::Itertools;
-    use temporal_sdk_core_protos::coresdk::workflow_activation::SignalWorkflow;
+    use temporal_sdk_core_protos::coresdk::workflow_activation::{
+        create_evict_activation, SignalWorkflow,
+    };
 
     #[test]
     fn jobs_sort() {
@@ -1481,4 +2152,28 @@ mod tests {
         };
         prepare_to_ship_activation(&mut act);
     }
+
+    #[test]
+    fn evictions_go_alone() {
+        let mut act =
+            create_evict_activation("run".into(), "evict".to_string(), EvictionReason::CacheFull);
+        prepare_to_ship_activation(&mut act);
+        assert!(act.is_only_eviction());
+    }
+
+    #[test]
+    #[should_panic]
+    fn evictions_cannot_go_with_other_jobs() {
+        let mut act =
+            create_evict_activation("run".into(), "evict".to_string(), EvictionReason::CacheFull);
+        act.jobs.insert(
+            0,
+            WorkflowActivationJob {
+                variant: Some(workflow_activation_job::Variant::FireTimer(
+                    Default::default(),
+                )),
+            },
+        );
+        prepare_to_ship_activation(&mut act);
+    }
 }
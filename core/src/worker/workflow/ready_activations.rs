@@ -0,0 +1,168 @@
+//! Activations which are ready to go to lang, but which lang has not yet polled for
+
+use super::{ActivationOrAuto, WorkflowTaskInfo};
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::BinaryHeap,
+    time::{Duration, Instant},
+};
+use temporal_sdk_core_api::worker::ActivationDeliveryOrder;
+
+/// Fraction of its timeout which, if no more than this much remains when an activation for a
+/// workflow task is delivered, counts as a near miss
+const NEAR_DEADLINE_FRACTION: f64 = 0.2;
+
+/// When the workflow task an activation belongs to will time out on server
+#[derive(Debug, Clone, Copy)]
+pub(super) struct WftDeadline {
+    pub(super) deadline: Instant,
+    pub(super) timeout: Duration,
+}
+
+impl WftDeadline {
+    /// True if no more than a small fraction of the task's timeout remains at `now`
+    pub(super) fn is_near(&self, now: Instant) -> bool {
+        self.deadline.saturating_duration_since(now) < self.timeout.mul_f64(NEAR_DEADLINE_FRACTION)
+    }
+}
+
+/// An activation (or automatic action) ready to be delivered, and the deadline and details of the
+/// workflow task it belongs to, if there is one
+#[derive(Debug)]
+pub(super) struct ReadyActivation {
+    pub(super) act: ActivationOrAuto,
+    pub(super) wft_deadline: Option<WftDeadline>,
+    pub(super) wft_info: Option<WorkflowTaskInfo>,
+}
+
+/// Ready activations, delivered in the configured [ActivationDeliveryOrder]
+pub(super) struct ReadyActivations {
+    order: ActivationDeliveryOrder,
+    queue: BinaryHeap<Queued>,
+    next_seq: u64,
+}
+
+struct Queued {
+    /// With [ActivationDeliveryOrder::Fifo] this is always `None`, so only `seq` matters
+    priority: Option<Instant>,
+    seq: u64,
+    ready: ReadyActivation,
+}
+
+impl Queued {
+    /// The heap pops the greatest element, so the key is inverted: earlier deadlines are greater,
+    /// any deadline is greater than none, and earlier arrivals are greater than later ones.
+    fn key(&self) -> (Option<Reverse<Instant>>, Reverse<u64>) {
+        (self.priority.map(Reverse), Reverse(self.seq))
+    }
+}
+
+impl PartialEq for Queued {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+impl Eq for Queued {}
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for Queued {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl ReadyActivations {
+    pub(super) fn new(order: ActivationDeliveryOrder) -> Self {
+        Self {
+            order,
+            queue: BinaryHeap::new(),
+            next_seq: 0,
+        }
+    }
+
+    pub(super) fn push(&mut self, ready: ReadyActivation) {
+        let priority = match self.order {
+            ActivationDeliveryOrder::Fifo => None,
+            ActivationDeliveryOrder::DeadlineFirst => ready.wft_deadline.map(|d| d.deadline),
+        };
+        self.queue.push(Queued {
+            priority,
+            seq: self.next_seq,
+            ready,
+        });
+        self.next_seq += 1;
+    }
+
+    pub(super) fn pop(&mut self) -> Option<ReadyActivation> {
+        self.queue.pop().map(|q| q.ready)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ready(run_id: &str, deadline_in: Option<Duration>, now: Instant) -> ReadyActivation {
+        ReadyActivation {
+            act: ActivationOrAuto::Autocomplete {
+                run_id: run_id.to_string(),
+            },
+            wft_deadline: deadline_in.map(|d| WftDeadline {
+                deadline: now + d,
+                timeout: Duration::from_secs(10),
+            }),
+            wft_info: None,
+        }
+    }
+
+    fn drain(mut ra: ReadyActivations) -> Vec<String> {
+        std::iter::from_fn(|| ra.pop())
+            .map(|r| match r.act {
+                ActivationOrAuto::Autocomplete { run_id } => run_id,
+                _ => unreachable!(),
+            })
+            .collect()
+    }
+
+    fn enqueue(order: ActivationDeliveryOrder) -> ReadyActivations {
+        let now = Instant::now();
+        let mut ra = ReadyActivations::new(order);
+        ra.push(ready("late", Some(Duration::from_secs(9)), now));
+        ra.push(ready("none", None, now));
+        ra.push(ready("soon", Some(Duration::from_secs(1)), now));
+        ra.push(ready("mid", Some(Duration::from_secs(5)), now));
+        ra.push(ready("mid_2", Some(Duration::from_secs(5)), now));
+        ra
+    }
+
+    #[test]
+    fn fifo_ignores_deadlines() {
+        assert_eq!(
+            drain(enqueue(ActivationDeliveryOrder::Fifo)),
+            ["late", "none", "soon", "mid", "mid_2"]
+        );
+    }
+
+    #[test]
+    fn deadline_first_delivers_soonest_deadline_first() {
+        assert_eq!(
+            drain(enqueue(ActivationDeliveryOrder::DeadlineFirst)),
+            ["soon", "mid", "mid_2", "late", "none"]
+        );
+    }
+
+    #[test]
+    fn near_deadline() {
+        let now = Instant::now();
+        let d = WftDeadline {
+            deadline: now + Duration::from_secs(3),
+            timeout: Duration::from_secs(10),
+        };
+        assert!(!d.is_near(now));
+        assert!(d.is_near(now + Duration::from_millis(1500)));
+        assert!(d.is_near(now + Duration::from_secs(5)));
+    }
+}
@@ -0,0 +1,250 @@
+//! Remembers the last completion lang sent for each run. Bindings can lose the result of a
+//! completion (ex: a panic or signal at the FFI boundary) after core applied it, in which case lang
+//! retries it, and core would otherwise reject the retry because the activation it completes is no
+//! longer outstanding.
+//!
+//! Only completions core rejected are checked, so nothing is ever applied twice. An identical
+//! retry is then reported as successful, while a different completion echoing the same activation's
+//! correlation id is still an error. A retry which doesn't echo the correlation id can only be
+//! recognized while none of the run's later activations is outstanding, since otherwise it is
+//! indistinguishable from a completion of that activation.
+//!
+//! Completions are compared whole rather than by a digest of their encoding, since map fields
+//! (ex: headers) don't encode in a deterministic order. To bound what that costs, completions
+//! larger than [MAX_COMPLETION_SIZE] aren't remembered (so retries of them are rejected as
+//! before), and the oldest are forgotten once all remembered ones exceed [MAX_TOTAL_SIZE].
+
+use parking_lot::Mutex;
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+use temporal_sdk_core_protos::coresdk::workflow_completion::WorkflowActivationCompletion;
+
+/// How long after it was applied a completion may be retried
+const WINDOW: Duration = Duration::from_secs(60);
+/// Most runs whose last completion is remembered at once. Beyond this the oldest are forgotten.
+const MAX_TRACKED: usize = 1_000;
+/// Largest encoded completion which is remembered
+pub(super) const MAX_COMPLETION_SIZE: usize = 64 * 1024;
+/// Most encoded bytes of completions remembered at once. Beyond this the oldest are forgotten.
+const MAX_TOTAL_SIZE: usize = 8 * 1024 * 1024;
+
+/// How a rejected completion relates to the last one applied for its run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Retry {
+    /// Identical to the last applied completion, so it succeeded the first time
+    Identical,
+    /// Echoes the correlation id of the activation the last applied completion was for, but
+    /// differs from it
+    Conflicting,
+    /// Unrelated to the last applied completion, or there is none within the window
+    Unrelated,
+}
+
+#[derive(Default)]
+pub(super) struct RecentCompletions {
+    state: Mutex<CompletionsState>,
+}
+
+#[derive(Default)]
+struct CompletionsState {
+    entries: HashMap<String, Entry>,
+    /// Run ids in the order their completions were applied, along with when
+    order: VecDeque<(String, Instant)>,
+    /// Total encoded size of the remembered completions
+    size: usize,
+}
+
+struct Entry {
+    completion: WorkflowActivationCompletion,
+    applied_at: Instant,
+    size: usize,
+}
+
+impl RecentCompletions {
+    /// Whether a completion of this encoded size would be remembered, so callers can avoid
+    /// keeping a copy of those which wouldn't
+    pub(super) fn would_remember(size: usize) -> bool {
+        size <= MAX_COMPLETION_SIZE
+    }
+
+    /// Record that a completion of the given encoded size was applied
+    pub(super) fn record(&self, completion: WorkflowActivationCompletion, size: usize) {
+        self.record_at(completion, size, Instant::now())
+    }
+
+    /// Record that a completion too large to remember was applied to the run, superseding the one
+    /// remembered for it
+    pub(super) fn forget(&self, run_id: &str) {
+        let mut state = self.state.lock();
+        if let Some(prev) = state.entries.remove(run_id) {
+            state.size -= prev.size;
+        }
+    }
+
+    fn record_at(&self, completion: WorkflowActivationCompletion, size: usize, now: Instant) {
+        let mut state = self.state.lock();
+        state.forget_expired(now);
+        let run_id = completion.run_id.clone();
+        // The run's previous completion, if any, is superseded whether or not this one is kept
+        if let Some(prev) = state.entries.remove(&run_id) {
+            state.size -= prev.size;
+        }
+        if !Self::would_remember(size) {
+            return;
+        }
+        state.order.push_back((run_id.clone(), now));
+        state.size += size;
+        state.entries.insert(
+            run_id,
+            Entry {
+                completion,
+                applied_at: now,
+                size,
+            },
+        );
+        while state.entries.len() > MAX_TRACKED || state.size > MAX_TOTAL_SIZE {
+            state.forget_oldest();
+        }
+    }
+
+    /// Determine whether a completion core rejected is a retry of one it already applied
+    pub(super) fn check(&self, completion: &WorkflowActivationCompletion) -> Retry {
+        self.check_at(completion, Instant::now())
+    }
+
+    fn check_at(&self, completion: &WorkflowActivationCompletion, now: Instant) -> Retry {
+        let mut state = self.state.lock();
+        state.forget_expired(now);
+        match state.entries.get(&completion.run_id).map(|e| &e.completion) {
+            Some(applied) if applied == completion => Retry::Identical,
+            Some(applied)
+                if !completion.correlation_id.is_empty()
+                    && completion.correlation_id == applied.correlation_id =>
+            {
+                Retry::Conflicting
+            }
+            _ => Retry::Unrelated,
+        }
+    }
+}
+
+impl CompletionsState {
+    fn forget_expired(&mut self, now: Instant) {
+        while self
+            .order
+            .front()
+            .is_some_and(|(_, at)| now.saturating_duration_since(*at) > WINDOW)
+        {
+            self.forget_oldest();
+        }
+    }
+
+    fn forget_oldest(&mut self) {
+        if let Some((run_id, at)) = self.order.pop_front() {
+            // A later completion of the same run replaces the entry, and is forgotten in its turn
+            if self
+                .entries
+                .get(&run_id)
+                .is_some_and(|e| e.applied_at == at)
+            {
+                if let Some(e) = self.entries.remove(&run_id) {
+                    self.size -= e.size;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use temporal_sdk_core_protos::coresdk::workflow_commands::CompleteWorkflowExecution;
+    use temporal_sdk_core_test_utils::start_timer_cmd;
+
+    fn timer_completion(run_id: &str, seq: u32) -> WorkflowActivationCompletion {
+        WorkflowActivationCompletion::from_cmd(run_id, start_timer_cmd(seq, Duration::from_secs(1)))
+            .with_correlation_id("act-1")
+    }
+
+    #[test]
+    fn retries_are_told_apart_from_other_completions() {
+        let rc = RecentCompletions::default();
+        let now = Instant::now();
+        rc.record_at(timer_completion("run", 1), 1, now);
+        assert_eq!(
+            rc.check_at(&timer_completion("run", 1), now),
+            Retry::Identical
+        );
+        assert_eq!(
+            rc.check_at(&timer_completion("run", 2), now),
+            Retry::Conflicting
+        );
+        let uncorrelated = WorkflowActivationCompletion::from_cmd(
+            "run",
+            CompleteWorkflowExecution { result: None }.into(),
+        );
+        assert_eq!(rc.check_at(&uncorrelated, now), Retry::Unrelated);
+        assert_eq!(
+            rc.check_at(&timer_completion("other-run", 1), now),
+            Retry::Unrelated
+        );
+    }
+
+    #[test]
+    fn completions_are_forgotten_after_the_window() {
+        let rc = RecentCompletions::default();
+        let now = Instant::now();
+        rc.record_at(timer_completion("run", 1), 1, now);
+        // A later completion of the run replaces the earlier one
+        rc.record_at(timer_completion("run", 2), 1, now + WINDOW / 2);
+        let later = now + WINDOW + Duration::from_secs(1);
+        assert_eq!(
+            rc.check_at(&timer_completion("run", 2), later),
+            Retry::Identical
+        );
+        let expired = now + WINDOW / 2 + WINDOW + Duration::from_secs(1);
+        assert_eq!(
+            rc.check_at(&timer_completion("run", 2), expired),
+            Retry::Unrelated
+        );
+        assert!(rc.state.lock().order.is_empty());
+        assert_eq!(rc.state.lock().size, 0);
+    }
+
+    #[test]
+    fn remembered_completions_are_bounded_by_size() {
+        let rc = RecentCompletions::default();
+        let now = Instant::now();
+        rc.record_at(timer_completion("run", 1), 1, now);
+        // Too large to keep, and it supersedes the run's previous completion
+        rc.record_at(timer_completion("run", 2), MAX_COMPLETION_SIZE + 1, now);
+        assert_eq!(
+            rc.check_at(&timer_completion("run", 1), now),
+            Retry::Unrelated
+        );
+        assert_eq!(
+            rc.check_at(&timer_completion("run", 2), now),
+            Retry::Unrelated
+        );
+
+        let runs = MAX_TOTAL_SIZE / MAX_COMPLETION_SIZE + 1;
+        for i in 0..runs {
+            rc.record_at(
+                timer_completion(&format!("run-{i}"), 1),
+                MAX_COMPLETION_SIZE,
+                now,
+            );
+        }
+        assert!(rc.state.lock().size <= MAX_TOTAL_SIZE);
+        assert_eq!(
+            rc.check_at(&timer_completion("run-0", 1), now),
+            Retry::Unrelated
+        );
+        assert_eq!(
+            rc.check_at(&timer_completion(&format!("run-{}", runs - 1), 1), now),
+            Retry::Identical
+        );
+    }
+}
@@ -0,0 +1,185 @@
+//! Recognizing completions server rejected because a command's attributes were invalid. Server
+//! answers those with `InvalidArgument`, naming the cause (ex: `BadScheduleActivityAttributes`)
+//! at the start of its message. Such a completion can never succeed, so rather than evicting the
+//! run as for any other reporting failure, the task is failed with that cause and lang is told
+//! which command was rejected.
+
+use temporal_sdk_core_protos::temporal::api::enums::v1::{CommandType, WorkflowTaskFailedCause};
+
+/// Every cause server reports for invalid command attributes, and the types of command it may be
+/// about. Besides the `BadXxxAttributes` causes, this includes `BadSignalInputSize`, since a
+/// signal whose input is too large is rejected the same way and can never succeed either. Search
+/// attributes are validated for every command which carries them.
+const BAD_ATTRIBUTES_CAUSES: [(WorkflowTaskFailedCause, &[CommandType]); 17] = [
+    (
+        WorkflowTaskFailedCause::BadScheduleActivityAttributes,
+        &[CommandType::ScheduleActivityTask],
+    ),
+    (
+        WorkflowTaskFailedCause::BadRequestCancelActivityAttributes,
+        &[CommandType::RequestCancelActivityTask],
+    ),
+    (
+        WorkflowTaskFailedCause::BadStartTimerAttributes,
+        &[CommandType::StartTimer],
+    ),
+    (
+        WorkflowTaskFailedCause::BadCancelTimerAttributes,
+        &[CommandType::CancelTimer],
+    ),
+    (
+        WorkflowTaskFailedCause::BadRecordMarkerAttributes,
+        &[CommandType::RecordMarker],
+    ),
+    (
+        WorkflowTaskFailedCause::BadCompleteWorkflowExecutionAttributes,
+        &[CommandType::CompleteWorkflowExecution],
+    ),
+    (
+        WorkflowTaskFailedCause::BadFailWorkflowExecutionAttributes,
+        &[CommandType::FailWorkflowExecution],
+    ),
+    (
+        WorkflowTaskFailedCause::BadCancelWorkflowExecutionAttributes,
+        &[CommandType::CancelWorkflowExecution],
+    ),
+    (
+        WorkflowTaskFailedCause::BadRequestCancelExternalWorkflowExecutionAttributes,
+        &[CommandType::RequestCancelExternalWorkflowExecution],
+    ),
+    (
+        WorkflowTaskFailedCause::BadContinueAsNewAttributes,
+        &[CommandType::ContinueAsNewWorkflowExecution],
+    ),
+    (
+        WorkflowTaskFailedCause::BadSignalWorkflowExecutionAttributes,
+        &[CommandType::SignalExternalWorkflowExecution],
+    ),
+    (
+        WorkflowTaskFailedCause::BadSignalInputSize,
+        &[CommandType::SignalExternalWorkflowExecution],
+    ),
+    (
+        WorkflowTaskFailedCause::BadStartChildExecutionAttributes,
+        &[CommandType::StartChildWorkflowExecution],
+    ),
+    (
+        WorkflowTaskFailedCause::BadModifyWorkflowPropertiesAttributes,
+        &[CommandType::ModifyWorkflowProperties],
+    ),
+    (
+        WorkflowTaskFailedCause::BadSearchAttributes,
+        &[
+            CommandType::UpsertWorkflowSearchAttributes,
+            CommandType::StartChildWorkflowExecution,
+            CommandType::ContinueAsNewWorkflowExecution,
+        ],
+    ),
+    (
+        WorkflowTaskFailedCause::BadScheduleNexusOperationAttributes,
+        &[CommandType::ScheduleNexusOperation],
+    ),
+    (
+        WorkflowTaskFailedCause::BadRequestCancelNexusOperationAttributes,
+        &[CommandType::RequestCancelNexusOperation],
+    ),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct RejectedCommand {
+    pub(super) cause: WorkflowTaskFailedCause,
+    /// Index of the rejected command among those sent. Server doesn't say which command it means,
+    /// so this is only known if just one command of the types the cause may be about was sent.
+    pub(super) command_index: Option<usize>,
+    pub(super) message: String,
+}
+
+impl RejectedCommand {
+    /// Recognize a rejection of command attributes in the error server answered a completion of
+    /// the commands with types `sent` with
+    pub(super) fn from_status(status: &tonic::Status, sent: &[CommandType]) -> Option<Self> {
+        if status.code() != tonic::Code::InvalidArgument {
+            return None;
+        }
+        let message = status.message();
+        let named = message
+            .split(|c: char| c == ':' || c.is_whitespace())
+            .next()?;
+        let (cause, command_types) = BAD_ATTRIBUTES_CAUSES
+            .iter()
+            .find(|(cause, _)| format!("{cause:?}") == named || cause.as_str_name() == named)?;
+        let mut of_type = sent
+            .iter()
+            .enumerate()
+            .filter(|(_, ct)| command_types.contains(ct))
+            .map(|(i, _)| i);
+        let command_index = match (of_type.next(), of_type.next()) {
+            (Some(i), None) => Some(i),
+            _ => None,
+        };
+        Some(Self {
+            cause: *cause,
+            command_index,
+            message: message.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_bad_attributes_causes() {
+        let sent = [
+            CommandType::StartTimer,
+            CommandType::ScheduleActivityTask,
+            CommandType::StartTimer,
+        ];
+        let rejected =
+            |msg: &str| RejectedCommand::from_status(&tonic::Status::invalid_argument(msg), &sent);
+        assert_eq!(
+            rejected("BadScheduleActivityAttributes: ActivityId is not set on command."),
+            Some(RejectedCommand {
+                cause: WorkflowTaskFailedCause::BadScheduleActivityAttributes,
+                command_index: Some(1),
+                message: "BadScheduleActivityAttributes: ActivityId is not set on command."
+                    .to_string(),
+            })
+        );
+        // Two timers were sent, so which one is meant can't be told
+        assert_matches!(
+            rejected("WORKFLOW_TASK_FAILED_CAUSE_BAD_START_TIMER_ATTRIBUTES: bad timeout"),
+            Some(RejectedCommand {
+                cause: WorkflowTaskFailedCause::BadStartTimerAttributes,
+                command_index: None,
+                ..
+            })
+        );
+        assert_eq!(rejected("UnhandledCommand"), None);
+        let rejected = |msg: &str| {
+            RejectedCommand::from_status(
+                &tonic::Status::invalid_argument(msg),
+                &[
+                    CommandType::StartTimer,
+                    CommandType::UpsertWorkflowSearchAttributes,
+                ],
+            )
+        };
+        assert_matches!(
+            rejected("BadSearchAttributes: search attribute CustomKeyword is not defined"),
+            Some(RejectedCommand {
+                cause: WorkflowTaskFailedCause::BadSearchAttributes,
+                command_index: Some(1),
+                ..
+            })
+        );
+        assert_eq!(
+            RejectedCommand::from_status(
+                &tonic::Status::internal("BadStartTimerAttributes: oops"),
+                &sent
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,220 @@
+//! Bounding how many runs are rebuilt from history at once. When a worker restarts with a large
+//! backlog (ex: the tasks of its lost sticky queue, redirected to the normal one), every task for a
+//! run it hasn't cached needs that run's history fetched and replayed. Doing dozens of those at
+//! once can saturate the CPU, making tasks time out on server, which then only retries them.
+//! See [temporal_sdk_core_api::worker::WorkerConfig::max_concurrent_history_replays].
+//!
+//! A run holds a replay slot from when its task is admitted until it has caught up with its
+//! history (or leaves the cache). Tasks arriving while every slot is taken are queued. Any which
+//! wait longer than their workflow task timeout are dropped rather than replayed, since server will
+//! already have given up on them and scheduled a retry.
+
+use super::PreparedWFT;
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Duration,
+};
+use temporal_sdk_core_protos::{
+    temporal::api::history::v1::history_event, utilities::TryIntoOrNone,
+};
+use tokio::time::Instant;
+
+/// True if a task for a run which isn't cached will have to replay history to be processed
+pub(super) fn needs_replay(work: &PreparedWFT) -> bool {
+    work.is_incremental() || work.update.previous_wft_started_id > 0
+}
+
+/// The workflow task timeout of the run a task is for, if its history includes the run's start
+pub(super) fn task_timeout(work: &PreparedWFT) -> Option<Duration> {
+    match &work.update.get_events().first()?.attributes {
+        Some(history_event::Attributes::WorkflowExecutionStartedEventAttributes(attrs)) => {
+            attrs.workflow_task_timeout.try_into_or_none()
+        }
+        _ => None,
+    }
+}
+
+/// Hands out replay slots to runs, and queues the tasks of runs which must wait for one
+pub(super) struct ReplayLimiter<T> {
+    max_replays: Option<usize>,
+    /// Runs holding a slot, and whether they are still waiting on a fetch of their history
+    admitted: HashMap<String, bool>,
+    /// Tasks waiting for a slot, oldest first
+    queued: VecDeque<Queued<T>>,
+}
+
+struct Queued<T> {
+    run_id: String,
+    task: T,
+    expires_at: Option<Instant>,
+}
+
+impl<T> ReplayLimiter<T> {
+    /// Create a limiter allowing up to `max_replays` at once, or any number if unset
+    pub(super) fn new(max_replays: Option<usize>) -> Self {
+        Self {
+            max_replays,
+            admitted: Default::default(),
+            queued: Default::default(),
+        }
+    }
+
+    /// Returns true if the run may replay, taking a slot for it if it didn't already have one.
+    /// Runs arriving while others are queued wait behind them.
+    pub(super) fn try_admit(&mut self, run_id: &str) -> bool {
+        let Some(max_replays) = self.max_replays else {
+            return true;
+        };
+        if self.admitted.contains_key(run_id) {
+            return true;
+        }
+        if self.admitted.len() >= max_replays || !self.queued.is_empty() {
+            return false;
+        }
+        self.admitted.insert(run_id.to_string(), false);
+        true
+    }
+
+    /// Queue a task which was refused a slot. It expires once it has waited `timeout`, if set, in
+    /// which case the time it expires at is returned.
+    pub(super) fn queue(
+        &mut self,
+        run_id: String,
+        task: T,
+        timeout: Option<Duration>,
+        now: Instant,
+    ) -> Option<Instant> {
+        let expires_at = timeout.map(|t| now + t);
+        self.queued.push_back(Queued {
+            run_id,
+            task,
+            expires_at,
+        });
+        expires_at
+    }
+
+    /// Record whether an admitted run is waiting on a fetch of its history. Fetching runs keep
+    /// their slot regardless of what [Self::release_finished] is told.
+    pub(super) fn set_fetching(&mut self, run_id: &str, fetching: bool) {
+        if let Some(f) = self.admitted.get_mut(run_id) {
+            *f = fetching;
+        }
+    }
+
+    /// Give up the run's slot, if it has one
+    pub(super) fn release(&mut self, run_id: &str) {
+        self.admitted.remove(run_id);
+    }
+
+    /// Frees the slots of runs which aren't fetching history and for which `still_replaying`
+    /// returns false
+    pub(super) fn release_finished(&mut self, mut still_replaying: impl FnMut(&str) -> bool) {
+        self.admitted
+            .retain(|run_id, fetching| *fetching || still_replaying(run_id));
+    }
+
+    /// Removes and returns queued tasks which have waited past their timeout as of `now`
+    pub(super) fn take_expired(&mut self, now: Instant) -> Vec<T> {
+        let (expired, waiting) = std::mem::take(&mut self.queued)
+            .into_iter()
+            .partition::<VecDeque<_>, _>(|q| q.expires_at.is_some_and(|e| e <= now));
+        self.queued = waiting;
+        expired.into_iter().map(|q| q.task).collect()
+    }
+
+    /// Admits queued runs, oldest first, while there are free slots, and returns their tasks. All
+    /// of a run's queued tasks are returned together, in the order they arrived.
+    pub(super) fn admit_queued(&mut self) -> Vec<T> {
+        let max_replays = self.max_replays.unwrap_or(usize::MAX);
+        let mut admitted = vec![];
+        while self.admitted.len() < max_replays {
+            let Some(next) = self.queued.pop_front() else {
+                break;
+            };
+            let (same_run, rest) = std::mem::take(&mut self.queued)
+                .into_iter()
+                .partition::<Vec<_>, _>(|q| q.run_id == next.run_id);
+            self.queued = rest;
+            self.admitted.insert(next.run_id, false);
+            admitted.push(next.task);
+            admitted.extend(same_run.into_iter().map(|q| q.task));
+        }
+        admitted
+    }
+
+    /// Removes every queued task, with the run it is for
+    pub(super) fn take_all_queued(&mut self) -> impl Iterator<Item = (String, T)> {
+        std::mem::take(&mut self.queued)
+            .into_iter()
+            .map(|q| (q.run_id, q.task))
+    }
+
+    /// Number of tasks waiting for a slot
+    pub(super) fn queue_len(&self) -> usize {
+        self.queued.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queued_runs_wait_for_a_free_slot_in_order() {
+        let mut limiter = ReplayLimiter::new(Some(1));
+        let now = Instant::now();
+        assert!(limiter.try_admit("a"));
+        assert!(limiter.try_admit("a"));
+        assert!(!limiter.try_admit("b"));
+        limiter.queue("b".to_string(), "b1", None, now);
+        assert!(!limiter.try_admit("c"));
+        limiter.queue("c".to_string(), "c1", None, now);
+        limiter.queue("b".to_string(), "b2", None, now);
+        assert!(limiter.admit_queued().is_empty());
+
+        // A run still fetching its history keeps its slot
+        limiter.set_fetching("a", true);
+        limiter.release_finished(|_| false);
+        assert!(limiter.admit_queued().is_empty());
+        limiter.set_fetching("a", false);
+        limiter.release_finished(|_| true);
+        assert!(limiter.admit_queued().is_empty());
+
+        limiter.release_finished(|_| false);
+        assert_eq!(limiter.admit_queued(), ["b1", "b2"]);
+        assert_eq!(limiter.queue_len(), 1);
+        limiter.release("b");
+        assert_eq!(limiter.admit_queued(), ["c1"]);
+        assert_eq!(limiter.queue_len(), 0);
+    }
+
+    #[test]
+    fn tasks_queued_past_their_timeout_expire() {
+        let mut limiter = ReplayLimiter::new(Some(1));
+        let now = Instant::now();
+        assert!(limiter.try_admit("a"));
+        assert_eq!(
+            limiter.queue("b".to_string(), "b", Some(Duration::from_secs(5)), now),
+            Some(now + Duration::from_secs(5))
+        );
+        limiter.queue("c".to_string(), "c", Some(Duration::from_secs(10)), now);
+        assert_eq!(limiter.queue("d".to_string(), "d", None, now), None);
+
+        assert!(limiter
+            .take_expired(now + Duration::from_secs(1))
+            .is_empty());
+        assert_eq!(limiter.take_expired(now + Duration::from_secs(6)), ["b"]);
+        assert_eq!(limiter.take_expired(now + Duration::from_secs(3600)), ["c"]);
+        assert_eq!(
+            limiter.take_all_queued().collect::<Vec<_>>(),
+            [("d".to_string(), "d")]
+        );
+    }
+
+    #[test]
+    fn unlimited_admits_everything() {
+        let mut limiter = ReplayLimiter::<()>::new(None);
+        assert!((0..100).all(|i| limiter.try_admit(&i.to_string())));
+        assert!(limiter.admitted.is_empty());
+    }
+}
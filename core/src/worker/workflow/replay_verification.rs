@@ -0,0 +1,307 @@
+//! Checking, before a workflow task completion is sent, that the run would replay to the same
+//! commands. See [temporal_sdk_core_api::worker::WorkerConfig::verify_completions_by_replay].
+//!
+//! Everything lang and the worker fed the run's machines is recorded: the jobs of each activation
+//! lang was given, the commands it answered with, and local activity resolutions, along with the
+//! protocol messages of the current task. To verify a completion, fresh machines are built from the
+//! history the run has processed, exactly as if it had been evicted and polled again. Each
+//! activation they produce is compared against the one lang was given at that point and answered
+//! with the commands lang sent for it, and the commands they end up with must equal the ones about
+//! to be sent.
+//!
+//! Lang isn't involved, so workflow code is never run twice. What is caught is any way the run
+//! could only have reached its commands with cached state: activations which replay would shape
+//! differently, commands sent in answer to activations which held only queries (replay never
+//! delivers those), and machines which have diverged from what the same history produces. When the
+//! fresh machines replay a task which ran local activities, their results come from the markers in
+//! history rather than the recorded resolutions, just as they would on a real replay.
+//!
+//! Every verification replays from the first event, so its cost grows with the run. Runs whose
+//! history or recorded inputs outgrow [MAX_VERIFIED_EVENTS] or [MAX_RECORDED_INPUTS] stop being
+//! verified, and what was recorded for them is dropped.
+
+use crate::{
+    protosext::protocol_messages::IncomingProtocolMessage,
+    telemetry::VecDisplayer,
+    worker::workflow::{
+        machines::WorkflowMachines, DrivenWorkflow, HistoryUpdate, LocalResolution, RunBasics,
+        WFCommand, WFMachinesError,
+    },
+    MetricsContext,
+};
+use std::{sync::Arc, time::Instant};
+use temporal_sdk_core_api::worker::WorkerConfig;
+use temporal_sdk_core_protos::{
+    coresdk::workflow_activation::{workflow_activation_job, WorkflowActivation},
+    temporal::api::{
+        command::v1::Command as ProtoCommand,
+        history::v1::{history_event, HistoryEvent},
+        workflowservice::v1::get_system_info_response,
+    },
+};
+
+/// Runs with longer histories than this are no longer verified
+pub(super) const MAX_VERIFIED_EVENTS: usize = 5_000;
+/// Runs for which more inputs than this have been recorded are no longer verified
+pub(super) const MAX_RECORDED_INPUTS: usize = 5_000;
+
+pub(super) struct ReplayVerifier {
+    /// The run's configuration, with everything which would make the fresh machines do more than
+    /// replay turned off
+    worker_config: Arc<WorkerConfig>,
+    workflow_id: String,
+    workflow_type: String,
+    run_id: String,
+    capabilities: get_system_info_response::Capabilities,
+    metrics: MetricsContext,
+    /// Protocol messages which came with the run's current workflow task
+    messages: Vec<IncomingProtocolMessage>,
+    /// Jobs of the activation lang is working on, if it is one lang was given
+    outstanding_jobs: Option<ActivationJobs>,
+    inputs: Vec<RecordedInput>,
+    /// Set once the run can no longer be verified, after which nothing more is recorded
+    stopped: bool,
+}
+
+enum RecordedInput {
+    /// Lang answered an activation holding these jobs with these commands
+    Completion {
+        jobs: ActivationJobs,
+        commands: Vec<WFCommand>,
+    },
+    LocalResolution(LocalResolution),
+}
+
+/// The jobs of an activation which replay could deliver again
+struct ActivationJobs {
+    /// Every job other than queries and evictions, as displayed
+    replayable: Vec<String>,
+    /// Set if the activation held queries and nothing replayable
+    only_queries: bool,
+}
+
+impl ActivationJobs {
+    fn of(act: &WorkflowActivation) -> Self {
+        let mut replayable = vec![];
+        let mut had_queries = false;
+        for variant in act.jobs.iter().filter_map(|j| j.variant.as_ref()) {
+            match variant {
+                workflow_activation_job::Variant::QueryWorkflow(_) => had_queries = true,
+                workflow_activation_job::Variant::RemoveFromCache(_) => {}
+                v => replayable.push(v.to_string()),
+            }
+        }
+        Self {
+            only_queries: had_queries && replayable.is_empty(),
+            replayable,
+        }
+    }
+}
+
+impl ReplayVerifier {
+    pub(super) fn new(basics: &RunBasics) -> Self {
+        let worker_config = WorkerConfig {
+            verify_completions_by_replay: false,
+            retain_history_for_handover: false,
+            lifecycle_observer: None,
+            ..(*basics.worker_config).clone()
+        };
+        Self {
+            worker_config: Arc::new(worker_config),
+            workflow_id: basics.workflow_id.clone(),
+            workflow_type: basics.workflow_type.clone(),
+            run_id: basics.run_id.clone(),
+            capabilities: *basics.capabilities,
+            metrics: basics.metrics.clone(),
+            messages: vec![],
+            outstanding_jobs: None,
+            inputs: vec![],
+            stopped: false,
+        }
+    }
+
+    /// True once the run is no longer being verified
+    pub(super) fn stopped(&self) -> bool {
+        self.stopped
+    }
+
+    fn stop(&mut self, why: &str) {
+        debug!(run_id = %self.run_id, "No longer verifying completions by replay: {why}");
+        self.stopped = true;
+        self.outstanding_jobs = None;
+        self.messages = vec![];
+        self.inputs = vec![];
+    }
+
+    fn record(&mut self, input: RecordedInput) {
+        if self.stopped {
+            return;
+        }
+        self.inputs.push(input);
+        if self.inputs.len() > MAX_RECORDED_INPUTS {
+            self.stop("too many inputs have been recorded");
+        }
+    }
+
+    pub(super) fn new_task(&mut self, messages: &[IncomingProtocolMessage]) {
+        if !self.stopped {
+            self.messages = messages.to_vec();
+        }
+    }
+
+    pub(super) fn activation_issued(&mut self, act: &WorkflowActivation) {
+        if !self.stopped {
+            self.outstanding_jobs = Some(ActivationJobs::of(act));
+        }
+    }
+
+    /// Record commands sent to the machines. Those which aren't lang's answer to an activation it
+    /// was given (ex: autocompletions) aren't, since replay has no counterpart to them.
+    pub(super) fn commands_pushed(&mut self, commands: &[WFCommand]) {
+        if let Some(jobs) = self.outstanding_jobs.take() {
+            self.record(RecordedInput::Completion {
+                jobs,
+                commands: commands.to_vec(),
+            });
+        }
+    }
+
+    pub(super) fn local_resolution(&mut self, resolution: &LocalResolution) {
+        self.record(RecordedInput::LocalResolution(resolution.clone()));
+    }
+
+    /// Replay the run against fresh machines, failing if they don't arrive at `sending`. Passes
+    /// without replaying if the run is no longer being verified, or stops being verified now.
+    pub(super) fn verify(
+        &mut self,
+        live: &WorkflowMachines,
+        sending: &[ProtoCommand],
+    ) -> Result<(), WFMachinesError> {
+        if self.stopped {
+            return Ok(());
+        }
+        let Some(events) = live.retained_history() else {
+            return Ok(());
+        };
+        if events.first().map(|e| e.event_id) != Some(1) {
+            self.stop("the run was picked up from a cache snapshot without its history");
+            return Ok(());
+        }
+        if events.len() > MAX_VERIFIED_EVENTS {
+            self.stop("its history is too long");
+            return Ok(());
+        }
+        let started = Instant::now();
+        let res = self.replay(events, live.get_last_wft_started_id());
+        self.metrics
+            .wf_completion_verification_latency(started.elapsed());
+        let replayed = res.map_err(|e| {
+            WFMachinesError::Nondeterminism(format!("Replay verification failed: {e}"))
+        })?;
+        if replayed.as_slice() != sending {
+            return Err(WFMachinesError::Nondeterminism(format!(
+                "Replay verification failed: replaying the run produces commands {} but the \
+                 completion would send {}",
+                replayed.display(),
+                sending.display()
+            )));
+        }
+        Ok(())
+    }
+
+    fn replay(
+        &self,
+        events: &[HistoryEvent],
+        wft_started_id: i64,
+    ) -> Result<Vec<ProtoCommand>, String> {
+        let (driven, sink) = DrivenWorkflow::new();
+        let mut fresh = WorkflowMachines::new(
+            RunBasics {
+                worker_config: self.worker_config.clone(),
+                workflow_id: self.workflow_id.clone(),
+                workflow_type: self.workflow_type.clone(),
+                run_id: self.run_id.clone(),
+                history: HistoryUpdate::dummy(),
+                metrics: MetricsContext::no_op(),
+                capabilities: &self.capabilities,
+            },
+            driven,
+        );
+        let (update, _) = HistoryUpdate::from_events(
+            events.to_vec(),
+            previous_completed_wft_started_id(events, wft_started_id),
+            wft_started_id,
+            true,
+        );
+        fresh
+            .new_work_from_server(update, self.messages.clone())
+            .map_err(|e| e.to_string())?;
+        for (i, input) in self.inputs.iter().enumerate() {
+            match input {
+                RecordedInput::Completion { jobs, commands } => {
+                    if jobs.only_queries {
+                        if !commands.is_empty() {
+                            return Err(format!(
+                                "lang answered an activation holding only queries, which replay \
+                                 never delivers, with commands {}",
+                                commands.display()
+                            ));
+                        }
+                        continue;
+                    }
+                    if !fresh.has_pending_jobs() {
+                        fresh
+                            .apply_next_wft_from_history()
+                            .map_err(|e| e.to_string())?;
+                    }
+                    let replayed = ActivationJobs::of(&fresh.get_wf_activation());
+                    if replayed.replayable != jobs.replayable {
+                        return Err(format!(
+                            "lang was given an activation with jobs {:?}, but replay gives it {:?} \
+                             instead",
+                            jobs.replayable, replayed.replayable
+                        ));
+                    }
+                    sink.send(commands.clone())
+                        .map_err(|_| "Fresh machines dropped their command sink".to_string())?;
+                    fresh.iterate_machines().map_err(|e| e.to_string())?;
+                    loop {
+                        let consumed = fresh
+                            .apply_next_wft_from_history()
+                            .map_err(|e| e.to_string())?;
+                        if consumed == 0 || !fresh.replaying || fresh.has_pending_jobs() {
+                            break;
+                        }
+                    }
+                    // Nothing is executed, the recorded resolutions stand in for the results
+                    fresh.drain_queued_local_activities();
+                }
+                RecordedInput::LocalResolution(resolution) => {
+                    if !fresh.replaying {
+                        fresh
+                            .local_resolution(resolution.clone())
+                            .map_err(|e| e.to_string())?;
+                    }
+                }
+            }
+        }
+        let replayed = fresh.prepare_for_wft_response().commands().collect();
+        Ok(replayed)
+    }
+}
+
+/// The started event id of the last workflow task completed before the one started at
+/// `wft_started_id`, which is what a full history poll for that task would report
+fn previous_completed_wft_started_id(events: &[HistoryEvent], wft_started_id: i64) -> i64 {
+    events
+        .iter()
+        .rev()
+        .filter(|e| e.event_id < wft_started_id)
+        .find_map(|e| match &e.attributes {
+            Some(history_event::Attributes::WorkflowTaskCompletedEventAttributes(a)) => {
+                Some(a.started_event_id)
+            }
+            _ => None,
+        })
+        .unwrap_or_default()
+}
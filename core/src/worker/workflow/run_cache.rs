@@ -3,23 +3,43 @@ use crate::{
     worker::workflow::{
         managed_run::{ManagedRun, RunUpdateAct},
         HistoryUpdate, LocalActivityRequestSink, PermittedWFT, RequestEvictMsg, RunBasics,
+        RunStatsRegistry,
     },
     MetricsContext,
 };
 use lru::LruCache;
-use std::{num::NonZeroUsize, rc::Rc, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    num::NonZeroUsize,
+    rc::Rc,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use temporal_sdk_core_api::worker::WorkerConfig;
 use temporal_sdk_core_protos::{
     coresdk::workflow_activation::remove_from_cache::EvictionReason,
-    temporal::api::workflowservice::v1::get_system_info_response,
+    temporal::api::workflowservice::v1::get_system_info_response, RunId,
 };
 
+/// A run coming back into the cache within this long of being evicted to make room counts as
+/// cache thrash
+const CACHE_THRASH_WINDOW: Duration = Duration::from_secs(60);
+
 pub(super) struct RunCache {
     worker_config: Arc<WorkerConfig>,
     server_capabilities: get_system_info_response::Capabilities,
     /// Run id -> Data
-    runs: LruCache<String, ManagedRun>,
+    runs: LruCache<RunId, ManagedRun>,
     local_activity_request_sink: Rc<dyn LocalActivityRequestSink>,
+    /// Runs recently evicted because the cache was full, oldest first, with when they were evicted
+    recent_cache_full_evictions: VecDeque<(RunId, Instant)>,
+    /// Run id -> approximate bytes retained by the run, as of the last [Self::refresh_sizes]
+    run_bytes: HashMap<RunId, usize>,
+    /// Sum of `run_bytes`
+    total_bytes: usize,
+    /// Runs which may have changed size since sizes were last refreshed
+    possibly_resized: HashSet<RunId>,
+    run_stats: RunStatsRegistry,
 
     metrics: MetricsContext,
 }
@@ -29,6 +49,7 @@ impl RunCache {
         worker_config: Arc<WorkerConfig>,
         server_capabilities: get_system_info_response::Capabilities,
         local_activity_request_sink: impl LocalActivityRequestSink,
+        run_stats: RunStatsRegistry,
         metrics: MetricsContext,
     ) -> Self {
         // The cache needs room for at least one run, otherwise we couldn't do anything. In
@@ -45,37 +66,48 @@ impl RunCache {
                 NonZeroUsize::new(lru_size).expect("LRU size is guaranteed positive"),
             ),
             local_activity_request_sink: Rc::new(local_activity_request_sink),
+            recent_cache_full_evictions: Default::default(),
+            run_bytes: Default::default(),
+            total_bytes: 0,
+            possibly_resized: Default::default(),
+            run_stats,
             metrics,
         }
     }
 
     pub(super) fn instantiate_or_update(&mut self, pwft: PermittedWFT) -> RunUpdateAct {
         let cur_num_cached_runs = self.runs.len();
-        let run_id = pwft.work.execution.run_id.clone();
+        let run_id = RunId::from(pwft.work.execution.run_id.as_str());
 
+        self.possibly_resized.insert(run_id.clone());
         if let Some(run_handle) = self.runs.get_mut(&run_id) {
             let rur = run_handle.incoming_wft(pwft);
             self.metrics.cache_size(cur_num_cached_runs as u64);
             return rur;
         }
 
+        self.record_if_thrash(run_id.as_str());
         // Create a new workflow machines instance for this workflow, initialize it, and
         // track it.
         let metrics = self
             .metrics
             .with_new_attrs([workflow_type(pwft.work.workflow_type.clone())]);
+        let stats = self
+            .run_stats
+            .register(&pwft.work.execution.workflow_id, run_id.as_str());
         let (mrh, rur) = ManagedRun::new(
             RunBasics {
                 worker_config: self.worker_config.clone(),
                 workflow_id: pwft.work.execution.workflow_id.clone(),
                 workflow_type: pwft.work.workflow_type.clone(),
-                run_id: run_id.clone(),
+                run_id: run_id.to_string(),
                 history: HistoryUpdate::dummy(),
                 metrics,
                 capabilities: &self.server_capabilities,
             },
             pwft,
             self.local_activity_request_sink.clone(),
+            stats,
         );
         if self.runs.push(run_id, mrh).is_some() {
             panic!("Overflowed run cache! Cache owner is expected to avoid this!");
@@ -87,7 +119,23 @@ impl RunCache {
     pub(super) fn remove(&mut self, k: &str) -> Option<ManagedRun> {
         let r = self.runs.pop(k);
         self.metrics.cache_size(self.len() as u64);
+        self.run_stats.remove(k);
+        self.possibly_resized.remove(k);
+        if let Some(bytes) = self.run_bytes.remove(k) {
+            self.total_bytes -= bytes;
+            self.metrics.cache_bytes(self.total_bytes as u64);
+        }
         if let Some(rh) = &r {
+            if matches!(
+                rh.trying_to_evict(),
+                Some(RequestEvictMsg {
+                    reason: EvictionReason::CacheFull,
+                    ..
+                })
+            ) {
+                self.recent_cache_full_evictions
+                    .push_back((k.into(), Instant::now()));
+            }
             // A workflow completing normally doesn't count as a forced eviction.
             if !matches!(
                 rh.trying_to_evict(),
@@ -102,8 +150,68 @@ impl RunCache {
         r
     }
 
+    fn record_if_thrash(&mut self, run_id: &str) {
+        while self
+            .recent_cache_full_evictions
+            .front()
+            .is_some_and(|(_, evicted_at)| evicted_at.elapsed() > CACHE_THRASH_WINDOW)
+        {
+            self.recent_cache_full_evictions.pop_front();
+        }
+        if let Some(ix) = self
+            .recent_cache_full_evictions
+            .iter()
+            .position(|(rid, _)| rid == run_id)
+        {
+            self.recent_cache_full_evictions.remove(ix);
+            self.metrics.cache_thrash();
+        }
+    }
+
     pub(super) fn get_mut(&mut self, k: &str) -> Option<&mut ManagedRun> {
-        self.runs.get_mut(k)
+        let r = self.runs.get_mut(k);
+        if r.is_some() && !self.possibly_resized.contains(k) {
+            self.possibly_resized.insert(k.into());
+        }
+        r
+    }
+
+    /// Recompute the sizes of runs which may have changed since the last refresh. Only runs handed
+    /// out mutably can have changed, so this costs little more than the changes themselves.
+    pub(super) fn refresh_sizes(&mut self) {
+        if self.possibly_resized.is_empty() {
+            return;
+        }
+        let before = self.total_bytes;
+        for run_id in self.possibly_resized.drain() {
+            let Some(run) = self.runs.peek(&run_id) else {
+                continue;
+            };
+            let bytes = run.approx_retained_bytes();
+            let old = self.run_bytes.insert(run_id, bytes).unwrap_or_default();
+            self.total_bytes = self.total_bytes - old + bytes;
+        }
+        if self.total_bytes != before {
+            self.metrics.cache_bytes(self.total_bytes as u64);
+        }
+    }
+
+    /// Approximate bytes retained by the run, as of the last refresh
+    pub(super) fn run_bytes(&self, k: &str) -> usize {
+        self.run_bytes.get(k).copied().unwrap_or_default()
+    }
+
+    /// Approximate bytes retained by all cached runs, as of the last refresh
+    pub(super) fn total_bytes(&self) -> usize {
+        self.total_bytes
+    }
+
+    /// How far cached runs exceed [WorkerConfig::max_cache_bytes], if it's set
+    pub(super) fn bytes_over_budget(&self) -> usize {
+        self.worker_config
+            .max_cache_bytes
+            .map(|max| self.total_bytes.saturating_sub(max))
+            .unwrap_or_default()
     }
 
     pub(super) fn get(&mut self, k: &str) -> Option<&ManagedRun> {
@@ -132,8 +240,9 @@ impl RunCache {
         self.runs.iter().map(|(_, v)| v)
     }
 
+    /// True if the cache is at its run limit, or its runs exceed the byte limit
     pub(super) fn is_full(&self) -> bool {
-        self.runs.cap().get() == self.runs.len()
+        self.runs.cap().get() == self.runs.len() || self.bytes_over_budget() > 0
     }
 
     pub(super) fn len(&self) -> usize {
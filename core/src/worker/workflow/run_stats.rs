@@ -0,0 +1,182 @@
+//! Per-run processing statistics, for finding the runs which dominate a worker's time (ex: ones
+//! with huge histories, or receiving a storm of signals). Every cached run has counters which its
+//! [super::managed_run::ManagedRun] bumps as it is processed. They are atomics shared with the
+//! worker, so that they can be read without a round trip through the workflow processing thread.
+
+use parking_lot::RwLock;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+/// How much processing one cached run has needed since it entered the cache
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RunProcessingStats {
+    pub workflow_id: String,
+    pub run_id: String,
+    /// Number of activations delivered to lang
+    pub activations: u64,
+    /// Number of jobs across all those activations
+    pub jobs: u64,
+    /// Total time spent applying history and commands to the run's state machines
+    pub machine_time: Duration,
+    /// Approximate size of the history the run most recently received from server
+    pub last_history_fetch_bytes: u64,
+    /// Number of jobs waiting for the next activation, including deferred queries
+    pub buffered_jobs: u64,
+}
+
+/// The runs ranking highest by each of the [RunProcessingStats] counters, highest first. Runs whose
+/// counter is zero are never included.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HotRuns {
+    pub by_activations: Vec<RunProcessingStats>,
+    pub by_jobs: Vec<RunProcessingStats>,
+    pub by_machine_time: Vec<RunProcessingStats>,
+    pub by_last_history_fetch_bytes: Vec<RunProcessingStats>,
+    pub by_buffered_jobs: Vec<RunProcessingStats>,
+}
+
+/// The counters for one cached run
+#[derive(Debug, Default)]
+pub(super) struct RunStats {
+    workflow_id: String,
+    activations: AtomicU64,
+    jobs: AtomicU64,
+    machine_time_nanos: AtomicU64,
+    last_history_fetch_bytes: AtomicU64,
+    buffered_jobs: AtomicU64,
+}
+
+impl RunStats {
+    pub(super) fn record_activation(&self, num_jobs: usize) {
+        self.activations.fetch_add(1, Ordering::Relaxed);
+        self.jobs.fetch_add(num_jobs as u64, Ordering::Relaxed);
+    }
+
+    pub(super) fn record_machine_time(&self, elapsed: Duration) {
+        self.machine_time_nanos
+            .fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    pub(super) fn record_history_fetch(&self, bytes: usize) {
+        self.last_history_fetch_bytes
+            .store(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub(super) fn set_buffered_jobs(&self, num_jobs: usize) {
+        self.buffered_jobs.store(num_jobs as u64, Ordering::Relaxed);
+    }
+
+    fn read(&self, run_id: &str) -> RunProcessingStats {
+        RunProcessingStats {
+            workflow_id: self.workflow_id.clone(),
+            run_id: run_id.to_string(),
+            activations: self.activations.load(Ordering::Relaxed),
+            jobs: self.jobs.load(Ordering::Relaxed),
+            machine_time: Duration::from_nanos(self.machine_time_nanos.load(Ordering::Relaxed)),
+            last_history_fetch_bytes: self.last_history_fetch_bytes.load(Ordering::Relaxed),
+            buffered_jobs: self.buffered_jobs.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// The counters of every cached run, by run id. Cheap to clone, and clones share counters.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RunStatsRegistry {
+    runs: Arc<RwLock<HashMap<String, Arc<RunStats>>>>,
+}
+
+impl RunStatsRegistry {
+    /// Start counting for a run entering the cache. A run re-entering the cache after an eviction
+    /// starts again from zero.
+    pub(super) fn register(&self, workflow_id: &str, run_id: &str) -> Arc<RunStats> {
+        let stats = Arc::new(RunStats {
+            workflow_id: workflow_id.to_string(),
+            ..Default::default()
+        });
+        self.runs.write().insert(run_id.to_string(), stats.clone());
+        stats
+    }
+
+    pub(super) fn remove(&self, run_id: &str) {
+        self.runs.write().remove(run_id);
+    }
+
+    /// Returns up to `k` of the highest ranking runs by each counter
+    pub(crate) fn hot_runs(&self, k: usize) -> HotRuns {
+        let all: Vec<_> = self
+            .runs
+            .read()
+            .iter()
+            .map(|(run_id, stats)| stats.read(run_id))
+            .collect();
+        let top_by = |key: fn(&RunProcessingStats) -> u128| {
+            let mut ranked: Vec<_> = all.iter().filter(|s| key(s) > 0).cloned().collect();
+            // Ties are broken by run id so that rankings are stable
+            ranked.sort_by(|a, b| key(b).cmp(&key(a)).then_with(|| a.run_id.cmp(&b.run_id)));
+            ranked.truncate(k);
+            ranked
+        };
+        HotRuns {
+            by_activations: top_by(|s| s.activations.into()),
+            by_jobs: top_by(|s| s.jobs.into()),
+            by_machine_time: top_by(|s| s.machine_time.as_nanos()),
+            by_last_history_fetch_bytes: top_by(|s| s.last_history_fetch_bytes.into()),
+            by_buffered_jobs: top_by(|s| s.buffered_jobs.into()),
+        }
+    }
+
+    /// The run which has spent the most time in its state machines, if any has spent any
+    pub(super) fn hottest_run(&self) -> Option<RunProcessingStats> {
+        self.hot_runs(1).by_machine_time.pop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranks_each_counter_independently() {
+        let registry = RunStatsRegistry::default();
+        let busy = registry.register("wf", "busy");
+        let big = registry.register("wf", "big");
+        registry.register("wf", "idle");
+        for _ in 0..3 {
+            busy.record_activation(2);
+            busy.record_machine_time(Duration::from_millis(5));
+        }
+        big.record_activation(1);
+        big.record_history_fetch(10_000);
+        big.record_machine_time(Duration::from_millis(1));
+        busy.record_history_fetch(100);
+
+        let hot = registry.hot_runs(5);
+        let ids = |ranked: &[RunProcessingStats]| -> Vec<_> {
+            ranked.iter().map(|s| s.run_id.clone()).collect()
+        };
+        assert_eq!(ids(&hot.by_activations), ["busy", "big"]);
+        assert_eq!(ids(&hot.by_jobs), ["busy", "big"]);
+        assert_eq!(ids(&hot.by_machine_time), ["busy", "big"]);
+        assert_eq!(ids(&hot.by_last_history_fetch_bytes), ["big", "busy"]);
+        assert!(hot.by_buffered_jobs.is_empty());
+        assert_eq!(hot.by_jobs[0].jobs, 6);
+        assert_eq!(registry.hot_runs(1).by_activations.len(), 1);
+        assert_eq!(registry.hottest_run().unwrap().run_id, "busy");
+    }
+
+    #[test]
+    fn counters_reset_when_run_reenters_cache() {
+        let registry = RunStatsRegistry::default();
+        registry.register("wf", "run").record_activation(4);
+        registry.remove("run");
+        assert_eq!(registry.hot_runs(1), HotRuns::default());
+        registry.register("wf", "run");
+        assert!(registry.hot_runs(1).by_jobs.is_empty());
+    }
+}
@@ -1,5 +1,6 @@
 use crate::{
     abstractions::OwnedMeteredSemPermit,
+    pollers::PollError,
     protosext::ValidPollWFTQResponse,
     worker::{
         client::WorkerClient,
@@ -13,6 +14,7 @@ use futures_util::{stream, stream::PollNext, FutureExt, Stream, StreamExt};
 use std::{future, sync::Arc};
 use temporal_sdk_core_api::worker::WorkflowSlotKind;
 use temporal_sdk_core_protos::{coresdk::WorkflowSlotInfo, TaskToken};
+use tokio::sync::Semaphore;
 use tracing::Span;
 
 /// Transforms incoming validated WFTs and history fetching requests into [PermittedWFT]s ready
@@ -45,7 +47,7 @@ pub(crate) type WFTStreamIn = Result<
         ValidPollWFTQResponse,
         OwnedMeteredSemPermit<WorkflowSlotKind>,
     ),
-    tonic::Status,
+    PollError,
 >;
 #[derive(derive_more::From, Debug)]
 pub(super) enum HistoryFetchReq {
@@ -60,33 +62,45 @@ impl WFTExtractor {
     pub(super) fn build(
         client: Arc<dyn WorkerClient>,
         max_fetch_concurrency: usize,
+        max_fetch_bytes: Option<usize>,
+        fetch_permits: Arc<Semaphore>,
         wft_stream: impl Stream<Item = WFTStreamIn> + Send + 'static,
         fetch_stream: impl Stream<Item = HistoryFetchReq> + Send + 'static,
-    ) -> impl Stream<Item = Result<WFTExtractorOutput, tonic::Status>> + Send + 'static {
+    ) -> impl Stream<Item = Result<WFTExtractorOutput, PollError>> + Send + 'static {
         let fetch_client = client.clone();
         let wft_stream = wft_stream
             .map(move |stream_in| {
                 let client = client.clone();
+                let fetch_permits = fetch_permits.clone();
                 async move {
                     match stream_in {
                         Ok((wft, permit)) => {
                             let run_id = wft.workflow_execution.run_id.clone();
                             let tt = wft.task_token.clone();
-                            Ok(match HistoryPaginator::from_poll(wft, client).await {
-                                Ok((pag, prep)) => WFTExtractorOutput::NewWFT(PermittedWFT {
-                                    permit: permit.into_used(WorkflowSlotInfo {
-                                        workflow_type: prep.workflow_type.clone(),
-                                        is_sticky: prep.is_incremental(),
+                            Ok(
+                                match HistoryPaginator::from_poll(
+                                    wft,
+                                    client,
+                                    max_fetch_bytes,
+                                    fetch_permits,
+                                )
+                                .await
+                                {
+                                    Ok((pag, prep)) => WFTExtractorOutput::NewWFT(PermittedWFT {
+                                        permit: permit.into_used(WorkflowSlotInfo {
+                                            workflow_type: prep.workflow_type.clone(),
+                                            is_sticky: prep.is_incremental(),
+                                        }),
+                                        work: prep,
+                                        paginator: pag,
                                     }),
-                                    work: prep,
-                                    paginator: pag,
-                                }),
-                                Err(err) => WFTExtractorOutput::FailedFetch {
-                                    run_id,
-                                    err,
-                                    auto_reply_fail_tt: Some(tt),
+                                    Err(err) => WFTExtractorOutput::FailedFetch {
+                                        run_id,
+                                        err,
+                                        auto_reply_fail_tt: Some(tt),
+                                    },
                                 },
-                            })
+                            )
                         }
                         Err(e) => Err(e),
                     }
@@ -129,7 +143,7 @@ impl WFTExtractor {
                                     rc,
                                 },
                                 Err(err) => WFTExtractorOutput::FailedFetch {
-                                    run_id: req.paginator.run_id,
+                                    run_id: req.paginator.run_id.into(),
                                     err,
                                     auto_reply_fail_tt: None,
                                 },
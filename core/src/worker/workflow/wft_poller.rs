@@ -1,63 +1,124 @@
 use crate::{
     abstractions::OwnedMeteredSemPermit,
-    pollers::{BoxedWFPoller, Poller},
+    pollers::{BoxedWFPoller, PollError, Poller},
     protosext::ValidPollWFTQResponse,
+    telemetry::metrics::task_queue_kind,
+    worker::clock_skew::ClockSkewEstimator,
     MetricsContext,
 };
 use futures_util::{stream, Stream};
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
 use temporal_sdk_core_api::worker::WorkflowSlotKind;
-use temporal_sdk_core_protos::temporal::api::workflowservice::v1::PollWorkflowTaskQueueResponse;
+use temporal_sdk_core_protos::temporal::api::{
+    enums::v1::TaskQueueKind, history::v1::history_event::Attributes,
+    workflowservice::v1::PollWorkflowTaskQueueResponse,
+};
+
+/// Schedule-to-start latency is recorded separately for each queue kind, so that tasks falling
+/// back to the normal queue after timing out on the sticky one can be told apart from a backlog
+struct SchedToStartMetrics {
+    normal: MetricsContext,
+    sticky: MetricsContext,
+}
+
+impl SchedToStartMetrics {
+    fn new(metrics: &MetricsContext) -> Self {
+        Self {
+            normal: metrics.with_new_attrs([task_queue_kind(TaskQueueKind::Normal)]),
+            sticky: metrics.with_new_attrs([task_queue_kind(TaskQueueKind::Sticky)]),
+        }
+    }
+
+    fn for_kind(&self, kind: TaskQueueKind) -> &MetricsContext {
+        match kind {
+            TaskQueueKind::Sticky => &self.sticky,
+            _ => &self.normal,
+        }
+    }
+}
 
 pub(crate) fn new_wft_poller(
     poller: BoxedWFPoller,
     metrics: MetricsContext,
+    clock_skew: Arc<ClockSkewEstimator>,
 ) -> impl Stream<
     Item = Result<
         (
             ValidPollWFTQResponse,
             OwnedMeteredSemPermit<WorkflowSlotKind>,
         ),
-        tonic::Status,
+        PollError,
     >,
 > {
-    stream::unfold((poller, metrics), |(poller, metrics)| async move {
-        loop {
-            return match poller.poll().await {
-                Some(Ok((wft, permit))) => {
-                    if wft == PollWorkflowTaskQueueResponse::default() {
-                        // We get the default proto in the event that the long poll times out.
-                        debug!("Poll wft timeout");
-                        metrics.wf_tq_poll_empty();
-                        continue;
-                    }
-                    if let Some(dur) = wft.sched_to_start() {
-                        metrics.wf_task_sched_to_start_latency(dur);
+    let sched_to_start = SchedToStartMetrics::new(&metrics);
+    stream::unfold(
+        (poller, metrics, sched_to_start, clock_skew),
+        |(poller, metrics, sched_to_start, clock_skew)| async move {
+            match poller.poll().await {
+                Ok(((wft, queue_kind), permit)) => {
+                    clock_skew.observe_task_start(wft.started_time.as_ref());
+                    if let Some(dur) = sched_to_start(&wft) {
+                        sched_to_start
+                            .for_kind(queue_kind)
+                            .wf_task_sched_to_start_latency(dur);
                     }
-                    let work = match validate_wft(wft) {
+                    let mut work = match validate_wft(wft) {
                         Ok(w) => w,
                         Err(e) => {
                             error!(error=?e, "Server returned an unparseable workflow task");
-                            continue;
+                            let err = PollError::MalformedResponse {
+                                reason: e.message().to_string(),
+                            };
+                            return Some((Err(err), (poller, metrics, sched_to_start, clock_skew)));
                         }
                     };
+                    work.task_queue_kind = queue_kind;
                     metrics.wf_tq_poll_ok();
-                    Some((Ok((work, permit)), (poller, metrics)))
+                    Some((
+                        Ok((work, permit)),
+                        (poller, metrics, sched_to_start, clock_skew),
+                    ))
                 }
-                Some(Err(e)) => {
-                    warn!(error=?e, "Error while polling for workflow tasks");
-                    Some((Err(e), (poller, metrics)))
-                }
-                // If poller returns None, it's dead, thus we also return None to terminate this
-                // stream.
-                None => {
-                    // Make sure we call the actual shutdown function here to propagate any panics
-                    // inside the polling tasks as errors.
+                // If the poller is shut down, it's dead, thus we also return None to terminate
+                // this stream.
+                Err(PollError::ShutDown) => {
+                    // Make sure we call the actual shutdown function here to propagate any
+                    // panics inside the polling tasks as errors.
                     poller.shutdown_box().await;
                     None
                 }
-            };
-        }
-    })
+                Err(e) => {
+                    warn!(error=?e, "Error while polling for workflow tasks");
+                    Some((Err(e), (poller, metrics, sched_to_start, clock_skew)))
+                }
+            }
+        },
+    )
+}
+
+/// How long the task waited to be picked up. A run's first task can't be dispatched until its
+/// execution's start delay (or retry or cron backoff) is over, so that wait isn't counted, even
+/// if the task is reported as scheduled when the execution started.
+fn sched_to_start(wft: &PollWorkflowTaskQueueResponse) -> Option<Duration> {
+    let scheduled: SystemTime = wft.scheduled_time.clone()?.try_into().ok()?;
+    let started: SystemTime = wft.started_time.clone()?.try_into().ok()?;
+    let scheduled = first_task_dispatch_time(wft).map_or(scheduled, |d| scheduled.max(d));
+    started.duration_since(scheduled).ok()
+}
+
+/// If the task's history begins with the execution's start, the earliest its first task could
+/// have been dispatched: the start time plus the first workflow task backoff
+fn first_task_dispatch_time(wft: &PollWorkflowTaskQueueResponse) -> Option<SystemTime> {
+    let start = wft.history.as_ref()?.events.first()?;
+    let Some(Attributes::WorkflowExecutionStartedEventAttributes(attrs)) = &start.attributes else {
+        return None;
+    };
+    let backoff: Duration = attrs.first_workflow_task_backoff.clone()?.try_into().ok()?;
+    let start_time: SystemTime = start.event_time.clone()?.try_into().ok()?;
+    Some(start_time + backoff)
 }
 
 pub(crate) fn validate_wft(
@@ -75,44 +136,162 @@ pub(crate) fn validate_wft(
 mod tests {
     use super::*;
     use crate::{
-        abstractions::tests::fixed_size_permit_dealer, pollers::MockPermittedPollBuffer,
-        test_help::mock_poller,
+        abstractions::tests::fixed_size_permit_dealer,
+        pollers::MockPermittedPollBuffer,
+        telemetry::metrics::WORKFLOW_TASK_SCHED_TO_START_LATENCY_HISTOGRAM_NAME,
+        test_help::{
+            canned_histories, hist_to_poll_resp, mock_poller, BufferedTelemetry, ResponseType,
+        },
     };
     use futures_util::{pin_mut, StreamExt};
-    use std::sync::Arc;
-    use temporal_sdk_core_api::worker::WorkflowSlotKind;
+    use prost_types::Timestamp;
+    use temporal_sdk_core_api::{telemetry::metrics::MetricUpdateVal, worker::WorkflowSlotKind};
+    use temporal_sdk_core_protos::{default_wes_attribs, TestHistoryBuilder};
+
+    fn recorded_sched_to_start(telem: &BufferedTelemetry) -> Vec<(String, Duration)> {
+        telem
+            .updates()
+            .into_iter()
+            .filter(|(name, _, _)| {
+                name.ends_with(WORKFLOW_TASK_SCHED_TO_START_LATENCY_HISTOGRAM_NAME)
+            })
+            .map(|(_, attrs, update)| {
+                let MetricUpdateVal::Duration(d) = update else {
+                    panic!("Latency must be recorded as a duration");
+                };
+                (attrs.get("task_queue_kind").cloned().unwrap_or_default(), d)
+            })
+            .collect()
+    }
 
     #[tokio::test]
-    async fn poll_timeouts_do_not_produce_responses() {
+    async fn poll_errors_do_produce_responses() {
         let mut mock_poller = mock_poller();
         mock_poller
             .expect_poll()
             .times(1)
-            .returning(|| Some(Ok(PollWorkflowTaskQueueResponse::default())));
-        mock_poller.expect_poll().times(1).returning(|| None);
-        mock_poller.expect_shutdown().times(1).returning(|| ());
+            .returning(|| Err(tonic::Status::internal("ahhh").into()));
         let sem = Arc::new(fixed_size_permit_dealer::<WorkflowSlotKind>(10));
         let stream = new_wft_poller(
             Box::new(MockPermittedPollBuffer::new(sem, mock_poller)),
             MetricsContext::no_op(),
+            Default::default(),
         );
         pin_mut!(stream);
-        assert_matches!(stream.next().await, None);
+        assert_matches!(stream.next().await, Some(Err(PollError::TonicError(_))));
     }
 
     #[tokio::test]
-    async fn poll_errors_do_produce_responses() {
+    async fn malformed_tasks_produce_errors() {
         let mut mock_poller = mock_poller();
-        mock_poller
-            .expect_poll()
-            .times(1)
-            .returning(|| Some(Err(tonic::Status::internal("ahhh"))));
+        mock_poller.expect_poll().times(1).returning(|| {
+            // Not the default response, which means the poll timed out, but missing everything
+            // a workflow task needs
+            let wft = PollWorkflowTaskQueueResponse {
+                task_token: vec![1],
+                ..Default::default()
+            };
+            Ok((wft, TaskQueueKind::Normal))
+        });
         let sem = Arc::new(fixed_size_permit_dealer::<WorkflowSlotKind>(10));
         let stream = new_wft_poller(
             Box::new(MockPermittedPollBuffer::new(sem, mock_poller)),
             MetricsContext::no_op(),
+            Default::default(),
         );
         pin_mut!(stream);
-        assert_matches!(stream.next().await, Some(Err(_)));
+        assert_matches!(
+            stream.next().await,
+            Some(Err(PollError::MalformedResponse { .. }))
+        );
+    }
+
+    #[tokio::test]
+    async fn sched_to_start_latency_is_tagged_with_queue_kind() {
+        let telem = BufferedTelemetry::new();
+        let metrics = MetricsContext::top_level("ns".to_string(), "tq".to_string(), &telem);
+
+        let t = canned_histories::single_timer("1");
+        let mut tasks = vec![(TaskQueueKind::Sticky, 2), (TaskQueueKind::Normal, 5)]
+            .into_iter()
+            .map(move |(kind, latency_secs)| {
+                let mut resp = hist_to_poll_resp(&t, "wfid", ResponseType::AllHistory).resp;
+                resp.scheduled_time = Some(Timestamp {
+                    seconds: 100,
+                    nanos: 0,
+                });
+                resp.started_time = Some(Timestamp {
+                    seconds: 100 + latency_secs,
+                    nanos: 0,
+                });
+                (resp, kind)
+            });
+        let mut mock_poller = mock_poller();
+        mock_poller
+            .expect_poll()
+            .times(3)
+            .returning(move || tasks.next().ok_or(PollError::ShutDown));
+        mock_poller.expect_shutdown().returning(|| ());
+        let sem = Arc::new(fixed_size_permit_dealer::<WorkflowSlotKind>(10));
+        let stream = new_wft_poller(
+            Box::new(MockPermittedPollBuffer::new(sem, mock_poller)),
+            metrics,
+            Default::default(),
+        );
+        let kinds: Vec<_> = stream.map(|r| r.unwrap().0.task_queue_kind).collect().await;
+        assert_eq!(kinds, [TaskQueueKind::Sticky, TaskQueueKind::Normal]);
+
+        assert_eq!(
+            recorded_sched_to_start(&telem),
+            [
+                ("sticky".to_string(), Duration::from_secs(2)),
+                ("normal".to_string(), Duration::from_secs(5)),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn first_task_sched_to_start_excludes_the_start_delay() {
+        let telem = BufferedTelemetry::new();
+        let metrics = MetricsContext::top_level("ns".to_string(), "tq".to_string(), &telem);
+
+        let mut t = TestHistoryBuilder::default();
+        let mut wes_attrs = default_wes_attribs();
+        wes_attrs.first_workflow_task_backoff =
+            Some(Duration::from_secs(60 * 60).try_into().unwrap());
+        t.add(wes_attrs);
+        t.add_workflow_task_scheduled_and_started();
+        let start = Timestamp {
+            seconds: 100,
+            nanos: 0,
+        };
+        t.modify_event(1, |e| e.event_time = Some(start.clone()));
+        let mut resp = hist_to_poll_resp(&t, "wfid", ResponseType::AllHistory).resp;
+        // Reported as scheduled when the execution started, but only dispatched an hour later
+        resp.scheduled_time = Some(start);
+        resp.started_time = Some(Timestamp {
+            seconds: 100 + 60 * 60 + 3,
+            nanos: 0,
+        });
+        let mut resp = Some(resp);
+        let mut mock_poller = mock_poller();
+        mock_poller.expect_poll().times(2).returning(move || {
+            resp.take()
+                .map(|r| (r, TaskQueueKind::Normal))
+                .ok_or(PollError::ShutDown)
+        });
+        mock_poller.expect_shutdown().returning(|| ());
+        let sem = Arc::new(fixed_size_permit_dealer::<WorkflowSlotKind>(10));
+        let stream = new_wft_poller(
+            Box::new(MockPermittedPollBuffer::new(sem, mock_poller)),
+            metrics,
+            Default::default(),
+        );
+        assert_eq!(stream.count().await, 1);
+
+        assert_eq!(
+            recorded_sched_to_start(&telem),
+            [("normal".to_string(), Duration::from_secs(3))]
+        );
     }
 }
@@ -1,7 +1,12 @@
 use crate::{
     abstractions::dbg_panic,
+    pollers::PollError,
     worker::workflow::{
+        activation_deadlines::ActivationDeadlineMsg,
+        cache_snapshot::RunSnapshot,
+        history_update::is_history_too_large,
         managed_run::RunUpdateAct,
+        replay_limiter::{self, ReplayLimiter},
         run_cache::RunCache,
         wft_extraction::{HistfetchRC, HistoryFetchReq, WFTExtractorOutput},
         *,
@@ -9,12 +14,27 @@ use crate::{
     MetricsContext,
 };
 use futures_util::{stream, stream::PollNext, Stream, StreamExt};
-use std::{collections::VecDeque, fmt::Debug, future, sync::Arc};
-use temporal_sdk_core_api::errors::PollWfError;
-use temporal_sdk_core_protos::coresdk::workflow_activation::remove_from_cache::EvictionReason;
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Debug,
+    future,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use temporal_sdk_core_api::errors::{CompleteWfError, PollWfError};
+use temporal_sdk_core_protos::{
+    coresdk::workflow_activation::remove_from_cache::EvictionReason,
+    temporal::api::enums::v1::TaskQueueKind,
+};
+use tokio::sync::mpsc::UnboundedSender;
 use tokio_util::sync::CancellationToken;
 use tracing::{Level, Span};
 
+/// Minimum time between the status lines logged while the stream is processing inputs
+const STATUS_LOG_INTERVAL: Duration = Duration::from_secs(60);
+/// Failure message for tasks which were never processed because the worker shut down first
+const SHUTDOWN_BEFORE_PROCESSING: &str = "Worker shut down before this task could be processed";
+
 /// This struct holds all the state needed for tracking the state of currently cached workflow runs
 /// and directs all actions which affect them. It is ultimately the top-level arbiter of nearly
 /// everything important relating to workflow state.
@@ -26,13 +46,25 @@ pub(super) struct WFStream {
     /// The inner list is possibly multiple buffered tasks for one run, which can happen if there
     /// is a backlog of queries.
     buffered_polls_need_cache_slot: VecDeque<Vec<PermittedWFT>>,
+    /// Set while buffered polls are waiting because every cached run is busy
+    intake_delayed: bool,
+    /// Bounds how many uncached runs replay their history at once, holding the polls of those
+    /// which must wait
+    replays: ReplayLimiter<PermittedWFT>,
     /// Is filled with runs that we decided need to have their history fetched during state
     /// manipulation. Must be drained after handling each input.
     runs_needing_fetching: VecDeque<HistoryFetchReq>,
+    /// Runs handed over by another worker's cache snapshot, by run id, which have not yet been
+    /// seen by this one
+    handed_over_runs: HashMap<String, RunSnapshot>,
 
     history_fetch_refcounter: Arc<HistfetchRC>,
     shutdown_token: CancellationToken,
     ignore_evicts_on_shutdown: bool,
+    /// Used to schedule our own inputs, like the expiry of deferred queries
+    local_input_tx: UnboundedSender<LocalInput>,
+    run_stats: RunStatsRegistry,
+    last_status_log: Instant,
 
     metrics: MetricsContext,
 }
@@ -43,6 +75,8 @@ impl WFStream {
     ///    by a poller (or mock), via [WFTExtractor].
     /// * `local_rx` is a stream of actions that workflow state needs to see. Things like
     ///    completions, local activities finishing, etc. See [LocalInputs].
+    /// * `local_tx` sends into `local_rx`, and is used to schedule inputs which should arrive
+    ///    after a delay, like the expiry of deferred queries.
     /// * `local_activity_request_sink` is used to handle outgoing requests to start or cancel
     ///    local activities, and may return resolutions that need to be handled immediately.
     ///
@@ -61,8 +95,9 @@ impl WFStream {
     /// manager), which is a quite substantial change.
     pub(super) fn build(
         basics: WorkflowBasics,
-        wft_stream: impl Stream<Item = Result<WFTExtractorOutput, tonic::Status>> + Send + 'static,
+        wft_stream: impl Stream<Item = Result<WFTExtractorOutput, PollError>> + Send + 'static,
         local_rx: impl Stream<Item = LocalInput> + Send + 'static,
+        local_tx: UnboundedSender<LocalInput>,
         local_activity_request_sink: impl LocalActivityRequestSink,
     ) -> impl Stream<Item = Result<WFStreamOutput, PollWfError>> {
         let all_inputs = stream::select_with_strategy(
@@ -75,26 +110,34 @@ impl WFStream {
             // Priority always goes to the local stream
             |_: &mut ()| PollNext::Left,
         );
-        Self::build_internal(all_inputs, basics, local_activity_request_sink)
+        Self::build_internal(all_inputs, basics, local_tx, local_activity_request_sink)
     }
 
     fn build_internal(
         all_inputs: impl Stream<Item = WFStreamInput>,
         basics: WorkflowBasics,
+        local_input_tx: UnboundedSender<LocalInput>,
         local_activity_request_sink: impl LocalActivityRequestSink,
     ) -> impl Stream<Item = Result<WFStreamOutput, PollWfError>> {
         let mut state = WFStream {
             buffered_polls_need_cache_slot: Default::default(),
+            intake_delayed: false,
+            replays: ReplayLimiter::new(basics.worker_config.max_concurrent_history_replays),
             runs: RunCache::new(
                 basics.worker_config.clone(),
                 basics.server_capabilities,
                 local_activity_request_sink,
+                basics.run_stats.clone(),
                 basics.metrics.clone(),
             ),
             shutdown_token: basics.shutdown_token,
             ignore_evicts_on_shutdown: basics.worker_config.ignore_evicts_on_shutdown,
+            local_input_tx,
+            run_stats: basics.run_stats,
+            last_status_log: Instant::now(),
             metrics: basics.metrics,
             runs_needing_fetching: Default::default(),
+            handed_over_runs: Default::default(),
             history_fetch_refcounter: Arc::new(HistfetchRC {}),
         };
         all_inputs
@@ -106,6 +149,7 @@ impl WFStream {
                 let maybe_act = match action {
                     WFStreamInput::NewWft(pwft) => {
                         debug!(run_id=%pwft.work.execution.run_id, "New WFT");
+                        state.detect_sticky_timeout_fallback(&pwft.work);
                         state.instantiate_or_update(*pwft)
                     }
                     WFStreamInput::Local(local_input) => {
@@ -135,60 +179,120 @@ impl WFStream {
                             LocalInputs::HeartbeatTimeout(hbt) => {
                                 state.process_heartbeat_timeout(hbt)
                             }
+                            LocalInputs::QueryDeferralTimeout(run_id) => state
+                                .runs
+                                .get_mut(&run_id)
+                                .and_then(|rh| rh.fail_expired_deferred_queries()),
+                            LocalInputs::ActivationDeadline(expired) => {
+                                Some(ActivationOrAuto::FailStuckActivation(expired))
+                            }
+                            // Expired tasks are dropped by `admit_queued_replays`, which runs
+                            // after every input
+                            LocalInputs::ReplayQueueExpiry => None,
                             LocalInputs::RequestEviction(evict) => {
                                 state.request_eviction(evict).into_run_update_resp()
                             }
                             LocalInputs::GetStateInfo(gsi) => {
                                 let _ = gsi.response_tx.send(WorkflowStateInfo {
                                     cached_workflows: state.runs.len(),
+                                    cached_workflow_bytes: state.runs.total_bytes(),
                                     outstanding_wft: state.outstanding_wfts(),
                                 });
                                 None
                             }
+                            LocalInputs::ExportCacheSnapshot(ecs) => {
+                                let _ = ecs.response_tx.send(
+                                    state
+                                        .runs
+                                        .runs_lru_order()
+                                        .filter_map(|(_, rh)| rh.cache_snapshot())
+                                        .collect(),
+                                );
+                                None
+                            }
+                            LocalInputs::ImportCacheSnapshot(ics) => {
+                                debug!(runs = ics.runs.len(), "Importing cache snapshot");
+                                state
+                                    .handed_over_runs
+                                    .extend(ics.runs.into_iter().map(|r| (r.run_id.clone(), r)));
+                                None
+                            }
                         }
                     }
                     WFStreamInput::FailedFetch {
                         run_id,
                         err,
                         auto_reply_fail_tt,
-                    } => state
-                        .request_eviction(RequestEvictMsg {
-                            run_id,
-                            message: format!("Fetching history failed: {err:?}"),
-                            reason: EvictionReason::PaginationOrHistoryFetch,
-                            auto_reply_fail_tt,
-                        })
-                        .into_run_update_resp(),
+                    } => {
+                        state.replays.release(&run_id);
+                        let message = if is_history_too_large(&err) {
+                            state.metrics.wf_history_fetch_limit_exceeded();
+                            err.message().to_string()
+                        } else {
+                            format!("Fetching history failed: {err:?}")
+                        };
+                        state
+                            .request_eviction(RequestEvictMsg {
+                                run_id,
+                                message,
+                                reason: EvictionReason::PaginationOrHistoryFetch,
+                                auto_reply_fail_tt,
+                            })
+                            .into_run_update_resp()
+                    }
                     WFStreamInput::PollerDead => {
                         debug!("WFT poller died, beginning shutdown");
                         state.shutdown_token.cancel();
                         None
                     }
                     WFStreamInput::PollerError(e) => {
-                        return Err(PollWfError::TonicError(e));
+                        return Err(e.into());
                     }
                 };
 
                 activations.extend(maybe_act);
+                activations.extend(state.admit_queued_replays());
+                state.runs.refresh_sizes();
                 activations.extend(state.reconcile_buffered());
+                state.log_status_if_due();
 
                 if state.shutdown_done() {
-                    info!("Workflow shutdown is done");
-                    return Err(PollWfError::ShutDown);
+                    let unstarted = state.fail_unstarted_buffered_polls();
+                    if unstarted.is_empty() {
+                        info!("Workflow shutdown is done");
+                        return Err(PollWfError::ShutDown);
+                    }
+                    activations.extend(unstarted);
                 }
 
+                let activations = activations
+                    .into_iter()
+                    .map(|act| {
+                        let rh = state.runs.peek(act.run_id());
+                        ReadyActivation {
+                            wft_deadline: rh.and_then(|rh| rh.wft_deadline()),
+                            wft_info: rh.and_then(|rh| rh.wft()).map(|wft| wft.info.clone()),
+                            act,
+                        }
+                    })
+                    .collect();
                 Ok(WFStreamOutput {
-                    activations: activations.into(),
+                    activations,
                     fetch_histories: std::mem::take(&mut state.runs_needing_fetching),
                 })
             })
             .inspect(|o| {
                 if let Some(e) = o.as_ref().err() {
-                    if !matches!(e, PollWfError::ShutDown) {
-                        error!(
+                    match e {
+                        // Lang is expected to poll again after these, and the poller has already
+                        // logged them
+                        PollWfError::ShutDown
+                        | PollWfError::RetryableTonicError(_)
+                        | PollWfError::MalformedResponse { .. } => {}
+                        _ => error!(
                             "Workflow processing encountered fatal error and must shut down {:?}",
                             e
-                        );
+                        ),
                     }
                 }
             })
@@ -196,6 +300,38 @@ impl WFStream {
             .take_while(|o| future::ready(!matches!(o, Err(PollWfError::ShutDown))))
     }
 
+    /// Log a line summarizing the cache, including its hottest run, if one hasn't been logged for
+    /// [STATUS_LOG_INTERVAL]
+    fn log_status_if_due(&mut self) {
+        if self.last_status_log.elapsed() < STATUS_LOG_INTERVAL {
+            return;
+        }
+        self.last_status_log = Instant::now();
+        let hottest = self.run_stats.hottest_run();
+        info!(
+            cached_runs = self.runs.len(),
+            cached_bytes = self.runs.total_bytes(),
+            hottest_run_id = hottest.as_ref().map(|h| h.run_id.as_str()),
+            hottest_machine_time = ?hottest.as_ref().map(|h| h.machine_time),
+            hottest_activations = hottest.as_ref().map(|h| h.activations),
+            "Workflow cache status"
+        );
+    }
+
+    /// Tasks for cached runs are sent to this worker's sticky queue. One arriving on the normal
+    /// queue instead means server gave up waiting for it to be taken from the sticky one. Legacy
+    /// queries always arrive on the normal queue, so they don't count.
+    fn detect_sticky_timeout_fallback(&self, work: &PreparedWFT) {
+        if work.task_queue_kind == TaskQueueKind::Normal
+            && work.legacy_query.is_none()
+            && self.runs.has_run(&work.execution.run_id)
+        {
+            debug!(run_id=%work.execution.run_id,
+                   "Workflow task for a cached run arrived on the normal queue");
+            self.metrics.sticky_timeout_fallback();
+        }
+    }
+
     /// Instantiate or update run machines with a new WFT
     #[instrument(skip(self, pwft)
                  fields(run_id=%pwft.work.execution.run_id,
@@ -217,9 +353,15 @@ impl WFStream {
         // If the run already exists, possibly buffer the work and return early if we can't handle
         // it yet.
         let pwft = if let Some(rh) = self.runs.get_mut(&pwft.work.execution.run_id) {
+            let is_query_only = pwft.work.is_query_only();
+            let run_id = pwft.work.execution.run_id.clone();
+            let deferral_timeout = rh.query_deferral_timeout();
             if let Some(w) = rh.buffer_wft_if_outstanding_work(pwft) {
                 w
             } else {
+                if is_query_only {
+                    self.start_query_deferral_timeout(run_id, deferral_timeout);
+                }
                 return Ok(None);
             }
         } else {
@@ -234,37 +376,137 @@ impl WFStream {
             return Ok(None);
         }
 
+        // Runs which must be rebuilt from history wait their turn if too many already are
+        if !self.runs.has_run(&run_id)
+            && replay_limiter::needs_replay(&pwft.work)
+            && !self.replays.try_admit(&run_id)
+        {
+            debug!(run_id=%run_id, "Queueing WFT until a history replay slot is free");
+            let timeout = replay_limiter::task_timeout(&pwft.work);
+            let now = tokio::time::Instant::now();
+            if let Some(expires_at) = self.replays.queue(run_id, pwft, timeout, now) {
+                self.start_replay_queue_expiry_timer(expires_at);
+            }
+            return Ok(None);
+        }
+
         // This check can't really be lifted up higher since we could EX: See it's in the cache,
         // not fetch more history, send the task, see cache is full, buffer it, then evict that
         // run, and now we still have a cache miss.
+        let handed_over = self.handed_over_runs.remove(&run_id);
         if !self.runs.has_run(&run_id) && pwft.work.is_incremental() {
-            debug!(run_id=?run_id, "Workflow task has partial history, but workflow is not in \
-                   cache. Will fetch history");
-            self.metrics.sticky_cache_miss();
+            let handed_over_history = handed_over.and_then(|r| r.history_preceding(&pwft.work));
+            if handed_over_history.is_some() {
+                debug!(run_id=?run_id, "Workflow task has partial history for a run handed over \
+                       from another worker. Will use its history");
+            } else {
+                debug!(run_id=?run_id, "Workflow task has partial history, but workflow is not \
+                       in cache. Will fetch history");
+                self.metrics.sticky_cache_miss();
+            }
+            self.replays.set_fetching(&run_id, true);
             return Err(HistoryFetchReq::Full(
-                Box::new(CacheMissFetchReq { original_wft: pwft }),
+                Box::new(CacheMissFetchReq {
+                    original_wft: pwft,
+                    handed_over_history,
+                }),
                 self.history_fetch_refcounter.clone(),
             ));
         }
 
+        self.replays.set_fetching(&run_id, false);
         let rur = self.runs.instantiate_or_update(pwft);
         Ok(rur)
     }
 
-    fn process_completion(&mut self, complete: NewOrFetchedComplete) -> Vec<ActivationOrAuto> {
+    /// Frees the replay slots of runs which have caught up with their history, drops queued tasks
+    /// which server will have already timed out, and starts as many of the rest as slots allow
+    fn admit_queued_replays(&mut self) -> Vec<ActivationOrAuto> {
+        let runs = &self.runs;
+        self.replays.release_finished(|run_id| {
+            runs.peek(run_id)
+                .is_some_and(|rh| rh.wft().is_some() && rh.is_replaying())
+        });
+        for expired in self.replays.take_expired(tokio::time::Instant::now()) {
+            warn!(run_id=%expired.work.execution.run_id,
+                  "Dropping workflow task which waited past its timeout to replay history");
+            self.metrics.history_replay_queue_expired();
+        }
+        let acts = self
+            .replays
+            .admit_queued()
+            .into_iter()
+            .filter_map(|pwft| self.instantiate_or_update(pwft))
+            .collect();
+        self.metrics
+            .history_replay_queue_depth(self.replays.queue_len() as u64);
+        acts
+    }
+
+    fn process_completion(&mut self, mut complete: NewOrFetchedComplete) -> Vec<ActivationOrAuto> {
+        if let NewOrFetchedComplete::New(c) = &mut complete {
+            if let Some(reason) = self.unexpected_completion_reason(&c.completion) {
+                warn!(run_id=%c.completion.run_id(), reason=%reason, "Rejecting completion");
+                // Prefer the id of the activation the run is actually waiting on, since lang may
+                // have echoed the wrong one
+                let correlation_id = self
+                    .runs
+                    .peek(c.completion.run_id())
+                    .filter(|rh| rh.activation().is_some())
+                    .map(|rh| rh.activation_correlation_id())
+                    .filter(|id| !id.is_empty())
+                    .unwrap_or_else(|| c.completion.correlation_id())
+                    .to_string();
+                if let Some(tx) = c.response_tx.take() {
+                    let _ = tx.send(ActivationCompleteResult {
+                        replaying: false,
+                        outcome: ActivationCompleteOutcome::Rejected(
+                            CompleteWfError::InvalidCompletion {
+                                reason,
+                                run_id: c.completion.run_id().to_string(),
+                                correlation_id,
+                            },
+                        ),
+                    });
+                }
+                return vec![];
+            }
+        }
         let rh = if let Some(rh) = self.runs.get_mut(complete.run_id()) {
             rh
         } else {
             dbg_panic!("Run missing during completion {:?}", complete);
             return vec![];
         };
+        if let NewOrFetchedComplete::New(WFActCompleteMsg {
+            completion,
+            size_warning: Some(size),
+            ..
+        }) = &complete
+        {
+            warn!(
+                run_id = %completion.run_id(),
+                workflow_type = rh.workflow_type(),
+                size,
+                "Activation completion exceeds the message size warning threshold"
+            );
+        }
+        let mut discarded = None;
         let mut acts: Vec<_> = match complete {
             NewOrFetchedComplete::New(complete) => match complete.completion {
                 ValidatedCompletion::Success {
                     commands,
                     used_flags,
+                    random_checksum,
+                    request_reactivation,
                     ..
-                } => match rh.successful_completion(commands, used_flags, complete.response_tx) {
+                } => match rh.successful_completion(
+                    commands,
+                    used_flags,
+                    random_checksum,
+                    request_reactivation,
+                    complete.response_tx,
+                ) {
                     Ok(acts) => acts,
                     Err(npr) => {
                         self.runs_needing_fetching
@@ -279,23 +521,36 @@ impl WFStream {
                     failure,
                     is_autocomplete,
                     ..
-                } => rh.failed_completion(
-                    failure.force_cause(),
-                    if is_autocomplete {
-                        EvictionReason::Unspecified
-                    } else {
-                        EvictionReason::LangFail
-                    },
-                    failure,
-                    is_autocomplete,
-                    complete.response_tx,
-                ),
+                } => {
+                    // Lang has told us its state for this run is unrecoverable. Anything buffered
+                    // arrived before that, so it shouldn't be applied to a fresh instance of the
+                    // run - the next task polled after this one is failed will be instead.
+                    if failure.force_eviction {
+                        discarded = rh.fail_buffered_tasks(
+                            "Workflow was evicted by lang before this task could be processed",
+                        );
+                    }
+                    rh.failed_completion(
+                        failure.force_cause(),
+                        if is_autocomplete {
+                            EvictionReason::Unspecified
+                        } else if failure.force_eviction {
+                            EvictionReason::LangRequested
+                        } else {
+                            EvictionReason::LangFail
+                        },
+                        failure,
+                        is_autocomplete,
+                        complete.response_tx,
+                    )
+                }
             },
             NewOrFetchedComplete::Fetched(update, paginator) => {
                 rh.fetched_page_completion(update, *paginator)
             }
         }
         .into_iter()
+        .chain(discarded)
         .collect();
         // Always queue evictions after completion when we have a zero-size cache
         if self.runs.cache_capacity() == 0 {
@@ -304,6 +559,35 @@ impl WFStream {
         acts
     }
 
+    /// Lang may only complete activations we've handed it. Returns why this completion can't be
+    /// one of them, if it can't.
+    fn unexpected_completion_reason(&self, completion: &ValidatedCompletion) -> Option<String> {
+        let run_id = completion.run_id();
+        let Some(rh) = self.runs.peek(run_id) else {
+            return Some(format!(
+                "No run with id {run_id} is cached, so the completion cannot be for an \
+                 activation of it. It may have been intended for another run."
+            ));
+        };
+        if matches!(completion, ValidatedCompletion::Success { .. }) && rh.activation().is_none() {
+            return Some(format!(
+                "Run {run_id} has no outstanding activation for this completion to complete"
+            ));
+        }
+        let echoed = completion.correlation_id();
+        if !echoed.is_empty()
+            && rh.activation().is_some()
+            && echoed != rh.activation_correlation_id()
+        {
+            return Some(format!(
+                "Completion is for activation {echoed}, but the outstanding activation of run \
+                 {run_id} is {}",
+                rh.activation_correlation_id()
+            ));
+        }
+        None
+    }
+
     fn process_post_activation(&mut self, report: PostActivationMsg) -> RunUpdateAct {
         let run_id = &report.run_id;
         let wft_from_complete = report.wft_from_complete;
@@ -377,10 +661,16 @@ impl WFStream {
             if let Some(rh) = self.runs.get_mut(run_id) {
                 // Attempt to produce the next activation if needed
                 res = rh.check_more_activations();
-                // If there's no more work and we reported workflow completion to server, evict.
+                // If there's no more work and server accepted the workflow's completion, evict.
                 if res.is_none()
                     && rh.workflow_is_finished()
-                    && matches!(report.wft_report_status, WFTReportStatus::Reported { .. })
+                    && matches!(
+                        report.wft_report_status,
+                        WFTReportStatus::Reported {
+                            completion_accepted: true,
+                            ..
+                        }
+                    )
                 {
                     res = rh
                         .request_eviction(RequestEvictMsg {
@@ -409,6 +699,35 @@ impl WFStream {
         }
     }
 
+    /// Arrange for any queries deferred for the run by now to be failed if they're still deferred
+    /// once the deferral timeout elapses
+    fn start_query_deferral_timeout(&self, run_id: String, timeout: Duration) {
+        let tx = self.local_input_tx.clone();
+        let deadline = Instant::now() + timeout;
+        let span = Span::current();
+        tokio::spawn(async move {
+            tokio::time::sleep_until(deadline.into()).await;
+            let _ = tx.send(LocalInput {
+                input: LocalInputs::QueryDeferralTimeout(run_id),
+                span,
+            });
+        });
+    }
+
+    /// Wake the stream once `expires_at` passes, so that a task queued for a replay slot is dropped
+    /// when it expires rather than whenever the next input happens to arrive
+    fn start_replay_queue_expiry_timer(&self, expires_at: tokio::time::Instant) {
+        let tx = self.local_input_tx.clone();
+        let span = Span::current();
+        tokio::spawn(async move {
+            tokio::time::sleep_until(expires_at).await;
+            let _ = tx.send(LocalInput {
+                input: LocalInputs::ReplayQueueExpiry,
+                span,
+            });
+        });
+    }
+
     fn process_heartbeat_timeout(&mut self, run_id: String) -> RunUpdateAct {
         if let Some(rh) = self.runs.get_mut(&run_id) {
             rh.heartbeat_timeout()
@@ -473,6 +792,10 @@ impl WFStream {
             {
                 return None;
             }
+            // Nor if what was reported is the answer to a query which didn't wait for the WFT
+            if rh.answering_eager_query() {
+                return None;
+            }
 
             rh.mark_wft_complete(wft_report_status)
         } else {
@@ -498,25 +821,48 @@ impl WFStream {
     /// waiting on a cache slot
     fn reconcile_buffered(&mut self) -> Vec<ActivationOrAuto> {
         // We must ensure that there are at least as many pending evictions as there are tasks
-        // that we might need to un-buffer (skipping runs which already have buffered tasks for
-        // themselves)
+        // that we might need to un-buffer. Only idle runs are evicted, since evicting a run which
+        // has a task in flight or buffered means immediately fetching its whole history again.
+        //
+        // Independently, if cached runs exceed the byte limit, idle runs are evicted until enough
+        // bytes will be freed. The most recently used run is spared unless a buffered task needs
+        // its slot, so one run larger than the limit doesn't get evicted after every task.
         let num_in_buff = self.buffered_polls_need_cache_slot.len();
         let mut evict_these = vec![];
-        let num_existing_evictions = self
+        let (num_existing_evictions, bytes_being_evicted) = self
             .runs
             .runs_lru_order()
             .filter(|(_, h)| h.trying_to_evict().is_some())
-            .count();
+            .fold((0, 0), |(n, b), (rid, _)| {
+                (n + 1, b + self.runs.run_bytes(rid))
+            });
         let mut num_evicts_needed = num_in_buff.saturating_sub(num_existing_evictions);
-        for (rid, handle) in self.runs.runs_lru_order() {
-            if num_evicts_needed == 0 {
+        let mut bytes_to_free = self
+            .runs
+            .bytes_over_budget()
+            .saturating_sub(bytes_being_evicted);
+        let mru_ix = self.runs.len().saturating_sub(1);
+        for (ix, (rid, handle)) in self.runs.runs_lru_order().enumerate() {
+            if num_evicts_needed == 0 && bytes_to_free == 0 {
                 break;
             }
-            if !handle.has_buffered_wft() {
-                num_evicts_needed -= 1;
+            if num_evicts_needed == 0 && ix == mru_ix {
+                break;
+            }
+            if !handle.has_any_pending_work(false, false) {
+                num_evicts_needed = num_evicts_needed.saturating_sub(1);
+                bytes_to_free = bytes_to_free.saturating_sub(self.runs.run_bytes(rid));
                 evict_these.push(rid.to_string());
             }
         }
+        // If every cached run is busy, the buffered tasks simply wait for one to go idle. Since
+        // they keep holding their permits, this also holds off polling for more new runs.
+        let intake_delayed = num_evicts_needed > 0;
+        if intake_delayed && !self.intake_delayed {
+            debug!("All cached runs are busy, new runs must wait for one to become idle");
+            self.metrics.cache_intake_delayed();
+        }
+        self.intake_delayed = intake_delayed;
         let mut acts = vec![];
         for run_id in evict_these {
             acts.extend(
@@ -532,6 +878,32 @@ impl WFStream {
         acts
     }
 
+    /// Polls still waiting for a cache slot once shutdown is otherwise done will never be
+    /// processed. Hand them all back to be failed, rather than dropping them.
+    fn fail_unstarted_buffered_polls(&mut self) -> Vec<ActivationOrAuto> {
+        let waiting_for_replay = self
+            .replays
+            .take_all_queued()
+            .map(|(run_id, wft)| ActivationOrAuto::FailUnstartedTasks {
+                run_id,
+                tasks: vec![wft.work],
+                reason: SHUTDOWN_BEFORE_PROCESSING,
+            })
+            .collect::<Vec<_>>();
+        std::mem::take(&mut self.buffered_polls_need_cache_slot)
+            .into_iter()
+            .filter_map(|wfts| {
+                let run_id = wfts.first()?.work.execution.run_id.clone();
+                Some(ActivationOrAuto::FailUnstartedTasks {
+                    run_id,
+                    tasks: wfts.into_iter().map(|w| w.work).collect(),
+                    reason: SHUTDOWN_BEFORE_PROCESSING,
+                })
+            })
+            .chain(waiting_for_replay)
+            .collect()
+    }
+
     fn shutdown_done(&self) -> bool {
         if self.shutdown_token.is_cancelled() {
             if Arc::strong_count(&self.history_fetch_refcounter) > 1 {
@@ -559,7 +931,8 @@ impl WFStream {
         if let Some(r) = self.runs.peek(run_id) {
             info!(run_id, wft=?r.wft(), activation=?r.activation(),
                   buffered_wft=r.has_buffered_wft(),
-                  trying_to_evict=r.trying_to_evict().is_some(), more_work=r.more_pending_work());
+                  trying_to_evict=r.trying_to_evict().is_some(), more_work=r.more_pending_work(),
+                  machines=%r.dump_machine_state());
         } else {
             info!(run_id, "Run not found");
         }
@@ -573,9 +946,9 @@ enum WFStreamInput {
     Local(LocalInput),
     /// The stream given to us which represents the poller (or a mock) terminated.
     PollerDead,
-    /// The stream given to us which represents the poller (or a mock) encountered a non-retryable
-    /// error while polling
-    PollerError(tonic::Status),
+    /// The stream given to us which represents the poller (or a mock) encountered an error while
+    /// polling
+    PollerError(PollError),
     FailedFetch {
         run_id: String,
         err: tonic::Status,
@@ -611,18 +984,31 @@ pub(super) enum LocalInputs {
     PostActivation(Box<PostActivationMsg>),
     RequestEviction(RequestEvictMsg),
     HeartbeatTimeout(String),
+    #[from(ignore)]
+    QueryDeferralTimeout(String),
+    ActivationDeadline(ActivationDeadlineMsg),
+    /// A task queued for a history replay slot may have expired
+    #[from(ignore)]
+    ReplayQueueExpiry,
     GetStateInfo(GetStateInfoMsg),
+    ExportCacheSnapshot(ExportCacheSnapshotMsg),
+    ImportCacheSnapshot(ImportCacheSnapshotMsg),
 }
 impl LocalInputs {
     fn run_id(&self) -> Option<&str> {
         Some(match self {
             LocalInputs::Completion(c) => c.completion.run_id(),
-            LocalInputs::FetchedPageCompletion { paginator, .. } => &paginator.run_id,
+            LocalInputs::FetchedPageCompletion { paginator, .. } => paginator.run_id.as_str(),
             LocalInputs::LocalResolution(lr) => &lr.run_id,
             LocalInputs::PostActivation(pa) => &pa.run_id,
             LocalInputs::RequestEviction(re) => &re.run_id,
             LocalInputs::HeartbeatTimeout(hb) => hb,
-            LocalInputs::GetStateInfo(_) => return None,
+            LocalInputs::QueryDeferralTimeout(run_id) => run_id,
+            LocalInputs::ActivationDeadline(expired) => &expired.run_id,
+            LocalInputs::GetStateInfo(_)
+            | LocalInputs::ReplayQueueExpiry
+            | LocalInputs::ExportCacheSnapshot(_)
+            | LocalInputs::ImportCacheSnapshot(_) => return None,
         })
     }
 }
@@ -631,7 +1017,7 @@ impl LocalInputs {
 enum ExternalPollerInputs {
     NewWft(PermittedWFT),
     PollerDead,
-    PollerError(tonic::Status),
+    PollerError(PollError),
     FetchedUpdate(PermittedWFT),
     NextPage {
         paginator: HistoryPaginator,
@@ -671,8 +1057,8 @@ impl From<ExternalPollerInputs> for WFStreamInput {
         }
     }
 }
-impl From<Result<WFTExtractorOutput, tonic::Status>> for ExternalPollerInputs {
-    fn from(v: Result<WFTExtractorOutput, tonic::Status>) -> Self {
+impl From<Result<WFTExtractorOutput, PollError>> for ExternalPollerInputs {
+    fn from(v: Result<WFTExtractorOutput, PollError>) -> Self {
         match v {
             Ok(WFTExtractorOutput::NewWFT(pwft)) => ExternalPollerInputs::NewWft(pwft),
             Ok(WFTExtractorOutput::FetchResult(updated_wft, _)) => {
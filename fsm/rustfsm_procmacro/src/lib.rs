@@ -379,6 +379,10 @@ impl StateMachineDefinition {
             };
             quote! { #state_enum_name::#s(_) => #val }
         });
+        let state_name_match_arms = states.iter().map(|s| {
+            let statestr = s.to_string();
+            quote! { #state_enum_name::#s(_) => #statestr }
+        });
         let states_enum_impl = quote! {
             impl #state_enum_name {
                 fn is_final(&self) -> bool {
@@ -386,6 +390,12 @@ impl StateMachineDefinition {
                         #(#state_is_final_match_arms),*
                     }
                 }
+
+                fn name(&self) -> &'static str {
+                    match self {
+                        #(#state_name_match_arms),*
+                    }
+                }
             }
         };
 
@@ -567,6 +577,10 @@ impl StateMachineDefinition {
                   #name_str
                 }
 
+                fn static_name(&self) -> &'static str {
+                  #name_str
+                }
+
                 fn on_event(&mut self, event: #events_enum_name)
                   -> ::core::result::Result<::std::vec::Vec<Self::Command>,
                                             ::rustfsm::MachineError<Self::Error>> {
@@ -580,6 +594,10 @@ impl StateMachineDefinition {
                     self.state.as_ref().unwrap()
                 }
 
+                fn state_name(&self) -> &'static str {
+                    self.state.as_ref().unwrap().name()
+                }
+
                 fn set_state(&mut self, new: Self::State) {
                     self.state = Some(new)
                 }
@@ -28,8 +28,21 @@ pub trait StateMachine: Sized {
 
     fn name(&self) -> &str;
 
+    /// Returns the machine's name for callers which need to keep it (ex: in a log of its
+    /// transitions) without copying it. Defaults to the name of the implementing type. Machines
+    /// generated by `fsm!` return the same name as [StateMachine::name].
+    fn static_name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
     /// Returns the current state of the machine
     fn state(&self) -> &Self::State;
+    /// Returns the name of the current state, without allocating. Defaults to the name of the
+    /// state type, which doesn't tell states apart. Machines generated by `fsm!` return the name
+    /// of the current state.
+    fn state_name(&self) -> &'static str {
+        std::any::type_name::<Self::State>()
+    }
     fn set_state(&mut self, new_state: Self::State);
 
     /// Returns the current shared state of the machine
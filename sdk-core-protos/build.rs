@@ -3,11 +3,107 @@ use std::{env, path::PathBuf};
 static ALWAYS_SERDE: &str = "#[cfg_attr(not(feature = \"serde_serialize\"), \
                                derive(::serde::Serialize, ::serde::Deserialize))]";
 
+/// Bytes fields which are rendered as base64 strings when serializing with serde
+static SERDE_BASE64_FIELDS: &[&str] = &[
+    "temporal.api.common.v1.Payload.data",
+    "coresdk.activity_task.ActivityTask.task_token",
+    "coresdk.ActivityTaskCompletion.task_token",
+    "coresdk.ActivityHeartbeat.task_token",
+];
+
+/// Enum fields which are rendered as their variant names when serializing with serde, along with
+/// the module in `serde_helpers` which handles their type
+static SERDE_ENUM_FIELDS: &[(&str, &str)] = &[
+    (
+        "coresdk.activity_task.Cancel.reason",
+        "activity_cancel_reason",
+    ),
+    (
+        "coresdk.workflow_commands.ScheduleActivity.cancellation_type",
+        "activity_cancellation_type",
+    ),
+    (
+        "coresdk.workflow_commands.ScheduleLocalActivity.cancellation_type",
+        "activity_cancellation_type",
+    ),
+    (
+        "coresdk.workflow_commands.ScheduleActivity.versioning_intent",
+        "versioning_intent",
+    ),
+    (
+        "coresdk.workflow_commands.ContinueAsNewWorkflowExecution.versioning_intent",
+        "versioning_intent",
+    ),
+    (
+        "coresdk.workflow_commands.StartChildWorkflowExecution.versioning_intent",
+        "versioning_intent",
+    ),
+    (
+        "coresdk.workflow_commands.StartChildWorkflowExecution.parent_close_policy",
+        "parent_close_policy",
+    ),
+    (
+        "coresdk.workflow_commands.StartChildWorkflowExecution.workflow_id_reuse_policy",
+        "workflow_id_reuse_policy",
+    ),
+    (
+        "coresdk.workflow_commands.StartChildWorkflowExecution.cancellation_type",
+        "child_workflow_cancellation_type",
+    ),
+    (
+        "coresdk.workflow_activation.InitializeWorkflow.continued_initiator",
+        "continue_as_new_initiator",
+    ),
+    (
+        "coresdk.workflow_activation.ResolveChildWorkflowExecutionStartFailure.cause",
+        "start_child_workflow_execution_failed_cause",
+    ),
+    (
+        "coresdk.workflow_activation.RemoveFromCache.reason",
+        "eviction_reason",
+    ),
+    (
+        "coresdk.workflow_completion.Failure.force_cause",
+        "workflow_task_failed_cause",
+    ),
+    (
+        "temporal.api.failure.v1.TimeoutFailureInfo.timeout_type",
+        "timeout_type",
+    ),
+    (
+        "temporal.api.failure.v1.ActivityFailureInfo.retry_state",
+        "retry_state",
+    ),
+    (
+        "temporal.api.failure.v1.ChildWorkflowExecutionFailureInfo.retry_state",
+        "retry_state",
+    ),
+];
+
+/// Map fields generated as `BTreeMap`s, so their entries are always encoded sorted by key. Commands
+/// carrying them would otherwise encode differently every time, even for identical contents.
+///
+/// This changes the public type of these fields from what every other map field gets, and broke
+/// code constructing them from `HashMap`s directly as of 0.2.0. Build them with `.into()` (or
+/// `collect()`) instead, which works with either.
+static SORTED_MAP_FIELDS: &[&str] = &[
+    "temporal.api.common.v1.Header.fields",
+    "temporal.api.common.v1.Memo.fields",
+    "temporal.api.common.v1.SearchAttributes.indexed_fields",
+];
+
+fn serde_with(module: &str) -> String {
+    format!(
+        "#[cfg_attr(feature = \"serde_serialize\", \
+         serde(with = \"crate::serde_helpers::{module}\"))]"
+    )
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("cargo:rerun-if-changed=./protos");
     let out = PathBuf::from(env::var("OUT_DIR").unwrap());
     let descriptor_file = out.join("descriptors.bin");
-    tonic_build::configure()
+    let mut builder = tonic_build::configure()
         // We don't actually want to build the grpc definitions - we don't need them (for now).
         // Just build the message structs.
         .build_server(false)
@@ -96,26 +192,23 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             "coresdk.external_data.LocalActivityMarkerData.backoff",
             "#[serde(with = \"opt_duration\")]",
         )
-        .extern_path(
-            ".google.protobuf.Any",
-            "::prost_wkt_types::Any"
-        )
-        .extern_path(
-            ".google.protobuf.Timestamp",
-            "::prost_wkt_types::Timestamp"
-        )
-        .extern_path(
-            ".google.protobuf.Duration",
-            "::prost_wkt_types::Duration"
-        )
-        .extern_path(
-            ".google.protobuf.Value",
-            "::prost_wkt_types::Value"
-        )
-        .extern_path(
-            ".google.protobuf.FieldMask",
-            "::prost_wkt_types::FieldMask"
-        )
+        .field_attribute(
+            "temporal.api.common.v1.Payload.metadata",
+            serde_with("base64_bytes_map"),
+        );
+    for field in SERDE_BASE64_FIELDS {
+        builder = builder.field_attribute(field, serde_with("base64_bytes"));
+    }
+    for (field, module) in SERDE_ENUM_FIELDS {
+        builder = builder.field_attribute(field, serde_with(module));
+    }
+    builder
+        .extern_path(".google.protobuf.Any", "::prost_wkt_types::Any")
+        .extern_path(".google.protobuf.Timestamp", "::prost_wkt_types::Timestamp")
+        .extern_path(".google.protobuf.Duration", "::prost_wkt_types::Duration")
+        .extern_path(".google.protobuf.Value", "::prost_wkt_types::Value")
+        .extern_path(".google.protobuf.FieldMask", "::prost_wkt_types::FieldMask")
+        .btree_map(SORTED_MAP_FIELDS)
         .file_descriptor_set_path(descriptor_file)
         .skip_debug("temporal.api.common.v1.Payload")
         .compile_protos(
@@ -126,6 +219,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 "./protos/api_cloud_upstream/temporal/api/cloud/cloudservice/v1/service.proto",
                 "./protos/testsrv_upstream/temporal/api/testservice/v1/service.proto",
                 "./protos/grpc/health/v1/health.proto",
+                "./protos/api_upstream/temporal/api/errordetails/v1/message.proto",
+                "./protos/google/rpc/status.proto",
             ],
             &[
                 "./protos/api_upstream",
@@ -133,6 +228,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 "./protos/local",
                 "./protos/testsrv_upstream",
                 "./protos/grpc",
+                "./protos",
             ],
         )?;
 
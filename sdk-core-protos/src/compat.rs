@@ -0,0 +1,148 @@
+//! Guards against lang and core disagreeing about the shape of the coresdk protos. Prost drops any
+//! field it doesn't know about when decoding, so a lang built against newer protos than core
+//! would otherwise have parts of its completions (ex: new command attributes) silently ignored.
+//! Lang declares the version it was built against, and core refuses to work with any version whose
+//! messages it might not fully understand.
+
+use std::{fmt, str::FromStr};
+
+/// The version of the coresdk protos this crate was built with. The major version changes
+/// whenever a change is made that older or newer readers can't safely ignore. The minor version
+/// changes whenever fields or messages are added. A test fails if the protos change without it.
+pub const CORESDK_PROTO_VERSION: ProtoVersion = ProtoVersion { major: 1, minor: 0 };
+
+/// A coresdk proto version, written as `major.minor`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ProtoVersion {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl fmt::Display for ProtoVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+impl FromStr for ProtoVersion {
+    type Err = ProtoCompatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let malformed = || ProtoCompatError::MalformedVersion(s.to_string());
+        let (major, minor) = s.split_once('.').ok_or_else(malformed)?;
+        Ok(Self {
+            major: major.parse().map_err(|_| malformed())?,
+            minor: minor.parse().map_err(|_| malformed())?,
+        })
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum ProtoCompatError {
+    #[error("Proto version `{0}` is not of the form `major.minor`")]
+    MalformedVersion(String),
+    #[error(
+        "Lang was built against coresdk protos {lang}, which are incompatible with core's \
+         {core}. Upgrade whichever is older."
+    )]
+    IncompatibleVersion {
+        lang: ProtoVersion,
+        core: ProtoVersion,
+    },
+}
+
+/// Check that a lang built against the coresdk protos of version `lang_version` can work with
+/// this core. The major versions must match, and lang's minor version must not be newer than
+/// core's, since lang could then send fields core would drop.
+pub fn check_proto_version(lang_version: &str) -> Result<ProtoVersion, ProtoCompatError> {
+    let lang: ProtoVersion = lang_version.parse()?;
+    if lang.major != CORESDK_PROTO_VERSION.major || lang.minor > CORESDK_PROTO_VERSION.minor {
+        return Err(ProtoCompatError::IncompatibleVersion {
+            lang,
+            core: CORESDK_PROTO_VERSION,
+        });
+    }
+    Ok(lang)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use siphasher::sip::SipHasher13;
+    use std::{
+        fs,
+        hash::Hasher,
+        path::{Path, PathBuf},
+    };
+
+    /// Fingerprint of the coresdk proto definitions as of [CORESDK_PROTO_VERSION]
+    const PROTO_FINGERPRINT: &str = "1c61dfdd4791f958";
+
+    fn proto_files(dir: &Path, out: &mut Vec<PathBuf>) {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                proto_files(&path, out);
+            } else if path.extension().is_some_and(|e| e == "proto") {
+                out.push(path);
+            }
+        }
+    }
+
+    #[test]
+    fn proto_changes_come_with_a_version_bump() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("protos/local/temporal/sdk/core");
+        let mut files = vec![];
+        proto_files(&root, &mut files);
+        let mut files: Vec<_> = files
+            .into_iter()
+            .map(|p| {
+                let rel = p.strip_prefix(&root).unwrap().to_string_lossy();
+                (rel.replace('\\', "/"), p)
+            })
+            .collect();
+        files.sort();
+        assert!(!files.is_empty());
+
+        let mut hasher = SipHasher13::new();
+        for (rel, path) in files {
+            hasher.write(rel.as_bytes());
+            hasher.write(&[0]);
+            hasher.write(&fs::read(path).unwrap());
+            hasher.write(&[0]);
+        }
+        let fingerprint = format!("{:016x}", hasher.finish());
+        assert_eq!(
+            fingerprint, PROTO_FINGERPRINT,
+            "The coresdk protos changed. Bump CORESDK_PROTO_VERSION (the minor version if only \
+             fields or messages were added, otherwise the major version), then set \
+             PROTO_FINGERPRINT to {fingerprint}"
+        );
+    }
+
+    #[test]
+    fn version_check() {
+        assert_eq!(
+            check_proto_version("1.0").unwrap(),
+            ProtoVersion { major: 1, minor: 0 }
+        );
+        assert_eq!(
+            check_proto_version("2.0"),
+            Err(ProtoCompatError::IncompatibleVersion {
+                lang: ProtoVersion { major: 2, minor: 0 },
+                core: CORESDK_PROTO_VERSION,
+            })
+        );
+        assert_eq!(
+            check_proto_version("1.1"),
+            Err(ProtoCompatError::IncompatibleVersion {
+                lang: ProtoVersion { major: 1, minor: 1 },
+                core: CORESDK_PROTO_VERSION,
+            })
+        );
+        assert_eq!(
+            check_proto_version("1"),
+            Err(ProtoCompatError::MalformedVersion("1".to_string()))
+        );
+    }
+}
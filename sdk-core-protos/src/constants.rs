@@ -5,3 +5,11 @@ pub const PATCH_MARKER_NAME: &str = "core_patch";
 
 /// Used as `marker_name` field when recording local activity markers
 pub const LOCAL_ACTIVITY_MARKER_NAME: &str = "core_local_activity";
+
+/// Used as `marker_name` field when recording side effect markers
+pub const SIDE_EFFECT_MARKER_NAME: &str = "core_side_effect";
+
+/// Header attached to activities scheduled by workflows with an execution timeout, if the worker
+/// is configured to. Its value is a JSON payload holding the number of milliseconds the workflow
+/// had left before timing out, as of when it scheduled the activity.
+pub const WORKFLOW_TIME_REMAINING_HEADER: &str = "temporal-workflow-time-remaining-ms";
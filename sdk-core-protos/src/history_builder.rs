@@ -1,11 +1,11 @@
 use crate::{
-    constants::{LOCAL_ACTIVITY_MARKER_NAME, PATCH_MARKER_NAME},
+    constants::{LOCAL_ACTIVITY_MARKER_NAME, PATCH_MARKER_NAME, SIDE_EFFECT_MARKER_NAME},
     coresdk::{
         common::{
             build_has_change_marker_details, build_local_activity_marker_details,
-            NamespacedWorkflowExecution,
+            build_side_effect_marker_details, NamespacedWorkflowExecution,
         },
-        external_data::LocalActivityMarkerData,
+        external_data::{LocalActivityMarkerData, SideEffectMarkerData},
         workflow_commands::ScheduleActivity,
         AsJsonPayloadExt, IntoPayloadsExt,
     },
@@ -14,7 +14,7 @@ use crate::{
             ActivityType, Payload, Payloads, SearchAttributes, WorkflowExecution, WorkflowType,
         },
         enums::v1::{EventType, TaskQueueKind, WorkflowTaskFailedCause},
-        failure::v1::{failure, CanceledFailureInfo, Failure},
+        failure::v1::{failure, CanceledFailureInfo, Failure, ResetWorkflowFailureInfo},
         history::v1::{history_event::Attributes, *},
         taskqueue::v1::TaskQueue,
         update,
@@ -25,7 +25,7 @@ use crate::{
 use anyhow::bail;
 use prost_wkt_types::Timestamp;
 use std::{
-    collections::HashMap,
+    collections::BTreeMap,
     time::{Duration, SystemTime},
 };
 use uuid::Uuid;
@@ -243,6 +243,26 @@ impl TestHistoryBuilder {
         self.build_and_push_event(EventType::WorkflowTaskFailed, attrs.into());
     }
 
+    /// Add the workflow task failure server records when an operator resets the workflow to the
+    /// current workflow task, creating the run with id `new_run_id`
+    pub fn add_workflow_reset(&mut self, new_run_id: &str, reason: &str) {
+        let attrs = WorkflowTaskFailedEventAttributes {
+            scheduled_event_id: self.workflow_task_scheduled_event_id,
+            cause: WorkflowTaskFailedCause::ResetWorkflow.into(),
+            failure: Some(Failure {
+                message: reason.to_string(),
+                failure_info: Some(failure::FailureInfo::ResetWorkflowFailureInfo(
+                    ResetWorkflowFailureInfo::default(),
+                )),
+                ..Default::default()
+            }),
+            base_run_id: self.original_run_id.clone(),
+            new_run_id: new_run_id.into(),
+            ..Default::default()
+        };
+        self.build_and_push_event(EventType::WorkflowTaskFailed, attrs.into());
+    }
+
     pub fn add_timer_started(&mut self, timer_id: String) {
         self.add(TimerStartedEventAttributes {
             timer_id,
@@ -277,6 +297,22 @@ impl TestHistoryBuilder {
         self.build_and_push_event(EventType::MarkerRecorded, attrs.into());
     }
 
+    pub fn add_side_effect_marker(&mut self, seq: u32, mutable_id: &str, result: Payload) {
+        let attrs = MarkerRecordedEventAttributes {
+            marker_name: SIDE_EFFECT_MARKER_NAME.to_string(),
+            details: build_side_effect_marker_details(
+                SideEffectMarkerData {
+                    seq,
+                    mutable_id: mutable_id.to_string(),
+                },
+                Some(result),
+            ),
+            workflow_task_completed_event_id: self.previous_task_completed_id,
+            ..Default::default()
+        };
+        self.build_and_push_event(EventType::MarkerRecorded, attrs.into());
+    }
+
     pub fn add_local_activity_marker(
         &mut self,
         seq: u32,
@@ -434,7 +470,7 @@ impl TestHistoryBuilder {
     }
 
     pub fn add_upsert_search_attrs_for_patch(&mut self, attribs: &[String]) {
-        let mut indexed_fields = HashMap::new();
+        let mut indexed_fields = BTreeMap::new();
         indexed_fields.insert(
             "TemporalChangeVersion".to_string(),
             attribs.as_json_payload().unwrap(),
@@ -1,6 +1,6 @@
 use crate::temporal::api::{
     common::v1::WorkflowType,
-    enums::v1::{EventType, TaskQueueKind},
+    enums::v1::{EventType, TaskQueueKind, WorkflowTaskFailedCause},
     history::v1::{history_event, History, HistoryEvent, WorkflowExecutionStartedEventAttributes},
     taskqueue::v1::TaskQueue,
     workflowservice::v1::{GetWorkflowExecutionHistoryResponse, PollWorkflowTaskQueueResponse},
@@ -145,6 +145,25 @@ impl HistoryInfo {
         &self.wf_exe_started_attrs.original_execution_run_id
     }
 
+    /// The id of the run this history belongs to. Usually that's the [Self::orig_run_id], but a
+    /// run created by resetting the workflow keeps the started event of the run it was reset from.
+    /// Its id is instead recorded by the reset, as the last one in its history.
+    pub fn run_id(&self) -> &str {
+        self.events
+            .iter()
+            .rev()
+            .find_map(|e| match &e.attributes {
+                Some(history_event::Attributes::WorkflowTaskFailedEventAttributes(a))
+                    if a.cause == WorkflowTaskFailedCause::ResetWorkflow as i32
+                        && !a.new_run_id.is_empty() =>
+                {
+                    Some(a.new_run_id.as_str())
+                }
+                _ => None,
+            })
+            .unwrap_or_else(|| self.orig_run_id())
+    }
+
     /// Return total workflow task count in this history
     pub const fn wf_task_count(&self) -> usize {
         self.wf_task_count
@@ -208,7 +227,10 @@ impl From<HistoryInfo> for GetWorkflowExecutionHistoryResponse {
 
 #[cfg(test)]
 mod tests {
-    use crate::{temporal::api::enums::v1::EventType, TestHistoryBuilder};
+    use crate::{
+        temporal::api::enums::v1::{EventType, WorkflowTaskFailedCause},
+        TestHistoryBuilder,
+    };
 
     fn single_timer(timer_id: &str) -> TestHistoryBuilder {
         let mut t = TestHistoryBuilder::default();
@@ -230,6 +252,21 @@ mod tests {
         assert_eq!(8, history_info.events().len());
     }
 
+    #[test]
+    fn run_id_is_the_one_named_by_the_last_reset() {
+        let mut t = single_timer("timer1");
+        let hi = t.get_full_history_info().unwrap();
+        assert_eq!(hi.run_id(), hi.orig_run_id());
+
+        t.add_workflow_task_failed_new_id(WorkflowTaskFailedCause::ResetWorkflow, "reset-1");
+        t.add_workflow_task_scheduled_and_started();
+        t.add_workflow_task_failed_new_id(WorkflowTaskFailedCause::ResetWorkflow, "reset-2");
+        t.add_workflow_task_scheduled_and_started();
+        let hi = t.get_full_history_info().unwrap();
+        assert_eq!(hi.run_id(), "reset-2");
+        assert_ne!(hi.orig_run_id(), "reset-2");
+    }
+
     #[test]
     fn incremental_works() {
         let t = single_timer("timer1");
@@ -0,0 +1,150 @@
+//! Distinct types for the string identifiers of workflows, runs, activities and task queues, so
+//! that (for example) a run id can't be passed where a workflow id is expected. Each is a cheaply
+//! cloned `Arc<str>`, converts from and into `String` for use at the proto and FFI boundaries, and
+//! serializes as a plain string.
+//!
+//! ```compile_fail
+//! use temporal_sdk_core_protos::{RunId, WorkflowId};
+//! fn fetch_history(workflow_id: WorkflowId, run_id: RunId) {}
+//! let (wid, rid) = (WorkflowId::from("wf"), RunId::from("run"));
+//! // Swapped arguments don't compile
+//! fetch_history(rid, wid);
+//! ```
+//!
+//! ```compile_fail
+//! use temporal_sdk_core_protos::{ActivityId, TaskQueueName};
+//! let activity_id: ActivityId = TaskQueueName::from("q");
+//! ```
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    borrow::Borrow,
+    fmt::{Debug, Display, Formatter},
+    sync::Arc,
+};
+
+macro_rules! string_id {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub struct $name(Arc<str>);
+
+        impl $name {
+            /// The identifier as a string slice
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl Display for $name {
+            fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl Debug for $name {
+            fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{}({:?})", stringify!($name), &*self.0)
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(s: String) -> Self {
+                Self(s.into())
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(s: &str) -> Self {
+                Self(s.into())
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(id: $name) -> Self {
+                id.0.to_string()
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        /// Lets maps keyed by the identifier be looked up with a plain `&str`
+        impl Borrow<str> for $name {
+            fn borrow(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl PartialEq<str> for $name {
+            fn eq(&self, other: &str) -> bool {
+                &*self.0 == other
+            }
+        }
+
+        impl PartialEq<&str> for $name {
+            fn eq(&self, other: &&str) -> bool {
+                &*self.0 == *other
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(&self.0)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                String::deserialize(deserializer).map(Into::into)
+            }
+        }
+    };
+}
+
+string_id!(
+    /// Identifies a workflow execution chain across all of its runs
+    WorkflowId
+);
+string_id!(
+    /// Identifies one run of a workflow
+    RunId
+);
+string_id!(
+    /// Identifies an activity within the workflow run which scheduled it
+    ActivityId
+);
+string_id!(
+    /// The name of a task queue
+    TaskQueueName
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn converts_and_compares_like_the_string_it_wraps() {
+        let wid = WorkflowId::from("my-wf".to_string());
+        assert_eq!(wid, "my-wf");
+        assert_eq!(wid.to_string(), "my-wf");
+        assert_eq!(format!("{wid:?}"), "WorkflowId(\"my-wf\")");
+        assert_eq!(String::from(wid.clone()), "my-wf");
+        assert_eq!(wid.clone(), WorkflowId::from("my-wf"));
+
+        let runs = HashMap::from([(RunId::from("run-1"), 1), (RunId::from("run-2"), 2)]);
+        assert_eq!(runs.get("run-2"), Some(&2));
+        assert_eq!(runs.get("run-3"), None);
+    }
+
+    #[test]
+    fn serializes_as_a_plain_string() {
+        let tq = TaskQueueName::from("q");
+        assert_eq!(serde_json::to_string(&tq).unwrap(), "\"q\"");
+        assert_eq!(serde_json::from_str::<TaskQueueName>("\"q\"").unwrap(), tq);
+    }
+}
@@ -2,14 +2,20 @@
 //! the Temporal Core SDK. Language SDK authors can generate structs using the proto definitions
 //! that will match the generated structs in this module.
 
+pub mod compat;
 pub mod constants;
+pub mod rng;
 pub mod utilities;
 
 #[cfg(feature = "history_builders")]
 mod history_builder;
 #[cfg(feature = "history_builders")]
 mod history_info;
+mod ids;
+#[cfg(feature = "serde_serialize")]
+mod serde_helpers;
 mod task_token;
+mod wf_time;
 
 #[cfg(feature = "history_builders")]
 pub use history_builder::{
@@ -18,7 +24,9 @@ pub use history_builder::{
 };
 #[cfg(feature = "history_builders")]
 pub use history_info::HistoryInfo;
+pub use ids::{ActivityId, RunId, TaskQueueName, WorkflowId};
 pub use task_token::TaskToken;
+pub use wf_time::{InvalidTimeField, TimeFieldProblem, WfDuration, WfTimestamp, MAX_WF_DURATION};
 
 pub static ENCODING_PAYLOAD_KEY: &str = "encoding";
 pub static JSON_ENCODING_VAL: &str = "json/plain";
@@ -285,7 +293,7 @@ pub mod coresdk {
 
     pub mod common {
         tonic::include_proto!("coresdk.common");
-        use super::external_data::LocalActivityMarkerData;
+        use super::external_data::{LocalActivityMarkerData, SideEffectMarkerData};
         use crate::{
             coresdk::{
                 external_data::PatchedMarkerData, AsJsonPayloadExt, FromJsonPayloadExt,
@@ -365,6 +373,37 @@ pub mod coresdk {
             let result = details.remove("result").and_then(|mut p| p.payloads.pop());
             (data, result)
         }
+
+        pub fn build_side_effect_marker_details(
+            metadata: SideEffectMarkerData,
+            result: Option<Payload>,
+        ) -> HashMap<String, Payloads> {
+            let mut hm = HashMap::new();
+            if let Some(jsonified) = metadata.as_json_payload().into_payloads() {
+                hm.insert("data".to_string(), jsonified);
+            }
+            if let Some(res) = result {
+                hm.insert("result".to_string(), res.into());
+            }
+            hm
+        }
+
+        /// Given a side effect marker's detail map, returns its info and recorded value if the
+        /// marker data is well-formed
+        pub fn decode_side_effect_marker_details(
+            details: &HashMap<String, Payloads>,
+        ) -> Option<(SideEffectMarkerData, Option<Payload>)> {
+            let data = details
+                .get("data")
+                .and_then(|p| p.payloads.first())
+                .and_then(|p| std::str::from_utf8(&p.data).ok())
+                .and_then(|s| serde_json::from_str(s).ok())?;
+            let result = details
+                .get("result")
+                .and_then(|p| p.payloads.first())
+                .cloned();
+            Some((data, result))
+        }
     }
 
     pub mod external_data {
@@ -464,6 +503,7 @@ pub mod coresdk {
                 },
                 query::v1::WorkflowQuery,
             },
+            RunId,
         };
         use prost_wkt_types::Timestamp;
         use std::fmt::{Display, Formatter};
@@ -471,13 +511,13 @@ pub mod coresdk {
         tonic::include_proto!("coresdk.workflow_activation");
 
         pub fn create_evict_activation(
-            run_id: String,
+            run_id: RunId,
             message: String,
             reason: EvictionReason,
         ) -> WorkflowActivation {
             WorkflowActivation {
                 timestamp: None,
-                run_id,
+                run_id: run_id.into(),
                 is_replaying: false,
                 history_length: 0,
                 jobs: vec![WorkflowActivationJob::from(
@@ -490,6 +530,8 @@ pub mod coresdk {
                 history_size_bytes: 0,
                 continue_as_new_suggested: false,
                 build_id_for_current_task: "".to_string(),
+                metadata: None,
+                correlation_id: "".to_string(),
             }
         }
 
@@ -611,6 +653,9 @@ pub mod coresdk {
                     workflow_activation_job::Variant::NotifyHasPatch(_) => {
                         write!(f, "NotifyHasPatch")
                     }
+                    workflow_activation_job::Variant::ResolveSideEffect(r) => {
+                        write!(f, "ResolveSideEffect({})", r.seq)
+                    }
                     workflow_activation_job::Variant::ResolveChildWorkflowExecutionStart(_) => {
                         write!(f, "ResolveChildWorkflowExecutionStart")
                     }
@@ -677,24 +722,42 @@ pub mod coresdk {
         }
 
         impl From<WorkflowExecutionCancelRequestedEventAttributes> for CancelWorkflow {
-            fn from(_a: WorkflowExecutionCancelRequestedEventAttributes) -> Self {
-                Self { details: vec![] }
+            fn from(a: WorkflowExecutionCancelRequestedEventAttributes) -> Self {
+                Self {
+                    // Server only records the reason as a string, there are no payloads to pass on
+                    details: vec![],
+                    reason: a.cause,
+                    identity: a.identity,
+                    external_workflow_execution: a.external_workflow_execution,
+                    external_initiated_event_id: a.external_initiated_event_id,
+                }
             }
         }
 
-        /// Create a [InitializeWorkflow] job from corresponding event attributes
+        /// Create a [InitializeWorkflow] job from corresponding event attributes, for the run with
+        /// id `run_id`
         pub fn start_workflow_from_attribs(
             attrs: WorkflowExecutionStartedEventAttributes,
             workflow_id: String,
+            run_id: &str,
             randomness_seed: u64,
             start_time: Timestamp,
         ) -> InitializeWorkflow {
+            // A reset copies the started event of the run being reset, which keeps the id of the
+            // run that event was first written for, into a run with a new id
+            let reset_from_execution_run_id = if !attrs.original_execution_run_id.is_empty()
+                && attrs.original_execution_run_id != run_id
+            {
+                attrs.original_execution_run_id.clone()
+            } else {
+                String::new()
+            };
             InitializeWorkflow {
                 workflow_type: attrs.workflow_type.map(|wt| wt.name).unwrap_or_default(),
                 workflow_id,
                 arguments: Vec::from_payloads(attrs.input),
                 randomness_seed,
-                headers: attrs.header.unwrap_or_default().fields,
+                headers: attrs.header.map(Into::into).unwrap_or_default(),
                 identity: attrs.identity,
                 parent_workflow_info: attrs.parent_workflow_execution.map(|pe| {
                     NamespacedWorkflowExecution {
@@ -719,6 +782,8 @@ pub mod coresdk {
                 memo: attrs.memo,
                 search_attributes: attrs.search_attributes,
                 start_time: Some(start_time),
+                is_reset: !reset_from_execution_run_id.is_empty(),
+                reset_from_execution_run_id,
             }
         }
     }
@@ -741,6 +806,7 @@ pub mod coresdk {
                 Failure {
                     failure: Some(f),
                     force_cause: WorkflowTaskFailedCause::Unspecified as i32,
+                    force_eviction: false,
                 }
             }
         }
@@ -841,6 +907,12 @@ pub mod coresdk {
             }
         }
 
+        impl Display for RecordSideEffect {
+            fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                write!(f, "RecordSideEffect({})", self.seq)
+            }
+        }
+
         impl Display for StartChildWorkflowExecution {
             fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
                 write!(
@@ -953,6 +1025,8 @@ pub mod coresdk {
             Self {
                 commands: v,
                 used_internal_flags: vec![],
+                random_checksum: 0,
+                request_reactivation: false,
             }
         }
     }
@@ -980,6 +1054,7 @@ pub mod coresdk {
             Self {
                 run_id: run_id.into(),
                 status: Some(workflow_activation_completion::Status::Successful(success)),
+                ..Default::default()
             }
         }
 
@@ -989,6 +1064,7 @@ pub mod coresdk {
             Self {
                 run_id: run_id.into(),
                 status: Some(workflow_activation_completion::Status::Successful(success)),
+                ..Default::default()
             }
         }
 
@@ -998,6 +1074,7 @@ pub mod coresdk {
             Self {
                 run_id: run_id.into(),
                 status: Some(workflow_activation_completion::Status::Successful(success)),
+                ..Default::default()
             }
         }
 
@@ -1012,11 +1089,35 @@ pub mod coresdk {
                     workflow_completion::Failure {
                         failure: Some(failure),
                         force_cause: cause.unwrap_or(WorkflowTaskFailedCause::Unspecified) as i32,
+                        force_eviction: false,
+                    },
+                )),
+                ..Default::default()
+            }
+        }
+
+        /// Fail the activation and have core evict the run as part of handling the failure. See
+        /// [workflow_completion::Failure::force_eviction].
+        pub fn fail_and_evict(run_id: impl Into<String>, failure: Failure) -> Self {
+            Self {
+                run_id: run_id.into(),
+                status: Some(workflow_activation_completion::Status::Failed(
+                    workflow_completion::Failure {
+                        failure: Some(failure),
+                        force_cause: WorkflowTaskFailedCause::Unspecified as i32,
+                        force_eviction: true,
                     },
                 )),
+                ..Default::default()
             }
         }
 
+        /// Echo the correlation id of the activation being completed
+        pub fn with_correlation_id(mut self, correlation_id: impl Into<String>) -> Self {
+            self.correlation_id = correlation_id.into();
+            self
+        }
+
         /// Returns true if the activation has either a fail, continue, cancel, or complete workflow
         /// execution command in it.
         pub fn has_execution_ending(&self) -> bool {
@@ -1125,6 +1226,7 @@ pub mod coresdk {
             WorkflowActivationCompletion {
                 run_id,
                 status: Some(workflow_activation_completion::Status::Successful(success)),
+                ..Default::default()
             }
         }
     }
@@ -1199,6 +1301,7 @@ pub mod coresdk {
                         heartbeat_timeout: r.heartbeat_timeout,
                         retry_policy: r.retry_policy.map(Into::into),
                         is_local: false,
+                        previous_retry_delay: None,
                     },
                 )),
             }
@@ -1499,6 +1602,7 @@ pub mod temporal {
                         common::v1::{ActivityType, WorkflowType},
                         enums::v1::CommandType,
                     },
+                    InvalidTimeField, WfDuration,
                 };
                 use command::Attributes;
                 use std::fmt::{Display, Formatter};
@@ -1609,28 +1713,46 @@ pub mod temporal {
                     }
                 }
 
+                /// Convert lang's schedule activity command to the API command attributes. Fails
+                /// if any of the timeouts is set to an invalid duration.
                 pub fn schedule_activity_cmd_to_api(
                     s: workflow_commands::ScheduleActivity,
                     use_workflow_build_id: bool,
-                ) -> command::Attributes {
-                    command::Attributes::ScheduleActivityTaskCommandAttributes(
+                ) -> Result<command::Attributes, InvalidTimeField> {
+                    let timeout = |field, d: Option<prost_wkt_types::Duration>| {
+                        WfDuration::optional(field, d.as_ref()).map(|d| d.map(Into::into))
+                    };
+                    Ok(command::Attributes::ScheduleActivityTaskCommandAttributes(
                         ScheduleActivityTaskCommandAttributes {
                             activity_id: s.activity_id,
                             activity_type: Some(ActivityType {
                                 name: s.activity_type,
                             }),
                             task_queue: Some(s.task_queue.into()),
-                            header: Some(s.headers.into()),
+                            header: if s.headers.is_empty() {
+                                None
+                            } else {
+                                Some(s.headers.into())
+                            },
                             input: s.arguments.into_payloads(),
-                            schedule_to_close_timeout: s.schedule_to_close_timeout,
-                            schedule_to_start_timeout: s.schedule_to_start_timeout,
-                            start_to_close_timeout: s.start_to_close_timeout,
-                            heartbeat_timeout: s.heartbeat_timeout,
+                            schedule_to_close_timeout: timeout(
+                                "schedule_to_close_timeout",
+                                s.schedule_to_close_timeout,
+                            )?,
+                            schedule_to_start_timeout: timeout(
+                                "schedule_to_start_timeout",
+                                s.schedule_to_start_timeout,
+                            )?,
+                            start_to_close_timeout: timeout(
+                                "start_to_close_timeout",
+                                s.start_to_close_timeout,
+                            )?,
+                            heartbeat_timeout: timeout("heartbeat_timeout", s.heartbeat_timeout)?,
                             retry_policy: s.retry_policy.map(Into::into),
                             request_eager_execution: !s.do_not_eagerly_execute,
                             use_workflow_build_id,
                         },
-                    )
+                    ))
                 }
 
                 pub fn start_child_workflow_cmd_to_api(
@@ -1646,7 +1768,11 @@ pub mod temporal {
                             control: "".into(),
                             namespace: s.namespace,
                             task_queue: Some(s.task_queue.into()),
-                            header: Some(s.headers.into()),
+                            header: if s.headers.is_empty() {
+                                None
+                            } else {
+                                Some(s.headers.into())
+                            },
                             memo: Some(s.memo.into()),
                             search_attributes: Some(s.search_attributes.into()),
                             input: s.input.into_payloads(),
@@ -1847,6 +1973,22 @@ pub mod temporal {
                     }
                 }
 
+                impl From<HashMap<String, Payload>> for Header {
+                    fn from(h: HashMap<String, Payload>) -> Self {
+                        Self {
+                            fields: h.into_iter().collect(),
+                        }
+                    }
+                }
+
+                impl From<HashMap<String, Payload>> for Memo {
+                    fn from(h: HashMap<String, Payload>) -> Self {
+                        Self {
+                            fields: h.into_iter().collect(),
+                        }
+                    }
+                }
+
                 impl From<HashMap<String, Payload>> for SearchAttributes {
                     fn from(h: HashMap<String, Payload>) -> Self {
                         Self {
@@ -1889,6 +2031,11 @@ pub mod temporal {
                 tonic::include_proto!("temporal.api.enums.v1");
             }
         }
+        pub mod errordetails {
+            pub mod v1 {
+                tonic::include_proto!("temporal.api.errordetails.v1");
+            }
+        }
         pub mod failure {
             pub mod v1 {
                 tonic::include_proto!("temporal.api.failure.v1");
@@ -1902,7 +2049,8 @@ pub mod temporal {
         pub mod history {
             pub mod v1 {
                 use crate::temporal::api::{
-                    enums::v1::EventType, history::v1::history_event::Attributes,
+                    enums::v1::{CommandType, EventType},
+                    history::v1::history_event::Attributes,
                 };
                 use anyhow::bail;
                 use prost::alloc::fmt::Formatter;
@@ -1934,28 +2082,101 @@ pub mod temporal {
                     }
                 }
 
+                /// Each command type, with the types of event it can be recorded as. Both
+                /// [HistoryEvent::is_command_event] and [command_event_types] read this, so what
+                /// counts as a command event and what each command may produce always agree.
+                const COMMAND_EVENT_TYPES: &[(CommandType, &[EventType])] = &[
+                    (
+                        CommandType::ScheduleActivityTask,
+                        &[EventType::ActivityTaskScheduled],
+                    ),
+                    (
+                        CommandType::RequestCancelActivityTask,
+                        &[EventType::ActivityTaskCancelRequested],
+                    ),
+                    (CommandType::StartTimer, &[EventType::TimerStarted]),
+                    (CommandType::CancelTimer, &[EventType::TimerCanceled]),
+                    (
+                        CommandType::CompleteWorkflowExecution,
+                        &[EventType::WorkflowExecutionCompleted],
+                    ),
+                    (
+                        CommandType::FailWorkflowExecution,
+                        &[EventType::WorkflowExecutionFailed],
+                    ),
+                    (
+                        CommandType::CancelWorkflowExecution,
+                        &[EventType::WorkflowExecutionCanceled],
+                    ),
+                    (
+                        CommandType::ContinueAsNewWorkflowExecution,
+                        &[EventType::WorkflowExecutionContinuedAsNew],
+                    ),
+                    (CommandType::RecordMarker, &[EventType::MarkerRecorded]),
+                    (
+                        CommandType::StartChildWorkflowExecution,
+                        &[EventType::StartChildWorkflowExecutionInitiated],
+                    ),
+                    (
+                        CommandType::SignalExternalWorkflowExecution,
+                        &[EventType::SignalExternalWorkflowExecutionInitiated],
+                    ),
+                    (
+                        CommandType::RequestCancelExternalWorkflowExecution,
+                        &[EventType::RequestCancelExternalWorkflowExecutionInitiated],
+                    ),
+                    (
+                        CommandType::UpsertWorkflowSearchAttributes,
+                        &[EventType::UpsertWorkflowSearchAttributes],
+                    ),
+                    (
+                        CommandType::ModifyWorkflowProperties,
+                        &[EventType::WorkflowPropertiesModified],
+                    ),
+                    (
+                        CommandType::ProtocolMessage,
+                        &[
+                            EventType::WorkflowExecutionUpdateAccepted,
+                            EventType::WorkflowExecutionUpdateRejected,
+                            EventType::WorkflowExecutionUpdateCompleted,
+                        ],
+                    ),
+                ];
+
+                /// Returns the types of event a command of `command_type` can be recorded as, or
+                /// `None` if it is not a command type this knows
+                pub fn command_event_types(
+                    command_type: CommandType,
+                ) -> Option<&'static [EventType]> {
+                    COMMAND_EVENT_TYPES
+                        .iter()
+                        .find(|(ct, _)| *ct == command_type)
+                        .map(|(_, event_types)| *event_types)
+                }
+
                 impl HistoryEvent {
                     /// Returns true if this is an event created to mirror a command
                     pub fn is_command_event(&self) -> bool {
-                        EventType::try_from(self.event_type).map_or(false, |et| match et {
-                            EventType::ActivityTaskScheduled
-                            | EventType::ActivityTaskCancelRequested
-                            | EventType::MarkerRecorded
-                            | EventType::RequestCancelExternalWorkflowExecutionInitiated
-                            | EventType::SignalExternalWorkflowExecutionInitiated
-                            | EventType::StartChildWorkflowExecutionInitiated
-                            | EventType::TimerCanceled
-                            | EventType::TimerStarted
-                            | EventType::UpsertWorkflowSearchAttributes
-                            | EventType::WorkflowPropertiesModified
-                            | EventType::WorkflowExecutionCanceled
-                            | EventType::WorkflowExecutionCompleted
-                            | EventType::WorkflowExecutionContinuedAsNew
-                            | EventType::WorkflowExecutionFailed
-                            | EventType::WorkflowExecutionUpdateAccepted
-                            | EventType::WorkflowExecutionUpdateRejected
-                            | EventType::WorkflowExecutionUpdateCompleted => true,
-                            _ => false,
+                        EventType::try_from(self.event_type).map_or(false, |et| {
+                            COMMAND_EVENT_TYPES
+                                .iter()
+                                .any(|(_, event_types)| event_types.contains(&et))
+                        })
+                    }
+
+                    /// Returns true if this is an event server records on its own which only
+                    /// carries metadata about the execution. It mirrors no command, drives no state
+                    /// machine, and has no activation job, so the machines skip it.
+                    ///
+                    /// `WorkflowExecutionUpdateAdmitted` is also recorded by server without a
+                    /// command, but is not included since it becomes a `DoUpdate` job.
+                    pub fn is_metadata_event(&self) -> bool {
+                        EventType::try_from(self.event_type).map_or(false, |et| {
+                            matches!(
+                                et,
+                                EventType::WorkflowPropertiesModifiedExternally
+                                    | EventType::ActivityPropertiesModifiedExternally
+                            )
                         })
                     }
 
@@ -2298,10 +2519,50 @@ pub mod grpc {
     }
 }
 
+#[allow(
+    clippy::all,
+    missing_docs,
+    rustdoc::broken_intra_doc_links,
+    rustdoc::bare_urls
+)]
+pub mod google {
+    pub mod rpc {
+        tonic::include_proto!("google.rpc");
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::temporal::api::failure::v1::Failure;
+    use crate::{
+        coresdk::{
+            activity_task::{activity_task, ActivityTask, Start},
+            workflow_activation::{start_workflow_from_attribs, CancelWorkflow, SignalWorkflow},
+            workflow_commands::{ContinueAsNewWorkflowExecution, ScheduleActivity},
+        },
+        temporal::api::{
+            command::v1::{
+                command, continue_as_new_cmd_to_api, schedule_activity_cmd_to_api,
+                ContinueAsNewWorkflowExecutionCommandAttributes,
+            },
+            common::v1::{
+                ActivityType, Header, Payload, Payloads, RetryPolicy, WorkflowExecution,
+                WorkflowType,
+            },
+            enums::v1::ContinueAsNewInitiator,
+            failure::v1::Failure,
+            history::v1::{
+                WorkflowExecutionCancelRequestedEventAttributes,
+                WorkflowExecutionSignaledEventAttributes, WorkflowExecutionStartedEventAttributes,
+            },
+            workflowservice::v1::PollActivityTaskQueueResponse,
+        },
+    };
     use anyhow::anyhow;
+    use prost::Message;
+    use std::{
+        collections::{BTreeMap, HashMap},
+        time::{Duration, SystemTime},
+    };
 
     #[test]
     fn anyhow_to_failure_conversion() {
@@ -2316,4 +2577,338 @@ mod tests {
         assert_eq!(as_fail.cause.as_ref().unwrap().message, "fail 2");
         assert_eq!(as_fail.cause.unwrap().cause.unwrap().message, "fail 1");
     }
+
+    #[test]
+    fn start_job_carries_retry_and_cron_info() {
+        let expiration = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let retry_policy = RetryPolicy {
+            maximum_attempts: 5,
+            ..Default::default()
+        };
+        let attrs = WorkflowExecutionStartedEventAttributes {
+            retry_policy: Some(retry_policy.clone()),
+            attempt: 3,
+            cron_schedule: "0 * * * *".to_string(),
+            workflow_execution_expiration_time: Some(expiration.into()),
+            first_workflow_task_backoff: Some(Duration::from_secs(60).try_into().unwrap()),
+            ..Default::default()
+        };
+        let init = start_workflow_from_attribs(
+            attrs,
+            "wid".to_string(),
+            "run",
+            1,
+            SystemTime::now().into(),
+        );
+        assert_eq!(init.retry_policy, Some(retry_policy));
+        assert_eq!(init.attempt, 3);
+        assert_eq!(init.cron_schedule, "0 * * * *");
+        assert_eq!(
+            init.workflow_execution_expiration_time,
+            Some(expiration.into())
+        );
+        assert_eq!(
+            init.cron_schedule_to_schedule_interval,
+            Some(Duration::from_secs(60).try_into().unwrap())
+        );
+    }
+
+    #[test]
+    fn start_job_carries_cron_continuation_info() {
+        let last_result = Payloads {
+            payloads: vec![Payload::from(vec![1])],
+        };
+        let failure = Failure {
+            message: "previous run failed".to_string(),
+            ..Default::default()
+        };
+        let cron_run = start_workflow_from_attribs(
+            WorkflowExecutionStartedEventAttributes {
+                cron_schedule: "*/5 * * * *".to_string(),
+                continued_execution_run_id: "previous".to_string(),
+                initiator: ContinueAsNewInitiator::CronSchedule as i32,
+                last_completion_result: Some(last_result.clone()),
+                continued_failure: Some(failure.clone()),
+                first_execution_run_id: "first".to_string(),
+                attempt: 2,
+                ..Default::default()
+            },
+            "wid".to_string(),
+            "current",
+            1,
+            SystemTime::now().into(),
+        );
+        assert_eq!(cron_run.cron_schedule, "*/5 * * * *");
+        assert_eq!(cron_run.continued_from_execution_run_id, "previous");
+        assert_eq!(
+            cron_run.continued_initiator,
+            ContinueAsNewInitiator::CronSchedule as i32
+        );
+        assert_eq!(cron_run.last_completion_result, Some(last_result));
+        assert_eq!(cron_run.continued_failure, Some(failure));
+        assert_eq!(cron_run.first_execution_run_id, "first");
+        assert_eq!(cron_run.attempt, 2);
+
+        let fresh_run = start_workflow_from_attribs(
+            WorkflowExecutionStartedEventAttributes {
+                first_execution_run_id: "current".to_string(),
+                attempt: 1,
+                ..Default::default()
+            },
+            "wid".to_string(),
+            "current",
+            1,
+            SystemTime::now().into(),
+        );
+        assert_eq!(fresh_run.cron_schedule, "");
+        assert_eq!(fresh_run.continued_from_execution_run_id, "");
+        assert_eq!(
+            fresh_run.continued_initiator,
+            ContinueAsNewInitiator::Unspecified as i32
+        );
+        assert_eq!(fresh_run.last_completion_result, None);
+        assert_eq!(fresh_run.continued_failure, None);
+        assert_eq!(fresh_run.first_execution_run_id, "current");
+        assert_eq!(fresh_run.attempt, 1);
+    }
+
+    #[test]
+    fn start_job_carries_reset_info() {
+        let attrs = |original_run_id: &str| WorkflowExecutionStartedEventAttributes {
+            original_execution_run_id: original_run_id.to_string(),
+            first_execution_run_id: "first".to_string(),
+            ..Default::default()
+        };
+        let start = |attrs, run_id| {
+            start_workflow_from_attribs(
+                attrs,
+                "wid".to_string(),
+                run_id,
+                1,
+                SystemTime::now().into(),
+            )
+        };
+
+        let reset = start(attrs("first"), "after_reset");
+        assert!(reset.is_reset);
+        assert_eq!(reset.reset_from_execution_run_id, "first");
+
+        let not_reset = start(attrs("first"), "first");
+        assert!(!not_reset.is_reset);
+        assert_eq!(not_reset.reset_from_execution_run_id, "");
+        // Histories written without the original run id can't be told apart, and aren't resets
+        let unknown = start(attrs(""), "first");
+        assert!(!unknown.is_reset);
+    }
+
+    #[test]
+    fn headers_are_carried_through_signals_and_activity_commands() {
+        let headers = HashMap::from([(
+            "traceparent".to_string(),
+            Payload::from(b"00-abc-def-01".to_vec()),
+        )]);
+        let signal: SignalWorkflow = WorkflowExecutionSignaledEventAttributes {
+            signal_name: "sig".to_string(),
+            header: Some(headers.clone().into()),
+            ..Default::default()
+        }
+        .into();
+        assert_eq!(signal.headers, headers);
+
+        let schedule_header = |headers| {
+            let attrs = schedule_activity_cmd_to_api(
+                ScheduleActivity {
+                    headers,
+                    ..Default::default()
+                },
+                false,
+            )
+            .unwrap();
+            match attrs {
+                command::Attributes::ScheduleActivityTaskCommandAttributes(a) => a.header,
+                other => panic!("Unexpected attributes {other:?}"),
+            }
+        };
+        assert_eq!(
+            schedule_header(headers.clone()),
+            Some(Header {
+                fields: headers.clone().into_iter().collect()
+            })
+        );
+        // Nothing is sent for activities scheduled without headers
+        assert_eq!(schedule_header(HashMap::new()), None);
+    }
+
+    #[test]
+    fn cancel_job_carries_reason_and_requester() {
+        let requester = WorkflowExecution {
+            workflow_id: "parent".to_string(),
+            run_id: "parent-run".to_string(),
+        };
+        let cancel: CancelWorkflow = WorkflowExecutionCancelRequestedEventAttributes {
+            cause: "no longer needed".to_string(),
+            external_initiated_event_id: 7,
+            external_workflow_execution: Some(requester.clone()),
+            identity: "parent-worker".to_string(),
+        }
+        .into();
+        assert_eq!(
+            cancel,
+            CancelWorkflow {
+                details: vec![],
+                reason: "no longer needed".to_string(),
+                identity: "parent-worker".to_string(),
+                external_workflow_execution: Some(requester),
+                external_initiated_event_id: 7,
+            }
+        );
+        // Cancels requested by clients don't have an initiating workflow
+        let cancel: CancelWorkflow = WorkflowExecutionCancelRequestedEventAttributes {
+            identity: "tctl".to_string(),
+            ..Default::default()
+        }
+        .into();
+        assert_eq!(cancel.identity, "tctl");
+        assert_eq!(cancel.external_workflow_execution, None);
+    }
+
+    #[test]
+    fn activity_start_carries_every_poll_response_field() {
+        let at = |secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+        let dur = |secs| Duration::from_secs(secs).try_into().unwrap();
+        let payload = |b: u8| Payload::from(vec![b]);
+        let retry_policy = RetryPolicy {
+            initial_interval: Some(dur(1)),
+            backoff_coefficient: 2.0,
+            maximum_interval: Some(dur(100)),
+            maximum_attempts: 5,
+            non_retryable_error_types: vec!["Fatal".to_string()],
+        };
+        let execution = WorkflowExecution {
+            workflow_id: "wid".to_string(),
+            run_id: "rid".to_string(),
+        };
+        let task = ActivityTask::start_from_poll_resp(PollActivityTaskQueueResponse {
+            task_token: vec![1, 2, 3],
+            workflow_namespace: "ns".to_string(),
+            workflow_type: Some(WorkflowType {
+                name: "wf-type".to_string(),
+            }),
+            workflow_execution: Some(execution.clone()),
+            activity_type: Some(ActivityType {
+                name: "act-type".to_string(),
+            }),
+            activity_id: "act-id".to_string(),
+            header: Some(Header {
+                fields: BTreeMap::from([("h".to_string(), payload(1))]),
+            }),
+            input: Some(Payloads {
+                payloads: vec![payload(2), payload(3)],
+            }),
+            heartbeat_details: Some(Payloads {
+                payloads: vec![payload(4)],
+            }),
+            scheduled_time: Some(at(10).into()),
+            current_attempt_scheduled_time: Some(at(20).into()),
+            started_time: Some(at(21).into()),
+            attempt: 3,
+            schedule_to_close_timeout: Some(dur(60)),
+            start_to_close_timeout: Some(dur(30)),
+            heartbeat_timeout: Some(dur(5)),
+            retry_policy: Some(retry_policy.clone()),
+        });
+        assert_eq!(task.task_token, vec![1, 2, 3]);
+        assert_eq!(
+            task.variant,
+            Some(activity_task::Variant::Start(Start {
+                workflow_namespace: "ns".to_string(),
+                workflow_type: "wf-type".to_string(),
+                workflow_execution: Some(execution),
+                activity_id: "act-id".to_string(),
+                activity_type: "act-type".to_string(),
+                header_fields: HashMap::from([("h".to_string(), payload(1))]),
+                input: vec![payload(2), payload(3)],
+                heartbeat_details: vec![payload(4)],
+                scheduled_time: Some(at(10).into()),
+                current_attempt_scheduled_time: Some(at(20).into()),
+                started_time: Some(at(21).into()),
+                attempt: 3,
+                schedule_to_close_timeout: Some(dur(60)),
+                start_to_close_timeout: Some(dur(30)),
+                heartbeat_timeout: Some(dur(5)),
+                retry_policy: Some(retry_policy),
+                is_local: false,
+                previous_retry_delay: None,
+            }))
+        );
+    }
+
+    fn continue_as_new_attrs(
+        cmd: ContinueAsNewWorkflowExecution,
+    ) -> ContinueAsNewWorkflowExecutionCommandAttributes {
+        match continue_as_new_cmd_to_api(cmd, false) {
+            command::Attributes::ContinueAsNewWorkflowExecutionCommandAttributes(a) => a,
+            other => panic!("Unexpected attributes {other:?}"),
+        }
+    }
+
+    #[test]
+    fn identical_commands_encode_to_identical_bytes() {
+        // Each map is filled in the opposite order, and hashes with its own seed
+        let keys = (0..32).map(|i| format!("key-{i}")).collect::<Vec<_>>();
+        let map = |reversed: bool| -> HashMap<String, Payload> {
+            let mut ordered = keys.iter().collect::<Vec<_>>();
+            if reversed {
+                ordered.reverse();
+            }
+            ordered
+                .into_iter()
+                .map(|k| (k.clone(), Payload::from(k.as_bytes())))
+                .collect()
+        };
+        let encoded = |reversed: bool| {
+            continue_as_new_attrs(ContinueAsNewWorkflowExecution {
+                workflow_type: "wf".to_string(),
+                headers: map(reversed),
+                memo: map(reversed),
+                search_attributes: map(reversed),
+                ..Default::default()
+            })
+            .encode_to_vec()
+        };
+        assert_eq!(encoded(false), encoded(true));
+    }
+
+    #[test]
+    fn map_entries_are_encoded_sorted_by_key() {
+        let payload = |b: u8| Payload {
+            data: vec![b],
+            ..Default::default()
+        };
+        let attrs = continue_as_new_attrs(ContinueAsNewWorkflowExecution {
+            workflow_type: "wf".to_string(),
+            task_queue: "q".to_string(),
+            headers: HashMap::from([("b".to_string(), payload(2)), ("a".to_string(), payload(1))]),
+            memo: HashMap::from([("d".to_string(), payload(4)), ("c".to_string(), payload(3))]),
+            search_attributes: HashMap::from([
+                ("f".to_string(), payload(6)),
+                ("e".to_string(), payload(5)),
+            ]),
+            ..Default::default()
+        });
+        let golden: [&[u8]; 8] = [
+            // Workflow type and task queue
+            b"\x0a\x04\x0a\x02\x77\x66",
+            b"\x12\x05\x0a\x01\x71\x10\x01",
+            // Header, memo, and search attributes: each entry is its key then a payload holding
+            // just its data
+            b"\x62\x14",
+            b"\x0a\x08\x0a\x01\x61\x12\x03\x12\x01\x01\x0a\x08\x0a\x01\x62\x12\x03\x12\x01\x02",
+            b"\x6a\x14",
+            b"\x0a\x08\x0a\x01\x63\x12\x03\x12\x01\x03\x0a\x08\x0a\x01\x64\x12\x03\x12\x01\x04",
+            b"\x72\x14",
+            b"\x0a\x08\x0a\x01\x65\x12\x03\x12\x01\x05\x0a\x08\x0a\x01\x66\x12\x03\x12\x01\x06",
+        ];
+        assert_eq!(attrs.encode_to_vec(), golden.concat());
+    }
 }
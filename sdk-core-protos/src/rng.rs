@@ -0,0 +1,115 @@
+//! The reference pseudo-random number generator for workflow code. Workflows must draw the same
+//! random values every time they are replayed, so lang seeds its generator with the randomness
+//! seed core delivers in `InitializeWorkflow`, and re-seeds it whenever an `UpdateRandomSeed` job
+//! arrives. Lang need not use this generator, but if it does (or reproduces it exactly), it can
+//! report [random_checksum] of its current seed in each completion so core can check that it was
+//! re-seeded correctly.
+//!
+//! The generator is PCG32 (the XSH-RR 64/32 variant, see <https://www.pcg-random.org>), using the
+//! reference seeding procedure and the default increment. Its output must never change.
+
+/// Multiplier of the underlying 64-bit linear congruential generator
+const MULTIPLIER: u64 = 6364136223846793005;
+/// Increment of the underlying LCG. Must be odd.
+const INCREMENT: u64 = 1442695040888963407;
+
+/// A deterministic PCG32 generator. See the [module docs](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeterministicRng {
+    state: u64,
+    increment: u64,
+}
+
+/// Create the reference generator for a workflow randomness seed
+pub fn deterministic_rng(seed: u64) -> DeterministicRng {
+    DeterministicRng::with_increment(seed, INCREMENT)
+}
+
+/// The checksum lang reports in a completion when it uses the reference generator: the first
+/// value drawn from a fresh generator for the seed it is currently using
+pub fn random_checksum(seed: u64) -> u64 {
+    deterministic_rng(seed).next_u64()
+}
+
+impl DeterministicRng {
+    fn with_increment(seed: u64, increment: u64) -> Self {
+        let mut rng = Self {
+            state: 0,
+            increment,
+        };
+        rng.step();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.step();
+        rng
+    }
+
+    fn step(&mut self) {
+        self.state = self
+            .state
+            .wrapping_mul(MULTIPLIER)
+            .wrapping_add(self.increment);
+    }
+
+    /// Draw 32 random bits
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.step();
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+
+    /// Draw 64 random bits, made of two draws of 32 with the first as the high half
+    pub fn next_u64(&mut self) -> u64 {
+        let high = self.next_u32() as u64;
+        (high << 32) | self.next_u32() as u64
+    }
+
+    /// Draw a float uniformly from `[0, 1)`, from the top 53 bits of [Self::next_u64]
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_pcg32_reference() {
+        // Output of the reference implementation's demo, seeded with `pcg32_srandom(42, 54)`
+        let mut rng = DeterministicRng::with_increment(42, (54 << 1) | 1);
+        let drawn: Vec<_> = (0..6).map(|_| rng.next_u32()).collect();
+        assert_eq!(
+            drawn,
+            [0xa15c02b7, 0x7b47f409, 0xba1d3330, 0x83d2f293, 0xbfa4784b, 0xcbed606e]
+        );
+    }
+
+    /// Vectors for lang implementations to check themselves against
+    #[test]
+    fn test_vectors() {
+        for (seed, expected) in [
+            (0, [0xe823a24e, 0x7a7ecbd9, 0x89fd6c06, 0xae646aa8]),
+            (42, [0xc2f57bd6, 0x6b07c4a9, 0x72b7b29b, 0x44215383]),
+            (
+                0xdeadbeefcafebabe,
+                [0x1a8ac206, 0x63725ced, 0x82d3b8ed, 0x1aba2865],
+            ),
+            (u64::MAX, [0xd9313036, 0xcd4b6992, 0x7b8ec69e, 0x999dd010]),
+        ] {
+            let mut rng = deterministic_rng(seed);
+            let drawn: Vec<_> = (0..4).map(|_| rng.next_u32()).collect();
+            assert_eq!(drawn, expected, "seed {seed:#x}");
+        }
+        assert_eq!(random_checksum(42), 0xc2f57bd66b07c4a9);
+        assert_eq!(random_checksum(u64::MAX), 0xd9313036cd4b6992);
+    }
+
+    #[test]
+    fn floats_are_in_unit_interval() {
+        let mut rng = deterministic_rng(7);
+        for _ in 0..1000 {
+            assert!((0.0..1.0).contains(&rng.next_f64()));
+        }
+    }
+}
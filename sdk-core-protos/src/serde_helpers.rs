@@ -0,0 +1,360 @@
+//! Serde (de)serialization of a few protobuf field types which aren't nice to look at with the
+//! default derives - bytes are rendered as base64 strings, and enums by their variant names rather
+//! than numbers. Used by the field attributes set up in `build.rs` when the `serde_serialize`
+//! feature is enabled.
+
+use crate::coresdk::{
+    activity_task::ActivityTask, workflow_activation::WorkflowActivation,
+    workflow_completion::WorkflowActivationCompletion, ActivityTaskCompletion,
+};
+use base64::{prelude::BASE64_STANDARD, Engine};
+use serde::{de::Error, Deserialize, Deserializer, Serializer};
+use std::collections::HashMap;
+
+pub(crate) mod base64_bytes {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(value: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&BASE64_STANDARD.encode(value))
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<u8>, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        BASE64_STANDARD.decode(s).map_err(D::Error::custom)
+    }
+}
+
+pub(crate) mod base64_bytes_map {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        value: &HashMap<String, Vec<u8>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(value.iter().map(|(k, v)| (k, BASE64_STANDARD.encode(v))))
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<String, Vec<u8>>, D::Error> {
+        HashMap::<String, String>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(k, v)| Ok((k, BASE64_STANDARD.decode(v).map_err(D::Error::custom)?)))
+            .collect()
+    }
+}
+
+/// Accepted when deserializing enum fields. Numbers are allowed so that values this version of
+/// the protos doesn't know about survive a round trip.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NameOrNumber {
+    Name(String),
+    Number(i32),
+}
+
+macro_rules! enum_as_name {
+    ($mod_name:ident, $enum_type:ty) => {
+        pub(crate) mod $mod_name {
+            use super::*;
+
+            pub(crate) fn serialize<S: Serializer>(
+                value: &i32,
+                serializer: S,
+            ) -> Result<S::Ok, S::Error> {
+                match <$enum_type>::try_from(*value) {
+                    Ok(e) => serializer.serialize_str(e.as_str_name()),
+                    Err(_) => serializer.serialize_i32(*value),
+                }
+            }
+
+            pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+                deserializer: D,
+            ) -> Result<i32, D::Error> {
+                match NameOrNumber::deserialize(deserializer)? {
+                    NameOrNumber::Name(name) => <$enum_type>::from_str_name(&name)
+                        .map(|e| e as i32)
+                        .ok_or_else(|| {
+                            D::Error::custom(format!(
+                                "unknown {} variant `{name}`",
+                                stringify!($enum_type)
+                            ))
+                        }),
+                    NameOrNumber::Number(n) => Ok(n),
+                }
+            }
+        }
+    };
+}
+
+enum_as_name!(
+    activity_cancel_reason,
+    crate::coresdk::activity_task::ActivityCancelReason
+);
+enum_as_name!(
+    activity_cancellation_type,
+    crate::coresdk::workflow_commands::ActivityCancellationType
+);
+enum_as_name!(versioning_intent, crate::coresdk::common::VersioningIntent);
+enum_as_name!(
+    parent_close_policy,
+    crate::coresdk::child_workflow::ParentClosePolicy
+);
+enum_as_name!(
+    child_workflow_cancellation_type,
+    crate::coresdk::child_workflow::ChildWorkflowCancellationType
+);
+enum_as_name!(
+    start_child_workflow_execution_failed_cause,
+    crate::coresdk::child_workflow::StartChildWorkflowExecutionFailedCause
+);
+enum_as_name!(
+    eviction_reason,
+    crate::coresdk::workflow_activation::remove_from_cache::EvictionReason
+);
+enum_as_name!(
+    workflow_id_reuse_policy,
+    crate::temporal::api::enums::v1::WorkflowIdReusePolicy
+);
+enum_as_name!(
+    continue_as_new_initiator,
+    crate::temporal::api::enums::v1::ContinueAsNewInitiator
+);
+enum_as_name!(
+    workflow_task_failed_cause,
+    crate::temporal::api::enums::v1::WorkflowTaskFailedCause
+);
+enum_as_name!(timeout_type, crate::temporal::api::enums::v1::TimeoutType);
+enum_as_name!(retry_state, crate::temporal::api::enums::v1::RetryState);
+
+macro_rules! impl_to_json_pretty {
+    ($($t:ty),+) => {
+        $(
+            impl $t {
+                /// Render as pretty-printed JSON, with payload bytes as base64 and enums by name
+                pub fn to_json_pretty(&self) -> serde_json::Result<String> {
+                    serde_json::to_string_pretty(self)
+                }
+            }
+        )+
+    };
+}
+
+impl_to_json_pretty!(
+    WorkflowActivation,
+    WorkflowActivationCompletion,
+    ActivityTask,
+    ActivityTaskCompletion
+);
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coresdk::{
+            activity_result::ActivityExecutionResult,
+            activity_task::{self, ActivityCancelReason, ActivityTask},
+            child_workflow::{ChildWorkflowCancellationType, ParentClosePolicy},
+            common::VersioningIntent,
+            workflow_activation::{
+                remove_from_cache::EvictionReason, workflow_activation_job, InitializeWorkflow,
+                RemoveFromCache, WorkflowActivation, WorkflowActivationJob,
+            },
+            workflow_commands::{
+                ActivityCancellationType, ScheduleActivity, StartChildWorkflowExecution,
+            },
+            workflow_completion::WorkflowActivationCompletion,
+            ActivityTaskCompletion,
+        },
+        temporal::api::{
+            common::v1::{Payload, RetryPolicy, WorkflowExecution},
+            enums::v1::{ContinueAsNewInitiator, WorkflowIdReusePolicy},
+            failure::v1::Failure,
+        },
+    };
+    use serde::{de::DeserializeOwned, Serialize};
+    use std::{collections::HashMap, fmt::Debug};
+
+    fn payload(data: &[u8]) -> Payload {
+        Payload {
+            metadata: HashMap::from([("encoding".to_string(), b"binary/plain".to_vec())]),
+            data: data.to_vec(),
+        }
+    }
+
+    fn assert_round_trips<T>(val: &T) -> serde_json::Value
+    where
+        T: Serialize + DeserializeOwned + PartialEq + Debug,
+    {
+        let json = serde_json::to_string(val).unwrap();
+        let back: T = serde_json::from_str(&json).unwrap();
+        assert_eq!(&back, val);
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn activation_round_trips() {
+        let act = WorkflowActivation {
+            run_id: "run".to_string(),
+            timestamp: Some(prost_wkt_types::Timestamp {
+                seconds: 100,
+                nanos: 5,
+            }),
+            is_replaying: true,
+            history_length: 10,
+            jobs: vec![
+                WorkflowActivationJob {
+                    variant: Some(workflow_activation_job::Variant::InitializeWorkflow(
+                        InitializeWorkflow {
+                            workflow_type: "wf".to_string(),
+                            workflow_id: "wid".to_string(),
+                            arguments: vec![payload(b"\x00\x01\xff")],
+                            randomness_seed: 7,
+                            continued_initiator: ContinueAsNewInitiator::Workflow as i32,
+                            retry_policy: Some(RetryPolicy {
+                                backoff_coefficient: 2.0,
+                                ..Default::default()
+                            }),
+                            ..Default::default()
+                        },
+                    )),
+                },
+                WorkflowActivationJob {
+                    variant: Some(workflow_activation_job::Variant::RemoveFromCache(
+                        RemoveFromCache {
+                            message: "bye".to_string(),
+                            reason: EvictionReason::LangRequested as i32,
+                        },
+                    )),
+                },
+            ],
+            available_internal_flags: vec![1, 2],
+            history_size_bytes: 1024,
+            continue_as_new_suggested: true,
+            ..Default::default()
+        };
+        let json = assert_round_trips(&act);
+        let init = &json["jobs"][0]["variant"]["InitializeWorkflow"];
+        assert_eq!(
+            init["continued_initiator"],
+            "CONTINUE_AS_NEW_INITIATOR_WORKFLOW"
+        );
+        assert_eq!(init["arguments"][0]["data"], "AAH/");
+        assert_eq!(
+            init["arguments"][0]["metadata"]["encoding"],
+            "YmluYXJ5L3BsYWlu"
+        );
+        assert_eq!(
+            json["jobs"][1]["variant"]["RemoveFromCache"]["reason"],
+            "LANG_REQUESTED"
+        );
+        assert!(act.to_json_pretty().unwrap().contains('\n'));
+    }
+
+    #[test]
+    fn completion_round_trips() {
+        let success = WorkflowActivationCompletion::from_cmds(
+            "run",
+            vec![
+                ScheduleActivity {
+                    seq: 1,
+                    activity_id: "act".to_string(),
+                    activity_type: "echo".to_string(),
+                    arguments: vec![payload(b"hi")],
+                    cancellation_type: ActivityCancellationType::Abandon as i32,
+                    versioning_intent: VersioningIntent::Compatible as i32,
+                    ..Default::default()
+                }
+                .into(),
+                StartChildWorkflowExecution {
+                    seq: 2,
+                    workflow_id: "child".to_string(),
+                    workflow_type: "wf".to_string(),
+                    parent_close_policy: ParentClosePolicy::Abandon as i32,
+                    workflow_id_reuse_policy: WorkflowIdReusePolicy::RejectDuplicate as i32,
+                    cancellation_type: ChildWorkflowCancellationType::TryCancel as i32,
+                    ..Default::default()
+                }
+                .into(),
+            ],
+        );
+        let json = assert_round_trips(&success);
+        let cmds = &json["status"]["Successful"]["commands"];
+        assert_eq!(
+            cmds[0]["variant"]["ScheduleActivity"]["cancellation_type"],
+            "ABANDON"
+        );
+        assert_eq!(
+            cmds[1]["variant"]["StartChildWorkflowExecution"]["workflow_id_reuse_policy"],
+            "WORKFLOW_ID_REUSE_POLICY_REJECT_DUPLICATE"
+        );
+
+        let failed = WorkflowActivationCompletion::fail_and_evict("run", "oh no".into());
+        let json = assert_round_trips(&failed);
+        assert_eq!(
+            json["status"]["Failed"]["force_cause"],
+            "WORKFLOW_TASK_FAILED_CAUSE_UNSPECIFIED"
+        );
+    }
+
+    #[test]
+    fn activity_task_and_completion_round_trip() {
+        let task = ActivityTask {
+            task_token: vec![0, 1, 2, 255],
+            variant: Some(activity_task::Variant::Start(activity_task::Start {
+                workflow_namespace: "ns".to_string(),
+                workflow_type: "wf".to_string(),
+                workflow_execution: Some(WorkflowExecution {
+                    workflow_id: "wid".to_string(),
+                    run_id: "run".to_string(),
+                }),
+                activity_id: "act".to_string(),
+                activity_type: "echo".to_string(),
+                input: vec![payload(b"in")],
+                heartbeat_details: vec![payload(b"hb")],
+                attempt: 2,
+                ..Default::default()
+            })),
+        };
+        let json = assert_round_trips(&task);
+        assert_eq!(json["task_token"], "AAEC/w==");
+
+        let cancel = ActivityTask {
+            task_token: vec![9],
+            variant: Some(activity_task::Variant::Cancel(activity_task::Cancel {
+                reason: ActivityCancelReason::TimedOut as i32,
+            })),
+        };
+        let json = assert_round_trips(&cancel);
+        assert_eq!(json["variant"]["Cancel"]["reason"], "TIMED_OUT");
+
+        let completion = ActivityTaskCompletion {
+            task_token: vec![0, 1, 2, 255],
+            result: Some(ActivityExecutionResult::ok(Payload::from(
+                b"out".as_slice(),
+            ))),
+        };
+        assert_round_trips(&completion);
+        let failed = ActivityTaskCompletion {
+            task_token: vec![3],
+            result: Some(ActivityExecutionResult::fail(Failure::application_failure(
+                "bad".to_string(),
+                true,
+            ))),
+        };
+        assert_round_trips(&failed);
+    }
+
+    #[test]
+    fn unknown_enum_values_survive_round_trip() {
+        let rm = RemoveFromCache {
+            message: String::new(),
+            reason: 9999,
+        };
+        let json = assert_round_trips(&rm);
+        assert_eq!(json["reason"], 9999);
+        let err = serde_json::from_str::<RemoveFromCache>(r#"{"message":"","reason":"NOPE"}"#);
+        assert!(err.is_err());
+    }
+}
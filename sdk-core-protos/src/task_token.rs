@@ -1,7 +1,9 @@
 use base64::{prelude::BASE64_STANDARD, Engine};
+use siphasher::sip::SipHasher13;
 use std::{
     borrow::Borrow,
     fmt::{Debug, Display, Formatter},
+    hash::Hasher,
 };
 
 static LOCAL_ACT_TASK_TOKEN_PREFIX: &[u8] = b"local_act_";
@@ -32,6 +34,15 @@ impl TaskToken {
     pub fn is_local_activity_task(&self) -> bool {
         self.0.starts_with(LOCAL_ACT_TASK_TOKEN_PREFIX)
     }
+
+    /// A short digest of the token, for correlating the spans and log lines of one task without
+    /// writing out the whole token. Uses fixed keys, so the same token has the same digest in
+    /// every process and with every version of core.
+    pub fn hashed(&self) -> String {
+        let mut hasher = SipHasher13::new();
+        hasher.write(&self.0);
+        format!("{:016x}", hasher.finish())
+    }
 }
 
 impl Display for TaskToken {
@@ -55,3 +66,17 @@ impl Borrow<[u8]> for TaskToken {
 pub(crate) fn fmt_tt(tt: &[u8]) -> String {
     BASE64_STANDARD.encode(tt)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashed_digest_is_stable() {
+        assert_eq!(
+            TaskToken(b"task-token".to_vec()).hashed(),
+            "f8ff2b1cc2ff7dfb"
+        );
+        assert_eq!(TaskToken(vec![1, 2, 3]).hashed(), "60ec29c17db287a3");
+    }
+}
@@ -0,0 +1,282 @@
+//! Typed durations and timestamps for values crossing the proto boundary. Proto durations and
+//! timestamps are optional, signed, and may carry out of range nanoseconds, none of which is ever
+//! meaningful for a timeout or an event time. Converting through these types rejects such values
+//! with an error naming the offending field, rather than letting them surface later as negative
+//! timers or times at the epoch.
+
+use prost_wkt_types::{Duration as PbDuration, Timestamp as PbTimestamp};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The longest duration accepted for any timeout or timer. Anything longer is almost certainly a
+/// unit mixup.
+pub const MAX_WF_DURATION: Duration = Duration::from_secs(100 * 365 * 24 * 60 * 60);
+/// 9999-12-31T23:59:59Z, the latest time the timestamp proto can represent
+const MAX_TIMESTAMP_SECS: i64 = 253_402_300_799;
+const MAX_NANOS: i32 = 999_999_999;
+
+/// A duration or timestamp field held an unusable value
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("`{field}` {problem}")]
+pub struct InvalidTimeField {
+    /// The name of the proto field the value came from
+    pub field: &'static str,
+    /// What was wrong with it
+    pub problem: TimeFieldProblem,
+}
+
+/// The ways a duration or timestamp field can be invalid
+#[derive(derive_more::Display, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeFieldProblem {
+    /// A required field was not set
+    #[display("must be set")]
+    Missing,
+    /// A duration was negative
+    #[display("must not be negative")]
+    Negative,
+    /// A required duration was zero
+    #[display("must be greater than zero")]
+    Zero,
+    /// A timestamp was not after the unix epoch
+    #[display("must be after the unix epoch")]
+    NotAfterEpoch,
+    /// The value exceeds [MAX_WF_DURATION], or the latest representable timestamp
+    #[display("is unreasonably large")]
+    TooLarge,
+    /// The nanoseconds component was out of range
+    #[display("has out of range nanoseconds")]
+    InvalidNanos,
+}
+
+impl TimeFieldProblem {
+    fn on(self, field: &'static str) -> InvalidTimeField {
+        InvalidTimeField {
+            field,
+            problem: self,
+        }
+    }
+}
+
+/// A non-negative duration no longer than [MAX_WF_DURATION]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WfDuration(Duration);
+
+impl WfDuration {
+    /// Check that `d` is an acceptable duration for `field`
+    pub fn new(field: &'static str, d: Duration) -> Result<Self, InvalidTimeField> {
+        if d > MAX_WF_DURATION {
+            return Err(TimeFieldProblem::TooLarge.on(field));
+        }
+        Ok(Self(d))
+    }
+
+    /// Convert a proto duration read from `field`
+    pub fn from_proto(field: &'static str, d: &PbDuration) -> Result<Self, InvalidTimeField> {
+        if d.nanos.abs() > MAX_NANOS {
+            return Err(TimeFieldProblem::InvalidNanos.on(field));
+        }
+        if d.seconds < 0 || d.nanos < 0 {
+            return Err(TimeFieldProblem::Negative.on(field));
+        }
+        // Both components are non-negative, so the casts are lossless
+        Self::new(field, Duration::new(d.seconds as u64, d.nanos as u32))
+    }
+
+    /// Convert a proto duration read from `field`, which must be set to more than zero. A zero
+    /// duration is what an unset one turns into when defaulted, so it can't stand in for a real
+    /// value.
+    pub fn required(field: &'static str, d: Option<&PbDuration>) -> Result<Self, InvalidTimeField> {
+        let d = Self::from_proto(field, d.ok_or(TimeFieldProblem::Missing.on(field))?)?;
+        if d.0.is_zero() {
+            return Err(TimeFieldProblem::Zero.on(field));
+        }
+        Ok(d)
+    }
+
+    /// Convert a proto duration read from `field`, which may be unset. Server treats a zero
+    /// duration in an optional field as unset, so it is converted to `None` as well.
+    pub fn optional(
+        field: &'static str,
+        d: Option<&PbDuration>,
+    ) -> Result<Option<Self>, InvalidTimeField> {
+        Ok(d.map(|d| Self::from_proto(field, d))
+            .transpose()?
+            .filter(|d| !d.0.is_zero()))
+    }
+
+    /// Returns the duration as a std duration
+    pub fn as_duration(&self) -> Duration {
+        self.0
+    }
+}
+
+impl From<WfDuration> for Duration {
+    fn from(d: WfDuration) -> Self {
+        d.0
+    }
+}
+
+impl From<WfDuration> for PbDuration {
+    fn from(d: WfDuration) -> Self {
+        // Bounded by MAX_WF_DURATION, so always fits
+        PbDuration {
+            seconds: d.0.as_secs() as i64,
+            nanos: d.0.subsec_nanos() as i32,
+        }
+    }
+}
+
+/// A point in time after the unix epoch which the timestamp proto can represent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WfTimestamp(SystemTime);
+
+impl WfTimestamp {
+    /// Convert a proto timestamp read from `field`
+    pub fn from_proto(field: &'static str, t: &PbTimestamp) -> Result<Self, InvalidTimeField> {
+        if !(0..=MAX_NANOS).contains(&t.nanos) {
+            return Err(TimeFieldProblem::InvalidNanos.on(field));
+        }
+        // A zeroed timestamp is what an unset one turns into when defaulted, and never a real
+        // event time.
+        if t.seconds < 0 || (t.seconds == 0 && t.nanos == 0) {
+            return Err(TimeFieldProblem::NotAfterEpoch.on(field));
+        }
+        if t.seconds > MAX_TIMESTAMP_SECS {
+            return Err(TimeFieldProblem::TooLarge.on(field));
+        }
+        Ok(Self(
+            UNIX_EPOCH + Duration::new(t.seconds as u64, t.nanos as u32),
+        ))
+    }
+
+    /// Convert a proto timestamp read from `field`, which must be set
+    pub fn required(
+        field: &'static str,
+        t: Option<&PbTimestamp>,
+    ) -> Result<Self, InvalidTimeField> {
+        let t = t.ok_or(TimeFieldProblem::Missing.on(field))?;
+        Self::from_proto(field, t)
+    }
+
+    /// Convert a proto timestamp read from `field`, which may be unset
+    pub fn optional(
+        field: &'static str,
+        t: Option<&PbTimestamp>,
+    ) -> Result<Option<Self>, InvalidTimeField> {
+        t.map(|t| Self::from_proto(field, t)).transpose()
+    }
+
+    /// Returns the timestamp as a system time
+    pub fn as_system_time(&self) -> SystemTime {
+        self.0
+    }
+}
+
+impl From<WfTimestamp> for SystemTime {
+    fn from(t: WfTimestamp) -> Self {
+        t.0
+    }
+}
+
+impl From<WfTimestamp> for PbTimestamp {
+    fn from(t: WfTimestamp) -> Self {
+        t.0.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pb_dur(seconds: i64, nanos: i32) -> PbDuration {
+        PbDuration { seconds, nanos }
+    }
+
+    fn pb_ts(seconds: i64, nanos: i32) -> PbTimestamp {
+        PbTimestamp { seconds, nanos }
+    }
+
+    #[test]
+    fn duration_conversions() {
+        use TimeFieldProblem::*;
+        let max_secs = MAX_WF_DURATION.as_secs() as i64;
+        let cases: [(Option<PbDuration>, Result<Duration, TimeFieldProblem>); 10] = [
+            (None, Err(Missing)),
+            (Some(pb_dur(0, 0)), Err(Zero)),
+            (Some(pb_dur(0, 1)), Ok(Duration::from_nanos(1))),
+            (Some(pb_dur(5, 500)), Ok(Duration::new(5, 500))),
+            (Some(pb_dur(-1, 0)), Err(Negative)),
+            (Some(pb_dur(0, -1)), Err(Negative)),
+            (Some(pb_dur(1, 1_000_000_000)), Err(InvalidNanos)),
+            (Some(pb_dur(max_secs, 0)), Ok(MAX_WF_DURATION)),
+            (Some(pb_dur(max_secs, 1)), Err(TooLarge)),
+            (Some(pb_dur(i64::MAX, 0)), Err(TooLarge)),
+        ];
+        for (input, expected) in cases {
+            let res = WfDuration::required("some_timeout", input.as_ref());
+            assert_eq!(
+                res.map(Duration::from),
+                expected.map_err(|p| p.on("some_timeout")),
+                "input: {input:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn optional_duration_accepts_unset_and_zero() {
+        assert_eq!(WfDuration::optional("some_timeout", None), Ok(None));
+        assert_eq!(
+            WfDuration::optional("some_timeout", Some(&pb_dur(0, 0))),
+            Ok(None)
+        );
+        assert_eq!(
+            WfDuration::optional("some_timeout", Some(&pb_dur(-5, 0))),
+            Err(TimeFieldProblem::Negative.on("some_timeout"))
+        );
+    }
+
+    #[test]
+    fn duration_round_trips_through_proto() {
+        let d = WfDuration::new("some_timeout", Duration::new(12, 345)).unwrap();
+        let pb = PbDuration::from(d);
+        assert_eq!(WfDuration::from_proto("some_timeout", &pb), Ok(d));
+    }
+
+    #[test]
+    fn timestamp_conversions() {
+        use TimeFieldProblem::*;
+        let cases: [(Option<PbTimestamp>, Result<SystemTime, TimeFieldProblem>); 9] = [
+            (None, Err(Missing)),
+            (Some(pb_ts(0, 0)), Err(NotAfterEpoch)),
+            (Some(pb_ts(0, 1)), Ok(UNIX_EPOCH + Duration::from_nanos(1))),
+            (
+                Some(pb_ts(1_700_000_000, 5)),
+                Ok(UNIX_EPOCH + Duration::new(1_700_000_000, 5)),
+            ),
+            (Some(pb_ts(-1, 0)), Err(NotAfterEpoch)),
+            (Some(pb_ts(10, -1)), Err(InvalidNanos)),
+            (Some(pb_ts(10, 1_000_000_000)), Err(InvalidNanos)),
+            (
+                Some(pb_ts(MAX_TIMESTAMP_SECS, 0)),
+                Ok(UNIX_EPOCH + Duration::from_secs(MAX_TIMESTAMP_SECS as u64)),
+            ),
+            (Some(pb_ts(i64::MAX, 0)), Err(TooLarge)),
+        ];
+        for (input, expected) in cases {
+            let res = WfTimestamp::required("event_time", input.as_ref());
+            assert_eq!(
+                res.map(SystemTime::from),
+                expected.map_err(|p| p.on("event_time")),
+                "input: {input:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn errors_name_the_field() {
+        let err = WfDuration::required("start_to_fire_timeout", Some(&pb_dur(-3, 0))).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`start_to_fire_timeout` must not be negative"
+        );
+    }
+}
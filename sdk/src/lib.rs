@@ -113,6 +113,8 @@ use tokio_stream::wrappers::UnboundedReceiverStream;
 use tokio_util::sync::CancellationToken;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
+const POLL_BACKOFF_INITIAL: Duration = Duration::from_millis(100);
+const POLL_BACKOFF_MAX: Duration = Duration::from_secs(10);
 
 /// Returns a [ClientOptionsBuilder] with required fields set to appropriate values
 /// for the Rust SDK.
@@ -157,6 +159,31 @@ struct WorkflowFutureHandle<F: Future<Output = Result<WorkflowResult<Payload>, J
     run_id: String,
 }
 
+/// Spaces out polls retried after errors core says are worth retrying, so the polling loops don't
+/// spin while server is unavailable
+struct PollBackoff {
+    next: Duration,
+}
+
+impl Default for PollBackoff {
+    fn default() -> Self {
+        Self {
+            next: POLL_BACKOFF_INITIAL,
+        }
+    }
+}
+
+impl PollBackoff {
+    async fn wait(&mut self) {
+        tokio::time::sleep(self.next).await;
+        self.next = (self.next * 2).min(POLL_BACKOFF_MAX);
+    }
+
+    fn reset(&mut self) {
+        self.next = POLL_BACKOFF_INITIAL;
+    }
+}
+
 struct ActivityHalf {
     /// Maps activity type to the function for executing activities of that type
     activity_fns: HashMap<String, ActivityFunction>,
@@ -278,13 +305,30 @@ impl Worker {
         tokio::try_join!(
             // Workflow polling loop
             async {
+                let mut backoff = PollBackoff::default();
                 loop {
                     let activation = match common.worker.poll_workflow_activation().await {
-                        Err(PollWfError::ShutDown) => {
+                        // Activity-only workers have no workflows to poll for
+                        Err(
+                            PollWfError::ShutDown
+                            | PollWfError::WorkerConfiguredWithoutThisTaskType,
+                        ) => {
                             break;
                         }
+                        // Outstanding workflows will free up slots as they make progress
+                        Err(PollWfError::WorkerSaturated { .. }) => continue,
+                        Err(PollWfError::RetryableTonicError(e)) => {
+                            warn!(error=?e, "Workflow poll failed, retrying");
+                            backoff.wait().await;
+                            continue;
+                        }
+                        Err(PollWfError::MalformedResponse { reason }) => {
+                            warn!(%reason, "Dropping a workflow task core can't interpret");
+                            continue;
+                        }
                         o => o?,
                     };
+                    backoff.reset();
                     if let Some(ref i) = common.worker_interceptor {
                         i.on_workflow_activation(&activation).await?;
                     }
@@ -313,10 +357,22 @@ impl Worker {
             // makes tests which use mocks dramatically more manageable.
             async {
                 if !act_half.activity_fns.is_empty() {
+                    let mut backoff = PollBackoff::default();
                     loop {
                         let activity = common.worker.poll_activity_task().await;
-                        if matches!(activity, Err(PollActivityError::ShutDown)) {
-                            break;
+                        match activity {
+                            Err(PollActivityError::ShutDown) => break,
+                            Err(PollActivityError::WorkerSaturated { .. }) => continue,
+                            Err(PollActivityError::RetryableTonicError(ref e)) => {
+                                warn!(error=?e, "Activity poll failed, retrying");
+                                backoff.wait().await;
+                                continue;
+                            }
+                            Err(PollActivityError::MalformedResponse { ref reason }) => {
+                                warn!(%reason, "Dropping an activity task core can't interpret");
+                                continue;
+                            }
+                            _ => backoff.reset(),
                         }
                         act_half.activity_task_handler(
                             common.worker.clone(),
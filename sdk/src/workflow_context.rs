@@ -35,7 +35,7 @@ use temporal_sdk_core_protos::{
         workflow_activation::resolve_child_workflow_execution_start::Status as ChildWorkflowStartStatus,
         workflow_commands::{
             signal_external_workflow_execution as sig_we, workflow_command,
-            CancelChildWorkflowExecution, ModifyWorkflowProperties,
+            CancelChildWorkflowExecution, ModifyWorkflowProperties, RecordSideEffect,
             RequestCancelExternalWorkflowExecution, SetPatchMarker,
             SignalExternalWorkflowExecution, StartTimer, UpsertWorkflowSearchAttributes,
         },
@@ -86,6 +86,7 @@ impl WfContext {
                     next_child_workflow_sequence_number: 1,
                     next_cancel_external_wf_sequence_number: 1,
                     next_signal_external_wf_sequence_number: 1,
+                    next_side_effect_sequence_number: 1,
                 })),
             },
             rx,
@@ -255,6 +256,50 @@ impl WfContext {
         res
     }
 
+    /// Run a side effect and record the value it produces. When replaying, the recorded value is
+    /// returned instead, and `f` is not run.
+    pub fn side_effect(&self, f: impl FnOnce() -> Payload) -> Payload {
+        let seq = self.seq_nums.write().next_side_effect_seq();
+        let recorded = self.shared.write().side_effects.remove(&seq);
+        let result = recorded.unwrap_or_else(f);
+        self.send_side_effect(seq, &result, "");
+        result
+    }
+
+    /// Run a side effect every time it is reached, only recording its value when it differs from
+    /// the last one recorded for `id`. When replaying, the last recorded value is returned
+    /// instead, and `f` is not run.
+    pub fn mutable_side_effect(&self, id: &str, f: impl FnOnce() -> Payload) -> Payload {
+        let seq = self.seq_nums.write().next_side_effect_seq();
+        let known = {
+            let mut shared = self.shared.write();
+            shared.side_effects.remove(&seq).or_else(|| {
+                shared
+                    .is_replaying
+                    .then(|| shared.mutable_side_effects.get(id).cloned())
+                    .flatten()
+            })
+        };
+        let result = known.unwrap_or_else(f);
+        self.shared
+            .write()
+            .mutable_side_effects
+            .insert(id.to_string(), result.clone());
+        self.send_side_effect(seq, &result, id);
+        result
+    }
+
+    fn send_side_effect(&self, seq: u32, result: &Payload, mutable_id: &str) {
+        self.send(
+            workflow_command::Variant::RecordSideEffect(RecordSideEffect {
+                seq,
+                result: Some(result.clone()),
+                mutable_id: mutable_id.to_string(),
+            })
+            .into(),
+        );
+    }
+
     /// Send a signal to an external workflow. May resolve as a failure if the signal didn't work
     /// or was cancelled.
     pub fn signal_workflow(
@@ -286,7 +331,7 @@ impl WfContext {
         self.send(RustWfCmd::NewNonblockingCmd(
             workflow_command::Variant::ModifyWorkflowProperties(ModifyWorkflowProperties {
                 upserted_memo: Some(Memo {
-                    fields: HashMap::from_iter(attr_iter),
+                    fields: attr_iter.into_iter().collect(),
                 }),
             }),
         ))
@@ -386,6 +431,7 @@ struct WfCtxProtectedDat {
     next_child_workflow_sequence_number: u32,
     next_cancel_external_wf_sequence_number: u32,
     next_signal_external_wf_sequence_number: u32,
+    next_side_effect_sequence_number: u32,
 }
 
 impl WfCtxProtectedDat {
@@ -414,12 +460,21 @@ impl WfCtxProtectedDat {
         self.next_signal_external_wf_sequence_number += 1;
         seq
     }
+    fn next_side_effect_seq(&mut self) -> u32 {
+        let seq = self.next_side_effect_sequence_number;
+        self.next_side_effect_sequence_number += 1;
+        seq
+    }
 }
 
 #[derive(Clone, Debug, Default)]
 pub(crate) struct WfContextSharedData {
     /// Maps change ids -> resolved status
     pub(crate) changes: HashMap<String, bool>,
+    /// Maps side effect sequence numbers -> values recorded in history
+    pub(crate) side_effects: HashMap<u32, Payload>,
+    /// Maps mutable side effect ids -> the value most recently returned for them
+    pub(crate) mutable_side_effects: HashMap<String, Payload>,
     pub(crate) is_replaying: bool,
     pub(crate) wf_time: Option<SystemTime>,
     pub(crate) history_length: u32,
@@ -18,8 +18,8 @@ use temporal_sdk_core_protos::{
     coresdk::{
         workflow_activation::{
             workflow_activation_job::Variant, FireTimer, NotifyHasPatch, ResolveActivity,
-            ResolveChildWorkflowExecution, ResolveChildWorkflowExecutionStart, WorkflowActivation,
-            WorkflowActivationJob,
+            ResolveChildWorkflowExecution, ResolveChildWorkflowExecutionStart, ResolveSideEffect,
+            WorkflowActivation, WorkflowActivationJob,
         },
         workflow_commands::{
             update_response, workflow_command, CancelChildWorkflowExecution, CancelSignalWorkflow,
@@ -233,6 +233,13 @@ impl WorkflowFuture {
                 Variant::NotifyHasPatch(NotifyHasPatch { patch_id }) => {
                     self.wf_ctx.shared.write().changes.insert(patch_id, true);
                 }
+                Variant::ResolveSideEffect(ResolveSideEffect { seq, result }) => {
+                    self.wf_ctx
+                        .shared
+                        .write()
+                        .side_effects
+                        .insert(seq, result.unwrap_or_default());
+                }
                 Variant::ResolveSignalExternalWorkflow(attrs) => {
                     self.unblock(UnblockEvent::SignalExternal(attrs.seq, attrs.failure))?;
                 }
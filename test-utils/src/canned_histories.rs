@@ -109,7 +109,7 @@ pub fn workflow_fails_with_reset_after_timer(
     original_run_id: &str,
 ) -> TestHistoryBuilder {
     let mut t = single_timer(timer_id);
-    t.add_workflow_task_failed_new_id(WorkflowTaskFailedCause::ResetWorkflow, original_run_id);
+    t.add_workflow_reset(original_run_id, "operator asked nicely");
 
     t.add_workflow_task_scheduled_and_started();
     t
@@ -1019,6 +1019,37 @@ fn timer_cancel_req_then(
     t
 }
 
+///  1: EVENT_TYPE_WORKFLOW_EXECUTION_STARTED
+///  2: EVENT_TYPE_WORKFLOW_TASK_SCHEDULED
+///  3: EVENT_TYPE_WORKFLOW_TASK_STARTED
+///  4: EVENT_TYPE_WORKFLOW_TASK_COMPLETED
+///  5: EVENT_TYPE_TIMER_STARTED
+///  6: EVENT_TYPE_WORKFLOW_EXECUTION_CANCEL_REQUESTED
+///  7: EVENT_TYPE_WORKFLOW_EXECUTION_SIGNALED
+///  8: EVENT_TYPE_WORKFLOW_TASK_SCHEDULED
+///  9: EVENT_TYPE_WORKFLOW_TASK_STARTED
+/// 10: EVENT_TYPE_WORKFLOW_TASK_COMPLETED
+/// 11: EVENT_TYPE_WORKFLOW_EXECUTION_SIGNALED
+/// 12: EVENT_TYPE_WORKFLOW_TASK_SCHEDULED
+/// 13: EVENT_TYPE_WORKFLOW_TASK_STARTED
+/// 14: EVENT_TYPE_WORKFLOW_TASK_COMPLETED
+/// 15: EVENT_TYPE_TIMER_STARTED
+/// 16: EVENT_TYPE_WORKFLOW_EXECUTION_CANCELED
+pub fn timer_wf_cancel_req_then_signals_then_cancelled(signal_name: &str) -> TestHistoryBuilder {
+    let mut t = TestHistoryBuilder::default();
+    t.add_by_type(EventType::WorkflowExecutionStarted);
+    t.add_full_wf_task();
+    t.add_by_type(EventType::TimerStarted);
+    t.add_cancel_requested();
+    t.add_we_signaled(signal_name, vec![]);
+    t.add_full_wf_task();
+    t.add_we_signaled(signal_name, vec![]);
+    t.add_full_wf_task();
+    t.add_by_type(EventType::TimerStarted);
+    t.add_cancelled();
+    t
+}
+
 ///  1: EVENT_TYPE_WORKFLOW_EXECUTION_STARTED
 ///  2: EVENT_TYPE_WORKFLOW_EXECUTION_CANCEL_REQUESTED
 ///  3: EVENT_TYPE_WORKFLOW_TASK_SCHEDULED
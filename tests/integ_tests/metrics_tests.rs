@@ -767,7 +767,7 @@ async fn activity_metrics() {
              workflow_type=\"{wf_name}\"}} 1"
     )));
     assert!(body.contains(&format!(
-        "temporal_local_activity_execution_cancelled{{activity_type=\"pass_fail_act\",\
+        "temporal_local_activity_execution_canceled{{activity_type=\"pass_fail_act\",\
              namespace=\"{NAMESPACE}\",service_name=\"temporal-core-sdk\",\
              task_queue=\"{task_queue}\",\
              workflow_type=\"{wf_name}\"}} 1"